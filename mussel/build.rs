@@ -0,0 +1,10 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Links the bindings for Node.js, when they are built, with the symbols of Node, which are only
+// found once the addon is loaded, see `src/node.rs`.
+
+fn main() {
+    #[cfg(feature = "node")]
+    napi_build::setup();
+}
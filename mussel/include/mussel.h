@@ -0,0 +1,152 @@
+/*
+ * Copyright (c) 2025 Francesco Giannice
+ * Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+ *
+ * The C API of mussel, to embed the interpreter in a C or C++ program. The program links with the
+ * library built by `cargo build --lib --release`: `libmussel.so`, `libmussel.dylib` or `mussel.dll`.
+ *
+ *     MusselInterpreter *interpreter = mussel_new();
+ *     MusselValue *area = mussel_eval(interpreter, "let width = 6\nwidth * 7");
+ *     if (area == NULL) {
+ *         fprintf(stderr, "%s\n", mussel_last_error(interpreter));
+ *     } else {
+ *         printf("%lld\n", (long long) mussel_value_int(area));
+ *         mussel_value_free(area);
+ *     }
+ *     mussel_free(interpreter);
+ *
+ * The code given to the same interpreter runs in the same global scope, so what it declares is kept
+ * for the next one. The values returned by the functions are owned by the program, which frees them
+ * with `mussel_value_free`, and the values given to the functions are only read. The strings
+ * returned are valid as long as what returned them, the strings given are copied.
+ * The pointers given can't be NULL, but where written otherwise. An interpreter and its values are
+ * used from the thread that created it.
+ * The functions of this header are kept from one version to the next, new ones can be added.
+ */
+
+#ifndef MUSSEL_H
+#define MUSSEL_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An interpreter, with the variables of its global scope. */
+typedef struct MusselInterpreter MusselInterpreter;
+
+/* A value given by the code, or given to it. */
+typedef struct MusselValue MusselValue;
+
+/* The types of the values, see `mussel_value_type`. */
+typedef enum MusselType {
+    MUSSEL_NIL = 0,
+    MUSSEL_INT = 1,
+    MUSSEL_FLOAT = 2,
+    MUSSEL_BOOL = 3,
+    MUSSEL_STRING = 4,
+    MUSSEL_ARRAY = 5,
+    /* The functions, structs, instances, enums, variants, ranges and modules. */
+    MUSSEL_OTHER = 6,
+} MusselType;
+
+/*
+ * A function of the program, called by the code with `count` arguments, which are valid until it
+ * returns, and the data it was registered with. It returns a new value, or an error made by
+ * `mussel_error` to stop the code at the call. Returning NULL stops it too.
+ */
+typedef MusselValue *(*MusselFn)(const MusselValue *const *arguments, size_t count, void *data);
+
+/*
+ * Creates an interpreter, with the built-ins that don't need an `include`. The calls nested too deep
+ * for the stack of the thread running the code stop it with an error instead of overflowing the
+ * stack: the interpreter asks the system for the size of the stack, or assumes 1 MiB on the systems
+ * which don't tell it, see `mussel_set_stack`.
+ */
+MusselInterpreter *mussel_new(void);
+
+/* Frees an interpreter, which can be NULL. Its values stay valid. */
+void mussel_free(MusselInterpreter *interpreter);
+
+/*
+ * Sets the bytes of the stack the code run by an interpreter can use below the calls of
+ * `mussel_eval`, instead of the size the system tells. It must not be more than what is left of the
+ * stack of the thread running the code.
+ */
+void mussel_set_stack(MusselInterpreter *interpreter, size_t size);
+
+/*
+ * Runs code in the global scope of an interpreter and returns the value of its last statement, or
+ * NULL when an error stopped it, or `exit`. After an error, the interpreter can go on with what the
+ * code declared before it.
+ */
+MusselValue *mussel_eval(MusselInterpreter *interpreter, const char *source);
+
+/*
+ * Returns the error which stopped the code run last by `mussel_eval`, like
+ * "error[E1002]: cannot find function `f` in this scope", or NULL when it ran to its end. It is valid
+ * until the next call of `mussel_eval`.
+ */
+const char *mussel_last_error(const MusselInterpreter *interpreter);
+
+/* Returns the status given to `exit` by the code run last, or -1 when it didn't call it. */
+int32_t mussel_exit_status(const MusselInterpreter *interpreter);
+
+/*
+ * Declares a function of the program in the global scope of an interpreter, for the code to call
+ * with any number of arguments. `data`, which can be NULL, is given to it on each call.
+ */
+void mussel_register_fn(MusselInterpreter *interpreter, const char *name, MusselFn function, void *data);
+
+/* Returns the value of a variable of the global scope, or NULL when it isn't declared. */
+MusselValue *mussel_get(const MusselInterpreter *interpreter, const char *name);
+
+/* Declares a variable in the global scope, or gives it a new value, for the code to read. */
+void mussel_set(MusselInterpreter *interpreter, const char *name, const MusselValue *value);
+
+/* Create values. */
+MusselValue *mussel_nil(void);
+MusselValue *mussel_int(int64_t number);
+MusselValue *mussel_float(double number);
+MusselValue *mussel_bool(bool boolean);
+MusselValue *mussel_string(const char *string);
+/* Creates an array from `count` values, `elements` can be NULL when there are none. */
+MusselValue *mussel_array(const MusselValue *const *elements, size_t count);
+
+/* Creates the error a `MusselFn` returns to stop the code calling it with a message. */
+MusselValue *mussel_error(const char *message);
+
+/* Frees a value, which can be NULL. */
+void mussel_value_free(MusselValue *value);
+
+MusselType mussel_value_type(const MusselValue *value);
+
+/* Returns the integer held by a value, 0 for the values which aren't integers. */
+int64_t mussel_value_int(const MusselValue *value);
+
+/* Returns the number held by a value, an integer converted, 0 for the values which aren't numbers. */
+double mussel_value_float(const MusselValue *value);
+
+/* Returns the boolean held by a value, false for the values which aren't booleans. */
+bool mussel_value_bool(const MusselValue *value);
+
+/* Returns the value as text, like `println` shows it: a string is itself. */
+const char *mussel_value_text(const MusselValue *value);
+
+/* Returns the number of elements of an array, 0 for the values which aren't arrays. */
+size_t mussel_value_len(const MusselValue *value);
+
+/* Returns an element of an array, or NULL when the value isn't an array or has no such element. */
+MusselValue *mussel_value_at(const MusselValue *value, size_t index);
+
+/* Returns a field of a struct instance, or NULL when the value isn't an instance with this field. */
+MusselValue *mussel_value_field(const MusselValue *value, const char *name);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MUSSEL_H */
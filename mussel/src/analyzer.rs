@@ -0,0 +1,512 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+//! Analysis of a script before it runs, warning about code that is likely a mistake: variables
+//! declared with `let` or `const` that are never read, functions that are never called, and
+//! statements that never run because they follow a `return`, `throw`, `break` or `continue`.
+//!
+//! Each kind of warning has a name, shown with it, to turn it off, see `warnings`. The warnings
+//! don't stop the script. Only the code as written is looked at, so a variable read
+//! by `eval` or a function called with `call` isn't seen: names starting with `_` are never
+//! reported, to keep such declarations quiet.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use crate::error::{AnalysisWarning, FileIdentifier};
+use crate::lexer::TokenRecord;
+use crate::parser::{BindingExpression, Expression, ParameterExpression, PatternExpression};
+use crate::warnings;
+
+/// The functions the operators call when their operands are struct instances (see
+/// `interpreter::binary`, `unary` and `compare`), which are used without being called by name.
+const OPERATOR_FUNCTIONS: [&str; 7] = ["add", "sub", "mul", "div", "neg", "eq", "lt"];
+
+/// Analyzes a parsed file, returning the warnings found, in the order of the code.
+/// The top-level declarations of a module are there for the files including it, so they aren't
+/// reported when the module doesn't use them itself.
+pub fn analyze(file: FileIdentifier, content: &str, expressions: &[Expression], module: bool) -> Vec<AnalysisWarning> {
+    let mut analyzer = Analyzer::new(file, content, None);
+    analyzer.statements(expressions);
+    let global = analyzer.scopes.pop().expect("the global scope is never removed");
+    if !module {
+        analyzer.finish(global);
+    }
+    analyzer.warnings.sort_by_key(AnalysisWarning::start);
+    analyzer.warnings
+}
+
+/// Returns the declarations visible at an offset of a parsed file, for the completion of the
+/// editors, see `lsp`: the ones of the innermost scope around it and of the scopes around that one,
+/// written before the offset unless they are functions.
+pub(crate) fn visible(file: FileIdentifier, content: &str, expressions: &[Expression], offset: usize) -> Vec<Range<usize>> {
+    let mut analyzer = Analyzer::new(file, content, Some(offset));
+    analyzer.statements(expressions);
+    let visible = analyzer.visible.take().unwrap_or_else(|| analyzer.in_scope());
+    visible
+        .into_iter()
+        .map(|index| &analyzer.symbols[index])
+        .filter(|symbol| symbol.kind == Kind::Function || symbol.token.range().start <= offset)
+        .map(|symbol| symbol.token.range())
+        .collect()
+}
+
+/// How a name was declared, which tells whether it is reported when it is never used.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Variable,
+    Constant,
+    Function,
+    // Parameters, loop variables, the variables of a pattern and the other names that are bound
+    // by the construct using them, which are never reported
+    Other,
+}
+
+/// A declared name and whether it was used.
+struct Symbol {
+    token: TokenRecord,
+    kind: Kind,
+    used: bool,
+}
+
+struct Analyzer<'a> {
+    file: FileIdentifier,
+    content: &'a str,
+    symbols: Vec<Symbol>,
+    // The names declared in each scope, with their index in `symbols`, the innermost last
+    scopes: Vec<HashMap<String, usize>>,
+    // The functions whose body is being analyzed, the innermost last, `None` for closures and
+    // methods: a function calling itself isn't used by that call
+    functions: Vec<Option<usize>>,
+    // The names read by a function before anything with that name was declared: the function can
+    // run after the declaration, so the declaration is used
+    late: HashSet<String>,
+    warnings: Vec<AnalysisWarning>,
+    // The part of the file covered by the names written in each scope, the innermost last
+    spans: Vec<Option<Range<usize>>>,
+    // The offset looked for by `visible`, and the declarations visible there, kept when the
+    // innermost scope around it ends
+    probe: Option<usize>,
+    visible: Option<Vec<usize>>,
+}
+
+impl<'a> Analyzer<'a> {
+    fn new(file: FileIdentifier, content: &'a str, probe: Option<usize>) -> Self {
+        Analyzer {
+            file,
+            content,
+            symbols: Vec::new(),
+            scopes: vec![HashMap::new()],
+            functions: Vec::new(),
+            late: HashSet::new(),
+            warnings: Vec::new(),
+            spans: vec![None],
+            probe,
+            visible: None,
+        }
+    }
+
+    fn name(&self, token: &TokenRecord) -> String {
+        token.get_content(self.content).to_string()
+    }
+
+    // Declares a name in the innermost scope. A declaration with the same name in that scope is
+    // done with: it is reported if it wasn't used.
+    fn declare(&mut self, token: &TokenRecord, kind: Kind) -> usize {
+        self.see(token.range());
+        let name = self.name(token);
+        let used = self.late.contains(&name);
+        self.symbols.push(Symbol { token: token.clone(), kind, used });
+        let index = self.symbols.len() - 1;
+        let scope = self.scopes.last_mut().expect("the global scope is never removed");
+        if let Some(previous) = scope.insert(name, index) {
+            self.unused(previous);
+        }
+        index
+    }
+
+    fn read(&mut self, name: &str) {
+        match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+            Some(index) if self.functions.contains(&Some(*index)) => {}
+            Some(index) => self.symbols[*index].used = true,
+            None if !self.functions.is_empty() => {
+                self.late.insert(name.to_string());
+            }
+            None => {}
+        }
+    }
+
+    // Reads the variables interpolated in a string, see `interpolated_names`.
+    fn interpolated(&mut self, token: &TokenRecord) {
+        for name in interpolated_names(token.get_content(self.content)) {
+            self.read(&name);
+        }
+    }
+
+    // Starts a new innermost scope.
+    fn enter(&mut self) {
+        self.scopes.push(HashMap::new());
+        self.spans.push(None);
+    }
+
+    // Ends the innermost scope, reporting the declarations that weren't used. When it is the first
+    // one to end around the offset looked for by `visible`, the declarations visible there are kept.
+    fn leave(&mut self) {
+        let span = self.spans.pop().expect("the global scope is never removed");
+        if let (Some(offset), Some(span), None) = (self.probe, &span, &self.visible)
+            && around(self.content, span, offset)
+        {
+            self.visible = Some(self.in_scope());
+        }
+        let scope = self.scopes.pop().expect("the global scope is never removed");
+        self.finish(scope);
+        // What is written in a scope is written in the one around it too.
+        if let Some(span) = span {
+            self.see(span);
+        }
+    }
+
+    // Notes a part of the file written in the innermost scope.
+    fn see(&mut self, range: Range<usize>) {
+        let span = self.spans.last_mut().expect("the global scope is never removed");
+        *span = Some(match span.take() {
+            Some(span) => span.start.min(range.start)..span.end.max(range.end),
+            None => range,
+        });
+    }
+
+    // Returns the declarations of the scopes open now, as indexes in `symbols`.
+    fn in_scope(&self) -> Vec<usize> {
+        self.scopes.iter().flat_map(|scope| scope.values().copied()).collect()
+    }
+
+    // Reports the declarations of a scope that ended without being used.
+    fn finish(&mut self, scope: HashMap<String, usize>) {
+        for index in scope.into_values() {
+            self.unused(index);
+        }
+    }
+
+    fn unused(&mut self, index: usize) {
+        let symbol = &self.symbols[index];
+        let name = symbol.token.get_content(self.content);
+        if symbol.used || name.starts_with('_') {
+            return;
+        }
+        let (warning, message, label) = match symbol.kind {
+            Kind::Variable => ("unused-variable", format!("unused variable `{name}`"), "declared here but never read"),
+            Kind::Constant => ("unused-variable", format!("unused constant `{name}`"), "declared here but never read"),
+            Kind::Function if OPERATOR_FUNCTIONS.contains(&name) => return,
+            Kind::Function => ("unused-function", format!("function `{name}` is never called"), "defined here"),
+            Kind::Other => return,
+        };
+        let help = format!("remove it, or name it `_{name}` if it is needed");
+        let range = Some(symbol.token.range());
+        self.warn(warning, range, message, label.to_string(), Some(help));
+    }
+
+    // Adds a warning, unless it is turned off, see `warnings`.
+    fn warn(&mut self, name: &'static str, range: Option<Range<usize>>, message: String, label: String, help: Option<String>) {
+        if warnings::reported(name, self.content, range.as_ref()) {
+            self.warnings.push(AnalysisWarning::new(self.file, range, message, label, help).with_rule(name));
+        }
+    }
+
+    // Analyzes the statements of a block in a new scope.
+    fn block(&mut self, block: &[Expression]) {
+        self.enter();
+        self.statements(block);
+        self.leave();
+    }
+
+    // Analyzes statements in the innermost scope. The statement following one that always leaves
+    // the block is reported, the ones after it are unreachable for the same reason.
+    fn statements(&mut self, statements: &[Expression]) {
+        let mut left_by = None;
+        for statement in statements {
+            if let Some(keyword) = left_by.take() {
+                let message = "unreachable statement".to_string();
+                let label = format!("this never runs, it follows a `{keyword}`");
+                self.warn("unreachable-code", location(statement), message, label, None);
+            }
+            self.visit(statement);
+            left_by = match statement {
+                Expression::Return { .. } => Some("return"),
+                Expression::Throw { .. } => Some("throw"),
+                Expression::Break { .. } => Some("break"),
+                Expression::Continue { .. } => Some("continue"),
+                _ => None,
+            };
+        }
+    }
+
+    // Declares the variables of a `let`, `const` or `for`.
+    fn bind(&mut self, binding: &BindingExpression, kind: Kind) {
+        match binding {
+            BindingExpression::Identifier(id) => {
+                self.declare(id, kind);
+            }
+            BindingExpression::Wildcard => {}
+            BindingExpression::Array(bindings, _) => {
+                for binding in bindings {
+                    self.bind(binding, kind);
+                }
+            }
+        }
+    }
+
+    // Declares the variables bound by the pattern of a `match` arm.
+    fn bind_pattern(&mut self, pattern: &PatternExpression) {
+        match pattern {
+            PatternExpression::Identifier(id) | PatternExpression::Rest(_, Some(id)) => {
+                self.declare(id, Kind::Other);
+            }
+            PatternExpression::Array(patterns) | PatternExpression::Variant(_, patterns) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern);
+                }
+            }
+            PatternExpression::Struct(_, fields) => {
+                for (field, pattern) in fields {
+                    match pattern {
+                        Some(pattern) => self.bind_pattern(pattern),
+                        None => {
+                            self.declare(field, Kind::Other);
+                        }
+                    }
+                }
+            }
+            PatternExpression::Literal(literal) => self.visit(literal),
+            PatternExpression::Wildcard | PatternExpression::Rest(_, None) => {}
+        }
+    }
+
+    // Analyzes the body of a function, with its parameters declared in a new scope.
+    fn function(&mut self, symbol: Option<usize>, args: &[ParameterExpression], block: &[Expression]) {
+        self.enter();
+        for arg in args {
+            if let Some(default) = &arg.default {
+                self.visit(default);
+            }
+            self.declare(&arg.id, Kind::Other);
+        }
+        self.functions.push(symbol);
+        self.statements(block);
+        self.functions.pop();
+        self.leave();
+    }
+
+    fn visit(&mut self, expr: &Expression) {
+        if let Expression::Identifier(token)
+        | Expression::String(token)
+        | Expression::Char(token)
+        | Expression::Integer(token)
+        | Expression::Float(token)
+        | Expression::Bool(token)
+        | Expression::Call { region: token, .. }
+        | Expression::Index { region: token, .. } = expr
+        {
+            self.see(token.range());
+        }
+        match expr {
+            Expression::Identifier(id) => self.read(id.get_content(self.content)),
+            Expression::String(token) => self.interpolated(token),
+            Expression::Char(_)
+            | Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::Bool(_)
+            | Expression::Nil
+            | Expression::Path { .. }
+            | Expression::Continue { .. }
+            | Expression::Struct { .. }
+            | Expression::Trait { .. }
+            | Expression::Enum { .. } => {}
+            Expression::Include { alias, .. } => {
+                if let Some(alias) = alias {
+                    self.declare(alias, Kind::Other);
+                }
+            }
+            Expression::Array(items) => {
+                for item in items {
+                    self.visit(item);
+                }
+            }
+            Expression::Closure { args, block, .. } => self.function(None, args, block),
+            // Declared before the body is analyzed, so the body can call it.
+            Expression::Function { id, args, block, .. } => {
+                let symbol = self.declare(id, Kind::Function);
+                self.function(Some(symbol), args, block);
+            }
+            Expression::Impl { methods, .. } => {
+                for method in methods {
+                    if let Expression::Function { args, block, .. } = method {
+                        self.function(None, args, block);
+                    }
+                }
+            }
+            Expression::Let { binding, expr, .. } => {
+                self.visit(expr);
+                self.bind(binding, Kind::Variable);
+            }
+            Expression::Const { binding, expr, .. } => {
+                self.visit(expr);
+                self.bind(binding, Kind::Constant);
+            }
+            // Assigning a variable doesn't read it, but assigning an element or a field does.
+            Expression::Assignment { left, right, .. } => {
+                self.visit(right);
+                if !matches!(&**left, Expression::Identifier(_)) {
+                    self.visit(left);
+                }
+            }
+            Expression::Return { expr } | Expression::Throw { expr, .. } | Expression::Labeled { expr, .. } => {
+                self.visit(expr)
+            }
+            Expression::Break { expr, .. } => {
+                if let Some(expr) = expr {
+                    self.visit(expr);
+                }
+            }
+            Expression::Assert { expr, message, .. } => {
+                self.visit(expr);
+                if let Some(message) = message {
+                    self.visit(message);
+                }
+            }
+            Expression::Try { block, id, catch_block } => {
+                self.block(block);
+                self.enter();
+                self.declare(id, Kind::Other);
+                self.block(catch_block);
+                self.leave();
+            }
+            Expression::If { expr, block, else_block, .. } => {
+                self.visit(expr);
+                self.block(block);
+                if let Some(else_block) = else_block {
+                    self.block(else_block);
+                }
+            }
+            Expression::Until { expr, block, .. } => {
+                self.visit(expr);
+                self.block(block);
+            }
+            Expression::Loop { block } => self.block(block),
+            Expression::For { binding, expr, block, .. } => {
+                self.visit(expr);
+                self.enter();
+                self.bind(binding, Kind::Other);
+                self.block(block);
+                self.leave();
+            }
+            Expression::Comprehension { expr, binding, iterable, condition, .. } => {
+                self.visit(iterable);
+                self.enter();
+                self.bind(binding, Kind::Other);
+                if let Some(condition) = condition {
+                    self.visit(condition);
+                }
+                self.visit(expr);
+                self.leave();
+            }
+            Expression::Match { expr, arms } => {
+                self.visit(expr);
+                for (pattern, block) in arms {
+                    self.enter();
+                    self.bind_pattern(pattern);
+                    self.block(block);
+                    self.leave();
+                }
+            }
+            Expression::Binary { left, right, .. } => {
+                self.visit(left);
+                self.visit(right);
+            }
+            Expression::Unary { expr, .. } => self.visit(expr),
+            Expression::Range { start, end, .. } => {
+                self.visit(start);
+                self.visit(end);
+            }
+            Expression::Pipeline { value, function, .. } => {
+                self.visit(value);
+                self.visit(function);
+            }
+            Expression::Call { left, args, .. } => {
+                self.visit(left);
+                for arg in args {
+                    self.visit(arg);
+                }
+            }
+            Expression::Index { left, index, .. } => {
+                self.visit(left);
+                self.visit(index);
+            }
+            Expression::Field { left, .. } => self.visit(left),
+        }
+    }
+}
+
+/// Returns the names used in the placeholders of a string literal. The placeholders aren't parsed
+/// here: every name in them is taken, which can hide a warning but never gives a wrong one.
+pub(crate) fn interpolated_names(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut depth = 0usize;
+    let mut word = String::new();
+    for c in text.chars() {
+        if depth > 0 && (c.is_alphanumeric() || c == '_') {
+            word.push(c);
+            continue;
+        }
+        if word.starts_with(|c: char| !c.is_ascii_digit()) {
+            names.push(word.clone());
+        }
+        word.clear();
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    names
+}
+
+// Tells whether an offset is in a part of a file, or in the blank space between it and the braces
+// around it, where the code of a block is typed.
+fn around(content: &str, span: &Range<usize>, offset: usize) -> bool {
+    let before = content[..span.start].trim_end();
+    let after = content[span.end..].trim_start();
+    let start = if before.ends_with('{') { before.len() } else { span.start };
+    let end = if after.starts_with('}') { content.len() - after.len() } else { span.end };
+    (start..=end).contains(&offset)
+}
+
+// Returns where a statement was written, to point at it.
+fn location(statement: &Expression) -> Option<Range<usize>> {
+    fn first_name(binding: &BindingExpression) -> Option<Range<usize>> {
+        match binding {
+            BindingExpression::Identifier(id) => Some(id.range()),
+            BindingExpression::Wildcard => None,
+            BindingExpression::Array(bindings, _) => bindings.iter().find_map(first_name),
+        }
+    }
+    statement.span().or_else(|| match statement {
+        Expression::Let { binding, expr, .. } | Expression::Const { binding, expr, .. } => {
+            first_name(binding).or_else(|| expr.span())
+        }
+        Expression::Return { expr } | Expression::Throw { expr, .. } => expr.span(),
+        Expression::Function { id, .. }
+        | Expression::Struct { id, .. }
+        | Expression::Trait { id, .. }
+        | Expression::Enum { id, .. }
+        | Expression::Include { id, .. }
+        | Expression::Try { id, .. } => Some(id.range()),
+        Expression::For { keyword, .. }
+        | Expression::Until { keyword, .. }
+        | Expression::If { keyword, .. }
+        | Expression::Break { keyword, .. }
+        | Expression::Continue { keyword, .. } => Some(keyword.range()),
+        Expression::Impl { region, .. } => Some(region.range()),
+        Expression::Labeled { label, .. } => Some(label.range()),
+        Expression::Match { expr, .. } => expr.span(),
+        _ => None,
+    })
+}
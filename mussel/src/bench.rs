@@ -0,0 +1,100 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel bench`, which measures how long functions written in Mussel take, to see how a change of
+// the interpreter or of the standard library affects them.
+// The benchmarks are the functions whose name starts with `bench_` in the files whose name ends
+// with `_bench.mus`, found like the tests of `mussel test`. Each file runs in its own interpreter,
+// then each benchmark is called a few times to warm up, and then timed over a number of runs.
+
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use crate::interpreter;
+use crate::interpreter::Backend;
+use crate::testing;
+
+// What ends the name of the files holding benchmarks.
+const BENCH_FILE_SUFFIX: &str = "_bench.mus";
+
+// What starts the name of the benchmark functions.
+const BENCH_FUNCTION_PREFIX: &str = "bench_";
+
+// How the benchmarks are run.
+pub struct Options {
+    pub warmup: usize, // The calls before the timed ones, which aren't measured.
+    pub runs: usize,   // The timed calls.
+    pub check_types: bool,
+    pub max_depth: usize,
+    pub backend: Backend,
+}
+
+// Runs the benchmarks of the given files, and of the benchmark files found in the given
+// directories, printing the time their runs took. Fails when an error stopped a benchmark.
+pub fn run(paths: &[String], options: &Options) -> ExitCode {
+    let mut failed = false;
+    for file in testing::files(paths, BENCH_FILE_SUFFIX) {
+        let name = testing::display(&file);
+        let started = testing::start(&file, BENCH_FUNCTION_PREFIX, options.check_types, options.max_depth, options.backend);
+        let Some((mut context, benchmarks)) = started else {
+            println!("bench {name} ... FAILED");
+            failed = true;
+            continue;
+        };
+        for benchmark in benchmarks {
+            let Some(call) = testing::call(&benchmark, &mut context) else {
+                failed = true;
+                continue;
+            };
+            let mut times = Vec::with_capacity(options.runs);
+            let completed = (0..options.warmup + options.runs).all(|run| {
+                let start = Instant::now();
+                let passed = testing::passed(interpreter::interact(call.clone(), &mut context));
+                if run >= options.warmup {
+                    times.push(start.elapsed());
+                }
+                passed
+            });
+            if completed {
+                println!("bench {name}::{benchmark} ... {}", statistics(&times));
+            } else {
+                println!("bench {name}::{benchmark} ... FAILED");
+                failed = true;
+            }
+            io::stdout().flush().expect("Failed to flush stdout");
+        }
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+// Describes the times of the runs of a benchmark: their mean, median and standard deviation.
+fn statistics(times: &[Duration]) -> String {
+    if times.is_empty() {
+        return "no timed runs".to_string();
+    }
+    let mut seconds: Vec<f64> = times.iter().map(Duration::as_secs_f64).collect();
+    seconds.sort_by(f64::total_cmp);
+    let count = seconds.len() as f64;
+    let mean = seconds.iter().sum::<f64>() / count;
+    let middle = seconds.len() / 2;
+    let median = if seconds.len().is_multiple_of(2) { (seconds[middle - 1] + seconds[middle]) / 2.0 } else { seconds[middle] };
+    let deviation = (seconds.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / count).sqrt();
+    format!(
+        "mean {}, median {}, stddev {} ({} runs)",
+        duration(mean),
+        duration(median),
+        duration(deviation),
+        seconds.len()
+    )
+}
+
+// Writes a number of seconds with the unit that suits it, see also `profile` and `timing`.
+pub(crate) fn duration(seconds: f64) -> String {
+    if seconds >= 1.0 {
+        format!("{seconds:.3} s")
+    } else if seconds >= 1e-3 {
+        format!("{:.3} ms", seconds * 1e3)
+    } else {
+        format!("{:.3} µs", seconds * 1e6)
+    }
+}
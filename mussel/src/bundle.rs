@@ -0,0 +1,212 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Standalone executables, made with `mussel build`.
+// An executable is a copy of the interpreter with a bundle appended to it: the script, the files
+// it includes, and the options to run it with. When the interpreter starts, it looks for a bundle
+// at the end of its own file and runs the script in it instead of reading its command line.
+// The files are kept as source code, parsed again when the executable runs, and named by their
+// path relative to the directory of the script, so the includes find them where they were.
+//
+// Layout of the bundle, after the interpreter:
+//   max depth (u64) | backend (u8) | number of files (u64) | for each file: path, content
+//   | length of all of the above (u64) | MAGIC
+// where the paths and the contents are each a length (u64) followed by UTF-8 bytes, and all the
+// numbers are little-endian.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::process::ExitCode;
+use crate::error::FileError;
+use crate::interpreter::Backend;
+use crate::lexer;
+use crate::lexer::Token;
+use crate::loader::Loader;
+
+// Marks the end of an executable carrying a bundle.
+const MAGIC: &[u8; 8] = b"MUSSELB1";
+
+// A script bundled with the interpreter, and how to run it.
+pub struct Bundle {
+    pub max_depth: usize,
+    pub backend: Backend,
+    // The script first, then the files it includes.
+    files: Vec<(PathBuf, String)>,
+}
+
+impl Bundle {
+    // Returns the path of the script.
+    pub fn script(&self) -> &Path {
+        &self.files[0].0
+    }
+
+    // Returns the content of a bundled file.
+    pub fn file(&self, path: &Path) -> Option<&str> {
+        self.files.iter().find(|(name, _)| name == path).map(|(_, content)| content.as_str())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        fn text(bytes: &mut Vec<u8>, text: &str) {
+            bytes.extend((text.len() as u64).to_le_bytes());
+            bytes.extend(text.as_bytes());
+        }
+        let mut bytes = Vec::new();
+        bytes.extend((self.max_depth as u64).to_le_bytes());
+        bytes.push(match self.backend {
+            Backend::Tree => 0,
+            Backend::Vm => 1,
+        });
+        bytes.extend((self.files.len() as u64).to_le_bytes());
+        for (path, content) in &self.files {
+            text(&mut bytes, &path.to_string_lossy());
+            text(&mut bytes, content);
+        }
+        bytes.extend((bytes.len() as u64).to_le_bytes());
+        bytes.extend(MAGIC);
+        bytes
+    }
+
+    // Reads a bundle encoded by `encode`, without its length and magic.
+    fn decode(mut bytes: &[u8]) -> Option<Bundle> {
+        fn number(bytes: &mut &[u8]) -> Option<usize> {
+            let (number, rest) = bytes.split_first_chunk::<8>()?;
+            *bytes = rest;
+            usize::try_from(u64::from_le_bytes(*number)).ok()
+        }
+        fn text(bytes: &mut &[u8]) -> Option<String> {
+            let length = number(bytes)?;
+            let (text, rest) = bytes.split_at_checked(length)?;
+            *bytes = rest;
+            String::from_utf8(text.to_vec()).ok()
+        }
+        let max_depth = number(&mut bytes)?;
+        let (backend, rest) = bytes.split_first()?;
+        bytes = rest;
+        let backend = match backend {
+            0 => Backend::Tree,
+            1 => Backend::Vm,
+            _ => return None,
+        };
+        let count = number(&mut bytes)?;
+        let files = (0..count)
+            .map(|_| Some((PathBuf::from(text(&mut bytes)?), text(&mut bytes)?)))
+            .collect::<Option<Vec<_>>>()?;
+        if files.is_empty() {
+            return None;
+        }
+        Some(Bundle { max_depth, backend, files })
+    }
+}
+
+// Returns the bundle appended to the running executable, if there is one.
+pub fn embedded() -> Option<Bundle> {
+    let mut executable = File::open(std::env::current_exe().ok()?).ok()?;
+    let mut trailer = [0; 16];
+    executable.seek(SeekFrom::End(-16)).ok()?;
+    executable.read_exact(&mut trailer).ok()?;
+    let (length, magic) = trailer.split_at(8);
+    if magic != MAGIC {
+        return None;
+    }
+    let length = u64::from_le_bytes(length.try_into().ok()?);
+    let mut bytes = vec![0; usize::try_from(length).ok()?];
+    executable.seek(SeekFrom::End(-16 - i64::try_from(length).ok()?)).ok()?;
+    executable.read_exact(&mut bytes).ok()?;
+    Bundle::decode(&bytes)
+}
+
+// Writes a standalone executable running the given script, made of the running interpreter and
+// the script with every file it includes. The files are parsed first, so their errors are
+// reported now rather than when the executable runs. Fails when an error was reported.
+pub fn build(file: &str, output: &Path, max_depth: usize, backend: Backend) -> ExitCode {
+    let mut loader = Loader::new(false);
+    let script = PathBuf::from(Path::new(file).file_name().unwrap_or_else(|| file.as_ref()));
+    let mut files: Vec<(PathBuf, String)> = Vec::new();
+    // The files left to bundle, named in the bundle and where they are found now.
+    let mut pending = vec![(script.clone(), PathBuf::from(file))];
+    loader.enter(Path::new(file));
+    while let Some((name, path)) = pending.pop() {
+        if files.iter().any(|(bundled, _)| *bundled == name) {
+            continue;
+        }
+        // The included files are loaded as included by the script, like when it runs.
+        let entered = name != script && loader.enter(&path).is_none();
+        let loaded = loader.load(&path);
+        if entered {
+            loader.leave();
+        }
+        if let Err(error) = loaded {
+            loader.report(error);
+            return ExitCode::FAILURE;
+        }
+        let content = std::fs::read_to_string(&path).expect("the file was just loaded");
+        for include in includes(&content) {
+            let Ok(found) = loader.find_from(&path, Path::new(&include)) else {
+                let message = format!("Failed to find the file `{include}` included by this file");
+                loader.report(FileError::new(&path, message));
+                return ExitCode::FAILURE;
+            };
+            let directory = name.parent().unwrap_or(Path::new(""));
+            pending.push((normalize(&directory.join(&include)), found));
+        }
+        files.push((name, content));
+    }
+    let bundle = Bundle { max_depth, backend, files };
+    let written = std::env::current_exe().and_then(|interpreter| {
+        let mut executable = std::fs::read(interpreter)?;
+        executable.extend(bundle.encode());
+        std::fs::write(output, executable)?;
+        make_executable(output)
+    });
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            loader.report(FileError::new(output, format!("Failed to write the executable: {error}")));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+// Returns the paths of the files included by some code, as written.
+// An `include` is always followed by the path of a file in a string, or the name of a library.
+pub(crate) fn includes(content: &str) -> Vec<String> {
+    let Some(tokens) = lexer::lex_range(content, 0..content.len()) else {
+        return Vec::new();
+    };
+    tokens
+        .windows(2)
+        .filter(|pair| pair[0].token_type == Token::Include && pair[1].token_type == Token::String)
+        .map(|pair| {
+            let literal = pair[1].get_content(content);
+            literal.trim_matches('"').to_string()
+        })
+        .collect()
+}
+
+// Resolves the `.` and `..` of a path without looking at the file system, as the bundled files
+// only exist in the bundle.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
@@ -0,0 +1,910 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// A compiler turning expressions into a flat list of instructions, and the virtual machine running them.
+// The tree-walking interpreter consumes the expressions it evaluates, so it clones the body of a
+// loop for every iteration and the body of a function for every call. Compiled code is built once
+// and only read while it runs.
+// The machine works on the same values and context as the interpreter, and uses its operators,
+// calls and errors, so both backends behave the same. Every expression leaves its value on a stack
+// of values, where the instructions take their operands from. Loops and `try` blocks record what
+// to restore when they are left early, by a `break`, a `continue` or a thrown error.
+// A function created by compiled code is a `Compiled` value, whose body runs on the machine
+// wherever it is called from. Declarations that run only once, like structs and includes, are
+// evaluated by the interpreter.
+// The compiler knows the variables each block declares, and resolves the variables used by the
+// code to their slot in the scope of one of these blocks, so the machine doesn't look up their
+// names. The variables declared outside of the compiled blocks, like the global ones, are still
+// looked up by name, skipping the scopes of the blocks, which don't have them.
+
+use std::rc::Rc;
+use crate::context::{Context, Environment, Layout, Resolution};
+use crate::error;
+use crate::error::{AssertionError, ConditionTypeError, Location, UndeclaredLabelError};
+use crate::expr::{
+    Atom, BinOp, Binding, Expr, FormatSpec, LogicOp, Operator, Parameter, Pattern, StringPart, TypeAnnotation, UnaryOp,
+};
+use crate::interpreter;
+use crate::interpreter::{Eval, Iteration};
+use crate::walk::binding_names;
+
+// A function compiled from its body, called like a closure by `interpreter::call`.
+#[derive(Debug)]
+pub struct Function {
+    pub parameters: Vec<Parameter>,
+    pub return_type: Option<TypeAnnotation>,
+    pub layout: Rc<Layout>, // The parameters and the variables declared by the body, in the scope of the call.
+    pub code: Vec<Instruction>,
+}
+
+// The instructions of the virtual machine. The operands of an instruction are the values on top
+// of the stack, the last one on top. A jump goes to the instruction at the given index.
+#[derive(Debug)]
+pub enum Instruction {
+    Constant(Expr),                   // Pushes a value.
+    Load(String, Resolution, Location), // Pushes the value of a variable.
+    LoadFunction(String, Resolution, Location), // Pushes the function called by name.
+    Pop,                              // Drops a value.
+    Declare(Binding, bool),           // Pops a value and declares the variables of the binding, as constants when true.
+    DeclareSlot(usize, String, bool), // Like `Declare`, for a variable with a slot in the innermost scope.
+    Assign(Expr, Location),           // Pops a value and assigns it to the target.
+    Store(String, Resolution, Location), // Pops a value and assigns it to a variable.
+    Closure(Rc<Function>),            // Pushes a function capturing the innermost scope.
+    Define(String, Rc<Function>),     // Declares a named function capturing the innermost scope.
+    Impl(String, Option<String>, Vec<(String, Rc<Function>)>, Location), // Adds methods to a struct.
+    Evaluate(Expr),                   // Pushes the value of an expression evaluated by the interpreter.
+    Array(usize),                     // Pops values and pushes an array of them.
+    Append,                           // Pops a value and appends it to the array below it.
+    Format(Option<FormatSpec>),       // Pops a value and pushes it as a string, following the specifier.
+    Concat(usize),                    // Pops strings and pushes them joined.
+    Binary(BinOp, Location),          // Pops two values and applies an arithmetic operator.
+    Unary(UnaryOp, Location),         // Pops a value and applies a unary operator.
+    Compare(Operator, Location),      // Pops two values and compares them.
+    Logic(LogicOp, Location, usize),  // Pops the left operand of `and`/`or`, jumping with the result when it decides it.
+    CheckBool(LogicOp, Location),     // Checks that the right operand of `and`/`or` is a boolean.
+    Range(bool, Location),            // Pops two bounds and pushes a range, including the end when true.
+    Get(Location),                    // Pops an array and an index, and pushes the element.
+    Field(String, Location),          // Pops a struct instance or a module, and pushes one of its fields.
+    Path(String, String, Location),   // Pushes the variant or method named by a path.
+    Call(usize, String, Location),    // Pops the arguments and the function, and calls it.
+    MethodCall(usize, String, Location), // Pops the arguments and the receiver, and calls the method.
+    TailCall(usize, String, Location), // Like `Call`, but leaves the function, which performs the call.
+    Return,                           // Pops a value and returns it from the function.
+    Throw(Location),                  // Pops a value and throws it.
+    Assert(Location, usize),          // Pops the condition of an assertion, jumping when it holds.
+    AssertionFailed(bool, Location),  // Stops the script, with the message popped when true.
+    Jump(usize),
+    Branch(bool, usize, &'static str, Location), // Pops a condition, jumping when it has the given value.
+    PushScope(Rc<Layout>),            // Starts a scope with slots for the variables of the layout.
+    PopScope,
+    Enter { iterate: Option<Location>, keep: bool, exit: usize }, // Starts a loop, see `Frame`.
+    Next(usize),                      // Pushes the next item of the loop, or jumps when there are none left.
+    Leave,                            // Ends the innermost loop.
+    Break(usize),                     // Pops a value and leaves the loop with the given frame.
+    Continue(usize),                  // Starts the next iteration of the loop with the given frame.
+    BreakOut(Option<String>, Location), // Pops a value and hands a `break` to the caller, outside of any loop here.
+    ContinueOut(Option<String>, Location), // Hands a `continue` to the caller, outside of any loop here.
+    Try(usize),                       // Starts a try block, whose catch block is at the given index.
+    EndTry,                           // Ends the innermost try block.
+    Match(Pattern, Rc<Layout>, usize), // Matches the value on top against a pattern, see `Expr::Match`.
+}
+
+// Compiles a statement of a script, to be run with `run`.
+pub fn evaluate(expr: &Expr, context: &mut Context) -> Eval {
+    let mut compiler = Compiler::default();
+    compiler.expression(expr);
+    run(&compiler.code, context)
+}
+
+// A block of code in which a `break`, a `continue` or a thrown error can end up, while compiling.
+// There is one for each frame the machine has when running the code of the block.
+enum Block {
+    Loop(Option<String>), // A loop, with its label.
+    Comprehension,        // A comprehension, which a `break` or `continue` doesn't target.
+    Handler,              // The body of a `try` block.
+}
+
+#[derive(Default)]
+struct Compiler {
+    code: Vec<Instruction>,
+    blocks: Vec<Block>,
+    // The index of the `Constant(Void)` giving the value of the last declaration, which isn't needed
+    // when the value of the declaration is dropped.
+    declared: Option<usize>,
+    // The layouts of the compiled blocks whose scopes are visible, the innermost last, starting
+    // with the blocks around the function being compiled.
+    scopes: Vec<Rc<Layout>>,
+}
+
+impl Compiler {
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    // Makes the jump of the instruction at the given index go to the next instruction emitted.
+    fn patch(&mut self, at: usize) {
+        let target = self.code.len();
+        match &mut self.code[at] {
+            Instruction::Jump(to)
+            | Instruction::Branch(_, to, _, _)
+            | Instruction::Logic(_, _, to)
+            | Instruction::Assert(_, to)
+            | Instruction::Next(to)
+            | Instruction::Try(to)
+            | Instruction::Match(_, _, to)
+            | Instruction::Enter { exit: to, .. } => *to = target,
+            instruction => unreachable!("`{instruction:?}` doesn't jump"),
+        }
+    }
+
+    // Declarations evaluate to void.
+    fn declaration(&mut self) {
+        self.declared = Some(self.emit(Instruction::Constant(Expr::Void)));
+    }
+
+    // Finds the frame of the loop targeted by a `break` or `continue`: the innermost one, or the
+    // one with the label.
+    fn target(&self, label: Option<&str>) -> Option<usize> {
+        self.blocks.iter().rposition(|block| match block {
+            Block::Loop(name) => label.is_none_or(|label| name.as_deref() == Some(label)),
+            _ => false,
+        })
+    }
+
+    // Finds the innermost compiled block declaring a variable, whose scope is `depth` scopes out.
+    fn resolve(&self, name: &str) -> Resolution {
+        self.scopes
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(depth, layout)| Some(Resolution::Slot { depth, slot: layout.slot(name)? }))
+            .unwrap_or(Resolution::Outer { depth: self.scopes.len() })
+    }
+
+    fn push_scope(&mut self, layout: Layout) {
+        let layout = Rc::new(layout);
+        self.scopes.push(layout.clone());
+        self.emit(Instruction::PushScope(layout));
+    }
+
+    fn pop_scope(&mut self) {
+        self.scopes.pop();
+        self.emit(Instruction::PopScope);
+    }
+
+    // Declares the variables of a binding in the innermost scope.
+    fn declare(&mut self, binding: &Binding, constant: bool) {
+        let slot = match binding {
+            Binding::Name(name) => self.scopes.last().and_then(|layout| layout.slot(name)),
+            _ => None,
+        };
+        match (binding, slot) {
+            (Binding::Name(name), Some(slot)) => self.emit(Instruction::DeclareSlot(slot, name.clone(), constant)),
+            (binding, _) => self.emit(Instruction::Declare(binding.clone(), constant)),
+        };
+    }
+
+    // Compiles the statements of a block, leaving the value of the last one, in the current scope.
+    fn statements(&mut self, body: &[Expr]) {
+        let Some((last, body)) = body.split_last() else {
+            self.emit(Instruction::Constant(Expr::Void));
+            return;
+        };
+        for expr in body {
+            self.expression(expr);
+            if self.declared == Some(self.code.len() - 1) {
+                self.code.pop();
+            } else {
+                self.emit(Instruction::Pop);
+            }
+        }
+        self.expression(last);
+    }
+
+    // Compiles a block running in its own scope.
+    fn block(&mut self, body: &[Expr]) {
+        self.push_scope(Layout::new(declared(body)));
+        self.statements(body);
+        self.pop_scope();
+    }
+
+    // Compiles the body of a loop, whose value is dropped.
+    fn loop_body(&mut self, body: &[Expr]) {
+        self.push_scope(Layout::new(declared(body)));
+        self.statements(body);
+        self.emit(Instruction::Pop);
+        self.pop_scope();
+    }
+
+    // Compiles the function of a call, returning the name of the call for the call stack.
+    fn callee(&mut self, function: &Expr) -> String {
+        match function {
+            Expr::Variable(name, location) => {
+                self.emit(Instruction::LoadFunction(name.clone(), self.resolve(name), location.clone()));
+                name.clone()
+            }
+            function => {
+                self.expression(function);
+                "<anonymous>".to_string()
+            }
+        }
+    }
+
+    fn expressions(&mut self, exprs: &[Expr]) {
+        for expr in exprs {
+            self.expression(expr);
+        }
+    }
+
+    // Compiles an expression, see `interpreter_expr` for what each one does.
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Constant(_)
+            | Expr::Void
+            | Expr::Closure(_, _, _, Some(_))
+            | Expr::Compiled(..)
+            | Expr::Instance(_, _)
+            | Expr::Module(_, _)
+            | Expr::Variant(_, _, _)
+            | Expr::RangeValue(_, _, _, _)
+            | Expr::TailCall(..)
+            | Expr::Builtin(_)
+            | Expr::Native(_) => {
+                self.emit(Instruction::Constant(expr.clone()));
+            }
+            Expr::Variable(name, location) => {
+                self.emit(Instruction::Load(name.clone(), self.resolve(name), location.clone()));
+            }
+            Expr::Closure(parameters, return_type, body, None) => {
+                self.emit(Instruction::Closure(compile_function(parameters, return_type, body, &self.scopes)));
+            }
+            Expr::Function(name, parameters, return_type, body) => {
+                let function = compile_function(parameters, return_type, body, &self.scopes);
+                self.emit(Instruction::Define(name.clone(), function));
+                self.declaration();
+            }
+            Expr::Impl(name, trait_name, functions, location) => {
+                let methods = functions
+                    .iter()
+                    .map(|function| {
+                        let Expr::Function(method, parameters, return_type, body) = function else {
+                            unreachable!("an `impl` block only contains functions")
+                        };
+                        (method.clone(), compile_function(parameters, return_type, body, &self.scopes))
+                    })
+                    .collect();
+                self.emit(Instruction::Impl(name.clone(), trait_name.clone(), methods, location.clone()));
+                self.declaration();
+            }
+            Expr::Struct(..) | Expr::Trait(..) | Expr::Enum(..) | Expr::Include(..) => {
+                self.emit(Instruction::Evaluate(expr.clone()));
+            }
+            Expr::Array(items) => {
+                self.expressions(items);
+                self.emit(Instruction::Array(items.len()));
+            }
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => {
+                            self.emit(Instruction::Constant(Expr::Constant(Atom::String(text.as_str().into()))));
+                        }
+                        StringPart::Value(expr, spec) => {
+                            self.expression(expr);
+                            self.emit(Instruction::Format(spec.clone()));
+                        }
+                    }
+                }
+                self.emit(Instruction::Concat(parts.len()));
+            }
+            Expr::Let(binding, value, _) | Expr::Const(binding, value, _) => {
+                self.expression(value);
+                self.declare(binding, matches!(expr, Expr::Const(..)));
+                self.declaration();
+            }
+            Expr::Assign(target, value, location) => {
+                self.expression(value);
+                match &**target {
+                    Expr::Variable(name, _) => {
+                        self.emit(Instruction::Store(name.clone(), self.resolve(name), location.clone()))
+                    }
+                    target => self.emit(Instruction::Assign(target.clone(), location.clone())),
+                };
+                self.declaration();
+            }
+            Expr::Binary(left, op, right, location) => {
+                self.expression(left);
+                self.expression(right);
+                self.emit(Instruction::Binary(op.clone(), location.clone()));
+            }
+            Expr::Unary(op, operand, location) => {
+                self.expression(operand);
+                self.emit(Instruction::Unary(op.clone(), location.clone()));
+            }
+            Expr::Compare(left, operator, right, location) => {
+                self.expression(left);
+                self.expression(right);
+                self.emit(Instruction::Compare(operator.clone(), location.clone()));
+            }
+            Expr::Logic(left, op, right, location) => {
+                self.expression(left);
+                let decided = self.emit(Instruction::Logic(op.clone(), location.clone(), 0));
+                self.expression(right);
+                self.emit(Instruction::CheckBool(op.clone(), location.clone()));
+                self.patch(decided);
+            }
+            Expr::Range(start, end, inclusive, location) => {
+                self.expression(start);
+                self.expression(end);
+                self.emit(Instruction::Range(*inclusive, location.clone()));
+            }
+            Expr::Get(array, index, location) => {
+                self.expression(array);
+                self.expression(index);
+                self.emit(Instruction::Get(location.clone()));
+            }
+            Expr::Field(instance, field, location) => {
+                self.expression(instance);
+                self.emit(Instruction::Field(field.clone(), location.clone()));
+            }
+            Expr::Path(name, variant, location) => {
+                self.emit(Instruction::Path(name.clone(), variant.clone(), location.clone()));
+            }
+            Expr::Call(function, args, location) => {
+                let name = self.callee(function);
+                self.expressions(args);
+                self.emit(Instruction::Call(args.len(), name, location.clone()));
+            }
+            Expr::MethodCall(receiver, method, args, location) => {
+                self.expression(receiver);
+                self.expressions(args);
+                self.emit(Instruction::MethodCall(args.len(), method.clone(), location.clone()));
+            }
+            // Inside a `try` block, the call is made before returning, so the errors it throws are caught.
+            Expr::Return(value) => match &**value {
+                Expr::Call(function, args, location)
+                    if !self.blocks.iter().any(|block| matches!(block, Block::Handler)) =>
+                {
+                    let name = self.callee(function);
+                    self.expressions(args);
+                    self.emit(Instruction::TailCall(args.len(), name, location.clone()));
+                }
+                value => {
+                    self.expression(value);
+                    self.emit(Instruction::Return);
+                }
+            },
+            Expr::Throw(value, location) => {
+                self.expression(value);
+                self.emit(Instruction::Throw(location.clone()));
+            }
+            Expr::Assert(condition, message, location) => {
+                self.expression(condition);
+                let holds = self.emit(Instruction::Assert(location.clone(), 0));
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+                self.emit(Instruction::AssertionFailed(message.is_some(), location.clone()));
+                self.patch(holds);
+            }
+            Expr::If(condition, then, otherwise, location) => {
+                self.expression(condition);
+                let skip = self.emit(Instruction::Branch(false, 0, "if", location.clone()));
+                self.block(then);
+                let end = self.emit(Instruction::Jump(0));
+                self.patch(skip);
+                match otherwise {
+                    Some(body) => self.block(body),
+                    None => {
+                        self.emit(Instruction::Constant(Expr::Void));
+                    }
+                }
+                self.patch(end);
+            }
+            Expr::Try(body, name, catch_body) => {
+                let handler = self.emit(Instruction::Try(0));
+                self.blocks.push(Block::Handler);
+                self.block(body);
+                self.blocks.pop();
+                self.emit(Instruction::EndTry);
+                let end = self.emit(Instruction::Jump(0));
+                // The machine jumps here with the error on the stack.
+                self.patch(handler);
+                self.push_scope(Layout::new(std::iter::once(name.clone()).chain(declared(catch_body))));
+                self.declare(&Binding::Name(name.clone()), false);
+                self.statements(catch_body);
+                self.pop_scope();
+                self.patch(end);
+            }
+            Expr::Match(value, arms) => {
+                self.expression(value);
+                let mut ends = Vec::new();
+                for (pattern, body) in arms {
+                    let mut names = Vec::new();
+                    pattern_names(pattern, &mut names);
+                    let layout = Rc::new(Layout::new(names.into_iter().chain(declared(body))));
+                    let next = self.emit(Instruction::Match(pattern.clone(), layout.clone(), 0));
+                    self.scopes.push(layout);
+                    self.statements(body);
+                    self.pop_scope();
+                    ends.push(self.emit(Instruction::Jump(0)));
+                    self.patch(next);
+                }
+                // No arm matched.
+                self.emit(Instruction::Pop);
+                self.emit(Instruction::Constant(Expr::Void));
+                for end in ends {
+                    self.patch(end);
+                }
+            }
+            Expr::For(binding, collection, body, location, label) => {
+                self.expression(collection);
+                let enter = self.emit(Instruction::Enter { iterate: Some(location.clone()), keep: false, exit: 0 });
+                self.blocks.push(Block::Loop(label.clone()));
+                let next = self.emit(Instruction::Next(0));
+                let mut names = Vec::new();
+                binding_names(binding, &mut names);
+                self.push_scope(Layout::new(names.into_iter().chain(declared(body))));
+                self.declare(binding, false);
+                self.statements(body);
+                self.emit(Instruction::Pop);
+                self.pop_scope();
+                self.emit(Instruction::Jump(next));
+                self.blocks.pop();
+                self.patch(next);
+                self.emit(Instruction::Leave);
+                self.emit(Instruction::Constant(Expr::Void));
+                self.patch(enter);
+            }
+            Expr::Until(condition, body, location, label) => {
+                let enter = self.emit(Instruction::Enter { iterate: None, keep: false, exit: 0 });
+                self.blocks.push(Block::Loop(label.clone()));
+                self.expression(condition);
+                let done = self.emit(Instruction::Branch(true, 0, "until", location.clone()));
+                self.loop_body(body);
+                self.emit(Instruction::Jump(enter + 1));
+                self.blocks.pop();
+                self.patch(done);
+                self.emit(Instruction::Leave);
+                self.emit(Instruction::Constant(Expr::Void));
+                self.patch(enter);
+            }
+            // A `loop` is only left by a `break`, which gives its value.
+            Expr::Loop(body, label) => {
+                let enter = self.emit(Instruction::Enter { iterate: None, keep: true, exit: 0 });
+                self.blocks.push(Block::Loop(label.clone()));
+                self.loop_body(body);
+                self.emit(Instruction::Jump(enter + 1));
+                self.blocks.pop();
+                self.patch(enter);
+            }
+            Expr::Comprehension(value, binding, collection, location, condition) => {
+                self.emit(Instruction::Constant(Expr::Array(Rc::new(Vec::new()))));
+                self.expression(collection);
+                let enter = self.emit(Instruction::Enter { iterate: Some(location.clone()), keep: false, exit: 0 });
+                self.blocks.push(Block::Comprehension);
+                let next = self.emit(Instruction::Next(0));
+                let mut names = Vec::new();
+                binding_names(binding, &mut names);
+                self.push_scope(Layout::new(names));
+                self.declare(binding, false);
+                let skip = condition.as_ref().map(|(condition, location)| {
+                    self.expression(condition);
+                    self.emit(Instruction::Branch(false, 0, "if", location.clone()))
+                });
+                self.expression(value);
+                self.emit(Instruction::Append);
+                if let Some(skip) = skip {
+                    self.patch(skip);
+                }
+                self.pop_scope();
+                self.emit(Instruction::Jump(next));
+                self.blocks.pop();
+                self.patch(next);
+                self.emit(Instruction::Leave);
+                self.patch(enter);
+            }
+            Expr::Break(value, label, location) => {
+                // `break name` leaves the loop labeled `name` if there is one, otherwise it breaks
+                // with the value of the variable `name`.
+                if label.is_none()
+                    && let Expr::Variable(name, _) = &**value
+                    && let Some(target) = self.target(Some(name))
+                {
+                    self.emit(Instruction::Constant(Expr::Void));
+                    self.emit(Instruction::Break(target));
+                    return;
+                }
+                self.expression(value);
+                match self.target(label.as_deref()) {
+                    Some(target) => self.emit(Instruction::Break(target)),
+                    None => self.emit(Instruction::BreakOut(label.clone(), location.clone())),
+                };
+            }
+            Expr::Continue(label, location) => {
+                match self.target(label.as_deref()) {
+                    Some(target) => self.emit(Instruction::Continue(target)),
+                    None => self.emit(Instruction::ContinueOut(label.clone(), location.clone())),
+                };
+                // The value of a `continue` is never used, but every expression leaves one.
+                self.emit(Instruction::Constant(Expr::Void));
+            }
+        }
+    }
+}
+
+// Compiles the body of a function created inside the compiled blocks with the given layouts.
+fn compile_function(
+    parameters: &[Parameter],
+    return_type: &Option<TypeAnnotation>,
+    body: &[Expr],
+    scopes: &[Rc<Layout>],
+) -> Rc<Function> {
+    let names = parameters.iter().map(|parameter| parameter.name.clone()).chain(declared(body));
+    let layout = Rc::new(Layout::new(names));
+    let mut compiler = Compiler { scopes: scopes.to_vec(), ..Compiler::default() };
+    compiler.scopes.push(layout.clone());
+    compiler.statements(body);
+    Rc::new(Function { parameters: parameters.to_vec(), return_type: return_type.clone(), layout, code: compiler.code })
+}
+
+// Returns the names of the variables declared by the statements of a block in its own scope.
+// The variables declared by `eval` or an `include` without a name aren't known.
+fn declared(body: &[Expr]) -> Vec<String> {
+    let mut names = Vec::new();
+    for expr in body {
+        match expr {
+            Expr::Let(binding, ..) | Expr::Const(binding, ..) => binding_names(binding, &mut names),
+            Expr::Function(name, ..)
+            | Expr::Struct(name, ..)
+            | Expr::Enum(name, ..)
+            | Expr::Trait(name, ..)
+            | Expr::Include(_, Some(name), _) => names.push(name.clone()),
+            _ => {}
+        }
+    }
+    names
+}
+
+fn pattern_names(pattern: &Pattern, names: &mut Vec<String>) {
+    match pattern {
+        Pattern::Binding(name) | Pattern::Rest(Some(name)) => names.push(name.clone()),
+        Pattern::Variant(_, _, Some(patterns)) | Pattern::Array(patterns) => {
+            for pattern in patterns {
+                pattern_names(pattern, names);
+            }
+        }
+        Pattern::Struct(_, fields) => {
+            for (_, pattern) in fields {
+                pattern_names(pattern, names);
+            }
+        }
+        Pattern::Wildcard | Pattern::Literal(_) | Pattern::Variant(_, _, None) | Pattern::Rest(None) => {}
+    }
+}
+
+// What the machine restores when a loop or a `try` block is left early: the height of the stack
+// and the scopes visible when it started.
+struct Frame {
+    kind: FrameKind,
+    stack: usize,
+    environment: Environment,
+}
+
+enum FrameKind {
+    // A loop, with the items it iterates over, whether a `break` gives the value of the loop, and
+    // where its next iteration starts and where it ends.
+    Loop { items: Option<Iteration>, keep: bool, next: usize, exit: usize },
+    // A `try` block, with the index of its catch block.
+    Handler(usize),
+}
+
+// Runs compiled code and returns the value of its last statement. Like `interpreter_expr`, a
+// `return`, a thrown error, or a `break` or `continue` outside of the loops of the code, stops it
+// and is returned as-is, for the caller to handle.
+pub fn run(code: &[Instruction], context: &mut Context) -> Eval {
+    let environment = context.environment();
+    let mut stack: Vec<Expr> = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut pc = 0;
+    while let Some(instruction) = code.get(pc) {
+        pc += 1;
+        context.step()?;
+        // The value pushed by the instruction, if any.
+        let value = match instruction {
+            Instruction::Constant(value) => Some(value.clone()),
+            Instruction::Load(name, resolution, location) => match context.get_resolved(name, *resolution) {
+                Some(value) => Some(value),
+                None => return interpreter::not_found(name, location),
+            },
+            Instruction::LoadFunction(name, resolution, location) => match context.get_resolved(name, *resolution) {
+                Some(function) => Some(function),
+                None => return interpreter::function_not_found(name, location),
+            },
+            Instruction::Pop => {
+                stack.pop();
+                None
+            }
+            Instruction::Declare(binding, constant) => {
+                let value = pop(&mut stack);
+                for (name, value) in interpreter::destructure(binding.clone(), value)? {
+                    if *constant {
+                        context.insert_constant(name, value);
+                    } else {
+                        context.insert(name, value);
+                    }
+                }
+                None
+            }
+            Instruction::DeclareSlot(slot, name, constant) => {
+                context.declare_slot(*slot, name, pop(&mut stack), *constant);
+                None
+            }
+            Instruction::Assign(target, location) => {
+                let value = pop(&mut stack);
+                interpreter::assign(target.clone(), value, location.clone(), context)?;
+                None
+            }
+            // A missing variable or a constant is reported by `assign`.
+            Instruction::Store(name, resolution, location) => {
+                if let Some(value) = context.assign_resolved(name, *resolution, pop(&mut stack)) {
+                    let target = Expr::Variable(name.clone(), location.clone());
+                    interpreter::assign(target, value, location.clone(), context)?;
+                }
+                None
+            }
+            Instruction::Closure(function) => Some(Expr::Compiled(function.clone(), context.environment())),
+            // The function is declared in the scope it captures, so it can call itself.
+            Instruction::Define(name, function) => {
+                let closure = Expr::Compiled(function.clone(), context.environment());
+                context.insert(name.clone(), closure);
+                None
+            }
+            Instruction::Impl(name, trait_name, functions, location) => {
+                let methods = functions
+                    .iter()
+                    .map(|(method, function)| (method.clone(), Expr::Compiled(function.clone(), context.environment())))
+                    .collect();
+                interpreter::implement(name.clone(), trait_name.clone(), methods, location.clone(), context)?;
+                None
+            }
+            Instruction::Evaluate(expr) => Some(interpreter::interpreter_expr(expr.clone(), context)?),
+            Instruction::Array(count) => Some(Expr::Array(Rc::new(stack.split_off(stack.len() - count)))),
+            Instruction::Append => {
+                let value = pop(&mut stack);
+                if let Some(Expr::Array(values)) = stack.last_mut() {
+                    Rc::make_mut(values).push(value);
+                }
+                None
+            }
+            Instruction::Format(spec) => {
+                let value = pop(&mut stack);
+                Some(Expr::Constant(Atom::String(interpreter::format_value(&value, spec.as_ref()).into())))
+            }
+            Instruction::Concat(count) => {
+                let mut result = String::new();
+                for part in stack.split_off(stack.len() - count) {
+                    if let Expr::Constant(Atom::String(text)) = part {
+                        result.push_str(&text);
+                    }
+                }
+                Some(Expr::Constant(Atom::String(result.into())))
+            }
+            Instruction::Binary(op, location) => {
+                let right = pop(&mut stack);
+                let left = pop(&mut stack);
+                Some(interpreter::binary(left, op.clone(), right, location, context)?)
+            }
+            Instruction::Unary(op, location) => {
+                let value = pop(&mut stack);
+                Some(interpreter::unary(op.clone(), value, location, context)?)
+            }
+            Instruction::Compare(operator, location) => {
+                let right = pop(&mut stack);
+                let left = pop(&mut stack);
+                Some(interpreter::compare(left, operator.clone(), right, location, context)?)
+            }
+            // The right side is only evaluated when the left side doesn't decide the result.
+            Instruction::Logic(op, location, decided) => {
+                let left = interpreter::boolean_operand(pop(&mut stack), op, location)?;
+                match op {
+                    LogicOp::And if !left => {
+                        pc = *decided;
+                        Some(Expr::Constant(Atom::Boolean(false)))
+                    }
+                    LogicOp::Or if left => {
+                        pc = *decided;
+                        Some(Expr::Constant(Atom::Boolean(true)))
+                    }
+                    _ => None,
+                }
+            }
+            Instruction::CheckBool(op, location) => {
+                let right = interpreter::boolean_operand(pop(&mut stack), op, location)?;
+                Some(Expr::Constant(Atom::Boolean(right)))
+            }
+            Instruction::Range(inclusive, location) => {
+                let end = pop(&mut stack);
+                let start = pop(&mut stack);
+                Some(interpreter::range(start, end, *inclusive, location)?)
+            }
+            Instruction::Get(location) => {
+                let index = pop(&mut stack);
+                let array = pop(&mut stack);
+                Some(interpreter::element(array, index, location)?)
+            }
+            Instruction::Field(field, location) => Some(interpreter::field(pop(&mut stack), field, location)?),
+            Instruction::Path(name, variant, location) => {
+                Some(interpreter::path(name.clone(), variant.clone(), location, context)?)
+            }
+            Instruction::Call(count, name, location) => {
+                let args = stack.split_off(stack.len() - count);
+                let function = pop(&mut stack);
+                Some(interpreter::call_at(location, function, name.clone(), args, context)?)
+            }
+            Instruction::MethodCall(count, method, location) => {
+                let args = stack.split_off(stack.len() - count);
+                let receiver = pop(&mut stack);
+                Some(interpreter::call_method(receiver, method.clone(), args, location, context)?)
+            }
+            // A user-defined function is called by the function being left (see `call_closure`).
+            Instruction::TailCall(count, name, location) => {
+                let args = stack.split_off(stack.len() - count);
+                match pop(&mut stack) {
+                    closure @ (Expr::Closure(..) | Expr::Compiled(..)) => {
+                        Some(Expr::TailCall(name.clone(), Box::new(closure), args, location.clone()))
+                    }
+                    function => match interpreter::call_at(location, function, name.clone(), args, context)? {
+                        thrown @ Expr::Throw(..) => Some(thrown),
+                        value => Some(Expr::Return(Box::new(value))),
+                    },
+                }
+            }
+            Instruction::Return => Some(Expr::Return(Box::new(pop(&mut stack)))),
+            Instruction::Throw(location) => Some(Expr::Throw(Box::new(pop(&mut stack)), location.clone())),
+            Instruction::Assert(location, holds) => match pop(&mut stack) {
+                Expr::Constant(Atom::Boolean(true)) => {
+                    pc = *holds;
+                    Some(Expr::Void)
+                }
+                Expr::Constant(Atom::Boolean(false)) => None,
+                condition => {
+                    let error = ConditionTypeError::new(location.clone(), "assert".to_string(), condition.type_name());
+                    return error::raise(error);
+                }
+            },
+            Instruction::AssertionFailed(message, location) => {
+                let message = message.then(|| pop(&mut stack).to_string());
+                return error::raise(AssertionError::new(location.clone(), message));
+            }
+            Instruction::Jump(target) => {
+                pc = *target;
+                None
+            }
+            Instruction::Branch(when, target, construct, location) => {
+                if interpreter::condition(pop(&mut stack), construct, location)? == *when {
+                    pc = *target;
+                }
+                None
+            }
+            Instruction::PushScope(layout) => {
+                context.push_layout(layout.clone());
+                None
+            }
+            Instruction::PopScope => {
+                context.pop_scope();
+                None
+            }
+            Instruction::Enter { iterate, keep, exit } => {
+                let items = match iterate {
+                    Some(location) => Some(Iteration::new(pop(&mut stack), location)?),
+                    None => None,
+                };
+                let kind = FrameKind::Loop { items, keep: *keep, next: pc, exit: *exit };
+                frames.push(Frame { kind, stack: stack.len(), environment: context.environment() });
+                None
+            }
+            Instruction::Next(done) => {
+                let Some(Frame { kind: FrameKind::Loop { items: Some(items), .. }, .. }) = frames.last_mut() else {
+                    unreachable!("`Next` runs in a loop over items")
+                };
+                let item = items.next(context)?;
+                if item.is_none() {
+                    pc = *done;
+                }
+                item
+            }
+            Instruction::Leave | Instruction::EndTry => {
+                frames.pop();
+                None
+            }
+            Instruction::Break(target) => {
+                let value = pop(&mut stack);
+                frames.truncate(target + 1);
+                let frame = frames.pop().expect("a `break` runs in its loop");
+                let FrameKind::Loop { keep, exit, .. } = frame.kind else {
+                    unreachable!("a `break` targets a loop")
+                };
+                stack.truncate(frame.stack);
+                context.replace_environment(frame.environment);
+                pc = exit;
+                Some(if keep { value } else { Expr::Void })
+            }
+            Instruction::Continue(target) => {
+                frames.truncate(target + 1);
+                let frame = frames.last().expect("a `continue` runs in its loop");
+                let FrameKind::Loop { next, .. } = frame.kind else {
+                    unreachable!("a `continue` targets a loop")
+                };
+                stack.truncate(frame.stack);
+                context.replace_environment(frame.environment.clone());
+                pc = next;
+                None
+            }
+            Instruction::BreakOut(label, location) => {
+                Some(Expr::Break(Box::new(pop(&mut stack)), label.clone(), location.clone()))
+            }
+            Instruction::ContinueOut(label, location) => {
+                if let Some(name) = label
+                    && !context.has_label(name)
+                {
+                    return error::raise(UndeclaredLabelError::new(location.clone(), name.clone()));
+                }
+                Some(Expr::Continue(label.clone(), location.clone()))
+            }
+            Instruction::Try(catch) => {
+                let kind = FrameKind::Handler(*catch);
+                frames.push(Frame { kind, stack: stack.len(), environment: context.environment() });
+                None
+            }
+            // The arm runs in a new scope with the variables bound by its pattern.
+            Instruction::Match(pattern, layout, next) => {
+                let mut bindings = Vec::new();
+                let value = stack.last().expect("a `match` has a value");
+                if interpreter::pattern_matches(pattern, value, &mut bindings) {
+                    stack.pop();
+                    context.push_layout(layout.clone());
+                    for (name, value) in bindings {
+                        context.insert(name, value);
+                    }
+                } else {
+                    pc = *next;
+                }
+                None
+            }
+        };
+        match value {
+            // A thrown error goes to the catch block of the innermost `try` block, with the error
+            // on the stack. Other signals leave the code.
+            Some(signal) if interpreter::is_signal(&signal) => {
+                let handler = frames.iter().rposition(|frame| matches!(frame.kind, FrameKind::Handler(_)));
+                match (signal, handler) {
+                    (Expr::Throw(error, _), Some(handler)) => {
+                        frames.truncate(handler + 1);
+                        let Some(Frame { kind: FrameKind::Handler(catch), stack: height, environment }) = frames.pop()
+                        else {
+                            unreachable!("the frame of the handler was found")
+                        };
+                        stack.truncate(height);
+                        context.replace_environment(environment);
+                        stack.push(*error);
+                        pc = catch;
+                    }
+                    (signal, _) => {
+                        context.replace_environment(environment);
+                        return Ok(signal);
+                    }
+                }
+            }
+            Some(value) => stack.push(value),
+            None => {}
+        }
+    }
+    Ok(stack.pop().unwrap_or(Expr::Void))
+}
+
+// Takes the operand on top of the stack, which the compiler always leaves there.
+fn pop(stack: &mut Vec<Expr>) -> Expr {
+    stack.pop().expect("the stack holds the operands of the instruction")
+}
@@ -0,0 +1,288 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The C API, to embed the interpreter in a C or C++ program, declared by `include/mussel.h`. The
+// program links with the library built by `cargo build --lib --release`, `libmussel.so` (or
+// `libmussel.dylib`, `mussel.dll`), and uses it like the Rust programs use `embed`: an interpreter
+// runs code in its global scope, and the code calls the functions of the program registered in it.
+// The values given by the library are handles owned by the program, freed with `mussel_value_free`,
+// and the values given to it are only read. The strings given by the library are valid as long as
+// what gave them, the strings given to it are copied.
+// An interpreter, and its values, are used from the thread that created it. Nothing here is kept
+// from one version to the next but the functions of the header, which only get new ones.
+// The pointers given to the functions are valid and not NULL, but where the header says otherwise:
+// what the functions require of them is written there, for the C programs, rather than here.
+#![allow(clippy::missing_safety_doc)]
+
+use std::cell::OnceCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use crate::embed::{Error, Interpreter, Value};
+
+/// An interpreter, with the error which stopped the code it ran last, if any, and the status it
+/// gave to `exit`, if it called it.
+pub struct MusselInterpreter {
+    interpreter: Interpreter,
+    error: Option<CString>,
+    exit: Option<u8>,
+}
+
+/// A value, or an error returned by a function of the program to stop the code calling it.
+pub struct MusselValue {
+    value: Value,
+    error: Option<String>,
+    text: OnceCell<CString>, // The value as text, once asked for.
+}
+
+impl MusselValue {
+    fn new(value: Value) -> Self {
+        MusselValue { value, error: None, text: OnceCell::new() }
+    }
+
+    fn boxed(value: Value) -> *mut MusselValue {
+        Box::into_raw(Box::new(MusselValue::new(value)))
+    }
+}
+
+/// The types of the values, see `mussel_value_type`.
+#[repr(C)]
+pub enum MusselType {
+    Nil = 0,
+    Int = 1,
+    Float = 2,
+    Bool = 3,
+    String = 4,
+    Array = 5,
+    Other = 6, // The functions, structs, instances, enums, variants, ranges and modules.
+}
+
+/// A function of the program, called with the arguments of the call and the data it was
+/// registered with. It returns a new value, or an error made by `mussel_error`.
+pub type MusselFunction =
+    unsafe extern "C" fn(arguments: *const *const MusselValue, count: usize, data: *mut c_void) -> *mut MusselValue;
+
+// Copies a string given by the program.
+unsafe fn text(string: *const c_char) -> String {
+    if string.is_null() {
+        return String::new();
+    }
+    unsafe { CStr::from_ptr(string) }.to_string_lossy().into_owned()
+}
+
+// Makes a string to give to the program, which ends at its first nul character, if any.
+fn c_string(text: String) -> CString {
+    CString::new(text).unwrap_or_else(|error| {
+        let end = error.nul_position();
+        CString::new(&error.into_vec()[..end]).expect("the string ends before its first nul")
+    })
+}
+
+// Creates an interpreter, see `embed::Interpreter::new`.
+#[unsafe(no_mangle)]
+pub extern "C" fn mussel_new() -> *mut MusselInterpreter {
+    Box::into_raw(Box::new(MusselInterpreter { interpreter: Interpreter::new(), error: None, exit: None }))
+}
+
+// Frees an interpreter. Its values stay valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_free(interpreter: *mut MusselInterpreter) {
+    if !interpreter.is_null() {
+        drop(unsafe { Box::from_raw(interpreter) });
+    }
+}
+
+// Sets the bytes of the stack the code run by an interpreter can use, see
+// `embed::Interpreter::set_stack_size`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_set_stack(interpreter: *mut MusselInterpreter, size: usize) {
+    unsafe { &mut *interpreter }.interpreter.set_stack_size(size);
+}
+
+// Runs code in the global scope of an interpreter and returns the value of its last statement, or
+// NULL when an error stopped it, see `mussel_last_error`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_eval(interpreter: *mut MusselInterpreter, source: *const c_char) -> *mut MusselValue {
+    let interpreter = unsafe { &mut *interpreter };
+    let source = unsafe { text(source) };
+    let result = interpreter.interpreter.eval_source(&source);
+    interpreter.exit = match result {
+        Err(Error::Exit(status)) => Some(status),
+        _ => None,
+    };
+    match result {
+        Ok(value) => {
+            interpreter.error = None;
+            MusselValue::boxed(value)
+        }
+        Err(error) => {
+            interpreter.error = Some(c_string(error.to_string()));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+// Returns the error which stopped the code run last by `mussel_eval`, like
+// `error[E1002]: cannot find function `f` in this scope`, or NULL when it ran to its end.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_last_error(interpreter: *const MusselInterpreter) -> *const c_char {
+    let interpreter = unsafe { &*interpreter };
+    interpreter.error.as_ref().map_or(std::ptr::null(), |error| error.as_ptr())
+}
+
+// Returns the status given to `exit` by the code run last, or -1 when it didn't call it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_exit_status(interpreter: *const MusselInterpreter) -> i32 {
+    unsafe { &*interpreter }.exit.map_or(-1, i32::from)
+}
+
+// Declares a function of the program in the global scope of an interpreter, for the code to call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_register_fn(
+    interpreter: *mut MusselInterpreter,
+    name: *const c_char,
+    function: MusselFunction,
+    data: *mut c_void,
+) {
+    let interpreter = unsafe { &mut *interpreter };
+    let name = unsafe { text(name) };
+    let failed = format!("the function `{name}` failed");
+    interpreter.interpreter.register(&name, move |arguments| {
+        let arguments: Vec<MusselValue> = arguments.iter().cloned().map(MusselValue::new).collect();
+        let pointers: Vec<*const MusselValue> = arguments.iter().map(|argument| argument as *const MusselValue).collect();
+        let result = unsafe { function(pointers.as_ptr(), pointers.len(), data) };
+        if result.is_null() {
+            return Err(failed.clone());
+        }
+        let result = unsafe { Box::from_raw(result) };
+        match result.error {
+            Some(message) => Err(message),
+            None => Ok(result.value),
+        }
+    });
+}
+
+// Returns the value of a variable of the global scope of an interpreter, or NULL when it isn't
+// declared.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_get(interpreter: *const MusselInterpreter, name: *const c_char) -> *mut MusselValue {
+    let interpreter = unsafe { &*interpreter };
+    let name = unsafe { text(name) };
+    interpreter.interpreter.get(&name).map_or(std::ptr::null_mut(), MusselValue::boxed)
+}
+
+// Declares a variable in the global scope of an interpreter, or gives it a new value.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_set(interpreter: *mut MusselInterpreter, name: *const c_char, value: *const MusselValue) {
+    let interpreter = unsafe { &mut *interpreter };
+    let name = unsafe { text(name) };
+    let value = unsafe { &*value };
+    interpreter.interpreter.set(&name, value.value.clone());
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mussel_nil() -> *mut MusselValue {
+    MusselValue::boxed(Value::NIL)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mussel_int(number: i64) -> *mut MusselValue {
+    MusselValue::boxed(number.into())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mussel_float(number: f64) -> *mut MusselValue {
+    MusselValue::boxed(number.into())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn mussel_bool(boolean: bool) -> *mut MusselValue {
+    MusselValue::boxed(boolean.into())
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_string(string: *const c_char) -> *mut MusselValue {
+    MusselValue::boxed(unsafe { text(string) }.into())
+}
+
+// Creates an array from `count` values.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_array(elements: *const *const MusselValue, count: usize) -> *mut MusselValue {
+    let elements = match count {
+        0 => &[],
+        _ => unsafe { std::slice::from_raw_parts(elements, count) },
+    };
+    let elements: Vec<Value> = elements.iter().map(|element| unsafe { &**element }.value.clone()).collect();
+    MusselValue::boxed(elements.into())
+}
+
+// Creates the error a function of the program returns to stop the code calling it with a message.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_error(message: *const c_char) -> *mut MusselValue {
+    let message = unsafe { text(message) };
+    Box::into_raw(Box::new(MusselValue { error: Some(message), ..MusselValue::new(Value::NIL) }))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_free(value: *mut MusselValue) {
+    if !value.is_null() {
+        drop(unsafe { Box::from_raw(value) });
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_type(value: *const MusselValue) -> MusselType {
+    let value = &unsafe { &*value }.value;
+    match value.type_name().as_str() {
+        _ if value.is_nil() => MusselType::Nil,
+        "int" => MusselType::Int,
+        "float" => MusselType::Float,
+        "bool" => MusselType::Bool,
+        "string" => MusselType::String,
+        "array" => MusselType::Array,
+        _ => MusselType::Other,
+    }
+}
+
+// Returns the integer held by a value, 0 for the values which aren't integers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_int(value: *const MusselValue) -> i64 {
+    unsafe { &*value }.value.as_int().unwrap_or_default()
+}
+
+// Returns the number held by a value, an integer converted, 0 for the values which aren't numbers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_float(value: *const MusselValue) -> f64 {
+    unsafe { &*value }.value.as_float().unwrap_or_default()
+}
+
+// Returns the boolean held by a value, false for the values which aren't booleans.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_bool(value: *const MusselValue) -> bool {
+    unsafe { &*value }.value.as_bool().unwrap_or_default()
+}
+
+// Returns the value as text, like `println` shows it: a string is itself.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_text(value: *const MusselValue) -> *const c_char {
+    let value = unsafe { &*value };
+    value.text.get_or_init(|| c_string(value.value.to_string())).as_ptr()
+}
+
+// Returns the number of elements of an array, 0 for the values which aren't arrays.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_len(value: *const MusselValue) -> usize {
+    unsafe { &*value }.value.array_len().unwrap_or_default()
+}
+
+// Returns an element of an array, or NULL when the value isn't an array or has no such element.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_at(value: *const MusselValue, index: usize) -> *mut MusselValue {
+    unsafe { &*value }.value.element(index).map_or(std::ptr::null_mut(), MusselValue::boxed)
+}
+
+// Returns a field of a struct instance, or NULL when the value isn't an instance with this field.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mussel_value_field(value: *const MusselValue, name: *const c_char) -> *mut MusselValue {
+    let name = unsafe { text(name) };
+    unsafe { &*value }.value.field(&name).map_or(std::ptr::null_mut(), MusselValue::boxed)
+}
+
@@ -0,0 +1,54 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel check`, which finds the errors of a script without running it, for editors to call when a
+// file is saved. The script and the files it includes are lexed, parsed, analyzed and, when asked,
+// type checked, like when they run, and every error found is reported instead of the first one.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use crate::bundle;
+use crate::error::FileError;
+use crate::loader::Loader;
+
+// Checks a script and the files it includes, reporting their errors and warnings.
+// Fails when an error was reported.
+pub fn check(file: &str, check_types: bool) -> ExitCode {
+    let mut loader = Loader::new(check_types);
+    let script = std::fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+    let mut checked: Vec<PathBuf> = Vec::new();
+    let mut pending = vec![script.clone()];
+    let mut failed = false;
+    loader.enter(&script);
+    while let Some(path) = pending.pop() {
+        if checked.contains(&path) {
+            continue;
+        }
+        checked.push(path.clone());
+        // The included files are loaded as included by the script, like when it runs.
+        let entered = path != script && loader.enter(&path).is_none();
+        let loaded = loader.load(&path);
+        if entered {
+            loader.leave();
+        }
+        if let Err(error) = loaded {
+            loader.show(&error);
+            failed = true;
+        }
+        // The files included by a file with errors are checked too.
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        for include in bundle::includes(&content) {
+            match loader.find_from(&path, Path::new(&include)) {
+                Ok(found) => pending.push(found),
+                Err(_) => {
+                    let message = format!("Failed to find the file `{include}` included by this file");
+                    loader.show(&FileError::new(&path, message));
+                    failed = true;
+                }
+            }
+        }
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
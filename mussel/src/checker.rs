@@ -0,0 +1,591 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+//! Optional type checker, enabled with the `--check-types` option.
+//! It runs on the parsed script before it is converted and interpreted, so the mistakes it finds
+//! are reported with their location without running any code.
+//!
+//! The checker infers the simple types of the values (int, float, string, bool, array, function...)
+//! from literals, operators and the types written in function signatures. When a type can't be
+//! known before running, like the result of a function without a return type, the value is
+//! unknown and accepted everywhere: only the mistakes that would certainly fail are reported.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use crate::error::{ErrorCollection, FileIdentifier, TypeCheckError};
+use crate::expr::Type;
+use crate::lexer::TokenRecord;
+use crate::parser::{BinaryOperator, BindingExpression, Expression, ParameterExpression, PatternExpression, UnaryOperator};
+
+/// Checks the types of a parsed script, returning all the errors found.
+pub fn check_types(file: FileIdentifier, content: &str, expressions: &[Expression]) -> Result<(), ErrorCollection> {
+    let checker = Checker::run(file, content, expressions);
+    if checker.errors.is_empty() {
+        return Ok(());
+    }
+    let mut errors = ErrorCollection::new();
+    for error in checker.errors {
+        errors.add_error(error);
+    }
+    Err(errors)
+}
+
+/// Returns what the names written in a parsed script refer to, with what the checker knows about
+/// them, for the editors, see `lsp`. The names declared outside the script, like the built-in
+/// functions, are left out.
+pub(crate) fn references(file: FileIdentifier, content: &str, expressions: &[Expression]) -> Vec<Reference> {
+    Checker::run(file, content, expressions).references
+}
+
+/// A name written in a script, and the declaration it refers to.
+pub(crate) struct Reference {
+    pub(crate) range: Range<usize>,
+    pub(crate) declaration: Range<usize>, // The name in its declaration, the range itself for a declaration
+    pub(crate) kind: Kind,
+    pub(crate) description: String, // The name with its type when it is known, like `count: int`
+}
+
+/// What kind of name a reference is.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Kind {
+    Variable,
+    Function,
+    Struct,
+}
+
+/// What the checker knows about a name.
+#[derive(Clone)]
+enum Symbol {
+    // A variable or parameter, with its type when it is known
+    Variable(Option<Type>),
+    // A function declared with `fn`
+    Function(Signature),
+    // A struct, which is called to create instances
+    Struct(String),
+}
+
+impl Symbol {
+    fn kind(&self) -> Kind {
+        match self {
+            Symbol::Variable(_) => Kind::Variable,
+            Symbol::Function(_) => Kind::Function,
+            Symbol::Struct(_) => Kind::Struct,
+        }
+    }
+
+    // Describes a symbol with its name, like `count: int`, or `count` when its type is unknown.
+    fn describe(&self, name: &str) -> String {
+        match self {
+            Symbol::Variable(Some(ty)) => format!("{name}: {ty}"),
+            Symbol::Variable(None) => name.to_string(),
+            Symbol::Function(_) => format!("{name}: {}", Type::Function),
+            Symbol::Struct(_) => format!("struct {name}"),
+        }
+    }
+}
+
+/// The parameters and return type of a function.
+#[derive(Clone)]
+struct Signature {
+    parameters: Vec<(Option<Type>, bool)>, // The type of each parameter and whether it has a default value
+    return_type: Option<Type>,
+}
+
+struct Checker<'a> {
+    file: FileIdentifier,
+    content: &'a str,
+    // The symbols declared in each scope, with where their name is declared
+    scopes: Vec<HashMap<String, (Symbol, Range<usize>)>>,
+    // The return types of the functions being checked, the innermost last
+    returns: Vec<Option<Type>>,
+    errors: Vec<TypeCheckError>,
+    references: Vec<Reference>,
+}
+
+impl<'a> Checker<'a> {
+    // Checks the expressions of a script.
+    fn run(file: FileIdentifier, content: &'a str, expressions: &[Expression]) -> Checker<'a> {
+        let mut checker = Checker {
+            file,
+            content,
+            scopes: vec![HashMap::new()],
+            returns: Vec::new(),
+            errors: Vec::new(),
+            references: Vec::new(),
+        };
+        for expr in expressions {
+            checker.infer(expr);
+        }
+        checker
+    }
+
+    fn error(&mut self, expr: &Expression, message: String) {
+        self.errors.push(TypeCheckError::new(self.file, expr.span(), message));
+    }
+
+    fn name(&self, token: &TokenRecord) -> String {
+        token.get_content(self.content).to_string()
+    }
+
+    fn declare(&mut self, id: &TokenRecord, symbol: Symbol) {
+        let name = self.name(id);
+        let description = symbol.describe(&name);
+        self.references.push(Reference { range: id.range(), declaration: id.range(), kind: symbol.kind(), description });
+        self.scopes.last_mut().expect("the global scope is never removed").insert(name, (symbol, id.range()));
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name)).map(|(symbol, _)| symbol)
+    }
+
+    // Records the declaration a name written in the script refers to, when it is known.
+    fn refer(&mut self, id: &TokenRecord) {
+        let name = id.get_content(self.content);
+        let Some((symbol, declaration)) = self.scopes.iter().rev().find_map(|scope| scope.get(name)) else {
+            return;
+        };
+        let (kind, description) = (symbol.kind(), symbol.describe(name));
+        self.references.push(Reference { range: id.range(), declaration: declaration.clone(), kind, description });
+    }
+
+    // Checks the expressions of a block in a new scope.
+    fn block(&mut self, block: &[Expression]) {
+        self.scopes.push(HashMap::new());
+        for expr in block {
+            self.infer(expr);
+        }
+        self.scopes.pop();
+    }
+
+    // Reports an error when a known type is not the expected one.
+    fn expect(&mut self, expr: &Expression, found: &Option<Type>, expected: &Type, subject: &str) {
+        if let Some(found) = found && found != expected {
+            self.error(expr, format!("mismatched types: {subject} should be `{expected}`, found `{found}`"));
+        }
+    }
+
+    // Declares the variables of a `let`, `const` or `for`.
+    // A variable initialized with `nil` usually gets a value later, so its type is left unknown.
+    fn bind(&mut self, binding: &BindingExpression, ty: Option<Type>) {
+        match binding {
+            BindingExpression::Identifier(id) => {
+                let ty = ty.filter(|ty| *ty != Type::Nil);
+                self.declare(id, Symbol::Variable(ty));
+            }
+            BindingExpression::Wildcard => {}
+            BindingExpression::Array(bindings, _) => {
+                for binding in bindings {
+                    self.bind(binding, None);
+                }
+            }
+        }
+    }
+
+    // Declares the variables bound by the pattern of a `match` arm, their types are unknown.
+    fn bind_pattern(&mut self, pattern: &PatternExpression) {
+        match pattern {
+            PatternExpression::Identifier(id) | PatternExpression::Rest(_, Some(id)) => {
+                self.declare(id, Symbol::Variable(None));
+            }
+            PatternExpression::Array(patterns) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern);
+                }
+            }
+            PatternExpression::Struct(_, fields) => {
+                for (field, pattern) in fields {
+                    match pattern {
+                        Some(pattern) => self.bind_pattern(pattern),
+                        None => self.declare(field, Symbol::Variable(None)),
+                    }
+                }
+            }
+            PatternExpression::Variant(_, patterns) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern);
+                }
+            }
+            PatternExpression::Wildcard | PatternExpression::Literal(_) | PatternExpression::Rest(_, None) => {}
+        }
+    }
+
+    // Checks a function or closure, declaring it when it has a name.
+    // The parameters are declared in a new scope, and the body is checked against the return type.
+    fn function(&mut self, name: Option<&TokenRecord>, args: &[ParameterExpression],
+                return_type: &Option<TokenRecord>, block: &[Expression]) {
+        let signature = self.signature(args, return_type);
+        if let Some(name) = name {
+            // Declared before the body is checked, so recursive calls are checked too.
+            self.declare(name, Symbol::Function(signature.clone()));
+        }
+        self.scopes.push(HashMap::new());
+        for (arg, (ty, _)) in args.iter().zip(&signature.parameters) {
+            if let (Some(default), Some(ty)) = (&arg.default, ty) {
+                let found = self.infer(default);
+                let subject = format!("the default value of `{}`", self.name(&arg.id));
+                self.expect(default, &found, ty, &subject);
+            }
+            self.declare(&arg.id, Symbol::Variable(ty.clone()));
+        }
+        self.returns.push(signature.return_type);
+        self.block(block);
+        self.returns.pop();
+        self.scopes.pop();
+    }
+
+    fn signature(&self, args: &[ParameterExpression], return_type: &Option<TokenRecord>) -> Signature {
+        let parameters = args
+            .iter()
+            .map(|arg| {
+                let ty = arg.annotation.as_ref().map(|ty| Type::from_name(ty.get_content(self.content)));
+                (ty, arg.default.is_some())
+            })
+            .collect();
+        let return_type = return_type.as_ref().map(|ty| Type::from_name(ty.get_content(self.content)));
+        Signature { parameters, return_type }
+    }
+
+    // Checks a call, returning the type of its result when it is known.
+    fn call(&mut self, callee: &Expression, args: Vec<&Expression>) -> Option<Type> {
+        if let Expression::Identifier(id) = callee {
+            let name = self.name(id);
+            match self.lookup(&name).cloned() {
+                Some(Symbol::Function(signature)) => {
+                    self.refer(id);
+                    return self.call_function(callee, &name, signature, args);
+                }
+                Some(Symbol::Struct(name)) => {
+                    self.refer(id);
+                    for arg in args {
+                        self.infer(arg);
+                    }
+                    return Some(Type::Named(name));
+                }
+                _ => {}
+            }
+        }
+        // Calling a variant of an enum gives it the values it carries.
+        if let Expression::Path { id, .. } = callee
+            && !matches!(self.lookup(id.get_content(self.content)), Some(Symbol::Struct(_)))
+        {
+            for arg in args {
+                self.infer(arg);
+            }
+            return Some(Type::Named(self.name(id)));
+        }
+        match self.infer(callee) {
+            Some(Type::Function) | None => {}
+            Some(ty) => self.error(callee, format!("a value of type `{ty}` can't be called, it is not a function")),
+        }
+        for arg in args {
+            self.infer(arg);
+        }
+        None
+    }
+
+    // Checks the arguments of a call to a function with a known signature.
+    fn call_function(&mut self, callee: &Expression, name: &str, signature: Signature, args: Vec<&Expression>) -> Option<Type> {
+        let required = signature.parameters.iter().take_while(|(_, has_default)| !has_default).count();
+        if args.len() < required || args.len() > signature.parameters.len() {
+            let expected = if required == signature.parameters.len() {
+                required.to_string()
+            } else {
+                format!("{required} to {}", signature.parameters.len())
+            };
+            self.error(callee, format!("`{name}` expects {expected} arguments, but {} were given", args.len()));
+        }
+        for (i, arg) in args.iter().enumerate() {
+            let found = self.infer(arg);
+            if let Some((Some(expected), _)) = signature.parameters.get(i) {
+                self.expect(arg, &found, expected, &format!("argument {} of `{name}`", i + 1));
+            }
+        }
+        signature.return_type
+    }
+
+    // Infers the type of an expression, checking it and the expressions inside it.
+    fn infer(&mut self, expr: &Expression) -> Option<Type> {
+        match expr {
+            Expression::Integer(_) => Some(Type::Int),
+            Expression::Float(_) => Some(Type::Float),
+            Expression::String(_) => Some(Type::String),
+            Expression::Char(_) => Some(Type::Char),
+            Expression::Bool(_) => Some(Type::Bool),
+            Expression::Nil => Some(Type::Nil),
+            // A path into a struct names one of its methods.
+            Expression::Path { id, .. } => match self.lookup(id.get_content(self.content)) {
+                Some(Symbol::Struct(_)) => Some(Type::Function),
+                _ => Some(Type::Named(self.name(id))),
+            },
+            Expression::Identifier(id) => {
+                self.refer(id);
+                match self.lookup(id.get_content(self.content)) {
+                    Some(Symbol::Variable(ty)) => ty.clone(),
+                    Some(Symbol::Function(_) | Symbol::Struct(_)) => Some(Type::Function),
+                    None => None,
+                }
+            }
+            Expression::Array(items) => {
+                for item in items {
+                    self.infer(item);
+                }
+                Some(Type::Array)
+            }
+            Expression::Closure { args, return_type, block } => {
+                self.function(None, args, return_type, block);
+                Some(Type::Function)
+            }
+            Expression::Function { id, args, return_type, block, .. } => {
+                self.function(Some(id), args, return_type, block);
+                None
+            }
+            Expression::Struct { id, .. } => {
+                self.declare(id, Symbol::Struct(self.name(id)));
+                None
+            }
+            // Methods are checked like closures, they aren't visible by their name.
+            Expression::Impl { methods, .. } => {
+                for method in methods {
+                    if let Expression::Function { args, return_type, block, .. } = method {
+                        self.function(None, args, return_type, block);
+                    }
+                }
+                None
+            }
+            Expression::Let { binding, expr, .. } | Expression::Const { binding, expr, .. } => {
+                let ty = self.infer(expr);
+                if let (BindingExpression::Array(..), Some(ty)) = (binding, &ty) && *ty != Type::Array {
+                    self.error(expr, format!("can't unpack a value of type `{ty}`, it is not an array"));
+                }
+                self.bind(binding, ty);
+                None
+            }
+            Expression::Assignment { left, right, .. } => {
+                let found = self.infer(right);
+                if let Expression::Identifier(id) = &**left {
+                    self.refer(id);
+                    let name = self.name(id);
+                    if let Some(Symbol::Variable(Some(expected))) = self.lookup(&name).cloned() {
+                        self.expect(right, &found, &expected, &format!("the new value of `{name}`"));
+                    }
+                } else {
+                    self.infer(left);
+                }
+                None
+            }
+            Expression::Return { expr } => {
+                let found = self.infer(expr);
+                if let Some(Some(expected)) = self.returns.last().cloned() {
+                    self.expect(expr, &found, &expected, "the returned value");
+                }
+                None
+            }
+            Expression::Throw { expr, .. } => {
+                self.infer(expr);
+                None
+            }
+            Expression::Assert { expr, message, .. } => {
+                let found = self.infer(expr);
+                self.expect(expr, &found, &Type::Bool, "the condition of `assert`");
+                if let Some(message) = message {
+                    self.infer(message);
+                }
+                None
+            }
+            Expression::Try { block, id, catch_block } => {
+                self.block(block);
+                self.scopes.push(HashMap::new());
+                self.declare(id, Symbol::Variable(None));
+                self.block(catch_block);
+                self.scopes.pop();
+                None
+            }
+            Expression::If { expr, block, else_block, .. } => {
+                let found = self.infer(expr);
+                self.expect(expr, &found, &Type::Bool, "the condition of `if`");
+                self.block(block);
+                if let Some(else_block) = else_block {
+                    self.block(else_block);
+                }
+                None
+            }
+            Expression::Until { expr, block, .. } => {
+                let found = self.infer(expr);
+                self.expect(expr, &found, &Type::Bool, "the condition of `until`");
+                self.block(block);
+                None
+            }
+            Expression::Loop { block } => {
+                self.block(block);
+                None
+            }
+            Expression::Labeled { expr, .. } => self.infer(expr),
+            Expression::Break { expr, .. } => {
+                if let Some(expr) = expr {
+                    self.infer(expr);
+                }
+                None
+            }
+            Expression::Continue { .. } => None,
+            Expression::For { binding, expr, block, .. } => {
+                let item = self.item(expr);
+                self.scopes.push(HashMap::new());
+                self.bind(binding, item);
+                self.block(block);
+                self.scopes.pop();
+                None
+            }
+            Expression::Comprehension { expr, binding, iterable, condition, .. } => {
+                let item = self.item(iterable);
+                self.scopes.push(HashMap::new());
+                self.bind(binding, item);
+                if let Some(condition) = condition {
+                    let found = self.infer(condition);
+                    self.expect(condition, &found, &Type::Bool, "the condition of a comprehension");
+                }
+                self.infer(expr);
+                self.scopes.pop();
+                Some(Type::Array)
+            }
+            Expression::Match { expr, arms } => {
+                self.infer(expr);
+                for (pattern, block) in arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_pattern(pattern);
+                    self.block(block);
+                    self.scopes.pop();
+                }
+                None
+            }
+            Expression::Range { start, end, .. } => {
+                for bound in [start, end] {
+                    let found = self.infer(bound);
+                    self.expect(bound, &found, &Type::Int, "a range bound");
+                }
+                Some(Type::Range)
+            }
+            Expression::Unary { operator: (operator, _), expr: operand } => {
+                let found = self.infer(operand);
+                match (operator, found) {
+                    (UnaryOperator::Negate, Some(ty @ (Type::Int | Type::Float))) => Some(ty),
+                    // A struct instance is negated by its `neg` function.
+                    (UnaryOperator::Negate, Some(ty)) if self.is_struct(&ty) => None,
+                    (UnaryOperator::Negate, Some(ty)) => {
+                        self.error(expr, format!("only numbers can be negated, found `{ty}`"));
+                        None
+                    }
+                    (UnaryOperator::Not, found) => {
+                        self.expect(operand, &found, &Type::Bool, "the operand of `not`");
+                        Some(Type::Bool)
+                    }
+                    (UnaryOperator::Negate, None) => None,
+                }
+            }
+            Expression::Binary { left, operator: (operator, token), right } => {
+                let left_type = self.infer(left);
+                let right_type = self.infer(right);
+                let symbol = token.get_content(self.content);
+                self.binary(expr, (*operator, symbol), left_type, right_type)
+            }
+            Expression::Call { left, args, .. } => self.call(left, args.iter().collect()),
+            // The value on the left of `|>` is the first argument of the call on the right.
+            Expression::Pipeline { value, function, .. } => match &**function {
+                Expression::Call { left, args, .. } => self.call(left, std::iter::once(&**value).chain(args).collect()),
+                function => self.call(function, vec![value]),
+            },
+            Expression::Index { left, index, .. } => {
+                let array = self.infer(left);
+                if let Some(ty) = &array && *ty != Type::Array {
+                    self.error(left, format!("a value of type `{ty}` can't be indexed, it is not an array"));
+                }
+                match self.infer(index) {
+                    Some(Type::Range) => Some(Type::Array),
+                    Some(Type::Int) | None => None,
+                    Some(ty) => {
+                        self.error(index, format!("an array index should be `int` or a range, found `{ty}`"));
+                        None
+                    }
+                }
+            }
+            Expression::Field { left, .. } => {
+                self.infer(left);
+                None
+            }
+            Expression::Include { .. } | Expression::Enum { .. } | Expression::Trait { .. } => None,
+        }
+    }
+
+    // Infers the type of the items of a collection a loop iterates over.
+    fn item(&mut self, collection: &Expression) -> Option<Type> {
+        match self.infer(collection) {
+            Some(Type::Range) => Some(Type::Int),
+            Some(Type::String) => Some(Type::Char),
+            // A struct instance is iterated by calling its `next` field.
+            Some(Type::Array | Type::Named(_)) | None => None,
+            Some(ty) => {
+                self.error(collection, format!("can't loop over a value of type `{ty}`"));
+                None
+            }
+        }
+    }
+
+    // Tells whether a type is a struct, whose instances can overload the operators.
+    fn is_struct(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Named(name) if matches!(self.lookup(name), Some(Symbol::Struct(_))))
+    }
+
+    // Infers the type of a binary operation from the types of its operands.
+    fn binary(&mut self, expr: &Expression, (operator, symbol): (BinaryOperator, &str),
+              left: Option<Type>, right: Option<Type>) -> Option<Type> {
+        let (Some(left), Some(right)) = (left, right) else {
+            // Comparisons and logical operators give a boolean even when the operands are unknown.
+            return match operator {
+                BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply
+                | BinaryOperator::Divide | BinaryOperator::Power => None,
+                _ => Some(Type::Bool),
+            };
+        };
+        let equality = matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual);
+        let overloaded = self.is_struct(&left) || self.is_struct(&right);
+        match operator {
+            // A negative exponent gives a float, so the type of an integer power isn't known.
+            BinaryOperator::Power if left == Type::Int && right == Type::Int => None,
+            BinaryOperator::Add | BinaryOperator::Subtract | BinaryOperator::Multiply
+            | BinaryOperator::Divide | BinaryOperator::Power => match (&left, &right) {
+                (Type::Int, Type::Int) | (Type::Float, Type::Float) => Some(left),
+                // The functions overloading the operators for structs can return anything.
+                _ if overloaded && !matches!(operator, BinaryOperator::Power) => None,
+                (Type::String, Type::String | Type::Char) | (Type::Char, Type::String)
+                    if matches!(operator, BinaryOperator::Add) => Some(Type::String),
+                (Type::String, Type::String | Type::Char) | (Type::Char, Type::String) => {
+                    self.error(expr, "only `+` is supported by strings".to_string());
+                    None
+                }
+                _ => {
+                    self.error(expr, format!("`{symbol}` isn't supported between `{left}` and `{right}`"));
+                    None
+                }
+            },
+            BinaryOperator::And | BinaryOperator::Or => {
+                if left != Type::Bool || right != Type::Bool {
+                    self.error(expr, format!("`{symbol}` is only supported by booleans, found `{left}` and `{right}`"));
+                }
+                Some(Type::Bool)
+            }
+            _ => {
+                let valid = match (&left, &right) {
+                    (Type::Int | Type::Float, Type::Int | Type::Float) | (Type::Char, Type::Char) => true,
+                    (Type::Nil, _) | (_, Type::Nil) => equality,
+                    _ if overloaded => true,
+                    (Type::Bool, Type::Bool) | (Type::String, Type::String) => equality,
+                    (Type::Named(left), Type::Named(right)) => equality && left == right,
+                    _ => false,
+                };
+                if !valid {
+                    self.error(expr, format!("`{symbol}` can't compare `{left}` with `{right}`"));
+                }
+                Some(Type::Bool)
+            }
+        }
+    }
+}
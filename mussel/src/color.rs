@@ -0,0 +1,59 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Whether the errors and warnings are written in color, chosen with `--color`. By default they are
+// only when the standard error is a terminal and the `NO_COLOR` environment variable isn't set
+// (https://no-color.org), so they aren't garbled by escape codes when written to a file.
+// The choice holds for the whole process, the script runs in another thread than the one reading
+// the options.
+
+use std::io::IsTerminal;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+use codespan_reporting::term::termcolor::ColorChoice;
+
+/// When the errors and warnings are written in color.
+#[derive(Clone, Copy)]
+pub enum Color {
+    Auto,   // When the standard error is a terminal and `NO_COLOR` isn't set.
+    Always, // Even when `NO_COLOR` is set, as it was asked for.
+    Never,
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "auto" => Ok(Color::Auto),
+            "always" => Ok(Color::Always),
+            "never" => Ok(Color::Never),
+            _ => Err(format!("unknown color `{name}`, expected `auto`, `always` or `never`")),
+        }
+    }
+}
+
+// The color chosen, as the index of its variant.
+static CHOSEN: AtomicU8 = AtomicU8::new(Color::Auto as u8);
+
+// Sets when the errors and warnings are written in color.
+pub fn set(color: Color) {
+    CHOSEN.store(color as u8, Ordering::Relaxed);
+}
+
+// Returns how the errors and warnings are written to the standard error.
+pub(crate) fn choice() -> ColorChoice {
+    let color = match CHOSEN.load(Ordering::Relaxed) {
+        0 => Color::Auto,
+        1 => Color::Always,
+        _ => Color::Never,
+    };
+    match color {
+        Color::Always => ColorChoice::Always,
+        Color::Never => ColorChoice::Never,
+        _ if std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) => ColorChoice::Never,
+        // `termcolor` still turns the colors off for a `dumb` terminal.
+        Color::Auto if std::io::stderr().is_terminal() => ColorChoice::Auto,
+        Color::Auto => ColorChoice::Never,
+    }
+}
@@ -0,0 +1,733 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+use std::cell::{Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+use crate::error;
+use crate::error::{CallFrame, EvaluationError, Halt, LimitError, Location, RecursionError};
+use crate::expr::Expr;
+use crate::interpreter::Backend;
+use crate::limits;
+use crate::limits::Meter;
+use crate::loader::Loader;
+use crate::profile::Profiler;
+use crate::trace::{Trace, Tracer};
+
+// Pauses a script while it runs, to look at it, see `dap`.
+pub trait Debugger {
+    // Called by the tree-walker before each statement it runs which has a location, with the
+    // context running it.
+    // Returns `exit` to stop the script.
+    fn pause(&mut self, location: &Location, context: &mut Context) -> Result<(), Halt>;
+}
+
+// The maximum depth of nested function calls when no limit is given on the command line, or by the
+// `MUSSEL_MAX_DEPTH` environment variable.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+// The number of scopes created before the first collection of the scopes that only keep each other
+// alive, see `Heap`.
+const COLLECTION_THRESHOLD: usize = 10_000;
+
+// The variables visible while a script runs, organised as a chain of scopes.
+// The outermost scope holds the global variables. Every block (a loop body, a branch of an `if`,
+// a function call...) starts a new scope linked to the enclosing one, which is dropped when the
+// block ends, so the variables declared inside a block stay local to it while the enclosing ones
+// can still be read and assigned. A variable is looked up from the innermost scope to its parents.
+// Variables declared with `const` are marked as constants in their scope.
+// The code compiled for the VM knows which variables each of its blocks declares: their scopes
+// come with a layout giving each of these variables a slot, so the compiled code can reach them
+// without looking up their names. The variables declared any other way (by `eval`, `include`, or
+// the tree-walker) are kept by name.
+// Every file included runs once, in a module of its own: what it declares is kept in a registry
+// by path, and including the file again only declares the same things again.
+// Scopes are shared: a closure keeps the scope it was created in (its environment), and through it
+// the enclosing ones, so it can read and assign the variables around it even after the block that
+// declared them has ended, and the changes are seen by everyone else using these variables.
+// Creating a closure or calling it only swaps the innermost scope, nothing is copied.
+// A closure stored in a scope it captured keeps that scope alive, and the scope keeps the closure:
+// the heap of the context frees these cycles from time to time.
+// The context also keeps track of the functions being called, to stop runaway recursion, and of
+// the labels of the loops running in each of them, and of the call being evaluated, so the errors
+// found while calling a function can point at it, and counts the steps of the script, to stop it
+// when it goes past its limits, see `limits`. It holds the loader of the script, to load the files
+// it includes, the backend running it, and the arguments given to it on the command line.
+pub struct Context {
+    scope: Rc<RefCell<Scope>>,
+    heap: Heap,
+    calls: Vec<CallFrame>,
+    labels: Vec<(String, usize)>,
+    max_depth: usize,
+    meter: Meter,
+    loader: Loader,
+    modules: HashMap<PathBuf, Declarations>,
+    call_site: Option<Location>,
+    backend: Backend,
+    arguments: Vec<String>,
+    debugger: Option<Box<dyn Debugger>>,
+    tracer: Option<Tracer>,
+    profiler: Option<Profiler>,
+}
+
+// The variables declared in a block, which of them are constants, and the scope of the enclosing
+// block, if any. The variables in the layout of the block are kept in their slots, which are
+// empty until they are declared, and the others by name.
+#[derive(Debug, Clone, Default)]
+struct Scope {
+    layout: Option<Rc<Layout>>,
+    slots: Vec<Option<Expr>>,
+    variables: HashMap<String, Expr>,
+    constants: HashSet<String>,
+    parent: Option<Rc<RefCell<Scope>>>,
+}
+
+impl Scope {
+    // Returns the slot of a variable in the layout of the block, if it has one.
+    fn slot(&self, name: &str) -> Option<usize> {
+        self.layout.as_ref().and_then(|layout| layout.slot(name))
+    }
+
+    // Returns the value of a variable declared in this scope.
+    fn get(&self, name: &str) -> Option<&Expr> {
+        match self.slot(name) {
+            Some(slot) => self.slots[slot].as_ref(),
+            None => self.variables.get(name),
+        }
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Expr> {
+        match self.slot(name) {
+            Some(slot) => self.slots[slot].as_mut(),
+            None => self.variables.get_mut(name),
+        }
+    }
+
+    fn set(&mut self, name: String, value: Expr) {
+        match self.slot(&name) {
+            Some(slot) => self.slots[slot] = Some(value),
+            None => {
+                self.variables.insert(name, value);
+            }
+        }
+    }
+
+    // Returns the variables declared in this scope, in no particular order.
+    fn entries(&self) -> impl Iterator<Item = (&String, &Expr)> {
+        let slots = self.layout.iter().flat_map(|layout| layout.slots.iter());
+        slots
+            .filter_map(|(name, slot)| Some((name, self.slots[*slot].as_ref()?)))
+            .chain(&self.variables)
+    }
+
+    // Calls `reference` with every scope this one keeps alive: its parent, and the environments of
+    // the functions in its variables.
+    fn references(&self, reference: &mut impl FnMut(&Rc<RefCell<Scope>>)) {
+        if let Some(parent) = &self.parent {
+            reference(parent);
+        }
+        for (_, value) in self.entries() {
+            environments(value, reference);
+        }
+    }
+}
+
+// Calls `reference` with every scope a value keeps alive, the environments of the functions in it.
+// An array shared with other values is skipped: the scopes it keeps alive aren't kept alive by this
+// value alone.
+fn environments(value: &Expr, reference: &mut impl FnMut(&Rc<RefCell<Scope>>)) {
+    match value {
+        Expr::Closure(_, _, _, Some(Environment(scope))) | Expr::Compiled(_, Environment(scope)) => reference(scope),
+        Expr::Array(values) if Rc::strong_count(values) == 1 => {
+            values.iter().for_each(|value| environments(value, reference))
+        }
+        Expr::Variant(_, _, values) => values.iter().for_each(|value| environments(value, reference)),
+        Expr::Struct(_, _, members) | Expr::Instance(_, members) | Expr::Module(_, members) => {
+            members.iter().for_each(|(_, value)| environments(value, reference))
+        }
+        _ => {}
+    }
+}
+
+// Every scope created while the script runs, to free the ones that only keep each other alive,
+// like a function stored in the scope it captured once the block of the scope has ended.
+// A collection counts, for each scope, the references held by the other scopes. A scope with more
+// references than that is used by the interpreter itself, in the scopes visible or in a value being
+// evaluated, and is alive, along with every scope it keeps alive. The others can't be reached by the
+// script anymore: their variables are dropped, which breaks their cycles and frees them.
+// A collection runs once enough scopes were created since the last one, twice as many as those
+// still alive after it.
+struct Heap {
+    scopes: Vec<Weak<RefCell<Scope>>>,
+    threshold: usize,
+}
+
+impl Heap {
+    fn new() -> Self {
+        Heap { scopes: Vec::new(), threshold: COLLECTION_THRESHOLD }
+    }
+
+    // Creates a scope, collecting the unreachable ones first when it is time to.
+    fn allocate(&mut self, scope: Scope) -> Rc<RefCell<Scope>> {
+        if self.scopes.len() >= self.threshold {
+            self.collect();
+            self.threshold = COLLECTION_THRESHOLD.max(self.scopes.len() * 2);
+        }
+        let scope = Rc::new(RefCell::new(scope));
+        self.scopes.push(Rc::downgrade(&scope));
+        scope
+    }
+
+    // Frees the scopes that are only kept alive by other unreachable scopes.
+    fn collect(&mut self) {
+        self.scopes.retain(|scope| scope.strong_count() > 0);
+        let scopes: Vec<Rc<RefCell<Scope>>> = self.scopes.iter().filter_map(Weak::upgrade).collect();
+        // A scope being changed is in use: the collection waits for the next time.
+        let Some(borrowed) = scopes.iter().map(|scope| scope.try_borrow().ok()).collect::<Option<Vec<Ref<Scope>>>>()
+        else {
+            return;
+        };
+        let indexes: HashMap<*const RefCell<Scope>, usize> =
+            scopes.iter().enumerate().map(|(index, scope)| (Rc::as_ptr(scope), index)).collect();
+        let references: Vec<Vec<usize>> = borrowed
+            .iter()
+            .map(|scope| {
+                let mut references = Vec::new();
+                scope.references(&mut |referenced| references.extend(indexes.get(&Rc::as_ptr(referenced))));
+                references
+            })
+            .collect();
+        drop(borrowed);
+        let mut held = vec![0; scopes.len()];
+        for &referenced in references.iter().flatten() {
+            held[referenced] += 1;
+        }
+        // `scopes` holds one more reference to each scope.
+        let mut alive: Vec<bool> =
+            scopes.iter().zip(&held).map(|(scope, held)| Rc::strong_count(scope) - 1 > *held).collect();
+        let mut pending: Vec<usize> = (0..scopes.len()).filter(|&index| alive[index]).collect();
+        while let Some(index) = pending.pop() {
+            for &referenced in &references[index] {
+                if !alive[referenced] {
+                    alive[referenced] = true;
+                    pending.push(referenced);
+                }
+            }
+        }
+        let unreachable: Vec<Scope> =
+            scopes.iter().zip(&alive).filter(|(_, alive)| !**alive).map(|(scope, _)| scope.take()).collect();
+        drop(unreachable);
+    }
+}
+
+// The variables a compiled block declares, each with its slot in the scope of the block.
+#[derive(Debug, Default)]
+pub struct Layout {
+    slots: HashMap<String, usize>,
+}
+
+impl Layout {
+    // Gives the variables slots in order. A name given twice keeps its first slot.
+    pub fn new(names: impl IntoIterator<Item = String>) -> Self {
+        let mut slots = HashMap::new();
+        for name in names {
+            let slot = slots.len();
+            slots.entry(name).or_insert(slot);
+        }
+        Layout { slots }
+    }
+
+    pub fn slot(&self, name: &str) -> Option<usize> {
+        self.slots.get(name).copied()
+    }
+}
+
+// Where the compiled code finds a variable: in a slot of the scope `depth` scopes out of the
+// innermost one, or by name starting from that scope, because none of the scopes of the compiled
+// blocks in between declares it.
+#[derive(Debug, Clone, Copy)]
+pub enum Resolution {
+    Slot { depth: usize, slot: usize },
+    Outer { depth: usize },
+}
+
+// The innermost scope visible where a closure was created, linked to the enclosing ones.
+#[derive(Clone)]
+pub struct Environment(Rc<RefCell<Scope>>);
+
+// Where a paused script was, to go back there after an error stopped code a debugger evaluated.
+pub struct Checkpoint {
+    scope: Rc<RefCell<Scope>>,
+    calls: usize,
+    labels: usize,
+    call_site: Option<Location>,
+}
+
+// The scopes can contain the closure itself, so they aren't printed.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<environment>")
+    }
+}
+
+// What a module declared: the innermost scope it ran in.
+#[derive(Clone)]
+pub struct Declarations(Rc<RefCell<Scope>>);
+
+impl Declarations {
+    // Returns the declared variables, sorted by name.
+    pub fn variables(&self) -> Vec<(String, Expr)> {
+        let scope = self.0.borrow();
+        let mut variables: Vec<(String, Expr)> =
+            scope.entries().map(|(name, value)| (name.clone(), value.clone())).collect();
+        variables.sort_by(|(a, _), (b, _)| a.cmp(b));
+        variables
+    }
+}
+
+impl Context {
+    // Creates a context with an empty global scope, allowing at most `max_depth` nested calls.
+    pub fn new(loader: Loader, max_depth: usize, backend: Backend, arguments: Vec<String>) -> Self {
+        let mut heap = Heap::new();
+        Context {
+            scope: heap.allocate(Scope::default()),
+            heap,
+            calls: Vec::new(),
+            labels: Vec::new(),
+            max_depth,
+            meter: Meter::new(),
+            loader,
+            modules: HashMap::new(),
+            call_site: None,
+            backend,
+            arguments,
+            debugger: None,
+            tracer: None,
+            profiler: None,
+        }
+    }
+
+    // Lets a debugger pause the script before its statements.
+    pub fn attach(&mut self, debugger: Box<dyn Debugger>) {
+        self.debugger = Some(debugger);
+    }
+
+    // Tells whether a debugger is attached.
+    pub fn debugging(&self) -> bool {
+        self.debugger.is_some()
+    }
+
+    // Gives the debugger the statement about to run. It is detached while it looks at the context,
+    // so the code it evaluates doesn't pause.
+    pub fn pause(&mut self, location: &Location) -> Result<(), Halt> {
+        if let Some(mut debugger) = self.debugger.take() {
+            let paused = debugger.pause(location, self);
+            self.debugger = Some(debugger);
+            paused?;
+        }
+        Ok(())
+    }
+
+    // Writes the expressions evaluated from now on to the standard error, see `trace`.
+    pub fn start_trace(&mut self, trace: Trace) {
+        self.tracer = Some(Tracer::new(trace));
+    }
+
+    // Returns what writes the expressions evaluated, when they are traced.
+    pub(crate) fn tracer(&self) -> Option<&Tracer> {
+        self.tracer.as_ref()
+    }
+
+    // Writes an evaluated expression with its value, when they are traced.
+    pub(crate) fn trace(&mut self, kind: &str, location: &Location, value: &Expr) {
+        if let Some(mut tracer) = self.tracer.take() {
+            tracer.write(kind, location, value, self);
+            self.tracer = Some(tracer);
+        }
+    }
+
+    // Measures the time spent in each function from now on, see `profile`. The folded stacks are
+    // written to the given file, or else the table to the standard error.
+    pub fn start_profile(&mut self, stacks: Option<PathBuf>) {
+        self.profiler = Some(Profiler::new(stacks));
+    }
+
+    // Stops measuring the functions, giving back what was measured.
+    pub(crate) fn take_profiler(&mut self) -> Option<Profiler> {
+        self.profiler.take()
+    }
+
+    // Returns the backend running the script.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    // Returns the arguments given to the script on the command line.
+    pub fn arguments(&self) -> &[String] {
+        &self.arguments
+    }
+
+    // Returns the loader of the script, to load the files it includes.
+    pub fn loader(&mut self) -> &mut Loader {
+        &mut self.loader
+    }
+
+    // Returns the visible scopes, from the innermost one to the global scope.
+    fn scopes(&self) -> impl Iterator<Item = Rc<RefCell<Scope>>> {
+        std::iter::successors(Some(self.scope.clone()), |scope| scope.borrow().parent.clone())
+    }
+
+    // Looks up a variable, starting from the innermost scope, and returns a copy of its value.
+    pub fn get(&self, name: &str) -> Option<Expr> {
+        self.scopes().find_map(|scope| scope.borrow().get(name).cloned())
+    }
+
+    // Finds the scope where the compiled code starts looking for a variable, `depth` scopes out of
+    // the innermost one. A variable with the same name declared by name in the scopes in between,
+    // like by `eval`, is found first, as it shadows the one found by the compiler: its scope is
+    // returned instead, telling the scope out wasn't reached.
+    fn resolved_scope(&self, name: &str, depth: usize) -> (Rc<RefCell<Scope>>, bool) {
+        let mut scope = self.scope.clone();
+        for _ in 0..depth {
+            let parent = {
+                let current = scope.borrow();
+                if current.variables.contains_key(name) {
+                    drop(current);
+                    return (scope, false);
+                }
+                current.parent.clone().expect("the compiled code has more scopes than the context")
+            };
+            scope = parent;
+        }
+        (scope, true)
+    }
+
+    // Looks up a variable where the compiler found it, and returns a copy of its value.
+    // A slot that wasn't declared yet is skipped, like a variable not declared yet in that scope.
+    pub fn get_resolved(&self, name: &str, resolution: Resolution) -> Option<Expr> {
+        let (depth, slot) = match resolution {
+            Resolution::Slot { depth, slot } => (depth, Some(slot)),
+            Resolution::Outer { depth } => (depth, None),
+        };
+        let (scope, reached) = self.resolved_scope(name, depth);
+        if reached
+            && let Some(slot) = slot
+            && let Some(value) = &scope.borrow().slots[slot]
+        {
+            return Some(value.clone());
+        }
+        std::iter::successors(Some(scope), |scope| scope.borrow().parent.clone())
+            .find_map(|scope| scope.borrow().get(name).cloned())
+    }
+
+    // Assigns a new value to a variable where the compiler found it.
+    // Gives the value back when the variable doesn't exist or is a constant, for the caller to
+    // report it.
+    pub fn assign_resolved(&self, name: &str, resolution: Resolution, value: Expr) -> Option<Expr> {
+        let depth = match resolution {
+            Resolution::Slot { depth, .. } | Resolution::Outer { depth } => depth,
+        };
+        let (start, _) = self.resolved_scope(name, depth);
+        let scope = std::iter::successors(Some(start), |scope| scope.borrow().parent.clone())
+            .find(|scope| scope.borrow().get(name).is_some());
+        match scope {
+            Some(scope) if !scope.borrow().constants.contains(name) => {
+                scope.borrow_mut().set(name.to_string(), value);
+                None
+            }
+            _ => Some(value),
+        }
+    }
+
+    // Declares a variable in its slot of the innermost scope, which was started with a layout.
+    pub fn declare_slot(&mut self, slot: usize, name: &str, value: Expr, constant: bool) {
+        let mut scope = self.scope.borrow_mut();
+        if constant {
+            scope.constants.insert(name.to_string());
+        } else if !scope.constants.is_empty() {
+            scope.constants.remove(name);
+        }
+        scope.slots[slot] = Some(value);
+    }
+
+    // Returns the names of the variables visible now, sorted, without the built-in functions.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .scopes()
+            .flat_map(|scope| {
+                let scope = scope.borrow();
+                scope
+                    .entries()
+                    .filter(|(_, value)| !matches!(value, Expr::Builtin(_) | Expr::Native(_)))
+                    .map(|(name, _)| name.clone())
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    // Returns the variables visible now, for a debugger: the local ones, those of the scopes of the
+    // function running and of the blocks around, then the global ones, without the built-in
+    // functions. Each list is sorted, and a variable hidden by another with its name is left out.
+    pub fn variables(&self) -> [Vec<(String, Expr)>; 2] {
+        let scopes: Vec<Rc<RefCell<Scope>>> = self.scopes().collect();
+        let mut seen = HashSet::new();
+        let mut lists = [Vec::new(), Vec::new()];
+        for (index, scope) in scopes.iter().enumerate() {
+            let list = &mut lists[usize::from(index + 1 == scopes.len())];
+            for (name, value) in scope.borrow().entries() {
+                if !matches!(value, Expr::Builtin(_)) && seen.insert(name.clone()) {
+                    list.push((name.clone(), value.clone()));
+                }
+            }
+        }
+        for list in &mut lists {
+            list.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+        lists
+    }
+
+    // Looks up a variable, starting from the innermost scope, and lets `modify` change its value in place.
+    // Returns `None` when the variable doesn't exist.
+    pub fn modify<T>(&self, name: &str, modify: impl FnOnce(&mut Expr) -> T) -> Option<T> {
+        let scope = self.scopes().find(|scope| scope.borrow().get(name).is_some())?;
+        let mut scope = scope.borrow_mut();
+        scope.get_mut(name).map(modify)
+    }
+
+    // Declares a variable in the innermost scope.
+    // A variable with the same name in an enclosing scope is shadowed until the scope ends.
+    pub fn insert(&mut self, name: String, value: Expr) {
+        let mut scope = self.scope.borrow_mut();
+        scope.constants.remove(&name);
+        scope.set(name, value);
+    }
+
+    // Declares a constant in the innermost scope, a variable that can't be assigned a new value.
+    pub fn insert_constant(&mut self, name: String, value: Expr) {
+        let mut scope = self.scope.borrow_mut();
+        scope.constants.insert(name.clone());
+        scope.set(name, value);
+    }
+
+    // Tells whether the variable visible with the given name was declared as a constant.
+    pub fn is_constant(&self, name: &str) -> bool {
+        self.scopes()
+            .find(|scope| scope.borrow().get(name).is_some())
+            .is_some_and(|scope| scope.borrow().constants.contains(name))
+    }
+
+    // Starts a new scope for a block, inside the innermost one.
+    pub fn push_scope(&mut self) {
+        let parent = self.scope.clone();
+        self.scope = self.heap.allocate(Scope { parent: Some(parent), ..Scope::default() });
+    }
+
+    // Starts a new scope for a compiled block, with a slot for each variable in its layout.
+    pub fn push_layout(&mut self, layout: Rc<Layout>) {
+        let parent = self.scope.clone();
+        let slots = vec![None; layout.slots.len()];
+        self.scope = self.heap.allocate(Scope {
+            layout: Some(layout),
+            slots,
+            parent: Some(parent),
+            ..Scope::default()
+        });
+    }
+
+    // Ends the innermost scope, dropping the variables declared in it unless a closure captured it.
+    pub fn pop_scope(&mut self) {
+        let parent = self.scope.borrow().parent.clone();
+        if let Some(parent) = parent {
+            self.scope = parent;
+        }
+    }
+
+    // Returns the innermost scope, to be captured by a closure.
+    pub fn environment(&self) -> Environment {
+        Environment(self.scope.clone())
+    }
+
+    // Makes the scopes of an environment the visible ones, to run the body of a closure.
+    // Returns the scopes that were visible before, to be restored when the body ends.
+    pub fn replace_environment(&mut self, environment: Environment) -> Environment {
+        Environment(std::mem::replace(&mut self.scope, environment.0))
+    }
+
+    // Makes the scopes of an environment the visible ones again after an error stopped the code
+    // running in an interactive session, forgetting the calls and loops it left unfinished.
+    pub fn recover(&mut self, environment: Environment) {
+        self.scope = environment.0;
+        self.calls.clear();
+        self.labels.clear();
+        self.call_site = None;
+    }
+
+    // Returns where the script is, see `restore`.
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            scope: self.scope.clone(),
+            calls: self.calls.len(),
+            labels: self.labels.len(),
+            call_site: self.call_site.clone(),
+        }
+    }
+
+    // Goes back to where the script was when the checkpoint was taken, forgetting the calls and
+    // loops started since then, which an error left unfinished.
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.scope = checkpoint.scope;
+        self.calls.truncate(checkpoint.calls);
+        self.labels.truncate(checkpoint.labels);
+        self.call_site = checkpoint.call_site;
+    }
+
+    // Makes a new empty global scope the only visible one, to run a module apart from the script.
+    // Returns the scopes that were visible before, to be restored by `leave_module`.
+    pub fn enter_module(&mut self) -> Environment {
+        let scope = self.heap.allocate(Scope::default());
+        self.replace_environment(Environment(scope))
+    }
+
+    // Ends a module, making the scopes visible before it visible again.
+    // Returns what was declared in the innermost scope of the module.
+    pub fn leave_module(&mut self, previous: Environment) -> Declarations {
+        let scope = self.scope.clone();
+        self.replace_environment(previous);
+        Declarations(scope)
+    }
+
+    // Declares in the innermost scope everything a module declared, keeping its constants constant.
+    pub fn import(&mut self, declarations: &Declarations) {
+        let declared = declarations.0.borrow();
+        let mut scope = self.scope.borrow_mut();
+        for (name, value) in declared.entries() {
+            scope.constants.remove(name);
+            scope.set(name.clone(), value.clone());
+        }
+        scope.constants.extend(declared.constants.iter().cloned());
+    }
+
+    // Returns what the file with the given path declared, if it already ran.
+    pub fn module(&self, path: &Path) -> Option<Declarations> {
+        self.modules.get(path).cloned()
+    }
+
+    // Records what the file with the given path declared, so it doesn't run again.
+    pub fn add_module(&mut self, path: PathBuf, declarations: Declarations) {
+        self.modules.insert(path, declarations);
+    }
+
+    // Records a call to the named function, made from the call site being evaluated.
+    // Returns an error when the call would exceed the recursion limit, or the stack of the
+    // interpreter.
+    pub fn push_call(&mut self, name: &str) -> Result<(), RecursionError> {
+        if self.calls.len() >= self.max_depth {
+            return Err(RecursionError::new(self.max_depth, self.call_site.clone()));
+        }
+        if limits::stack_exhausted() {
+            return Err(RecursionError::stack(self.calls.len(), self.call_site.clone()));
+        }
+        self.calls.push(CallFrame { name: name.to_string(), site: self.call_site.clone() });
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(name);
+        }
+        Ok(())
+    }
+
+    // Counts a step of the script, an expression evaluated or an instruction run, stopping it with an
+    // error when it goes past its limits.
+    #[inline]
+    pub fn step(&mut self) -> Result<(), Halt> {
+        match self.meter.step() {
+            Some(limit) => error::raise(LimitError::new(limit, self.call_site.clone())),
+            None => Ok(()),
+        }
+    }
+
+    // Replaces the innermost function call with a call to another function, for tail calls.
+    pub fn replace_call(&mut self, name: &str) {
+        if let Some(call) = self.calls.last_mut() {
+            *call = CallFrame { name: name.to_string(), site: self.call_site.clone() };
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.leave();
+            profiler.enter(name);
+        }
+    }
+
+    // Returns the functions being called, the outermost first.
+    // The calls aren't removed when an error stops the script, so they are still there to report it.
+    pub fn backtrace(&self) -> Vec<CallFrame> {
+        self.calls.clone()
+    }
+
+    // Returns how many function calls are running.
+    pub fn depth(&self) -> usize {
+        self.calls.len()
+    }
+
+    // Records that the innermost function call returned.
+    pub fn pop_call(&mut self) {
+        self.calls.pop();
+        if let Some(profiler) = &mut self.profiler {
+            profiler.leave();
+        }
+    }
+
+    // Records the location of the call about to be evaluated, returning the location of the
+    // enclosing one, which is restored with `leave_call_site` when the call ends.
+    pub fn enter_call_site(&mut self, location: Location) -> Option<Location> {
+        self.call_site.replace(location)
+    }
+
+    // Returns the location of the call being evaluated.
+    pub fn call_site(&self) -> Option<Location> {
+        self.call_site.clone()
+    }
+
+    pub fn leave_call_site(&mut self, previous: Option<Location>) {
+        self.call_site = previous;
+    }
+
+    // Stops the script with an error pointing at the call being evaluated, for the errors found
+    // while calling a function, like a built-in given arguments of the wrong type.
+    pub fn fail<T>(&self, message: String) -> Result<T, Halt> {
+        self.fail_as("E1000", message)
+    }
+
+    // Like `fail`, for a kind of error with a code of its own, see `explain`.
+    pub fn fail_as<T>(&self, code: &'static str, message: String) -> Result<T, Halt> {
+        let error = match &self.call_site {
+            Some(location) => EvaluationError::new(location.clone(), message, "in this call".to_string()),
+            // Only the built-ins run by the interpreter itself, like `next` in a `for` loop over
+            // the top level, have no call written in the script.
+            None => EvaluationError::unlocated(message),
+        };
+        error::raise(error.with_code(code))
+    }
+
+    // Records that a labeled loop started running in the innermost function call.
+    pub fn push_label(&mut self, label: String) {
+        self.labels.push((label, self.calls.len()));
+    }
+
+    // Records that the innermost labeled loop ended.
+    pub fn pop_label(&mut self) {
+        self.labels.pop();
+    }
+
+    // Tells whether a loop with the given label is running in the innermost function call.
+    // The loops of the callers can't be left from a function they called.
+    pub fn has_label(&self, label: &str) -> bool {
+        self.labels
+            .iter()
+            .rev()
+            .take_while(|(_, depth)| *depth == self.calls.len())
+            .any(|(name, _)| name == label)
+    }
+}
@@ -0,0 +1,436 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel dap`, a debug adapter for the editors supporting the Debug Adapter Protocol, like VS Code.
+// It talks with the editor on the standard input and output, runs the script it is asked to launch
+// with the tree-walker, and supports:
+// - the breakpoints on the lines of the script and of the files it includes,
+// - pausing the script, continuing it, and stepping over, into and out of the line it is paused at,
+// - the calls being run, the local and global variables, with the elements of the arrays and the
+//   fields of the instances,
+// - evaluating code where the script is paused, like `eval` does.
+// What the script writes is shown by the editor. The script can't read from the standard input,
+// which the editor writes the messages to. The script pauses like with `mussel --debug`, see
+// `debugger::Stepper`.
+
+use std::cell::RefCell;
+use std::io;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use crate::context::{Context, Debugger};
+use crate::debugger::{show, Step, Stepper};
+use crate::error::{Halt, Location};
+use crate::expr::Expr;
+use crate::interpreter;
+use crate::loader::Loader;
+use crate::lsp::json::{object, Json};
+use crate::lsp::{receive, send};
+use crate::output;
+
+// The script runs on a single thread, the only one shown to the editor.
+const THREAD: usize = 1;
+
+// The references of the scopes of the paused script. The values which have items, like the arrays,
+// get the following ones while the script is paused.
+const LOCALS: usize = 1;
+const GLOBALS: usize = 2;
+
+// The number of the last message sent, each message is numbered.
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+// Runs the debug adapter until the editor disconnects. Fails when the standard input is closed
+// first.
+pub fn serve(max_depth: usize) -> ExitCode {
+    // The messages are read by another thread, so the requests sent while the script runs, like
+    // pausing it, are answered between its statements.
+    let (sender, messages) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut input = io::stdin().lock();
+        while let Some(message) = receive(&mut input) {
+            if sender.send(message).is_err() {
+                break;
+            }
+        }
+    });
+    let session = Rc::new(RefCell::new(Session {
+        messages,
+        stepper: Stepper::new(Step::Run),
+        values: Vec::new(),
+        disconnected: false,
+    }));
+    let Some(launch) = session.borrow_mut().configure() else {
+        return if session.borrow().disconnected { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+    };
+    let adapter = Box::new(Adapter(session.clone()));
+    output::forward(write, || {
+        let mut loader = Loader::new(launch.check_types);
+        // The script is the first file running, so the files it includes can't include it again.
+        loader.enter(&launch.program);
+        match loader.load(&launch.program) {
+            Ok(exprs) => {
+                interpreter::debug(exprs, loader, max_depth, launch.arguments, adapter);
+            }
+            Err(error) => loader.report(error),
+        }
+    });
+    let mut session = session.borrow_mut();
+    if !session.disconnected {
+        event("terminated", object([]));
+    }
+    session.finish()
+}
+
+// Shows what the script writes in the editor.
+fn write(text: &str) {
+    event("output", object([("category", "stdout".into()), ("output", text.into())]));
+}
+
+// The script to run, as given by the `launch` request.
+struct Launch {
+    program: PathBuf,
+    arguments: Vec<String>,
+    check_types: bool,
+}
+
+struct Session {
+    messages: Receiver<Json>,
+    stepper: Stepper,
+    values: Vec<Expr>, // The values shown with their items while the script is paused, see `GLOBALS`.
+    disconnected: bool,
+}
+
+impl Session {
+    // Answers the requests sent before the script runs, until the editor has asked to launch it
+    // and has sent the breakpoints. Returns `None` when the editor disconnects first.
+    fn configure(&mut self) -> Option<Launch> {
+        let mut launch = None;
+        let mut configured = false;
+        while launch.is_none() || !configured {
+            let request = self.messages.recv().ok()?;
+            match command(&request) {
+                "launch" => match launched(&request) {
+                    Ok((launched, stop_on_entry)) => {
+                        if stop_on_entry {
+                            self.stepper.step = Step::Entry;
+                        }
+                        launch = Some(launched);
+                        respond(&request, Ok(object([])));
+                    }
+                    Err(message) => respond(&request, Err(message)),
+                },
+                "configurationDone" => {
+                    configured = true;
+                    respond(&request, Ok(object([])));
+                }
+                command => {
+                    if !self.handle(&request) {
+                        respond(&request, Err(format!("The script isn't running, `{command}` can't be answered")));
+                    }
+                    if self.disconnected {
+                        return None;
+                    }
+                }
+            }
+        }
+        launch
+    }
+
+    // Answers the requests sent after the script ended, until the editor disconnects.
+    fn finish(&mut self) -> ExitCode {
+        while !self.disconnected {
+            let Ok(request) = self.messages.recv() else {
+                return ExitCode::FAILURE;
+            };
+            if !self.handle(&request) {
+                respond(&request, Err("The script has ended".to_string()));
+            }
+        }
+        ExitCode::SUCCESS
+    }
+
+    // Answers the requests sent while the script runs, without waiting for them.
+    fn poll(&mut self) -> Result<(), Halt> {
+        while let Ok(request) = self.messages.try_recv() {
+            if !self.handle(&request) {
+                respond(&request, Err(format!("The script is running, `{}` can't be answered", command(&request))));
+            }
+            if self.disconnected {
+                return interpreter::exit(0);
+            }
+        }
+        Ok(())
+    }
+
+    // Answers a request which can be sent at any time. Returns false for the other requests, which
+    // aren't answered.
+    fn handle(&mut self, request: &Json) -> bool {
+        let arguments = request.get(&["arguments"]).cloned().unwrap_or(Json::Null);
+        match command(request) {
+            "initialize" => {
+                let capabilities = object([
+                    ("supportsConfigurationDoneRequest", true.into()),
+                    ("supportsEvaluateForHovers", true.into()),
+                    ("supportsTerminateRequest", true.into()),
+                ]);
+                respond(request, Ok(capabilities));
+                // The editor sends the breakpoints once it knows the adapter is ready for them.
+                event("initialized", object([]));
+            }
+            "setBreakpoints" => {
+                let path = arguments.get(&["source", "path"]).and_then(Json::as_str).unwrap_or_default();
+                let path = std::fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+                let lines: Vec<usize> = arguments
+                    .get(&["breakpoints"])
+                    .and_then(Json::as_array)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|breakpoint| breakpoint.get(&["line"]).and_then(Json::as_usize))
+                    .collect();
+                let breakpoints =
+                    lines.iter().map(|line| object([("verified", true.into()), ("line", (*line).into())])).collect();
+                self.stepper.breakpoints.insert(path, lines);
+                respond(request, Ok(object([("breakpoints", Json::Array(breakpoints))])));
+            }
+            // The errors of a script always stop it, there is nothing to configure.
+            "setExceptionBreakpoints" => respond(request, Ok(object([]))),
+            "threads" => {
+                let thread = object([("id", THREAD.into()), ("name", "main".into())]);
+                respond(request, Ok(object([("threads", Json::Array(vec![thread]))])));
+            }
+            "pause" => {
+                self.stepper.step = Step::Pause;
+                respond(request, Ok(object([])));
+            }
+            "disconnect" | "terminate" => {
+                self.disconnected = true;
+                respond(request, Ok(object([])));
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    // Stops the script before a statement when it reached a breakpoint or the end of a step.
+    fn pause(&mut self, location: &Location, context: &mut Context) -> Result<(), Halt> {
+        self.poll()?;
+        match self.stepper.stop(location, context) {
+            Some(reason) => self.stopped(reason, location, context),
+            None => Ok(()),
+        }
+    }
+
+    // Tells the editor the script stopped, then answers its requests until it resumes the script.
+    fn stopped(&mut self, reason: &str, location: &Location, context: &mut Context) -> Result<(), Halt> {
+        let body = object([("reason", reason.into()), ("threadId", THREAD.into()), ("allThreadsStopped", true.into())]);
+        event("stopped", body);
+        self.values.clear();
+        loop {
+            // The script can't go on without the editor.
+            let Ok(request) = self.messages.recv() else {
+                return interpreter::exit(0);
+            };
+            let arguments = request.get(&["arguments"]).cloned().unwrap_or(Json::Null);
+            let step = match command(&request) {
+                "continue" => Step::Run,
+                "next" => Step::Over(context.depth()),
+                "stepIn" => Step::In,
+                "stepOut" => Step::Out(context.depth()),
+                "stackTrace" => {
+                    respond(&request, Ok(self.stack(location, context)));
+                    continue;
+                }
+                "scopes" => {
+                    let frame = arguments.get(&["frameId"]).and_then(Json::as_usize).unwrap_or_default();
+                    respond(&request, Ok(scopes(frame)));
+                    continue;
+                }
+                "variables" => {
+                    let reference = arguments.get(&["variablesReference"]).and_then(Json::as_usize).unwrap_or_default();
+                    respond(&request, self.variables(reference, context));
+                    continue;
+                }
+                "evaluate" => {
+                    let code = arguments.get(&["expression"]).and_then(Json::as_str).unwrap_or_default();
+                    let result = interpreter::inspect(code, context)?.map(|value| {
+                        let variable = self.variable(String::new(), value);
+                        let reference = variable.get(&["variablesReference"]).cloned().unwrap_or(Json::Null);
+                        let result = variable.get(&["value"]).cloned().unwrap_or(Json::Null);
+                        object([("result", result), ("variablesReference", reference)])
+                    });
+                    respond(&request, result);
+                    continue;
+                }
+                command => {
+                    if !self.handle(&request) {
+                        respond(&request, Err(format!("Unsupported request `{command}`")));
+                    }
+                    if self.disconnected {
+                        return interpreter::exit(0);
+                    }
+                    continue;
+                }
+            };
+            self.stepper.step = step;
+            respond(&request, Ok(object([("allThreadsContinued", true.into())])));
+            return Ok(());
+        }
+    }
+
+    // Lists the function calls running, the innermost first, each paused at the call of the
+    // next one. The script itself is the outermost.
+    fn stack(&mut self, location: &Location, context: &mut Context) -> Json {
+        let calls = context.backtrace();
+        let names = calls.iter().rev().map(|call| call.name.as_str()).chain(["<script>"]);
+        let sites = std::iter::once(Some(location.clone())).chain(calls.iter().rev().map(|call| call.site.clone()));
+        let mut frames = Vec::new();
+        for (id, (name, site)) in names.zip(sites).enumerate() {
+            let mut frame = vec![("id".to_string(), id.into()), ("name".to_string(), name.into())];
+            let (line, column) = match site {
+                Some(site) => {
+                    let (canonical, line, column) = self.stepper.place(&site, context);
+                    let path = context.loader().path(site.file());
+                    let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.display().to_string());
+                    let mut described = vec![("name".to_string(), name.into())];
+                    if let Some(path) = canonical {
+                        described.push(("path".to_string(), path.display().to_string().into()));
+                    }
+                    frame.push(("source".to_string(), Json::Object(described)));
+                    (line, column)
+                }
+                // Only the functions called by the interpreter itself have no call site.
+                None => (0, 0),
+            };
+            frame.push(("line".to_string(), line.into()));
+            frame.push(("column".to_string(), column.into()));
+            frames.push(Json::Object(frame));
+        }
+        let total = frames.len();
+        object([("stackFrames", Json::Array(frames)), ("totalFrames", total.into())])
+    }
+
+    // Lists the variables of a scope, or the items of a value.
+    fn variables(&mut self, reference: usize, context: &mut Context) -> Result<Json, String> {
+        let items = match reference {
+            LOCALS | GLOBALS => {
+                let [locals, globals] = context.variables();
+                if reference == LOCALS { locals } else { globals }
+            }
+            _ => match self.values.get(reference.wrapping_sub(GLOBALS + 1)) {
+                Some(value) => items(value),
+                None => return Err(format!("Unknown variables reference {reference}")),
+            },
+        };
+        let variables = items.into_iter().map(|(name, value)| self.variable(name, value)).collect();
+        Ok(object([("variables", Json::Array(variables))]))
+    }
+
+    // Describes a variable. A value which has items is given a reference, to list them.
+    fn variable(&mut self, name: String, value: Expr) -> Json {
+        let text = show(&value);
+        let kind = value.type_name();
+        let reference = match &value {
+            Expr::Array(items) if items.is_empty() => 0,
+            Expr::Variant(_, _, values) if values.is_empty() => 0,
+            Expr::Instance(_, fields) | Expr::Module(_, fields) if fields.is_empty() => 0,
+            Expr::Array(_) | Expr::Variant(..) | Expr::Instance(..) | Expr::Module(..) => {
+                self.values.push(value);
+                self.values.len() + GLOBALS
+            }
+            _ => 0,
+        };
+        object([
+            ("name", name.into()),
+            ("value", text.into()),
+            ("type", kind.into()),
+            ("variablesReference", reference.into()),
+        ])
+    }
+}
+
+// Pauses the script for the editor, see `Session::pause`.
+struct Adapter(Rc<RefCell<Session>>);
+
+impl Debugger for Adapter {
+    fn pause(&mut self, location: &Location, context: &mut Context) -> Result<(), Halt> {
+        self.0.borrow_mut().pause(location, context)
+    }
+}
+
+// The local variables are only known for the innermost call.
+fn scopes(frame: usize) -> Json {
+    let scope = |name: &str, reference: usize| {
+        object([("name", name.into()), ("variablesReference", reference.into()), ("expensive", false.into())])
+    };
+    let mut scopes = Vec::new();
+    if frame == 0 {
+        scopes.push(scope("Locals", LOCALS));
+    }
+    scopes.push(scope("Globals", GLOBALS));
+    object([("scopes", Json::Array(scopes))])
+}
+
+// Returns the items of a value shown with them: the elements of an array, the fields of an
+// instance, what a module declares and the values a variant carries.
+fn items(value: &Expr) -> Vec<(String, Expr)> {
+    match value {
+        Expr::Array(items) => items.iter().cloned().enumerate().map(|(index, item)| (index.to_string(), item)).collect(),
+        Expr::Variant(_, _, values) => {
+            values.iter().cloned().enumerate().map(|(index, value)| (index.to_string(), value)).collect()
+        }
+        Expr::Instance(_, fields) | Expr::Module(_, fields) => fields.clone(),
+        _ => Vec::new(),
+    }
+}
+
+// Reads the arguments of the `launch` request: the script and its arguments, and whether the
+// types are checked and whether the script pauses before its first statement.
+fn launched(request: &Json) -> Result<(Launch, bool), String> {
+    let arguments = request.get(&["arguments"]).cloned().unwrap_or(Json::Null);
+    let program = arguments.get(&["program"]).and_then(Json::as_str).ok_or("The script to debug, `program`, is missing")?;
+    let launch = Launch {
+        program: PathBuf::from(program),
+        arguments: arguments
+            .get(&["args"])
+            .and_then(Json::as_array)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|argument| argument.as_str().map(str::to_string))
+            .collect(),
+        check_types: arguments.get(&["checkTypes"]) == Some(&Json::Bool(true)),
+    };
+    Ok((launch, arguments.get(&["stopOnEntry"]) == Some(&Json::Bool(true))))
+}
+
+fn command(request: &Json) -> &str {
+    request.get(&["command"]).and_then(Json::as_str).unwrap_or_default()
+}
+
+// Answers a request with the body of the response, or with the message of the error.
+fn respond(request: &Json, result: Result<Json, String>) {
+    let request_seq = request.get(&["seq"]).cloned().unwrap_or(Json::Null);
+    let command = command(request).into();
+    let success = result.is_ok().into();
+    let last = match result {
+        Ok(body) => ("body", body),
+        Err(message) => ("message", message.into()),
+    };
+    send(&object([
+        ("seq", sequence()),
+        ("type", "response".into()),
+        ("request_seq", request_seq),
+        ("success", success),
+        ("command", command),
+        last,
+    ]));
+}
+
+fn event(name: &str, body: Json) {
+    send(&object([("seq", sequence()), ("type", "event".into()), ("event", name.into()), ("body", body)]));
+}
+
+fn sequence() -> Json {
+    (SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1).into()
+}
@@ -0,0 +1,303 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel --debug`, which runs a script in the terminal and pauses it before its first statement
+// and at its breakpoints, then reads commands to step through it and look at its variables and
+// calls, see `HELP`.
+// Where a script pauses, at the breakpoints and the end of the steps, is found the same way by the
+// debug adapter, see `dap`.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use crate::context::{Context, Debugger};
+use crate::error::{FileIdentifier, Halt, Location};
+use crate::expr::{Atom, Expr};
+use crate::interpreter;
+use crate::loader::Loader;
+
+const HELP: &str = "\
+break [FILE:]LINE    pause on a line of the file paused in, or of FILE (or b)
+break                list the breakpoints
+delete [FILE:]LINE   remove a breakpoint (or d)
+continue             run until a breakpoint (or c)
+step                 run until the next line, in the function it calls if any (or s)
+next                 run until the next line, after the functions it calls (or n)
+finish               run until the function paused in returns (or f)
+print CODE           evaluate code where the script is paused and print its value (or p)
+vars                 list the local and global variables and their values (or v)
+backtrace            list the calls being run, the innermost first (or bt)
+help                 show this help
+quit                 stop the script (or Ctrl-D)";
+
+// Runs a script like `interpreter::interpreter` does, with the tree-walker, pausing it for the
+// commands typed.
+pub fn debug(exprs: Vec<Expr>, loader: Loader, max_depth: usize, arguments: Vec<String>) -> ExitCode {
+    println!("Paused before the first statement - type help for the commands");
+    let terminal = Terminal { stepper: Stepper::new(Step::Entry) };
+    interpreter::debug(exprs, loader, max_depth, arguments, Box::new(terminal))
+}
+
+// How far a script runs before it is paused again.
+#[derive(Clone, Copy)]
+pub(crate) enum Step {
+    Run,         // Until a breakpoint.
+    Entry,       // Until the first statement.
+    Pause,       // Until the next statement, when the editor asked to pause.
+    In,          // Until the next line, in the function it calls or after it.
+    Over(usize), // Until the next line run at this call depth or at a lower one.
+    Out(usize),  // Until the next line run at a call depth lower than this one.
+}
+
+// Where the script is, before a statement.
+#[derive(Clone, Copy)]
+struct Position {
+    file: FileIdentifier,
+    line: usize,
+    depth: usize,  // How many function calls are running.
+    offset: usize, // Where the statement starts in the file, to tell a loop running the line again.
+}
+
+// A file the script runs, to find the lines of its statements.
+struct Source {
+    path: Option<PathBuf>, // The canonical path of the file, `None` for the libraries, which aren't files.
+    lines: Vec<usize>,     // Where each line starts.
+}
+
+impl Source {
+    // Returns the line of an offset, counted from 1.
+    fn line(&self, offset: usize) -> usize {
+        self.lines.partition_point(|start| *start <= offset)
+    }
+}
+
+// Decides where a script pauses, from its breakpoints and the step it was resumed for.
+pub(crate) struct Stepper {
+    pub(crate) step: Step,
+    pub(crate) breakpoints: HashMap<PathBuf, Vec<usize>>, // The lines of the breakpoints, by canonical path.
+    last: Option<Position>, // Where the last statement run was.
+    sources: HashMap<FileIdentifier, Source>,
+}
+
+impl Stepper {
+    pub(crate) fn new(step: Step) -> Self {
+        Stepper { step, breakpoints: HashMap::new(), last: None, sources: HashMap::new() }
+    }
+
+    // Tells why the script pauses before a statement, a breakpoint or the end of a step, or returns
+    // `None` when it goes on. Only the first statement run on a line pauses it, not the ones
+    // written after it on the same line, until the line runs again, like in the next iteration of
+    // a loop. The code of the standard library never pauses it.
+    pub(crate) fn stop(&mut self, location: &Location, context: &mut Context) -> Option<&'static str> {
+        let source = source(&mut self.sources, location.file(), context);
+        let path = source.path.as_ref()?;
+        let offset = location.range().start;
+        let position = Position { file: location.file(), line: source.line(offset), depth: context.depth(), offset };
+        let breakpoint = self.breakpoints.get(path).is_some_and(|lines| lines.contains(&position.line));
+        let entered = self.last.is_none_or(|last| {
+            (last.file, last.line, last.depth) != (position.file, position.line, position.depth)
+                || offset <= last.offset
+        });
+        self.last = Some(position);
+        match self.step {
+            Step::Pause => Some("pause"),
+            _ if !entered => None,
+            Step::Entry => Some("entry"),
+            Step::In => Some("step"),
+            Step::Over(depth) if position.depth <= depth => Some("step"),
+            Step::Out(depth) if position.depth < depth => Some("step"),
+            _ if breakpoint => Some("breakpoint"),
+            _ => None,
+        }
+    }
+
+    // Returns the canonical path of the file of a location, when it is a file, and its line and
+    // column, counted from 1.
+    pub(crate) fn place(&mut self, location: &Location, context: &mut Context) -> (Option<PathBuf>, usize, usize) {
+        let source = source(&mut self.sources, location.file(), context);
+        let offset = location.range().start;
+        let line = source.line(offset);
+        let start = source.lines[line - 1];
+        let path = source.path.clone();
+        let column = context.loader().content(location.file())[start..offset].chars().count() + 1;
+        (path, line, column)
+    }
+}
+
+// Returns the lines of a file the script runs, found when it first runs a statement of the file.
+fn source<'a>(
+    sources: &'a mut HashMap<FileIdentifier, Source>,
+    file: FileIdentifier,
+    context: &mut Context,
+) -> &'a Source {
+    sources.entry(file).or_insert_with(|| {
+        let loader = context.loader();
+        let path = std::fs::canonicalize(loader.path(file)).ok();
+        let content = loader.content(file);
+        let lines = std::iter::once(0).chain(content.match_indices('\n').map(|(index, _)| index + 1)).collect();
+        Source { path, lines }
+    })
+}
+
+// Writes a value like `println` does, with the strings and characters quoted, and the functions
+// and the other values which print nothing as their type.
+pub(crate) fn show(value: &Expr) -> String {
+    match value {
+        Expr::Constant(Atom::String(text)) => format!("{text:?}"),
+        Expr::Constant(Atom::Char(c)) => format!("{c:?}"),
+        _ => match value.to_string() {
+            text if text.is_empty() => format!("<{}>", value.type_name()),
+            text => text,
+        },
+    }
+}
+
+// Pauses the script for the commands typed in the terminal.
+struct Terminal {
+    stepper: Stepper,
+}
+
+impl Debugger for Terminal {
+    fn pause(&mut self, location: &Location, context: &mut Context) -> Result<(), Halt> {
+        let Some(reason) = self.stepper.stop(location, context) else {
+            return Ok(());
+        };
+        let (_, line, _) = self.stepper.place(location, context);
+        let name = relative(context.loader().path(location.file()));
+        let text = context.loader().content(location.file()).lines().nth(line - 1).unwrap_or_default().to_string();
+        println!("{} at {name}:{line}", if reason == "breakpoint" { "Breakpoint" } else { "Paused" });
+        println!("{line} │ {text}");
+        let mut lines = io::stdin().lock().lines();
+        loop {
+            print!("(debug) ");
+            io::stdout().flush().expect("Failed to flush stdout");
+            // The end of the input stops the script, like `quit`.
+            let Some(Ok(entry)) = lines.next() else {
+                println!();
+                return interpreter::exit(0);
+            };
+            let (command, argument) = entry.trim().split_once(' ').unwrap_or((entry.trim(), ""));
+            let argument = argument.trim();
+            let step = match command {
+                "" => continue,
+                "continue" | "c" => Step::Run,
+                "step" | "s" => Step::In,
+                "next" | "n" => Step::Over(context.depth()),
+                "finish" | "f" => Step::Out(context.depth()),
+                "break" | "b" if argument.is_empty() => {
+                    self.list_breakpoints();
+                    continue;
+                }
+                "break" | "b" | "delete" | "d" => {
+                    let Some((path, line)) = breakpoint(argument, location, context) else {
+                        eprintln!("error: expected a line, or a file and a line like `main.mus:12`");
+                        continue;
+                    };
+                    let lines = self.stepper.breakpoints.entry(path).or_default();
+                    if matches!(command, "break" | "b") {
+                        if !lines.contains(&line) {
+                            lines.push(line);
+                        }
+                    } else if let Some(index) = lines.iter().position(|added| *added == line) {
+                        lines.remove(index);
+                    } else {
+                        eprintln!("error: there is no breakpoint on this line");
+                    }
+                    continue;
+                }
+                "print" | "p" if !argument.is_empty() => {
+                    match interpreter::inspect(argument, context)? {
+                        Ok(value) => println!("{}", show(&value)),
+                        Err(message) => eprintln!("error: {message}"),
+                    }
+                    continue;
+                }
+                "vars" | "v" => {
+                    let [locals, globals] = context.variables();
+                    for (title, variables) in [("Locals", locals), ("Globals", globals)] {
+                        if !variables.is_empty() {
+                            println!("{title}:");
+                        }
+                        for (name, value) in variables {
+                            println!("  {name} = {}", show(&value));
+                        }
+                    }
+                    continue;
+                }
+                "backtrace" | "bt" => {
+                    self.backtrace(location, context);
+                    continue;
+                }
+                "help" => {
+                    println!("{HELP}");
+                    continue;
+                }
+                "quit" | "q" => return interpreter::exit(0),
+                "print" | "p" => {
+                    eprintln!("error: print expects some code, see help");
+                    continue;
+                }
+                _ => {
+                    eprintln!("error: unknown command `{command}`, see help");
+                    continue;
+                }
+            };
+            self.stepper.step = step;
+            return Ok(());
+        }
+    }
+}
+
+impl Terminal {
+    fn list_breakpoints(&self) {
+        let mut breakpoints: Vec<(&PathBuf, &usize)> =
+            self.stepper.breakpoints.iter().flat_map(|(path, lines)| lines.iter().map(move |line| (path, line))).collect();
+        breakpoints.sort();
+        if breakpoints.is_empty() {
+            println!("No breakpoints");
+        }
+        for (path, line) in breakpoints {
+            println!("{}:{line}", relative(path));
+        }
+    }
+
+    // Lists the calls being run, the innermost first, each paused at the call of the next one. The
+    // script itself is the outermost.
+    fn backtrace(&mut self, location: &Location, context: &mut Context) {
+        let calls = context.backtrace();
+        let names = calls.iter().rev().map(|call| call.name.as_str()).chain(["<script>"]);
+        let sites = std::iter::once(Some(location.clone())).chain(calls.iter().rev().map(|call| call.site.clone()));
+        for (index, (name, site)) in names.zip(sites).enumerate() {
+            match site {
+                Some(site) => {
+                    let (_, line, _) = self.stepper.place(&site, context);
+                    println!("#{index} {name} at {}:{line}", relative(context.loader().path(site.file())));
+                }
+                // Only the functions called by the interpreter itself have no call site.
+                None => println!("#{index} {name}"),
+            }
+        }
+    }
+}
+
+// Writes a path from the current directory when it is in it.
+pub(crate) fn relative(path: &Path) -> String {
+    let current = std::env::current_dir().unwrap_or_default();
+    path.strip_prefix(current).unwrap_or(path).display().to_string()
+}
+
+// Reads where a breakpoint goes: a line of the file paused in, or a file and a line. The file is
+// found from the current directory, or else from the directory of the file paused in.
+fn breakpoint(argument: &str, location: &Location, context: &mut Context) -> Option<(PathBuf, usize)> {
+    let current = context.loader().path(location.file()).to_path_buf();
+    let (file, line) = match argument.rsplit_once(':') {
+        Some((file, line)) => (Path::new(file), line),
+        None => (current.as_path(), argument),
+    };
+    let line = line.trim().parse().ok().filter(|line| *line > 0)?;
+    let path = std::fs::canonicalize(file)
+        .or_else(|_| std::fs::canonicalize(current.parent().unwrap_or(Path::new(".")).join(file)))
+        .unwrap_or_else(|_| file.to_path_buf());
+    Some((path, line))
+}
@@ -0,0 +1,288 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel doc`, which writes the documentation of a script and of the files it includes, in
+// Markdown or HTML. Each file gets a section listing its functions and its structs, with their
+// methods, each with its signature and the `///` comments written right before it.
+// The comments are written in Markdown. In HTML, only their paragraphs and their `code` are kept.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use crate::error::FileError;
+use crate::lexer::{Token, TokenRecord};
+use crate::loader::Loader;
+use crate::parser::{Expression, ParameterExpression};
+
+/// How the documentation is written.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "markdown" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            _ => Err(format!("unknown documentation format `{name}`, expected `markdown` or `html`")),
+        }
+    }
+}
+
+// A part of the documentation, written the same way in every format.
+enum Block {
+    Heading(usize, String), // The level of the heading, 1 for the title of a file, and its text.
+    Code(String),
+    Text(String), // What doc comments say, in Markdown.
+}
+
+// A function or struct to document.
+struct Item {
+    signature: String,
+    doc: String,
+    methods: Vec<Item>,
+}
+
+// Writes the documentation of a script and the files it includes, to `output` or else to the
+// standard output. Fails when an error was reported.
+pub fn document(file: &str, format: Format, output: Option<&Path>) -> ExitCode {
+    let mut loader = Loader::new(false);
+    let script = std::fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+    let directory = script.parent().map(Path::to_path_buf).unwrap_or_default();
+    let mut documented = Vec::new();
+    let mut blocks = Vec::new();
+    let mut failed = false;
+    let mut pending = vec![script];
+    while let Some(path) = pending.pop() {
+        if documented.contains(&path) {
+            continue;
+        }
+        documented.push(path.clone());
+        let (file, expressions) = match loader.syntax(&path, None) {
+            Ok(syntax) => syntax,
+            Err(error) => {
+                loader.show(&error);
+                failed = true;
+                continue;
+            }
+        };
+        let content = loader.content(file);
+        let name = path.strip_prefix(&directory).unwrap_or(&path).display().to_string();
+        blocks.push(Block::Heading(1, name));
+        blocks.extend(module(content, &expressions));
+        // The included files follow the file, in the order of their `include`. The names which
+        // aren't files are the libraries of the standard library.
+        let mut includes = Vec::new();
+        for expression in &expressions {
+            let Expression::Include { id, .. } = expression else {
+                continue;
+            };
+            let target = id.get_content(content);
+            let found = match id.token_type {
+                Token::String => loader.find_from(&path, Path::new(target.trim_matches('"'))),
+                _ => match loader.find_from(&path, Path::new(&format!("{target}.mus"))) {
+                    Ok(found) => Ok(found),
+                    Err(_) => continue,
+                },
+            };
+            match found {
+                Ok(found) => includes.push(found),
+                Err(_) => {
+                    let message = format!("Failed to find the file `{}` included by this file", target.trim_matches('"'));
+                    loader.show(&FileError::new(&path, message));
+                    failed = true;
+                }
+            }
+        }
+        pending.extend(includes.into_iter().rev());
+    }
+    let documentation = match format {
+        Format::Markdown => markdown(&blocks),
+        Format::Html => html(&blocks),
+    };
+    match output {
+        Some(output) => {
+            if let Err(error) = std::fs::write(output, documentation) {
+                loader.show(&FileError::new(output, format!("Failed to write the documentation: {error}")));
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{documentation}"),
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+// Documents the functions and the structs declared by a file, in the order they are written. The
+// methods of a struct follow it, those of a struct declared in another file follow its `impl`.
+fn module(content: &str, expressions: &[Expression]) -> Vec<Block> {
+    let mut functions = Vec::new();
+    let mut structs: Vec<(String, Item)> = Vec::new();
+    for expression in expressions {
+        match expression {
+            Expression::Function { doc, id, args, return_type, .. } => {
+                functions.push(function(content, doc, id, args, return_type.as_ref()));
+            }
+            Expression::Struct { id, .. } => {
+                let (signature, doc) = summary(content, expression).expect("a struct has a summary");
+                structs.push((id.get_content(content).to_string(), Item { signature, doc, methods: Vec::new() }));
+            }
+            Expression::Impl { id, trait_id, methods, .. } => {
+                let name = id.get_content(content).to_string();
+                let methods = methods.iter().filter_map(|method| match method {
+                    Expression::Function { doc, id, args, return_type, .. } => {
+                        Some(function(content, doc, id, args, return_type.as_ref()))
+                    }
+                    _ => None,
+                });
+                match structs.iter_mut().find(|(declared, _)| *declared == name) {
+                    Some((_, item)) => item.methods.extend(methods),
+                    None => {
+                        let signature = match trait_id {
+                            Some(trait_id) => format!("impl {} for {name}", trait_id.get_content(content)),
+                            None => format!("impl {name}"),
+                        };
+                        structs.push((name, Item { signature, doc: String::new(), methods: methods.collect() }));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut blocks = Vec::new();
+    if functions.is_empty() && structs.is_empty() {
+        blocks.push(Block::Text("Nothing is declared in this file.".to_string()));
+    }
+    for (title, items) in [("Functions", functions), ("Structs", structs.into_iter().map(|(_, item)| item).collect())] {
+        if items.is_empty() {
+            continue;
+        }
+        blocks.push(Block::Heading(2, title.to_string()));
+        for item in items {
+            item.blocks(3, &mut blocks);
+        }
+    }
+    blocks
+}
+
+impl Item {
+    fn blocks(self, level: usize, blocks: &mut Vec<Block>) {
+        blocks.push(Block::Heading(level, format!("`{}`", name(&self.signature))));
+        blocks.push(Block::Code(self.signature));
+        if !self.doc.is_empty() {
+            blocks.push(Block::Text(self.doc));
+        }
+        if !self.methods.is_empty() {
+            blocks.push(Block::Heading(level + 1, "Methods".to_string()));
+            for method in self.methods {
+                method.blocks(level + 2, blocks);
+            }
+        }
+    }
+}
+
+// Returns the signature and the doc comments of a function or a struct, for the editors, see `lsp`.
+pub(crate) fn summary(content: &str, expression: &Expression) -> Option<(String, String)> {
+    let item = match expression {
+        Expression::Function { doc, id, args, return_type, .. } => function(content, doc, id, args, return_type.as_ref()),
+        Expression::Struct { doc, id, fields } => {
+            let fields: Vec<&str> = fields.iter().map(|field| field.get_content(content)).collect();
+            let signature = format!("struct {} {{ {} }}", id.get_content(content), fields.join(", "));
+            Item { signature, doc: comments(content, doc), methods: Vec::new() }
+        }
+        _ => return None,
+    };
+    Some((item.signature, item.doc))
+}
+
+// Returns the name in a signature, after its keyword.
+fn name(signature: &str) -> &str {
+    let declared = signature.split_once(' ').map_or(signature, |(_, rest)| rest);
+    declared.split(['(', ' ']).next().unwrap_or(declared)
+}
+
+// Documents a function or a method, with its signature as written.
+fn function(
+    content: &str,
+    doc: &[TokenRecord],
+    id: &TokenRecord,
+    args: &[ParameterExpression],
+    return_type: Option<&TokenRecord>,
+) -> Item {
+    let parameters: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            let mut parameter = arg.id.get_content(content).to_string();
+            if let Some(annotation) = &arg.annotation {
+                parameter.push_str(&format!(": {}", annotation.get_content(content)));
+            }
+            if let Some(default) = &arg.default {
+                let value = default.span().map_or("...", |range| &content[range]);
+                parameter.push_str(&format!(" = {value}"));
+            }
+            parameter
+        })
+        .collect();
+    let mut signature = format!("fn {}({})", id.get_content(content), parameters.join(", "));
+    if let Some(return_type) = return_type {
+        signature.push_str(&format!(" -> {}", return_type.get_content(content)));
+    }
+    Item { signature, doc: comments(content, doc), methods: Vec::new() }
+}
+
+// Returns the text of doc comments, without their `///` and the space following it.
+fn comments(content: &str, doc: &[TokenRecord]) -> String {
+    let lines: Vec<&str> = doc
+        .iter()
+        .map(|comment| {
+            let text = comment.get_content(content).trim_end().trim_start_matches("///");
+            text.strip_prefix(' ').unwrap_or(text)
+        })
+        .collect();
+    lines.join("\n").trim().to_string()
+}
+
+fn markdown(blocks: &[Block]) -> String {
+    let sections: Vec<String> = blocks
+        .iter()
+        .map(|block| match block {
+            Block::Heading(level, text) => format!("{} {text}", "#".repeat(*level)),
+            Block::Code(code) => format!("```\n{code}\n```"),
+            Block::Text(text) => text.clone(),
+        })
+        .collect();
+    sections.join("\n\n") + "\n"
+}
+
+fn html(blocks: &[Block]) -> String {
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Documentation</title>\n</head>\n<body>\n");
+    for block in blocks {
+        match block {
+            Block::Heading(level, text) => html.push_str(&format!("<h{level}>{}</h{level}>\n", inline(text))),
+            Block::Code(code) => html.push_str(&format!("<pre><code>{}</code></pre>\n", escape(code))),
+            Block::Text(text) => {
+                for paragraph in text.split("\n\n") {
+                    html.push_str(&format!("<p>{}</p>\n", inline(paragraph.trim())));
+                }
+            }
+        }
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+// Writes Markdown text in HTML, with the text between backquotes as code.
+fn inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(index, part)| if index % 2 == 1 { format!("<code>{}</code>", escape(part)) } else { escape(part) })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
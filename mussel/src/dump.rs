@@ -0,0 +1,465 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Shows the expressions a script is parsed into, for `--dump-ast`: as an indented tree to read, or
+// as JSON for other tools. Every expression is a node named after its variant of `Expr`, with its
+// parts as named fields, and the byte range of the code it points at in errors as `span`.
+
+use std::fmt::Write;
+use std::ops::Range;
+use std::str::FromStr;
+use crate::error::Location;
+use crate::expr::{Align, Atom, BinOp, Binding, Expr, FormatSpec, IncludeTarget, LogicOp, Operator, Parameter,
+                  Pattern, StringPart, TypeAnnotation, UnaryOp};
+
+// How the expressions are shown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Tree, // An indented tree, one field per line.
+    Json, // An array of JSON objects, whose `kind` is the variant.
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "tree" => Ok(Format::Tree),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unknown format `{name}`, expected `tree` or `json`")),
+        }
+    }
+}
+
+// A part of an expression.
+enum Value {
+    Node(Node),
+    List(Vec<Value>),
+    Text(String),    // A name, an operator or a string.
+    Literal(String), // A number or a boolean, written as is in JSON too.
+    Span(Range<usize>),
+    Missing, // An optional part which isn't written, `null` in JSON.
+}
+
+// An expression, or a part of it with parts of its own, like a parameter.
+struct Node {
+    kind: &'static str,
+    fields: Vec<(&'static str, Value)>,
+}
+
+// Shows the expressions of a file.
+pub fn dump(exprs: &[Expr], format: Format) -> String {
+    let mut output = String::new();
+    match format {
+        Format::Tree => {
+            for expr in exprs {
+                tree(&node(expr), 0, &mut output);
+            }
+        }
+        Format::Json => {
+            json(&list(exprs), 0, &mut output);
+            output.push('\n');
+        }
+    }
+    output
+}
+
+fn node(expr: &Expr) -> Node {
+    let (kind, fields) = match expr {
+        Expr::Void => ("Void", vec![]),
+        Expr::Array(items) => ("Array", vec![("items", list(items.iter()))]),
+        Expr::Constant(atom) => return constant(atom),
+        Expr::Variable(name, location) => ("Variable", vec![text("name", name), span(location)]),
+        Expr::Interpolation(parts) => ("Interpolation", vec![("parts", Value::List(parts.iter().map(part).collect()))]),
+        Expr::Let(target, value, _) => ("Let", vec![("binding", binding(target)), expression("value", value)]),
+        Expr::Const(target, value, _) => ("Const", vec![("binding", binding(target)), expression("value", value)]),
+        Expr::Assign(target, value, location) => {
+            ("Assign", vec![expression("target", target), expression("value", value), span(location)])
+        }
+        Expr::Call(callee, arguments, location) => {
+            ("Call", vec![expression("callee", callee), ("arguments", list(arguments)), span(location)])
+        }
+        Expr::MethodCall(receiver, method, arguments, location) => (
+            "MethodCall",
+            vec![expression("receiver", receiver), text("method", method), ("arguments", list(arguments)), span(location)],
+        ),
+        Expr::Compare(left, operator, right, location) => {
+            let operator = match operator {
+                Operator::Equal => "==",
+                Operator::NotEqual => "!=",
+                Operator::LessThan => "<",
+                Operator::LessThanEqual => "<=",
+                Operator::GreaterThan => ">",
+                Operator::GreaterThanEqual => ">=",
+            };
+            ("Compare", vec![text("operator", operator), expression("left", left), expression("right", right), span(location)])
+        }
+        Expr::Logic(left, operator, right, location) => {
+            let operator = match operator {
+                LogicOp::And => "and",
+                LogicOp::Or => "or",
+            };
+            ("Logic", vec![text("operator", operator), expression("left", left), expression("right", right), span(location)])
+        }
+        Expr::Closure(parameters, return_type, body, _) => {
+            ("Closure", vec![("parameters", signature(parameters)), annotation(return_type), ("body", list(body))])
+        }
+        Expr::Compiled(function, _) => {
+            ("Compiled", vec![("parameters", signature(&function.parameters)), annotation(&function.return_type)])
+        }
+        Expr::Function(name, parameters, return_type, body) => (
+            "Function",
+            vec![text("name", name), ("parameters", signature(parameters)), annotation(return_type), ("body", list(body))],
+        ),
+        Expr::If(condition, then, otherwise, location) => (
+            "If",
+            vec![
+                expression("condition", condition),
+                ("then", list(then)),
+                ("else", otherwise.as_ref().map_or(Value::Missing, list)),
+                span(location),
+            ],
+        ),
+        Expr::Return(value) => ("Return", vec![expression("value", value)]),
+        Expr::TailCall(name, callee, arguments, location) => (
+            "TailCall",
+            vec![text("name", name), expression("callee", callee), ("arguments", list(arguments)), span(location)],
+        ),
+        Expr::Throw(value, _) => ("Throw", vec![expression("value", value)]),
+        Expr::Try(body, error, catch) => ("Try", vec![("body", list(body)), text("error", error), ("catch", list(catch))]),
+        Expr::Assert(condition, message, location) => (
+            "Assert",
+            vec![
+                expression("condition", condition),
+                ("message", message.as_ref().map_or(Value::Missing, |message| Value::Node(node(message)))),
+                span(location),
+            ],
+        ),
+        Expr::For(target, iterable, body, location, label) => (
+            "For",
+            vec![
+                optional_text("label", label),
+                ("binding", binding(target)),
+                expression("iterable", iterable),
+                ("body", list(body)),
+                span(location),
+            ],
+        ),
+        Expr::Comprehension(value, target, iterable, location, condition) => (
+            "Comprehension",
+            vec![
+                expression("value", value),
+                ("binding", binding(target)),
+                expression("iterable", iterable),
+                ("condition", condition.as_ref().map_or(Value::Missing, |(condition, _)| Value::Node(node(condition)))),
+                span(location),
+            ],
+        ),
+        Expr::Get(collection, index, location) => {
+            ("Get", vec![expression("collection", collection), expression("index", index), span(location)])
+        }
+        Expr::Until(condition, body, location, label) => (
+            "Until",
+            vec![optional_text("label", label), expression("condition", condition), ("body", list(body)), span(location)],
+        ),
+        Expr::Loop(body, label) => ("Loop", vec![optional_text("label", label), ("body", list(body))]),
+        Expr::Break(value, label, location) => {
+            ("Break", vec![optional_text("label", label), expression("value", value), span(location)])
+        }
+        Expr::Continue(label, location) => ("Continue", vec![optional_text("label", label), span(location)]),
+        Expr::Binary(left, operator, right, location) => {
+            let operator = match operator {
+                BinOp::Add => "+",
+                BinOp::Sub => "-",
+                BinOp::Mul => "*",
+                BinOp::Div => "/",
+                BinOp::Pow => "**",
+            };
+            ("Binary", vec![text("operator", operator), expression("left", left), expression("right", right), span(location)])
+        }
+        Expr::Unary(operator, value, location) => {
+            let operator = match operator {
+                UnaryOp::Neg => "-",
+                UnaryOp::Not => "not",
+            };
+            ("Unary", vec![text("operator", operator), expression("value", value), span(location)])
+        }
+        Expr::Range(start, end, inclusive, location) => (
+            "Range",
+            vec![expression("start", start), expression("end", end), literal("inclusive", inclusive), span(location)],
+        ),
+        Expr::RangeValue(start, end, step, inclusive) => (
+            "RangeValue",
+            vec![literal("start", start), literal("end", end), literal("step", step), literal("inclusive", inclusive)],
+        ),
+        Expr::Include(target, name, location) => {
+            let target = match target {
+                IncludeTarget::Name(library) => text("library", library),
+                IncludeTarget::Path(path) => text("path", path),
+            };
+            ("Include", vec![target, optional_text("as", name), span(location)])
+        }
+        Expr::Match(subject, arms) => {
+            let arms = arms.iter().map(|(arm, body)| {
+                Value::Node(Node { kind: "Arm", fields: vec![("pattern", pattern(arm)), ("body", list(body))] })
+            });
+            ("Match", vec![expression("subject", subject), ("arms", Value::List(arms.collect()))])
+        }
+        Expr::Struct(name, fields, methods) => (
+            "Struct",
+            vec![text("name", name), ("fields", names(fields)), ("methods", list(methods.iter().map(|(_, method)| method)))],
+        ),
+        Expr::Impl(name, implemented, methods, location) => (
+            "Impl",
+            vec![text("name", name), optional_text("trait", implemented), ("methods", list(methods)), span(location)],
+        ),
+        Expr::Trait(name, methods) => ("Trait", vec![text("name", name), ("methods", names(methods))]),
+        Expr::Instance(name, fields) => ("Instance", vec![text("name", name), ("fields", members("Field", fields))]),
+        Expr::Field(object, name, location) => {
+            ("Field", vec![expression("object", object), text("name", name), span(location)])
+        }
+        Expr::Module(name, members_) => ("Module", vec![text("name", name), ("members", members("Member", members_))]),
+        Expr::Enum(name, variants) => {
+            let variants = variants.iter().map(|(variant, values)| {
+                Value::Node(Node { kind: "Variant", fields: vec![text("name", variant), ("values", names(values))] })
+            });
+            ("Enum", vec![text("name", name), ("variants", Value::List(variants.collect()))])
+        }
+        Expr::Path(name, variant, location) => ("Path", vec![text("enum", name), text("variant", variant), span(location)]),
+        Expr::Variant(name, variant, values) => {
+            ("Variant", vec![text("enum", name), text("variant", variant), ("values", list(values))])
+        }
+        Expr::Builtin(_) => ("Builtin", vec![]),
+        Expr::Native(native) => ("Native", vec![text("name", &native.name)]),
+    };
+    Node { kind, fields }
+}
+
+fn constant(atom: &Atom) -> Node {
+    let (kind, value) = match atom {
+        Atom::Number(number) => ("Int", Value::Literal(number.to_string())),
+        // Infinite floats, like `1e999`, have no JSON number.
+        Atom::Float(float) if float.is_finite() => ("Float", Value::Literal(format!("{float:?}"))),
+        Atom::Float(float) => ("Float", Value::Text(float.to_string())),
+        Atom::Boolean(boolean) => ("Bool", Value::Literal(boolean.to_string())),
+        Atom::String(string) => ("String", Value::Text(string.to_string())),
+        Atom::Char(c) => ("Char", Value::Text(c.to_string())),
+        Atom::Nil => return Node { kind: "Nil", fields: vec![] },
+    };
+    Node { kind, fields: vec![("value", value)] }
+}
+
+fn part(part: &StringPart) -> Value {
+    Value::Node(match part {
+        StringPart::Text(string) => Node { kind: "Text", fields: vec![text("text", string)] },
+        StringPart::Value(value, spec) => Node {
+            kind: "Value",
+            fields: vec![("value", Value::Node(node(value))), ("format", spec.as_ref().map_or(Value::Missing, format_spec))],
+        },
+    })
+}
+
+// Writes a format specifier back like it was written.
+fn format_spec(spec: &FormatSpec) -> Value {
+    let mut written = String::new();
+    if let Some(align) = spec.align {
+        if spec.fill != ' ' {
+            written.push(spec.fill);
+        }
+        written.push(match align {
+            Align::Left => '<',
+            Align::Right => '>',
+            Align::Center => '^',
+        });
+    }
+    if spec.zero {
+        written.push('0');
+    }
+    if spec.width > 0 {
+        written.push_str(&spec.width.to_string());
+    }
+    if let Some(precision) = spec.precision {
+        written.push_str(&format!(".{precision}"));
+    }
+    Value::Text(written)
+}
+
+fn binding(binding: &Binding) -> Value {
+    match binding {
+        Binding::Name(name) => Value::Text(name.clone()),
+        Binding::Wildcard => Value::Text("_".to_string()),
+        Binding::Array(bindings, _) => Value::List(bindings.iter().map(self::binding).collect()),
+    }
+}
+
+fn pattern(pattern: &Pattern) -> Value {
+    let (kind, fields) = match pattern {
+        Pattern::Wildcard => ("Wildcard", vec![]),
+        Pattern::Literal(atom) => ("Literal", vec![("value", Value::Node(constant(atom)))]),
+        Pattern::Variant(name, variant, values) => (
+            "Variant",
+            vec![
+                text("enum", name),
+                text("variant", variant),
+                ("values", values.as_ref().map_or(Value::Missing, |values| Value::List(values.iter().map(self::pattern).collect()))),
+            ],
+        ),
+        Pattern::Binding(name) => ("Binding", vec![text("name", name)]),
+        Pattern::Array(elements) => ("Array", vec![("elements", Value::List(elements.iter().map(self::pattern).collect()))]),
+        Pattern::Rest(name) => ("Rest", vec![optional_text("name", name)]),
+        Pattern::Struct(name, fields) => {
+            let fields = fields.iter().map(|(field, pattern)| {
+                Value::Node(Node { kind: "Field", fields: vec![text("name", field), ("pattern", self::pattern(pattern))] })
+            });
+            ("Struct", vec![text("name", name), ("fields", Value::List(fields.collect()))])
+        }
+    };
+    Value::Node(Node { kind, fields })
+}
+
+fn signature(parameters: &[Parameter]) -> Value {
+    let parameters = parameters.iter().map(|parameter| {
+        Value::Node(Node {
+            kind: "Parameter",
+            fields: vec![
+                text("name", &parameter.name),
+                annotation(&parameter.annotation),
+                ("default", parameter.default.as_ref().map_or(Value::Missing, |default| Value::Node(node(default)))),
+            ],
+        })
+    });
+    Value::List(parameters.collect())
+}
+
+fn annotation(annotation: &Option<TypeAnnotation>) -> (&'static str, Value) {
+    ("type", annotation.as_ref().map_or(Value::Missing, |annotation| Value::Text(annotation.ty.to_string())))
+}
+
+fn members(kind: &'static str, members: &[(String, Expr)]) -> Value {
+    let members = members.iter().map(|(name, value)| {
+        Value::Node(Node { kind, fields: vec![text("name", name), ("value", Value::Node(node(value)))] })
+    });
+    Value::List(members.collect())
+}
+
+fn list<'e>(exprs: impl IntoIterator<Item = &'e Expr>) -> Value {
+    Value::List(exprs.into_iter().map(|expr| Value::Node(node(expr))).collect())
+}
+
+fn names(names: &[String]) -> Value {
+    Value::List(names.iter().map(|name| Value::Text(name.clone())).collect())
+}
+
+fn expression(name: &'static str, expr: &Expr) -> (&'static str, Value) {
+    (name, Value::Node(node(expr)))
+}
+
+fn text(name: &'static str, text: &str) -> (&'static str, Value) {
+    (name, Value::Text(text.to_string()))
+}
+
+fn optional_text(name: &'static str, text: &Option<String>) -> (&'static str, Value) {
+    (name, text.as_ref().map_or(Value::Missing, |text| Value::Text(text.clone())))
+}
+
+fn literal(name: &'static str, literal: &impl ToString) -> (&'static str, Value) {
+    (name, Value::Literal(literal.to_string()))
+}
+
+fn span(location: &Location) -> (&'static str, Value) {
+    ("span", Value::Span(location.range()))
+}
+
+// Writes a node starting where the output is, and its fields on the next lines, one level deeper.
+// The fields left out are omitted.
+fn tree(node: &Node, depth: usize, output: &mut String) {
+    output.push_str(node.kind);
+    output.push('\n');
+    for (name, value) in &node.fields {
+        if matches!(value, Value::Missing) {
+            continue;
+        }
+        output.push_str(&"  ".repeat(depth + 1));
+        output.push_str(name);
+        output.push(':');
+        // The items of a list start on the next lines.
+        if !matches!(value, Value::List(items) if !items.is_empty()) {
+            output.push(' ');
+        }
+        tree_value(value, depth + 1, output);
+    }
+}
+
+fn tree_value(value: &Value, depth: usize, output: &mut String) {
+    match value {
+        Value::Node(node) => tree(node, depth, output),
+        Value::List(items) if items.is_empty() => output.push_str("[]\n"),
+        Value::List(items) => {
+            output.push('\n');
+            for item in items {
+                output.push_str(&"  ".repeat(depth + 1));
+                output.push_str("- ");
+                tree_value(item, depth + 2, output);
+            }
+        }
+        Value::Text(text) => {
+            let _ = writeln!(output, "{text:?}");
+        }
+        Value::Literal(literal) => {
+            let _ = writeln!(output, "{literal}");
+        }
+        Value::Span(range) => {
+            let _ = writeln!(output, "{range:?}");
+        }
+        Value::Missing => output.push_str("-\n"),
+    }
+}
+
+// Writes a value as indented JSON, starting where the output is.
+fn json(value: &Value, depth: usize, output: &mut String) {
+    let indent = "  ".repeat(depth + 1);
+    match value {
+        Value::Node(node) => {
+            let _ = write!(output, "{{\n{indent}\"kind\": \"{}\"", node.kind);
+            for (name, value) in &node.fields {
+                let _ = write!(output, ",\n{indent}\"{name}\": ");
+                json(value, depth + 1, output);
+            }
+            let _ = write!(output, "\n{}}}", "  ".repeat(depth));
+        }
+        Value::List(items) if items.is_empty() => output.push_str("[]"),
+        Value::List(items) => {
+            output.push('[');
+            for (index, item) in items.iter().enumerate() {
+                output.push_str(if index == 0 { "\n" } else { ",\n" });
+                output.push_str(&indent);
+                json(item, depth + 1, output);
+            }
+            let _ = write!(output, "\n{}]", "  ".repeat(depth));
+        }
+        Value::Text(text) => json_string(text, output),
+        Value::Literal(literal) => output.push_str(literal),
+        Value::Span(range) => {
+            let _ = write!(output, "{{ \"start\": {}, \"end\": {} }}", range.start, range.end);
+        }
+        Value::Missing => output.push_str("null"),
+    }
+}
+
+fn json_string(text: &str, output: &mut String) {
+    output.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(output, "\\u{:04x}", c as u32);
+            }
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+}
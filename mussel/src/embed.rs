@@ -0,0 +1,466 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The API to embed the interpreter in a Rust program, as its scripting language:
+//
+//     let mut interpreter = mussel::Interpreter::new();
+//     interpreter.set("width", 6_i64.into());
+//     let area = interpreter.eval_source("let height = 7\nwidth * height")?;
+//     assert_eq!(area.as_int(), Some(42));
+//
+// The code given to the same interpreter runs in the same global scope, like the entries of an
+// interactive session, so what it declares is kept for the next one: the program calls the functions
+// it declares with `call`, and it calls the functions of the program given with `register`. Its
+// errors are returned, instead of being reported, while what it prints is written to the standard
+// output as usual. The C programs use it through `capi`.
+// Every nested call of a function takes room on the stack: the calls nested too deep for the stack
+// of the thread stop with the recursion error, so a program runs the code in a thread with a large
+// stack to allow deep recursion.
+
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use codespan_reporting::diagnostic::Severity;
+use crate::context::{self, Context};
+use crate::error::{LError, Location};
+use crate::expr::{Atom, Expr, Native};
+use crate::interpreter::{self, Stop};
+use crate::limits;
+use crate::loader::Loader;
+
+pub use crate::interpreter::Backend;
+
+// The name of the code given to the interpreter in the errors.
+const SOURCE: &str = "<embedded>";
+
+// The name of the calls made by `Interpreter::call`, in the calls that led to an error.
+const CALLER: &str = "<call>";
+
+/// An interpreter running code given by the program embedding it, see `eval_source`.
+pub struct Interpreter {
+    context: Context,
+    stack_size: Option<usize>, // The bytes of the stack the code can use, when the program gave them.
+    call_site: Location,       // Where the functions called by `call` are called from.
+}
+
+impl Interpreter {
+    /// Creates an interpreter running the code with the tree-walker, allowing the default number
+    /// of nested calls, with the built-ins that don't need an `include`.
+    pub fn new() -> Self {
+        Interpreter::with(context::DEFAULT_MAX_DEPTH, Backend::Tree)
+    }
+
+    /// Creates an interpreter allowing at most `max_depth` nested calls, running the code with the
+    /// given backend.
+    pub fn with(max_depth: usize, backend: Backend) -> Self {
+        let mut context = interpreter::session(Loader::new(false), max_depth, backend);
+        // The code given is the script running, the files it includes are modules.
+        context.loader().enter(Path::new(SOURCE));
+        let call_site = context.loader().add_code(CALLER, "");
+        Interpreter { context, stack_size: None, call_site }
+    }
+
+    /// Sets the bytes of the stack the code can use below the calls of `eval_source` and `call`,
+    /// which must not be more than what is left of the stack of the thread. Unless it is set, the
+    /// code uses the stack of the thread as the system tells it, or 1 MiB of it, the smallest stack
+    /// of a main thread, on the systems which don't tell it.
+    pub fn set_stack_size(&mut self, size: usize) {
+        self.stack_size = Some(size);
+    }
+
+    // Records the stack the code can use, before running it.
+    fn bound_stack(&self) {
+        match self.stack_size {
+            Some(size) => limits::set_stack(size),
+            None => limits::set_thread_stack(),
+        }
+    }
+
+    /// Runs code in the global scope of the interpreter and returns the value of its last
+    /// statement. After an error, the interpreter can go on with what the code declared before it.
+    pub fn eval_source(&mut self, source: &str) -> Result<Value, Error> {
+        let exprs = self.context.loader().load_source(SOURCE, source).map_err(|error| Error::from_report(&*error))?;
+        self.bound_stack();
+        interpreter::run_statements(exprs, &mut self.context).map(Value).map_err(Error::from_stop)
+    }
+
+    /// Calls a function of the global scope, declared by the code run before, with the arguments
+    /// given as a tuple, and converts the value it returns:
+    ///
+    /// ```
+    /// let mut interpreter = mussel::Interpreter::new();
+    /// interpreter.eval_source("fn fib(n) {\n    if n < 2 { return n }\n    return fib(n - 1) + fib(n - 2)\n}")?;
+    /// let fib: i64 = interpreter.call("fib", (20,))?;
+    /// assert_eq!(fib, 6765);
+    /// # Ok::<(), mussel::Error>(())
+    /// ```
+    ///
+    /// The errors stopping the function are returned like the ones of `eval_source`, as is a value
+    /// which can't be converted.
+    pub fn call<T: FromValue>(&mut self, name: &str, arguments: impl Arguments) -> Result<T, Error> {
+        let arguments = arguments.into_values().into_iter().map(|argument| argument.0).collect();
+        self.bound_stack();
+        let location = self.call_site.clone();
+        let value = interpreter::call_global(name.to_string(), location, arguments, &mut self.context);
+        let value = value.map_err(Error::from_stop)?;
+        let found = value.type_name();
+        T::from_value(Value(value)).ok_or_else(|| Error::Failed {
+            code: None,
+            message: format!("`{name}` returned a value of type `{found}`, expected `{}`", T::TYPE),
+        })
+    }
+
+    /// Returns the value of a variable of the global scope, if it is declared.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.context.get(name).map(Value)
+    }
+
+    /// Returns the variables and functions of the global scope, sorted by name, without the
+    /// built-in functions:
+    ///
+    /// ```
+    /// let mut interpreter = mussel::Interpreter::new();
+    /// interpreter.eval_source("let total = 6\nfn double(n) { return n * 2 }")?;
+    /// let names: Vec<String> = interpreter.variables().into_iter().map(|(name, _)| name).collect();
+    /// assert_eq!(names, ["double", "total"]);
+    /// # Ok::<(), mussel::Error>(())
+    /// ```
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        let [_, globals] = self.context.variables();
+        globals.into_iter().map(|(name, value)| (name, Value(value))).collect()
+    }
+
+    /// Declares a variable in the global scope, or gives it a new value, for the code to read.
+    pub fn set(&mut self, name: &str, value: Value) {
+        self.context.insert(name.to_string(), value.0);
+    }
+
+    /// Declares a function in the global scope, for the code to call with any number of arguments.
+    /// An error returned by the function stops the code, pointing at the call.
+    pub fn register(&mut self, name: &str, function: impl Fn(&[Value]) -> Result<Value, String> + 'static) {
+        let function = move |arguments: Vec<Expr>, context: &mut Context| {
+            let arguments: Vec<Value> = arguments.into_iter().map(Value).collect();
+            match function(&arguments) {
+                Ok(value) => Ok(value.0),
+                Err(message) => context.fail(message),
+            }
+        };
+        let native = Native { name: name.to_string(), function: Box::new(function) };
+        self.context.insert(name.to_string(), Expr::Native(Rc::new(native)));
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+/// A value given by the code, or given to it.
+#[derive(Clone)]
+pub struct Value(Expr);
+
+impl Value {
+    /// The nil value.
+    pub const NIL: Value = Value(Expr::Constant(Atom::Nil));
+
+    /// Returns the name of the type of the value, like `int` or `array`.
+    pub fn type_name(&self) -> String {
+        self.0.type_name()
+    }
+
+    /// Tells whether the value is nil, which is also the value of the statements without one.
+    pub fn is_nil(&self) -> bool {
+        matches!(self.0, Expr::Void | Expr::Constant(Atom::Nil))
+    }
+
+    /// Returns the value of an integer.
+    pub fn as_int(&self) -> Option<i64> {
+        match self.0 {
+            Expr::Constant(Atom::Number(number)) => Some(number),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a float, or of an integer converted to a float, for the programs which
+    /// take both. The arithmetic of the scripts doesn't mix them: `1.5 + 1` is an error.
+    pub fn as_float(&self) -> Option<f64> {
+        match self.0 {
+            Expr::Constant(Atom::Float(number)) => Some(number),
+            Expr::Constant(Atom::Number(number)) => Some(number as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.0 {
+            Expr::Constant(Atom::Boolean(boolean)) => Some(boolean),
+            _ => None,
+        }
+    }
+
+    /// Returns the text of a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.0 {
+            Expr::Constant(Atom::String(string)) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements of an array.
+    pub fn as_array(&self) -> Option<Vec<Value>> {
+        match &self.0 {
+            Expr::Array(elements) => Some(elements.iter().cloned().map(Value).collect()),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of elements of an array, without copying them like `as_array`.
+    pub fn array_len(&self) -> Option<usize> {
+        match &self.0 {
+            Expr::Array(elements) => Some(elements.len()),
+            _ => None,
+        }
+    }
+
+    /// Returns an element of an array, without copying the others like `as_array`.
+    pub fn element(&self, index: usize) -> Option<Value> {
+        match &self.0 {
+            Expr::Array(elements) => elements.get(index).cloned().map(Value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a field of a struct instance.
+    pub fn field(&self, name: &str) -> Option<Value> {
+        match &self.0 {
+            Expr::Instance(_, fields) => {
+                fields.iter().find(|(field, _)| field == name).map(|(_, value)| Value(value.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the fields of a struct instance, with their values, in the order of the struct.
+    pub fn fields(&self) -> Option<Vec<(String, Value)>> {
+        match &self.0 {
+            Expr::Instance(_, fields) => {
+                Some(fields.iter().map(|(name, value)| (name.clone(), Value(value.clone()))).collect())
+            }
+            _ => None,
+        }
+    }
+}
+
+// A value is shown like `println` shows it.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Value({}: {})", self.0, self.type_name())
+    }
+}
+
+impl From<i32> for Value {
+    fn from(number: i32) -> Self {
+        Value(Expr::Constant(Atom::Number(number.into())))
+    }
+}
+
+impl From<i64> for Value {
+    fn from(number: i64) -> Self {
+        Value(Expr::Constant(Atom::Number(number)))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(number: f64) -> Self {
+        Value(Expr::Constant(Atom::Float(number)))
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Self {
+        Value(Expr::Constant(Atom::Boolean(boolean)))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Value(Expr::Constant(Atom::String(string.into())))
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value(Expr::Constant(Atom::String(string.into())))
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(elements: Vec<Value>) -> Self {
+        Value(Expr::Array(elements.into_iter().map(|element| element.0).collect::<Vec<Expr>>().into()))
+    }
+}
+
+/// The arguments of a function called by `Interpreter::call`: a tuple of values converted to
+/// `Value`, like `(30,)` or `("name", 2.5)`, or a `Vec<Value>`.
+pub trait Arguments {
+    /// Converts the arguments, in order.
+    fn into_values(self) -> Vec<Value>;
+}
+
+impl Arguments for () {
+    fn into_values(self) -> Vec<Value> {
+        Vec::new()
+    }
+}
+
+impl Arguments for Vec<Value> {
+    fn into_values(self) -> Vec<Value> {
+        self
+    }
+}
+
+macro_rules! arguments {
+    ($($index:tt $name:ident),+) => {
+        impl<$($name: Into<Value>),+> Arguments for ($($name,)+) {
+            fn into_values(self) -> Vec<Value> {
+                vec![$(self.$index.into()),+]
+            }
+        }
+    };
+}
+
+arguments!(0 A);
+arguments!(0 A, 1 B);
+arguments!(0 A, 1 B, 2 C);
+arguments!(0 A, 1 B, 2 C, 3 D);
+arguments!(0 A, 1 B, 2 C, 3 D, 4 E);
+arguments!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+arguments!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+arguments!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+
+/// The types a value returned by `Interpreter::call` converts to.
+pub trait FromValue: Sized {
+    /// The type of the values converted, as written in a function signature, for the errors.
+    const TYPE: &'static str;
+
+    /// Converts a value, or returns `None` when it is of another type.
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+impl FromValue for Value {
+    const TYPE: &'static str = "any";
+
+    fn from_value(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl FromValue for () {
+    const TYPE: &'static str = "nil";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.is_nil().then_some(())
+    }
+}
+
+impl FromValue for i64 {
+    const TYPE: &'static str = "int";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_int()
+    }
+}
+
+impl FromValue for f64 {
+    const TYPE: &'static str = "float";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_float()
+    }
+}
+
+impl FromValue for bool {
+    const TYPE: &'static str = "bool";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_bool()
+    }
+}
+
+impl FromValue for String {
+    const TYPE: &'static str = "string";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_str().map(str::to_string)
+    }
+}
+
+// An array converts when all of its elements do.
+impl<T: FromValue> FromValue for Vec<T> {
+    const TYPE: &'static str = "array";
+
+    fn from_value(value: Value) -> Option<Self> {
+        value.as_array()?.into_iter().map(T::from_value).collect()
+    }
+}
+
+// Nil converts to `None`, the other values to `Some` when they convert.
+impl<T: FromValue> FromValue for Option<T> {
+    const TYPE: &'static str = T::TYPE;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value.is_nil() {
+            true => Some(None),
+            false => T::from_value(value).map(Some),
+        }
+    }
+}
+
+/// What stopped code run by an `Interpreter`.
+#[derive(Debug)]
+pub enum Error {
+    /// An error found in the code before it ran, like a syntax error, or which stopped it, with its
+    /// code, like `E1002`, see `mussel explain`. The messages of several errors are on several lines.
+    Failed { code: Option<String>, message: String },
+    /// The code called `exit` with this status.
+    Exit(u8),
+}
+
+impl Error {
+    fn from_stop(stop: Stop) -> Self {
+        match stop {
+            Stop::Raised(error) => Error::from_report(&*error),
+            Stop::Exit(status) => Error::Exit(status),
+        }
+    }
+
+    // Takes the code and the messages of the errors reported, leaving out the notes, like the calls
+    // that led to an error.
+    fn from_report(error: &dyn LError) -> Self {
+        let errors = error.report().into_iter().filter(|diagnostic| diagnostic.severity == Severity::Error);
+        let errors: Vec<_> = errors.collect();
+        let code = errors.first().and_then(|diagnostic| diagnostic.code.clone());
+        let messages: Vec<String> = errors.into_iter().map(|diagnostic| diagnostic.message).collect();
+        Error::Failed { code, message: messages.join("\n") }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Failed { code: Some(code), message } => write!(f, "error[{code}]: {message}"),
+            Error::Failed { code: None, message } => write!(f, "error: {message}"),
+            Error::Exit(status) => write!(f, "exited with status {status}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
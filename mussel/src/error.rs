@@ -6,11 +6,16 @@ use std::ops::Range;
 use std::path::{Path, PathBuf};
 use codespan_reporting::{
     diagnostic::{Diagnostic, Label},
-    files::SimpleFiles,
+    files::{Files, SimpleFiles},
     term,
 };
-use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+use codespan_reporting::term::termcolor::{NoColor, StandardStream};
+use crate::color;
 use crate::lexer::{TokenRecord};
+use crate::limits::Limit;
+use crate::output;
+use crate::sarif;
+use crate::warnings;
 
 /// Represents a set of files and their content.
 /// Only one of these should exist at a time.
@@ -34,15 +39,21 @@ impl FileSet {
         self.files.get(id.0).ok().map(|r| r.source().as_ref())
     }
 
-    pub fn get_path(&self, id: FileIdentifier) -> Option<&FilePath> {
-        self.files.get(id.0).ok().map(|r| r.name())
+    pub fn get_path(&self, id: FileIdentifier) -> Option<&Path> {
+        self.files.get(id.0).ok().map(|r| r.name().as_ref())
     }
 
+    /// Returns the path of a file, and the line and the column of an offset in it, counted from 1.
+    pub(crate) fn position(&self, id: usize, offset: usize) -> Option<(&Path, usize, usize)> {
+        let location = self.files.location(id, offset).ok()?;
+        let path = self.files.get(id).ok()?.name().as_ref();
+        Some((path, location.line_number, location.column_number))
+    }
 }
 
 /// File identifier used to lookup files in the `FileSet`.
 /// Every file identifier points to a valid file.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct FileIdentifier(usize);
 
 /// Wrapper for a file path.
@@ -74,7 +85,7 @@ impl Display for FilePath {
 /// Error reporter to print errors to stderr.
 ///
 /// Usage:
-/// ```
+/// ```ignore
 /// let files = FileSet::new();
 /// let file_id = files.add_file("example.mus", "let x = 42;".to_string());
 ///
@@ -85,7 +96,7 @@ pub struct Reporter {
     files: FileSet,
     config: term::Config,
 
-    /// Writer to output the errors to (stderr).
+    /// Writer to output the errors to (stderr), in color when it was chosen, see `color`.
     writer: StandardStream,
 }
 
@@ -95,30 +106,48 @@ impl Reporter {
         Reporter {
             files,
             config,
-            writer: StandardStream::stderr(ColorChoice::Always),
+            writer: StandardStream::stderr(color::choice()),
         }
     }
 
     pub fn report<T : LError>(self, error: T) {
         let diagnostics = error.report();
         for diagnostic in diagnostics {
-            term::emit(
-                &mut self.writer.lock(),
-                &self.config,
-                &self.files.files,
-                &diagnostic,
-            ).expect("Error emitting diagnostic");
+            emit(&self.writer, &self.config, &self.files, &diagnostic);
         }
 
     }
 }
 
+/// Prints warnings to stderr along with the code they point at. Unlike `Reporter::report`, the
+/// files are kept, to report the errors found later.
+pub fn warn<T: LError>(files: &FileSet, warning: &T) {
+    let writer = StandardStream::stderr(color::choice());
+    for diagnostic in warning.report() {
+        emit(&writer, &term::Config::default(), files, &diagnostic);
+    }
+}
+
+/// Prints a diagnostic to the writer, or, while the output is captured, adds it without colors to
+/// the captured text, or, while the diagnostics are collected, adds it to them, see `sarif`.
+fn emit(writer: &StandardStream, config: &term::Config, files: &FileSet, diagnostic: &Diagnostic<usize>) {
+    if sarif::collecting() {
+        sarif::add(files, diagnostic);
+    } else if output::capturing() {
+        let mut buffer = NoColor::new(Vec::new());
+        term::emit(&mut buffer, config, &files.files, diagnostic).expect("Error emitting diagnostic");
+        output::print(&String::from_utf8_lossy(&buffer.into_inner()));
+    } else {
+        term::emit(&mut writer.lock(), config, &files.files, diagnostic).expect("Error emitting diagnostic");
+    }
+}
+
 /// Base trait for all errors.
 pub trait LError {
     fn report(&self) -> Vec<Diagnostic<usize>>;
 }
 
-pub fn boxed<E: LError + 'static>(err: E) -> Box<dyn LError> {
+pub fn boxed<E: LError + Send + 'static>(err: E) -> Box<dyn LError + Send> {
     Box::new(err)
 }
 
@@ -126,9 +155,6 @@ pub fn boxed<E: LError + 'static>(err: E) -> Box<dyn LError> {
 fn label(file: FileIdentifier, range: Range<usize>) -> Label<usize> {
     Label::primary(file.0, range)
 }
-fn secondary_label(file: FileIdentifier, range: Range<usize>) -> Label<usize> {
-    Label::secondary(file.0, range)
-}
 
 impl LError for Box<dyn LError> {
     fn report(&self) -> Vec<Diagnostic<usize>> {
@@ -136,9 +162,38 @@ impl LError for Box<dyn LError> {
     }
 }
 
+/// An error found while the script runs, like exceeding the recursion limit.
+pub type RuntimeError = Box<dyn LError + Send>;
+
+impl LError for RuntimeError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        (**self).report()
+    }
+}
+
+/// What stops a script while it runs: an error, or a call to `exit` with its status.
+/// It is returned by the functions evaluating the script, up to the one running it, which reports
+/// the error like the errors found before running.
+pub enum Halt {
+    Error(RuntimeError),
+    Exit(u8),
+}
+
+impl<E: LError + Send + 'static> From<E> for Halt {
+    fn from(error: E) -> Self {
+        Halt::Error(Box::new(error))
+    }
+}
+
+/// Stops the script with an error, to be returned by the function finding it: `return raise(error)`,
+/// or `raise(error)?` where a value is expected.
+pub fn raise<T, E: LError + Send + 'static>(error: E) -> Result<T, Halt> {
+    Err(error.into())
+}
+
 /// Used to aggregate multiple errors into a single error.
 pub struct ErrorCollection {
-    errors: Vec<Box<dyn LError>>,
+    errors: Vec<Box<dyn LError + Send>>,
 }
 
 impl ErrorCollection {
@@ -148,7 +203,7 @@ impl ErrorCollection {
         }
     }
 
-    pub fn add_error<T: LError + 'static>(&mut self, error: T) {
+    pub fn add_error<T: LError + Send + 'static>(&mut self, error: T) {
         self.errors.push(Box::new(error));
     }
 }
@@ -181,6 +236,7 @@ impl FileError {
 impl LError for FileError {
     fn report(&self) -> Vec<Diagnostic<usize>> {
         let diagnostic = Diagnostic::error()
+            .with_code("E0005")
             .with_message(self.message.clone())
             .with_notes(vec![format!("File: {}", self.path)]);
         vec![diagnostic]
@@ -200,7 +256,7 @@ impl TokenError {
 
 impl LError for TokenError {
     fn report(&self) -> Vec<Diagnostic<usize>> {
-        let diagnostic = Diagnostic::error().with_message("Unknown symbol");
+        let diagnostic = Diagnostic::error().with_code("E0002").with_message("Unknown symbol");
         vec![
             diagnostic.with_labels(vec![
                 label(self.file, self.index..self.index + 1),
@@ -230,6 +286,7 @@ impl UnexpectedTokenError {
 impl LError for UnexpectedTokenError {
     fn report(&self) -> Vec<Diagnostic<usize>> {
         let diagnostic = Diagnostic::error()
+            .with_code("E0001")
             .with_message(self.message.clone())
             .with_labels(vec![
                 label(self.file, self.record.range()),
@@ -254,6 +311,7 @@ impl UnexpectedEndOfFileError {
 impl LError for UnexpectedEndOfFileError {
     fn report(&self) -> Vec<Diagnostic<usize>> {
         let diagnostic = Diagnostic::error()
+            .with_code("E0003")
             .with_message("Unexpected end of file")
             .with_labels(vec![
                 label(self.file, self.index-1..self.index),
@@ -269,7 +327,7 @@ pub struct NotSupportedOperationError {
     message: String,
 }
 impl NotSupportedOperationError {
-    pub fn new(file: FileIdentifier, record: TokenRecord, message: String) -> Self {
+    pub(crate) fn new(file: FileIdentifier, record: TokenRecord, message: String) -> Self {
         NotSupportedOperationError {
             file,
             record,
@@ -281,10 +339,573 @@ impl NotSupportedOperationError {
 impl LError for NotSupportedOperationError {
     fn report(&self) -> Vec<Diagnostic<usize>> {
         let diagnostic = Diagnostic::error()
+            .with_code("E0004")
             .with_message(self.message.clone())
             .with_labels(vec![
                 label(self.file, self.record.range()),
             ]);
         vec![diagnostic]
     }
-}
\ No newline at end of file
+}
+
+/// The place in a file where an expression was written,
+/// kept in the expressions that can fail while the script runs.
+#[derive(Debug, Clone)]
+pub struct Location {
+    file: FileIdentifier,
+    record: TokenRecord,
+}
+
+impl Location {
+    pub(crate) fn new(file: FileIdentifier, record: TokenRecord) -> Self {
+        Location { file, record }
+    }
+
+    pub fn file(&self) -> FileIdentifier {
+        self.file
+    }
+
+    /// Returns the byte range of the code pointed at.
+    pub fn range(&self) -> Range<usize> {
+        self.record.range()
+    }
+
+    /// Tells whether two locations point at the same code.
+    fn same_place(&self, other: &Location) -> bool {
+        self.file.0 == other.file.0 && self.record.range() == other.record.range()
+    }
+}
+
+/// A function being called while the script runs, with the location of the call.
+/// The functions called by the interpreter itself, like the `next` function of a `for` loop over
+/// an iterator, have no call written in the script.
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub name: String,
+    pub site: Option<Location>,
+}
+
+/// An error raised while functions were being called, reported with the calls that led to it,
+/// the most recent first.
+pub struct TracedError {
+    error: RuntimeError,
+    calls: Vec<CallFrame>,
+}
+
+impl TracedError {
+    pub fn new(error: RuntimeError, calls: Vec<CallFrame>) -> Self {
+        TracedError { error, calls }
+    }
+}
+
+impl LError for TracedError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let mut diagnostics = self.error.report();
+        // Consecutive calls from the same place are collapsed, so deep recursion stays readable.
+        let mut frames: Vec<(usize, usize)> = Vec::new();
+        for (index, call) in self.calls.iter().enumerate() {
+            match frames.last_mut() {
+                Some((last, count))
+                    if self.calls[*last].name == call.name
+                        && match (&self.calls[*last].site, &call.site) {
+                            (Some(a), Some(b)) => a.same_place(b),
+                            (a, b) => a.is_none() && b.is_none(),
+                        } =>
+                {
+                    *last = index;
+                    *count += 1;
+                }
+                _ => frames.push((index, 1)),
+            }
+        }
+        for &(index, count) in frames.iter().rev() {
+            let call = &self.calls[index];
+            // A tail call replaces the call that made it, so only the call site tells who the caller is.
+            let mut diagnostic = Diagnostic::note().with_message(format!("in a call to `{}`", call.name));
+            if let Some(site) = &call.site {
+                let message = match count {
+                    1 => "called here".to_string(),
+                    _ => format!("called here {count} times"),
+                };
+                diagnostic = diagnostic.with_labels(vec![label(site.file, site.record.range()).with_message(message)]);
+            }
+            diagnostics.push(diagnostic);
+        }
+        diagnostics
+    }
+}
+
+/// Raised when a variable declared with `const` is assigned a new value.
+pub struct ConstantAssignmentError {
+    location: Location,
+    name: String,
+}
+
+impl ConstantAssignmentError {
+    pub fn new(location: Location, name: String) -> Self {
+        ConstantAssignmentError { location, name }
+    }
+}
+
+impl LError for ConstantAssignmentError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let diagnostic = Diagnostic::error()
+            .with_code("E1007")
+            .with_message(format!("cannot assign twice to constant `{}`", self.name))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message("cannot assign to a constant"),
+            ])
+            .with_notes(vec![
+                format!("help: declare `{}` with `let` to be able to change it", self.name),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when the condition of an `assert` is false.
+pub struct AssertionError {
+    location: Location,
+    message: Option<String>,
+}
+
+impl AssertionError {
+    pub fn new(location: Location, message: Option<String>) -> Self {
+        AssertionError { location, message }
+    }
+}
+
+impl LError for AssertionError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let message = match &self.message {
+            Some(message) => format!("assertion failed: {message}"),
+            None => "assertion failed".to_string(),
+        };
+        let diagnostic = Diagnostic::error()
+            .with_code("E1011")
+            .with_message(message)
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message("the condition is false"),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when an error thrown with `throw` isn't caught by any `try` block.
+pub struct UncaughtError {
+    location: Location,
+    error: String,
+}
+
+impl UncaughtError {
+    pub fn new(location: Location, error: String) -> Self {
+        UncaughtError { location, error }
+    }
+}
+
+impl LError for UncaughtError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let diagnostic = Diagnostic::error()
+            .with_code("E1016")
+            .with_message(format!("uncaught error: {}", self.error))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message("thrown here and never caught"),
+            ])
+            .with_notes(vec![
+                "help: handle it with `try { ... } catch err { ... }`".to_string(),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when the condition of an `if` or `until` isn't a boolean.
+pub struct ConditionTypeError {
+    location: Location,
+    construct: String,
+    found: String,
+}
+
+impl ConditionTypeError {
+    pub fn new(location: Location, construct: String, found: String) -> Self {
+        ConditionTypeError { location, construct, found }
+    }
+}
+
+impl LError for ConditionTypeError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let diagnostic = Diagnostic::error()
+            .with_code("E1008")
+            .with_message(format!(
+                "mismatched types: the condition of `{}` should be `bool`, found `{}`",
+                self.construct, self.found
+            ))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message("expected `bool`"),
+            ])
+            .with_notes(vec![
+                "help: values aren't converted to booleans, compare them instead, like `n != 0`".to_string(),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when a `break` or `continue` isn't inside a loop, for example in a function called by the loop.
+pub struct LoopControlError {
+    location: Location,
+    keyword: String,
+}
+
+impl LoopControlError {
+    pub fn new(location: Location, keyword: String) -> Self {
+        LoopControlError { location, keyword }
+    }
+}
+
+impl LError for LoopControlError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let diagnostic = Diagnostic::error()
+            .with_code("E1009")
+            .with_message(format!("`{}` outside of a loop", self.keyword))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message(format!("cannot `{}` outside of a loop", self.keyword)),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when a `continue` refers to a label that no enclosing loop has.
+pub struct UndeclaredLabelError {
+    location: Location,
+    label: String,
+}
+
+impl UndeclaredLabelError {
+    pub fn new(location: Location, label: String) -> Self {
+        UndeclaredLabelError { location, label }
+    }
+}
+
+impl LError for UndeclaredLabelError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let diagnostic = Diagnostic::error()
+            .with_code("E1010")
+            .with_message(format!("use of undeclared label `{}`", self.label))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message("no enclosing loop has this label"),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when the file of an `include` can't be found.
+pub struct IncludeError {
+    location: Location,
+    path: PathBuf,
+    searched: Vec<PathBuf>,
+}
+
+impl IncludeError {
+    pub fn new(location: Location, path: PathBuf, searched: Vec<PathBuf>) -> Self {
+        IncludeError { location, path, searched }
+    }
+}
+
+impl LError for IncludeError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let searched = self
+            .searched
+            .iter()
+            .map(|path| format!("  {}", path.display()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let diagnostic = Diagnostic::error()
+            .with_code("E0006")
+            .with_message(format!("couldn't find `{}` to include", self.path.display()))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range()).with_message("included here"),
+            ])
+            .with_notes(vec![
+                format!("tried:\n{searched}"),
+                "help: add the directory of the file to `MUSSEL_PATH`".to_string(),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when a file includes itself, directly or through the files it includes.
+pub struct CircularIncludeError {
+    location: Location,
+    cycle: Vec<PathBuf>,
+}
+
+impl CircularIncludeError {
+    pub fn new(location: Location, cycle: Vec<PathBuf>) -> Self {
+        CircularIncludeError { location, cycle }
+    }
+}
+
+impl LError for CircularIncludeError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let included = self.cycle.last().expect("a cycle holds at least the file included twice");
+        let cycle = self
+            .cycle
+            .windows(2)
+            .map(|pair| format!("  `{}` includes `{}`", pair[0].display(), pair[1].display()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let diagnostic = Diagnostic::error()
+            .with_code("E0007")
+            .with_message(format!("circular include of `{}`", included.display()))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message("this file is already being included"),
+            ])
+            .with_notes(vec![format!("the include chain loops:\n{cycle}")]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when an `impl` block for a trait doesn't declare all the methods of the trait.
+pub struct MissingMethodsError {
+    location: Location,
+    trait_name: String,
+    struct_name: String,
+    missing: Vec<String>,
+}
+
+impl MissingMethodsError {
+    pub fn new(location: Location, trait_name: String, struct_name: String, missing: Vec<String>) -> Self {
+        MissingMethodsError { location, trait_name, struct_name, missing }
+    }
+}
+
+impl LError for MissingMethodsError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let missing: Vec<String> = self.missing.iter().map(|method| format!("`{method}`")).collect();
+        let diagnostic = Diagnostic::error()
+            .with_code("E1014")
+            .with_message(format!(
+                "not all methods of trait `{}` are implemented for `{}`",
+                self.trait_name, self.struct_name
+            ))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message(format!("missing {}", missing.join(", "))),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised by the type checker, enabled with `--check-types`, before the script runs.
+/// The range is the expression with the wrong type, when it is known.
+pub struct TypeCheckError {
+    file: FileIdentifier,
+    range: Option<Range<usize>>,
+    message: String,
+}
+
+impl TypeCheckError {
+    pub fn new(file: FileIdentifier, range: Option<Range<usize>>, message: String) -> Self {
+        TypeCheckError { file, range, message }
+    }
+}
+
+impl LError for TypeCheckError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let labels = self.range.iter().map(|range| label(self.file, range.clone())).collect();
+        vec![Diagnostic::error().with_code("E2001").with_message(self.message.clone()).with_labels(labels)]
+    }
+}
+
+/// Found by the analysis of a script before it runs, like a variable that is never read.
+/// A warning doesn't stop the script, unless the warnings are denied, see `warnings`.
+pub struct AnalysisWarning {
+    file: FileIdentifier,
+    range: Option<Range<usize>>,
+    message: String,
+    label: String,
+    help: Option<String>,
+    // The name of the warning, like the rule of `mussel lint` which found it, shown with the message.
+    rule: Option<&'static str>,
+}
+
+impl AnalysisWarning {
+    pub fn new(file: FileIdentifier, range: Option<Range<usize>>, message: String, label: String, help: Option<String>) -> Self {
+        AnalysisWarning { file, range, message, label, help, rule: None }
+    }
+
+    pub fn with_rule(self, rule: &'static str) -> Self {
+        AnalysisWarning { rule: Some(rule), ..self }
+    }
+
+    // Where the warning points in its file, to show the warnings in order.
+    pub fn start(&self) -> usize {
+        self.range.as_ref().map_or(0, |range| range.start)
+    }
+}
+
+impl LError for AnalysisWarning {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let labels = self.range.iter().map(|range| label(self.file, range.clone()).with_message(self.label.clone()));
+        let notes = self.help.iter().map(|help| format!("help: {help}"));
+        // With `--deny-warnings`, the warnings are reported as errors.
+        let diagnostic = if warnings::denied() { Diagnostic::error() } else { Diagnostic::warning() };
+        let diagnostic = diagnostic.with_message(self.message.clone());
+        let diagnostic = match self.rule {
+            Some(rule) => diagnostic.with_code(rule),
+            None => diagnostic,
+        };
+        vec![diagnostic.with_labels(labels.collect()).with_notes(notes.collect())]
+    }
+}
+
+/// Raised when a value doesn't have the type written in a function signature.
+pub struct TypeMismatchError {
+    location: Location,
+    subject: String,
+    expected: String,
+    found: String,
+}
+
+impl TypeMismatchError {
+    pub fn new(location: Location, subject: String, expected: String, found: String) -> Self {
+        TypeMismatchError { location, subject, expected, found }
+    }
+}
+
+impl LError for TypeMismatchError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let diagnostic = Diagnostic::error()
+            .with_code("E1012")
+            .with_message(format!(
+                "mismatched types: {} should be `{}`, found `{}`",
+                self.subject, self.expected, self.found
+            ))
+            .with_labels(vec![
+                label(self.location.file, self.location.record.range())
+                    .with_message(format!("expected `{}` because of this type", self.expected)),
+            ]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when an expression can't be evaluated, like a call to a function that doesn't exist or a
+/// division by zero. Holds what went wrong and the label shown under the expression, and the code
+/// of the kind of error, see `explain`.
+pub struct EvaluationError {
+    location: Option<Location>,
+    message: String,
+    label: String,
+    code: &'static str,
+}
+
+impl EvaluationError {
+    pub fn new(location: Location, message: String, label: String) -> Self {
+        EvaluationError { location: Some(location), message, label, code: "E1000" }
+    }
+
+    // The error found where the script has no code to point at, like in a built-in run by the
+    // interpreter itself.
+    pub fn unlocated(message: String) -> Self {
+        EvaluationError { location: None, message, label: String::new(), code: "E1000" }
+    }
+
+    pub fn with_code(self, code: &'static str) -> Self {
+        EvaluationError { code, ..self }
+    }
+}
+
+impl LError for EvaluationError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let labels = self
+            .location
+            .iter()
+            .map(|location| label(location.file, location.record.range()).with_message(self.label.clone()))
+            .collect();
+        let diagnostic = Diagnostic::error().with_code(self.code).with_message(self.message.clone()).with_labels(labels);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when a script goes past one of its limits, set by `--max-steps`, `--max-memory` or
+/// `--timeout`, see `limits`.
+pub struct LimitError {
+    limit: Limit,
+    site: Option<Location>,
+}
+
+impl LimitError {
+    pub fn new(limit: Limit, site: Option<Location>) -> Self {
+        LimitError { limit, site }
+    }
+}
+
+impl LError for LimitError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        let (message, option) = match &self.limit {
+            Limit::Steps(steps) => (format!("the script ran more than {steps} steps"), "--max-steps"),
+            Limit::Memory(memory) => (format!("the script used more than {memory} of memory"), "--max-memory"),
+            Limit::Time(timeout) => (format!("the script ran for more than {timeout}"), "--timeout"),
+        };
+        let labels = self
+            .site
+            .iter()
+            .map(|site| label(site.file, site.record.range()).with_message("while running this call"))
+            .collect();
+        let diagnostic = Diagnostic::error()
+            .with_code("E1015")
+            .with_message(format!("execution limit exceeded: {message}"))
+            .with_labels(labels)
+            .with_notes(vec![format!("help: use `{option}` to raise the limit")]);
+        vec![diagnostic]
+    }
+}
+
+/// Raised when nested function calls go deeper than the configured limit, or than the stack of the
+/// interpreter allows.
+/// Holds the names of the functions on the call stack, the outermost first.
+pub struct RecursionError {
+    limit: usize,
+    site: Option<Location>,
+    stack: bool, // Whether the stack is full, with `limit` calls, before the limit was reached.
+}
+
+impl RecursionError {
+    pub fn new(limit: usize, site: Option<Location>) -> Self {
+        RecursionError { limit, site, stack: false }
+    }
+
+    // The error raised when the stack of the interpreter is full, with `depth` nested calls.
+    pub fn stack(depth: usize, site: Option<Location>) -> Self {
+        RecursionError { limit: depth, site, stack: true }
+    }
+}
+
+impl LError for RecursionError {
+    fn report(&self) -> Vec<Diagnostic<usize>> {
+        // The functions being called are shown by the backtrace of the error.
+        let labels = self
+            .site
+            .iter()
+            .map(|site| label(site.file, site.record.range()).with_message("one call too many"))
+            .collect();
+        let (message, help) = match self.stack {
+            false => (format!("{} nested calls", self.limit), "use `--max-depth` to raise the limit"),
+            true => (
+                format!("{} nested calls fill the stack", self.limit),
+                "use `--max-stack` to give the interpreter a larger stack",
+            ),
+        };
+        let diagnostic = Diagnostic::error()
+            .with_code("E1013")
+            .with_message(format!("maximum recursion depth exceeded ({message})"))
+            .with_labels(labels)
+            .with_notes(vec![format!("help: {help}")]);
+        vec![diagnostic]
+    }
+}
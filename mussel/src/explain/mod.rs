@@ -0,0 +1,66 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The codes of the errors, shown with them like `error[E1002]`, and `mussel explain`, which
+// describes the error of a code with an example of code raising it and the way to fix it.
+// The codes don't change from one version to the next, so they can be looked up: `E0` for the
+// errors of the files and their syntax, `E1` for the errors raised while the script runs and `E2`
+// for the errors of the type checker. The warnings have their name as code instead, see `warnings`.
+
+use std::process::ExitCode;
+use crate::warnings::{self, WARNINGS};
+
+/// The codes of the errors, with what each one means and its explanation.
+pub const CODES: [(&str, &str, &str); 26] = [
+    ("E0001", "unexpected token", include_str!("E0001.md")),
+    ("E0002", "unknown symbol", include_str!("E0002.md")),
+    ("E0003", "unexpected end of file", include_str!("E0003.md")),
+    ("E0004", "unsupported syntax", include_str!("E0004.md")),
+    ("E0005", "unreadable file", include_str!("E0005.md")),
+    ("E0006", "included file not found", include_str!("E0006.md")),
+    ("E0007", "circular include", include_str!("E0007.md")),
+    ("E1000", "invalid operation", include_str!("E1000.md")),
+    ("E1001", "unknown name", include_str!("E1001.md")),
+    ("E1002", "unknown function", include_str!("E1002.md")),
+    ("E1003", "unknown field", include_str!("E1003.md")),
+    ("E1004", "wrong number of arguments", include_str!("E1004.md")),
+    ("E1005", "index out of bounds", include_str!("E1005.md")),
+    ("E1006", "division by zero", include_str!("E1006.md")),
+    ("E1007", "assignment to a constant", include_str!("E1007.md")),
+    ("E1008", "condition not a `bool`", include_str!("E1008.md")),
+    ("E1009", "`break` or `continue` outside of a loop", include_str!("E1009.md")),
+    ("E1010", "undeclared label", include_str!("E1010.md")),
+    ("E1011", "assertion failed", include_str!("E1011.md")),
+    ("E1012", "mismatched types in a signature", include_str!("E1012.md")),
+    ("E1013", "maximum recursion depth exceeded", include_str!("E1013.md")),
+    ("E1014", "missing methods of a trait", include_str!("E1014.md")),
+    ("E1015", "execution limit exceeded", include_str!("E1015.md")),
+    ("E1016", "uncaught error", include_str!("E1016.md")),
+    ("E1017", "integer overflow", include_str!("E1017.md")),
+    ("E2001", "type error", include_str!("E2001.md")),
+];
+
+// Prints the explanation of an error code, or of a warning from its name. Fails when nothing has
+// this code.
+pub fn explain(code: &str) -> ExitCode {
+    let code = code.trim();
+    if let Some((code, title, explanation)) = CODES.iter().find(|(id, _, _)| id.eq_ignore_ascii_case(code)) {
+        println!("{code}: {title}\n\n{}", explanation.trim_end());
+        return ExitCode::SUCCESS;
+    }
+    if let Some(index) = WARNINGS.iter().position(|(id, _, _)| *id == code) {
+        let (name, enabled, description) = WARNINGS[index];
+        let found_by = match index < warnings::ANALYSIS {
+            true => "of the analysis done before a script runs",
+            false => "of `mussel lint`",
+        };
+        let default = if enabled { "on" } else { "off" };
+        println!("{name}: a warning {found_by}, {default} by default, for {description}.");
+        println!("\nIt is turned on with `-W {name}`, and off with `-A {name}` or, for a line, with a");
+        println!("`// mussel:allow({name})` comment on it or on the line before it.");
+        return ExitCode::SUCCESS;
+    }
+    let codes: Vec<&str> = CODES.iter().map(|(id, _, _)| *id).collect();
+    eprintln!("error: unknown error code `{code}`, expected one of {} or the name of a warning", codes.join(", "));
+    ExitCode::FAILURE
+}
@@ -1,6 +1,12 @@
 use std::fmt;
-use crate::error::{FileIdentifier, FileSet, NotSupportedOperationError};
-use crate::parser::Expression;
+use std::ops::Range;
+use std::rc::Rc;
+use crate::context::Environment;
+use crate::error::{FileIdentifier, FileSet, Location, NotSupportedOperationError};
+use crate::lexer;
+use crate::lexer::{Token, TokenRecord};
+use crate::parser;
+use crate::parser::{BindingExpression, Expression, ParameterExpression, PatternExpression, UnaryOperator};
 
 // Define the `Atom` enum representing the basic literal values in the language.
 #[derive(Debug, Clone, PartialEq)]
@@ -8,8 +14,9 @@ pub enum Atom {
     Number(i64),   // Represents an integer.
     Float(f64),    // Represents a floating-point number.
     Boolean(bool), // Represents a boolean value.
-    Name(String),  // Represents an identifier.
-    String(String),// Represents a string literal.
+    String(Rc<str>), // Represents a string, shared by its copies.
+    Char(char),    // Represents a single character.
+    Nil,           // Represents the absence of a value.
 }
 
 // Implement the Display trait for Atom so that it can be converted to a user-friendly string.
@@ -19,8 +26,9 @@ impl fmt::Display for Atom {
             Atom::Number(number) => write!(f, "{number}"), // Write the number.
             Atom::Float(float) => write!(f, "{float}"), // Write the float.
             Atom::Boolean(boolean) => write!(f, "{boolean}"), // Write the boolean.
-            Atom::Name(name) => write!(f, "{name}"), // Write the name.
+            Atom::Nil => write!(f, "nil"), // Write nil.
             Atom::String(string) => write!(f, "{string}"), // Write the string.
+            Atom::Char(c) => write!(f, "{c}"), // Write the character.
         }
     }
 }
@@ -36,6 +44,13 @@ pub enum Operator {
     GreaterThanEqual, // Represents ">="
 }
 
+// Define an enum for the logical operators, which short-circuit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogicOp {
+    And, // Represents "and"
+    Or,  // Represents "or"
+}
+
 
 
 #[derive(Debug, Clone, PartialEq)]
@@ -44,38 +59,296 @@ pub enum BinOp {
     Sub, // -
     Mul, // *
     Div, // /
+    Pow, // **
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnaryOp {
+    Neg, // -
+    Not, // not
+}
 
-// Define an enum for expressions in the language.
+// Define an enum for the types that can be written in function signatures.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,           // int
+    Float,         // float
+    String,        // string
+    Char,          // char
+    Bool,          // bool
+    Nil,           // nil
+    Array,         // array
+    Range,         // range
+    Function,      // function, also matched by built-in functions
+    Named(String), // The name of a struct or an enum.
+}
+
+impl Type {
+    // Returns the type with the given name, names other than the built-in types refer to structs
+    // and enums.
+    pub fn from_name(name: &str) -> Type {
+        match name {
+            "int" => Type::Int,
+            "float" => Type::Float,
+            "string" => Type::String,
+            "char" => Type::Char,
+            "bool" => Type::Bool,
+            "nil" => Type::Nil,
+            "array" => Type::Array,
+            "range" => Type::Range,
+            "function" => Type::Function,
+            name => Type::Named(name.to_string()),
+        }
+    }
+}
+
+// Implement the Display trait for Type, printing it like it is written.
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Char => write!(f, "char"),
+            Type::Bool => write!(f, "bool"),
+            Type::Nil => write!(f, "nil"),
+            Type::Array => write!(f, "array"),
+            Type::Range => write!(f, "range"),
+            Type::Function => write!(f, "function"),
+            Type::Named(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+// Define a struct for a type written in a function signature, with where it was written.
+#[derive(Debug, Clone)]
+pub struct TypeAnnotation {
+    pub ty: Type,
+    pub location: Location, // Used to point at the type when a value doesn't match it.
+}
+
+// Define a struct for the parameters of functions and closures.
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub annotation: Option<TypeAnnotation>, // The type of the argument, checked at each call.
+    pub default: Option<Expr>, // The value used when the argument is omitted, evaluated at each call.
+}
+
+// Define an enum for the alignment of a formatted value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,   // <
+    Right,  // >
+    Center, // ^
+}
+
+// Define a struct for the format specifier of an interpolated value, written after `:` like in
+// Rust: `{price:>8.2}`
 #[derive(Debug, Clone, PartialEq)]
+pub struct FormatSpec {
+    pub fill: char,           // The character used to reach the width, a space by default.
+    pub align: Option<Align>, // Numbers are aligned to the right by default, other values to the left.
+    pub zero: bool,           // Pads numbers with zeros after the sign: `{n:05}`
+    pub width: usize,         // The minimum number of characters.
+    pub precision: Option<usize>, // The digits after the dot of numbers, or the maximum length of other values.
+}
+
+impl FormatSpec {
+    // Parses a format specifier: `[[fill]align][0][width][.precision]`
+    pub fn parse(spec: &str) -> Option<FormatSpec> {
+        let align_of = |c| match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        };
+        let mut chars = spec.chars();
+        let (fill, align, rest) = match (chars.next(), chars.next()) {
+            (Some(fill), Some(align)) if align_of(align).is_some() => {
+                (fill, align_of(align), &spec[fill.len_utf8() + 1..])
+            }
+            (Some(align), _) if align_of(align).is_some() => (' ', align_of(align), &spec[1..]),
+            _ => (' ', None, spec),
+        };
+        let (zero, rest) = match rest.strip_prefix('0') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let digits = |text: &str| text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+        let (width, rest) = rest.split_at(digits(rest));
+        let width = if width.is_empty() { 0 } else { width.parse().ok()? };
+        let precision = match rest.strip_prefix('.') {
+            Some(precision) if !precision.is_empty() && digits(precision) == precision.len() => {
+                Some(precision.parse().ok()?)
+            }
+            None if rest.is_empty() => None,
+            _ => return None,
+        };
+        Some(FormatSpec { fill, align, zero, width, precision })
+    }
+}
+
+// Define an enum for what an `include` loads.
+#[derive(Debug, Clone)]
+pub enum IncludeTarget {
+    Name(String), // `include utils`: a library of the standard library, or else `utils.mus` next to the script.
+    Path(String), // `include "lib/utils.mus"`: a file, relative to the directory of the script.
+}
+
+// Define an enum for the parts of a string with interpolated values.
+#[derive(Debug, Clone)]
+pub enum StringPart {
+    Text(String), // Text written as is.
+    Value(Expr, Option<FormatSpec>), // An expression between `{` and `}`, with its format specifier.
+}
+
+// Define an enum for the variables declared by `let` statements and `for` loops.
+#[derive(Debug, Clone)]
+pub enum Binding {
+    Name(String),        // Binds the whole value to a variable.
+    Wildcard,            // Ignores the value: `_`
+    Array(Vec<Binding>, Location), // Unpacks an array with exactly one element per binding: `[a, b]` or `(a, b)`
+}
+
+// Define an enum for the patterns used by the arms of a `match` expression.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Wildcard,      // Matches any value: `_`
+    Literal(Atom), // Matches a value equal to the literal.
+    Variant(String, String, Option<Vec<Pattern>>), // Matches a variant of an enum and, when given, its values: `Color::Red`, `Result::Ok(value)`
+    Binding(String), // Matches any value and binds it to a variable: `x`
+    Array(Vec<Pattern>), // Matches an array whose elements match the patterns: `[first, ...rest]`
+    Rest(Option<String>), // Inside an array pattern, matches the remaining elements as an array: `...rest`
+    Struct(String, Vec<(String, Pattern)>), // Matches an instance whose fields match the patterns: `Point { x, y: 0 }`
+}
+
+// Define an enum for expressions in the language.
+#[derive(Debug, Clone)]
 pub enum Expr {
     Void, // Represents a no-value or empty expression.
-    Array(Vec<Expr>), // Represents an array of expressions.
+    Array(Rc<Vec<Expr>>), // Represents an array of expressions, shared by its copies until one of them is modified.
     Constant(Atom), // Wraps an Atom literal as an expression.
-    Let(String, Box<Expr>), // A let-binding that associates a name with an expression (boxed to allow recursion).
-    Call(String, Vec<Expr>), // A function call with a name and arguments.
-    Compare(Box<Expr>, Operator, Box<Expr>), // A comparison between two expressions.
-    Closure(Vec<String>, Vec<Expr>), // A closure with parameters and a body of expressions.
-    Function(String, Vec<String>, Vec<Expr>), // A named function definition.
-    If(Box<Expr>, Vec<Expr>, Option<Vec<Expr>>), // An if statement with an optional else branch.
+    Variable(String, Location), // The name of a variable, evaluated to its value.
+    Interpolation(Vec<StringPart>), // A string with interpolated values: `"sum = {a + b}"`
+    Let(Binding, Box<Expr>, Location), // A let-binding that associates names with an expression (boxed to allow recursion), located at `let`.
+    Const(Binding, Box<Expr>, Location), // Like `Let`, but the names can't be assigned a new value.
+    Assign(Box<Expr>, Box<Expr>, Location), // Assigns a new value to an existing variable, array element or struct field.
+    Call(Box<Expr>, Vec<Expr>, Location), // A function call: the expression giving the function and the arguments.
+    MethodCall(Box<Expr>, String, Vec<Expr>, Location), // A method call `receiver.method(args)`.
+    Compare(Box<Expr>, Operator, Box<Expr>, Location), // A comparison between two expressions, located at the operator.
+    Logic(Box<Expr>, LogicOp, Box<Expr>, Location), // A logical `and`/`or`, the right side is evaluated only when needed.
+    Closure(Vec<Parameter>, Option<TypeAnnotation>, Vec<Expr>, Option<Environment>), // A closure with parameters, return type, body and, once evaluated, the environment it was created in.
+    Compiled(Rc<crate::bytecode::Function>, Environment), // A function compiled for the virtual machine, with the environment it was created in.
+    Function(String, Vec<Parameter>, Option<TypeAnnotation>, Vec<Expr>), // A named function definition.
+    If(Box<Expr>, Vec<Expr>, Option<Vec<Expr>>, Location), // An if statement with an optional else branch, and the location of its condition.
     Return(Box<Expr>), // A return expression.
-    For(String, Box<Expr>, Vec<Expr>), // A for loop iterating over a collection.
-    Get(String, usize), // Access an element in an array by name and index.
-    Until(Box<Expr>, Vec<Expr>), // An until loop: execute the body until the condition becomes true.
-    Binary(Box<Expr>, BinOp, Box<Expr>), // Binary arithmetic expression.
-    Include(String),
-    Builtin(fn(Vec<Expr>, &mut std::collections::HashMap<String, Expr>) -> Expr),
+    TailCall(String, Box<Expr>, Vec<Expr>, Location), // A `return f(x)`: the function name, its closure, the evaluated arguments and the location of the call.
+    Throw(Box<Expr>, Location), // Throws an error, propagated like a return until a `try` catches it.
+    Try(Vec<Expr>, String, Vec<Expr>), // A try block, the name bound to the caught error and the catch block.
+    Assert(Box<Expr>, Option<Box<Expr>>, Location), // Stops the script when the condition is false, with an optional message.
+    For(Binding, Box<Expr>, Vec<Expr>, Location, Option<String>), // A for loop iterating over a collection, with the location of the collection and its optional label.
+    Comprehension(Box<Expr>, Binding, Box<Expr>, Location, Option<(Box<Expr>, Location)>), // `[expr for binding in collection if condition]`, builds an array.
+    Get(Box<Expr>, Box<Expr>, Location), // Access an element of an array: the array expression and the index expression.
+    Until(Box<Expr>, Vec<Expr>, Location, Option<String>), // An until loop: execute the body until the condition becomes true.
+    Loop(Vec<Expr>, Option<String>), // A loop running its body until a `break`, the value of the `break` is the value of the loop.
+    Break(Box<Expr>, Option<String>, Location), // Leaves the innermost loop, or the labeled one, with a value; propagated like a return until the loop.
+    Continue(Option<String>, Location), // Starts the next iteration of the innermost loop, or of the labeled one.
+    Binary(Box<Expr>, BinOp, Box<Expr>, Location), // Binary arithmetic expression, located at the operator.
+    Unary(UnaryOp, Box<Expr>, Location), // Unary expression: numeric negation or boolean `not`.
+    Range(Box<Expr>, Box<Expr>, bool, Location), // A range expression `start..end`, the flag is true for `start..=end`.
+    RangeValue(i64, i64, i64, bool), // An evaluated range: start, end, step and whether the end is included.
+    Include(IncludeTarget, Option<String>, Location), // Loads a library of the standard library or a file, in the current scope or in a module with the given name.
+    Match(Box<Expr>, Vec<(Pattern, Vec<Expr>)>), // A match expression: the first arm whose pattern matches is evaluated.
+    Struct(String, Vec<String>, Vec<(String, Expr)>), // A struct definition with its field names and methods, also used as the constructor.
+    Impl(String, Option<String>, Vec<Expr>, Location), // The methods of a struct declared as functions, and the trait they implement.
+    Trait(String, Vec<String>), // A trait with the names of the methods a struct must have to implement it.
+    Instance(String, Vec<(String, Expr)>), // An instance of a struct: the struct name and the value of each field.
+    Field(Box<Expr>, String, Location), // Access a field of a struct instance, located at the field name.
+    Module(String, Vec<(String, Expr)>), // A file included with `include ... as name`: its name and what it declares.
+    Enum(String, Vec<(String, Vec<String>)>), // An enum definition with its variants and the names of the values they carry.
+    Path(String, String, Location), // A path like `Color::Red`, evaluated to the variant it names.
+    Variant(String, String, Vec<Expr>), // A variant value: the enum name, the variant name and the values it carries.
+    Builtin(fn(Vec<Expr>, &mut crate::context::Context) -> crate::interpreter::Eval),
+    Native(Rc<Native>), // A function of the program embedding the interpreter, see `embed`.
+}
+
+// A function given to the interpreter by the program embedding it, with the name it was given.
+pub struct Native {
+    pub name: String,
+    pub function: Box<NativeFn>,
+}
+
+/// The functions given by the program embedding the interpreter, called with the arguments of a call.
+pub type NativeFn = dyn Fn(Vec<Expr>, &mut crate::context::Context) -> crate::interpreter::Eval;
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Native({})", self.name)
+    }
 }
 
 impl Expr {
 
-    pub fn from_parser(files:& FileSet, file: FileIdentifier, parsed: Vec<Expression>)
+    // Returns the name of the type of a value, as it would be written in a function signature.
+    // Struct and enum definitions, which can't be written in a signature, are named `struct` and `enum`.
+    pub fn type_name(&self) -> String {
+        match self {
+            Expr::Constant(Atom::Number(_)) => "int".to_string(),
+            Expr::Constant(Atom::Float(_)) => "float".to_string(),
+            Expr::Constant(Atom::String(_)) => "string".to_string(),
+            Expr::Constant(Atom::Char(_)) => "char".to_string(),
+            Expr::Constant(Atom::Boolean(_)) => "bool".to_string(),
+            Expr::Constant(Atom::Nil) => "nil".to_string(),
+            Expr::Array(_) => "array".to_string(),
+            Expr::RangeValue(..) => "range".to_string(),
+            Expr::Closure(..) | Expr::Compiled(..) | Expr::Builtin(_) | Expr::Native(_) => "function".to_string(),
+            Expr::Instance(name, _) | Expr::Variant(name, _, _) => name.clone(),
+            Expr::Struct(..) => "struct".to_string(),
+            Expr::Enum(..) => "enum".to_string(),
+            Expr::Module(..) => "module".to_string(),
+            Expr::Void => "nothing".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    // Returns where a statement is written, where a debugger pauses before running it. The
+    // statements only declaring something, like a function or a struct, have none.
+    pub fn statement_location(&self) -> Option<&Location> {
+        match self {
+            Expr::Variable(_, location)
+            | Expr::Let(_, _, location)
+            | Expr::Const(_, _, location)
+            | Expr::Assign(_, _, location)
+            | Expr::Call(_, _, location)
+            | Expr::MethodCall(_, _, _, location)
+            | Expr::Compare(_, _, _, location)
+            | Expr::Logic(_, _, _, location)
+            | Expr::If(_, _, _, location)
+            | Expr::Assert(_, _, location)
+            | Expr::For(_, _, _, location, _)
+            | Expr::Comprehension(_, _, _, location, _)
+            | Expr::Get(_, _, location)
+            | Expr::Until(_, _, location, _)
+            | Expr::Break(_, _, location)
+            | Expr::Continue(_, location)
+            | Expr::Binary(_, _, _, location)
+            | Expr::Unary(_, _, location)
+            | Expr::Range(_, _, _, location)
+            | Expr::Include(_, _, location)
+            | Expr::Field(_, _, location)
+            | Expr::Path(_, _, location)
+            | Expr::Throw(_, location) => Some(location),
+            Expr::Return(value) | Expr::Match(value, _) => value.statement_location(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn from_parser(files:& FileSet, file: FileIdentifier, parsed: Vec<Expression>)
                        -> Result<Vec<Expr>, NotSupportedOperationError> {
 
-        let content = files.get_content(file).unwrap_or_else(|| {
-            panic!("Failed to retrieve content");
-        });
+        // Every file identifier points to a file of the set, see `FileIdentifier`.
+        let content = files.get_content(file).unwrap_or_default();
 
         Self::from_parser_block(file, content, parsed)
 
@@ -83,32 +356,136 @@ impl Expr {
 
     fn from_parser_inner(file: FileIdentifier, content: &str, expr: Expression) -> Result<Expr, NotSupportedOperationError> {
         Ok(match expr {
-            Expression::Include { id } => {
-                Expr::Include(id.get_content(content).to_string())
+            Expression::Include { id, alias } => {
+                let location = Location::new(file, id.clone());
+                let alias = alias.map(|alias| alias.get_content(content).to_string());
+                if id.token_type != Token::String {
+                    return Ok(Expr::Include(IncludeTarget::Name(id.get_content(content).to_string()), alias, location));
+                }
+                match Self::from_parser_inner(file, content, Expression::String(id.clone()))? {
+                    Expr::Constant(Atom::String(path)) => Expr::Include(IncludeTarget::Path(path.to_string()), alias, location),
+                    _ => {
+                        return Err(NotSupportedOperationError::new(
+                            file,
+                            id,
+                            "The path of an `include` can't interpolate values".to_string(),
+                        ));
+                    }
+                }
             }
             Expression::Return { expr } =>  {
                 Expr::Return(Box::new(Self::from_parser_inner(file, content, *expr)?))
             }
-            Expression::Function { id, args, block } => {
-                let name = id.get_content(content).to_string();
-                let args = args.iter()
-                    .map(|arg| arg.get_content(content).to_string())
-                    .collect::<Vec<String>>();
+            Expression::Throw { keyword, expr } => {
+                Expr::Throw(Box::new(Self::from_parser_inner(file, content, *expr)?), Location::new(file, keyword))
+            }
+            Expression::Assert { region, expr, message } => {
+                let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
+                let message = match message {
+                    Some(message) => Some(Box::new(Self::from_parser_inner(file, content, *message)?)),
+                    None => None,
+                };
+                Expr::Assert(expr, message, Location::new(file, region))
+            }
+            Expression::Try { block, id, catch_block } => {
                 let body = Self::from_parser_block(file, content, block)?;
-                Expr::Function(name, args, body)
+                let name = id.get_content(content).to_string();
+                let catch_body = Self::from_parser_block(file, content, catch_block)?;
+                Expr::Try(body, name, catch_body)
             }
-            Expression::For { id, expr, block } => {
+            Expression::Function { id, args, return_type, block, .. } => {
                 let name = id.get_content(content).to_string();
+                let args = Self::from_parser_parameters(file, content, args)?;
+                let return_type = return_type.map(|ty| Self::from_parser_type(file, content, ty));
+                let body = Self::from_parser_block(file, content, block)?;
+                Expr::Function(name, args, return_type, body)
+            }
+            Expression::Comprehension { expr, binding, keyword, iterable, condition } => {
+                let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
+                let binding = Self::from_parser_binding(file, content, binding);
+                let location = expression_location(file, &keyword, &iterable);
+                let iterable = Box::new(Self::from_parser_inner(file, content, *iterable)?);
+                let condition = match condition {
+                    Some(condition) => {
+                        let location = expression_location(file, &keyword, &condition);
+                        Some((Box::new(Self::from_parser_inner(file, content, *condition)?), location))
+                    }
+                    None => None,
+                };
+                Expr::Comprehension(expr, binding, iterable, location, condition)
+            }
+            Expression::For { binding, keyword, expr, block } => {
+                let binding = Self::from_parser_binding(file, content, binding);
                 let body = Self::from_parser_block(file, content, block)?;
+                let location = expression_location(file, &keyword, &expr);
                 let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
-                Expr::For(name, expr, body)
+                Expr::For(binding, expr, body, location, None)
             }
-            Expression::Until { expr, block } => {
+            Expression::Until { keyword, expr, block } => {
+                let location = expression_location(file, &keyword, &expr);
                 let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
                 let body = Self::from_parser_block(file, content, block)?;
-                Expr::Until(expr, body)
+                Expr::Until(expr, body, location, None)
+            }
+            Expression::Loop { block } => Expr::Loop(Self::from_parser_block(file, content, block)?, None),
+            Expression::Labeled { label, expr } => {
+                let label = Some(label.get_content(content).to_string());
+                match Self::from_parser_inner(file, content, *expr)? {
+                    Expr::For(binding, collection, body, location, _) => Expr::For(binding, collection, body, location, label),
+                    Expr::Until(condition, body, location, _) => Expr::Until(condition, body, location, label),
+                    Expr::Loop(body, _) => Expr::Loop(body, label),
+                    _ => unreachable!("the parser only labels loops"),
+                }
             }
-            Expression::If { expr, block, else_block } => {
+            // `break outer` can't be told apart from a `break` with the value of a variable yet:
+            // it leaves the loop labeled `outer` if there is one, see the interpreter.
+            Expression::Break { keyword, expr } => {
+                let expr = match expr {
+                    Some(expr) => Self::from_parser_inner(file, content, *expr)?,
+                    None => Expr::Void,
+                };
+                Expr::Break(Box::new(expr), None, Location::new(file, keyword))
+            }
+            Expression::Continue { keyword, label } => {
+                let label = label.map(|label| label.get_content(content).to_string());
+                Expr::Continue(label, Location::new(file, keyword))
+            }
+            Expression::Struct { id, fields, .. } => {
+                let name = id.get_content(content).to_string();
+                let fields = fields.iter()
+                    .map(|field| field.get_content(content).to_string())
+                    .collect::<Vec<String>>();
+                Expr::Struct(name, fields, Vec::new())
+            }
+            Expression::Impl { region, id, trait_id, methods } => {
+                let name = id.get_content(content).to_string();
+                let trait_name = trait_id.map(|id| id.get_content(content).to_string());
+                let methods = Self::from_parser_block(file, content, methods)?;
+                Expr::Impl(name, trait_name, methods, Location::new(file, region))
+            }
+            Expression::Trait { id, methods } => {
+                let name = id.get_content(content).to_string();
+                let methods = methods.iter()
+                    .map(|method| method.get_content(content).to_string())
+                    .collect::<Vec<String>>();
+                Expr::Trait(name, methods)
+            }
+            Expression::Enum { id, variants } => {
+                let name = id.get_content(content).to_string();
+                let variants = variants.iter()
+                    .map(|(variant, fields)| {
+                        let fields = fields.iter().map(|field| field.get_content(content).to_string()).collect();
+                        (variant.get_content(content).to_string(), fields)
+                    })
+                    .collect::<Vec<(String, Vec<String>)>>();
+                Expr::Enum(name, variants)
+            }
+            Expression::Path { id, item } => {
+                let location = spanning(file, &id, Some(item.range()));
+                Expr::Path(id.get_content(content).to_string(), item.get_content(content).to_string(), location)
+            }
+            Expression::If { keyword, expr, block, else_block } => {
+                let location = expression_location(file, &keyword, &expr);
                 let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
                 let body = Self::from_parser_block(file, content, block)?;
                 let else_body = if let Some(else_block) = else_block {
@@ -116,20 +493,28 @@ impl Expr {
                 } else {
                     None
                 };
-                Expr::If(expr, body, else_body)
+                Expr::If(expr, body, else_body, location)
             }
-            Expression::Let { id, expr } => {
-                let name = id.get_content(content).to_string();
+            Expression::Let { keyword, binding, expr } => {
+                let binding = Self::from_parser_binding(file, content, binding);
                 let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
-                Expr::Let(name, expr)
+                Expr::Let(binding, expr, Location::new(file, keyword))
+            }
+            Expression::Const { keyword, binding, expr } => {
+                let binding = Self::from_parser_binding(file, content, binding);
+                let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
+                Expr::Const(binding, expr, Location::new(file, keyword))
             }
             Expression::Binary { left, operator: (operator, token), right } => {
                 let lhs = Box::new(Self::from_parser_inner(file, content, *left)?);
                 let rhs = Box::new(Self::from_parser_inner(file, content, *right)?);
-                return if let Some(binOp) = operator.into() {
-                    Ok(Expr::Binary(lhs, binOp, rhs))
+                let location = Location::new(file, token.clone());
+                return if let Some(bin_op) = operator.into() {
+                    Ok(Expr::Binary(lhs, bin_op, rhs, location))
+                } else if let Some(op) = operator.into() {
+                    Ok(Expr::Compare(lhs, op, rhs, location))
                 } else if let Some(op) = operator.into() {
-                    Ok(Expr::Compare(lhs, op, rhs))
+                    Ok(Expr::Logic(lhs, op, rhs, location))
                 } else {
                     Err(NotSupportedOperationError::new(
                         file,
@@ -138,40 +523,81 @@ impl Expr {
                     ))
                 }
             }
-            Expression::Unary { operator: (_, record), .. } => {
-                return Err(NotSupportedOperationError::new(
-                    file,
-                    record,
-                    "Unary operations are not supported".to_string(),
-                ));
+            Expression::Range { start, operator, end, inclusive } => {
+                let start = Box::new(Self::from_parser_inner(file, content, *start)?);
+                let end = Box::new(Self::from_parser_inner(file, content, *end)?);
+                Expr::Range(start, end, inclusive, Location::new(file, operator))
             }
-            Expression::Assignment { region, .. } => {
-                return Err(NotSupportedOperationError::new(
-                    file,
-                    region,
-                    "Assignment operations are not supported".to_string(),
-                ));
+            // A pipeline is turned into a call: `x |> f` calls `f(x)`, and `x |> f(a)` calls `f(x, a)`.
+            Expression::Pipeline { value, operator, function } => {
+                let location = expression_location(file, &operator, &function);
+                let value = Self::from_parser_inner(file, content, *value)?;
+                match Self::from_parser_inner(file, content, *function)? {
+                    Expr::Call(function, mut args, location) => {
+                        args.insert(0, value);
+                        Expr::Call(function, args, location)
+                    }
+                    Expr::MethodCall(receiver, method, mut args, location) => {
+                        args.insert(0, value);
+                        Expr::MethodCall(receiver, method, args, location)
+                    }
+                    function => Expr::Call(Box::new(function), vec![value], location),
+                }
             }
-            Expression::Identifier(name) => {
-                let name = name.get_content(content).to_string();
-                Expr::Constant(Atom::Name(name))
+            Expression::Unary { operator: (operator, token), expr } => {
+                let op = match operator {
+                    UnaryOperator::Negate => UnaryOp::Neg,
+                    UnaryOperator::Not => UnaryOp::Not,
+                };
+                let location = spanning(file, &token, expr.span());
+                Expr::Unary(op, Box::new(Self::from_parser_inner(file, content, *expr)?), location)
+            }
+            Expression::Assignment { region, left, right } => {
+                let target = Self::from_parser_inner(file, content, *left)?;
+                // Only variables, array elements and struct fields can be assigned to.
+                match target {
+                    Expr::Variable(..) | Expr::Get(..) | Expr::Field(..) => {}
+                    _ => {
+                        return Err(NotSupportedOperationError::new(
+                            file,
+                            region,
+                            "Invalid assignment target".to_string(),
+                        ));
+                    }
+                }
+                let value = Self::from_parser_inner(file, content, *right)?;
+                Expr::Assign(Box::new(target), Box::new(value), Location::new(file, region))
+            }
+            Expression::Identifier(token) => {
+                let name = token.get_content(content).to_string();
+                Expr::Variable(name, Location::new(file, token))
             }
             Expression::String(token) => {
                 let string = token.get_content(content).to_string();
-                if (string.len() < 2) {
+                if string.len() < 2 {
                     return Err(NotSupportedOperationError::new(
                         file,
                         token,
                         format!("Invalid string: {string}"),
                     ));
                 }
-                let string = string[1..string.len() - 1].to_string();
-                Expr::Constant(Atom::String(string))
+                return Self::from_parser_string(file, content, token);
+            }
+            Expression::Char(token) => {
+                let literal = token.get_content(content);
+                return match char_value(&literal[1..literal.len() - 1]) {
+                    Some(c) => Ok(Expr::Constant(Atom::Char(c))),
+                    None => Err(NotSupportedOperationError::new(
+                        file,
+                        token,
+                        format!("Invalid character: {literal}"),
+                    )),
+                };
             }
             Expression::Integer(token) => {
                 let number = token.get_content(content).to_string();
-                return if let Ok(asInt) = number.parse::<i64>() {
-                    Ok(Expr::Constant(Atom::Number(asInt)))
+                return if let Ok(as_int) = number.parse::<i64>() {
+                    Ok(Expr::Constant(Atom::Number(as_int)))
                 } else {
                     Err(NotSupportedOperationError::new(
                         file,
@@ -182,8 +608,8 @@ impl Expr {
             }
             Expression::Float(token) => {
                 let number = token.get_content(content).to_string();
-                return if let Ok(asFloat) = number.parse::<f64>() {
-                    Ok(Expr::Constant(Atom::Float(asFloat)))
+                return if let Ok(as_float) = number.parse::<f64>() {
+                    Ok(Expr::Constant(Atom::Float(as_float)))
                 } else {
                     Err(NotSupportedOperationError::new(
                         file,
@@ -192,10 +618,11 @@ impl Expr {
                     ))
                 }
             }
+            Expression::Nil => Expr::Constant(Atom::Nil),
             Expression::Bool(bool) => {
                 let boolean = bool.get_content(content).to_string();
-                return if let Ok(asBool) = boolean.parse::<bool>() {
-                    Ok(Expr::Constant(Atom::Boolean(asBool)))
+                return if let Ok(as_bool) = boolean.parse::<bool>() {
+                    Ok(Expr::Constant(Atom::Boolean(as_bool)))
                 } else {
                     Err(NotSupportedOperationError::new(
                         file,
@@ -206,57 +633,379 @@ impl Expr {
             }
             Expression::Array(inner) => {
                 let items = Self::from_parser_block(file, content, inner)?;
-                Expr::Array(items)
+                Expr::Array(Rc::new(items))
             }
-            Expression::Closure { args, block } => {
-                let args = args.iter()
-                    .map(|arg| arg.get_content(content).to_string())
-                    .collect::<Vec<String>>();
+            Expression::Closure { args, return_type, block } => {
+                let args = Self::from_parser_parameters(file, content, args)?;
+                let return_type = return_type.map(|ty| Self::from_parser_type(file, content, ty));
                 let body = Self::from_parser_block(file, content, block)?;
-                Expr::Closure(args, body)
+                Expr::Closure(args, return_type, body, None)
+            }
+            Expression::Match { expr, arms } => {
+                let expr = Box::new(Self::from_parser_inner(file, content, *expr)?);
+                let arms = arms.into_iter().map(|(pattern, block)| {
+                    let pattern = Self::from_parser_pattern(file, content, pattern)?;
+                    Ok((pattern, Self::from_parser_block(file, content, block)?))
+                }).collect::<Result<Vec<_>, _>>()?;
+                Expr::Match(expr, arms)
             }
-            Expression::Call { region, left, args } => {
-                let name = Self::from_parser_inner(file, content, *left)?;
+            Expression::Call { left, args, region } => {
+                // The whole call is pointed at, from the function to the closing parenthesis.
+                let location = spanning(file, &region, left.span());
+                // Any expression can be called, as long as it evaluates to a function.
+                let function = Self::from_parser_inner(file, content, *left)?;
                 let args = Self::from_parser_block(file, content, args)?;
-                return if let Expr::Constant(Atom::Name(name)) = name {
-                    Ok(Expr::Call(name.to_string(), args))
-                } else {
-                    return Err(NotSupportedOperationError::new(
-                        file,
-                        region,
-                        format!("Invalid function call, only call a function directly yet: {name}"),
-                    ));
+                match function {
+                    // Calling a field is a method call: `s.uppercase()`.
+                    Expr::Field(receiver, method, _) => Expr::MethodCall(receiver, method, args, location),
+                    function => Expr::Call(Box::new(function), args, location),
                 }
             }
-            Expression::Index { region, left, index } => {
-                let name = Self::from_parser_inner(file, content, *left)?;
+            Expression::Index { left, index, region } => {
+                let location = spanning(file, &region, left.span());
+                let array = Self::from_parser_inner(file, content, *left)?;
                 let index = Self::from_parser_inner(file, content, *index)?;
-                return if let Expr::Constant(Atom::Name(name)) = name {
-                    if let Expr::Constant(Atom::Number(index)) = index {
-                        Ok(Expr::Get(name.to_string(), index as usize))
-                    } else {
-                        Err(NotSupportedOperationError::new(
+                Expr::Get(Box::new(array), Box::new(index), location)
+            }
+            Expression::Field { left, field } => {
+                let left = Self::from_parser_inner(file, content, *left)?;
+                let name = field.get_content(content).to_string();
+                Expr::Field(Box::new(left), name, Location::new(file, field))
+            }
+        })
+    }
+    fn from_parser_block(file: FileIdentifier, content: &str, block: Vec<Expression>) -> Result<Vec<Expr>, NotSupportedOperationError> {
+        block.into_iter().map(|expr| {
+            Self::from_parser_inner(file, content, expr)
+        }).collect()
+    }
+
+    // Converts a string literal.
+    // The expressions between `{` and `}` are parsed here like the rest of the code, and evaluated
+    // every time the string is. `{{` and `}}` are literal braces.
+    fn from_parser_string(file: FileIdentifier, content: &str, token: TokenRecord)
+                          -> Result<Expr, NotSupportedOperationError> {
+        let raw = token.get_content(content);
+        // The text of the string, with the offset in the file of each of its bytes.
+        let (text, offsets) = if raw.len() >= 6 && raw.starts_with("\"\"\"") {
+            strip_indentation(&raw[3..raw.len() - 3], token.offset + 3)
+        } else {
+            (raw[1..raw.len() - 1].to_string(), (token.offset + 1..token.offset + raw.len() - 1).collect())
+        };
+        // An error pointing at the bytes of the text between `start` and `end`.
+        let error = |start: usize, end: usize, message: &str| {
+            let offset = offsets[start];
+            let length = offsets[end.max(start + 1) - 1] + 1 - offset;
+            let record = TokenRecord { token_type: Token::String, offset, length, after_newline: false };
+            NotSupportedOperationError::new(file, record, message.to_string())
+        };
+
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((open, c)) = chars.next() {
+            match c {
+                '{' if chars.next_if(|(_, c)| *c == '{').is_some() => literal.push('{'),
+                '}' if chars.next_if(|(_, c)| *c == '}').is_some() => literal.push('}'),
+                '{' => {
+                    let Some(close) = placeholder_end(&text, open + 1) else {
+                        return Err(error(open, open + 1, "Unclosed `{` in string, write `{{` for a literal brace"));
+                    };
+                    while chars.next_if(|(i, _)| *i <= close).is_some() {}
+                    let placeholder = &text[open + 1..close];
+                    let (source, spec) = match format_spec_start(placeholder) {
+                        Some(colon) => (&placeholder[..colon], Some(&placeholder[colon + 1..])),
+                        None => (placeholder, None),
+                    };
+                    if source.trim().is_empty() {
+                        return Err(error(open, close + 1, "Missing expression between `{` and `}`"));
+                    }
+                    // The expression is lexed and parsed from the file, so errors in it point at it.
+                    let (start, end) = (open + 1, open + 1 + source.len());
+                    let expression = lexer::lex_range(content, offsets[start]..offsets[end - 1] + 1)
+                        .and_then(|tokens| parser::parse_expression(&tokens))
+                        .ok_or_else(|| error(start, end, "Invalid expression in string interpolation"))?;
+                    let spec = match spec {
+                        Some(spec) => Some(FormatSpec::parse(spec).ok_or_else(|| {
+                            error(end + 1, close, &format!("Invalid format specifier `{spec}`"))
+                        })?),
+                        None => None,
+                    };
+                    if !literal.is_empty() {
+                        parts.push(StringPart::Text(std::mem::take(&mut literal)));
+                    }
+                    parts.push(StringPart::Value(Self::from_parser_inner(file, content, expression)?, spec));
+                }
+                c => literal.push(c),
+            }
+        }
+        if parts.is_empty() {
+            return Ok(Expr::Constant(Atom::String(literal.into())));
+        }
+        if !literal.is_empty() {
+            parts.push(StringPart::Text(literal));
+        }
+        Ok(Expr::Interpolation(parts))
+    }
+
+    // Converts the pattern of a `match` arm.
+    fn from_parser_pattern(file: FileIdentifier, content: &str, pattern: PatternExpression)
+                           -> Result<Pattern, NotSupportedOperationError> {
+        Ok(match pattern {
+            PatternExpression::Wildcard => Pattern::Wildcard,
+            PatternExpression::Literal(literal) => {
+                let token = match &*literal {
+                    Expression::String(token) => Some(token.clone()),
+                    _ => None,
+                };
+                match Self::from_parser_inner(file, content, *literal)? {
+                    Expr::Constant(atom) => Pattern::Literal(atom),
+                    Expr::Path(name, variant, _) => Pattern::Variant(name, variant, None),
+                    // Negative numbers are parsed as a negation of the literal.
+                    Expr::Unary(UnaryOp::Neg, literal, _) => match *literal {
+                        Expr::Constant(Atom::Number(n)) => Pattern::Literal(Atom::Number(-n)),
+                        Expr::Constant(Atom::Float(n)) => Pattern::Literal(Atom::Float(-n)),
+                        _ => unreachable!("the parser only negates number patterns"),
+                    },
+                    // A pattern is compared as it is written, it can't depend on other values.
+                    Expr::Interpolation(_) => {
+                        return Err(NotSupportedOperationError::new(
                             file,
-                            region,
-                            format!("Invalid index, not supported yet: {index}"),
-                        ))
+                            token.expect("only strings are interpolated"),
+                            "A string used as pattern can't interpolate values, write `{{` for a literal brace".to_string(),
+                        ));
                     }
-                } else {
-                    Err(NotSupportedOperationError::new(
+                    _ => unreachable!("the parser only produces literal patterns"),
+                }
+            }
+            PatternExpression::Variant(path, patterns) => {
+                let Expression::Path { id, item } = *path else {
+                    unreachable!("the parser only produces paths in variant patterns")
+                };
+                let patterns = patterns.into_iter()
+                    .map(|pattern| Self::from_parser_pattern(file, content, pattern))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let name = id.get_content(content).to_string();
+                Pattern::Variant(name, item.get_content(content).to_string(), Some(patterns))
+            }
+            PatternExpression::Identifier(id) => Pattern::Binding(id.get_content(content).to_string()),
+            PatternExpression::Rest(_, id) => Pattern::Rest(id.map(|id| id.get_content(content).to_string())),
+            PatternExpression::Array(items) => {
+                // The remaining elements can only be taken once, or it wouldn't be clear how
+                // many elements each `...` takes.
+                let mut rests = items.iter().filter_map(|item| match item {
+                    PatternExpression::Rest(ellipsis, _) => Some(ellipsis.clone()),
+                    _ => None,
+                });
+                if let (Some(_), Some(second)) = (rests.next(), rests.next()) {
+                    return Err(NotSupportedOperationError::new(
                         file,
-                        region,
-                        format!("Invalid array access, not supported yet: {name}"),
-                    ))
+                        second,
+                        "An array pattern can only have one `...`".to_string(),
+                    ));
                 }
+                let items = items.into_iter()
+                    .map(|item| Self::from_parser_pattern(file, content, item))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Pattern::Array(items)
+            }
+            PatternExpression::Struct(id, fields) => {
+                let fields = fields.into_iter().map(|(field, pattern)| {
+                    let name = field.get_content(content).to_string();
+                    // `Point { x }` is short for `Point { x: x }`.
+                    let pattern = match pattern {
+                        Some(pattern) => Self::from_parser_pattern(file, content, pattern)?,
+                        None => Pattern::Binding(name.clone()),
+                    };
+                    Ok((name, pattern))
+                }).collect::<Result<Vec<_>, _>>()?;
+                Pattern::Struct(id.get_content(content).to_string(), fields)
             }
         })
     }
-    fn from_parser_block(file: FileIdentifier, content: &str, block: Vec<Expression>) -> Result<Vec<Expr>, NotSupportedOperationError> {
-        block.into_iter().map(|expr| {
-            Self::from_parser_inner(file, content, expr)
+
+    // Converts the variables declared by a `let` statement or a `for` loop.
+    fn from_parser_binding(file: FileIdentifier, content: &str, binding: BindingExpression) -> Binding {
+        match binding {
+            BindingExpression::Identifier(id) => Binding::Name(id.get_content(content).to_string()),
+            BindingExpression::Wildcard => Binding::Wildcard,
+            BindingExpression::Array(bindings, region) => Binding::Array(
+                bindings.into_iter().map(|binding| Self::from_parser_binding(file, content, binding)).collect(),
+                Location::new(file, region),
+            ),
+        }
+    }
+
+    // Converts the parameters of a function or closure.
+    // Parameters with a default value can be omitted by the caller, so they must come last.
+    fn from_parser_parameters(file: FileIdentifier, content: &str, parameters: Vec<ParameterExpression>)
+                              -> Result<Vec<Parameter>, NotSupportedOperationError> {
+        let mut has_default = false;
+        parameters.into_iter().map(|parameter| {
+            if has_default && parameter.default.is_none() {
+                return Err(NotSupportedOperationError::new(
+                    file,
+                    parameter.id,
+                    "Parameters without a default value must come before the ones with a default value".to_string(),
+                ));
+            }
+            has_default = parameter.default.is_some();
+            let default = parameter.default
+                .map(|default| Self::from_parser_inner(file, content, default))
+                .transpose()?;
+            let annotation = parameter.annotation.map(|ty| Self::from_parser_type(file, content, ty));
+            Ok(Parameter { name: parameter.id.get_content(content).to_string(), annotation, default })
         }).collect()
     }
 
+    // Converts a type written in a function signature.
+    fn from_parser_type(file: FileIdentifier, content: &str, token: TokenRecord) -> TypeAnnotation {
+        let ty = Type::from_name(token.get_content(content));
+        TypeAnnotation { ty, location: Location::new(file, token) }
+    }
+
+}
+
+// Removes the indentation of a multiline string, so it can be indented like the code around it.
+// The line break after the opening `"""` is dropped, and so is the last line when it only holds
+// the indentation of the closing `"""`. Then the indentation shared by the lines is removed, the
+// closing `"""` included, so moving it to the left keeps some indentation in the string.
+// The lines of a file written with `\r\n` end the same, with a `\n`.
+// Returns the text along with the offset in the file of each of its bytes, given the offset of
+// the text.
+fn strip_indentation(text: &str, offset: usize) -> (String, Vec<usize>) {
+    let start = if text.starts_with('\n') {
+        1
+    } else if text.starts_with("\r\n") {
+        2
+    } else {
+        0
+    };
+    let mut lines = Vec::new();
+    let mut line_start = start;
+    for (i, byte) in text.bytes().enumerate().skip(start) {
+        if byte == b'\n' {
+            let end = if i > line_start && text.as_bytes()[i - 1] == b'\r' { i - 1 } else { i };
+            lines.push(line_start..end);
+            line_start = i + 1;
+        }
+    }
+    lines.push(line_start..text.len());
+    let closing = match lines.last() {
+        Some(line) if lines.len() > 1 && text[line.clone()].trim().is_empty() => lines.pop(),
+        _ => None,
+    };
+    let indentation = lines
+        .iter()
+        .filter(|line| !text[(*line).clone()].trim().is_empty())
+        .chain(closing.as_ref())
+        .map(|line| text[line.clone()].len() - text[line.clone()].trim_start().len())
+        .min()
+        .unwrap_or(0);
+    let mut stripped = String::new();
+    let mut offsets = Vec::new();
+    for (n, line) in lines.into_iter().enumerate() {
+        if n > 0 {
+            // The line break ending the previous line.
+            stripped.push('\n');
+            offsets.push(offset + line.start - 1);
+        }
+        let kept = (line.start + indentation).min(line.end)..line.end;
+        if let Some(kept_text) = text.get(kept.clone()) {
+            stripped.push_str(kept_text);
+            offsets.extend(kept.map(|i| offset + i));
+        }
+    }
+    (stripped, offsets)
+}
+
+// Finds the `}` closing an interpolated expression that starts at `start`, skipping the braces,
+// brackets and strings inside the expression.
+fn placeholder_end(text: &str, start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_string = false;
+    for (i, c) in text[start..].char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '{' | '(' | '[' => depth += 1,
+            '}' if depth == 0 => return Some(start + i),
+            '}' | ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+// Finds the `:` separating an interpolated expression from its format specifier, ignoring the
+// `::` of paths and the `:` inside brackets and strings.
+fn format_spec_start(placeholder: &str) -> Option<usize> {
+    let bytes = placeholder.as_bytes();
+    let mut depth = 0;
+    let mut in_string = false;
+    for (i, c) in placeholder.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '{' | '(' | '[' => depth += 1,
+            '}' | ')' | ']' => depth -= 1,
+            ':' if depth == 0 => {
+                let path = (i > 0 && bytes[i - 1] == b':') || bytes.get(i + 1) == Some(&b':');
+                if !path {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Returns the location of the condition of an `if` or `until`, of the collection of a `for`, or of the
+// function of a pipeline, or of the keyword or operator before it when its tokens aren't known.
+fn expression_location(file: FileIdentifier, keyword: &TokenRecord, condition: &Expression) -> Location {
+    let range = condition.span().unwrap_or_else(|| keyword.range());
+    let record = TokenRecord {
+        token_type: keyword.token_type,
+        offset: range.start,
+        length: range.len(),
+        after_newline: keyword.after_newline,
+    };
+    Location::new(file, record)
+}
+
+// Returns the location of a token extended to the part of the file in `span`, like a call from the
+// function to its closing parenthesis.
+fn spanning(file: FileIdentifier, token: &TokenRecord, span: Option<Range<usize>>) -> Location {
+    let range = match span {
+        Some(span) => span.start.min(token.offset)..span.end.max(token.offset + token.length),
+        None => token.range(),
+    };
+    let record = TokenRecord {
+        token_type: token.token_type,
+        offset: range.start,
+        length: range.len(),
+        after_newline: token.after_newline,
+    };
+    Location::new(file, record)
+}
+
+// Returns the character written between the quotes of a character literal, which is either a
+// single character or an escape sequence like `\n`.
+fn char_value(literal: &str) -> Option<char> {
+    let mut chars = literal.chars();
+    let c = match chars.next()? {
+        '\\' => match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            c @ ('\\' | '\'' | '"') => c,
+            _ => return None,
+        },
+        c => c,
+    };
+    chars.next().is_none().then_some(c)
 }
 
 // Implement Display for Expr so that it can be printed.
@@ -276,6 +1025,41 @@ impl fmt::Display for Expr {
                 }
                 write!(f, "]")
             }
+            // For ranges, print the bounds like they are written.
+            Expr::RangeValue(start, end, 1, inclusive) => {
+                write!(f, "{start}{}{end}", if *inclusive { "..=" } else { ".." })
+            }
+            // Stepped ranges can only be created by `range()`.
+            Expr::RangeValue(start, end, step, _) => write!(f, "range({start}, {end}, {step})"),
+            // For enum variants, print the full path of the variant and the values it carries.
+            Expr::Variant(name, variant, values) if values.is_empty() => write!(f, "{name}::{variant}"),
+            Expr::Variant(name, variant, values) => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, "{name}::{variant}({})", values.join(", "))
+            }
+            // For struct instances, print the struct name followed by every field.
+            Expr::Instance(name, fields) => {
+                write!(f, "{name} {{ ")?;
+                for (i, (field, value)) in fields.iter().enumerate() {
+                    write!(f, "{field}: {value}")?;
+                    if i + 1 < fields.len() {
+                        write!(f, ", ")?;
+                    }
+                }
+                write!(f, " }}")
+            }
+            // Functions are values too, but their body isn't printed.
+            Expr::Closure(parameters, _, _, _) => {
+                let names: Vec<&str> = parameters.iter().map(|parameter| parameter.name.as_str()).collect();
+                write!(f, "<function({})>", names.join(", "))
+            }
+            Expr::Compiled(function, _) => {
+                let names: Vec<&str> = function.parameters.iter().map(|parameter| parameter.name.as_str()).collect();
+                write!(f, "<function({})>", names.join(", "))
+            }
+            Expr::Builtin(_) => write!(f, "<built-in function>"),
+            Expr::Native(native) => write!(f, "<native function {}>", native.name),
+            Expr::Module(name, _) => write!(f, "<module {name}>"),
             _ => Ok(()), // For other expressions, do nothing.
         }
     }
@@ -0,0 +1,210 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel fmt`, which writes scripts in a canonical layout: four spaces of indentation per open
+// bracket, one space between the tokens except around `.`, `::`, `..` and inside brackets, no
+// trailing whitespace and at most one blank line in a row.
+// Only the whitespace between the tokens changes. The lines are kept where they are broken, as a
+// new line can end a statement, and so are the comments. The code formatted is lexed again and
+// must give the same tokens on the same lines, so formatting never changes what a script does.
+
+use std::path::Path;
+use std::process::ExitCode;
+use crate::error::FileError;
+use crate::lexer;
+use crate::lexer::Token;
+use crate::loader::Loader;
+
+// The indentation of one level of brackets.
+const INDENT: &str = "    ";
+
+// Formats files in place, or with `check` only tells which ones aren't formatted.
+// A file with syntax errors is left as it is. Fails when an error was reported or, with `check`,
+// when a file isn't formatted.
+pub fn format_files(files: &[String], check: bool) -> ExitCode {
+    let mut loader = Loader::new(false);
+    let mut failed = false;
+    for file in files {
+        let path = Path::new(file);
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) => {
+                loader.show(&FileError::new(path, format!("Failed to read file: {error}")));
+                failed = true;
+                continue;
+            }
+        };
+        if let Err(error) = loader.load_source(path, &content) {
+            loader.show(&error);
+            failed = true;
+            continue;
+        }
+        let Some(formatted) = format(&content) else {
+            loader.show(&FileError::new(path, "Failed to format the file without changing its code".to_string()));
+            failed = true;
+            continue;
+        };
+        if formatted == content {
+            continue;
+        }
+        if check {
+            println!("{file} is not formatted");
+            failed = true;
+        } else if let Err(error) = std::fs::write(path, formatted) {
+            loader.show(&FileError::new(path, format!("Failed to write the file: {error}")));
+            failed = true;
+        }
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+// A token or a comment, with the whitespace written before it.
+struct Piece<'a> {
+    token: Token,
+    text: &'a str,
+    comment: bool,
+    newlines: usize, // The new lines before the piece.
+    gap: usize,      // The characters before the piece on its line, after the previous one.
+}
+
+// Formats code which can be lexed. Returns `None` when the formatted code wouldn't give the same
+// tokens, which would be a mistake of the formatter.
+pub(crate) fn format(content: &str) -> Option<String> {
+    let pieces = pieces(content)?;
+    let mut output = String::new();
+    // The level of the line each open bracket is on.
+    let mut open: Vec<usize> = Vec::new();
+    let mut line_level = 0;
+    // The last piece which isn't a comment.
+    let mut last: Option<&Piece> = None;
+    let mut previous: Option<&Piece> = None;
+    let mut unary = false;
+    // Whether the last `|` opened the parameters of a closure.
+    let mut parameters = false;
+    for piece in &pieces {
+        let closing = matches!(piece.token, Token::RParenthesis | Token::RBracket | Token::RBrace);
+        if let Some(before) = previous.filter(|_| piece.newlines > 0) {
+            output.push('\n');
+            // A blank line is kept, but not at the start or the end of a block.
+            if piece.newlines > 1 && !closing && !is_opening(before) {
+                output.push('\n');
+            }
+        }
+        if previous.is_none() || piece.newlines > 0 {
+            line_level = if closing {
+                open.last().copied().unwrap_or(0)
+            } else {
+                let continued = !piece.comment && last.is_some_and(|last| continues(last, piece, unary));
+                open.last().map_or(0, |level| level + 1) + usize::from(continued)
+            };
+            output.push_str(&INDENT.repeat(line_level));
+        } else if let Some(before) = previous {
+            if piece.comment {
+                output.push_str(&" ".repeat(piece.gap.max(1)));
+            } else if before.comment || spaced(before, piece, unary, parameters) {
+                output.push(' ');
+            }
+        }
+        output.push_str(piece.text);
+        if piece.comment {
+            previous = Some(piece);
+            continue;
+        }
+        match piece.token {
+            Token::LParenthesis | Token::LBracket | Token::LBrace => open.push(line_level),
+            Token::RParenthesis | Token::RBracket | Token::RBrace => {
+                open.pop();
+            }
+            Token::Bar => parameters = !parameters,
+            _ => {}
+        }
+        // A `-` is a negation unless it follows a value.
+        unary = piece.token == Token::Minus && !last.is_some_and(ends_value);
+        last = Some(piece);
+        previous = Some(piece);
+    }
+    if !output.is_empty() {
+        output.push('\n');
+    }
+    same_code(&pieces, &self::pieces(&output)?).then_some(output)
+}
+
+// Splits code into its tokens and comments, dropping the whitespace.
+fn pieces(content: &str) -> Option<Vec<Piece<'_>>> {
+    let mut pieces = Vec::new();
+    let mut end = 0;
+    for record in lexer::lex_all(content)? {
+        let text = record.get_content(content);
+        let comment = match record.token_type {
+            Token::Ignore if text.starts_with("//") || text.starts_with("/*") => true,
+            Token::Ignore => continue,
+            Token::DocComment => true,
+            _ => false,
+        };
+        // A line comment ends with its new line.
+        let text = if comment { text.trim_end() } else { text };
+        let between = &content[end..record.offset];
+        let newlines = between.matches('\n').count();
+        let gap = between.rsplit('\n').next().unwrap_or("").chars().count();
+        pieces.push(Piece { token: record.token_type, text, comment, newlines, gap });
+        end = record.offset + text.len();
+    }
+    Some(pieces)
+}
+
+// Tells whether two lists of pieces have the same tokens and comments, with new lines between the
+// same ones.
+fn same_code(pieces: &[Piece], formatted: &[Piece]) -> bool {
+    pieces.len() == formatted.len()
+        && pieces.iter().zip(formatted).all(|(piece, formatted)| {
+            piece.token == formatted.token && piece.text == formatted.text && (piece.newlines > 0) == (formatted.newlines > 0)
+        })
+}
+
+// Tells whether a space separates two pieces on a line. `unary` tells whether `before` is a
+// negation, and `parameters` whether they are between the `|` of the parameters of a closure.
+fn spaced(before: &Piece, after: &Piece, unary: bool, parameters: bool) -> bool {
+    use Token::*;
+    match (before.token, after.token) {
+        (_, Comma | Semicolon | RParenthesis | RBracket | Dot | ColonColon | Colon | DotDot | DotDotEquals) => false,
+        (LParenthesis | LBracket | Dot | ColonColon | DotDot | DotDotEquals | Ellipsis, _) => false,
+        (LBrace, RBrace) => false,
+        // Calls and indexes.
+        (Assert, LParenthesis) => false,
+        (_, LParenthesis | LBracket) if ends_value(before) => false,
+        // The parameters of a closure: `|a, b| { ... }`
+        (Bar, _) | (_, Bar) if parameters => false,
+        (Minus, _) => !unary,
+        _ => true,
+    }
+}
+
+// Tells whether a line goes on with the statement of the line before, which ended with `last`.
+fn continues(last: &Piece, first: &Piece, unary: bool) -> bool {
+    use Token::*;
+    let operator = |token| {
+        matches!(
+            token,
+            Plus | Star | RSlash | StarStar | EqualsEquals | NotEquals | LessThan | GreaterThan | LessThanEquals
+                | GreaterThanEquals | Pipe | And | Or | DotDot | DotDotEquals
+        )
+    };
+    operator(last.token)
+        || matches!(last.token, Equals | FatArrow | Arrow)
+        || (last.token == Minus && !unary)
+        || operator(first.token)
+        || first.token == Dot
+}
+
+fn is_opening(piece: &Piece) -> bool {
+    !piece.comment && matches!(piece.token, Token::LParenthesis | Token::LBracket | Token::LBrace)
+}
+
+// Tells whether a token can end a value, so a `(` or `[` after it is a call or an index.
+fn ends_value(piece: &Piece) -> bool {
+    use Token::*;
+    matches!(
+        piece.token,
+        Identifier | Integer | Float | Boolean | Nil | String | Char | Underscore | RParenthesis | RBracket | RBrace
+    )
+}
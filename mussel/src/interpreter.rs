@@ -1,377 +1,1832 @@
-// Copyright (c) 2025 Francesco Giannice
-// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
-
-// Import definitions from the parser module that are needed for evaluation.
-use core::panic;
-// Import the HashMap collection to maintain variable bindings.
-use std::collections::HashMap;
-use crate::expr::{Atom, BinOp, Expr, Operator};
-
-// The main interpreter function that takes a vector of expressions.
-pub fn interpreter(exprs: Vec<Expr>) {
-    // Create a mutable context (a HashMap) to store variable bindings.
-    let mut context = HashMap::new();
-    // Evaluate each expression in order.
-    for expr in exprs {
-        interpreter_expr(expr, &mut context);
-    }
-}
-
-// The recursive function that evaluates an expression given the current context.
-// It returns a new expression representing the evaluated result.
-fn interpreter_expr(expr: Expr, context: &mut HashMap<String, Expr>) -> Expr {
-    // Use pattern matching on the expression to determine how to evaluate it.
-    match expr {
-        // For these variants, no further evaluation is needed so we return the expression as-is.
-        Expr::Void | Expr::Closure(_, _) | Expr::Array(_) => expr,
-        // For a return expression, evaluate the inner expression and re-wrap it.
-        Expr::Return(expr) => Expr::Return(Box::new(interpreter_expr(*expr, context))),
-        // If the expression is a string constant, attempt to parse interpolation.
-        Expr::Constant(Atom::String(ref string)) => {
-            let mut result = string.clone();
-            let mut start = 0;
-
-            while let Some(open) = result[start..].find('{') {
-                if let Some(close) = result[start + open..].find('}') {
-                    let placeholder = &result[start + open + 1..start + open + close];
-                    let value = if placeholder.contains('[') {
-                        // Handle array access like `fruits[1]`
-                        let parts: Vec<&str> = placeholder.split('[').collect();
-                        if parts.len() == 2 {
-                            let array_name = parts[0];
-                            let index_str = parts[1].trim_end_matches(']');
-                            if let Ok(index) = index_str.parse::<usize>() {
-                                if let Some(Expr::Array(items)) = context.get(array_name) {
-                                    if let Some(item) = items.get(index) {
-                                        item.to_string()
-                                    } else {
-                                        format!("{{{placeholder}}}")
-                                    }
-                                } else {
-                                    format!("{{{placeholder}}}")
-                                }
-                            } else {
-                                format!("{{{placeholder}}}")
-                            }
-                        } else {
-                            format!("{{{placeholder}}}")
-                        }
-                    } else if placeholder.contains(' ') {
-                        // Handle arithmetic expressions like `end - start`
-                        let tokens: Vec<&str> = placeholder.split_whitespace().collect();
-                        if tokens.len() == 3 {
-                            let left = tokens[0];
-                            let operator = tokens[1];
-                            let right = tokens[2];
-
-                            if let (Some(Expr::Constant(Atom::Number(left_val))),
-                                    Some(Expr::Constant(Atom::Number(right_val)))) =
-                                (context.get(left), context.get(right)) {
-                                match operator {
-                                    "+" => (left_val + right_val).to_string(),
-                                    "-" => (left_val - right_val).to_string(),
-                                    "*" => (left_val * right_val).to_string(),
-                                    "/" => {
-                                        if *right_val == 0 {
-                                            "Division by zero".to_string()
-                                        } else {
-                                            (left_val / right_val).to_string()
-                                        }
-                                    },
-                                    _ => format!("{{{placeholder}}}"),
-                                }
-                            } else {
-                                format!("{{{placeholder}}}")
-                            }
-                        } else {
-                            format!("{{{placeholder}}}")
-                        }
-                    } else {
-                        // Handle simple variable interpolation
-                        context.get(placeholder).map_or_else(
-                            || format!("{{{placeholder}}}"),
-                            |expr| expr.to_string(),
-                        )
-                    };
-
-                    result.replace_range(start + open..start + open + close + 1, &value);
-                    start += open + value.len();
-                } else {
-                    break;
-                }
-            }
-
-            Expr::Constant(Atom::String(result))
-        }
-        // If the constant is a name, look it up in the context.
-        Expr::Constant(ref atom) => match atom {
-            Atom::Name(name) => context
-                .get(name)
-                .expect(&format!("{name} doesn't exist!"))
-                .clone(),
-            _ => expr, // For other atoms, return as is.
-        },
-        // Evaluate a let-binding by evaluating the right-hand side and storing it in the context.
-        Expr::Let(name, expr) => {
-            let expr = interpreter_expr(*expr, context);
-            context.insert(name, expr);
-            // Let statements evaluate to void.
-            Expr::Void
-        }
-        // Evaluate a comparison expression.
-        Expr::Compare(left, operator, right) => {
-            let left = interpreter_expr(*left, context);
-            let right = interpreter_expr(*right, context);
-            match (&left, operator, &right) {
-                (
-                    Expr::Constant(Atom::Number(left)),
-                    operator,
-                    Expr::Constant(Atom::Number(right)),
-                ) => match operator {
-                    Operator::LessThan => Expr::Constant(Atom::Boolean(left < right)),
-                    Operator::LessThanEqual => Expr::Constant(Atom::Boolean(left <= right)),
-                    Operator::GreaterThan => Expr::Constant(Atom::Boolean(left > right)),
-                    Operator::GreaterThanEqual => Expr::Constant(Atom::Boolean(left >= right)),
-                    Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
-                    Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
-                },
-                (
-                    Expr::Constant(Atom::Float(left)),
-                    operator,
-                    Expr::Constant(Atom::Float(right)),
-                ) => match operator {
-                    Operator::LessThan => Expr::Constant(Atom::Boolean(left < right)),
-                    Operator::LessThanEqual => Expr::Constant(Atom::Boolean(left <= right)),
-                    Operator::GreaterThan => Expr::Constant(Atom::Boolean(left > right)),
-                    Operator::GreaterThanEqual => Expr::Constant(Atom::Boolean(left >= right)),
-                    Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
-                    Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
-                },
-                // Branch for booleans.
-                (
-                    Expr::Constant(Atom::Boolean(left)),
-                    operator,
-                    Expr::Constant(Atom::Boolean(right)),
-                ) => match operator {
-                    Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
-                    Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
-                    _ => panic!(
-                        "Invalid comparison operator for booleans: {:?}. Use == or !=",
-                        operator
-                    ),
-                },
-                // New branch for comparing strings.
-                (
-                    Expr::Constant(Atom::String(left)),
-                    operator,
-                    Expr::Constant(Atom::String(right)),
-                ) => match operator {
-                    Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
-                    Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
-                    _ => panic!(
-                        "Invalid comparison operator for strings: {:?}. Use == or !=",
-                        operator
-                    ),
-                },
-                _ => panic!("Can't compare {left} or {right}"),
-            }
-        }
-        // Evaluate an if-statement.
-        Expr::If(statement, then, otherwise) => {
-            // Evaluate the condition expecting a boolean result.
-            if let Expr::Constant(Atom::Boolean(value)) = interpreter_expr(*statement, context) {
-                if value {
-                    // If true, evaluate all expressions in the "then" branch.
-                    for expr in then {
-                        interpreter_expr(expr, context);
-                    }
-                } else {
-                    // If false, and an "else" branch exists, evaluate it.
-                    if let Some(body) = otherwise {
-                        for expr in body {
-                            interpreter_expr(expr, context);
-                        }
-                    }
-                }
-            }
-            // If the if-statement doesn't yield a value, return void.
-            Expr::Void
-        }
-        // Evaluate a function call.
-        Expr::Call(name, args) => {
-            // Evaluate arguments.
-            let evaluated_args: Vec<Expr> = args
-                .into_iter()
-                .map(|arg| interpreter_expr(arg, context))
-                .collect();
-            // Check if the function name is one of the built-in ones.
-            if let Some(val) = context.get(&name) {
-                match val {
-                    Expr::Builtin(func) => return func(evaluated_args, context),
-                    Expr::Closure(parameters, body) => {
-                        // Existing closure call handling remains here.
-                        let mut scope = context.clone();
-                        for (parameter, arg) in
-                            parameters.into_iter().zip(evaluated_args.into_iter())
-                        {
-                            let expr = interpreter_expr(arg, &mut scope);
-                            scope.insert(parameter.clone(), expr);
-                        }
-                        for expr in body {
-                            if let Expr::Return(expr) = interpreter_expr(expr.clone(), &mut scope) {
-                                return *expr;
-                            }
-                        }
-                        return Expr::Void;
-                    }
-                    _ => { /* Fall through */ }
-                }
-            }
-
-            // Special cases (like "println" and "input") remain unchanged.
-            if name == "println" {
-                for arg in evaluated_args {
-                    print!("{}", interpreter_expr(arg, context));
-                }
-                println!();
-                return Expr::Void;
-            } else if name == "input" {
-                let prompt = if !evaluated_args.is_empty() {
-                    interpreter_expr(evaluated_args[0].clone(), context).to_string()
-                } else {
-                    String::new()
-                };
-                print!("{}", prompt);
-                use std::io::{self, Write};
-                io::stdout().flush().expect("Failed to flush stdout");
-                let mut input_text = String::new();
-                io::stdin()
-                    .read_line(&mut input_text)
-                    .expect("Failed to read line");
-                let input_text = input_text.trim_end().to_string();
-                return Expr::Constant(Atom::String(input_text));
-            }
-
-            panic!("Function `{name}` doesn't exist.");
-        }
-        // Define a function by storing it as a closure in the context.
-        Expr::Function(name, args, body) => {
-            context.insert(name, Expr::Closure(args, body));
-            Expr::Void
-        }
-        // Evaluate a for loop.
-        Expr::For(name, collection, body) => {
-            let array = interpreter_expr(*collection, context);
-            match array {
-                // Ensure the collection is an array.
-                Expr::Array(items) => {
-                    // Create a new scope for the loop.
-                    let mut scope = context.clone();
-                    for item in items {
-                        // Bind the loop variable to the current item.
-                        scope.insert(name.clone(), item);
-                        // Evaluate each expression in the loop body.
-                        for expr in &body {
-                            interpreter_expr(expr.clone(), &mut scope);
-                        }
-                    }
-                    Expr::Void
-                }
-                // Panic if the loop variable is not an array.
-                _ => panic!("Can't loop over `{array}`"),
-            }
-        }
-        // Evaluate an array element access.
-        Expr::Get(name, index) => match context.get(&name) {
-            Some(Expr::Array(items)) => {
-                // Retrieve the element at the given index and evaluate it.
-                let expr = items[index].clone();
-                return interpreter_expr(expr, context);
-            }
-            Some(invalid) => panic!("Expected array, got {invalid}"),
-            None => panic!("Couldn't find {name}"),
-        },
-        Expr::Until(condition, body) => {
-            // Loop until the condition evaluates to true.
-            loop {
-                // Evaluate the condition. Clone the condition so it can be used repeatedly.
-                let cond_result = interpreter_expr((*condition).clone(), context);
-                // Expect the condition to yield a boolean.
-                if let Expr::Constant(Atom::Boolean(true)) = cond_result {
-                    break;
-                }
-                // Otherwise, run each expression in the body.
-                // We clone the body because it may be re-used in further iterations.
-                for expr in body.clone() {
-                    interpreter_expr(expr, context);
-                }
-            }
-            Expr::Void
-        }
-        Expr::Binary(left_expr, op, right_expr) => {
-            let left = interpreter_expr(*left_expr, context);
-            let right = interpreter_expr(*right_expr, context);
-            match (&left, &right) {
-                (Expr::Constant(Atom::Number(l)), Expr::Constant(Atom::Number(r))) => {
-                    let result = match op {
-                        BinOp::Add => l + r,
-                        BinOp::Sub => l - r,
-                        BinOp::Mul => l * r,
-                        BinOp::Div => {
-                            if *r == 0 {
-                                panic!("Division by zero");
-                            } else {
-                                l / r
-                            }
-                        }
-                    };
-                    Expr::Constant(Atom::Number(result))
-                }
-                // If you also want to support floating-point arithmetic, you can add a branch:
-                (Expr::Constant(Atom::Float(l)), Expr::Constant(Atom::Float(r))) => {
-                    let result = match op {
-                        BinOp::Add => l + r,
-                        BinOp::Sub => l - r,
-                        BinOp::Mul => l * r,
-                        BinOp::Div => {
-                            if *r == 0.0 {
-                                panic!("Division by zero");
-                            } else {
-                                l / r
-                            }
-                        }
-                    };
-                    Expr::Constant(Atom::Float(result))
-                }
-                (Expr::Constant(Atom::String(l)), Expr::Constant(Atom::String(r))) => {
-                    let result = match op {
-                        BinOp::Add => format!("{}{}", l, r),
-                        _ => panic!("Only '+' is supported by strings"),
-                    };
-                    Expr::Constant(Atom::String(result))
-                }
-                _ => panic!("Arithmetic operations are only supported between numbers"),
-            }
-        }
-        Expr::Include(lib) => {
-            if lib == "random" {
-                crate::stdlib::random::load(context);
-            } else if lib == "string" {
-                crate::stdlib::string::load(context);
-            } else if lib == "time" {
-                crate::stdlib::time::load(context);
-            } else if lib == "math" {
-                crate::stdlib::math::load(context);
-            } else if lib == "os" {
-                crate::stdlib::os::load(context);
-            } else {
-                panic!("Unknown library: {lib}");
-            }
-            Expr::Void
-        }
-        Expr::Builtin(func) => {
-            // Builtins are meant to be called; simply return them.
-            Expr::Builtin(func)
-        }
-    }
-}
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Import definitions from the parser module that are needed for evaluation.
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::str::FromStr;
+use crate::context::{Context, Debugger, Declarations};
+use crate::error;
+use crate::error::{
+    AssertionError, CircularIncludeError, ConditionTypeError, ConstantAssignmentError, EvaluationError, Halt,
+    IncludeError, LError, Location, LoopControlError, MissingMethodsError, RuntimeError, TracedError,
+    TypeMismatchError, UncaughtError, UndeclaredLabelError,
+};
+use crate::expr::{
+    Align, Atom, BinOp, Binding, Expr, FormatSpec, IncludeTarget, LogicOp, Operator, Parameter, Pattern, StringPart,
+    Type, TypeAnnotation, UnaryOp,
+};
+use crate::loader::Loader;
+use crate::trace::Trace;
+
+/// How the statements of a script are run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Backend {
+    /// Evaluates the expressions directly, the reference implementation.
+    Tree,
+    /// Compiles the expressions to instructions for a virtual machine.
+    Vm,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "tree" => Ok(Backend::Tree),
+            "vm" => Ok(Backend::Vm),
+            _ => Err(format!("unknown backend `{name}`, expected `tree` or `vm`")),
+        }
+    }
+}
+
+// What evaluating code gives: a value, or what stopped the script, which is returned up to the
+// function running it, see `Halt`.
+pub type Eval<T = Expr> = Result<T, Halt>;
+
+// Stops the script with an exit status, for `exit`. It is returned up to `interpreter` like an error.
+pub fn exit<T>(status: u8) -> Eval<T> {
+    Err(Halt::Exit(status))
+}
+
+// The main interpreter function that takes a vector of expressions.
+// At most `max_depth` function calls can be nested. The script reads its command line arguments
+// with `args()`.
+// The errors raised while the script runs are reported with the code of the files of the loader.
+// Returns the exit status of the script: the one given to `exit`, a failure after an error, or else
+// the value of its last statement when it is an integer, success otherwise.
+pub fn interpreter(
+    exprs: Vec<Expr>,
+    loader: Loader,
+    max_depth: usize,
+    backend: Backend,
+    arguments: Vec<String>,
+) -> ExitCode {
+    // Create a mutable context to store variable bindings, starting with the global scope.
+    run(exprs, Context::new(loader, max_depth, backend, arguments))
+}
+
+// Runs a script like `interpreter` does, with the tree-walker, letting a debugger pause it before
+// its statements.
+pub fn debug(
+    exprs: Vec<Expr>,
+    loader: Loader,
+    max_depth: usize,
+    arguments: Vec<String>,
+    debugger: Box<dyn Debugger>,
+) -> ExitCode {
+    let mut context = Context::new(loader, max_depth, Backend::Tree, arguments);
+    context.attach(debugger);
+    run(exprs, context)
+}
+
+// Runs a script like `interpreter` does, with the tree-walker, writing the expressions it
+// evaluates to the standard error.
+pub fn trace(exprs: Vec<Expr>, loader: Loader, max_depth: usize, arguments: Vec<String>, trace: Trace) -> ExitCode {
+    let mut context = Context::new(loader, max_depth, Backend::Tree, arguments);
+    context.start_trace(trace);
+    run(exprs, context)
+}
+
+// Runs a script like `interpreter` does, measuring the time spent in each function, see `profile`.
+// The folded stacks are written to `stacks`, or else the table to the standard error.
+pub fn profile(
+    exprs: Vec<Expr>,
+    loader: Loader,
+    max_depth: usize,
+    backend: Backend,
+    arguments: Vec<String>,
+    stacks: Option<PathBuf>,
+) -> ExitCode {
+    let mut context = Context::new(loader, max_depth, backend, arguments);
+    context.start_profile(stacks);
+    run(exprs, context)
+}
+
+// Runs a script in a new context, returning its exit status.
+fn run(exprs: Vec<Expr>, mut context: Context) -> ExitCode {
+    // Load the built-ins that don't need an `include`.
+    crate::stdlib::core::load(&mut context);
+    let status = match interpreter_program(exprs, &mut context) {
+        Ok(value) => status(&value),
+        // Errors raised with `error::raise` are reported like any other error in the script.
+        // The functions being called when the error was raised are reported along with it.
+        Err(Halt::Error(error)) => {
+            let calls = context.backtrace();
+            context.loader().show(&TracedError::new(error, calls));
+            ExitCode::FAILURE
+        }
+        Err(Halt::Exit(status)) => ExitCode::from(status),
+    };
+    // The profile covers the whole run, even when an error stopped it.
+    if let Some(profiler) = context.take_profiler()
+        && let Err(error) = profiler.report()
+    {
+        eprintln!("error: Failed to write the profile: {error}");
+        return ExitCode::FAILURE;
+    }
+    status
+}
+
+// Returns the exit status set by the value of the last statement of a script: an integer between
+// 0 and 255 is the status, one out of that range is reported and fails, and any other value is a
+// success.
+fn status(value: &Expr) -> ExitCode {
+    match value {
+        Expr::Constant(Atom::Number(status)) => match u8::try_from(*status) {
+            Ok(status) => ExitCode::from(status),
+            Err(_) => {
+                eprintln!("error: The value of the last statement, {status}, isn't an exit status between 0 and 255");
+                ExitCode::FAILURE
+            }
+        },
+        _ => ExitCode::SUCCESS,
+    }
+}
+
+// Evaluates code typed while a debugger paused the script, in the scopes of the statement it is
+// paused at. An error, returned as its message, doesn't stop the script.
+// `exit` stops the script, even when called from the debugger.
+pub fn inspect(code: &str, context: &mut Context) -> Eval<Result<Expr, String>> {
+    let checkpoint = context.checkpoint();
+    let running = context.loader().running();
+    let message = match eval(code, context) {
+        Ok(Expr::Throw(error, _)) => format!("uncaught error: {error}"),
+        Ok(value) => return Ok(Ok(value)),
+        Err(Halt::Exit(status)) => return exit(status),
+        Err(Halt::Error(error)) => {
+            error.report().into_iter().next().map(|diagnostic| diagnostic.message).unwrap_or_default()
+        }
+    };
+    context.restore(checkpoint);
+    context.loader().unwind(running);
+    Ok(Err(message))
+}
+
+// Starts the context of an interactive session, with the built-ins that don't need an `include`.
+pub fn session(loader: Loader, max_depth: usize, backend: Backend) -> Context {
+    let mut context = Context::new(loader, max_depth, backend, Vec::new());
+    crate::stdlib::core::load(&mut context);
+    context
+}
+
+// What statements typed in an interactive session gave.
+pub enum Interaction {
+    Value(Expr), // The value of the last statement.
+    Failed,      // An error stopped them, it was reported.
+    Exit(u8),    // `exit` was called with the given status.
+}
+
+// Runs statements typed in an interactive session in its global scope.
+// After an error the session goes on, with what the statements before the error declared.
+pub fn interact(exprs: Vec<Expr>, context: &mut Context) -> Interaction {
+    match run_statements(exprs, context) {
+        Ok(value) => Interaction::Value(value),
+        Err(Stop::Exit(status)) => Interaction::Exit(status),
+        Err(Stop::Raised(error)) => {
+            context.loader().show(&*error);
+            Interaction::Failed
+        }
+    }
+}
+
+// What stopped statements run in the global scope of a session, see `run_statements`.
+pub(crate) enum Stop {
+    Raised(Box<TracedError>), // An error raised, with the calls that led to it.
+    Exit(u8),                 // `exit` was called with the given status.
+}
+
+// Runs statements in the global scope of a session, and returns the value of the last one or what
+// stopped them. After an error the session goes on, with what the statements before it declared.
+pub(crate) fn run_statements(exprs: Vec<Expr>, context: &mut Context) -> Result<Expr, Stop> {
+    run_global(context, |context| {
+        let mut value = Expr::Void;
+        for expr in exprs {
+            value = statement(expr, context)?;
+            if matches!(value, Expr::Throw(..)) {
+                break;
+            }
+        }
+        Ok(value)
+    })
+}
+
+// Calls a function of the global scope of a session by name, from the given call site, and returns
+// its value or what stopped it, like `run_statements`.
+pub(crate) fn call_global(name: String, location: Location, args: Vec<Expr>, context: &mut Context) -> Result<Expr, Stop> {
+    run_global(context, |context| {
+        let function = find_function(&name, &location, context)?;
+        call_at(&location, function, name, args, context)
+    })
+}
+
+// Runs code in the global scope of a session, going back to it when something stops the code.
+fn run_global(context: &mut Context, run: impl FnOnce(&mut Context) -> Eval) -> Result<Expr, Stop> {
+    let global = context.environment();
+    let running = context.loader().running();
+    let stop = match run(context) {
+        Ok(Expr::Throw(error, location)) => {
+            let error: RuntimeError = Box::new(UncaughtError::new(location, error.to_string()));
+            Stop::Raised(Box::new(TracedError::new(error, Vec::new())))
+        }
+        Ok(value) => return Ok(value),
+        Err(Halt::Exit(status)) => return Err(Stop::Exit(status)),
+        Err(Halt::Error(error)) => Stop::Raised(Box::new(TracedError::new(error, context.backtrace()))),
+    };
+    context.recover(global);
+    context.loader().unwind(running);
+    Err(stop)
+}
+
+// Evaluates the expressions of a script, or of a file it includes, in order, and returns the value
+// of the last one.
+fn interpreter_program(exprs: Vec<Expr>, context: &mut Context) -> Eval {
+    let mut value = Expr::Void;
+    for expr in exprs {
+        value = statement(expr, context)?;
+        if let Expr::Throw(error, location) = value {
+            return error::raise(UncaughtError::new(location, error.to_string()));
+        }
+    }
+    Ok(value)
+}
+
+// Evaluates a statement outside of any function, and returns its value or the error it threw.
+fn statement(expr: Expr, context: &mut Context) -> Eval {
+    pause(&expr, context)?;
+    let result = match evaluate(expr, context)? {
+        // A `return f(x)` outside of any function still calls the function.
+        Expr::TailCall(name, closure, args, location) => {
+            context.enter_call_site(location);
+            call_closure(name, *closure, args, context)?
+        }
+        result => result,
+    };
+    match result {
+        Expr::Break(_, _, location) => error::raise(LoopControlError::new(location, "break".to_string())),
+        Expr::Continue(_, location) => error::raise(LoopControlError::new(location, "continue".to_string())),
+        result => Ok(result),
+    }
+}
+
+// Lets the debugger attached to the context pause before a statement.
+fn pause(expr: &Expr, context: &mut Context) -> Eval<()> {
+    if context.debugging() && let Some(location) = expr.statement_location() {
+        context.pause(location)?;
+    }
+    Ok(())
+}
+
+// Evaluates a statement with the backend running the script.
+fn evaluate(expr: Expr, context: &mut Context) -> Eval {
+    match context.backend() {
+        Backend::Tree => interpreter_expr(expr, context),
+        Backend::Vm => crate::bytecode::evaluate(&expr, context),
+    }
+}
+
+// Loads a library of the standard library or a file, and returns what it declares.
+// A file only runs the first time it is included, then what it declared is reused.
+fn include(target: IncludeTarget, location: Location, context: &mut Context) -> Eval<Declarations> {
+    let (path, source) = match target {
+        IncludeTarget::Name(name) => match name.as_str() {
+            "random" => return library(crate::stdlib::random::load, context),
+            "string" => return library(crate::stdlib::string::load, context),
+            "time" => return library(crate::stdlib::time::load, context),
+            "math" => return library(crate::stdlib::math::load, context),
+            "os" => return library(crate::stdlib::os::load, context),
+            // The libraries written in Mussel are part of the interpreter, any other name is a file.
+            _ => (PathBuf::from(format!("{name}.mus")), crate::stdlib::source(&name)),
+        },
+        IncludeTarget::Path(path) => (PathBuf::from(path), None),
+    };
+    let path = match source {
+        Some(_) => Path::new("<std>").join(path),
+        None => match context.loader().find(location.file(), &path) {
+            Ok(found) => found,
+            Err(searched) => return error::raise(IncludeError::new(location, path, searched)),
+        },
+    };
+    if let Some(declarations) = context.module(&path) {
+        return Ok(declarations);
+    }
+    // A file including itself, directly or through other files, would never end.
+    if let Some(cycle) = context.loader().enter(&path) {
+        return error::raise(CircularIncludeError::new(location, cycle));
+    }
+    let loaded = match source {
+        Some(source) => context.loader().load_source(&path, source),
+        None => context.loader().load(&path),
+    };
+    let declarations = match loaded {
+        Ok(exprs) => module(|context| interpreter_program(exprs, context), context)?,
+        Err(error) => return error::raise(error),
+    };
+    context.loader().leave();
+    context.add_module(path, declarations.clone());
+    Ok(declarations)
+}
+
+// Runs a library of the standard library written in Rust, see `module`.
+fn library(load: fn(&mut Context), context: &mut Context) -> Eval<Declarations> {
+    module(
+        |context| {
+            load(context);
+            Ok(Expr::Void)
+        },
+        context,
+    )
+}
+
+// Runs a module apart from the script, with only the built-ins, and returns what it declares.
+fn module(run: impl FnOnce(&mut Context) -> Eval, context: &mut Context) -> Eval<Declarations> {
+    let previous = context.enter_module();
+    crate::stdlib::core::load(context);
+    context.push_scope();
+    run(context)?;
+    Ok(context.leave_module(previous))
+}
+
+// Stops the script with an error pointing at the expression that can't be evaluated.
+pub fn fail<T>(location: &Location, message: String, label: &str) -> Eval<T> {
+    error::raise(EvaluationError::new(location.clone(), message, label.to_string()))
+}
+
+// Like `fail`, for a kind of error with a code of its own, see `explain`.
+pub fn fail_as<T>(code: &'static str, location: &Location, message: String, label: &str) -> Eval<T> {
+    error::raise(EvaluationError::new(location.clone(), message, label.to_string()).with_code(code))
+}
+
+// The errors raised by variables, fields and indexes, wherever they are used.
+pub fn not_found<T>(name: &str, location: &Location) -> Eval<T> {
+    fail_as("E1001", location, format!("cannot find `{name}` in this scope"), "not found in this scope")
+}
+
+fn missing_field<T>(struct_name: &str, field: &str, location: &Location) -> Eval<T> {
+    fail_as("E1003", location, format!("no field `{field}` on struct `{struct_name}`"), "unknown field")
+}
+
+fn no_fields<T>(value: &Expr, field: &str, location: &Location) -> Eval<T> {
+    let message = format!("no field `{field}` on a value of type `{}`", value.type_name());
+    fail_as("E1003", location, message, "unknown field")
+}
+
+fn not_indexable<T>(value: &Expr, location: &Location) -> Eval<T> {
+    fail(location, format!("cannot index into a value of type `{}`", value.type_name()), "expected an array")
+}
+
+// Checks whether a value stops the evaluation of the enclosing blocks: a `return` (possibly of a
+// tail call), a thrown error, a `break` or a `continue`.
+pub fn is_signal(value: &Expr) -> bool {
+    matches!(
+        value,
+        Expr::Return(_) | Expr::TailCall(..) | Expr::Throw(..) | Expr::Break(_, _, _) | Expr::Continue(_, _)
+    )
+}
+
+// What a loop does after running its body once.
+enum LoopFlow {
+    Next,       // Run the next iteration.
+    Exit(Expr), // Leave the loop, which gives this value.
+    Leave(Expr), // Stop the loop and hand the signal to the enclosing blocks.
+}
+
+// Decides how a loop goes on from the result of its body: a `break` or `continue` without a label
+// or with the label of the loop is handled by the loop, other signals leave it.
+fn loop_flow(result: Expr, label: Option<&str>) -> LoopFlow {
+    let targets = |target: &Option<String>| target.as_deref().is_none_or(|target| Some(target) == label);
+    match result {
+        Expr::Break(value, target, _) if targets(&target) => LoopFlow::Exit(*value),
+        Expr::Continue(target, _) if targets(&target) => LoopFlow::Next,
+        signal if is_signal(&signal) => LoopFlow::Leave(signal),
+        _ => LoopFlow::Next,
+    }
+}
+
+// Runs a loop, making its label visible to the `break` and `continue` inside it.
+fn run_loop(label: Option<&str>, context: &mut Context, run: impl FnOnce(&mut Context) -> Eval) -> Eval {
+    if let Some(label) = label {
+        context.push_label(label.to_string());
+    }
+    let result = run(context)?;
+    if label.is_some() {
+        context.pop_label();
+    }
+    Ok(result)
+}
+
+// Evaluates a block of expressions in order and returns the value of the last one.
+// A `return` or `throw` inside the block stops the evaluation and is handed back to the caller as-is.
+// The block runs in its own scope, so the variables declared inside it are dropped when it ends.
+fn interpreter_block(body: Vec<Expr>, context: &mut Context) -> Eval {
+    interpreter_block_with(body, std::iter::empty(), context)
+}
+
+// Evaluates a block like `interpreter_block`, declaring the given variables in its scope first,
+// like the parameters of a function or the variable of a `for` loop.
+fn interpreter_block_with(
+    body: Vec<Expr>,
+    bindings: impl IntoIterator<Item = (String, Expr)>,
+    context: &mut Context,
+) -> Eval {
+    context.push_scope();
+    for (name, value) in bindings {
+        context.insert(name, value);
+    }
+    let result = interpreter_statements(body, context)?;
+    context.pop_scope();
+    Ok(result)
+}
+
+// Evaluates the expressions of a block in the current scope, stopping at a `return` or `throw`.
+fn interpreter_statements(body: Vec<Expr>, context: &mut Context) -> Eval {
+    let mut result = Expr::Void;
+    for expr in body {
+        pause(&expr, context)?;
+        result = interpreter_expr(expr, context)?;
+        if is_signal(&result) {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+// Evaluates an expression, returning early from the enclosing function if the evaluation stopped
+// with an error, threw an error or returned (like an `if` whose branch contains a `return`), so
+// that the signal keeps propagating until a `try` or a function call handles it.
+macro_rules! eval {
+    ($expr:expr, $context:expr) => {
+        match interpreter_expr($expr, $context)? {
+            signal if is_signal(&signal) => return Ok(signal),
+            value => value,
+        }
+    };
+}
+
+// Runs code given as a string in the current scope, for `eval`, and returns the value of its last
+// expression. Errors in the code point at the string, named `<eval>`.
+pub fn eval(code: &str, context: &mut Context) -> Eval {
+    let exprs = match context.loader().load_source("<eval>", code) {
+        Ok(exprs) => exprs,
+        Err(error) => return error::raise(error),
+    };
+    let mut value = Expr::Void;
+    for expr in exprs {
+        value = evaluate(expr, context)?;
+        if is_signal(&value) {
+            break;
+        }
+    }
+    Ok(value)
+}
+
+// Lazily produces the numbers of a range, counting up for a positive step and down for a negative one.
+fn range_values(start: i64, end: i64, step: i64, inclusive: bool) -> impl Iterator<Item = i64> {
+    std::iter::successors(Some(start), move |n| n.checked_add(step)).take_while(move |n| {
+        if step > 0 {
+            *n < end || (inclusive && *n == end)
+        } else {
+            *n > end || (inclusive && *n == end)
+        }
+    })
+}
+
+// The items a `for` loop or a comprehension iterates over.
+pub enum Iteration {
+    // The items of an array, a range or a string, produced without calling any function.
+    Values(Box<dyn Iterator<Item = Expr>>),
+    // A struct instance with a `next` field: the function is called for each item, until it returns nil.
+    Next(Expr),
+}
+
+impl Iteration {
+    // Starts iterating over a value, stopping the script if it can't be iterated.
+    // The location is the one of the collection, pointed at when it can't be iterated.
+    pub fn new(collection: Expr, location: &Location) -> Eval<Self> {
+        Ok(match collection {
+            // The items of an array are copied one at a time, the array itself is shared.
+            Expr::Array(items) => Iteration::Values(Box::new((0..items.len()).map(move |i| items[i].clone()))),
+            // Ranges are iterated lazily, without building an array of all their values.
+            Expr::RangeValue(start, end, step, inclusive) => Iteration::Values(Box::new(
+                range_values(start, end, step, inclusive).map(|n| Expr::Constant(Atom::Number(n))),
+            )),
+            // Strings are iterated character by character.
+            Expr::Constant(Atom::String(string)) => Iteration::Values(Box::new(
+                string.chars().map(|c| Expr::Constant(Atom::Char(c))).collect::<Vec<_>>().into_iter(),
+            )),
+            Expr::Instance(ref name, ref fields) => match fields.iter().find(|(field, _)| field == "next") {
+                Some((_, next)) => Iteration::Next(next.clone()),
+                None => fail(
+                    location,
+                    format!("`{name}` is not iterable, it has no `next` field"),
+                    "cannot loop over this value",
+                )?,
+            },
+            // Stop the script if the collection can't be iterated.
+            _ => fail(
+                location,
+                format!("`{}` is not iterable", collection.type_name()),
+                "cannot loop over this value",
+            )?,
+        })
+    }
+
+    // Returns the next item, or `None` when there are no items left.
+    // An error thrown by a `next` function is returned as the item, for the loop to propagate it.
+    pub fn next(&mut self, context: &mut Context) -> Eval<Option<Expr>> {
+        Ok(match self {
+            Iteration::Values(items) => items.next(),
+            Iteration::Next(next) => match call(next.clone(), "next".to_string(), Vec::new(), context)? {
+                Expr::Constant(Atom::Nil) => None,
+                item => Some(item),
+            },
+        })
+    }
+}
+
+// Evaluates the expression of a comprehension for the current item, or returns `None` when the item
+// doesn't satisfy the condition. A `return` or `throw` is returned as-is.
+fn comprehension_value(
+    expr: &Expr,
+    condition: Option<&(Box<Expr>, Location)>,
+    context: &mut Context,
+) -> Eval<Option<Expr>> {
+    if let Some((condition, location)) = condition {
+        match interpreter_expr((**condition).clone(), context)? {
+            Expr::Constant(Atom::Boolean(true)) => {}
+            Expr::Constant(Atom::Boolean(false)) => return Ok(None),
+            signal if is_signal(&signal) => return Ok(Some(signal)),
+            value => {
+                return error::raise(ConditionTypeError::new(location.clone(), "if".to_string(), value.type_name()));
+            }
+        }
+    }
+    Ok(Some(interpreter_expr(expr.clone(), context)?))
+}
+
+// One step of the path leading from a variable to the value being assigned.
+enum PlaceStep {
+    Index(Expr),   // An element of an array: `arr[i]`, the index is already evaluated.
+    Field(String), // A field of a struct instance: `point.x`
+}
+
+// Resolves the target of an assignment into the name of the variable holding it and the steps
+// leading from that variable to the target. The location is the one of the assignment.
+fn resolve_place(target: Expr, location: &Location, context: &mut Context) -> Eval<(String, Vec<PlaceStep>)> {
+    match target {
+        Expr::Variable(name, _) => Ok((name, Vec::new())),
+        Expr::Get(array, index, _) => {
+            let (name, mut steps) = resolve_place(*array, location, context)?;
+            steps.push(PlaceStep::Index(interpreter_expr(*index, context)?));
+            Ok((name, steps))
+        }
+        Expr::Field(instance, field, _) => {
+            let (name, mut steps) = resolve_place(*instance, location, context)?;
+            steps.push(PlaceStep::Field(field));
+            Ok((name, steps))
+        }
+        // Like `f().x = 1`: the value assigned to wouldn't be stored anywhere.
+        _ => fail(
+            location,
+            "invalid left-hand side of assignment".to_string(),
+            "only variables, and their elements and fields, can be assigned",
+        ),
+    }
+}
+
+// Returns the value of the condition of an `if` or `until`, stopping the script if it isn't a boolean.
+pub fn condition(value: Expr, construct: &str, location: &Location) -> Eval<bool> {
+    match value {
+        Expr::Constant(Atom::Boolean(value)) => Ok(value),
+        value => error::raise(ConditionTypeError::new(location.clone(), construct.to_string(), value.type_name())),
+    }
+}
+
+// Checks that an operand of `and`/`or` evaluated to a boolean.
+pub fn boolean_operand(value: Expr, op: &LogicOp, location: &Location) -> Eval<bool> {
+    match value {
+        Expr::Constant(Atom::Boolean(b)) => Ok(b),
+        invalid => {
+            let name = match op {
+                LogicOp::And => "and",
+                LogicOp::Or => "or",
+            };
+            fail(
+                location,
+                format!("mismatched types: `{name}` expects `bool` operands, found `{}`", invalid.type_name()),
+                "expected `bool` on both sides",
+            )
+        }
+    }
+}
+
+// Checks that an evaluated index is an integer within the bounds of an array of the given length.
+fn array_index(index: &Expr, length: usize, location: &Location) -> Eval<usize> {
+    match index {
+        Expr::Constant(Atom::Number(n)) if *n >= 0 && (*n as usize) < length => Ok(*n as usize),
+        Expr::Constant(Atom::Number(n)) => fail_as(
+            "E1005",
+            location,
+            format!("index out of bounds: the length is {length} but the index is {n}"),
+            "index out of bounds",
+        ),
+        _ => fail(
+            location,
+            format!("mismatched types: an array index should be `int`, found `{}`", index.type_name()),
+            "expected an `int` index",
+        ),
+    }
+}
+
+// Assigns a value to a variable, array element or struct field, modifying it in place.
+// Constants, and the elements and fields of constants, can't be assigned.
+pub fn assign(target: Expr, value: Expr, location: Location, context: &mut Context) -> Eval<()> {
+    let (name, steps) = resolve_place(target, &location, context)?;
+    if context.is_constant(&name) {
+        return error::raise(ConstantAssignmentError::new(location, name));
+    }
+    let assigned = context.modify(&name, |mut place| {
+        for step in steps {
+            place = match (place, step) {
+                // An array shared with other values is copied before it is modified, so they keep
+                // their elements.
+                (Expr::Array(items), PlaceStep::Index(index)) => {
+                    let index = array_index(&index, items.len(), &location)?;
+                    &mut Rc::make_mut(items)[index]
+                }
+                (Expr::Instance(struct_name, fields), PlaceStep::Field(field)) => {
+                    match fields.iter_mut().find(|(name, _)| *name == field) {
+                        Some((_, value)) => value,
+                        None => return missing_field(struct_name, &field, &location),
+                    }
+                }
+                (invalid, PlaceStep::Index(_)) => return not_indexable(invalid, &location),
+                (invalid, PlaceStep::Field(field)) => return no_fields(invalid, &field, &location),
+            };
+        }
+        *place = value;
+        Ok(())
+    });
+    match assigned {
+        Some(assigned) => assigned,
+        None => not_found(&name, &location),
+    }
+}
+
+// Evaluates the expression giving the function of a call, along with the name of the call for the
+// call stack. A missing function called by name is reported by its name.
+fn callee(function: Expr, context: &mut Context) -> Eval<(String, Expr)> {
+    match function {
+        Expr::Variable(name, location) => {
+            let function = find_function(&name, &location, context)?;
+            Ok((name, function))
+        }
+        function => Ok(("<anonymous>".to_string(), interpreter_expr(function, context)?)),
+    }
+}
+
+// Looks up the function called by name, stopping the script if there is none.
+pub fn find_function(name: &str, location: &Location, context: &Context) -> Eval {
+    context.get(name).map_or_else(|| function_not_found(name, location), Ok)
+}
+
+pub fn function_not_found<T>(name: &str, location: &Location) -> Eval<T> {
+    fail_as("E1002", location, format!("cannot find function `{name}` in this scope"), "not found in this scope")
+}
+
+// Calls a function value, the arguments are already evaluated.
+// The name of the function is the one shown in the call stack when the recursion limit is reached.
+pub fn call(function: Expr, name: String, evaluated_args: Vec<Expr>, context: &mut Context) -> Eval {
+    match function {
+        Expr::Builtin(func) => func(evaluated_args, context),
+        Expr::Native(native) => (native.function)(evaluated_args, context),
+        // Calling a struct constructs a new instance, one argument per field.
+        Expr::Struct(struct_name, fields, _) => {
+            if fields.len() != evaluated_args.len() {
+                return context.fail(format!(
+                    "struct `{struct_name}` has {} fields, but {} values were given",
+                    fields.len(),
+                    evaluated_args.len()
+                ));
+            }
+            let values = fields.into_iter().zip(evaluated_args).collect();
+            Ok(Expr::Instance(struct_name, values))
+        }
+        closure @ (Expr::Closure(..) | Expr::Compiled(..)) => call_closure(name, closure, evaluated_args, context),
+        // Calling a variant gives it the values it carries: `Result::Ok(value)`.
+        Expr::Variant(enum_name, variant, values) if values.is_empty() => {
+            let fields = match context.get(&enum_name) {
+                Some(Expr::Enum(_, variants)) => variants.into_iter().find(|(name, _)| *name == variant).map(|(_, fields)| fields),
+                _ => None,
+            };
+            let Some(fields) = fields else {
+                return context.fail(format!("cannot find `{enum_name}::{variant}`"));
+            };
+            if fields.len() != evaluated_args.len() {
+                return context.fail(format!(
+                    "variant `{enum_name}::{variant}` carries {} values, but {} were given",
+                    fields.len(),
+                    evaluated_args.len()
+                ));
+            }
+            Ok(Expr::Variant(enum_name, variant, evaluated_args))
+        }
+        invalid => context.fail(format!("`{name}` is not a function, found `{}`", invalid.type_name())),
+    }
+}
+
+// Matches a value against the variables of a `let` or `for`, returning the value of each variable.
+// Arrays are unpacked element by element, and must have exactly as many elements as the binding.
+pub fn destructure(binding: Binding, value: Expr) -> Eval<Vec<(String, Expr)>> {
+    let mut bindings = Vec::new();
+    destructure_into(binding, value, &mut bindings)?;
+    Ok(bindings)
+}
+
+fn destructure_into(binding: Binding, value: Expr, bindings: &mut Vec<(String, Expr)>) -> Eval<()> {
+    match (binding, value) {
+        (Binding::Name(name), value) => bindings.push((name, value)),
+        (Binding::Wildcard, _) => {}
+        (Binding::Array(names, _), Expr::Array(items)) if names.len() == items.len() => {
+            for (binding, item) in names.into_iter().zip(Rc::unwrap_or_clone(items)) {
+                destructure_into(binding, item, bindings)?;
+            }
+        }
+        (Binding::Array(names, location), Expr::Array(items)) => {
+            let message = format!("cannot unpack an array of {} elements into {} variables", items.len(), names.len());
+            let label = format!("expected {} elements", names.len());
+            return error::raise(EvaluationError::new(location, message, label));
+        }
+        (Binding::Array(_, location), value) => {
+            let message = format!("cannot unpack `{value}`, it is not an array");
+            let label = format!("expected an array, found `{}`", value.type_name());
+            return error::raise(EvaluationError::new(location, message, label));
+        }
+    }
+    Ok(())
+}
+
+// Declares the parameters of a function in the current scope, bound to the given arguments.
+// A parameter without an argument takes its default value, which is evaluated at every call, after
+// the parameters before it are declared, so it can refer to them: `fn f(a, b = a * 2)`.
+fn bind_arguments(name: &str, parameters: &[Parameter], args: Vec<Expr>, context: &mut Context) -> Eval<()> {
+    let required = parameters.iter().take_while(|parameter| parameter.default.is_none()).count();
+    if args.len() < required || args.len() > parameters.len() {
+        let expected = if required == parameters.len() {
+            required.to_string()
+        } else {
+            format!("{required} to {}", parameters.len())
+        };
+        let message = format!("function `{name}` expects {expected} arguments, but {} were given", args.len());
+        return context.fail_as("E1004", message);
+    }
+    let mut args = args.into_iter();
+    for parameter in parameters {
+        let value = match (args.next(), &parameter.default) {
+            (Some(arg), _) => arg,
+            (None, Some(default)) => interpreter_expr(default.clone(), context)?,
+            (None, None) => unreachable!("missing arguments are checked above"),
+        };
+        if let Some(annotation) = &parameter.annotation {
+            check_type(&value, annotation, || format!("argument `{}` of `{name}`", parameter.name))?;
+        }
+        context.insert(parameter.name.clone(), value);
+    }
+    Ok(())
+}
+
+// Tells whether a value has the given type.
+fn has_type(value: &Expr, ty: &Type) -> bool {
+    match (ty, value) {
+        (Type::Int, Expr::Constant(Atom::Number(_)))
+        | (Type::Float, Expr::Constant(Atom::Float(_)))
+        | (Type::String, Expr::Constant(Atom::String(_)))
+        | (Type::Char, Expr::Constant(Atom::Char(_)))
+        | (Type::Bool, Expr::Constant(Atom::Boolean(_)))
+        | (Type::Nil, Expr::Constant(Atom::Nil))
+        | (Type::Array, Expr::Array(_))
+        | (Type::Range, Expr::RangeValue(..))
+        | (Type::Function, Expr::Closure(..) | Expr::Compiled(..) | Expr::Builtin(_) | Expr::Native(_)) => true,
+        (Type::Named(name), Expr::Instance(struct_name, _)) => name == struct_name,
+        (Type::Named(name), Expr::Variant(enum_name, _, _)) => name == enum_name,
+        _ => false,
+    }
+}
+
+// Stops the script with a type mismatch if the value doesn't have the annotated type.
+// The subject describes the value in the error, like "argument `a` of `add`".
+fn check_type(value: &Expr, annotation: &TypeAnnotation, subject: impl FnOnce() -> String) -> Eval<()> {
+    if !has_type(value, &annotation.ty) {
+        return error::raise(TypeMismatchError::new(
+            annotation.location.clone(),
+            subject(),
+            annotation.ty.to_string(),
+            value.type_name(),
+        ));
+    }
+    Ok(())
+}
+
+// Calls a user-defined function.
+// A call in tail position (`return f(x)`) doesn't nest another call: the body hands it back as a
+// `TailCall`, which is performed here in a loop, reusing the frame of the current call. This way
+// functions that recurse in tail position don't grow the stack or count towards the recursion limit.
+fn call_closure(mut name: String, mut closure: Expr, mut args: Vec<Expr>, context: &mut Context) -> Eval {
+    // A tail call replaces the call site, which is restored for the caller when the call ends.
+    let call_site = context.call_site();
+    // Too many nested calls stop the program before the Rust stack overflows.
+    context.push_call(&name)?;
+    // The return types of the functions that made a tail call: their result is the result of the
+    // last function called, so it must have all of these types.
+    let mut return_types = Vec::new();
+    let result = loop {
+        // The body runs in the environment of the closure, in a new scope where each parameter is
+        // bound to its argument. The variables of the caller aren't visible.
+        // The body of a function created by compiled code runs on the virtual machine.
+        let (result, return_type) = match closure {
+            Expr::Closure(parameters, return_type, body, Some(environment)) => {
+                let caller = context.replace_environment(environment);
+                context.push_scope();
+                bind_arguments(&name, &parameters, args, context)?;
+                let result = interpreter_statements(body, context)?;
+                context.replace_environment(caller);
+                (result, return_type)
+            }
+            Expr::Compiled(function, environment) => {
+                let caller = context.replace_environment(environment);
+                context.push_layout(function.layout.clone());
+                bind_arguments(&name, &function.parameters, args, context)?;
+                let result = crate::bytecode::run(&function.code, context)?;
+                context.replace_environment(caller);
+                (result, function.return_type.clone())
+            }
+            _ => unreachable!("only evaluated closures are called by `call_closure`"),
+        };
+        return_types.extend(return_type.map(|return_type| (name.clone(), return_type)));
+        match result {
+            Expr::TailCall(callee, callee_closure, callee_args, location) => {
+                context.enter_call_site(location);
+                context.replace_call(&callee);
+                (name, closure, args) = (callee, *callee_closure, callee_args);
+            }
+            Expr::Return(expr) => break *expr,
+            // A thrown error keeps propagating to the caller.
+            thrown @ Expr::Throw(..) => break thrown,
+            // A loop can't be left from a function it called.
+            Expr::Break(_, _, location) => return error::raise(LoopControlError::new(location, "break".to_string())),
+            Expr::Continue(_, location) => {
+                return error::raise(LoopControlError::new(location, "continue".to_string()));
+            }
+            _ => break Expr::Void,
+        }
+    };
+    if !matches!(result, Expr::Throw(..)) {
+        for (name, return_type) in &return_types {
+            check_type(&result, return_type, || format!("the result of `{name}`"))?;
+        }
+    }
+    context.pop_call();
+    context.leave_call_site(call_site);
+    Ok(result)
+}
+
+// Finds a method declared in an `impl` block of the struct of a value.
+fn struct_method(value: &Expr, name: &str, context: &Context) -> Option<Expr> {
+    let Expr::Instance(struct_name, _) = value else {
+        return None;
+    };
+    let Some(Expr::Struct(_, _, methods)) = context.get(struct_name) else {
+        return None;
+    };
+    methods.into_iter().find(|(method, _)| method == name).map(|(_, closure)| closure)
+}
+
+// Tells whether two values are equal, comparing them like `==` does.
+fn values_equal(left: &Expr, right: &Expr, location: &Location, context: &mut Context) -> Eval<bool> {
+    let comparison = compare(left.clone(), Operator::Equal, right.clone(), location, context)?;
+    Ok(matches!(comparison, Expr::Constant(Atom::Boolean(true))))
+}
+
+// Stops the script when values that aren't ordered are compared with `<`, `>`, `<=` or `>=`.
+fn unordered<T>(kind: &str, location: &Location) -> Eval<T> {
+    fail(
+        location,
+        format!("{kind} can only be compared with `==` and `!=`"),
+        "these values aren't ordered",
+    )
+}
+
+// Compares two values with a comparison operator. The location is the one of the operator.
+pub fn compare(left: Expr, operator: Operator, right: Expr, location: &Location, context: &mut Context) -> Eval {
+    // Struct instances are compared by the `eq` and `lt` functions, when they are defined.
+    // Any value can still be checked for equality with nil.
+    if !matches!((&left, &right), (Expr::Constant(Atom::Nil), _) | (_, Expr::Constant(Atom::Nil))) {
+        let (name, operands, negate) = match operator {
+            Operator::Equal => ("eq", [&left, &right], false),
+            Operator::NotEqual => ("eq", [&left, &right], true),
+            Operator::LessThan => ("lt", [&left, &right], false),
+            Operator::GreaterThan => ("lt", [&right, &left], false),
+            Operator::LessThanEqual => ("lt", [&right, &left], true),
+            Operator::GreaterThanEqual => ("lt", [&left, &right], true),
+        };
+        if let Some(result) = overloaded(name, &operands, location, context)? {
+            return match result {
+                Expr::Constant(Atom::Boolean(result)) => Ok(Expr::Constant(Atom::Boolean(result != negate))),
+                signal if is_signal(&signal) => Ok(signal),
+                invalid => fail(
+                    location,
+                    format!("mismatched types: `{name}` should return `bool`, found `{}`", invalid.type_name()),
+                    &format!("compared with `{name}`"),
+                ),
+            };
+        }
+    }
+    // When an integer is compared with a float, the integer is converted to a float.
+    let (left, right) = match (left, right) {
+        (Expr::Constant(Atom::Number(left)), right @ Expr::Constant(Atom::Float(_))) => {
+            (Expr::Constant(Atom::Float(left as f64)), right)
+        }
+        (left @ Expr::Constant(Atom::Float(_)), Expr::Constant(Atom::Number(right))) => {
+            (left, Expr::Constant(Atom::Float(right as f64)))
+        }
+        pair => pair,
+    };
+    Ok(match (&left, operator, &right) {
+        (
+            Expr::Constant(Atom::Number(left)),
+            operator,
+            Expr::Constant(Atom::Number(right)),
+        ) => match operator {
+            Operator::LessThan => Expr::Constant(Atom::Boolean(left < right)),
+            Operator::LessThanEqual => Expr::Constant(Atom::Boolean(left <= right)),
+            Operator::GreaterThan => Expr::Constant(Atom::Boolean(left > right)),
+            Operator::GreaterThanEqual => Expr::Constant(Atom::Boolean(left >= right)),
+            Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
+            Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
+        },
+        (
+            Expr::Constant(Atom::Float(left)),
+            operator,
+            Expr::Constant(Atom::Float(right)),
+        ) => match operator {
+            Operator::LessThan => Expr::Constant(Atom::Boolean(left < right)),
+            Operator::LessThanEqual => Expr::Constant(Atom::Boolean(left <= right)),
+            Operator::GreaterThan => Expr::Constant(Atom::Boolean(left > right)),
+            Operator::GreaterThanEqual => Expr::Constant(Atom::Boolean(left >= right)),
+            Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
+            Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
+        },
+        // Characters are ordered by their code point.
+        (
+            Expr::Constant(Atom::Char(left)),
+            operator,
+            Expr::Constant(Atom::Char(right)),
+        ) => match operator {
+            Operator::LessThan => Expr::Constant(Atom::Boolean(left < right)),
+            Operator::LessThanEqual => Expr::Constant(Atom::Boolean(left <= right)),
+            Operator::GreaterThan => Expr::Constant(Atom::Boolean(left > right)),
+            Operator::GreaterThanEqual => Expr::Constant(Atom::Boolean(left >= right)),
+            Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
+            Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
+        },
+        // Branch for booleans.
+        (
+            Expr::Constant(Atom::Boolean(left)),
+            operator,
+            Expr::Constant(Atom::Boolean(right)),
+        ) => match operator {
+            Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
+            Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
+            _ => unordered("booleans", location)?,
+        },
+        // New branch for comparing strings.
+        (
+            Expr::Constant(Atom::String(left)),
+            operator,
+            Expr::Constant(Atom::String(right)),
+        ) => match operator {
+            Operator::Equal => Expr::Constant(Atom::Boolean(left == right)),
+            Operator::NotEqual => Expr::Constant(Atom::Boolean(left != right)),
+            _ => unordered("strings", location)?,
+        },
+        // Branch for enum variants.
+        (
+            Expr::Variant(left_name, left_variant, left_values),
+            operator,
+            Expr::Variant(right_name, right_variant, right_values),
+        ) => {
+            let mut equal =
+                left_name == right_name && left_variant == right_variant && left_values.len() == right_values.len();
+            for (left, right) in left_values.iter().zip(right_values) {
+                if !equal {
+                    break;
+                }
+                equal = values_equal(left, right, location, context)?;
+            }
+            match operator {
+                Operator::Equal => Expr::Constant(Atom::Boolean(equal)),
+                Operator::NotEqual => Expr::Constant(Atom::Boolean(!equal)),
+                _ => unordered("enum variants", location)?,
+            }
+        }
+        // Any value can be checked for equality with nil.
+        (Expr::Constant(Atom::Nil), operator, _) | (_, operator, Expr::Constant(Atom::Nil)) => {
+            let equal = matches!(
+                (&left, &right),
+                (Expr::Constant(Atom::Nil), Expr::Constant(Atom::Nil))
+            );
+            match operator {
+                Operator::Equal => Expr::Constant(Atom::Boolean(equal)),
+                Operator::NotEqual => Expr::Constant(Atom::Boolean(!equal)),
+                _ => unordered("`nil`", location)?,
+            }
+        }
+        _ => fail(
+            location,
+            format!("cannot compare `{}` with `{}`", left.type_name(), right.type_name()),
+            "these values can't be compared",
+        )?,
+    })
+}
+
+// Calls the function overloading an operator, named after it (`add` for `+`, `eq` for `==`...),
+// when one of the operands is a struct instance. The function is given the operands in order.
+// A method with that name in the struct of the first operand is preferred to a function.
+// Returns `None` when no operand is an instance or the function isn't defined, so the operator
+// behaves as usual. The location is the one of the operator, the call site of the function.
+fn overloaded(name: &str, operands: &[&Expr], location: &Location, context: &mut Context) -> Eval<Option<Expr>> {
+    if !operands.iter().any(|operand| matches!(operand, Expr::Instance(..))) {
+        return Ok(None);
+    }
+    let Some(function) = struct_method(operands[0], name, context).or_else(|| context.get(name)) else {
+        return Ok(None);
+    };
+    let operands = operands.iter().map(|&operand| operand.clone()).collect();
+    call_at(location, function, name.to_string(), operands, context).map(Some)
+}
+
+// Calls a function value from the given call site, which the errors of the call point at.
+pub fn call_at(location: &Location, function: Expr, name: String, args: Vec<Expr>, context: &mut Context) -> Eval {
+    let previous = context.enter_call_site(location.clone());
+    let result = call(function, name, args, context)?;
+    context.leave_call_site(previous);
+    Ok(result)
+}
+
+// Formats an interpolated value following its format specifier.
+pub fn format_value(value: &Expr, spec: Option<&FormatSpec>) -> String {
+    let Some(spec) = spec else {
+        return value.to_string();
+    };
+    let number = matches!(value, Expr::Constant(Atom::Number(_) | Atom::Float(_)));
+    let text = match (value, spec.precision) {
+        (Expr::Constant(Atom::Float(n)), Some(precision)) => format!("{n:.precision$}"),
+        (Expr::Constant(Atom::Number(n)), Some(precision)) => format!("{:.precision$}", *n as f64),
+        (value, Some(precision)) => value.to_string().chars().take(precision).collect(),
+        (value, None) => value.to_string(),
+    };
+    let padding = spec.width.saturating_sub(text.chars().count());
+    // Zeros go between the sign and the digits: `-0042`.
+    if spec.zero && number {
+        let (sign, digits) = text.split_at(if text.starts_with('-') { 1 } else { 0 });
+        return format!("{sign}{}{digits}", "0".repeat(padding));
+    }
+    let fill = |count: usize| spec.fill.to_string().repeat(count);
+    match spec.align.unwrap_or(if number { Align::Right } else { Align::Left }) {
+        Align::Left => format!("{text}{}", fill(padding)),
+        Align::Right => format!("{}{text}", fill(padding)),
+        Align::Center => format!("{}{text}{}", fill(padding / 2), fill(padding - padding / 2)),
+    }
+}
+
+// Checks whether a (fully evaluated) value is matched by a pattern of a `match` arm.
+// The variables bound by the pattern are added to `bindings`.
+pub fn pattern_matches(pattern: &Pattern, value: &Expr, bindings: &mut Vec<(String, Expr)>) -> bool {
+    match (pattern, value) {
+        (Pattern::Wildcard, _) => true,
+        (Pattern::Binding(name), value) => {
+            bindings.push((name.clone(), value.clone()));
+            true
+        }
+        (Pattern::Literal(literal), Expr::Constant(atom)) => literal == atom,
+        // Without patterns for its values, a variant pattern matches whatever values the variant carries.
+        (Pattern::Variant(name, variant, patterns), Expr::Variant(value_name, value_variant, values)) => {
+            name == value_name
+                && variant == value_variant
+                && patterns.as_ref().is_none_or(|patterns| {
+                    patterns.len() == values.len()
+                        && patterns.iter().zip(values).all(|(pattern, value)| pattern_matches(pattern, value, bindings))
+                })
+        }
+        (Pattern::Array(patterns), Expr::Array(items)) => {
+            match patterns.iter().position(|pattern| matches!(pattern, Pattern::Rest(_))) {
+                None => {
+                    patterns.len() == items.len()
+                        && patterns.iter().zip(items.iter()).all(|(pattern, item)| pattern_matches(pattern, item, bindings))
+                }
+                // The patterns before and after `...` match the first and the last elements,
+                // and `...` takes the ones in between.
+                Some(rest) => {
+                    let after = patterns.len() - rest - 1;
+                    if items.len() < rest + after {
+                        return false;
+                    }
+                    let rest_end = items.len() - after;
+                    let Pattern::Rest(name) = &patterns[rest] else { unreachable!() };
+                    if let Some(name) = name {
+                        bindings.push((name.clone(), Expr::Array(Rc::new(items[rest..rest_end].to_vec()))));
+                    }
+                    patterns[..rest].iter().zip(&items[..rest])
+                        .chain(patterns[rest + 1..].iter().zip(&items[rest_end..]))
+                        .all(|(pattern, item)| pattern_matches(pattern, item, bindings))
+                }
+            }
+        }
+        (Pattern::Struct(name, patterns), Expr::Instance(struct_name, fields)) => {
+            name == struct_name
+                && patterns.iter().all(|(field, pattern)| {
+                    fields
+                        .iter()
+                        .find(|(name, _)| name == field)
+                        .is_some_and(|(_, value)| pattern_matches(pattern, value, bindings))
+                })
+        }
+        _ => false,
+    }
+}
+
+// Applies an arithmetic operator to two values, or calls the function overloading it.
+// The location is the one of the operator.
+pub fn binary(left: Expr, op: BinOp, right: Expr, location: &Location, context: &mut Context) -> Eval {
+    let name = match op {
+        BinOp::Add => Some("add"),
+        BinOp::Sub => Some("sub"),
+        BinOp::Mul => Some("mul"),
+        BinOp::Div => Some("div"),
+        BinOp::Pow => None,
+    };
+    if let Some(name) = name
+        && let Some(result) = overloaded(name, &[&left, &right], location, context)?
+    {
+        return Ok(result);
+    }
+    Ok(match (&left, &right) {
+        // A negative integer exponent can't produce an integer result.
+        (Expr::Constant(Atom::Number(l)), Expr::Constant(Atom::Number(r)))
+            if op == BinOp::Pow && *r < 0 => {
+            Expr::Constant(Atom::Float((*l as f64).powf(*r as f64)))
+        }
+        (Expr::Constant(Atom::Number(l)), Expr::Constant(Atom::Number(r))) => {
+            if op == BinOp::Div && *r == 0 {
+                fail_as("E1006", location, "attempt to divide by zero".to_string(), "division by zero")?
+            }
+            let result = match op {
+                BinOp::Add => l.checked_add(*r),
+                BinOp::Sub => l.checked_sub(*r),
+                BinOp::Mul => l.checked_mul(*r),
+                BinOp::Div => l.checked_div(*r),
+                BinOp::Pow => integer_power(*l, *r),
+            };
+            match result {
+                Some(result) => Expr::Constant(Atom::Number(result)),
+                None => overflow(location)?,
+            }
+        }
+        // If you also want to support floating-point arithmetic, you can add a branch:
+        (Expr::Constant(Atom::Float(l)), Expr::Constant(Atom::Float(r))) => {
+            let result = match op {
+                BinOp::Add => l + r,
+                BinOp::Sub => l - r,
+                BinOp::Mul => l * r,
+                BinOp::Div => {
+                    if *r == 0.0 {
+                        fail_as("E1006", location, "attempt to divide by zero".to_string(), "division by zero")?
+                    } else {
+                        l / r
+                    }
+                }
+                BinOp::Pow => l.powf(*r),
+            };
+            Expr::Constant(Atom::Float(result))
+        }
+        (Expr::Constant(Atom::String(l)), Expr::Constant(Atom::String(r))) => {
+            let result = match op {
+                BinOp::Add => format!("{}{}", l, r),
+                _ => fail(location, "strings can only be joined with `+`".to_string(), "not supported by strings")?,
+            };
+            Expr::Constant(Atom::String(result.into()))
+        }
+        // A character can be joined to a string, on either side.
+        (Expr::Constant(Atom::String(l)), Expr::Constant(Atom::Char(r))) => {
+            let result = match op {
+                BinOp::Add => format!("{}{}", l, r),
+                _ => fail(location, "strings can only be joined with `+`".to_string(), "not supported by strings")?,
+            };
+            Expr::Constant(Atom::String(result.into()))
+        }
+        (Expr::Constant(Atom::Char(l)), Expr::Constant(Atom::String(r))) => {
+            let result = match op {
+                BinOp::Add => format!("{}{}", l, r),
+                _ => fail(location, "strings can only be joined with `+`".to_string(), "not supported by strings")?,
+            };
+            Expr::Constant(Atom::String(result.into()))
+        }
+        _ => fail(
+            location,
+            format!("cannot apply an arithmetic operator to `{}` and `{}`", left.type_name(), right.type_name()),
+            "only numbers are supported",
+        )?,
+    })
+}
+
+// Raises an integer to a positive power, or returns `None` when the result is too large for an
+// `int`. Only 0, 1 and -1 can be raised to a power larger than a `u32`.
+fn integer_power(base: i64, exponent: i64) -> Option<i64> {
+    match (u32::try_from(exponent), base) {
+        (Ok(exponent), _) => base.checked_pow(exponent),
+        (Err(_), 0 | 1) => Some(base),
+        (Err(_), -1) => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+        (Err(_), _) => None,
+    }
+}
+
+// Fails with the error of an integer result too large for an `int`, which is never wrapped around.
+fn overflow<T>(location: &Location) -> Eval<T> {
+    fail_as("E1017", location, "attempt to compute a number too large for an `int`".to_string(), "overflows an `int`")
+}
+
+// Applies a unary operator to a value, or calls the function overloading the negation.
+pub fn unary(op: UnaryOp, value: Expr, location: &Location, context: &mut Context) -> Eval {
+    if op == UnaryOp::Neg
+        && let Some(result) = overloaded("neg", &[&value], location, context)?
+    {
+        return Ok(result);
+    }
+    match (op, value) {
+        (UnaryOp::Neg, Expr::Constant(Atom::Number(n))) => match n.checked_neg() {
+            Some(n) => Ok(Expr::Constant(Atom::Number(n))),
+            None => overflow(location),
+        },
+        (UnaryOp::Neg, Expr::Constant(Atom::Float(n))) => Ok(Expr::Constant(Atom::Float(-n))),
+        (UnaryOp::Not, Expr::Constant(Atom::Boolean(b))) => Ok(Expr::Constant(Atom::Boolean(!b))),
+        (UnaryOp::Neg, invalid) => fail(
+            location,
+            format!("cannot negate a value of type `{}`", invalid.type_name()),
+            "only numbers can be negated",
+        ),
+        (UnaryOp::Not, invalid) => fail(
+            location,
+            format!("mismatched types: `not` expects a `bool`, found `{}`", invalid.type_name()),
+            "expected `bool`",
+        ),
+    }
+}
+
+// Calls a method on an evaluated receiver. A struct field holding a function, or a function of a
+// module, is called with the arguments, otherwise `receiver.method(args)` calls the method of the
+// struct, or else the function, named `method` with the receiver as first argument, so
+// `s.uppercase()` is the same as `uppercase(s)`.
+pub fn call_method(receiver: Expr, method: String, mut args: Vec<Expr>, location: &Location, context: &mut Context) -> Eval {
+    if let Expr::Instance(_, fields) = &receiver
+        && let Some((_, function)) = fields.iter().find(|(field, _)| *field == method)
+    {
+        let function = function.clone();
+        return call_at(location, function, method, args, context);
+    }
+    // A function of a module is called with the arguments only.
+    if let Expr::Module(name, declared) = receiver {
+        let Some((_, function)) = declared.into_iter().find(|(name, _)| *name == method) else {
+            let message = format!("cannot find `{method}` in module `{name}`");
+            return fail_as("E1001", location, message, "not found in this module");
+        };
+        return call_at(location, function, method, args, context);
+    }
+    let Some(function) = struct_method(&receiver, &method, context).or_else(|| context.get(&method)) else {
+        return fail(
+            location,
+            format!("no method named `{method}` found for `{}`", receiver.type_name()),
+            "method not found",
+        );
+    };
+    args.insert(0, receiver);
+    call_at(location, function, method, args, context)
+}
+
+// Builds the range between two evaluated bounds, which must be integers.
+pub fn range(start: Expr, end: Expr, inclusive: bool, location: &Location) -> Eval {
+    match (&start, &end) {
+        (Expr::Constant(Atom::Number(start)), Expr::Constant(Atom::Number(end))) => {
+            Ok(Expr::RangeValue(*start, *end, 1, inclusive))
+        }
+        _ => fail(
+            location,
+            format!(
+                "mismatched types: the bounds of a range should be `int`, found `{}` and `{}`",
+                start.type_name(),
+                end.type_name()
+            ),
+            "expected `int` bounds",
+        ),
+    }
+}
+
+// Returns the element of an array at an evaluated index, or the slice at a range.
+pub fn element(array: Expr, index: Expr, location: &Location) -> Eval {
+    match (array, index) {
+        // Indexing with a range takes a slice: a new array with the elements in the range.
+        (Expr::Array(items), Expr::RangeValue(start, end, step, inclusive)) => {
+            let slice = range_values(start, end, step, inclusive)
+                .map(|i| Ok(items[array_index(&Expr::Constant(Atom::Number(i)), items.len(), location)?].clone()))
+                .collect::<Eval<_>>()?;
+            Ok(Expr::Array(Rc::new(slice)))
+        }
+        (Expr::Array(items), index) => {
+            // Retrieve the element at the given index.
+            let index = array_index(&index, items.len(), location)?;
+            Ok(items[index].clone())
+        }
+        (invalid, _) => not_indexable(&invalid, location),
+    }
+}
+
+// Returns a field of a struct instance, or something a module declares.
+pub fn field(value: Expr, field: &str, location: &Location) -> Eval {
+    match value {
+        Expr::Instance(name, fields) => match fields.into_iter().find(|(name, _)| name == field) {
+            Some((_, value)) => Ok(value),
+            None => missing_field(&name, field, location),
+        },
+        Expr::Module(name, declared) => match declared.into_iter().find(|(name, _)| name == field) {
+            Some((_, value)) => Ok(value),
+            None => {
+                let message = format!("cannot find `{field}` in module `{name}`");
+                fail_as("E1001", location, message, "not found in this module")
+            }
+        },
+        invalid => no_fields(&invalid, field, location),
+    }
+}
+
+// Resolves a path like `Color::Red` to the variant it names, or `Point::new` to a method.
+pub fn path(name: String, variant: String, location: &Location, context: &Context) -> Eval {
+    match context.get(&name) {
+        Some(Expr::Enum(_, variants)) if variants.iter().any(|(name, _)| *name == variant) => {
+            Ok(Expr::Variant(name, variant, Vec::new()))
+        }
+        Some(Expr::Struct(_, _, methods)) => match methods.into_iter().find(|(method, _)| *method == variant) {
+            Some((_, closure)) => Ok(closure),
+            None => fail(location, format!("no method named `{variant}` on struct `{name}`"), "method not found"),
+        },
+        Some(Expr::Enum(_, _)) => {
+            fail(location, format!("no variant named `{variant}` in enum `{name}`"), "variant not found")
+        }
+        Some(invalid) => fail(
+            location,
+            format!("expected enum or struct, found `{}`", invalid.type_name()),
+            "not an enum or a struct",
+        ),
+        None => not_found(&name, location),
+    }
+}
+
+// Adds methods, already turned into functions, to the definition of a struct.
+// The methods implementing a trait must include all the methods of the trait.
+pub fn implement(
+    name: String,
+    trait_name: Option<String>,
+    methods: Vec<(String, Expr)>,
+    location: Location,
+    context: &mut Context,
+) -> Eval<()> {
+    if let Some(trait_name) = trait_name {
+        let required = match context.get(&trait_name) {
+            Some(Expr::Trait(_, required)) => required,
+            Some(invalid) => fail(
+                &location,
+                format!("expected trait, found `{}`", invalid.type_name()),
+                "not a trait",
+            )?,
+            None => fail(&location, format!("cannot find trait `{trait_name}` in this scope"), "not found")?,
+        };
+        let missing: Vec<String> = required
+            .into_iter()
+            .filter(|method| !methods.iter().any(|(name, _)| name == method))
+            .collect();
+        if !missing.is_empty() {
+            return error::raise(MissingMethodsError::new(location, trait_name, name, missing));
+        }
+    }
+    let added = context.modify(&name, |definition| {
+        let Expr::Struct(_, _, declared) = definition else {
+            return fail(
+                &location,
+                format!("methods can only be added to structs, but `{name}` is `{}`", definition.type_name()),
+                "not a struct",
+            );
+        };
+        for (method, function) in methods {
+            declared.retain(|(name, _)| *name != method);
+            declared.push((method, function));
+        }
+        Ok(())
+    });
+    match added {
+        Some(added) => added,
+        None => fail(&location, format!("cannot find struct `{name}` in this scope"), "not found"),
+    }
+}
+
+// The recursive function that evaluates an expression given the current context.
+// It returns a new expression representing the evaluated result.
+pub fn interpreter_expr(expr: Expr, context: &mut Context) -> Eval {
+    context.step()?;
+    // The expressions traced are written with their value once evaluated, see `trace`.
+    let Some((kind, location)) = context.tracer().and_then(|tracer| tracer.traced(&expr)) else {
+        return evaluate_expr(expr, context);
+    };
+    let value = evaluate_expr(expr, context)?;
+    context.trace(kind, &location, &value);
+    Ok(value)
+}
+
+fn evaluate_expr(expr: Expr, context: &mut Context) -> Eval {
+    // Use pattern matching on the expression to determine how to evaluate it.
+    Ok(match expr {
+        // For these variants, no further evaluation is needed so we return the expression as-is.
+        Expr::Void
+        | Expr::Closure(_, _, _, Some(_))
+        | Expr::Compiled(..)
+        | Expr::Instance(_, _)
+        | Expr::Module(_, _)
+        | Expr::Variant(_, _, _)
+        | Expr::RangeValue(_, _, _, _)
+        | Expr::TailCall(..) => expr,
+        // A closure captures the scopes it is created in, so its body can use the variables around it.
+        Expr::Closure(parameters, return_type, body, None) => {
+            Expr::Closure(parameters, return_type, body, Some(context.environment()))
+        }
+        // For an array, evaluate each of its elements.
+        Expr::Array(items) => {
+            let mut values = Vec::with_capacity(items.len());
+            for item in items.iter() {
+                values.push(eval!(item.clone(), context));
+            }
+            Expr::Array(Rc::new(values))
+        }
+        // Returning the result of a call to a user-defined function is a tail call: it is handed back
+        // to the function being left, which performs it without nesting (see `call_closure`).
+        Expr::Return(expr) => match *expr {
+            Expr::Call(function, args, location) => {
+                let (name, function) = callee(*function, context)?;
+                if is_signal(&function) {
+                    return Ok(function);
+                }
+                let mut evaluated_args = Vec::with_capacity(args.len());
+                for arg in args {
+                    evaluated_args.push(eval!(arg, context));
+                }
+                match function {
+                    closure @ (Expr::Closure(..) | Expr::Compiled(..)) => {
+                        Expr::TailCall(name, Box::new(closure), evaluated_args, location)
+                    }
+                    function => match call_at(&location, function, name, evaluated_args, context)? {
+                        thrown @ Expr::Throw(..) => thrown,
+                        value => Expr::Return(Box::new(value)),
+                    },
+                }
+            }
+            // For other return expressions, evaluate the inner expression and re-wrap it.
+            expr => Expr::Return(Box::new(eval!(expr, context))),
+        },
+        // For a throw expression, evaluate the error and re-wrap it, so it propagates like a return.
+        Expr::Throw(expr, location) => Expr::Throw(Box::new(eval!(*expr, context)), location),
+        // Evaluate an assertion, stopping the script with the message when the condition is false.
+        Expr::Assert(condition, message, location) => match eval!(*condition, context) {
+            Expr::Constant(Atom::Boolean(true)) => Expr::Void,
+            Expr::Constant(Atom::Boolean(false)) => {
+                let message = match message {
+                    Some(message) => Some(eval!(*message, context).to_string()),
+                    None => None,
+                };
+                return error::raise(AssertionError::new(location, message));
+            }
+            condition => {
+                return error::raise(ConditionTypeError::new(location, "assert".to_string(), condition.type_name()));
+            }
+        },
+        // Evaluate a try block: if it throws, the error is bound to the given name and the catch block runs.
+        Expr::Try(body, name, catch_body) => {
+            let result = match interpreter_block(body, context)? {
+                // A tail call is performed inside the try block, so the errors it throws are caught.
+                Expr::TailCall(callee, closure, args, location) => {
+                    let previous = context.enter_call_site(location);
+                    let result = call_closure(callee, *closure, args, context)?;
+                    context.leave_call_site(previous);
+                    match result {
+                        thrown @ Expr::Throw(..) => thrown,
+                        value => Expr::Return(Box::new(value)),
+                    }
+                }
+                result => result,
+            };
+            match result {
+                Expr::Throw(error, _) => interpreter_block_with(catch_body, [(name, *error)], context)?,
+                result => result,
+            }
+        }
+        // Evaluate the interpolated expressions of a string and insert their values in it.
+        Expr::Interpolation(parts) => {
+            let mut result = String::new();
+            for part in parts {
+                match part {
+                    StringPart::Text(text) => result.push_str(&text),
+                    StringPart::Value(expr, spec) => {
+                        let value = eval!(expr, context);
+                        result.push_str(&format_value(&value, spec.as_ref()));
+                    }
+                }
+            }
+            Expr::Constant(Atom::String(result.into()))
+        }
+        // Constants evaluate to themselves.
+        Expr::Constant(_) => expr,
+        // Look up the value of a variable in the context.
+        Expr::Variable(name, location) => match context.get(&name) {
+            Some(value) => value,
+            None => return not_found(&name, &location),
+        },
+        // Evaluate a let-binding by evaluating the right-hand side and storing it in the context.
+        Expr::Let(binding, expr, _) => {
+            let expr = eval!(*expr, context);
+            for (name, value) in destructure(binding, expr)? {
+                context.insert(name, value);
+            }
+            // Let statements evaluate to void.
+            Expr::Void
+        }
+        // Evaluate a constant declaration like a let-binding, marking the names as constants.
+        Expr::Const(binding, expr, _) => {
+            let expr = eval!(*expr, context);
+            for (name, value) in destructure(binding, expr)? {
+                context.insert_constant(name, value);
+            }
+            Expr::Void
+        }
+        // Evaluate an assignment by evaluating the right-hand side and replacing the target with it.
+        Expr::Assign(target, value, location) => {
+            let value = eval!(*value, context);
+            assign(*target, value, location, context)?;
+            // Assignments evaluate to void.
+            Expr::Void
+        }
+        // Evaluate a comparison expression.
+        Expr::Compare(left, operator, right, location) => {
+            let left = eval!(*left, context);
+            let right = eval!(*right, context);
+            compare(left, operator, right, &location, context)?
+        }
+        // Evaluate an if-statement.
+        Expr::If(statement, then, otherwise, location) => {
+            // Evaluate the condition, which must give a boolean.
+            if condition(eval!(*statement, context), "if", &location)? {
+                // If true, evaluate all expressions in the "then" branch.
+                return interpreter_block(then, context);
+            } else if let Some(body) = otherwise {
+                // If false, and an "else" branch exists, evaluate it.
+                // An `else if` is an "else" branch holding another if-statement.
+                return interpreter_block(body, context);
+            }
+            // If no branch was taken, the if-statement doesn't yield a value.
+            Expr::Void
+        }
+        // Evaluate a function call.
+        // The function can be any expression: a name, a closure stored in an array, the result of
+        // another call...
+        Expr::Call(function, args, location) => {
+            let (name, function) = callee(*function, context)?;
+            if is_signal(&function) {
+                return Ok(function);
+            }
+            // Evaluate arguments.
+            let mut evaluated_args = Vec::with_capacity(args.len());
+            for arg in args {
+                evaluated_args.push(eval!(arg, context));
+            }
+            call_at(&location, function, name, evaluated_args, context)?
+        }
+        // Evaluate a method call. A struct field holding a function, or a function of a module, is
+        // called with the arguments, otherwise `receiver.method(args)` calls the method of the
+        // struct, or else the function, named `method` with the receiver as first argument, so
+        // `s.uppercase()` is the same as `uppercase(s)`.
+        Expr::MethodCall(receiver, method, args, location) => {
+            let receiver = eval!(*receiver, context);
+            let mut evaluated_args = Vec::with_capacity(args.len() + 1);
+            for arg in args {
+                evaluated_args.push(eval!(arg, context));
+            }
+            call_method(receiver, method, evaluated_args, &location, context)?
+        }
+        // Define a function by storing it as a closure in the context.
+        // The closure captures the scope it is stored in, so it can call itself.
+        Expr::Function(name, args, return_type, body) => {
+            let environment = Some(context.environment());
+            context.insert(name, Expr::Closure(args, return_type, body, environment));
+            Expr::Void
+        }
+        // Evaluate a for loop.
+        Expr::For(binding, collection, body, location, label) => run_loop(label.as_deref(), context, |context| {
+            let mut items = Iteration::new(eval!(*collection, context), &location)?;
+            while let Some(item) = items.next(context)? {
+                if is_signal(&item) {
+                    return Ok(item);
+                }
+                // Each iteration is a step, so a loop with an empty body still stops at the limits.
+                context.step()?;
+                // Each iteration runs in a new scope where the loop variables are bound to the current item.
+                // A `break`, `return` or `throw` leaves the loop.
+                let bindings = destructure(binding.clone(), item)?;
+                match loop_flow(interpreter_block_with(body.clone(), bindings, context)?, label.as_deref()) {
+                    LoopFlow::Next => {}
+                    LoopFlow::Exit(_) => break,
+                    LoopFlow::Leave(signal) => return Ok(signal),
+                }
+            }
+            Ok(Expr::Void)
+        })?,
+        // Evaluate a comprehension, collecting the value of the expression for every item that
+        // satisfies the condition.
+        Expr::Comprehension(expr, binding, collection, location, condition) => {
+            let mut items = Iteration::new(eval!(*collection, context), &location)?;
+            let mut values = Vec::new();
+            while let Some(item) = items.next(context)? {
+                if is_signal(&item) {
+                    return Ok(item);
+                }
+                // Like the body of a `for` loop, each item is a step and is evaluated in a new scope.
+                context.step()?;
+                context.push_scope();
+                for (name, value) in destructure(binding.clone(), item)? {
+                    context.insert(name, value);
+                }
+                let value = comprehension_value(&expr, condition.as_ref(), context)?;
+                context.pop_scope();
+                match value {
+                    Some(signal) if is_signal(&signal) => return Ok(signal),
+                    Some(value) => values.push(value),
+                    None => {}
+                }
+            }
+            Expr::Array(Rc::new(values))
+        }
+        // Evaluate a range expression, both bounds must be integers.
+        Expr::Range(start, end, inclusive, location) => {
+            let start = eval!(*start, context);
+            let end = eval!(*end, context);
+            range(start, end, inclusive, &location)?
+        }
+        // Evaluate an array element access.
+        // The array can be any expression, so indexing can be chained (`matrix[i][j]`).
+        Expr::Get(array, index, location) => {
+            let array = eval!(*array, context);
+            // The index can be any expression too (`arr[i + 1]`).
+            let index = eval!(*index, context);
+            element(array, index, &location)?
+        }
+        Expr::Until(statement, body, location, label) => run_loop(label.as_deref(), context, |context| {
+            // Loop until the condition evaluates to true.
+            loop {
+                // Evaluate the condition. Clone the condition so it can be used repeatedly.
+                if condition(eval!((*statement).clone(), context), "until", &location)? {
+                    break;
+                }
+                // Otherwise, run the body, a `break`, `return` or `throw` leaves the loop.
+                // We clone the body because it may be re-used in further iterations.
+                match loop_flow(interpreter_block(body.clone(), context)?, label.as_deref()) {
+                    LoopFlow::Next => {}
+                    LoopFlow::Exit(_) => break,
+                    LoopFlow::Leave(signal) => return Ok(signal),
+                }
+            }
+            Ok(Expr::Void)
+        })?,
+        // Evaluate a loop, running its body until a `break` gives the value of the loop.
+        Expr::Loop(body, label) => run_loop(label.as_deref(), context, |context| loop {
+            // Each iteration is a step, so `loop {}` still stops at the limits.
+            context.step()?;
+            match loop_flow(interpreter_block(body.clone(), context)?, label.as_deref()) {
+                LoopFlow::Next => {}
+                LoopFlow::Exit(value) => return Ok(value),
+                LoopFlow::Leave(signal) => return Ok(signal),
+            }
+        })?,
+        // Evaluate the value of a `break`, which is handed back to the enclosing loop.
+        Expr::Break(expr, label, location) => {
+            // `break name` leaves the loop labeled `name` if there is one, otherwise it breaks with
+            // the value of the variable `name`.
+            if label.is_none()
+                && let Expr::Variable(name, _) = &*expr
+                && context.has_label(name)
+            {
+                return Ok(Expr::Break(Box::new(Expr::Void), Some(name.clone()), location));
+            }
+            Expr::Break(Box::new(eval!(*expr, context)), label, location)
+        }
+        // A `continue` is handed back to the enclosing loop, which must have its label.
+        Expr::Continue(label, location) => {
+            if let Some(name) = &label
+                && !context.has_label(name)
+            {
+                return error::raise(UndeclaredLabelError::new(location, name.clone()));
+            }
+            Expr::Continue(label, location)
+        }
+        Expr::Logic(left_expr, op, right_expr, location) => {
+            // The right side is only evaluated when the left side doesn't decide the result.
+            let left = boolean_operand(eval!(*left_expr, context), &op, &location)?;
+            let result = match op {
+                LogicOp::And if !left => false,
+                LogicOp::Or if left => true,
+                _ => boolean_operand(eval!(*right_expr, context), &op, &location)?,
+            };
+            Expr::Constant(Atom::Boolean(result))
+        }
+        Expr::Unary(op, operand, location) => {
+            let value = eval!(*operand, context);
+            unary(op, value, &location, context)?
+        }
+        Expr::Binary(left_expr, op, right_expr, location) => {
+            let left = eval!(*left_expr, context);
+            let right = eval!(*right_expr, context);
+            binary(left, op, right, &location, context)?
+        }
+        // Load a library of the standard library or a file, and declare what it declares in the
+        // current scope, or with a name in a module.
+        Expr::Include(target, None, location) => {
+            let declarations = include(target, location, context)?;
+            context.import(&declarations);
+            Expr::Void
+        }
+        Expr::Include(target, Some(name), location) => {
+            let declarations = include(target, location, context)?;
+            context.insert(name.clone(), Expr::Module(name, declarations.variables()));
+            Expr::Void
+        }
+        // Define a struct by storing its definition in the context, so it can be used as a constructor.
+        Expr::Struct(ref name, _, _) => {
+            context.insert(name.clone(), expr.clone());
+            Expr::Void
+        }
+        // Add methods to a struct, as closures stored in its definition.
+        // The methods implementing a trait must include all the methods of the trait.
+        Expr::Impl(name, trait_name, functions, location) => {
+            let environment = context.environment();
+            let methods = functions
+                .into_iter()
+                .map(|function| {
+                    let Expr::Function(method, args, return_type, body) = function else {
+                        unreachable!("an `impl` block only contains functions")
+                    };
+                    (method, Expr::Closure(args, return_type, body, Some(environment.clone())))
+                })
+                .collect();
+            implement(name, trait_name, methods, location, context)?;
+            Expr::Void
+        }
+        // Define a trait by storing the names of its methods in the context.
+        Expr::Trait(ref name, _) => {
+            context.insert(name.clone(), expr.clone());
+            Expr::Void
+        }
+        // Define an enum by storing its definition in the context, so its variants can be resolved.
+        Expr::Enum(ref name, _) => {
+            context.insert(name.clone(), expr.clone());
+            Expr::Void
+        }
+        // Resolve a path like `Color::Red` to the variant it names, or `Point::new` to a method.
+        Expr::Path(name, variant, location) => path(name, variant, &location, context)?,
+        // Evaluate a field access on a struct instance.
+        Expr::Field(instance, name, location) => {
+            let value = eval!(*instance, context);
+            field(value, &name, &location)?
+        }
+        // Evaluate a match expression: the body of the first arm whose pattern matches is evaluated.
+        Expr::Match(value, arms) => {
+            let value = eval!(*value, context);
+            for (pattern, body) in arms {
+                // The arm runs in a new scope with the variables bound by its pattern.
+                let mut bindings = Vec::new();
+                if pattern_matches(&pattern, &value, &mut bindings) {
+                    return interpreter_block_with(body, bindings, context);
+                }
+            }
+            // No arm matched.
+            Expr::Void
+        }
+        Expr::Builtin(func) => {
+            // Builtins are meant to be called; simply return them.
+            Expr::Builtin(func)
+        }
+        Expr::Native(native) => Expr::Native(native),
+    })
+}
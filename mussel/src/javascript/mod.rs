@@ -0,0 +1,1367 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Translation of a script to JavaScript, made with `mussel emit-js`.
+// The statements of the script become JavaScript statements, after a small runtime (`runtime.js`)
+// doing what JavaScript does differently: integers are BigInts, the operators and method calls
+// check their operands like the interpreter, and assigning an element or a field copies the array
+// or the instance, so two variables never share a value.
+// A block used as a value, like an `if` passed to a function, becomes a function called in place,
+// which a `return`, `break` or `continue` can't leave, so those are reported instead. The files a
+// script includes are translated along with it, each into a function run by its first `include`.
+// What only the interpreter can do, like `eval`, is reported too.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use crate::error;
+use crate::error::{
+    CircularIncludeError, ConstantAssignmentError, EvaluationError, FileError, IncludeError, LError, Location,
+    LoopControlError, UndeclaredLabelError,
+};
+use crate::expr::{
+    Align, Atom, BinOp, Binding, Expr, FormatSpec, IncludeTarget, LogicOp, Operator, Parameter, Pattern,
+    StringPart, UnaryOp,
+};
+use crate::loader::Loader;
+use crate::stdlib;
+use crate::walk::{any, binding_names, children, location, mentions};
+
+const RUNTIME: &str = include_str!("runtime.js");
+
+// The built-in functions declared by the runtime.
+const BUILTINS: [&str; 9] = ["println", "input", "range", "is_nil", "typeof", "get", "call", "args", "exit"];
+
+// The built-in functions working with the variables of the running script, which only the
+// interpreter knows.
+const UNSUPPORTED: [&str; 3] = ["eval", "vars", "defined"];
+
+// The libraries of the standard library written in Rust, implemented by the runtime, and what
+// they declare.
+const LIBRARIES: [(&str, &[&str]); 5] = [
+    ("math", &["abs", "sqrt", "pow"]),
+    ("string", &["lowercase", "uppercase", "length", "split", "reverse", "trim", "ltrim", "rtrim", "chars", "ord", "chr"]),
+    ("time", &["time_ms", "time_sec"]),
+    ("random", &["rand"]),
+    ("os", &["getcwd", "listdir", "exists"]),
+];
+
+// The functions the operators call when their operands are struct instances, which the runtime
+// must be given.
+const OPERATOR_FUNCTIONS: [&str; 7] = ["add", "sub", "mul", "div", "neg", "eq", "lt"];
+
+// The words JavaScript doesn't allow as names, or that name something the translation uses. A name
+// of the script that is one of them gets a `_` appended.
+const RESERVED: &[&str] = &[
+    "arguments", "await", "break", "case", "catch", "class", "const", "continue", "debugger", "default",
+    "delete", "do", "else", "enum", "eval", "export", "extends", "false", "finally", "for", "function",
+    "if", "implements", "import", "in", "instanceof", "interface", "let", "new", "null", "package",
+    "private", "protected", "public", "return", "static", "super", "switch", "this", "throw", "true",
+    "try", "typeof", "undefined", "var", "void", "while", "with", "yield", "Array", "Infinity", "NaN",
+];
+
+type Translated<T> = Result<T, Box<dyn LError + Send>>;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Variable,
+    Constant,
+    Struct,
+    Enum,
+    Builtin,
+    Unsupported,
+}
+
+// A name of the script, with its name in JavaScript.
+#[derive(Clone)]
+struct Symbol {
+    js: String,
+    kind: Kind,
+}
+
+// The names declared in a block, in the order of their declaration.
+#[derive(Default)]
+struct Scope {
+    symbols: HashMap<String, Symbol>,
+    order: Vec<String>,
+    // The names the block declares, and the ones it declares more than once, declared with `let`
+    // so they can be assigned.
+    declared: HashSet<String>,
+    repeated: HashSet<String>,
+}
+
+impl Scope {
+    // Creates the scope of a block, knowing in advance which names it declares more than once.
+    fn new(body: &[Expr], parameters: &[String]) -> Scope {
+        let mut names: HashSet<String> = parameters.iter().cloned().collect();
+        let mut repeated: HashSet<String> = HashSet::new();
+        for parameter in parameters {
+            if parameters.iter().filter(|other| *other == parameter).count() > 1 {
+                repeated.insert(parameter.clone());
+            }
+        }
+        for name in body.iter().flat_map(declared) {
+            if !names.insert(name.clone()) {
+                repeated.insert(name);
+            }
+        }
+        Scope { declared: names, repeated, ..Scope::default() }
+    }
+
+    // Creates the scope of the built-in functions, around the script and each included file.
+    fn builtins() -> Scope {
+        let mut scope = Scope::default();
+        for name in BUILTINS {
+            scope.insert(name, Symbol { js: identifier(name), kind: Kind::Builtin });
+        }
+        for name in UNSUPPORTED {
+            scope.insert(name, Symbol { js: identifier(name), kind: Kind::Unsupported });
+        }
+        scope
+    }
+
+    fn insert(&mut self, name: &str, symbol: Symbol) {
+        if self.symbols.insert(name.to_string(), symbol).is_none() {
+            self.order.push(name.to_string());
+        }
+    }
+}
+
+// What surrounds the code being translated, which `return`, `break` and `continue` leave.
+enum Frame {
+    Function,
+    // A block used as a value, in a function called in place, at the given location.
+    Value(Option<Location>),
+    // A loop, with its label and the variable receiving the value of its `break`.
+    Loop { label: Option<String>, value: Option<String> },
+}
+
+// Where the value of the last statement of a block goes.
+#[derive(Clone)]
+enum Target {
+    Discard,
+    Store(String),
+    Return,
+}
+
+// A function whose `return`s calling it again are a loop, so the calls don't nest, like the tail
+// calls of the interpreter: the label of the loop, its parameters and the scope of its body.
+struct Tail {
+    name: String,
+    label: String,
+    parameters: Vec<String>,
+    scope: usize,
+}
+
+// A file translated into a function giving what it declares.
+#[derive(Clone)]
+struct Module {
+    path: PathBuf,
+    js: String,
+    members: Vec<(String, Symbol)>,
+}
+
+struct Emitter<'a> {
+    loader: &'a mut Loader,
+    // The file being translated, named by the errors that have no location.
+    path: PathBuf,
+    code: String,
+    indent: usize,
+    scopes: Vec<Scope>,
+    frames: Vec<Frame>,
+    modules: Vec<Module>,
+    // The functions of the included files, written before the script.
+    definitions: String,
+    temporaries: usize,
+    // The function being written when it's a loop, unset in a `try` block.
+    tail: Option<Tail>,
+}
+
+// Translates a script and the files it includes to JavaScript, written to the output file or
+// else printed. The files are checked like when the script runs, and what can't be translated is
+// reported like an error of the script. Fails when an error was reported.
+pub fn translate(file: &str, output: Option<&Path>) -> ExitCode {
+    let mut loader = Loader::new(false);
+    loader.enter(Path::new(file));
+    let translated = loader.load(file).and_then(|script| Emitter::new(&mut loader, file).script(&script));
+    let code = match translated {
+        Ok(code) => code,
+        Err(error) => {
+            loader.report(error);
+            return ExitCode::FAILURE;
+        }
+    };
+    match output {
+        Some(output) => {
+            if let Err(error) = std::fs::write(output, code) {
+                loader.report(FileError::new(output, format!("Failed to write the JavaScript: {error}")));
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{code}"),
+    }
+    ExitCode::SUCCESS
+}
+
+impl<'a> Emitter<'a> {
+    fn new(loader: &'a mut Loader, file: &str) -> Self {
+        Emitter {
+            loader,
+            path: PathBuf::from(file),
+            code: String::new(),
+            indent: 1,
+            scopes: vec![Scope::builtins()],
+            frames: Vec::new(),
+            modules: Vec::new(),
+            definitions: String::new(),
+            temporaries: 0,
+            tail: None,
+        }
+    }
+
+    // Translates the script, in a block so its names don't hide the ones of the runtime.
+    fn script(mut self, script: &[Expr]) -> Translated<String> {
+        self.scopes.push(Scope::new(script, &[]));
+        // The value of the last statement is the exit status when it is an integer.
+        let status = self.temporary("status");
+        self.line(format!("let {status};"));
+        self.statements(script, &Target::Store(status.clone()))?;
+        self.line(format!("$.finish({status});"));
+        let name = self.path.file_name().map_or_else(|| self.path.to_string_lossy(), |name| name.to_string_lossy());
+        Ok(format!(
+            "// Translated from {name} by `mussel emit-js`.\n\"use strict\";\n\n{RUNTIME}\n{}{{\n{}}}\n",
+            self.definitions, self.code
+        ))
+    }
+
+    fn line<S: AsRef<str>>(&mut self, line: S) {
+        for _ in 0..self.indent {
+            self.code.push_str("    ");
+        }
+        self.code.push_str(line.as_ref());
+        self.code.push('\n');
+    }
+
+    // Writes lines apart from the code written so far and returns them.
+    fn capture(&mut self, write: impl FnOnce(&mut Self) -> Translated<()>) -> Translated<String> {
+        let code = std::mem::take(&mut self.code);
+        let written = write(self);
+        let captured = std::mem::replace(&mut self.code, code);
+        written.map(|_| captured)
+    }
+
+    fn temporary(&mut self, purpose: &str) -> String {
+        self.temporaries += 1;
+        format!("${purpose}{}", self.temporaries)
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Symbol> {
+        self.scopes.iter().rev().find_map(|scope| scope.symbols.get(name))
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("the script has a scope")
+    }
+
+    // Returns the name in JavaScript of a name declared in the current block, and whether the
+    // block already declared it, in which case it's assigned instead.
+    // A name hiding one of an enclosing block is renamed when the value it's declared with reads
+    // the hidden one, like `let n = n + 1`, as JavaScript would read the new one, not yet set.
+    fn name_for(&self, name: &str, hides: bool) -> (String, bool) {
+        let scope = self.scopes.last().expect("the script has a scope");
+        if let Some(symbol) = scope.symbols.get(name) {
+            return (symbol.js.clone(), true);
+        }
+        let js = identifier(name);
+        if !hides || self.lookup(name).is_none_or(|symbol| symbol.js != js) {
+            return (js, false);
+        }
+        let taken = |candidate: &str| self.scopes.iter().any(|scope| scope.symbols.values().any(|symbol| symbol.js == candidate));
+        let renamed = (1..).map(|n| format!("{js}_{n}")).find(|candidate| !taken(candidate)).expect("a name is free");
+        (renamed, false)
+    }
+
+    fn keyword(&self, name: &str, kind: Kind) -> &'static str {
+        let scope = self.scopes.last().expect("the script has a scope");
+        if kind == Kind::Variable || scope.repeated.contains(name) { "let" } else { "const" }
+    }
+
+    // Declares a name in the current block with the given value.
+    fn define(&mut self, name: &str, kind: Kind, value: String, hides: bool) {
+        let keyword = self.keyword(name, kind);
+        let (js, declared) = self.name_for(name, hides);
+        if declared {
+            self.line(format!("{js} = {value};"));
+        } else {
+            self.line(format!("{keyword} {js} = {value};"));
+        }
+        self.scope().insert(name, Symbol { js, kind });
+    }
+
+    // Returns the JavaScript name of a name read by the script.
+    fn reference(&self, name: &str, location: &Location) -> Translated<String> {
+        match self.lookup(name) {
+            Some(Symbol { kind: Kind::Unsupported, .. }) => Err(unsupported(
+                Some(location),
+                &self.path,
+                format!("`{name}` can't be translated to JavaScript"),
+                "it works with the variables of the running script",
+            )),
+            Some(symbol) => Ok(symbol.js.clone()),
+            None => Ok(identifier(name)),
+        }
+    }
+
+    fn statements(&mut self, body: &[Expr], target: &Target) -> Translated<()> {
+        for (i, expr) in body.iter().enumerate() {
+            let target = if i + 1 == body.len() { target } else { &Target::Discard };
+            self.statement(expr, target)?;
+        }
+        Ok(())
+    }
+
+    // Writes a block of statements, in its own scope, one level deeper.
+    fn block(&mut self, body: &[Expr], target: &Target) -> Translated<()> {
+        self.scopes.push(Scope::new(body, &[]));
+        self.indent += 1;
+        self.statements(body, target)?;
+        self.indent -= 1;
+        self.scopes.pop();
+        Ok(())
+    }
+
+    // Writes a statement, giving its value to the target.
+    fn statement(&mut self, expr: &Expr, target: &Target) -> Translated<()> {
+        match expr {
+            Expr::Void => Ok(()),
+            Expr::Let(binding, value, _) => self.binding(binding, value, Kind::Variable),
+            Expr::Const(binding, value, _) => self.binding(binding, value, Kind::Constant),
+            Expr::Assign(place, value, location) => self.assign(place, value, location),
+            Expr::Function(name, parameters, _, body) => self.function_declaration(name, parameters, body),
+            Expr::Struct(name, fields, _) => {
+                let fields = fields.iter().map(|field| string(field)).collect::<Vec<_>>().join(", ");
+                self.define(name, Kind::Struct, format!("$.struct({}, [{fields}])", string(name)), false);
+                Ok(())
+            }
+            Expr::Enum(name, variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|(variant, fields)| {
+                        let fields = fields.iter().map(|field| string(field)).collect::<Vec<_>>().join(", ");
+                        format!("{variant}: [{fields}]")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.define(name, Kind::Enum, format!("$.enumeration({}, {{ {variants} }})", string(name)), false);
+                Ok(())
+            }
+            Expr::Trait(name, methods) => {
+                let methods = methods.iter().map(|method| string(method)).collect::<Vec<_>>().join(", ");
+                self.define(name, Kind::Constant, format!("$.trait({}, [{methods}])", string(name)), false);
+                Ok(())
+            }
+            Expr::Impl(name, trait_name, methods, location) => self.implementation(name, trait_name, methods, location),
+            Expr::Include(target, alias, location) => self.include(target, alias, location),
+            Expr::Return(value) => self.return_statement(value),
+            Expr::Throw(value, _) => {
+                let value = self.value(value)?;
+                self.line(format!("throw new $.Thrown({value});"));
+                Ok(())
+            }
+            Expr::Break(value, _, location) => self.break_statement(value, location),
+            Expr::Continue(label, location) => {
+                self.find_loop(label.as_deref(), "continue", location)?;
+                match label {
+                    Some(label) => self.line(format!("continue {};", identifier(label))),
+                    None => self.line("continue;"),
+                }
+                Ok(())
+            }
+            Expr::If(condition, then, otherwise, _) => self.if_statement(condition, then, otherwise.as_deref(), target, "if"),
+            Expr::Match(value, arms) => self.match_statement(value, arms, target),
+            Expr::Try(body, name, catch) => {
+                self.line("try {");
+                // A call returned inside the block is made before leaving it, so its errors are caught.
+                let tail = self.tail.take();
+                self.block(body, target)?;
+                self.tail = tail;
+                self.scopes.push(Scope::new(catch, std::slice::from_ref(name)));
+                let (js, _) = self.name_for(name, false);
+                self.scope().insert(name, Symbol { js: js.clone(), kind: Kind::Variable });
+                self.line(format!("}} catch ({js}) {{"));
+                self.indent += 1;
+                self.line(format!("{js} = $.caught({js});"));
+                self.statements(catch, target)?;
+                self.indent -= 1;
+                self.scopes.pop();
+                self.line("}");
+                Ok(())
+            }
+            Expr::Loop(body, label) => {
+                let (value, result) = match target {
+                    Target::Discard => (None, None),
+                    Target::Store(variable) => (Some(variable.clone()), None),
+                    Target::Return => {
+                        let result = self.temporary("result");
+                        self.line(format!("let {result};"));
+                        (Some(result.clone()), Some(result))
+                    }
+                };
+                self.line(format!("{}while (true) {{", labeled(label)));
+                self.frames.push(Frame::Loop { label: label.clone(), value });
+                self.block(body, &Target::Discard)?;
+                self.frames.pop();
+                self.line("}");
+                if let Some(result) = result {
+                    self.line(format!("return {result};"));
+                }
+                Ok(())
+            }
+            Expr::For(binding, collection, body, _, label) => {
+                let iterated = self.expression(collection)?;
+                let mut names = Vec::new();
+                binding_names(binding, &mut names);
+                self.scopes.push(Scope::new(body, &names));
+                let keyword = if names.iter().any(|name| assigns(body, name) || self.scopes.last().is_some_and(|scope| scope.repeated.contains(name))) {
+                    "let"
+                } else {
+                    "const"
+                };
+                let pattern = self.pattern(binding, &|name| mentions(collection, name));
+                let pattern = if pattern.is_empty() { "_".to_string() } else { pattern };
+                self.line(format!("{}for ({keyword} {pattern} of $.iterate({iterated})) {{", labeled(label)));
+                self.frames.push(Frame::Loop { label: label.clone(), value: None });
+                self.indent += 1;
+                self.statements(body, &Target::Discard)?;
+                self.indent -= 1;
+                self.frames.pop();
+                self.scopes.pop();
+                self.line("}");
+                Ok(())
+            }
+            Expr::Until(condition, body, _, label) => {
+                let condition = negate(condition, self.expression(condition)?);
+                self.line(format!("{}while ({condition}) {{", labeled(label)));
+                self.frames.push(Frame::Loop { label: label.clone(), value: None });
+                self.block(body, &Target::Discard)?;
+                self.frames.pop();
+                self.line("}");
+                Ok(())
+            }
+            expr => {
+                let code = self.expression(expr)?;
+                match target {
+                    Target::Discard => self.line(format!("{code};")),
+                    Target::Store(variable) => self.line(format!("{variable} = {code};")),
+                    Target::Return => self.line(format!("return {code};")),
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Returns the code of a value, writing the statements a block used as the value needs first.
+    fn value(&mut self, expr: &Expr) -> Translated<String> {
+        if !is_block(expr) {
+            return self.expression(expr);
+        }
+        let temporary = self.temporary("value");
+        self.line(format!("let {temporary};"));
+        self.statement(expr, &Target::Store(temporary.clone()))?;
+        Ok(temporary)
+    }
+
+    fn binding(&mut self, binding: &Binding, value: &Expr, kind: Kind) -> Translated<()> {
+        match binding {
+            Binding::Wildcard => self.statement(value, &Target::Discard),
+            // A block is given the variable to set, declared before it.
+            Binding::Name(name) if is_block(value) => {
+                let (js, declared) = self.name_for(name, mentions(value, name));
+                if !declared {
+                    self.line(format!("let {js};"));
+                }
+                self.statement(value, &Target::Store(js.clone()))?;
+                self.scope().insert(name, Symbol { js, kind });
+                Ok(())
+            }
+            Binding::Name(name) => {
+                let code = self.expression(value)?;
+                self.define(name, kind, code, mentions(value, name));
+                Ok(())
+            }
+            Binding::Array(..) => {
+                let code = self.value(value)?;
+                let mut names = Vec::new();
+                binding_names(binding, &mut names);
+                // The names new to the block are declared by a destructuring, the others are
+                // assigned one by one.
+                let scope = self.scopes.last().expect("the script has a scope");
+                let destructured = names.iter().all(|name| {
+                    !scope.repeated.contains(name) && self.name_for(name, mentions(value, name)) == (identifier(name), false)
+                });
+                if destructured {
+                    let keyword = self.keyword("", kind);
+                    let pattern = self.pattern(binding, &|_| false);
+                    self.line(format!("{keyword} {pattern} = {code};"));
+                    for name in &names {
+                        if let Some(symbol) = self.scope().symbols.get_mut(name) {
+                            symbol.kind = kind;
+                        }
+                    }
+                    return Ok(());
+                }
+                let values = if is_identifier(&code) {
+                    code
+                } else {
+                    let values = self.temporary("values");
+                    self.line(format!("const {values} = {code};"));
+                    values
+                };
+                let mut elements = Vec::new();
+                binding_elements(binding, String::new(), &mut elements);
+                for (name, element) in elements {
+                    self.define(&name, kind, format!("{values}{element}"), mentions(value, &name));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // Declares the names of a binding as variables and returns the JavaScript pattern binding
+    // them, empty for `_`.
+    fn pattern(&mut self, binding: &Binding, hides: &dyn Fn(&str) -> bool) -> String {
+        match binding {
+            Binding::Name(name) => {
+                let (js, _) = self.name_for(name, hides(name));
+                self.scope().insert(name, Symbol { js: js.clone(), kind: Kind::Variable });
+                js
+            }
+            Binding::Wildcard => String::new(),
+            Binding::Array(bindings, _) => {
+                let patterns: Vec<String> = bindings.iter().map(|binding| self.pattern(binding, hides)).collect();
+                format!("[{}]", patterns.join(", "))
+            }
+        }
+    }
+
+    fn assign(&mut self, place: &Expr, value: &Expr, location: &Location) -> Translated<()> {
+        let value = self.value(value)?;
+        let mut keys = Vec::new();
+        let mut root = place;
+        let name = loop {
+            match root {
+                Expr::Get(array, index, _) => {
+                    keys.push(self.expression(index)?);
+                    root = array;
+                }
+                Expr::Field(instance, field, _) => {
+                    keys.push(string(field));
+                    root = instance;
+                }
+                Expr::Variable(name, _) => break name,
+                _ => {
+                    return Err(error::boxed(EvaluationError::new(
+                        location.clone(),
+                        "invalid left-hand side of assignment".to_string(),
+                        "only variables, their elements and their fields can be assigned".to_string(),
+                    )));
+                }
+            }
+        };
+        keys.reverse();
+        let js = match self.lookup(name) {
+            Some(Symbol { kind: Kind::Constant | Kind::Struct | Kind::Enum, .. }) => {
+                return Err(error::boxed(ConstantAssignmentError::new(location.clone(), name.clone())));
+            }
+            Some(symbol) => symbol.js.clone(),
+            None => identifier(name),
+        };
+        if keys.is_empty() {
+            self.line(format!("{js} = {value};"));
+        } else {
+            self.line(format!("{js} = $.set({js}, [{}], {value});", keys.join(", ")));
+        }
+        Ok(())
+    }
+
+    fn function_declaration(&mut self, name: &str, parameters: &[Parameter], body: &[Expr]) -> Translated<()> {
+        let (js, declared) = self.name_for(name, false);
+        self.scope().insert(name, Symbol { js: js.clone(), kind: Kind::Variable });
+        // A function declared again replaces the previous one from there on, and from its `return`s
+        // calling it.
+        let looped = !declared && !self.scope().repeated.contains(name) && loops(name, parameters, body);
+        let tail = looped.then_some(name);
+        if declared {
+            self.function(|parameters| format!("{js} = function {js}({parameters}) {{"), parameters, body, "};", tail)?;
+        } else {
+            self.function(|parameters| format!("function {js}({parameters}) {{"), parameters, body, "}", tail)?;
+        }
+        if OPERATOR_FUNCTIONS.contains(&name) {
+            self.line(format!("$.overload({}, {js});", string(name)));
+        }
+        Ok(())
+    }
+
+    // Writes a function, given the line opening it from its parameters and the line closing it, and
+    // its name when its body is a loop, see `Tail`.
+    // The parameters are declared in the scope of the body, like in the interpreter.
+    fn function(
+        &mut self,
+        header: impl FnOnce(&str) -> String,
+        parameters: &[Parameter],
+        body: &[Expr],
+        end: &str,
+        tail: Option<&str>,
+    ) -> Translated<()> {
+        let names: Vec<String> = parameters.iter().map(|parameter| parameter.name.clone()).collect();
+        self.frames.push(Frame::Function);
+        self.scopes.push(Scope::new(body, &names));
+        let mut declared = Vec::new();
+        for parameter in parameters {
+            let default = parameter.default.as_ref().map(|default| self.expression(default)).transpose()?;
+            let (js, _) = self.name_for(&parameter.name, false);
+            self.scope().insert(&parameter.name, Symbol { js: js.clone(), kind: Kind::Variable });
+            declared.push(match default {
+                Some(default) => format!("{js} = {default}"),
+                None => js,
+            });
+        }
+        self.line(header(&declared.join(", ")));
+        self.indent += 1;
+        let tail = tail.map(|name| Tail {
+            name: name.to_string(),
+            label: self.temporary("tail"),
+            parameters: names.iter().map(|name| self.scope().symbols[name].js.clone()).collect(),
+            scope: self.scopes.len() - 1,
+        });
+        let tail = std::mem::replace(&mut self.tail, tail);
+        if let Some(Tail { label, .. }) = &self.tail {
+            self.line(format!("{label}: while (true) {{"));
+            self.indent += 1;
+        }
+        self.statements(body, &Target::Discard)?;
+        if self.tail.is_some() {
+            if !matches!(body.last(), Some(Expr::Return(_))) {
+                self.line("return;");
+            }
+            self.indent -= 1;
+            self.line("}");
+        }
+        self.tail = tail;
+        self.indent -= 1;
+        self.line(end);
+        self.scopes.pop();
+        self.frames.pop();
+        Ok(())
+    }
+
+    fn implementation(&mut self, name: &str, trait_name: &Option<String>, methods: &[Expr], location: &Location) -> Translated<()> {
+        let definition = self.reference(name, location)?;
+        let trait_name = trait_name.as_ref().map(|name| self.reference(name, location)).transpose()?;
+        self.line(format!("$.impl({definition}, {{"));
+        self.indent += 1;
+        for method in methods {
+            let Expr::Function(method, parameters, _, body) = method else {
+                unreachable!("an `impl` block only contains functions");
+            };
+            self.function(|parameters| format!("{method}({parameters}) {{"), parameters, body, "},", None)?;
+        }
+        self.indent -= 1;
+        match trait_name {
+            Some(trait_name) => self.line(format!("}}, {trait_name});")),
+            None => self.line("});"),
+        }
+        Ok(())
+    }
+
+    fn include(&mut self, target: &IncludeTarget, alias: &Option<String>, location: &Location) -> Translated<()> {
+        let library = match target {
+            IncludeTarget::Name(name) => LIBRARIES.iter().find(|(library, _)| library == name),
+            IncludeTarget::Path(_) => None,
+        };
+        let (module, members) = match (target, library) {
+            (_, Some((name, functions))) => {
+                let members = functions
+                    .iter()
+                    .map(|function| (function.to_string(), Symbol { js: identifier(function), kind: Kind::Variable }))
+                    .collect::<Vec<_>>();
+                (format!("$.library({})", string(name)), members)
+            }
+            (IncludeTarget::Name(name), None) => {
+                let file = PathBuf::from(format!("{name}.mus"));
+                let module = match stdlib::source(name) {
+                    Some(source) => self.module(Path::new("<std>").join(file), Some(source), location)?,
+                    None => {
+                        let found = self.find(&file, location)?;
+                        self.module(found, None, location)?
+                    }
+                };
+                (format!("{}()", module.js), module.members)
+            }
+            (IncludeTarget::Path(path), None) => {
+                let found = self.find(Path::new(path), location)?;
+                let module = self.module(found, None, location)?;
+                (format!("{}()", module.js), module.members)
+            }
+        };
+        if let Some(alias) = alias {
+            self.define(alias, Kind::Variable, module, false);
+            return Ok(());
+        }
+        // What the file declares is declared in the current block, by a destructuring when none of
+        // it is declared there already. It's declared with `let` when the block declares some of it
+        // again later, which is known in advance for the libraries only.
+        let scope = self.scopes.last().expect("the script has a scope");
+        let fresh = members.iter().all(|(name, _)| !scope.symbols.contains_key(name) && !scope.repeated.contains(name));
+        let keyword = if library.is_none() && members.iter().any(|(name, _)| scope.declared.contains(name)) {
+            "let"
+        } else {
+            "const"
+        };
+        if fresh {
+            let names: Vec<String> = members
+                .iter()
+                .map(|(name, symbol)| {
+                    let js = identifier(name);
+                    self.scope().insert(name, Symbol { js: js.clone(), kind: symbol.kind });
+                    if js == *name { js } else { format!("{name}: {js}") }
+                })
+                .collect();
+            self.line(format!("{keyword} {{ {} }} = {module};", names.join(", ")));
+        } else {
+            let declarations = self.temporary("module");
+            self.line(format!("const {declarations} = {module};"));
+            for (name, symbol) in members {
+                self.define(&name, symbol.kind, format!("{declarations}.{name}"), false);
+            }
+        }
+        Ok(())
+    }
+
+    fn find(&self, path: &Path, location: &Location) -> Translated<PathBuf> {
+        self.loader
+            .find(location.file(), path)
+            .map_err(|searched| error::boxed(IncludeError::new(location.clone(), path.to_path_buf(), searched)))
+    }
+
+    // Translates an included file, once, into a function returning what it declares.
+    fn module(&mut self, path: PathBuf, source: Option<&str>, location: &Location) -> Translated<Module> {
+        if let Some(module) = self.modules.iter().find(|module| module.path == path) {
+            return Ok(module.clone());
+        }
+        if let Some(cycle) = self.loader.enter(&path) {
+            return Err(error::boxed(CircularIncludeError::new(location.clone(), cycle)));
+        }
+        let file = match source {
+            Some(source) => self.loader.load_source(&path, source)?,
+            None => self.loader.load(&path)?,
+        };
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let base: String = name.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+        let js = (0..)
+            .map(|n| if n == 0 { format!("${base}") } else { format!("${base}{n}") })
+            .find(|candidate| self.modules.iter().all(|module| module.js != *candidate))
+            .expect("a name is free");
+
+        // The file runs apart from the script, with only the built-in functions around it.
+        let scopes = std::mem::replace(&mut self.scopes, vec![Scope::builtins(), Scope::new(&file, &[])]);
+        let frames = std::mem::take(&mut self.frames);
+        let including = std::mem::replace(&mut self.path, path.clone());
+        let indent = std::mem::replace(&mut self.indent, 1);
+        let body = self.capture(|emitter| {
+            emitter.statements(&file, &Target::Discard)?;
+            let scope = emitter.scopes.last().expect("the file has a scope");
+            let exported: Vec<String> = scope
+                .order
+                .iter()
+                .map(|name| match &scope.symbols[name].js {
+                    js if js == name => js.clone(),
+                    js => format!("{name}: {js}"),
+                })
+                .collect();
+            emitter.line(format!("return {{ {} }};", exported.join(", ")));
+            Ok(())
+        })?;
+        let members = self.scopes[1].order.iter().map(|name| (name.clone(), self.scopes[1].symbols[name].clone())).collect();
+        self.scopes = scopes;
+        self.frames = frames;
+        self.path = including;
+        self.indent = indent;
+        self.loader.leave();
+
+        self.definitions.push_str(&format!("const {js} = $.module({}, () => {{\n{body}}});\n\n", string(&name)));
+        let module = Module { path, js, members };
+        self.modules.push(module.clone());
+        Ok(module)
+    }
+
+    fn return_statement(&mut self, value: &Expr) -> Translated<()> {
+        match self.frames.iter().rev().find(|frame| !matches!(frame, Frame::Loop { .. })) {
+            Some(Frame::Function) => {}
+            Some(Frame::Value(block)) => {
+                return Err(unsupported(
+                    location(value).or(block.as_ref()),
+                    &self.path,
+                    "`return` can't leave a block used as a value in JavaScript".to_string(),
+                    "inside a block used as a value",
+                ));
+            }
+            _ => {
+                return Err(unsupported(
+                    location(value),
+                    &self.path,
+                    "`return` outside of a function can't be translated to JavaScript".to_string(),
+                    "not inside a function",
+                ));
+            }
+        }
+        if let Expr::Call(callee, arguments, _) = value
+            && let Expr::Variable(name, _) = &**callee
+            && let Some(tail) = &self.tail
+            && tail.name == *name
+            && tail.parameters.len() == arguments.len()
+            && self.scopes.iter().rposition(|scope| scope.symbols.contains_key(name)) < Some(tail.scope)
+        {
+            let (label, parameters) = (tail.label.clone(), tail.parameters.clone());
+            let arguments = arguments.iter().map(|argument| self.value(argument)).collect::<Translated<Vec<_>>>()?;
+            match parameters.len() {
+                0 => {}
+                1 => self.line(format!("{} = {};", parameters[0], arguments[0])),
+                _ => self.line(format!("[{}] = [{}];", parameters.join(", "), arguments.join(", "))),
+            }
+            self.line(format!("continue {label};"));
+            return Ok(());
+        }
+        match value {
+            Expr::Void => self.line("return;"),
+            value if is_block(value) => self.statement(value, &Target::Return)?,
+            value => {
+                let code = self.expression(value)?;
+                self.line(format!("return {code};"));
+            }
+        }
+        Ok(())
+    }
+
+    fn break_statement(&mut self, value: &Expr, location: &Location) -> Translated<()> {
+        // `break outer` leaves the loop labeled `outer`, when there is one.
+        if let Expr::Variable(name, _) = value
+            && self.frames.iter().rev().take_while(|frame| !matches!(frame, Frame::Function)).any(
+                |frame| matches!(frame, Frame::Loop { label: Some(label), .. } if label == name),
+            )
+        {
+            self.find_loop(Some(name), "break", location)?;
+            self.line(format!("break {};", identifier(name)));
+            return Ok(());
+        }
+        let found = self.find_loop(None, "break", location)?;
+        let Frame::Loop { value: variable, .. } = &self.frames[found] else {
+            unreachable!("`find_loop` finds loops");
+        };
+        match (variable.clone(), value) {
+            (_, Expr::Void) => {}
+            (Some(variable), value) => {
+                let code = self.value(value)?;
+                self.line(format!("{variable} = {code};"));
+            }
+            (None, value) => self.statement(value, &Target::Discard)?,
+        }
+        self.line("break;");
+        Ok(())
+    }
+
+    // Returns the index of the loop left by a `break` or a `continue`: the one with the label, or
+    // else the innermost one.
+    fn find_loop(&self, label: Option<&str>, keyword: &str, location: &Location) -> Translated<usize> {
+        let mut crossed = false;
+        for (i, frame) in self.frames.iter().enumerate().rev() {
+            match frame {
+                Frame::Loop { label: name, .. } if label.is_none() || name.as_deref() == label => {
+                    if crossed {
+                        return Err(unsupported(
+                            Some(location),
+                            &self.path,
+                            format!("`{keyword}` can't leave a block used as a value in JavaScript"),
+                            "inside a block used as a value",
+                        ));
+                    }
+                    return Ok(i);
+                }
+                Frame::Loop { .. } => {}
+                Frame::Value(_) => crossed = true,
+                Frame::Function => break,
+            }
+        }
+        Err(match label {
+            Some(label) => error::boxed(UndeclaredLabelError::new(location.clone(), label.to_string())),
+            None => error::boxed(LoopControlError::new(location.clone(), keyword.to_string())),
+        })
+    }
+
+    fn if_statement(
+        &mut self,
+        condition: &Expr,
+        then: &[Expr],
+        otherwise: Option<&[Expr]>,
+        target: &Target,
+        keyword: &str,
+    ) -> Translated<()> {
+        let condition = self.expression(condition)?;
+        self.line(format!("{keyword} ({condition}) {{"));
+        self.block(then, target)?;
+        match otherwise {
+            Some([Expr::If(condition, then, otherwise, _)]) => {
+                self.if_statement(condition, then, otherwise.as_deref(), target, "} else if")
+            }
+            Some(otherwise) => {
+                self.line("} else {");
+                self.block(otherwise, target)?;
+                self.line("}");
+                Ok(())
+            }
+            None => {
+                self.line("}");
+                Ok(())
+            }
+        }
+    }
+
+    // Writes a `match` as a chain of `if`, each testing a pattern then declaring its names.
+    fn match_statement(&mut self, value: &Expr, arms: &[(Pattern, Vec<Expr>)], target: &Target) -> Translated<()> {
+        let subject = match value {
+            Expr::Variable(..) => self.expression(value)?,
+            value => {
+                let code = self.expression(value)?;
+                let subject = self.temporary("match");
+                self.line(format!("const {subject} = {code};"));
+                subject
+            }
+        };
+        for (i, (pattern, body)) in arms.iter().enumerate() {
+            let mut conditions = Vec::new();
+            let mut bindings = Vec::new();
+            test(pattern, &subject, &mut conditions, &mut bindings);
+            let always = conditions.is_empty();
+            match (i, always) {
+                (0, true) => self.line("{"),
+                (_, true) => self.line("} else {"),
+                (0, false) => self.line(format!("if ({}) {{", conditions.join(" && "))),
+                (_, false) => self.line(format!("}} else if ({}) {{", conditions.join(" && "))),
+            }
+            let names: Vec<String> = bindings.iter().map(|(name, _)| name.clone()).collect();
+            self.scopes.push(Scope::new(body, &names));
+            self.indent += 1;
+            for (name, element) in bindings {
+                let kind = if assigns(body, &name) { Kind::Variable } else { Kind::Constant };
+                let hides = matches!(value, Expr::Variable(subject, _) if *subject == name);
+                self.define(&name, kind, element, hides);
+                if let Some(symbol) = self.scope().symbols.get_mut(&name) {
+                    symbol.kind = Kind::Variable;
+                }
+            }
+            self.statements(body, target)?;
+            self.indent -= 1;
+            self.scopes.pop();
+            if always {
+                break;
+            }
+        }
+        if !arms.is_empty() {
+            self.line("}");
+        }
+        Ok(())
+    }
+
+    // Returns the code of an expression. A block becomes a function called in place, as
+    // JavaScript doesn't use statements as values.
+    fn expression(&mut self, expr: &Expr) -> Translated<String> {
+        Ok(match expr {
+            Expr::Void => "undefined".to_string(),
+            Expr::Constant(atom) => literal(atom),
+            Expr::Array(elements) => format!("[{}]", self.list(elements)?),
+            Expr::Variable(name, location) => self.reference(name, location)?,
+            Expr::Interpolation(parts) => self.interpolation(parts)?,
+            Expr::Call(callee, arguments, _) => {
+                let callee = self.operand(callee)?;
+                format!("{callee}({})", self.list(arguments)?)
+            }
+            // The function with the name of the method, if there is one, is called when the
+            // receiver has no such method.
+            Expr::MethodCall(receiver, method, arguments, _) => {
+                let mut values = vec![self.expression(receiver)?, string(method)];
+                values.push(match self.lookup(method) {
+                    Some(symbol) if symbol.kind != Kind::Unsupported => symbol.js.clone(),
+                    _ => "undefined".to_string(),
+                });
+                if !arguments.is_empty() {
+                    values.push(self.list(arguments)?);
+                }
+                format!("$.invoke({})", values.join(", "))
+            }
+            Expr::Compare(left, operator, right, _) => {
+                let function = match operator {
+                    Operator::Equal => "eq",
+                    Operator::NotEqual => "ne",
+                    Operator::LessThan => "lt",
+                    Operator::LessThanEqual => "le",
+                    Operator::GreaterThan => "gt",
+                    Operator::GreaterThanEqual => "ge",
+                };
+                format!("$.{function}({}, {})", self.expression(left)?, self.expression(right)?)
+            }
+            Expr::Logic(left, operator, right, _) => {
+                let mut side = |expr: &Expr| -> Translated<String> {
+                    let code = self.expression(expr)?;
+                    Ok(match expr {
+                        Expr::Logic(_, inner, _, _) if inner != operator => format!("({code})"),
+                        _ => code,
+                    })
+                };
+                let left = side(left)?;
+                let right = side(right)?;
+                let operator = match operator {
+                    LogicOp::And => "&&",
+                    LogicOp::Or => "||",
+                };
+                format!("{left} {operator} {right}")
+            }
+            Expr::Binary(left, operator, right, _) => {
+                let function = match operator {
+                    BinOp::Add => "add",
+                    BinOp::Sub => "sub",
+                    BinOp::Mul => "mul",
+                    BinOp::Div => "div",
+                    BinOp::Pow => "pow",
+                };
+                format!("$.{function}({}, {})", self.expression(left)?, self.expression(right)?)
+            }
+            Expr::Unary(UnaryOp::Neg, operand, _) => match &**operand {
+                Expr::Constant(atom @ (Atom::Number(_) | Atom::Float(_))) => format!("-{}", literal(atom)),
+                operand => format!("$.neg({})", self.expression(operand)?),
+            },
+            Expr::Unary(UnaryOp::Not, operand, _) => negate(operand, self.expression(operand)?),
+            Expr::Range(start, end, inclusive, _) => {
+                let (start, end) = (self.expression(start)?, self.expression(end)?);
+                if *inclusive { format!("$.interval({start}, {end}, true)") } else { format!("$.interval({start}, {end})") }
+            }
+            Expr::Get(array, index, _) => format!("$.index({}, {})", self.expression(array)?, self.expression(index)?),
+            Expr::Field(instance, field, _) => format!("{}.{field}", self.operand(instance)?),
+            Expr::Path(name, member, location) => {
+                let definition = self.reference(name, location)?;
+                match self.lookup(name).map(|symbol| symbol.kind) {
+                    Some(Kind::Enum) => format!("{definition}.{member}"),
+                    Some(Kind::Struct) => format!("{definition}.methods.{member}"),
+                    _ => format!("$.path({definition}, {})", string(member)),
+                }
+            }
+            Expr::Closure(parameters, _, body, None) => {
+                let code = self.capture(|emitter| emitter.function(|parameters| format!("({parameters}) => {{"), parameters, body, "}", None))?;
+                code.trim().to_string()
+            }
+            Expr::Comprehension(value, binding, collection, location, condition) => {
+                let collection = self.expression(collection)?;
+                self.frames.push(Frame::Value(Some(location.clone())));
+                self.scopes.push(Scope::default());
+                let pattern = self.pattern(binding, &|_| false);
+                let pattern = if pattern.is_empty() { "_".to_string() } else { pattern };
+                let value = self.expression(value)?;
+                let condition = condition.as_ref().map(|(condition, _)| self.expression(condition)).transpose()?;
+                self.scopes.pop();
+                self.frames.pop();
+                match condition {
+                    Some(condition) => format!("$.comprehension({collection}, ({pattern}) => {value}, ({pattern}) => {condition})"),
+                    None => format!("$.comprehension({collection}, ({pattern}) => {value})"),
+                }
+            }
+            Expr::Assert(condition, message, _) => {
+                let condition = self.expression(condition)?;
+                match message {
+                    Some(message) => format!("$.assert({condition}, () => {})", self.expression(message)?),
+                    None => format!("$.assert({condition})"),
+                }
+            }
+            Expr::If(..)
+            | Expr::Match(..)
+            | Expr::Loop(..)
+            | Expr::Try(..)
+            | Expr::For(..)
+            | Expr::Until(..)
+            | Expr::Let(..)
+            | Expr::Const(..)
+            | Expr::Assign(..)
+            | Expr::Function(..)
+            | Expr::Struct(..)
+            | Expr::Impl(..)
+            | Expr::Trait(..)
+            | Expr::Enum(..)
+            | Expr::Include(..)
+            | Expr::Return(_)
+            | Expr::Throw(..)
+            | Expr::Break(..)
+            | Expr::Continue(..) => {
+                self.frames.push(Frame::Value(location(expr).cloned()));
+                let code = self.capture(|emitter| {
+                    emitter.line("(() => {");
+                    emitter.scopes.push(Scope::new(std::slice::from_ref(expr), &[]));
+                    emitter.indent += 1;
+                    emitter.statement(expr, &Target::Return)?;
+                    emitter.indent -= 1;
+                    emitter.scopes.pop();
+                    emitter.line("})()");
+                    Ok(())
+                })?;
+                self.frames.pop();
+                code.trim().to_string()
+            }
+            Expr::Closure(_, _, _, Some(_))
+            | Expr::Compiled(..)
+            | Expr::TailCall(..)
+            | Expr::RangeValue(..)
+            | Expr::Instance(..)
+            | Expr::Module(..)
+            | Expr::Variant(..)
+            | Expr::Builtin(_)
+            | Expr::Native(_) => unreachable!("the values made by a running script are never parsed"),
+        })
+    }
+
+    // Returns the code of an expression followed by a call or a field, in parentheses when it
+    // would otherwise take them.
+    fn operand(&mut self, expr: &Expr) -> Translated<String> {
+        let code = self.expression(expr)?;
+        Ok(match expr {
+            Expr::Logic(..) | Expr::Unary(..) | Expr::Closure(..) => format!("({code})"),
+            _ => code,
+        })
+    }
+
+    fn list(&mut self, exprs: &[Expr]) -> Translated<String> {
+        let codes = exprs.iter().map(|expr| self.expression(expr)).collect::<Translated<Vec<_>>>()?;
+        Ok(codes.join(", "))
+    }
+
+    // Returns a template literal displaying the interpolated values like `println`.
+    fn interpolation(&mut self, parts: &[StringPart]) -> Translated<String> {
+        let mut code = String::from("`");
+        for part in parts {
+            match part {
+                StringPart::Text(text) => {
+                    code.push_str(&text.replace('\\', "\\\\").replace('`', "\\`").replace("${", "\\${").replace('\r', "\\r"));
+                }
+                StringPart::Value(value, None) => code.push_str(&format!("${{$.display({})}}", self.expression(value)?)),
+                StringPart::Value(value, Some(spec)) => {
+                    code.push_str(&format!("${{$.format({}, {})}}", self.expression(value)?, specification(spec)));
+                }
+            }
+        }
+        code.push('`');
+        Ok(code)
+    }
+}
+
+// Returns whether the `return`s of a function calling it again can be a loop: one of them calls it
+// with a value for each parameter, outside of a `try` block, and the function declares no function
+// which could keep its parameters, changed by the loop.
+fn loops(name: &str, parameters: &[Parameter], body: &[Expr]) -> bool {
+    fn calls(expr: &Expr, name: &str, count: usize) -> bool {
+        match expr {
+            Expr::Return(value) => matches!(
+                &**value,
+                Expr::Call(callee, arguments, _)
+                    if matches!(&**callee, Expr::Variable(callee, _) if callee == name) && arguments.len() == count
+            ),
+            Expr::Try(_, _, catch) => catch.iter().any(|expr| calls(expr, name, count)),
+            expr => children(expr, false).into_iter().any(|child| calls(child, name, count)),
+        }
+    }
+    let declares = |expr: &Expr| matches!(expr, Expr::Function(..) | Expr::Closure(..) | Expr::Impl(..));
+    body.iter().any(|expr| calls(expr, name, parameters.len())) && !body.iter().any(|expr| any(expr, false, &declares))
+}
+
+// Returns the names a statement declares in its block.
+fn declared(expr: &Expr) -> Vec<String> {
+    let mut names = Vec::new();
+    match expr {
+        Expr::Let(binding, ..) | Expr::Const(binding, ..) => binding_names(binding, &mut names),
+        Expr::Function(name, ..) | Expr::Struct(name, ..) | Expr::Enum(name, _) | Expr::Trait(name, _) => {
+            names.push(name.clone());
+        }
+        Expr::Include(_, Some(alias), _) => names.push(alias.clone()),
+        Expr::Include(IncludeTarget::Name(name), None, _) => {
+            if let Some((_, functions)) = LIBRARIES.iter().find(|(library, _)| library == name) {
+                names.extend(functions.iter().map(|function| function.to_string()));
+            }
+        }
+        _ => {}
+    }
+    names
+}
+
+// Returns the names of a binding with how to reach their element from the whole value: `[1][0]`
+fn binding_elements(binding: &Binding, element: String, elements: &mut Vec<(String, String)>) {
+    match binding {
+        Binding::Name(name) => elements.push((name.clone(), element)),
+        Binding::Wildcard => {}
+        Binding::Array(bindings, _) => {
+            for (i, binding) in bindings.iter().enumerate() {
+                binding_elements(binding, format!("{element}[{i}]"), elements);
+            }
+        }
+    }
+}
+
+// Adds the conditions for a value to match a pattern, and the names it binds with their value.
+fn test(pattern: &Pattern, value: &str, conditions: &mut Vec<String>, bindings: &mut Vec<(String, String)>) {
+    match pattern {
+        Pattern::Wildcard | Pattern::Rest(_) => {}
+        Pattern::Binding(name) => bindings.push((name.clone(), value.to_string())),
+        Pattern::Literal(atom) => conditions.push(format!("{value} === {}", literal(atom))),
+        Pattern::Variant(name, variant, values) => {
+            conditions.push(format!("$.variantOf({value}, {}, {})", string(name), string(variant)));
+            if let Some(patterns) = values {
+                conditions.push(format!("{value}.values.length === {}", patterns.len()));
+                for (i, pattern) in patterns.iter().enumerate() {
+                    test(pattern, &format!("{value}.values[{i}]"), conditions, bindings);
+                }
+            }
+        }
+        Pattern::Array(patterns) => {
+            conditions.push(format!("Array.isArray({value})"));
+            let Some(rest) = patterns.iter().position(|pattern| matches!(pattern, Pattern::Rest(_))) else {
+                conditions.push(format!("{value}.length === {}", patterns.len()));
+                for (i, pattern) in patterns.iter().enumerate() {
+                    test(pattern, &format!("{value}[{i}]"), conditions, bindings);
+                }
+                return;
+            };
+            let after = patterns.len() - rest - 1;
+            conditions.push(format!("{value}.length >= {}", rest + after));
+            for (i, pattern) in patterns[..rest].iter().enumerate() {
+                test(pattern, &format!("{value}[{i}]"), conditions, bindings);
+            }
+            for (i, pattern) in patterns[rest + 1..].iter().enumerate() {
+                test(pattern, &format!("{value}[{value}.length - {}]", after - i), conditions, bindings);
+            }
+            if let Pattern::Rest(Some(name)) = &patterns[rest] {
+                let slice = match after {
+                    0 => format!("{value}.slice({rest})"),
+                    after => format!("{value}.slice({rest}, {value}.length - {after})"),
+                };
+                bindings.push((name.clone(), slice));
+            }
+        }
+        Pattern::Struct(name, fields) => {
+            conditions.push(format!("$.instanceOf({value}, {})", string(name)));
+            for (field, pattern) in fields {
+                test(pattern, &format!("{value}.{field}"), conditions, bindings);
+            }
+        }
+    }
+}
+
+// Returns whether some statements assign a variable, or one of its elements or fields.
+fn assigns(body: &[Expr], name: &str) -> bool {
+    let predicate = |expr: &Expr| {
+        let Expr::Assign(place, _, _) = expr else {
+            return false;
+        };
+        let mut root = &**place;
+        while let Expr::Get(inner, _, _) | Expr::Field(inner, _, _) = root {
+            root = inner;
+        }
+        matches!(root, Expr::Variable(variable, _) if variable == name)
+    };
+    body.iter().any(|expr| any(expr, true, &predicate))
+}
+
+
+// Returns whether an expression is a statement of JavaScript, which can't be used as a value.
+fn is_block(expr: &Expr) -> bool {
+    matches!(expr, Expr::If(..) | Expr::Match(..) | Expr::Loop(..) | Expr::Try(..) | Expr::For(..) | Expr::Until(..))
+}
+
+fn unsupported(location: Option<&Location>, path: &Path, message: String, label: &str) -> Box<dyn LError + Send> {
+    match location {
+        Some(location) => error::boxed(EvaluationError::new(location.clone(), message, label.to_string())),
+        None => error::boxed(FileError::new(path, message)),
+    }
+}
+
+// Returns the name of a variable in JavaScript.
+fn identifier(name: &str) -> String {
+    if RESERVED.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+fn is_identifier(code: &str) -> bool {
+    code.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+fn labeled(label: &Option<String>) -> String {
+    label.as_ref().map(|label| format!("{}: ", identifier(label))).unwrap_or_default()
+}
+
+fn negate(expr: &Expr, code: String) -> String {
+    match expr {
+        Expr::Logic(..) => format!("!({code})"),
+        _ => format!("!{code}"),
+    }
+}
+
+fn literal(atom: &Atom) -> String {
+    match atom {
+        Atom::Number(number) => format!("{number}n"),
+        Atom::Float(float) => format!("{float:?}"),
+        Atom::Boolean(boolean) => boolean.to_string(),
+        Atom::String(text) => string(text),
+        Atom::Char(c) => format!("$.char({})", string(&c.to_string())),
+        Atom::Nil => "null".to_string(),
+    }
+}
+
+// Returns a string literal of JavaScript.
+fn string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => literal.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+// Returns the options of `$.format` for a format specifier, the default ones left out.
+fn specification(spec: &FormatSpec) -> String {
+    let mut options = Vec::new();
+    if spec.fill != ' ' {
+        options.push(format!("fill: {}", string(&spec.fill.to_string())));
+    }
+    if let Some(align) = spec.align {
+        let align = match align {
+            Align::Left => "<",
+            Align::Right => ">",
+            Align::Center => "^",
+        };
+        options.push(format!("align: \"{align}\""));
+    }
+    if spec.zero {
+        options.push("zero: true".to_string());
+    }
+    if spec.width > 0 {
+        options.push(format!("width: {}", spec.width));
+    }
+    if let Some(precision) = spec.precision {
+        options.push(format!("precision: {precision}"));
+    }
+    if options.is_empty() { "{}".to_string() } else { format!("{{ {} }}", options.join(", ")) }
+}
@@ -0,0 +1,769 @@
+// The runtime of the scripts translated by `mussel emit-js`: the values JavaScript doesn't have,
+// the operators and the built-in functions, written to behave like the interpreter.
+// Integers are BigInts kept to 64 bits and floats are numbers, so they never mix, like in Mussel.
+// A result too large for 64 bits fails instead of growing, like in the interpreter. Characters are
+// `Char` objects, nil is null, and the value of statements, nothing, is undefined.
+const $ = (() => {
+    // Marks the definitions of structs, enums and traits, and the prototype of the instances of a
+    // struct, which leads back to the struct.
+    const KIND = Symbol("kind");
+    const STRUCT = Symbol("struct");
+
+    // An error stopping the script, like the errors reported by the interpreter.
+    function fail(message) {
+        throw new Error(message);
+    }
+
+    // An error thrown by `throw`, carrying the thrown value until a `catch` takes it.
+    class Thrown extends Error {
+        constructor(value) {
+            super(`Uncaught error: ${display(value)}`);
+            this.value = value;
+        }
+    }
+
+    // Returns the value thrown by `throw` to a `catch`, other errors keep stopping the script.
+    function caught(error) {
+        if (error instanceof Thrown) {
+            return error.value;
+        }
+        throw error;
+    }
+
+    // A character, kept apart from the strings of one character. There is a single object per
+    // character, so characters compare with `===` like the other values.
+    class Char {
+        constructor(text) {
+            this.text = text;
+        }
+    }
+
+    const characters = new Map();
+
+    function char(text) {
+        let character = characters.get(text);
+        if (character === undefined) {
+            character = new Char(text);
+            characters.set(text, character);
+        }
+        return character;
+    }
+
+    class Range {
+        constructor(start, end, step, inclusive) {
+            this.start = start;
+            this.end = end;
+            this.step = step;
+            this.inclusive = inclusive;
+        }
+
+        *[Symbol.iterator]() {
+            const { end, step, inclusive } = this;
+            const within = (n) => (step > 0n ? n < end : n > end) || (inclusive && n === end);
+            for (let n = this.start; within(n); n += step) {
+                yield n;
+            }
+        }
+    }
+
+    class Variant {
+        constructor(enumeration, name, values) {
+            this.enumeration = enumeration;
+            this.name = name;
+            this.values = values;
+        }
+    }
+
+    // The things a file declares, when it's included with a name.
+    class Module {
+        constructor(name, members) {
+            Object.defineProperty(this, "$name", { value: name });
+            Object.assign(this, members);
+        }
+    }
+
+    function isInstance(value) {
+        return typeof value === "object" && value !== null && STRUCT in value;
+    }
+
+    // Returns the name of the type of a value, like `typeof`.
+    function type(value) {
+        switch (typeof value) {
+            case "bigint":
+                return "int";
+            case "number":
+                return "float";
+            case "string":
+                return "string";
+            case "boolean":
+                return "bool";
+            case "undefined":
+                return "nothing";
+            case "function":
+                return value[KIND] === "struct" ? "struct" : "function";
+        }
+        if (value === null) {
+            return "nil";
+        }
+        if (value instanceof Char) {
+            return "char";
+        }
+        if (Array.isArray(value)) {
+            return "array";
+        }
+        if (value instanceof Range) {
+            return "range";
+        }
+        if (value instanceof Variant) {
+            return value.enumeration;
+        }
+        if (value instanceof Module) {
+            return "module";
+        }
+        if (isInstance(value)) {
+            return value[STRUCT].structName;
+        }
+        return value[KIND] === "enum" ? "enum" : "";
+    }
+
+    // Writes a float like Rust does: without a trailing `.0` and without an exponent.
+    function float(n) {
+        if (Number.isNaN(n)) {
+            return "NaN";
+        }
+        if (!Number.isFinite(n)) {
+            return n > 0 ? "inf" : "-inf";
+        }
+        if (Object.is(n, -0)) {
+            return "-0";
+        }
+        const text = String(Math.abs(n));
+        const sign = n < 0 ? "-" : "";
+        const exponent = text.indexOf("e");
+        if (exponent < 0) {
+            return sign + text;
+        }
+        const mantissa = text.slice(0, exponent);
+        const digits = mantissa.replace(".", "");
+        const point = (mantissa.includes(".") ? mantissa.indexOf(".") : mantissa.length) + Number(text.slice(exponent + 1));
+        if (point <= 0) {
+            return `${sign}0.${"0".repeat(-point)}${digits}`;
+        }
+        if (point >= digits.length) {
+            return sign + digits + "0".repeat(point - digits.length);
+        }
+        return `${sign}${digits.slice(0, point)}.${digits.slice(point)}`;
+    }
+
+    // Converts a value to text, like `println` prints it.
+    function display(value) {
+        switch (typeof value) {
+            case "bigint":
+                return value.toString();
+            case "number":
+                return float(value);
+            case "string":
+                return value;
+            case "boolean":
+                return String(value);
+            case "undefined":
+                return "";
+            case "function":
+                return value[KIND] === "struct" ? "" : "<function>";
+        }
+        if (value === null) {
+            return "nil";
+        }
+        if (value instanceof Char) {
+            return value.text;
+        }
+        if (Array.isArray(value)) {
+            return `[${value.map(display).join(", ")}]`;
+        }
+        if (value instanceof Range) {
+            if (value.step !== 1n) {
+                return `range(${value.start}, ${value.end}, ${value.step})`;
+            }
+            return `${value.start}${value.inclusive ? "..=" : ".."}${value.end}`;
+        }
+        if (value instanceof Variant) {
+            const path = `${value.enumeration}::${value.name}`;
+            return value.values.length === 0 ? path : `${path}(${value.values.map(display).join(", ")})`;
+        }
+        if (value instanceof Module) {
+            return `<module ${value.$name}>`;
+        }
+        if (isInstance(value)) {
+            const fields = Object.entries(value).map(([field, value]) => `${field}: ${display(value)}`);
+            return `${value[STRUCT].structName} { ${fields.join(", ")} }`;
+        }
+        return "";
+    }
+
+    // Formats an interpolated value following its format specifier, like `{price:>8.2}`.
+    function format(value, { fill = " ", align, zero = false, width = 0, precision } = {}) {
+        const number = typeof value === "bigint" || typeof value === "number";
+        let text = display(value);
+        if (precision !== undefined) {
+            if (number && Number.isFinite(Number(value))) {
+                text = Number(value).toFixed(precision);
+            } else if (!number) {
+                text = Array.from(text).slice(0, precision).join("");
+            }
+        }
+        const padding = Math.max(0, width - Array.from(text).length);
+        // Zeros go between the sign and the digits: `-0042`.
+        if (zero && number) {
+            const sign = text.startsWith("-") ? "-" : "";
+            return sign + "0".repeat(padding) + text.slice(sign.length);
+        }
+        switch (align ?? (number ? ">" : "<")) {
+            case "<":
+                return text + fill.repeat(padding);
+            case ">":
+                return fill.repeat(padding) + text;
+            default:
+                return fill.repeat(Math.floor(padding / 2)) + text + fill.repeat(padding - Math.floor(padding / 2));
+        }
+    }
+
+    // The functions overloading the operators for struct instances, named after the operator.
+    const overloads = Object.create(null);
+
+    function overload(name, operator) {
+        overloads[name] = operator;
+    }
+
+    // Returns a call of the function overloading an operator when one of the operands is an
+    // instance: a method of the struct of the first operand, or else a function.
+    function overloaded(name, operands) {
+        if (!operands.some(isInstance)) {
+            return undefined;
+        }
+        const operator = (isInstance(operands[0]) && operands[0][STRUCT].methods[name]) || overloads[name];
+        return operator && (() => operator(...operands));
+    }
+
+    // Checks that an integer result fits in 64 bits, the integers of Mussel.
+    function int(value) {
+        return BigInt.asIntN(64, value) === value ? value : overflow();
+    }
+
+    function overflow() {
+        fail("attempt to compute a number too large for an `int`");
+    }
+
+    // Raises an integer to a positive power. Only 0, 1 and -1 can be raised to a power larger than
+    // 64 without overflowing, which is checked first so the BigInt never grows that large.
+    function power(base, exponent) {
+        if (exponent <= 64n) {
+            return int(base ** exponent);
+        }
+        if (base < -1n || base > 1n) {
+            return overflow();
+        }
+        return base === -1n && exponent % 2n === 0n ? 1n : base;
+    }
+
+    function arithmetic(name, left, right) {
+        const call = name === "pow" ? undefined : overloaded(name, [left, right]);
+        if (call) {
+            return call();
+        }
+        if (typeof left === "bigint" && typeof right === "bigint") {
+            switch (name) {
+                case "add":
+                    return int(left + right);
+                case "sub":
+                    return int(left - right);
+                case "mul":
+                    return int(left * right);
+                case "div":
+                    return right === 0n ? fail("attempt to divide by zero") : int(left / right);
+                // A negative integer exponent can't produce an integer result.
+                case "pow":
+                    return right < 0n ? Number(left) ** Number(right) : power(left, right);
+            }
+        }
+        if (typeof left === "number" && typeof right === "number") {
+            switch (name) {
+                case "add":
+                    return left + right;
+                case "sub":
+                    return left - right;
+                case "mul":
+                    return left * right;
+                case "div":
+                    return right === 0 ? fail("attempt to divide by zero") : left / right;
+                case "pow":
+                    return left ** right;
+            }
+        }
+        // A character can be joined to a string, on either side.
+        const joined = (value) => typeof value === "string" || value instanceof Char;
+        if (joined(left) && joined(right) && (typeof left === "string" || typeof right === "string")) {
+            return name === "add" ? display(left) + display(right) : fail("strings can only be joined with `+`");
+        }
+        fail(`cannot apply an arithmetic operator to \`${type(left)}\` and \`${type(right)}\``);
+    }
+
+    const add = (left, right) => arithmetic("add", left, right);
+    const sub = (left, right) => arithmetic("sub", left, right);
+    const mul = (left, right) => arithmetic("mul", left, right);
+    const div = (left, right) => arithmetic("div", left, right);
+    const pow = (left, right) => arithmetic("pow", left, right);
+
+    function neg(value) {
+        const call = overloaded("neg", [value]);
+        if (call) {
+            return call();
+        }
+        if (typeof value === "bigint") {
+            return int(-value);
+        }
+        if (typeof value === "number") {
+            return -value;
+        }
+        fail(`cannot negate a value of type \`${type(value)}\``);
+    }
+
+    // Tells whether two values are equal, for `==` and to compare the values of variants.
+    function equal(left, right) {
+        return compare("==", left, right);
+    }
+
+    // Compares two values with a comparison operator, or calls `eq` or `lt` for struct instances.
+    function compare(operator, left, right) {
+        if (left !== null && right !== null) {
+            const [name, operands, negate] = {
+                "==": ["eq", [left, right], false],
+                "!=": ["eq", [left, right], true],
+                "<": ["lt", [left, right], false],
+                ">": ["lt", [right, left], false],
+                "<=": ["lt", [right, left], true],
+                ">=": ["lt", [left, right], true],
+            }[operator];
+            const call = overloaded(name, operands);
+            if (call) {
+                const result = call();
+                if (typeof result !== "boolean") {
+                    fail(`mismatched types: \`${name}\` should return \`bool\`, found \`${type(result)}\``);
+                }
+                return result !== negate;
+            }
+        }
+        // When an integer is compared with a float, the integer is converted to a float.
+        if (typeof left === "bigint" && typeof right === "number") {
+            left = Number(left);
+        } else if (typeof left === "number" && typeof right === "bigint") {
+            right = Number(right);
+        }
+        const ordered = (left, right) => {
+            switch (operator) {
+                case "==":
+                    return left === right;
+                case "!=":
+                    return left !== right;
+                case "<":
+                    return left < right;
+                case "<=":
+                    return left <= right;
+                case ">":
+                    return left > right;
+                case ">=":
+                    return left >= right;
+            }
+        };
+        const unordered = (kind, equal) => {
+            switch (operator) {
+                case "==":
+                    return equal;
+                case "!=":
+                    return !equal;
+                default:
+                    fail(`${kind} can only be compared with \`==\` and \`!=\``);
+            }
+        };
+        if (typeof left === typeof right && ["bigint", "number", "string"].includes(typeof left)) {
+            return ordered(left, right);
+        }
+        // Characters are ordered by their code point.
+        if (left instanceof Char && right instanceof Char) {
+            return ordered(left.text.codePointAt(0), right.text.codePointAt(0));
+        }
+        if (typeof left === "boolean" && typeof right === "boolean") {
+            return unordered("booleans", left === right);
+        }
+        if (left instanceof Variant && right instanceof Variant) {
+            const same = left.enumeration === right.enumeration
+                && left.name === right.name
+                && left.values.length === right.values.length
+                && left.values.every((value, i) => equal(value, right.values[i]));
+            return unordered("enum variants", same);
+        }
+        if (left === null || right === null) {
+            return unordered("`nil`", left === right);
+        }
+        fail(`cannot compare \`${type(left)}\` with \`${type(right)}\``);
+    }
+
+    const eq = (left, right) => compare("==", left, right);
+    const ne = (left, right) => compare("!=", left, right);
+    const lt = (left, right) => compare("<", left, right);
+    const le = (left, right) => compare("<=", left, right);
+    const gt = (left, right) => compare(">", left, right);
+    const ge = (left, right) => compare(">=", left, right);
+
+    // Returns the range `start..end`, or `start..=end`.
+    function interval(start, end, inclusive = false) {
+        if (typeof start !== "bigint" || typeof end !== "bigint") {
+            fail(`mismatched types: the bounds of a range should be \`int\`, found \`${type(start)}\` and \`${type(end)}\``);
+        }
+        return new Range(start, end, 1n, inclusive);
+    }
+
+    // Returns the items a `for` loop or a comprehension goes through.
+    function* iterate(collection) {
+        if (Array.isArray(collection) || collection instanceof Range) {
+            yield* collection;
+        } else if (typeof collection === "string") {
+            yield* Array.from(collection, char);
+        } else if (isInstance(collection)) {
+            if (!Object.hasOwn(collection, "next")) {
+                fail(`\`${type(collection)}\` is not iterable, it has no \`next\` field`);
+            }
+            for (let item = collection.next(); item !== null; item = collection.next()) {
+                yield item;
+            }
+        } else {
+            fail(`\`${type(collection)}\` is not iterable`);
+        }
+    }
+
+    // Builds the array of a comprehension, `[value for item in collection if condition]`.
+    function comprehension(collection, value, condition) {
+        const values = [];
+        for (const item of iterate(collection)) {
+            if (condition === undefined || condition(item)) {
+                values.push(value(item));
+            }
+        }
+        return values;
+    }
+
+    // Checks that an index is an integer within the bounds of an array.
+    function position(array, index) {
+        if (typeof index !== "bigint") {
+            fail(`mismatched types: an array index should be \`int\`, found \`${type(index)}\``);
+        }
+        if (index < 0n || index >= BigInt(array.length)) {
+            fail(`index out of bounds: the length is ${array.length} but the index is ${index}`);
+        }
+        return Number(index);
+    }
+
+    // Returns the element of an array at an index, or the slice at a range.
+    function index(array, index) {
+        if (!Array.isArray(array)) {
+            fail(`cannot index into a value of type \`${type(array)}\``);
+        }
+        if (index instanceof Range) {
+            return Array.from(index, (i) => array[position(array, i)]);
+        }
+        return array[position(array, index)];
+    }
+
+    // Returns a copy of a value where the element or field at the end of the keys is replaced, for
+    // an assignment like `matrix[i][j] = 0`. Values are never shared, so the variable assigned to
+    // is the only one to change, like in Mussel.
+    function set(target, [key, ...keys], value) {
+        if (key === undefined) {
+            return value;
+        }
+        if (typeof key === "string") {
+            if (!isInstance(target)) {
+                fail(`no field \`${key}\` on a value of type \`${type(target)}\``);
+            }
+            if (!Object.hasOwn(target, key)) {
+                fail(`no field \`${key}\` on struct \`${type(target)}\``);
+            }
+            const copy = Object.assign(Object.create(Object.getPrototypeOf(target)), target);
+            copy[key] = set(target[key], keys, value);
+            return copy;
+        }
+        if (!Array.isArray(target)) {
+            fail(`cannot index into a value of type \`${type(target)}\``);
+        }
+        const copy = target.slice();
+        const i = position(target, key);
+        copy[i] = set(target[i], keys, value);
+        return copy;
+    }
+
+    // Returns the definition of a struct, called to construct an instance with a value per field.
+    function struct(name, fields) {
+        const definition = (...values) => {
+            if (values.length !== fields.length) {
+                fail(`struct \`${name}\` has ${fields.length} fields, but ${values.length} values were given`);
+            }
+            const instance = Object.create(definition.instances);
+            fields.forEach((field, i) => {
+                instance[field] = values[i];
+            });
+            return instance;
+        };
+        definition[KIND] = "struct";
+        definition.structName = name;
+        definition.instances = { [STRUCT]: definition };
+        definition.methods = Object.create(null);
+        return definition;
+    }
+
+    // Adds methods to a struct. The methods implementing a trait must include all its methods.
+    function impl(definition, methods, trait) {
+        if (trait !== undefined) {
+            const missing = trait.methods.filter((method) => !Object.hasOwn(methods, method));
+            if (missing.length > 0) {
+                fail(`not all methods of trait \`${trait.name}\` are implemented for \`${definition.structName}\``);
+            }
+        }
+        Object.assign(definition.methods, methods);
+    }
+
+    function trait(name, methods) {
+        return { [KIND]: "trait", name, methods };
+    }
+
+    // Returns the definition of an enum: the variants without values, and functions giving the
+    // variants with values.
+    function enumeration(name, variants) {
+        const definition = { [KIND]: "enum" };
+        for (const [variant, fields] of Object.entries(variants)) {
+            definition[variant] = fields.length === 0
+                ? new Variant(name, variant, [])
+                : (...values) => {
+                    if (values.length !== fields.length) {
+                        fail(`variant \`${name}::${variant}\` carries ${fields.length} values, but ${values.length} were given`);
+                    }
+                    return new Variant(name, variant, values);
+                };
+        }
+        return definition;
+    }
+
+    // Resolves a path like `Color::Red` to the variant it names, or `Point::new` to a method.
+    function path(definition, name) {
+        const found = definition?.[KIND] === "struct" ? definition.methods[name] : definition?.[name];
+        return found ?? fail(`cannot find \`${name}\` in \`${type(definition)}\``);
+    }
+
+    function variantOf(value, enumeration, name) {
+        return value instanceof Variant && value.enumeration === enumeration && value.name === name;
+    }
+
+    function instanceOf(value, name) {
+        return isInstance(value) && value[STRUCT].structName === name;
+    }
+
+    // Calls a method: a field of an instance holding a function or a function of a module is
+    // called with the arguments, otherwise the method of the struct, or else the function with the
+    // name of the method, is called with the receiver first.
+    function invoke(receiver, name, fallback, ...args) {
+        if ((isInstance(receiver) || receiver instanceof Module) && Object.hasOwn(receiver, name)) {
+            return receiver[name](...args);
+        }
+        if (receiver instanceof Module) {
+            fail(`cannot find \`${name}\` in module \`${receiver.$name}\``);
+        }
+        const method = (isInstance(receiver) && receiver[STRUCT].methods[name]) || fallback;
+        if (method === undefined) {
+            fail(`no method named \`${name}\` found for \`${type(receiver)}\``);
+        }
+        return method(receiver, ...args);
+    }
+
+    function assert(condition, message) {
+        if (condition === false) {
+            fail(message === undefined ? "assertion failed" : `assertion failed: ${display(message())}`);
+        }
+        if (condition !== true) {
+            fail(`mismatched types: the condition of \`assert\` should be \`bool\`, found \`${type(condition)}\``);
+        }
+    }
+
+    // Returns a function giving what a file declares, running the file the first time.
+    function module(name, run) {
+        let members;
+        return () => (members ??= new Module(name, run()));
+    }
+
+    const node = typeof process !== "undefined" && typeof require === "function";
+
+    function write(text) {
+        if (node) {
+            process.stdout.write(text);
+        } else {
+            console.log(text);
+        }
+    }
+
+    // Reads a line of the standard input with Node.js, or asks for it in a browser.
+    // Returns null when there is nothing left to read.
+    function readLine(prompt) {
+        if (!node) {
+            return globalThis.prompt?.(prompt) ?? null;
+        }
+        write(prompt);
+        const fs = require("fs");
+        const bytes = [];
+        const byte = Buffer.alloc(1);
+        let read;
+        while ((read = fs.readSync(0, byte, 0, 1, null)) === 1 && byte[0] !== 10) {
+            bytes.push(byte[0]);
+        }
+        if (read === 0 && bytes.length === 0) {
+            return null;
+        }
+        return Buffer.from(bytes).toString("utf8").trimEnd();
+    }
+
+    // The built-ins that don't need an `include`.
+    const builtins = {
+        println: (...values) => {
+            if (node) {
+                write(values.map(display).join("") + "\n");
+            } else {
+                console.log(values.map(display).join(""));
+            }
+        },
+        input: (prompt) => readLine(prompt === undefined ? "" : display(prompt)),
+        range: (start, end, step = 1n) => {
+            if (![start, end, step].every((n) => typeof n === "bigint")) {
+                fail("range expects integer arguments");
+            }
+            if (step === 0n) {
+                fail("range step can't be zero");
+            }
+            return new Range(start, end, step, false);
+        },
+        is_nil: (value) => value === null,
+        typeof_: (value) => type(value),
+        get: (collection, index, fallback = null) => {
+            if (typeof index !== "bigint") {
+                fail(`get expects an integer index, got ${display(index)}`);
+            }
+            const items = typeof collection === "string" ? Array.from(collection, char) : collection;
+            if (!Array.isArray(items)) {
+                fail(`get expects an array or a string, got ${display(collection)}`);
+            }
+            return index >= 0n && index < BigInt(items.length) ? items[Number(index)] : fallback;
+        },
+        call: (callee, args) => {
+            if (typeof callee === "string") {
+                fail("call can't find functions by name in JavaScript, pass the function itself");
+            }
+            return callee(...args);
+        },
+        // The arguments after the path of the script, in Node.js.
+        args: () => (node ? process.argv.slice(2) : []),
+        // Ends the script right away with an exit status, 0 by default, between 0 and 255, in Node.js.
+        exit: (status = 0n) => {
+            integer("exit", status);
+            if (status < 0n || status > 255n) {
+                fail(`exit expects a status between 0 and 255, not ${status}`);
+            }
+            return node ? process.exit(Number(status)) : fail("exit needs Node.js");
+        },
+    };
+
+    // Makes an integer value of the last statement of the script its exit status, which fails out
+    // of the range of the statuses, 0 to 255.
+    function finish(value) {
+        if (node && typeof value === "bigint") {
+            if (value < 0n || value > 255n) {
+                fail(`The value of the last statement, ${value}, isn't an exit status between 0 and 255`);
+            }
+            process.exitCode = Number(value);
+        }
+    }
+
+    function text(name, value) {
+        return typeof value === "string" ? value : fail(`${name} expects a string argument`);
+    }
+
+    function integer(name, value) {
+        return typeof value === "bigint" ? value : fail(`${name} expects an integer argument`);
+    }
+
+    function numeric(name, value) {
+        return typeof value === "bigint" || typeof value === "number" ? Number(value) : fail(`${name} expects a numeric argument`);
+    }
+
+    function system(name) {
+        return node ? require(name) : fail("the `os` library needs Node.js");
+    }
+
+    // The libraries of the standard library that aren't written in Mussel.
+    const libraries = {
+        math: {
+            abs: (n) => (typeof n === "bigint" ? int(n < 0n ? -n : n) : Math.abs(numeric("abs", n))),
+            sqrt: (n) => (numeric("sqrt", n) < 0 ? null : Math.sqrt(Number(n))),
+            pow: (base, exponent) => numeric("pow", base) ** numeric("pow", exponent),
+        },
+        string: {
+            lowercase: (s) => text("lowercase", s).toLowerCase(),
+            uppercase: (s) => text("uppercase", s).toUpperCase(),
+            // The length of a string is its number of bytes in UTF-8, like in Rust.
+            length: (value) => BigInt(Array.isArray(value) ? value.length : new TextEncoder().encode(text("length", value)).length),
+            split: (s, separator) => text("split", s).split(text("split", separator)),
+            reverse: (s) => Array.from(text("reverse", s)).reverse().join(""),
+            trim: (s) => text("trim", s).trim(),
+            ltrim: (s) => text("ltrim", s).trimStart(),
+            rtrim: (s) => text("rtrim", s).trimEnd(),
+            chars: (s) => Array.from(text("chars", s), char),
+            ord: (c) => (c instanceof Char ? BigInt(c.text.codePointAt(0)) : fail("ord expects a character argument")),
+            chr: (n) => {
+                const code = Number(integer("chr", n));
+                if (code < 0 || code > 0x10ffff || (code >= 0xd800 && code <= 0xdfff)) {
+                    fail(`${n} is not a valid character code`);
+                }
+                return char(String.fromCodePoint(code));
+            },
+        },
+        time: {
+            time_ms: () => BigInt(Date.now()),
+            time_sec: () => Date.now() / 1000,
+        },
+        random: {
+            rand: (min, max) => {
+                if (integer("rand", min) > integer("rand", max)) {
+                    fail(`rand expects min to be at most max, got ${min} and ${max}`);
+                }
+                return min + BigInt(Math.floor(Math.random() * Number(max - min + 1n)));
+            },
+        },
+        os: {
+            getcwd: () => system("process").cwd(),
+            listdir: (path) => {
+                try {
+                    return system("fs").readdirSync(text("listdir", path));
+                } catch {
+                    return null;
+                }
+            },
+            exists: (path) => system("fs").existsSync(text("exists", path)),
+        },
+    };
+
+    function library(name) {
+        return new Module(name, libraries[name]);
+    }
+
+    return {
+        Thrown, caught, char, display, format, overload, add, sub, mul, div, pow, neg, eq, ne, lt, le, gt, ge,
+        interval, iterate, comprehension, index, set, struct, impl, trait, enumeration, path, variantOf,
+        instanceOf, invoke, assert, module, library, finish, ...builtins,
+    };
+})();
+
+const { println, input, range, is_nil, typeof_, get, call, args, exit } = $;
@@ -0,0 +1,89 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The signatures of the messages of the kernel: HMAC with SHA-256, the `hmac-sha256` scheme of the
+// connection files (RFC 2104 and FIPS 180-4).
+
+// The first 32 bits of the fractional parts of the cube roots of the first 64 primes.
+const ROUNDS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5, 0xd807aa98,
+    0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786,
+    0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8,
+    0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13,
+    0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819,
+    0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a,
+    0x5b9cca4f, 0x682e6ff3, 0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
+    0xc67178f2,
+];
+
+// The first 32 bits of the fractional parts of the square roots of the first 8 primes.
+const START: [u32; 8] = [0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19];
+
+const BLOCK: usize = 64;
+
+// Returns the signature of the parts of a message, in lowercase hexadecimal.
+pub(crate) fn sign(key: &[u8], parts: &[&[u8]]) -> String {
+    // A key longer than a block is hashed first.
+    let mut padded = [0; BLOCK];
+    if key.len() > BLOCK {
+        padded[..32].copy_from_slice(&sha256(&[key]));
+    } else {
+        padded[..key.len()].copy_from_slice(key);
+    }
+    let inner_key = padded.map(|byte| byte ^ 0x36);
+    let outer_key = padded.map(|byte| byte ^ 0x5c);
+    let inner = sha256(&[&[&inner_key[..]], parts].concat());
+    let outer = sha256(&[&outer_key[..], &inner[..]]);
+    outer.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// Tells whether a signature is the one of the parts of a message. Every byte is compared whatever
+// the ones before, so the time taken doesn't tell how much of a forged signature is right.
+pub(crate) fn verify(key: &[u8], parts: &[&[u8]], signature: &[u8]) -> bool {
+    let expected = sign(key, parts);
+    let expected = expected.as_bytes();
+    expected.len() == signature.len()
+        && expected.iter().zip(signature).fold(0, |difference, (left, right)| difference | (left ^ right)) == 0
+}
+
+// Returns the SHA-256 hash of the parts of a message, joined.
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut message: Vec<u8> = parts.concat();
+    let length = message.len() as u64 * 8;
+    message.push(0x80);
+    while message.len() % BLOCK != BLOCK - 8 {
+        message.push(0);
+    }
+    message.extend(length.to_be_bytes());
+    let mut state = START;
+    for block in message.chunks(BLOCK) {
+        let mut words = [0u32; 64];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for index in 16..64 {
+            let (before, far) = (words[index - 2], words[index - 15]);
+            let high = before.rotate_right(17) ^ before.rotate_right(19) ^ (before >> 10);
+            let low = far.rotate_right(7) ^ far.rotate_right(18) ^ (far >> 3);
+            words[index] = high.wrapping_add(words[index - 7]).wrapping_add(low).wrapping_add(words[index - 16]);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+        for (round, word) in ROUNDS.iter().zip(words) {
+            let sum_e = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let first = h.wrapping_add(sum_e).wrapping_add(choice).wrapping_add(*round).wrapping_add(word);
+            let sum_a = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let second = sum_a.wrapping_add(majority);
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(first), c, b, a, first.wrapping_add(second));
+        }
+        for (value, added) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *value = value.wrapping_add(added);
+        }
+    }
+    let mut hash = [0; 32];
+    for (bytes, value) in hash.chunks_mut(4).zip(state) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    hash
+}
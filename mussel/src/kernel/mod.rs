@@ -0,0 +1,461 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel kernel`, a Jupyter kernel running the cells of notebooks, started by Jupyter with the
+// connection file telling the ports to listen on and the key signing the messages.
+// Every cell runs in the same global scope, like the entries of an interactive session, see
+// `repl`. What a cell prints is shown under it as it is written, followed by the value of its last
+// statement, with the arrays shown as tables too. The kernel also answers whether a cell is
+// complete, and completes and inspects the names declared.
+// The messages go through the sockets of the Jupyter messaging protocol:
+// - shell and control, where the notebook sends the requests and the kernel answers them,
+// - iopub, where the kernel publishes what the cells print and whether it is busy,
+// - heartbeat, which echoes what it receives to tell the kernel is alive,
+// - stdin, which the kernel doesn't use: `input()` reads the standard input of the kernel.
+// The requests are answered one at a time, so a control request waits for the cell running.
+
+mod hmac;
+mod zmtp;
+
+use std::cell::RefCell;
+use std::fmt::Write as _;
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::context::Context;
+use crate::expr::Expr;
+use crate::interpreter;
+use crate::interpreter::{Backend, Interaction};
+use crate::loader::Loader;
+use crate::lsp::json::{object, Json};
+use crate::output;
+use crate::repl;
+
+// The name of the cells in the errors reported.
+const CELL: &str = "<cell>";
+
+// The version of the messaging protocol spoken.
+const PROTOCOL: &str = "5.3";
+
+// Separates the identities of the peer from the parts of a message.
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+// The number of the last message sent, each message gets its own identifier.
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Where the text a cell writes is published, and the request running the cell.
+    static PRINTED: RefCell<Option<(Publisher, Json)>> = const { RefCell::new(None) };
+}
+
+// Runs the kernel until the notebook asks it to shut down. Fails when the connection file can't
+// be read or a port can't be listened on.
+pub fn serve(connection_file: &Path, check_types: bool, max_depth: usize, backend: Backend) -> ExitCode {
+    let connection = match std::fs::read_to_string(connection_file).ok().and_then(|text| Json::parse(&text)) {
+        Some(connection) => connection,
+        None => {
+            eprintln!("error: Failed to read the connection file {}", connection_file.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    let signer = Signer {
+        key: connection.get(&["key"]).and_then(Json::as_str).unwrap_or_default().as_bytes().to_vec(),
+        session: format!("{:032x}", rand::random::<u128>()),
+    };
+    if connection.get(&["signature_scheme"]).and_then(Json::as_str).is_some_and(|scheme| scheme != "hmac-sha256") {
+        eprintln!("error: only the hmac-sha256 signature scheme is supported");
+        return ExitCode::FAILURE;
+    }
+    let ip = connection.get(&["ip"]).and_then(Json::as_str).unwrap_or("127.0.0.1");
+    let mut listeners = Vec::new();
+    for port in ["shell_port", "control_port", "iopub_port", "stdin_port", "hb_port"] {
+        let port = connection.get(&[port]).and_then(Json::as_usize).unwrap_or_default();
+        match TcpListener::bind(format!("{ip}:{port}")) {
+            Ok(listener) => listeners.push(listener),
+            Err(error) => {
+                eprintln!("error: Failed to listen on {ip}:{port}: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let [shell, control, iopub, stdin, heartbeat] = <[TcpListener; 5]>::try_from(listeners).expect("five ports");
+
+    // The requests of the shell and control sockets are answered by this thread, which runs the
+    // cells, the other sockets are served by threads of their own.
+    let (sender, requests) = mpsc::channel();
+    for listener in [shell, control] {
+        let (sender, signer) = (sender.clone(), signer.clone());
+        accept(listener, "ROUTER", move |stream| read_requests(stream, &sender, &signer));
+    }
+    let publisher = Publisher { subscribers: Arc::new(Mutex::new(Vec::new())), signer: signer.clone() };
+    let subscribers = publisher.subscribers.clone();
+    accept(iopub, "PUB", move |stream| {
+        let Ok(mut reading) = stream.try_clone() else {
+            return;
+        };
+        subscribers.lock().expect("the subscribers are never poisoned").push(stream);
+        // The subscriptions are ignored, every message is sent to every subscriber.
+        while zmtp::receive(&mut reading).is_some() {}
+    });
+    accept(stdin, "ROUTER", |mut stream| while zmtp::receive(&mut stream).is_some() {});
+    accept(heartbeat, "REP", |mut stream| {
+        while let Some(frames) = zmtp::receive(&mut stream) {
+            if zmtp::send(&mut stream, &frames).is_err() {
+                return;
+            }
+        }
+    });
+
+    let mut context = interpreter::session(Loader::new(check_types), max_depth, backend);
+    // The cells are the script running, like the entries of an interactive session.
+    context.loader().enter(Path::new(CELL));
+    let mut kernel = Kernel { context, publisher, signer, count: 0 };
+    for (request, mut reply) in requests {
+        kernel.publisher.publish("status", &request.header, object([("execution_state", "busy".into())]));
+        let answer = kernel.answer(&request);
+        kernel.publisher.publish("status", &request.header, object([("execution_state", "idle".into())]));
+        let Some((msg_type, content, status)) = answer else {
+            continue;
+        };
+        let frames = kernel.signer.frames(&request.identities, &msg_type, &request.header, content);
+        if let Err(error) = zmtp::send(&mut reply, &frames) {
+            eprintln!("error: Failed to answer the notebook: {error}");
+        }
+        if let Some(status) = status {
+            return status;
+        }
+    }
+    ExitCode::FAILURE
+}
+
+// Accepts the connections to a socket, greeting each one and handing it to `serve` on a thread of
+// its own.
+fn accept(listener: TcpListener, socket: &'static str, serve: impl Fn(TcpStream) + Clone + Send + 'static) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let serve = serve.clone();
+            std::thread::spawn(move || {
+                if zmtp::handshake(&mut stream, socket).is_ok() {
+                    serve(stream);
+                }
+            });
+        }
+    });
+}
+
+// Reads the requests sent on a connection to the shell or control socket, and sends them with the
+// connection to answer them on. The messages with a wrong signature are dropped.
+fn read_requests(mut stream: TcpStream, sender: &Sender<(Request, TcpStream)>, signer: &Signer) {
+    let Ok(reply) = stream.try_clone() else {
+        return;
+    };
+    while let Some(frames) = zmtp::receive(&mut stream) {
+        let Some(delimiter) = frames.iter().position(|frame| frame == DELIMITER) else {
+            continue;
+        };
+        let [signature, header, parent, metadata, content, ..] = &frames[delimiter + 1..] else {
+            continue;
+        };
+        if !signer.key.is_empty() && !hmac::verify(&signer.key, &[header, parent, metadata, content], signature) {
+            eprintln!("error: a message with a wrong signature was dropped");
+            continue;
+        }
+        let parse = |frame: &[u8]| Json::parse(&String::from_utf8_lossy(frame));
+        let (Some(header), Some(content)) = (parse(header), parse(content)) else {
+            continue;
+        };
+        let request = Request { identities: frames[..delimiter].to_vec(), header, content };
+        let Ok(reply) = reply.try_clone() else {
+            return;
+        };
+        if sender.send((request, reply)).is_err() {
+            return;
+        }
+    }
+}
+
+// A request of the notebook.
+struct Request {
+    identities: Vec<Vec<u8>>, // Who sent it, to route the answer back.
+    header: Json,
+    content: Json,
+}
+
+impl Request {
+    fn string(&self, key: &str) -> &str {
+        self.content.get(&[key]).and_then(Json::as_str).unwrap_or_default()
+    }
+}
+
+// Writes the messages of the kernel, signed with the key of the connection file.
+#[derive(Clone)]
+struct Signer {
+    key: Vec<u8>,
+    session: String, // Identifies the kernel in the headers of its messages.
+}
+
+impl Signer {
+    // Returns the frames of a message answering the request with the given header.
+    fn frames(&self, identities: &[Vec<u8>], msg_type: &str, parent: &Json, content: Json) -> Vec<Vec<u8>> {
+        let sequence = SEQUENCE.fetch_add(1, Ordering::Relaxed) + 1;
+        let header = object([
+            ("msg_id", format!("{}_{sequence}", self.session).into()),
+            ("session", self.session.as_str().into()),
+            ("username", "kernel".into()),
+            ("date", now().into()),
+            ("msg_type", msg_type.into()),
+            ("version", PROTOCOL.into()),
+        ]);
+        let parts = [header, parent.clone(), object([]), content].map(|part| part.to_string().into_bytes());
+        let signature = match self.key.is_empty() {
+            true => String::new(),
+            false => hmac::sign(&self.key, &parts.each_ref().map(Vec::as_slice)),
+        };
+        let mut frames = identities.to_vec();
+        frames.extend([DELIMITER.to_vec(), signature.into_bytes()]);
+        frames.extend(parts);
+        frames
+    }
+}
+
+// Publishes messages on the iopub socket, to every notebook listening.
+#[derive(Clone)]
+struct Publisher {
+    subscribers: Arc<Mutex<Vec<TcpStream>>>,
+    signer: Signer,
+}
+
+impl Publisher {
+    // Publishes a message about the request with the given header. The subscribers which can't be
+    // written to anymore are forgotten.
+    fn publish(&self, msg_type: &str, parent: &Json, content: Json) {
+        let frames = self.signer.frames(&[msg_type.as_bytes().to_vec()], msg_type, parent, content);
+        let mut subscribers = self.subscribers.lock().expect("the subscribers are never poisoned");
+        subscribers.retain_mut(|subscriber| zmtp::send(subscriber, &frames).is_ok());
+    }
+}
+
+// Publishes what a cell writes as it is written.
+fn write(text: &str) {
+    PRINTED.with_borrow(|printed| {
+        if let Some((publisher, parent)) = printed {
+            publisher.publish("stream", parent, object([("name", "stdout".into()), ("text", text.into())]));
+        }
+    });
+}
+
+struct Kernel {
+    context: Context,
+    publisher: Publisher,
+    signer: Signer,
+    count: usize, // The number of cells run, shown next to them.
+}
+
+// An answer: its type, its content and, when the kernel stops after it, its exit status.
+type Answer = Option<(String, Json, Option<ExitCode>)>;
+
+impl Kernel {
+    // Answers a request, or returns `None` for the requests which aren't supported.
+    fn answer(&mut self, request: &Request) -> Answer {
+        let msg_type = request.header.get(&["msg_type"]).and_then(Json::as_str).unwrap_or_default();
+        let reply = msg_type.strip_suffix("_request")?.to_string() + "_reply";
+        let ok = ("status", Json::from("ok"));
+        let content = match msg_type {
+            "kernel_info_request" => object([
+                ok,
+                ("protocol_version", PROTOCOL.into()),
+                ("implementation", "mussel".into()),
+                ("implementation_version", env!("CARGO_PKG_VERSION").into()),
+                (
+                    "language_info",
+                    object([
+                        ("name", "mussel".into()),
+                        ("version", env!("CARGO_PKG_VERSION").into()),
+                        ("mimetype", "text/x-mussel".into()),
+                        ("file_extension", ".mus".into()),
+                    ]),
+                ),
+                ("banner", format!("Mussel {}", env!("CARGO_PKG_VERSION")).into()),
+                ("help_links", Json::Array(Vec::new())),
+            ]),
+            "execute_request" => return Some(self.execute(request, reply)),
+            "is_complete_request" => match repl::unfinished(request.string("code")) {
+                true => object([("status", "incomplete".into()), ("indent", "    ".into())]),
+                false => object([("status", "complete".into())]),
+            },
+            "complete_request" => self.complete(request),
+            "inspect_request" => self.inspect(request),
+            "history_request" => object([ok, ("history", Json::Array(Vec::new()))]),
+            "comm_info_request" => object([ok, ("comms", object([]))]),
+            "interrupt_request" => object([ok]),
+            "shutdown_request" => {
+                let restart = request.content.get(&["restart"]).cloned().unwrap_or(Json::Bool(false));
+                return Some((reply, object([ok, ("restart", restart)]), Some(ExitCode::SUCCESS)));
+            }
+            _ => return None,
+        };
+        Some((reply, content, None))
+    }
+
+    // Runs a cell, publishing what it writes and the value of its last statement. A silent cell
+    // isn't counted and its value isn't shown. `exit` in a cell stops the kernel.
+    fn execute(&mut self, request: &Request, reply: String) -> (String, Json, Option<ExitCode>) {
+        let code = request.string("code");
+        let silent = matches!(request.content.get(&["silent"]), Some(Json::Bool(true)));
+        if !silent {
+            self.count += 1;
+            let input = object([("code", code.into()), ("execution_count", self.count.into())]);
+            self.publisher.publish("execute_input", &request.header, input);
+        }
+        PRINTED.set(Some((self.publisher.clone(), request.header.clone())));
+        let interaction = output::forward(write, || match self.context.loader().load_source(CELL, code) {
+            Ok(exprs) => interpreter::interact(exprs, &mut self.context),
+            Err(error) => {
+                self.context.loader().show(&error);
+                Interaction::Failed
+            }
+        });
+        PRINTED.set(None);
+        let count = ("execution_count", Json::from(self.count));
+        let (content, status) = match interaction {
+            Interaction::Value(value) => {
+                if !silent && !matches!(value, Expr::Void) {
+                    let result = object([count.clone(), ("data", display(&value)), ("metadata", object([]))]);
+                    self.publisher.publish("execute_result", &request.header, result);
+                }
+                (object([("status", "ok".into()), count, ("user_expressions", object([]))]), None)
+            }
+            // The error was written with what the cell printed.
+            Interaction::Failed => {
+                let error = [("ename", "Error".into()), ("evalue", "".into()), ("traceback", Json::Array(Vec::new()))];
+                self.publisher.publish("error", &request.header, object(error.clone()));
+                let [ename, evalue, traceback] = error;
+                (object([("status", "error".into()), count, ename, evalue, traceback]), None)
+            }
+            Interaction::Exit(status) => (object([("status", "ok".into()), count]), Some(ExitCode::from(status))),
+        };
+        (reply, content, status)
+    }
+
+    // Completes the name written before the cursor with the names declared.
+    fn complete(&self, request: &Request) -> Json {
+        let (start, prefix) = name_before(request);
+        let matches: Vec<Json> =
+            self.context.names().into_iter().filter(|name| name.starts_with(&prefix)).map(Json::from).collect();
+        let end = start + prefix.chars().count();
+        object([
+            ("status", "ok".into()),
+            ("matches", Json::Array(matches)),
+            ("cursor_start", start.into()),
+            ("cursor_end", end.into()),
+            ("metadata", object([])),
+        ])
+    }
+
+    // Shows the type and the value of the name written at the cursor.
+    fn inspect(&self, request: &Request) -> Json {
+        let (start, before) = name_before(request);
+        let after: String = request.string("code").chars().skip(start + before.chars().count()).take_while(|c| is_name(*c)).collect();
+        let name = before + &after;
+        let data = match self.context.get(&name) {
+            Some(value) => object([("text/plain", format!("{name}: {} = {value}", value.type_name()).into())]),
+            None => object([]),
+        };
+        let found = !matches!(&data, Json::Object(members) if members.is_empty());
+        object([("status", "ok".into()), ("found", found.into()), ("data", data), ("metadata", object([]))])
+    }
+}
+
+fn is_name(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Returns the part of a name written before the cursor of a request, and where it starts, counted
+// in characters like the cursor.
+fn name_before(request: &Request) -> (usize, String) {
+    let cursor = request.content.get(&["cursor_pos"]).and_then(Json::as_usize).unwrap_or_default();
+    let before: Vec<char> = request.string("code").chars().take(cursor).collect();
+    let start = before.iter().rposition(|c| !is_name(*c)).map_or(0, |index| index + 1);
+    (start, before[start..].iter().collect())
+}
+
+// Returns how a value is shown: as text, like in an interactive session, and the arrays as an
+// HTML table too. The elements of an array of instances of the same struct are shown with a column
+// per field, the ones of an array of arrays with a column per index.
+fn display(value: &Expr) -> Json {
+    let text = ("text/plain", Json::from(value.to_string()));
+    let Expr::Array(elements) = value else {
+        return object([text]);
+    };
+    let rows: Vec<Vec<String>> = elements
+        .iter()
+        .map(|element| match element {
+            Expr::Instance(_, fields) => fields.iter().map(|(_, value)| value.to_string()).collect(),
+            Expr::Array(items) => items.iter().map(Expr::to_string).collect(),
+            element => vec![element.to_string()],
+        })
+        .collect();
+    let columns: Vec<String> = match elements.first() {
+        None => return object([text]),
+        Some(Expr::Instance(name, fields))
+            if elements.iter().all(|element| matches!(element, Expr::Instance(other, _) if other == name)) =>
+        {
+            fields.iter().map(|(field, _)| field.clone()).collect()
+        }
+        _ if elements.iter().all(|element| matches!(element, Expr::Array(_))) => {
+            (0..rows.iter().map(Vec::len).max().unwrap_or_default()).map(|index| index.to_string()).collect()
+        }
+        // The elements of mixed types are shown in a single column.
+        _ => vec!["value".to_string()],
+    };
+    let mut html = String::from("<table>\n<thead><tr><th></th>");
+    for column in &columns {
+        write!(html, "<th>{}</th>", escape(column)).expect("writing to a string can't fail");
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for (index, (element, row)) in elements.iter().zip(rows).enumerate() {
+        let row = if columns.len() == 1 && columns[0] == "value" { vec![element.to_string()] } else { row };
+        write!(html, "<tr><th>{index}</th>").expect("writing to a string can't fail");
+        for column in 0..columns.len() {
+            let cell = row.get(column).map(String::as_str).unwrap_or_default();
+            write!(html, "<td>{}</td>", escape(cell)).expect("writing to a string can't fail");
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>");
+    object([text, ("text/html", html.into())])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Returns the current time in UTC, written like `2025-03-01T12:30:05.123456Z`.
+fn now() -> String {
+    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (days, seconds) = (elapsed.as_secs() / 86400, elapsed.as_secs() % 86400);
+    // The civil date of a day counted from 1970-01-01, by eras of 400 years (Howard Hinnant's
+    // `civil_from_days`).
+    let days = days + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:06}Z",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        elapsed.subsec_micros()
+    )
+}
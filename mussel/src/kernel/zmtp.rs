@@ -0,0 +1,99 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The part of ZMTP 3.0, the protocol of ZeroMQ over TCP, that the kernel needs: each socket of the
+// kernel listens on a port and the notebook connects to it. A connection starts with a greeting
+// and a `READY` command giving the type of the sockets, without security (the `NULL` mechanism),
+// then carries messages made of frames (https://rfc.zeromq.org/spec/23/).
+// Each connection is handled on its own, so the kernel answers a message on the connection it came
+// from, like a `ROUTER` socket routing its reply to the peer which sent the request.
+
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+// The flags of a frame.
+const MORE: u8 = 0x01; // Another frame of the message follows.
+const LONG: u8 = 0x02; // The size is written on 8 bytes instead of 1.
+const COMMAND: u8 = 0x04; // The frame is a command, like `READY`, not a part of a message.
+
+// The size of the largest message read, its frames together, so a peer can't make the kernel
+// allocate more than that.
+const MAX_MESSAGE: usize = 64 * 1024 * 1024;
+
+// Greets the peer which connected, telling the type of the socket of the kernel: `ROUTER`, `PUB`
+// or `REP`. Fails when the peer doesn't speak ZMTP 3 without security.
+pub(crate) fn handshake(stream: &mut TcpStream, socket: &str) -> io::Result<()> {
+    let mut greeting = [0; 64];
+    greeting[0] = 0xff;
+    greeting[9] = 0x7f;
+    greeting[10] = 3; // The version, 3.0.
+    greeting[12..16].copy_from_slice(b"NULL");
+    stream.write_all(&greeting)?;
+    let mut peer = [0; 64];
+    stream.read_exact(&mut peer)?;
+    if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 || &peer[12..17] != b"NULL\0" {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the peer doesn't speak ZMTP 3 without security"));
+    }
+    let mut ready = vec![5];
+    ready.extend(b"READY");
+    ready.push(11);
+    ready.extend(b"Socket-Type");
+    ready.extend((socket.len() as u32).to_be_bytes());
+    ready.extend(socket.as_bytes());
+    stream.write_all(&frame(COMMAND, &ready))?;
+    // The `READY` of the peer is read like the commands coming later, and skipped.
+    Ok(())
+}
+
+// Reads the next message, skipping the commands. Returns `None` when the peer closed the connection,
+// or sent a message larger than `MAX_MESSAGE`, after which the connection can't be read anymore.
+pub(crate) fn receive(stream: &mut TcpStream) -> Option<Vec<Vec<u8>>> {
+    let mut frames = Vec::new();
+    let mut total: usize = 0;
+    loop {
+        let mut flags = [0];
+        stream.read_exact(&mut flags).ok()?;
+        let size = if flags[0] & LONG != 0 {
+            let mut size = [0; 8];
+            stream.read_exact(&mut size).ok()?;
+            usize::try_from(u64::from_be_bytes(size)).ok()?
+        } else {
+            let mut size = [0];
+            stream.read_exact(&mut size).ok()?;
+            size[0] as usize
+        };
+        total = total.checked_add(size).filter(|total| *total <= MAX_MESSAGE)?;
+        let mut body = vec![0; size];
+        stream.read_exact(&mut body).ok()?;
+        if flags[0] & COMMAND != 0 {
+            continue;
+        }
+        frames.push(body);
+        if flags[0] & MORE == 0 {
+            return Some(frames);
+        }
+    }
+}
+
+// Writes a message, each of its frames in turn.
+pub(crate) fn send(stream: &mut TcpStream, frames: &[Vec<u8>]) -> io::Result<()> {
+    let mut message = Vec::new();
+    for (index, body) in frames.iter().enumerate() {
+        message.extend(frame(if index + 1 < frames.len() { MORE } else { 0 }, body));
+    }
+    stream.write_all(&message)
+}
+
+// Writes a frame with its flags and its size.
+fn frame(flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(body.len() + 9);
+    match u8::try_from(body.len()) {
+        Ok(size) => frame.extend([flags, size]),
+        Err(_) => {
+            frame.push(flags | LONG);
+            frame.extend((body.len() as u64).to_be_bytes());
+        }
+    }
+    frame.extend(body);
+    frame
+}
@@ -5,17 +5,16 @@ extern crate nom;
 extern crate nom_locate;
 
 use std::ops::Range;
-use color_eyre::{Section};
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until};
 use nom::bytes::complete::{take_while, take_while1};
-use nom::character::complete::{char, multispace1, not_line_ending};
+use nom::character::complete::{anychar, char, multispace1, none_of, not_line_ending};
 use nom::character::complete::digit1;
 use nom::combinator::{map, map_res, opt, recognize};
 use nom::error::{ErrorKind, FromExternalError, ParseError};
-use nom::InputLength;
+use nom::{InputLength, Slice};
 use nom::multi::many0;
-use nom::sequence::{delimited, pair, tuple};
+use nom::sequence::{delimited, pair};
 use nom_locate::{position, LocatedSpan};
 use nom_supreme::final_parser::{final_parser, ExtractContext};
 use crate::error;
@@ -28,6 +27,7 @@ pub(crate) enum Token {
     Plus,              // '+'
     Minus,             // '-'
     Star,              // '*'
+    StarStar,          // '**'
     RSlash,            // '/'
     LSlash,            // '\'
     Equals,            // '='
@@ -53,17 +53,45 @@ pub(crate) enum Token {
     Else,              // 'else'
     Until,             // 'until'
     Let,               // 'let'
+    Const,             // 'const'
     Return,            // 'return'
     And,               // 'and'
     Or,                // 'or'
     Not,               // 'not'
+    Match,             // 'match'
+    FatArrow,          // '=>'
+    Underscore,        // '_'
+    Struct,            // 'struct'
+    Dot,               // '.'
+    Enum,              // 'enum'
+    ColonColon,        // '::'
+    Colon,             // ':'
+    Arrow,             // '->'
+    DotDot,            // '..'
+    DotDotEquals,      // '..='
+    Ellipsis,          // '...'
+    Throw,             // 'throw'
+    Try,               // 'try'
+    Catch,             // 'catch'
+    Pipe,              // '|>'
+    Assert,            // 'assert'
+    Loop,              // 'loop'
+    Break,             // 'break'
+    Continue,          // 'continue'
+    Impl,              // 'impl'
+    Trait,             // 'trait'
+    As,                // 'as'
+    Semicolon,         // ';'
+    DocComment,        // '///', kept only before a function or struct definition
 
     Ignore, //Comment and Whitespace (should be filtered before parsing)
 
     Integer,
     Float,
     Boolean,
+    Nil,
     String,
+    Char,
     Identifier,
 }
 
@@ -73,11 +101,14 @@ pub(crate) enum Token {
 /// Implements Clone, so it can be cloned in the parser.
 /// The record doesn't store data, only location, so copying is cheap.
 ///
+/// `after_newline` tells whether a new line separates the token from the previous one, so the
+/// parser can tell where a statement ends.
 #[derive(Debug, Clone)]
 pub(crate) struct TokenRecord {
     pub(crate) token_type: Token,
     pub(crate) offset: usize,
     pub(crate) length: usize,
+    pub(crate) after_newline: bool,
 }
 
 impl TokenRecord {
@@ -155,12 +186,28 @@ fn identifier(input: Span) -> IResult<Token> {
         "else" => Token::Else,
         "until" => Token::Until,
         "let" => Token::Let,
+        "const" => Token::Const,
         "return" => Token::Return,
         "true" => Token::Boolean,
         "false" => Token::Boolean,
+        "nil" => Token::Nil,
         "or" => Token::Or,
         "and" => Token::And,
         "not" => Token::Not,
+        "match" => Token::Match,
+        "_" => Token::Underscore,
+        "struct" => Token::Struct,
+        "enum" => Token::Enum,
+        "throw" => Token::Throw,
+        "try" => Token::Try,
+        "catch" => Token::Catch,
+        "assert" => Token::Assert,
+        "loop" => Token::Loop,
+        "break" => Token::Break,
+        "continue" => Token::Continue,
+        "impl" => Token::Impl,
+        "trait" => Token::Trait,
+        "as" => Token::As,
         _ => Token::Identifier,
     };
 
@@ -172,23 +219,43 @@ fn whitespace(input: Span) -> IResult<Token> {
     map(multispace1, |_| Token::Ignore)(input)
 }
 
-/// Tests for comments. Will be filtered out
+/// Tests for comments. Will be filtered out, except for doc comments
 fn comment(input: Span) -> IResult<Token> {
     alt((
-        map(delimited(tag("//"), not_line_ending, opt(tag("\n"))), |_| {
-            Token::Ignore
+        map(recognize(pair(tag("///"), not_line_ending)), |_| {
+            Token::DocComment
         }),
-        map(delimited(tag("/*"), take_until("*/"), tag("*/")), |_| {
+        map(delimited(tag("//"), not_line_ending, opt(tag("\n"))), |_| {
             Token::Ignore
         }),
+        block_comment,
     ))(input)
 }
 
+/// Tests for a block comment between `/*` and `*/`.
+/// Block comments can be nested, each `/*` must be closed by its own `*/`.
+fn block_comment(input: Span) -> IResult<Token> {
+    let (mut input, _) = tag("/*")(input)?;
+    let mut depth = 1;
+    while depth > 0 {
+        if let Ok((rest, _)) = tag::<_, _, TokenError>("/*")(input) {
+            depth += 1;
+            input = rest;
+        } else if let Ok((rest, _)) = tag::<_, _, TokenError>("*/")(input) {
+            depth -= 1;
+            input = rest;
+        } else {
+            input = anychar(input)?.0;
+        }
+    }
+    Ok((input, Token::Ignore))
+}
+
 /// Tests a number literal.
 /// Matches the following regex: [0-9]+(\.[0-9]+)?
 fn number(input: Span) -> IResult<Token> {
     map_res(
-        recognize(tuple((opt(char('-')), digit1, opt(pair(char('.'), digit1))))),
+        recognize(pair(digit1, opt(pair(char('.'), digit1)))),
         |num_str: Span| {
             if num_str.contains('.') {
                 Ok::<Token, TokenError>(Token::Float)
@@ -200,35 +267,67 @@ fn number(input: Span) -> IResult<Token> {
 }
 
 /// Tests a string starting and ending with double quotes.
+/// Tests a string literal, either between `"` or between `"""` for multiline strings.
+/// A multiline string can contain `"`, its indentation is removed when it's converted.
 fn string_literal(input: Span) -> IResult<Token> {
-    let (input, _) = delimited(char('"'), take_while(|c| c != '"'), char('"'))(input)?;
+    let (input, _) = alt((
+        delimited(tag("\"\"\""), take_until("\"\"\""), tag("\"\"\"")),
+        delimited(tag("\""), take_while(|c| c != '"'), tag("\"")),
+    ))(input)?;
     Ok((input, Token::String))
 }
 
+/// Tests a character literal between single quotes, like `'a'` or `'\n'`.
+/// The escape sequence is checked when the literal is converted.
+fn char_literal(input: Span) -> IResult<Token> {
+    let (input, _) = delimited(
+        char('\''),
+        alt((recognize(pair(char('\\'), anychar)), recognize(none_of("'\\")))),
+        char('\''),
+    )(input)?;
+    Ok((input, Token::Char))
+}
+
 /// Tests for other symbols literals
 fn simple_token(input: Span) -> IResult<Token> {
-        alt((
-            map(tag("=="), |_| Token::EqualsEquals),
-            map(tag("!="), |_| Token::NotEquals),
-            map(tag("<="), |_| Token::LessThanEquals),
-            map(tag(">="), |_| Token::GreaterThanEquals),
-            map(tag("+"),  |_| Token::Plus),
-            map(tag("-"),  |_| Token::Minus),
-            map(tag("*"),  |_| Token::Star),
-            map(tag("/"),  |_| Token::RSlash),
-            map(tag("\\"), |_|Token::LSlash),
-            map(tag("="),  |_| Token::Equals),
-            map(tag("<"),  |_| Token::LessThan),
-            map(tag(">"),  |_| Token::GreaterThan),
-            map(tag("("),  |_| Token::LParenthesis),
-            map(tag(")"),  |_| Token::RParenthesis),
-            map(tag("["),  |_| Token::LBracket),
-            map(tag("]"),  |_| Token::RBracket),
-            map(tag("{"),  |_| Token::LBrace),
-            map(tag("}"),  |_| Token::RBrace),
-            map(tag(","),  |_| Token::Comma),
-            map(tag("|"),  |_| Token::Bar),
-        ))(input)
+    // nom's `alt` is limited to 21 branches, so operators and punctuation are split.
+    // Longer operators must be tested before their prefixes (`==` before `=`).
+    let operators = alt((
+        map(tag("=="), |_| Token::EqualsEquals),
+        map(tag("=>"), |_| Token::FatArrow),
+        map(tag("::"), |_| Token::ColonColon),
+        map(tag("**"), |_| Token::StarStar),
+        map(tag("..."), |_| Token::Ellipsis),
+        map(tag("..="), |_| Token::DotDotEquals),
+        map(tag(".."), |_| Token::DotDot),
+        map(tag("!="), |_| Token::NotEquals),
+        map(tag("<="), |_| Token::LessThanEquals),
+        map(tag(">="), |_| Token::GreaterThanEquals),
+        map(tag("|>"), |_| Token::Pipe),
+        map(tag("->"), |_| Token::Arrow),
+        map(tag("+"),  |_| Token::Plus),
+        map(tag("-"),  |_| Token::Minus),
+        map(tag("*"),  |_| Token::Star),
+        map(tag("/"),  |_| Token::RSlash),
+        map(tag("\\"), |_|Token::LSlash),
+        map(tag("="),  |_| Token::Equals),
+        map(tag("<"),  |_| Token::LessThan),
+        map(tag(">"),  |_| Token::GreaterThan),
+    ));
+    let punctuation = alt((
+        map(tag("("),  |_| Token::LParenthesis),
+        map(tag(")"),  |_| Token::RParenthesis),
+        map(tag("["),  |_| Token::LBracket),
+        map(tag("]"),  |_| Token::RBracket),
+        map(tag("{"),  |_| Token::LBrace),
+        map(tag("}"),  |_| Token::RBrace),
+        map(tag(","),  |_| Token::Comma),
+        map(tag("|"),  |_| Token::Bar),
+        map(tag("."),  |_| Token::Dot),
+        map(tag(":"),  |_| Token::Colon),
+        map(tag(";"),  |_| Token::Semicolon),
+    ));
+    alt((operators, punctuation))(input)
 }
 
 /// Matches exactly one token.
@@ -241,32 +340,93 @@ fn one_token(input: Span) -> IResult<TokenRecord> {
         number,
         simple_token,
         string_literal,
+        char_literal,
         identifier,
     ))(input)?;
     let as_record = TokenRecord {
         token_type: result.1,
         offset: start.location_offset(),
         length: result.0.location_offset() - start.location_offset(),
+        after_newline: false,
     };
 
     Ok((result.0, as_record))
 }
 
 /// Parses all tokens
-/// Filters out whitespace and comments
+/// Filters out whitespace and comments, marking the tokens that follow a new line
+/// Doc comments are filtered out too, unless they document a function or a struct
 fn tokens(input: Span) -> IResult<Vec<TokenRecord>> {
+    let start = input.location_offset();
+    let content = *input.fragment();
     many0(one_token)(input).map(|(span, vec)| {
-
-        let filtered = vec
+        let mut newline = false;
+        let filtered: Vec<TokenRecord> = vec
             .into_iter()
+            .filter_map(|mut x| {
+                if matches!(x.token_type, Token::Ignore) {
+                    let ignored = &content[x.offset - start..x.offset - start + x.length];
+                    newline |= ignored.contains('\n');
+                    return None;
+                }
+                x.after_newline = std::mem::take(&mut newline);
+                Some(x)
+            })
+            .collect();
+        // A doc comment documents the function or struct right after it, possibly after more doc
+        // comments.
+        let mut documented = false;
+        let mut filtered: Vec<TokenRecord> = filtered
+            .into_iter()
+            .rev()
             .filter(|x| match x.token_type {
-                Token::Ignore => false,
-                _ => true,
-            }).collect();
+                Token::DocComment => documented,
+                token => {
+                    documented = matches!(token, Token::Fn | Token::Struct);
+                    true
+                }
+            })
+            .collect();
+        filtered.reverse();
         (span, filtered)
     })
 }
 
+/// Lexes a part of a file, like an expression interpolated in a string.
+/// The offsets of the tokens are relative to the whole file.
+pub(crate) fn lex_range(content: &str, range: Range<usize>) -> Option<Vec<TokenRecord>> {
+    let input = LocatedSpan::new(content).slice(range);
+    final_parser::<_, _, _, TokenError>(tokens)(input).ok()
+}
+
+/// Lexes a whole file, keeping the whitespace and the comments as `Ignore` tokens, and every doc
+/// comment, for the formatter.
+pub(crate) fn lex_all(content: &str) -> Option<Vec<TokenRecord>> {
+    final_parser::<_, _, _, TokenError>(many0(one_token))(LocatedSpan::new(content)).ok()
+}
+
+/// Lists tokens, one per line: the line and column where it starts, its byte range in the file,
+/// its type and its text.
+pub(crate) fn dump(content: &str, tokens: &[TokenRecord]) -> String {
+    let mut dump = String::new();
+    let (mut line, mut line_start, mut scanned) = (1, 0, 0);
+    for token in tokens {
+        for (index, c) in content[scanned..token.offset].char_indices() {
+            if c == '\n' {
+                line += 1;
+                line_start = scanned + index + 1;
+            }
+        }
+        scanned = token.offset;
+        let column = content[line_start..token.offset].chars().count() + 1;
+        let position = format!("{line}:{column}");
+        let range = format!("{}..{}", token.offset, token.offset + token.length);
+        let token_type = format!("{:?}", token.token_type);
+        dump.push_str(&format!("{position:<9} {range:<13} {token_type:<18} {:?}\n", token.get_content(content)));
+    }
+    dump
+}
+
 /// Main entry point for the lexer.
 pub fn lex(files: &FileSet, file: FileIdentifier) -> Result<Vec<TokenRecord>, error::TokenError> {
     let input = files.get_content(file).expect("File not found");
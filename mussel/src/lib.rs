@@ -0,0 +1,82 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The interpreter, used by the `mussel` command, by the programs embedding it, see `embed`, `capi`
+// and `node`, and, compiled to WebAssembly, by the playground.
+// The API of the library is `embed`, with `capi`, `node` and `playground` for the other languages:
+// the modules hidden from its documentation are used by the `mussel` command, and change from one
+// version to the next.
+
+#[doc(hidden)]
+pub mod interpreter;
+mod bytecode;
+mod analyzer;
+#[doc(hidden)]
+pub mod bench;
+#[doc(hidden)]
+pub mod bundle;
+pub mod capi;
+#[doc(hidden)]
+pub mod check;
+#[doc(hidden)]
+pub mod color;
+mod checker;
+#[doc(hidden)]
+pub mod dap;
+#[doc(hidden)]
+pub mod debugger;
+#[doc(hidden)]
+pub mod doc;
+#[doc(hidden)]
+pub mod dump;
+pub mod embed;
+#[doc(hidden)]
+pub mod explain;
+#[doc(hidden)]
+pub mod javascript;
+#[doc(hidden)]
+pub mod kernel;
+#[doc(hidden)]
+pub mod rust;
+#[doc(hidden)]
+pub mod context;
+mod stdlib;
+mod error;
+#[doc(hidden)]
+pub mod format;
+mod lexer;
+#[doc(hidden)]
+pub mod limits;
+#[doc(hidden)]
+pub mod lint;
+#[doc(hidden)]
+pub mod lsp;
+#[cfg(feature = "node")]
+pub mod node;
+#[doc(hidden)]
+pub mod loader;
+mod parser;
+mod expr;
+mod walk;
+mod output;
+pub mod playground;
+mod profile;
+#[doc(hidden)]
+pub mod project;
+#[doc(hidden)]
+pub mod repl;
+#[doc(hidden)]
+pub mod sarif;
+#[doc(hidden)]
+pub mod testing;
+#[doc(hidden)]
+pub mod timing;
+#[doc(hidden)]
+pub mod trace;
+#[doc(hidden)]
+pub mod warnings;
+#[cfg(not(target_arch = "wasm32"))]
+#[doc(hidden)]
+pub mod watch;
+
+pub use embed::{Arguments, Backend, Error, FromValue, Interpreter, Value};
@@ -0,0 +1,316 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The limits of what a script can use while it runs, to run scripts that can't be trusted:
+// `--max-steps` bounds the steps of the evaluation, the expressions evaluated by the tree-walker or
+// the instructions run by the VM, `--max-memory` the memory in use by the interpreter, and
+// `--timeout` the time the script runs for. None is set by default.
+// The limits hold for the whole process, like `color`, and each context checks them as its script
+// runs, see `Meter`: a script going past one stops with an error.
+// The memory is counted by the allocator of the `mussel` command, see `Counting`, a program using
+// the library without it has no memory limit. The memory is checked between the steps, so a single
+// step can go past the limit: past twice the limit, the allocator stops the process right away.
+// The calls nested too deep for the stack of the interpreter, see `--max-stack`, stop with an error
+// too, instead of overflowing it, see `set_stack`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+// How many steps go by between two checks of the number of steps and of the time.
+const CHECK_INTERVAL: u64 = 1024;
+
+const KIB: usize = 1024;
+const MIB: usize = 1024 * KIB;
+const GIB: usize = 1024 * MIB;
+
+/// The limits of a script, see `set`.
+#[derive(Clone, Copy, Default)]
+pub struct Limits {
+    pub max_steps: Option<u64>,
+    pub max_memory: Option<Memory>,
+    pub timeout: Option<Timeout>,
+}
+
+/// An amount of memory, written in bytes or with a `K`, `M` or `G` suffix, like `64M`.
+#[derive(Clone, Copy)]
+pub struct Memory(pub usize);
+
+impl FromStr for Memory {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (digits, unit) = match text.char_indices().last() {
+            Some((index, 'K' | 'k')) => (&text[..index], KIB),
+            Some((index, 'M' | 'm')) => (&text[..index], MIB),
+            Some((index, 'G' | 'g')) => (&text[..index], GIB),
+            _ => (text, 1),
+        };
+        match digits.parse::<usize>().ok().and_then(|amount| amount.checked_mul(unit)) {
+            Some(bytes) if bytes > 0 => Ok(Memory(bytes)),
+            _ => Err(format!("invalid amount of memory `{text}`, expected bytes, like `512K`, `64M` or `1G`")),
+        }
+    }
+}
+
+impl fmt::Display for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            bytes if bytes % GIB == 0 => write!(f, "{} GiB", bytes / GIB),
+            bytes if bytes % MIB == 0 => write!(f, "{} MiB", bytes / MIB),
+            bytes if bytes % KIB == 0 => write!(f, "{} KiB", bytes / KIB),
+            bytes => write!(f, "{bytes} bytes"),
+        }
+    }
+}
+
+/// A duration, written in seconds or with a `ms`, `s` or `m` suffix, like `500ms` or `1.5s`.
+#[derive(Clone, Copy)]
+pub struct Timeout(pub Duration);
+
+impl FromStr for Timeout {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = if let Some(number) = text.strip_suffix("ms") {
+            (number, 0.001)
+        } else if let Some(number) = text.strip_suffix('s') {
+            (number, 1.0)
+        } else if let Some(number) = text.strip_suffix('m') {
+            (number, 60.0)
+        } else {
+            (text, 1.0)
+        };
+        match number.parse::<f64>().ok().and_then(|number| Duration::try_from_secs_f64(number * unit).ok()) {
+            Some(duration) if !duration.is_zero() => Ok(Timeout(duration)),
+            _ => Err(format!("invalid timeout `{text}`, expected a duration, like `500ms`, `10s` or `2m`")),
+        }
+    }
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0.as_millis() {
+            millis if millis % 1000 == 0 => write!(f, "{}s", millis / 1000),
+            millis => write!(f, "{millis}ms"),
+        }
+    }
+}
+
+// The limits set, 0 when there is none.
+static MAX_STEPS: AtomicU64 = AtomicU64::new(0);
+static MAX_MEMORY: AtomicUsize = AtomicUsize::new(0);
+static TIMEOUT: AtomicU64 = AtomicU64::new(0); // In milliseconds.
+
+// The memory in use, counted by `Counting`, and the most that was in use at once.
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+// Sets the limits of the scripts run from now on.
+pub fn set(limits: Limits) {
+    MAX_STEPS.store(limits.max_steps.unwrap_or(0), Ordering::Relaxed);
+    MAX_MEMORY.store(limits.max_memory.map_or(0, |memory| memory.0), Ordering::Relaxed);
+    let timeout = limits.timeout.map_or(0, |timeout| timeout.0.as_millis().max(1) as u64);
+    TIMEOUT.store(timeout, Ordering::Relaxed);
+}
+
+// Returns the most memory that was in use at once, in bytes, when it is counted, see `Counting`.
+pub fn peak() -> Option<usize> {
+    Some(PEAK.load(Ordering::Relaxed)).filter(|peak| *peak > 0)
+}
+
+/// An allocator counting the memory in use, for `--max-memory` and `--time`, installed by the `mussel` command
+/// as its global allocator.
+pub struct Counting;
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        reserve(layout.size());
+        let pointer = unsafe { System.alloc(layout) };
+        if pointer.is_null() {
+            IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        pointer
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        reserve(layout.size());
+        let pointer = unsafe { System.alloc_zeroed(layout) };
+        if pointer.is_null() {
+            IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        pointer
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(pointer, layout) };
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, pointer: *mut u8, layout: Layout, size: usize) -> *mut u8 {
+        if size > layout.size() {
+            reserve(size - layout.size());
+        }
+        let reallocated = unsafe { System.realloc(pointer, layout, size) };
+        if reallocated.is_null() {
+            IN_USE.fetch_sub(size.saturating_sub(layout.size()), Ordering::Relaxed);
+        } else if size < layout.size() {
+            IN_USE.fetch_sub(layout.size() - size, Ordering::Relaxed);
+        }
+        reallocated
+    }
+}
+
+// Counts memory about to be allocated. Stops the process when it would take the memory in use past
+// twice the limit: an error can't be raised while allocating, and failing to allocate would abort.
+fn reserve(size: usize) {
+    let in_use = IN_USE.fetch_add(size, Ordering::Relaxed) + size;
+    if in_use > PEAK.load(Ordering::Relaxed) {
+        PEAK.fetch_max(in_use, Ordering::Relaxed);
+    }
+    let limit = MAX_MEMORY.load(Ordering::Relaxed);
+    if limit != 0 && in_use > limit.saturating_mul(2) {
+        // Writing to the standard error and formatting numbers don't allocate.
+        let message = "error[E1015]: execution limit exceeded: the script used more than";
+        let _ = writeln!(std::io::stderr(), "{message} {} of memory", Memory(limit));
+        std::process::exit(1);
+    }
+}
+
+thread_local! {
+    // The address past which the stack of the running thread is too full to call another function,
+    // 0 when its size isn't known.
+    static STACK_END: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The smallest stack the interpreter runs scripts with, see `set_stack`: `--max-stack` can't be
+/// smaller.
+pub const MIN_STACK: usize = MIB;
+
+// The room kept at the end of the stack on top of an eighth of it, for the code running between two
+// calls, whose frames are much larger without optimizations.
+const STACK_RESERVE: usize = if cfg!(debug_assertions) { 512 * KIB } else { 64 * KIB };
+
+// Records that the stack of the running thread, which starts about here, has `size` bytes, so the
+// calls nested too deep for it stop with an error instead of overflowing it. An eighth of it and
+// `STACK_RESERVE` are kept for the code running between two calls, so a stack smaller than
+// `MIN_STACK` leaves little or no room for calls.
+pub fn set_stack(size: usize) {
+    let reserve = (size / 8).saturating_add(STACK_RESERVE);
+    STACK_END.set(stack_top().saturating_sub(size.saturating_sub(reserve)));
+}
+
+// Records the stack of the running thread as the system tells it, like `set_stack`, for a program
+// which doesn't know its size. When the system doesn't tell it, `MIN_STACK` is assumed to be left
+// from here.
+pub fn set_thread_stack() {
+    match thread_stack() {
+        Some((start, size)) => STACK_END.set(start.saturating_add((size / 8).saturating_add(STACK_RESERVE).min(size))),
+        None => set_stack(MIN_STACK),
+    }
+}
+
+// Returns the lowest address and the size of the stack of the running thread.
+#[cfg(target_os = "linux")]
+fn thread_stack() -> Option<(usize, usize)> {
+    let mut attributes = std::mem::MaybeUninit::<libc::pthread_attr_t>::uninit();
+    // The attributes are only read once `pthread_getattr_np` filled them, and destroyed after.
+    unsafe {
+        if libc::pthread_getattr_np(libc::pthread_self(), attributes.as_mut_ptr()) != 0 {
+            return None;
+        }
+        let (mut start, mut size) = (std::ptr::null_mut(), 0);
+        let found = libc::pthread_attr_getstack(attributes.as_ptr(), &mut start, &mut size) == 0;
+        libc::pthread_attr_destroy(attributes.as_mut_ptr());
+        found.then_some((start as usize, size))
+    }
+}
+
+// Returns the lowest address and the size of the stack of the running thread.
+#[cfg(target_os = "macos")]
+fn thread_stack() -> Option<(usize, usize)> {
+    // The address given is the one the stack starts at, its highest address.
+    unsafe {
+        let thread = libc::pthread_self();
+        let size = libc::pthread_get_stacksize_np(thread);
+        Some(((libc::pthread_get_stackaddr_np(thread) as usize).checked_sub(size)?, size))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn thread_stack() -> Option<(usize, usize)> {
+    None
+}
+
+// Tells whether the stack of the running thread is too full to call another function.
+pub(crate) fn stack_exhausted() -> bool {
+    stack_top() < STACK_END.get()
+}
+
+// Returns about where the top of the stack is. The stack grows down, to the lower addresses, on
+// every system the interpreter runs on.
+fn stack_top() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// A limit a script went past.
+pub enum Limit {
+    Steps(u64),
+    Memory(Memory),
+    Time(Timeout),
+}
+
+// Counts the steps of a script, checking its limits as it goes.
+pub(crate) struct Meter {
+    steps: u64,
+    // The step at which the number of steps and the time are checked next, never without limits.
+    check: u64,
+    max_steps: u64,
+    max_memory: usize,
+    deadline: Option<(Instant, Timeout)>,
+}
+
+impl Meter {
+    // Starts counting with the limits set, the time of the script starting now.
+    pub(crate) fn new() -> Self {
+        let max_steps = MAX_STEPS.load(Ordering::Relaxed);
+        let max_memory = MAX_MEMORY.load(Ordering::Relaxed);
+        let timeout = Duration::from_millis(TIMEOUT.load(Ordering::Relaxed));
+        let deadline = (!timeout.is_zero()).then(|| (Instant::now() + timeout, Timeout(timeout)));
+        let check = match (max_steps, deadline) {
+            (0, None) => u64::MAX,
+            (0, Some(_)) => CHECK_INTERVAL,
+            (max_steps, _) => CHECK_INTERVAL.min(max_steps.saturating_add(1)),
+        };
+        Meter { steps: 0, check, max_steps, max_memory, deadline }
+    }
+
+    // Counts a step, returning the limit the script went past, if any.
+    #[inline]
+    pub(crate) fn step(&mut self) -> Option<Limit> {
+        self.steps += 1;
+        let memory = self.max_memory != 0 && IN_USE.load(Ordering::Relaxed) > self.max_memory;
+        if memory {
+            return Some(Limit::Memory(Memory(self.max_memory)));
+        }
+        if self.steps < self.check {
+            return None;
+        }
+        self.check = self.steps + CHECK_INTERVAL;
+        if self.max_steps != 0 {
+            if self.steps > self.max_steps {
+                return Some(Limit::Steps(self.max_steps));
+            }
+            self.check = self.check.min(self.max_steps.saturating_add(1));
+        }
+        match self.deadline {
+            Some((deadline, timeout)) if Instant::now() > deadline => Some(Limit::Time(timeout)),
+            _ => None,
+        }
+    }
+}
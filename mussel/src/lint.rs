@@ -0,0 +1,514 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+//! `mussel lint`, which looks for code that runs but is likely not what was meant: a variable
+//! hiding another one, a condition that never changes, an `include` nothing is used from, or an `=`
+//! written where `==` was meant.
+//!
+//! Each kind of mistake is found by a rule with a name, shown with its warnings, so it can be turned
+//! off with `-A` when the code is written that way on purpose, or turned on with `-W` when it is off
+//! by default, like the other warnings, see `warnings`. Like the analysis done before a script runs, only the code as written
+//! is looked at, and names starting with `_` are never reported.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use crate::analyzer;
+use crate::error::{AnalysisWarning, FileIdentifier};
+use crate::lexer::{Token, TokenRecord};
+use crate::loader::Loader;
+use crate::parser::{BinaryOperator, BindingExpression, Expression, ParameterExpression, PatternExpression};
+use crate::stdlib;
+use crate::warnings::{self, WARNINGS};
+
+/// The rules, with whether they are on by default and what they find, see `warnings`.
+pub const RULES: &[(&str, bool, &str)] = WARNINGS.split_at(warnings::ANALYSIS).1;
+
+/// The libraries of the standard library written in Rust, and what they declare.
+pub(crate) const LIBRARIES: [(&str, &[&str]); 5] = [
+    ("math", &["abs", "sqrt", "pow"]),
+    ("string", &["lowercase", "uppercase", "length", "split", "reverse", "trim", "ltrim", "rtrim", "chars", "ord", "chr"]),
+    ("time", &["time_ms", "time_sec"]),
+    ("random", &["rand"]),
+    ("os", &["getcwd", "listdir", "exists"]),
+];
+
+/// Lints scripts with the rules which are on, see `warnings::set`. Fails when a warning or an error
+/// was reported.
+pub fn lint(files: &[String]) -> ExitCode {
+    let mut loader = Loader::new(false);
+    let mut failed = false;
+    for file in files {
+        let path = std::fs::canonicalize(file).unwrap_or_else(|_| PathBuf::from(file));
+        let (file, expressions) = match loader.syntax(&path, None) {
+            Ok(syntax) => syntax,
+            Err(error) => {
+                loader.show(&error);
+                failed = true;
+                continue;
+            }
+        };
+        let includes = includes(&mut loader, file, &path, &expressions);
+        let mut linter = Linter {
+            file,
+            content: loader.content(file),
+            scopes: vec![HashMap::new()],
+            includes,
+            warnings: Vec::new(),
+        };
+        linter.statements(&expressions);
+        linter.unused_includes();
+        let mut warnings = linter.warnings;
+        warnings.sort_by_key(AnalysisWarning::start);
+        failed |= !warnings.is_empty();
+        for warning in &warnings {
+            loader.show(warning);
+        }
+    }
+    if failed { ExitCode::FAILURE } else { ExitCode::SUCCESS }
+}
+
+/// An `include` of the file linted, and whether something it declares is used.
+struct Include {
+    token: TokenRecord,
+    alias: Option<String>,
+    // What it declares, or `None` when that isn't known, like for a file that can't be found:
+    // the include is never reported then
+    names: Option<HashSet<String>>,
+    used: bool,
+}
+
+/// How a name was declared, which tells whether hiding another name with it is reported.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Variable,
+    Parameter,
+    // `catch` variables and the functions, which are named on purpose
+    Other,
+}
+
+struct Linter<'a> {
+    file: FileIdentifier,
+    content: &'a str,
+    // The names declared in each scope, the innermost last
+    scopes: Vec<HashMap<String, Kind>>,
+    includes: Vec<Include>,
+    warnings: Vec<AnalysisWarning>,
+}
+
+impl Linter<'_> {
+    fn name(&self, token: &TokenRecord) -> String {
+        token.get_content(self.content).to_string()
+    }
+
+    fn warn(&mut self, rule: &'static str, range: Option<Range<usize>>, message: String, label: &str, help: Option<&str>) {
+        if !warnings::reported(rule, self.content, range.as_ref()) {
+            return;
+        }
+        let warning = AnalysisWarning::new(self.file, range, message, label.to_string(), help.map(str::to_string));
+        self.warnings.push(warning.with_rule(rule));
+    }
+
+    // Declares a name in the innermost scope, reporting a variable hiding a name of an outer scope.
+    fn declare(&mut self, token: &TokenRecord, kind: Kind) {
+        let name = self.name(token);
+        let (scope, outer) = self.scopes.split_last_mut().expect("the global scope is never removed");
+        let hidden = match scope.contains_key(&name) {
+            true => None,
+            false => outer.iter().rev().find_map(|scope| scope.get(&name).copied()),
+        };
+        scope.insert(name.clone(), kind);
+        if kind != Kind::Variable || name.starts_with('_') {
+            return;
+        }
+        match hidden {
+            Some(Kind::Parameter) => {
+                let message = format!("`{name}` shadows a parameter of the function");
+                let help = "rename it, or assign the parameter with `=` if it should change";
+                self.warn("shadowed-variable", Some(token.range()), message, "hides the parameter", Some(help));
+            }
+            Some(_) => {
+                let message = format!("`{name}` shadows a variable declared outside this block");
+                let help = "rename it, or assign the outer variable with `=` if it should change";
+                self.warn("shadowed-variable", Some(token.range()), message, "hides the outer variable", Some(help));
+            }
+            None => {}
+        }
+    }
+
+    // Records that a name is used, which uses the include declaring it.
+    fn read(&mut self, name: &str) {
+        for include in &mut self.includes {
+            let declares = match &include.alias {
+                Some(alias) => alias == name,
+                None => include.names.as_ref().is_some_and(|names| names.contains(name)),
+            };
+            include.used |= declares;
+        }
+    }
+
+    fn unused_includes(&mut self) {
+        let unused: Vec<(TokenRecord, Option<String>)> = self
+            .includes
+            .iter()
+            .filter(|include| !include.used && include.names.is_some())
+            .map(|include| (include.token.clone(), include.alias.clone()))
+            .collect();
+        for (token, alias) in unused {
+            let included = self.name(&token);
+            let (message, label) = match alias {
+                Some(alias) => (format!("unused include `{included}`"), format!("the module `{alias}` is never used")),
+                None => (format!("unused include `{included}`"), "nothing it declares is used".to_string()),
+            };
+            self.warn("unused-include", Some(token.range()), message, &label, Some("remove it"));
+        }
+    }
+
+    fn block(&mut self, block: &[Expression]) {
+        self.scopes.push(HashMap::new());
+        self.statements(block);
+        self.scopes.pop();
+    }
+
+    fn statements(&mut self, statements: &[Expression]) {
+        for statement in statements {
+            self.visit(statement);
+        }
+    }
+
+    fn bind(&mut self, binding: &BindingExpression, kind: Kind) {
+        match binding {
+            BindingExpression::Identifier(id) => self.declare(id, kind),
+            BindingExpression::Wildcard => {}
+            BindingExpression::Array(bindings, _) => {
+                for binding in bindings {
+                    self.bind(binding, kind);
+                }
+            }
+        }
+    }
+
+    fn bind_pattern(&mut self, pattern: &PatternExpression) {
+        match pattern {
+            PatternExpression::Identifier(id) | PatternExpression::Rest(_, Some(id)) => self.declare(id, Kind::Variable),
+            PatternExpression::Array(patterns) => {
+                for pattern in patterns {
+                    self.bind_pattern(pattern);
+                }
+            }
+            PatternExpression::Variant(variant, patterns) => {
+                self.visit(variant);
+                for pattern in patterns {
+                    self.bind_pattern(pattern);
+                }
+            }
+            PatternExpression::Struct(id, fields) => {
+                self.read(id.get_content(self.content));
+                for (field, pattern) in fields {
+                    match pattern {
+                        Some(pattern) => self.bind_pattern(pattern),
+                        None => self.declare(field, Kind::Variable),
+                    }
+                }
+            }
+            PatternExpression::Literal(literal) => self.visit(literal),
+            PatternExpression::Wildcard | PatternExpression::Rest(_, None) => {}
+        }
+    }
+
+    // The parameters are in a scope of their own around the body, so a variable of the body
+    // hiding one is reported.
+    fn function(&mut self, args: &[ParameterExpression], block: &[Expression]) {
+        self.scopes.push(HashMap::new());
+        for arg in args {
+            if let Some(default) = &arg.default {
+                self.visit(default);
+            }
+            self.declare(&arg.id, Kind::Parameter);
+        }
+        self.block(block);
+        self.scopes.pop();
+    }
+
+    // Looks at the condition of an `if`, `until`, `assert` or comprehension.
+    fn condition(&mut self, condition: &Expression, keyword: &str) {
+        if let Expression::Assignment { left, .. } = condition {
+            self.suspicious_assignment(condition, left);
+        } else if constant(condition) {
+            let message = format!("constant condition in `{keyword}`");
+            let help = match condition {
+                Expression::Bool(token) if keyword == "until" && token.get_content(self.content) == "true" => {
+                    Some("use `loop` to repeat a block until a `break`")
+                }
+                _ => None,
+            };
+            self.warn("constant-condition", condition.span(), message, "this condition is always the same", help);
+        }
+        self.visit(condition);
+    }
+
+    fn suspicious_assignment(&mut self, assignment: &Expression, left: &Expression) {
+        let target = left.span().map_or("", |range| &self.content[range]);
+        let message = "assignment used as a condition".to_string();
+        let label = format!("this assigns `{target}`");
+        let help = "to compare the values, write `==`";
+        self.warn("suspicious-assignment", assignment.span(), message, &label, Some(help));
+    }
+
+    fn visit(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Identifier(id) => self.read(id.get_content(self.content)),
+            Expression::String(token) => {
+                for name in analyzer::interpolated_names(token.get_content(self.content)) {
+                    self.read(&name);
+                }
+            }
+            Expression::Path { id, .. } => self.read(id.get_content(self.content)),
+            Expression::Char(_)
+            | Expression::Integer(_)
+            | Expression::Float(_)
+            | Expression::Bool(_)
+            | Expression::Nil
+            | Expression::Continue { .. }
+            | Expression::Include { .. }
+            | Expression::Struct { .. }
+            | Expression::Trait { .. }
+            | Expression::Enum { .. } => {}
+            Expression::Array(items) => {
+                for item in items {
+                    self.visit(item);
+                }
+            }
+            Expression::Closure { args, block, .. } => self.function(args, block),
+            Expression::Function { id, args, block, .. } => {
+                self.declare(id, Kind::Other);
+                self.function(args, block);
+            }
+            Expression::Impl { id, trait_id, methods, .. } => {
+                self.read(id.get_content(self.content));
+                if let Some(trait_id) = trait_id {
+                    self.read(trait_id.get_content(self.content));
+                }
+                for method in methods {
+                    if let Expression::Function { args, block, .. } = method {
+                        self.function(args, block);
+                    }
+                }
+            }
+            Expression::Let { binding, expr, .. } | Expression::Const { binding, expr, .. } => {
+                self.visit(expr);
+                self.bind(binding, Kind::Variable);
+            }
+            Expression::Assignment { left, right, .. } => {
+                let text = |expr: &Expression| expr.span().map(|range| &self.content[range]);
+                if matches!(&**left, Expression::Identifier(_) | Expression::Field { .. }) && text(left) == text(right) {
+                    let target = text(left).unwrap_or_default();
+                    let message = format!("`{target}` is assigned to itself");
+                    self.warn("self-assignment", expr.span(), message, "this doesn't change anything", Some("remove it"));
+                }
+                self.visit(right);
+                self.visit(left);
+            }
+            Expression::Return { expr } | Expression::Throw { expr, .. } | Expression::Labeled { expr, .. } => {
+                self.visit(expr)
+            }
+            Expression::Break { expr, .. } => {
+                if let Some(expr) = expr {
+                    self.visit(expr);
+                }
+            }
+            Expression::Assert { expr, message, .. } => {
+                if let Expression::Assignment { left, .. } = &**expr {
+                    self.suspicious_assignment(expr, left);
+                }
+                self.visit(expr);
+                if let Some(message) = message {
+                    self.visit(message);
+                }
+            }
+            Expression::Try { block, id, catch_block } => {
+                self.block(block);
+                self.scopes.push(HashMap::new());
+                self.declare(id, Kind::Other);
+                self.block(catch_block);
+                self.scopes.pop();
+            }
+            Expression::If { expr, block, else_block, .. } => {
+                self.condition(expr, "if");
+                self.block(block);
+                if let Some(else_block) = else_block {
+                    self.block(else_block);
+                }
+            }
+            Expression::Until { expr, block, .. } => {
+                self.condition(expr, "until");
+                self.block(block);
+            }
+            Expression::Loop { block } => self.block(block),
+            Expression::For { binding, expr, block, .. } => {
+                self.visit(expr);
+                self.scopes.push(HashMap::new());
+                self.bind(binding, Kind::Variable);
+                self.block(block);
+                self.scopes.pop();
+            }
+            Expression::Comprehension { expr, binding, iterable, condition, .. } => {
+                self.visit(iterable);
+                self.scopes.push(HashMap::new());
+                self.bind(binding, Kind::Variable);
+                if let Some(condition) = condition {
+                    self.condition(condition, "if");
+                }
+                self.visit(expr);
+                self.scopes.pop();
+            }
+            Expression::Match { expr, arms } => {
+                self.visit(expr);
+                for (pattern, block) in arms {
+                    self.scopes.push(HashMap::new());
+                    self.bind_pattern(pattern);
+                    self.block(block);
+                    self.scopes.pop();
+                }
+            }
+            Expression::Binary { left, operator: (operator, _), right } => {
+                if matches!(operator, BinaryOperator::And | BinaryOperator::Or) {
+                    for operand in [left, right] {
+                        if let Expression::Assignment { left, .. } = &**operand {
+                            self.suspicious_assignment(operand, left);
+                        }
+                    }
+                }
+                if matches!(operator, BinaryOperator::Equal | BinaryOperator::NotEqual)
+                    && [left, right].iter().any(|operand| matches!(&***operand, Expression::Bool(_)))
+                {
+                    let message = "comparison with a boolean".to_string();
+                    let help = "write the condition itself, or its `not`";
+                    self.warn("comparison-to-bool", expr.span(), message, "this can be simplified", Some(help));
+                }
+                self.visit(left);
+                self.visit(right);
+            }
+            Expression::Unary { expr, .. } => self.visit(expr),
+            Expression::Range { start, end, .. } => {
+                self.visit(start);
+                self.visit(end);
+            }
+            Expression::Pipeline { value, function, .. } => {
+                self.visit(value);
+                self.visit(function);
+            }
+            Expression::Call { left, args, .. } => {
+                self.visit(left);
+                for arg in args {
+                    self.visit(arg);
+                }
+            }
+            Expression::Index { left, index, .. } => {
+                self.visit(left);
+                self.visit(index);
+            }
+            Expression::Field { left, .. } => self.visit(left),
+        }
+    }
+}
+
+// Tells whether an expression is made of literals only, so it always has the same value.
+fn constant(expr: &Expression) -> bool {
+    match expr {
+        Expression::Bool(_)
+        | Expression::Integer(_)
+        | Expression::Float(_)
+        | Expression::Char(_)
+        | Expression::Nil => true,
+        // A string can interpolate variables.
+        Expression::String(_) => false,
+        Expression::Unary { expr, .. } => constant(expr),
+        Expression::Binary { left, right, .. } => constant(left) && constant(right),
+        _ => false,
+    }
+}
+
+// Finds the includes at the top level of a file, with what they declare.
+fn includes(loader: &mut Loader, file: FileIdentifier, path: &Path, expressions: &[Expression]) -> Vec<Include> {
+    let content = loader.content(file);
+    let found: Vec<(TokenRecord, Option<String>, String)> = expressions
+        .iter()
+        .filter_map(|expression| match expression {
+            Expression::Include { id, alias } => {
+                let alias = alias.as_ref().map(|alias| alias.get_content(content).to_string());
+                Some((id.clone(), alias, id.get_content(content).to_string()))
+            }
+            _ => None,
+        })
+        .collect();
+    found
+        .into_iter()
+        .map(|(token, alias, target)| {
+            let names = match alias {
+                // A module is used by its name.
+                Some(_) => Some(HashSet::new()),
+                None => declarations(loader, path, token.token_type, &target, &mut vec![path.to_path_buf()]),
+            };
+            Include { token, alias, names, used: false }
+        })
+        .collect()
+}
+
+// Returns what an `include` in the file with the given path declares, or `None` when it can't be
+// known: the file can't be found or parsed, or it includes itself.
+fn declarations(
+    loader: &mut Loader,
+    including: &Path,
+    token: Token,
+    target: &str,
+    visited: &mut Vec<PathBuf>,
+) -> Option<HashSet<String>> {
+    let (path, source) = if token == Token::String {
+        (loader.find_from(including, Path::new(target.trim_matches('"'))).ok()?, None)
+    } else if let Some((_, names)) = LIBRARIES.iter().find(|(library, _)| *library == target) {
+        return Some(names.iter().map(|name| name.to_string()).collect());
+    } else if let Some(source) = stdlib::source(target) {
+        (Path::new("<std>").join(format!("{target}.mus")), Some(source))
+    } else {
+        (loader.find_from(including, Path::new(&format!("{target}.mus"))).ok()?, None)
+    };
+    if visited.contains(&path) {
+        return None;
+    }
+    visited.push(path.clone());
+    let (file, expressions) = loader.syntax(&path, source).ok()?;
+    let mut names = HashSet::new();
+    let mut nested = Vec::new();
+    for expression in &expressions {
+        let content = loader.content(file);
+        match expression {
+            Expression::Let { binding, .. } | Expression::Const { binding, .. } => {
+                names.extend(binding_names(binding, content));
+            }
+            Expression::Function { id, .. }
+            | Expression::Struct { id, .. }
+            | Expression::Trait { id, .. }
+            | Expression::Enum { id, .. }
+            | Expression::Include { alias: Some(id), .. } => {
+                names.insert(id.get_content(content).to_string());
+            }
+            Expression::Include { id, alias: None } => nested.push((id.token_type, id.get_content(content).to_string())),
+            _ => {}
+        }
+    }
+    // What a file includes without a name is declared next to its own names.
+    for (token, target) in nested {
+        names.extend(declarations(loader, &path, token, &target, visited)?);
+    }
+    Some(names)
+}
+
+fn binding_names(binding: &BindingExpression, content: &str) -> Vec<String> {
+    match binding {
+        BindingExpression::Identifier(id) => vec![id.get_content(content).to_string()],
+        BindingExpression::Wildcard => Vec::new(),
+        BindingExpression::Array(bindings, _) => bindings.iter().flat_map(|binding| binding_names(binding, content)).collect(),
+    }
+}
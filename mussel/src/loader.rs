@@ -0,0 +1,262 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+use std::path;
+use std::path::{Path, PathBuf};
+use crate::analyzer;
+use crate::bundle;
+use crate::bundle::Bundle;
+use crate::checker;
+use crate::dump;
+use crate::error;
+use crate::error::{ErrorCollection, FileError, FileIdentifier, FileSet, LError, Location, Reporter};
+use crate::expr::Expr;
+use crate::lexer;
+use crate::lexer::{Token, TokenRecord};
+use crate::parser;
+use crate::timing::{self, Phase};
+use crate::warnings;
+
+// Loads the files of a script: the one given on the command line and the ones it includes.
+// Every loaded file is kept, so the errors found while the script runs can show the code they
+// point at. The loader also keeps the chain of files running, the script first and then the files
+// it includes, each included by the one before it, so a file can't include itself in a loop.
+// The loader of a standalone executable reads the files from its bundle instead of the disk.
+pub struct Loader {
+    files: FileSet,
+    check_types: bool,
+    running: Vec<PathBuf>,
+    bundle: Option<Bundle>,
+    include: Vec<PathBuf>, // The directories of the `include` of the project, see `project`.
+}
+
+impl Loader {
+    // Creates a loader, checking the types of the files it loads when `check_types` is set.
+    pub fn new(check_types: bool) -> Self {
+        Loader { files: FileSet::new(), check_types, running: Vec::new(), bundle: None, include: Vec::new() }
+    }
+
+    // Adds directories to look up the included files in, see `find`.
+    pub fn include(&mut self, directories: Vec<PathBuf>) {
+        self.include.extend(directories);
+    }
+
+    // Creates a loader reading the files of a bundle.
+    pub fn bundled(bundle: Bundle) -> Self {
+        Loader { bundle: Some(bundle), ..Loader::new(false) }
+    }
+
+    // Reads a file and turns it into the expressions to evaluate.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<Expr>, Box<dyn LError + Send>> {
+        // A bundled script was analyzed when it was built, its warnings aren't shown again.
+        if let Some(bundle) = &self.bundle {
+            let path = path.as_ref();
+            let content = bundle.file(path).ok_or_else(|| {
+                error::boxed(FileError::new(path, "Failed to read file: not in the bundle".to_string()))
+            })?;
+            let file = self.files.add_file(path, content.to_string());
+            return self.parse(file, false);
+        }
+        // Load the file into the `FileSet`, so errors can point at its content.
+        let file = load_file(&mut self.files, &path).map_err(error::boxed)?;
+        self.parse(file, true)
+    }
+
+    // Reads a file and lists its tokens, see `lexer::dump`.
+    pub fn dump_tokens<P: AsRef<Path>>(&mut self, path: P) -> Result<String, Box<dyn LError + Send>> {
+        let file = load_file(&mut self.files, &path).map_err(error::boxed)?;
+        let tokens = lexer::lex(&self.files, file).map_err(error::boxed)?;
+        let content = self.files.get_content(file).expect("the file was just loaded");
+        Ok(lexer::dump(content, &tokens))
+    }
+
+    // Reads a file and shows the expressions it is parsed into, see `dump`.
+    pub fn dump_ast<P: AsRef<Path>>(&mut self, path: P, format: dump::Format) -> Result<String, Box<dyn LError + Send>> {
+        let file = load_file(&mut self.files, &path).map_err(error::boxed)?;
+        let exprs = self.parse(file, false)?;
+        Ok(dump::dump(&exprs, format))
+    }
+
+    // Reads a file, or takes the given source instead, and parses it without analyzing it, for the
+    // tools looking at the code as written, see `lint`. Returns the loaded file with its statements.
+    pub(crate) fn syntax<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        source: Option<&str>,
+    ) -> Result<(FileIdentifier, Vec<parser::Expression>), Box<dyn LError + Send>> {
+        let file = match source {
+            Some(source) => self.files.add_file(path, source.to_string()),
+            None => load_file(&mut self.files, &path).map_err(error::boxed)?,
+        };
+        let tokens = lexer::lex(&self.files, file).map_err(error::boxed)?;
+        Ok((file, parser::parser(file, &tokens)?))
+    }
+
+    // Returns the content of a loaded file.
+    pub(crate) fn content(&self, file: FileIdentifier) -> &str {
+        self.files.get_content(file).expect("every file identifier points to a loaded file")
+    }
+
+    // Turns code that isn't read from a file, like a library of the standard library, into the
+    // expressions to evaluate. The path names the code in errors.
+    pub fn load_source<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        content: &str,
+    ) -> Result<Vec<Expr>, Box<dyn LError + Send>> {
+        let file = self.files.add_file(path, content.to_string());
+        self.parse(file, false)
+    }
+
+    // Parses a loaded file. The warnings found by the analysis, when `analyze` is set, are reported
+    // right away, before the file runs.
+    fn parse(&mut self, file: FileIdentifier, analyze: bool) -> Result<Vec<Expr>, Box<dyn LError + Send>> {
+        let tokens = timing::measure(Phase::Lexing, || lexer::lex(&self.files, file)).map_err(error::boxed)?;
+
+        let expressions = timing::measure(Phase::Parsing, || parser::parser(file, &tokens))?;
+
+        timing::measure(Phase::Analysis, || self.analyze(file, &expressions, analyze))?;
+
+        timing::measure(Phase::Conversion, || Expr::from_parser(&self.files, file, expressions)).map_err(error::boxed)
+    }
+
+    // Reports the warnings found by the analysis of a parsed file, when `analyze` is set, and the
+    // type errors found by the type checker, when the types are checked.
+    fn analyze(
+        &self,
+        file: FileIdentifier,
+        expressions: &[parser::Expression],
+        analyze: bool,
+    ) -> Result<(), Box<dyn LError + Send>> {
+        let files = &self.files;
+        let content = files.get_content(file).expect("the file was just loaded");
+
+        if analyze {
+            // The script is the only file running when it is loaded, the others are included by it.
+            let module = self.running.len() > 1;
+            let warnings = analyzer::analyze(file, content, expressions, module);
+            // With `--deny-warnings`, the warnings are errors stopping the file.
+            if warnings::denied() && !warnings.is_empty() {
+                let mut errors = ErrorCollection::new();
+                for warning in warnings {
+                    errors.add_error(warning);
+                }
+                return Err(error::boxed(errors));
+            }
+            for warning in warnings {
+                error::warn(files, &warning);
+            }
+        }
+
+        // The type checker reports the type errors it finds before the file runs.
+        if self.check_types {
+            checker::check_types(file, content, expressions).map_err(error::boxed)?;
+        }
+        Ok(())
+    }
+
+    // Adds code which is only pointed at, not run, like the call of a function made by a program
+    // embedding the interpreter, and returns the location of all of it.
+    pub(crate) fn add_code<P: AsRef<Path>>(&mut self, path: P, content: &str) -> Location {
+        let file = self.files.add_file(path, content.to_string());
+        let length = content.len();
+        Location::new(file, TokenRecord { token_type: Token::Identifier, offset: 0, length, after_newline: false })
+    }
+
+    // Returns the path of a loaded file.
+    pub fn path(&self, file: FileIdentifier) -> &Path {
+        self.files.get_path(file).expect("every file identifier points to a loaded file")
+    }
+
+    // Finds the file of an `include` in the given file. A relative path is looked up from the
+    // directory of the including file, then from the current directory, then from the directories
+    // added with `include`, then from each directory listed in the `MUSSEL_PATH` environment variable.
+    // Returns the canonical path of the file, or the paths that were tried when none of them is a file.
+    // In a bundle, the path is only looked up from the directory of the including file.
+    pub fn find(&self, including: FileIdentifier, path: &Path) -> Result<PathBuf, Vec<PathBuf>> {
+        let including = self.path(including);
+        if let Some(bundle) = &self.bundle {
+            let directory = including.parent().unwrap_or(Path::new(""));
+            let candidate = bundle::normalize(&directory.join(path));
+            return match bundle.file(&candidate) {
+                Some(_) => Ok(candidate),
+                None => Err(vec![candidate]),
+            };
+        }
+        self.find_from(including, path)
+    }
+
+    // Finds the file of an `include` in the file with the given path, see `find`.
+    pub fn find_from(&self, including: &Path, path: &Path) -> Result<PathBuf, Vec<PathBuf>> {
+        let mut directories: Vec<PathBuf> = including.parent().map(Path::to_path_buf).into_iter().collect();
+        directories.extend(std::env::current_dir());
+        directories.extend(self.include.iter().cloned());
+        if let Some(search_path) = std::env::var_os("MUSSEL_PATH") {
+            directories.extend(std::env::split_paths(&search_path));
+        }
+        let candidates: Vec<PathBuf> = directories.iter().map(|directory| directory.join(path)).collect();
+        // The same file can be reached through different paths, like `lib/../utils.mus`.
+        match candidates.iter().find(|candidate| candidate.is_file()) {
+            Some(found) => Ok(std::fs::canonicalize(found).unwrap_or_else(|_| found.clone())),
+            None => Err(candidates),
+        }
+    }
+
+    // Records that a file starts running.
+    // Returns the chain of includes from the file to itself when the file is already running.
+    pub fn enter(&mut self, path: &Path) -> Option<Vec<PathBuf>> {
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(start) = self.running.iter().position(|running| *running == path) {
+            let mut cycle = self.running[start..].to_vec();
+            cycle.push(path);
+            return Some(cycle);
+        }
+        self.running.push(path);
+        None
+    }
+
+    // Records that the innermost file running has ended.
+    pub fn leave(&mut self) {
+        self.running.pop();
+    }
+
+    // Returns how many files are running, the script and the chain of files it includes.
+    pub fn running(&self) -> usize {
+        self.running.len()
+    }
+
+    // Records that the files left running by an error have ended, keeping the first `running` ones.
+    pub fn unwind(&mut self, running: usize) {
+        self.running.truncate(running);
+    }
+
+    // Reports an error to stderr, along with the code it points at.
+    pub fn report<T: LError>(self, error: T) {
+        Reporter::new(self.files).report(error);
+    }
+
+    // Reports an error like `report`, keeping the files to report the errors found later, for an
+    // interactive session going on after an error.
+    pub fn show<T: LError>(&self, error: &T) {
+        error::warn(&self.files, error);
+    }
+}
+
+fn load_file<P: AsRef<Path>>(files: &mut FileSet, path: P) -> Result<FileIdentifier, FileError> {
+    let path = path.as_ref();
+    let input = std::fs::read_to_string(path);
+    let path_qualified = path::absolute(path).unwrap_or(path.to_path_buf());
+    match input {
+        Ok(content) => {
+            let file_id = files.add_file(path_qualified, content);
+            Ok(file_id)
+        }
+        Err(err) => {
+            Err(FileError::new(
+                path_qualified,
+                format!("Failed to read file: {}", err),
+            ))
+        }
+    }
+
+}
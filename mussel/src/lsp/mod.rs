@@ -0,0 +1,554 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel lsp`, a language server for the editors supporting the Language Server Protocol.
+// It talks with the editor on the standard input and output, and supports:
+// - the diagnostics: the errors and the warnings of a file, found again each time it changes,
+// - going to the definition of a function, a struct or a variable, in the file or in the files it
+//   includes,
+// - hovering a name, which shows its type when the type checker knows it, or the signature and the
+//   doc comments of a function,
+// - the completion of the names of the file in scope at the cursor, of its includes and of the
+//   standard library.
+// The files are analyzed like `mussel check` does, from the text the editor has, which may not be
+// saved yet. The files they include are read from the disk.
+
+pub(crate) mod json;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use codespan_reporting::diagnostic::{Diagnostic, LabelStyle, Severity};
+use json::{object, Json};
+use crate::checker::{self, Kind, Reference};
+use crate::doc;
+use crate::error::{self, FileIdentifier, FileSet, LError};
+use crate::expr::Expr;
+use crate::lexer::{self, Token};
+use crate::lint::LIBRARIES;
+use crate::loader::Loader;
+use crate::parser::{self, BindingExpression, Expression};
+use crate::{analyzer, stdlib};
+
+// The functions every script can call without including anything, see `stdlib::core`.
+const CORE: [&str; 12] =
+    ["println", "input", "range", "is_nil", "typeof", "get", "eval", "vars", "defined", "call", "args", "exit"];
+
+const KEYWORDS: [&str; 29] = [
+    "fn", "include", "for", "in", "if", "else", "until", "let", "const", "return", "true", "false", "nil", "or",
+    "and", "not", "match", "struct", "enum", "throw", "try", "catch", "assert", "loop", "break", "continue", "impl",
+    "trait", "as",
+];
+
+// The error the protocol answers to the requests a server doesn't support.
+const METHOD_NOT_FOUND: f64 = -32601.0;
+
+// The kinds of the completion items, as numbered by the protocol.
+const FUNCTION_ITEM: usize = 3;
+const VARIABLE_ITEM: usize = 6;
+const MODULE_ITEM: usize = 9;
+const KEYWORD_ITEM: usize = 14;
+const STRUCT_ITEM: usize = 22;
+
+// Runs the language server until the editor tells it to exit. Fails when the editor exits without
+// asking the server to shut down first, or when the standard input is closed.
+pub fn serve(check_types: bool) -> ExitCode {
+    let mut server = Server { documents: HashMap::new(), check_types, shutdown: false };
+    let mut input = io::stdin().lock();
+    while let Some(message) = receive(&mut input) {
+        let method = message.get(&["method"]).and_then(Json::as_str).unwrap_or_default();
+        let params = message.get(&["params"]).cloned().unwrap_or(Json::Null);
+        if method == "exit" {
+            return if server.shutdown { ExitCode::SUCCESS } else { ExitCode::FAILURE };
+        }
+        let result = server.handle(method, &params);
+        // Only the requests have an identifier, the notifications aren't answered.
+        let Some(id) = message.get(&["id"]).cloned() else {
+            continue;
+        };
+        let response = match result {
+            Some(result) => object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)]),
+            None => {
+                let message = format!("Unsupported request `{method}`");
+                let error = object([("code", Json::Number(METHOD_NOT_FOUND)), ("message", message.into())]);
+                object([("jsonrpc", "2.0".into()), ("id", id), ("error", error)])
+            }
+        };
+        send(&response);
+    }
+    ExitCode::FAILURE
+}
+
+// Reads a message, after its headers. Returns `None` when the input is closed, and `Json::Null`
+// for a message which isn't valid JSON. The messages of the debug adapter are framed the same way,
+// see `dap`.
+pub(crate) fn receive(input: &mut impl BufRead) -> Option<Json> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') && name.eq_ignore_ascii_case("Content-Length") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    Some(Json::parse(&String::from_utf8_lossy(&body)).unwrap_or(Json::Null))
+}
+
+pub(crate) fn send(message: &Json) {
+    let body = message.to_string();
+    let mut output = io::stdout().lock();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len()).expect("Failed to write to stdout");
+    output.flush().expect("Failed to flush stdout");
+}
+
+struct Server {
+    documents: HashMap<String, String>, // The text of the open documents, by URI.
+    check_types: bool,
+    shutdown: bool,
+}
+
+impl Server {
+    // Handles a request or a notification, returning the result of a request, or `None` when the
+    // method isn't supported.
+    fn handle(&mut self, method: &str, params: &Json) -> Option<Json> {
+        let uri = params.get(&["textDocument", "uri"]).and_then(Json::as_str).unwrap_or_default().to_string();
+        match method {
+            "initialize" => {
+                let capabilities = object([
+                    ("textDocumentSync", 1.into()), // The whole text is sent on each change.
+                    ("definitionProvider", true.into()),
+                    ("hoverProvider", true.into()),
+                    ("completionProvider", object([])),
+                ]);
+                let info = object([("name", "mussel".into()), ("version", env!("CARGO_PKG_VERSION").into())]);
+                Some(object([("capabilities", capabilities), ("serverInfo", info)]))
+            }
+            "initialized" => Some(Json::Null),
+            "shutdown" => {
+                self.shutdown = true;
+                Some(Json::Null)
+            }
+            "textDocument/didOpen" => {
+                let text = params.get(&["textDocument", "text"]).and_then(Json::as_str).unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                self.publish(&uri);
+                Some(Json::Null)
+            }
+            "textDocument/didChange" => {
+                let changes = params.get(&["contentChanges"]).and_then(Json::as_array).unwrap_or_default();
+                if let Some(text) = changes.last().and_then(|change| change.get(&["text"])).and_then(Json::as_str) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                }
+                self.publish(&uri);
+                Some(Json::Null)
+            }
+            "textDocument/didSave" => {
+                self.publish(&uri);
+                Some(Json::Null)
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.publish(&uri);
+                Some(Json::Null)
+            }
+            "textDocument/definition" => Some(self.definition(&uri, params).unwrap_or(Json::Null)),
+            "textDocument/hover" => Some(self.hover(&uri, params).unwrap_or(Json::Null)),
+            "textDocument/completion" => Some(self.completion(&uri, params)),
+            _ => None,
+        }
+    }
+
+    // Sends the diagnostics of a document, none once it is closed.
+    fn publish(&self, uri: &str) {
+        let mut diagnostics = Vec::new();
+        if let Some(text) = self.documents.get(uri) {
+            // The parser may report the same error several times, it is shown once.
+            for diagnostic in Analysis::new(uri, text, self.check_types).diagnostics {
+                let diagnostic = convert(text, &diagnostic);
+                if !diagnostics.contains(&diagnostic) {
+                    diagnostics.push(diagnostic);
+                }
+            }
+        }
+        let params = object([("uri", uri.into()), ("diagnostics", Json::Array(diagnostics))]);
+        let method = "textDocument/publishDiagnostics";
+        send(&object([("jsonrpc", "2.0".into()), ("method", method.into()), ("params", params)]));
+    }
+
+    // Returns the analysis of a document, with the offset of the position of a request.
+    fn locate(&self, uri: &str, params: &Json) -> Option<(Analysis, usize)> {
+        let text = self.documents.get(uri)?;
+        let offset = offset(text, params.get(&["position"])?)?;
+        Some((Analysis::new(uri, text, self.check_types), offset))
+    }
+
+    fn definition(&self, uri: &str, params: &Json) -> Option<Json> {
+        let (analysis, offset) = self.locate(uri, params)?;
+        if let Some(reference) = analysis.reference(offset) {
+            return Some(location(uri, &analysis.text, &reference.declaration));
+        }
+        let (_, name) = word(&analysis.text, offset)?;
+        let declaration = analysis.included(name)?;
+        // The libraries of the standard library have no file to open.
+        let file = declaration.file?;
+        Some(location(&file_uri(&file), &declaration.content, &declaration.range))
+    }
+
+    fn hover(&self, uri: &str, params: &Json) -> Option<Json> {
+        let (analysis, offset) = self.locate(uri, params)?;
+        let (range, contents) = match analysis.reference(offset) {
+            Some(reference) => {
+                let summary = find(&analysis.expressions, &reference.declaration)
+                    .and_then(|declaration| doc::summary(&analysis.text, declaration));
+                (reference.range.clone(), hover_text(summary.unwrap_or((reference.description.clone(), String::new()))))
+            }
+            None => {
+                let (range, name) = word(&analysis.text, offset)?;
+                let summary = if let Some(declaration) = analysis.included(name) {
+                    declaration.summary.unwrap_or((name.to_string(), String::new()))
+                } else if CORE.contains(&name) {
+                    (format!("fn {name}(...)"), "A built-in function.".to_string())
+                } else {
+                    let (library, _) = LIBRARIES.iter().find(|(_, names)| names.contains(&name))?;
+                    let doc = format!("A function of the standard library, from `include {library}`.");
+                    (format!("fn {name}(...)"), doc)
+                };
+                (range, hover_text(summary))
+            }
+        };
+        let contents = object([("kind", "markdown".into()), ("value", contents.into())]);
+        Some(object([("contents", contents), ("range", span(&analysis.text, &range))]))
+    }
+
+    // Returns the names that can be completed in a document at the position of the request: the
+    // names of the document declared in the scopes around it, the structs, and the names of its
+    // includes. The editor keeps the ones matching what is typed.
+    fn completion(&self, uri: &str, params: &Json) -> Json {
+        let mut items = Vec::new();
+        if let Some(text) = self.documents.get(uri) {
+            let analysis = Analysis::new(uri, text, false);
+            let visible = params
+                .get(&["position"])
+                .and_then(|position| offset(text, position))
+                .map(|offset| analyzer::visible(analysis.file, text, &analysis.expressions, offset));
+            let declarations = analysis.references.iter().filter(|reference| {
+                reference.range == reference.declaration
+                    && (reference.kind == Kind::Struct
+                        || visible.as_ref().is_none_or(|visible| visible.contains(&reference.declaration)))
+            });
+            for reference in declarations {
+                let kind = match reference.kind {
+                    Kind::Variable => VARIABLE_ITEM,
+                    Kind::Function => FUNCTION_ITEM,
+                    Kind::Struct => STRUCT_ITEM,
+                };
+                items.push((text[reference.range.clone()].to_string(), kind, reference.description.clone()));
+            }
+            for declaration in analysis.includes() {
+                let detail = declaration.summary.map_or_else(String::new, |(signature, _)| signature);
+                items.push((declaration.name, declaration.kind, detail));
+            }
+        }
+        items.extend(CORE.iter().map(|name| (name.to_string(), FUNCTION_ITEM, "built-in".to_string())));
+        for (library, names) in LIBRARIES {
+            items.push((library.to_string(), MODULE_ITEM, "library".to_string()));
+            items.extend(names.iter().map(|name| (name.to_string(), FUNCTION_ITEM, format!("include {library}"))));
+        }
+        for (name, expressions, content) in libraries() {
+            items.push((name.to_string(), MODULE_ITEM, "library".to_string()));
+            for expression in &expressions {
+                if let Expression::Function { id, .. } = expression {
+                    items.push((id.get_content(&content).to_string(), FUNCTION_ITEM, format!("include {name}")));
+                }
+            }
+        }
+        items.extend(KEYWORDS.iter().map(|keyword| (keyword.to_string(), KEYWORD_ITEM, "keyword".to_string())));
+        // A name declared several times, like a variable of several functions, is completed once.
+        let mut completed: Vec<String> = Vec::new();
+        let mut list = Vec::new();
+        for (label, kind, detail) in items {
+            if completed.contains(&label) {
+                continue;
+            }
+            completed.push(label.clone());
+            list.push(object([("label", label.into()), ("kind", kind.into()), ("detail", detail.into())]));
+        }
+        Json::Array(list)
+    }
+}
+
+// What is known about a document, found again each time it is looked at, as it may have changed.
+struct Analysis {
+    path: PathBuf,
+    file: FileIdentifier,
+    text: String,
+    expressions: Vec<Expression>, // Empty when the document can't be parsed.
+    references: Vec<Reference>,
+    diagnostics: Vec<Diagnostic<usize>>,
+}
+
+// A declaration found in a file included by a document.
+struct Declaration {
+    name: String,
+    kind: usize, // The kind of its completion item.
+    file: Option<PathBuf>, // The file declaring it, `None` in the standard library.
+    content: String, // The content of the file.
+    range: Range<usize>,
+    summary: Option<(String, String)>, // The signature and the doc comments of a function or a struct.
+}
+
+impl Analysis {
+    fn new(uri: &str, text: &str, check_types: bool) -> Analysis {
+        let path = uri_path(uri);
+        let mut files = FileSet::new();
+        let file = files.add_file(&path, text.to_string());
+        let mut diagnostics = Vec::new();
+        let parsed = lexer::lex(&files, file).map_err(error::boxed).and_then(|tokens| {
+            let expressions = parser::parser(file, &tokens)?;
+            // The conversion takes the expressions, so they are parsed again for it.
+            let converted = parser::parser(file, &tokens)?;
+            Expr::from_parser(&files, file, converted).map_err(error::boxed)?;
+            Ok(expressions)
+        });
+        let expressions = parsed.unwrap_or_else(|error| {
+            diagnostics.extend(error.report());
+            Vec::new()
+        });
+        // The document may be included by other files, so its unused declarations aren't reported.
+        for warning in analyzer::analyze(file, text, &expressions, true) {
+            diagnostics.extend(warning.report());
+        }
+        if check_types && let Err(errors) = checker::check_types(file, text, &expressions) {
+            diagnostics.extend(errors.report());
+        }
+        let references = checker::references(file, text, &expressions);
+        Analysis { path, file, text: text.to_string(), expressions, references, diagnostics }
+    }
+
+    // Returns the name written at an offset, when it refers to a declaration of the document.
+    fn reference(&self, offset: usize) -> Option<&Reference> {
+        self.references.iter().find(|reference| reference.range.start <= offset && offset <= reference.range.end)
+    }
+
+    // Returns the declaration of a name in the files included by the document.
+    fn included(&self, name: &str) -> Option<Declaration> {
+        self.includes().into_iter().find(|declaration| declaration.name == name)
+    }
+
+    // Returns the declarations of the files included by the document, and of the files they
+    // include. The files which can't be found or parsed are left out.
+    fn includes(&self) -> Vec<Declaration> {
+        let mut loader = Loader::new(false);
+        let mut declarations = Vec::new();
+        let mut visited = vec![self.path.clone()];
+        let mut pending = vec![(self.path.clone(), include_targets(&self.text, &self.expressions))];
+        while let Some((including, targets)) = pending.pop() {
+            for (token, target) in targets {
+                let (path, source) = if token == Token::String {
+                    match loader.find_from(&including, Path::new(target.trim_matches('"'))) {
+                        Ok(path) => (path, None),
+                        Err(_) => continue,
+                    }
+                } else if let Some(source) = stdlib::source(&target) {
+                    (Path::new("<std>").join(format!("{target}.mus")), Some(source))
+                } else {
+                    match loader.find_from(&including, Path::new(&format!("{target}.mus"))) {
+                        Ok(path) => (path, None),
+                        Err(_) => continue,
+                    }
+                };
+                if visited.contains(&path) {
+                    continue;
+                }
+                visited.push(path.clone());
+                let Ok((file, expressions)) = loader.syntax(&path, source) else {
+                    continue;
+                };
+                let content = loader.content(file).to_string();
+                for expression in &expressions {
+                    let (id, kind) = match expression {
+                        Expression::Function { id, .. } => (id, FUNCTION_ITEM),
+                        Expression::Struct { id, .. } | Expression::Enum { id, .. } => (id, STRUCT_ITEM),
+                        Expression::Let { binding: BindingExpression::Identifier(id), .. }
+                        | Expression::Const { binding: BindingExpression::Identifier(id), .. } => (id, VARIABLE_ITEM),
+                        _ => continue,
+                    };
+                    declarations.push(Declaration {
+                        name: id.get_content(&content).to_string(),
+                        kind,
+                        file: source.is_none().then(|| path.clone()),
+                        content: content.clone(),
+                        range: id.range(),
+                        summary: doc::summary(&content, expression),
+                    });
+                }
+                pending.push((path, include_targets(&content, &expressions)));
+            }
+        }
+        declarations
+    }
+}
+
+// Returns the `include`s of a file, with the kind of token naming what they include: a string for
+// a path, or else the name of a library.
+fn include_targets(content: &str, expressions: &[Expression]) -> Vec<(Token, String)> {
+    expressions
+        .iter()
+        .filter_map(|expression| match expression {
+            Expression::Include { id, .. } => Some((id.token_type, id.get_content(content).to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+// Returns the libraries of the standard library written in Mussel, parsed, with their content.
+fn libraries() -> Vec<(&'static str, Vec<Expression>, String)> {
+    let mut loader = Loader::new(false);
+    ["list"]
+        .into_iter()
+        .filter_map(|name| {
+            let source = stdlib::source(name)?;
+            let (file, expressions) = loader.syntax(Path::new("<std>").join(format!("{name}.mus")), Some(source)).ok()?;
+            Some((name, expressions, loader.content(file).to_string()))
+        })
+        .collect()
+}
+
+// Finds the function or the struct declared with the given name, in a file or in the blocks of its
+// functions and methods.
+fn find<'a>(expressions: &'a [Expression], name: &Range<usize>) -> Option<&'a Expression> {
+    expressions.iter().find_map(|expression| match expression {
+        Expression::Function { id, .. } | Expression::Struct { id, .. } if id.range() == *name => Some(expression),
+        Expression::Function { block, .. } => find(block, name),
+        Expression::Impl { methods, .. } => find(methods, name),
+        _ => None,
+    })
+}
+
+// Writes a hover in Markdown, from a signature and its doc comments.
+fn hover_text((signature, doc): (String, String)) -> String {
+    let code = format!("```mussel\n{signature}\n```");
+    if doc.is_empty() { code } else { format!("{code}\n\n{doc}") }
+}
+
+// Returns the name written around an offset, with its range.
+fn word(text: &str, offset: usize) -> Option<(Range<usize>, &str)> {
+    let is_name = |c: char| c.is_alphanumeric() || c == '_';
+    let offset = offset.min(text.len());
+    let start = text[..offset].rfind(|c| !is_name(c)).map_or(0, |index| index + 1);
+    let end = text[offset..].find(|c| !is_name(c)).map_or(text.len(), |index| offset + index);
+    (start < end).then(|| (start..end, &text[start..end]))
+}
+
+// Converts a diagnostic into the one of the protocol. Its location is its primary label, and its
+// message is followed by the message of the label and by its notes.
+fn convert(text: &str, diagnostic: &Diagnostic<usize>) -> Json {
+    let label = diagnostic.labels.iter().find(|label| label.style == LabelStyle::Primary).or(diagnostic.labels.first());
+    let mut message = diagnostic.message.clone();
+    for line in label.map(|label| &label.message).into_iter().chain(&diagnostic.notes) {
+        if !line.is_empty() {
+            message.push('\n');
+            message.push_str(line);
+        }
+    }
+    let severity: usize = match diagnostic.severity {
+        Severity::Bug | Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+        Severity::Help => 4,
+    };
+    let mut members = vec![
+        ("range".to_string(), span(text, &label.map_or(0..0, |label| label.range.clone()))),
+        ("severity".to_string(), severity.into()),
+        ("source".to_string(), "mussel".into()),
+        ("message".to_string(), message.into()),
+    ];
+    if let Some(code) = &diagnostic.code {
+        members.push(("code".to_string(), code.as_str().into()));
+    }
+    Json::Object(members)
+}
+
+fn location(uri: &str, text: &str, range: &Range<usize>) -> Json {
+    object([("uri", uri.into()), ("range", span(text, range))])
+}
+
+fn span(text: &str, range: &Range<usize>) -> Json {
+    object([("start", position(text, range.start)), ("end", position(text, range.end))])
+}
+
+// Returns the position of an offset in a text: its line, and its character in the line, counted in
+// UTF-16 code units as the protocol does.
+fn position(text: &str, offset: usize) -> Json {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &text[..offset];
+    let start = before.rfind('\n').map_or(0, |index| index + 1);
+    let character: usize = before[start..].chars().map(char::len_utf16).sum();
+    object([("line", before.matches('\n').count().into()), ("character", character.into())])
+}
+
+// Returns the offset of a position of the protocol in a text.
+fn offset(text: &str, position: &Json) -> Option<usize> {
+    let line = position.get(&["line"])?.as_usize()?;
+    let character = position.get(&["character"])?.as_usize()?;
+    let start = match line {
+        0 => 0,
+        line => text.match_indices('\n').nth(line - 1)?.0 + 1,
+    };
+    let mut units = 0;
+    for (index, c) in text[start..].char_indices() {
+        if units >= character || c == '\n' {
+            return Some(start + index);
+        }
+        units += c.len_utf16();
+    }
+    Some(text.len())
+}
+
+// Returns the path of a `file:` URI, with its escaped bytes decoded.
+fn uri_path(uri: &str) -> PathBuf {
+    let encoded = uri.strip_prefix("file://").unwrap_or(uri).as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let hex = encoded.get(index + 1..index + 3).and_then(|hex| std::str::from_utf8(hex).ok());
+        let escaped = hex.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (encoded[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+// Returns the `file:` URI of a path, escaping the bytes URIs can't hold.
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
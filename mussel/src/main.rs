@@ -1,8 +1,6 @@
 // Copyright (c) 2025 Francesco Giannice
 // Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
 
-use std::path;
-use std::path::Path;
 // Import the `FromArgs` trait from the `argh` crate for parsing command line arguments.
 use argh::FromArgs;
 
@@ -10,84 +8,887 @@ use argh::FromArgs;
 // - `eyre` for creating error reports,
 // - `Result` as a convenient alias for a Result type.
 use color_eyre::Result;
-use crate::error::{FileError, FileIdentifier, FileSet, LError, Reporter};
-use crate::expr::Expr;
-
-
-mod interpreter;
-mod stdlib;
-mod error;
-mod lexer;
-mod parser;
-mod expr;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+use mussel::{
+    bench, bundle, check, color, context, dap, debugger, doc, dump, explain, format, interpreter, javascript, kernel,
+    limits, lint, lsp, project, repl, rust, sarif, testing, timing, warnings, watch,
+};
+use mussel::color::Color;
+use mussel::interpreter::Backend;
+use mussel::limits::{Limits, Memory, Timeout};
+use mussel::loader::Loader;
+use mussel::sarif::ErrorFormat;
+use mussel::timing::Phase;
+use mussel::trace::Trace;
 
 // Derive the `FromArgs` trait automatically so that command-line arguments can be parsed.
 // The doc-comment (triple slash) describes the application when running the help command.
 #[derive(FromArgs)]
 /// Interpreter for the salt language
+#[argh(note = "Use `{command_name} new NAME` to create a project, `{command_name} run` to run the project of \
+the current directory, `{command_name} build FILE -o OUTPUT` to bundle a script into a standalone executable, \
+`{command_name} check FILE` to report its errors without running it, `{command_name} fmt FILE` to format it, \
+`{command_name} lint FILE` to find the code that is likely a mistake, `{command_name} explain CODE` to describe an \
+error, `{command_name} test` to run the tests of \
+the current directory, `{command_name} bench` to time its benchmarks, `{command_name} doc FILE` to write the \
+documentation of a script, `{command_name} lsp` to serve the editors, `{command_name} dap` to debug scripts in them, \
+`{command_name} kernel --connection-file FILE` to run the cells of Jupyter notebooks, `{command_name} emit-js FILE` \
+to translate it to JavaScript, and \
+`{command_name} emit-rs FILE -o DIRECTORY` to translate it to a Rust program.")]
 struct Args {
-    /// file to run
-    // This attribute indicates that the field is a positional argument.
+    /// file to run, followed by the arguments given to it, read with `args()` (default: start an
+    /// interactive session)
+    // This attribute indicates that the field is a positional argument, taking the options after
+    // the file as arguments of the script.
+    #[argh(positional, greedy)]
+    script: Vec<String>, // The path to the file to run, then its arguments.
+
+    /// maximum number of nested function calls (default: 1000, or `MUSSEL_MAX_DEPTH`)
+    #[argh(option, default = "default_max_depth()")]
+    max_depth: usize,
+
+    /// size of the stack of the interpreter, in bytes or like `1G` and at least 1M, which bounds how
+    /// deep the calls can be nested with a large --max-depth (default: 512M, or `MUSSEL_MAX_STACK`)
+    #[argh(option, default = "default_max_stack()")]
+    max_stack: StackSize,
+
+    /// check the types of the script before running it
+    #[argh(switch)]
+    check_types: bool,
+
+    /// how to run the script: `tree` to evaluate it directly, `vm` to compile it to bytecode first (default: tree)
+    #[argh(option, default = "Backend::Tree")]
+    backend: Backend,
+
+    /// stop the script once it has taken this many steps, the expressions evaluated or the
+    /// instructions run (default: no limit)
+    #[argh(option)]
+    max_steps: Option<u64>,
+
+    /// stop the script once the interpreter uses more memory than this, in bytes or like `64M`
+    /// (default: no limit)
+    #[argh(option)]
+    max_memory: Option<Memory>,
+
+    /// stop the script once it has run for this long, in seconds or like `500ms` (default: no limit)
+    #[argh(option)]
+    timeout: Option<Timeout>,
+
+    /// print the tokens of the script, with their position, type and text, instead of running it
+    #[argh(switch)]
+    dump_tokens: bool,
+
+    /// print the expressions the script is parsed into, instead of running it: `tree` to read them,
+    /// `json` for other tools
+    #[argh(option)]
+    dump_ast: Option<dump::Format>,
+
+    /// run the script again each time it or a file it includes changes, clearing the screen first
+    #[argh(switch)]
+    watch: bool,
+
+    /// pause the script before its first statement, then run it with commands typed in the
+    /// terminal: breakpoints, steps, and printing its variables and calls
+    #[argh(switch)]
+    debug: bool,
+
+    /// write the expressions evaluated, with where they are written and their value, to the
+    /// standard error: `all` for every expression, `calls` for the calls only
+    #[argh(option)]
+    trace: Option<Trace>,
+
+    /// once the script ends, print the number of calls of each function and the time spent in
+    /// them, with and without the functions they called, to the standard error
+    #[argh(switch)]
+    profile: bool,
+
+    /// write the time spent in each chain of function calls to a file, as folded stacks to draw a
+    /// flame graph, instead of the table of --profile
+    #[argh(option)]
+    profile_stacks: Option<PathBuf>,
+
+    /// once the script ends, print how long lexing, parsing, analyzing, converting and evaluating
+    /// it took, and the peak memory used, to the standard error
+    #[argh(switch)]
+    time: bool,
+
+    /// turn on a warning which is off by default, by its name
+    #[argh(option, short = 'W')]
+    warn: Vec<String>,
+
+    /// turn off a warning, by its name
+    #[argh(option, short = 'A')]
+    allow: Vec<String>,
+
+    /// report the warnings as errors, failing when there are some
+    #[argh(switch)]
+    deny_warnings: bool,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The options of `mussel new`.
+#[derive(FromArgs)]
+/// Create a project in a new directory, with a `mussel.toml` manifest and a script to run
+struct NewArgs {
+    /// directory of the project, whose name is the name of the project
+    #[argh(positional)]
+    path: String,
+}
+
+// The options of `mussel run`.
+#[derive(FromArgs)]
+/// Run the entry script of the project of the current directory, declared in its `mussel.toml`
+struct RunArgs {
+    /// arguments given to the script, read with `args()`
+    #[argh(positional, greedy)]
+    arguments: Vec<String>,
+
+    /// maximum number of nested function calls (default: 1000, or `MUSSEL_MAX_DEPTH`)
+    #[argh(option, default = "default_max_depth()")]
+    max_depth: usize,
+
+    /// size of the stack of the interpreter, in bytes or like `1G` and at least 1M, which bounds how
+    /// deep the calls can be nested with a large --max-depth (default: 512M, or `MUSSEL_MAX_STACK`)
+    #[argh(option, default = "default_max_stack()")]
+    max_stack: StackSize,
+
+    /// check the types of the script before running it
+    #[argh(switch)]
+    check_types: bool,
+
+    /// how to run the script: `tree` or `vm` (default: tree)
+    #[argh(option, default = "Backend::Tree")]
+    backend: Backend,
+
+    /// stop the script once it has taken this many steps, the expressions evaluated or the
+    /// instructions run (default: no limit)
+    #[argh(option)]
+    max_steps: Option<u64>,
+
+    /// stop the script once the interpreter uses more memory than this, in bytes or like `64M`
+    /// (default: no limit)
+    #[argh(option)]
+    max_memory: Option<Memory>,
+
+    /// stop the script once it has run for this long, in seconds or like `500ms` (default: no limit)
+    #[argh(option)]
+    timeout: Option<Timeout>,
+
+    /// once the script ends, print how long each phase of running it took, and the peak memory
+    /// used, to the standard error
+    #[argh(switch)]
+    time: bool,
+
+    /// turn on a warning which is off by default, by its name
+    #[argh(option, short = 'W')]
+    warn: Vec<String>,
+
+    /// turn off a warning, by its name
+    #[argh(option, short = 'A')]
+    allow: Vec<String>,
+
+    /// report the warnings as errors, failing when there are some
+    #[argh(switch)]
+    deny_warnings: bool,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The options of `mussel build`.
+#[derive(FromArgs)]
+/// Bundle a script and the interpreter into a standalone executable, which runs the script
+struct BuildArgs {
+    /// script to bundle, along with the files it includes
+    #[argh(positional)]
+    file: String,
+
+    /// path of the executable to write (default: the name of the script without its extension)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// maximum number of nested function calls in the executable (default: 1000, or
+    /// `MUSSEL_MAX_DEPTH`)
+    #[argh(option, default = "default_max_depth()")]
+    max_depth: usize,
+
+    /// how the executable runs the script: `tree` or `vm` (default: tree)
+    #[argh(option, default = "Backend::Tree")]
+    backend: Backend,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The options of `mussel check`.
+#[derive(FromArgs)]
+/// Report the errors of a script and the files it includes without running it
+struct CheckArgs {
+    /// script to check
     #[argh(positional)]
-    file: String, // The `file` field will store the path to the file to run.
+    file: String,
+
+    /// check the types of the files too
+    #[argh(switch)]
+    check_types: bool,
+
+    /// turn on a warning which is off by default, by its name
+    #[argh(option, short = 'W')]
+    warn: Vec<String>,
+
+    /// turn off a warning, by its name
+    #[argh(option, short = 'A')]
+    allow: Vec<String>,
+
+    /// report the warnings as errors, failing when there are some
+    #[argh(switch)]
+    deny_warnings: bool,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+
+    /// how to write the errors and warnings: `human` to read them, or `sarif` to write them to the
+    /// standard output as a SARIF document, for code scanning tools (default: human)
+    #[argh(option, default = "ErrorFormat::Human")]
+    error_format: ErrorFormat,
+}
+
+// The options of `mussel fmt`.
+#[derive(FromArgs)]
+/// Format scripts in place, with the canonical indentation and spacing
+struct FormatArgs {
+    /// scripts to format
+    #[argh(positional)]
+    files: Vec<String>,
+
+    /// only list the scripts which aren't formatted, failing if there are some
+    #[argh(switch)]
+    check: bool,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The options of `mussel lint`.
+#[derive(FromArgs)]
+/// Report the code of scripts that is likely a mistake, like a variable hiding another one
+#[argh(note = "The rules are shadowed-variable, constant-condition, unused-include, suspicious-assignment and \
+self-assignment, on by default, and comparison-to-bool, off by default. They are warnings like the ones of the \
+analysis, turned on with -W and off with -A.")]
+struct LintArgs {
+    /// scripts to lint
+    #[argh(positional)]
+    files: Vec<String>,
+
+    /// turn on a rule or a warning which is off by default, by its name
+    #[argh(option, short = 'W')]
+    warn: Vec<String>,
+
+    /// turn off a rule or a warning, by its name
+    #[argh(option, short = 'A')]
+    allow: Vec<String>,
+
+    // The former names of `--warn` and `--allow`, still taken but left out of the help.
+    /// deprecated, use `--warn`
+    #[argh(option, hidden_help)]
+    enable: Vec<String>,
+
+    /// deprecated, use `--allow`
+    #[argh(option, hidden_help)]
+    disable: Vec<String>,
+
+    /// report the warnings as errors
+    #[argh(switch)]
+    deny_warnings: bool,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+
+    /// how to write the errors and warnings: `human` to read them, or `sarif` to write them to the
+    /// standard output as a SARIF document, for code scanning tools (default: human)
+    #[argh(option, default = "ErrorFormat::Human")]
+    error_format: ErrorFormat,
+}
+
+// The options of `mussel explain`.
+#[derive(FromArgs)]
+/// Describe an error from its code, like E1002, with an example of code raising it and how to fix it
+struct ExplainArgs {
+    /// the code of the error, shown with it, or the name of a rule of `mussel lint`
+    #[argh(positional)]
+    code: String,
+}
+
+// The options of `mussel lsp`.
+#[derive(FromArgs)]
+/// Run a language server, which gives the editors the diagnostics, definitions, hovers and
+/// completions of the scripts
+struct LspArgs {
+    /// report the type errors found by the type checker too
+    #[argh(switch)]
+    check_types: bool,
+}
+
+// The options of `mussel dap`.
+#[derive(FromArgs)]
+/// Run a debug adapter, which lets the editors run scripts with breakpoints, step through them and
+/// look at their variables
+struct DapArgs {
+    /// maximum number of nested function calls (default: 1000, or `MUSSEL_MAX_DEPTH`)
+    #[argh(option, default = "default_max_depth()")]
+    max_depth: usize,
+}
+
+// The options of `mussel kernel`.
+#[derive(FromArgs)]
+/// Run a Jupyter kernel, which runs the cells of notebooks in the same global scope
+struct KernelArgs {
+    /// the connection file written by Jupyter, with the ports to listen on and the signing key
+    #[argh(option)]
+    connection_file: PathBuf,
+
+    /// maximum number of nested function calls (default: 1000, or `MUSSEL_MAX_DEPTH`)
+    #[argh(option, default = "default_max_depth()")]
+    max_depth: usize,
+
+    /// check the types of the cells before running them
+    #[argh(switch)]
+    check_types: bool,
+
+    /// how to run the cells: `tree` or `vm` (default: tree)
+    #[argh(option, default = "Backend::Tree")]
+    backend: Backend,
+}
+
+// The options of `mussel test`.
+#[derive(FromArgs)]
+/// Run the `test_` functions of the files whose name ends with `_test.mus`
+struct TestArgs {
+    /// test files, or directories to find them in (default: the current directory)
+    #[argh(positional)]
+    paths: Vec<String>,
+
+    /// maximum number of nested function calls (default: 1000, or `MUSSEL_MAX_DEPTH`)
+    #[argh(option, default = "default_max_depth()")]
+    max_depth: usize,
+
+    /// check the types of the test files before running them
+    #[argh(switch)]
+    check_types: bool,
+
+    /// how to run the tests: `tree` or `vm` (default: tree)
+    #[argh(option, default = "Backend::Tree")]
+    backend: Backend,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
 }
 
-fn main() -> Result<()> {
+// The options of `mussel bench`.
+#[derive(FromArgs)]
+/// Time the `bench_` functions of the files whose name ends with `_bench.mus`
+struct BenchArgs {
+    /// benchmark files, or directories to find them in (default: the current directory)
+    #[argh(positional)]
+    paths: Vec<String>,
+
+    /// number of calls of each benchmark before the timed ones (default: 3)
+    #[argh(option, default = "3")]
+    warmup: usize,
+
+    /// number of timed calls of each benchmark (default: 10)
+    #[argh(option, default = "10")]
+    runs: usize,
+
+    /// maximum number of nested function calls (default: 1000, or `MUSSEL_MAX_DEPTH`)
+    #[argh(option, default = "default_max_depth()")]
+    max_depth: usize,
+
+    /// check the types of the benchmark files before running them
+    #[argh(switch)]
+    check_types: bool,
+
+    /// how to run the benchmarks: `tree` or `vm` (default: tree)
+    #[argh(option, default = "Backend::Tree")]
+    backend: Backend,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The options of `mussel doc`.
+#[derive(FromArgs)]
+/// Write the documentation of a script and the files it includes, from their `///` comments
+struct DocArgs {
+    /// script to document
+    #[argh(positional)]
+    file: String,
+
+    /// how to write the documentation: `markdown` or `html` (default: markdown)
+    #[argh(option, default = "doc::Format::Markdown")]
+    format: doc::Format,
+
+    /// path of the file to write (default: print it)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The options of `mussel emit-js`.
+#[derive(FromArgs)]
+/// Translate a script and the files it includes to JavaScript
+struct EmitArgs {
+    /// script to translate
+    #[argh(positional)]
+    file: String,
+
+    /// path of the JavaScript file to write (default: print it)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The options of `mussel emit-rs`.
+#[derive(FromArgs)]
+/// Translate a script and the files it includes to a Rust program, a cargo package
+struct EmitRustArgs {
+    /// script to translate
+    #[argh(positional)]
+    file: String,
+
+    /// directory of the cargo package to write (default: the name of the script without its extension)
+    #[argh(option, short = 'o')]
+    output: Option<String>,
+
+    /// when to write the errors in color: `auto`, `always` or `never` (default: auto, in color
+    /// only to a terminal, without `NO_COLOR` set)
+    #[argh(option, default = "Color::Auto")]
+    color: Color,
+}
+
+// The memory allocated is counted, to stop the scripts using more than `--max-memory`.
+#[global_allocator]
+static ALLOCATOR: limits::Counting = limits::Counting;
+
+// The stack size of the thread running the interpreter, unless `--max-stack` says otherwise.
+// Every nested call of a Mussel function uses several frames of the interpreter, so the default
+// stack of the main thread would overflow long before the recursion limit is reached.
+const INTERPRETER_STACK_SIZE: usize = 512 * 1024 * 1024;
+
+// The exit status is a failure when an error was reported, or the one the script ended with.
+fn main() -> Result<ExitCode> {
     // Install `color_eyre` which sets up enhanced error reporting (including colored output).
     // The `?` operator propagates any error that might occur during installation.
     color_eyre::install()?;
 
+    // A standalone executable runs the script bundled with it, given its whole command line.
+    if let Some(bundle) = bundle::embedded() {
+        let (max_depth, backend) = (bundle.max_depth, bundle.backend);
+        let arguments = std::env::args().skip(1).collect();
+        return spawn(default_max_stack().0, move || {
+            let file = bundle.script().to_path_buf();
+            run(Loader::bundled(bundle), file, max_depth, Mode::Run(backend), arguments)
+        });
+    }
+
+    // `mussel new` creates a project, which `mussel run` runs from its manifest.
+    let arguments: Vec<String> = std::env::args().collect();
+    if arguments.get(1).is_some_and(|command| command == "new") {
+        let NewArgs { path } = parse_command(&arguments);
+        return Ok(project::new(&path));
+    }
+    if arguments.get(1).is_some_and(|command| command == "run") {
+        let RunArgs {
+            arguments,
+            max_depth,
+            max_stack,
+            check_types,
+            backend,
+            max_steps,
+            max_memory,
+            timeout,
+            time,
+            warn,
+            allow,
+            deny_warnings,
+            color,
+        } = parse_command(&arguments);
+        color::set(color);
+        limits::set(Limits { max_steps, max_memory, timeout });
+        if !warnings::set(&warn, &allow, deny_warnings) {
+            return Ok(ExitCode::FAILURE);
+        }
+        let manifest = match project::Manifest::find() {
+            Ok(manifest) => manifest,
+            Err(message) => {
+                eprintln!("error: {message}");
+                return Ok(ExitCode::FAILURE);
+            }
+        };
+        let (file, directories) = (manifest.script(), manifest.directories());
+        return spawn(max_stack.0, move || {
+            let mut loader = Loader::new(check_types);
+            loader.include(directories);
+            timing::report(time, || run(loader, file, max_depth, Mode::Run(backend), arguments))
+        });
+    }
+
+    // `mussel build` writes a standalone executable instead of running the script.
+    if arguments.get(1).is_some_and(|command| command == "build") {
+        let BuildArgs { file, output, max_depth, backend, color } = parse_command(&arguments);
+        color::set(color);
+        let output = output.unwrap_or_else(|| {
+            Path::new(&file).file_stem().map_or("a.out".to_string(), |stem| stem.to_string_lossy().into_owned())
+        });
+        return Ok(bundle::build(&file, Path::new(&output), max_depth, backend));
+    }
+
+    // `mussel check` only reports the errors of the script.
+    if arguments.get(1).is_some_and(|command| command == "check") {
+        let CheckArgs { file, check_types, warn, allow, deny_warnings, color, error_format } =
+            parse_command(&arguments);
+        color::set(color);
+        if !warnings::set(&warn, &allow, deny_warnings) {
+            return Ok(ExitCode::FAILURE);
+        }
+        return Ok(sarif::report(error_format, || check::check(&file, check_types)));
+    }
+
+    // `mussel fmt` rewrites the scripts in the canonical layout.
+    if arguments.get(1).is_some_and(|command| command == "fmt") {
+        let FormatArgs { files, check, color } = parse_command(&arguments);
+        color::set(color);
+        return Ok(format::format_files(&files, check));
+    }
+
+    // `mussel lint` reports the code that is likely a mistake.
+    if arguments.get(1).is_some_and(|command| command == "lint") {
+        let LintArgs { files, enable, disable, warn, allow, deny_warnings, color, error_format } =
+            parse_command(&arguments);
+        color::set(color);
+        for (deprecated, flag, used) in [("--enable", "--warn", &enable), ("--disable", "--allow", &disable)] {
+            if !used.is_empty() {
+                eprintln!("warning: `{deprecated}` is deprecated, use `{flag}` instead");
+            }
+        }
+        if !warnings::set(&[enable, warn].concat(), &[disable, allow].concat(), deny_warnings) {
+            return Ok(ExitCode::FAILURE);
+        }
+        return Ok(sarif::report(error_format, || lint::lint(&files)));
+    }
+
+    // `mussel explain` describes the error of a code.
+    if arguments.get(1).is_some_and(|command| command == "explain") {
+        let ExplainArgs { code } = parse_command(&arguments);
+        return Ok(explain::explain(&code));
+    }
+
+    // `mussel lsp` talks with an editor on the standard input and output.
+    if arguments.get(1).is_some_and(|command| command == "lsp") {
+        let LspArgs { check_types } = parse_command(&arguments);
+        return Ok(lsp::serve(check_types));
+    }
+
+    // `mussel dap` talks with an editor on the standard input and output, and runs the script it
+    // is asked to debug.
+    if arguments.get(1).is_some_and(|command| command == "dap") {
+        let DapArgs { max_depth } = parse_command(&arguments);
+        return spawn(default_max_stack().0, move || dap::serve(max_depth));
+    }
+
+    // `mussel kernel` runs the cells of Jupyter notebooks.
+    if arguments.get(1).is_some_and(|command| command == "kernel") {
+        let KernelArgs { connection_file, max_depth, check_types, backend } = parse_command(&arguments);
+        return spawn(default_max_stack().0, move || kernel::serve(&connection_file, check_types, max_depth, backend));
+    }
+
+    // `mussel test` runs the tests found in the given paths.
+    if arguments.get(1).is_some_and(|command| command == "test") {
+        let TestArgs { mut paths, max_depth, check_types, backend, color } = parse_command(&arguments);
+        color::set(color);
+        if paths.is_empty() {
+            paths.push(".".to_string());
+        }
+        return spawn(default_max_stack().0, move || testing::run(&paths, check_types, max_depth, backend));
+    }
+
+    // `mussel bench` times the benchmarks found in the given paths.
+    if arguments.get(1).is_some_and(|command| command == "bench") {
+        let BenchArgs { mut paths, warmup, runs, max_depth, check_types, backend, color } = parse_command(&arguments);
+        color::set(color);
+        if paths.is_empty() {
+            paths.push(".".to_string());
+        }
+        let options = bench::Options { warmup, runs, check_types, max_depth, backend };
+        return spawn(default_max_stack().0, move || bench::run(&paths, &options));
+    }
+
+    // `mussel doc` writes the documentation of the script instead of running it.
+    if arguments.get(1).is_some_and(|command| command == "doc") {
+        let DocArgs { file, format, output, color } = parse_command(&arguments);
+        color::set(color);
+        return Ok(doc::document(&file, format, output.as_deref().map(Path::new)));
+    }
+
+    // `mussel emit-js` translates the script to JavaScript instead of running it.
+    if arguments.get(1).is_some_and(|command| command == "emit-js") {
+        let EmitArgs { file, output, color } = parse_command(&arguments);
+        color::set(color);
+        return Ok(javascript::translate(&file, output.as_deref().map(Path::new)));
+    }
+
+    // `mussel emit-rs` translates the script to a Rust program instead of running it.
+    if arguments.get(1).is_some_and(|command| command == "emit-rs") {
+        let EmitRustArgs { file, output, color } = parse_command(&arguments);
+        color::set(color);
+        let output = output.unwrap_or_else(|| {
+            Path::new(&file).file_stem().map_or("a.out".to_string(), |stem| stem.to_string_lossy().into_owned())
+        });
+        return Ok(rust::translate(&file, Path::new(&output)));
+    }
+
     // Parse command-line arguments from the environment and destructure to extract `file`.
-    let Args { file } = argh::from_env();
+    let Args {
+        script,
+        max_depth,
+        max_stack,
+        check_types,
+        backend,
+        max_steps,
+        max_memory,
+        timeout,
+        dump_tokens,
+        dump_ast,
+        watch,
+        debug,
+        trace,
+        profile,
+        profile_stacks,
+        time,
+        warn,
+        allow,
+        deny_warnings,
+        color,
+    } = argh::from_env();
+    color::set(color);
+    limits::set(Limits { max_steps, max_memory, timeout });
+    if !warnings::set(&warn, &allow, deny_warnings) {
+        return Ok(ExitCode::FAILURE);
+    }
+    let profile = profile || profile_stacks.is_some();
 
-    // Create a new `FileSet` instance to manage files.
-    let mut files = FileSet::new();
+    // Without a script, the code is typed in an interactive session.
+    let mut script = script.into_iter();
+    let Some(file) = script.next() else {
+        if dump_tokens || dump_ast.is_some() || watch || debug || trace.is_some() || profile || time {
+            let option = if dump_tokens {
+                "--dump-tokens"
+            } else if watch {
+                "--watch"
+            } else if debug {
+                "--debug"
+            } else if trace.is_some() {
+                "--trace"
+            } else if profile {
+                "--profile"
+            } else if time {
+                "--time"
+            } else {
+                "--dump-ast"
+            };
+            eprintln!("error: {option} needs a script\nRun mussel --help for more information.");
+            return Ok(ExitCode::FAILURE);
+        }
+        return spawn(max_stack.0, move || repl::run(check_types, max_depth, backend));
+    };
+    let arguments = script.collect();
 
-    let parsed = match parse(&mut files, file) {
-        Ok(file_id) => file_id,
-        Err(error) => {
-            let reporter = Reporter::new(files);
-            reporter.report(error);
-            return Ok(());
+    // `--watch` runs the script in another `mussel`, given the same arguments without `--watch`.
+    if watch {
+        let mut arguments: Vec<String> = std::env::args().skip(1).collect();
+        if let Some(option) = arguments.iter().position(|argument| argument == "--watch") {
+            arguments.remove(option);
+        }
+        return Ok(watch::watch(&file, &arguments));
+    }
+
+    // `--dump-tokens` and `--dump-ast` only show how the script is lexed or parsed, to debug the grammar.
+    if dump_tokens || dump_ast.is_some() {
+        let mut loader = Loader::new(false);
+        let dump = match dump_ast {
+            Some(format) if !dump_tokens => loader.dump_ast(&file, format),
+            _ => loader.dump_tokens(&file),
+        };
+        return match dump {
+            Ok(dump) => {
+                print!("{dump}");
+                Ok(ExitCode::SUCCESS)
+            }
+            Err(error) => {
+                loader.report(error);
+                Ok(ExitCode::FAILURE)
+            }
+        };
+    }
+
+    // `--debug` and `--trace` follow the expressions the script evaluates, which only the
+    // tree-walker can do.
+    let mode = match (debug, trace, profile) {
+        (false, None, false) => Mode::Run(backend),
+        (true, None, false) | (false, Some(_), false) if matches!(backend, Backend::Vm) => {
+            let option = if debug { "--debug" } else { "--trace" };
+            eprintln!("error: {option} runs the script with the tree-walker, it can't be used with --backend vm");
+            return Ok(ExitCode::FAILURE);
+        }
+        (true, None, false) => Mode::Debug,
+        (false, Some(trace), false) => Mode::Trace(trace),
+        (false, None, true) => Mode::Profile(backend, profile_stacks),
+        _ => {
+            eprintln!("error: only one of --debug, --trace and --profile can be used at once");
+            return Ok(ExitCode::FAILURE);
         }
     };
 
-    // Pass the parsed expressions to the interpreter to evaluate them.
-    interpreter::interpreter(parsed);
+    // The loader is created in the thread running the script, see `spawn`.
+    spawn(max_stack.0, move || timing::report(time, || run(Loader::new(check_types), file, max_depth, mode, arguments)))
+}
+
+// The default of `--max-depth`, given by the `MUSSEL_MAX_DEPTH` environment variable when it is set.
+fn default_max_depth() -> usize {
+    from_env("MUSSEL_MAX_DEPTH", context::DEFAULT_MAX_DEPTH)
+}
 
-    // Return success.
-    Ok(())
+// The default of `--max-stack`, given by the `MUSSEL_MAX_STACK` environment variable when it is set.
+fn default_max_stack() -> StackSize {
+    from_env("MUSSEL_MAX_STACK", StackSize(INTERPRETER_STACK_SIZE))
 }
 
-fn parse<P: AsRef<Path>>(files: &mut FileSet, file: P) -> Result<Vec<Expr>, Box<dyn LError>> {
+// The size of the stack of the interpreter given by `--max-stack`, at least `limits::MIN_STACK`.
+struct StackSize(usize);
 
-    // Load the file specified in the command-line arguments into the `FileSet`.
-    // If loading fails, print the error using the `Reporter` and return early.
-    let file = load_file(files, &file).map_err(|e| error::boxed(e))?;
+impl FromStr for StackSize {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let Memory(size) = text.parse()?;
+        if size < limits::MIN_STACK {
+            let minimum = Memory(limits::MIN_STACK);
+            return Err(format!("the stack of the interpreter needs at least {minimum}, `{text}` is smaller"));
+        }
+        Ok(StackSize(size))
+    }
+}
 
-    let tokens = lexer::lex(files, file).map_err(|e| error::boxed(e))?;
+// Reads the value of an environment variable, or returns `default` when it isn't set. Exits, telling
+// so, when the value is invalid.
+fn from_env<T: FromStr<Err: Display>>(name: &str, default: T) -> T {
+    let Ok(value) = std::env::var(name) else {
+        return default;
+    };
+    value.parse().unwrap_or_else(|message| {
+        eprintln!("error: invalid {name} `{value}`: {message}");
+        std::process::exit(1)
+    })
+}
 
-    let expressions= parser::parser(file, &tokens)?;
+// Parses the arguments following a command like `build`, exiting like `argh::from_env` for
+// `--help` or mistakes.
+fn parse_command<T: FromArgs>(arguments: &[String]) -> T {
+    let command = format!("{} {}", arguments[0], arguments[1]);
+    let options: Vec<&str> = arguments[2..].iter().map(String::as_str).collect();
+    match T::from_args(&[&command], &options) {
+        Ok(args) => args,
+        Err(exit) => match exit.status {
+            Ok(()) => {
+                println!("{}", exit.output);
+                std::process::exit(0)
+            }
+            Err(()) => {
+                eprintln!("{}\nRun {command} --help for more information.", exit.output);
+                std::process::exit(1)
+            }
+        },
+    }
+}
 
-    Expr::from_parser(&files, file, expressions).map_err(|e| error::boxed(e))
+// Parses and runs a script in a thread with a stack of the given size, large enough for deep
+// recursion, and returns its exit status.
+// The values of a running script can't be sent between threads, so the script is parsed in the
+// thread that runs it.
+fn spawn(stack: usize, script: impl FnOnce() -> ExitCode + Send + 'static) -> Result<ExitCode> {
+    let interpreter = std::thread::Builder::new().stack_size(stack).spawn(move || {
+        limits::set_stack(stack);
+        script()
+    })?;
+    // Panics that aren't errors of the script have already been printed and keep unwinding.
+    match interpreter.join() {
+        Ok(status) => Ok(status),
+        Err(payload) => std::panic::resume_unwind(payload),
+    }
 }
 
+// How a script is run.
+enum Mode {
+    Run(Backend),
+    Debug,        // Paused for the commands typed in the terminal, see `--debug`.
+    Trace(Trace), // Writing the expressions it evaluates, see `--trace`.
+    Profile(Backend, Option<PathBuf>), // Measuring its functions, see `--profile` and `--profile-stacks`.
+}
 
-fn load_file<P: AsRef<Path>>(files: &mut FileSet, path: P) -> Result<FileIdentifier, FileError> {
-    let path = path.as_ref();
-    let input = std::fs::read_to_string(path.to_path_buf());
-    let path_qualified = path::absolute(path.to_path_buf()).unwrap_or(path.to_path_buf());
-    match input {
-        Ok(content) => {
-            let file_id = files.add_file(path_qualified, content);
-            Ok(file_id)
-        }
-        Err(err) => {
-            Err(FileError::new(
-                path_qualified,
-                format!("Failed to read file: {}", err),
-            ))
+// Parses a script and runs it, reporting the errors found along the way.
+// The loader reads the script and the files it includes. The script is given the arguments.
+// Returns the exit status of the script.
+fn run(
+    mut loader: Loader,
+    file: impl AsRef<Path>,
+    max_depth: usize,
+    mode: Mode,
+    arguments: Vec<String>,
+) -> ExitCode {
+    // The script is the first file running, so the files it includes can't include it again.
+    loader.enter(file.as_ref());
+    let parsed = match loader.load(file) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            loader.report(error);
+            return ExitCode::FAILURE;
         }
-    }
+    };
 
+    // Pass the parsed expressions to the interpreter to evaluate them.
+    timing::measure(Phase::Evaluation, || match mode {
+        Mode::Run(backend) => interpreter::interpreter(parsed, loader, max_depth, backend, arguments),
+        Mode::Debug => debugger::debug(parsed, loader, max_depth, arguments),
+        Mode::Trace(trace) => interpreter::trace(parsed, loader, max_depth, arguments, trace),
+        Mode::Profile(backend, stacks) => interpreter::profile(parsed, loader, max_depth, backend, arguments, stacks),
+    })
 }
@@ -0,0 +1,167 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The bindings for Node.js, to embed the interpreter in a Node or Electron application, built as a
+// native addon with the `node` feature:
+//   cargo build --lib --release --features node
+//   cp target/release/libmussel.so mussel.node
+// Only the library is built with it: the symbols of Node it uses are missing from the `mussel`
+// command, which can't be linked with them.
+// The addon gives the `Interpreter` class of `embed` to JavaScript:
+//   const { Interpreter } = require("./mussel.node");
+//   const interpreter = new Interpreter();
+//   interpreter.register("double", (n) => n * 2);
+//   interpreter.evalSource("double(21)"); // 42
+// The values are converted as they cross: nil is `null`, the integers and floats are numbers, the
+// arrays are arrays and the struct instances are objects with their fields. The other values, like
+// the functions, can't be given to JavaScript and are given as the text `println` shows.
+// The code runs on the thread of JavaScript, so the functions registered are called right away, and
+// on its stack, which is smaller than the one of the `mussel` command: the calls nested too deep
+// for it stop with the recursion error, see `Options`.
+
+use napi::{Env, JsFunction, JsObject, JsString, JsUnknown, Ref, ValueType};
+use napi_derive::napi;
+use crate::context;
+use crate::embed::{Backend, Interpreter, Value};
+
+// The stack the code can use below the call of `evalSource`, by default. The worker threads of
+// Node have a stack of 4 MiB, part of which is used by JavaScript.
+const STACK_SIZE: u32 = 2 * 1024 * 1024;
+
+/// The options of a new interpreter, `{ maxDepth, stackSize }`, each one optional.
+#[napi(object)]
+pub struct Options {
+    /// The number of nested calls allowed, see `--max-depth`.
+    pub max_depth: Option<u32>,
+    /// The bytes of the stack the code can use, for the threads started with a larger stack.
+    pub stack_size: Option<u32>,
+}
+
+/// An interpreter, the `Interpreter` class of JavaScript, see `embed::Interpreter`.
+#[napi(js_name = "Interpreter")]
+pub struct NodeInterpreter {
+    interpreter: Interpreter,
+}
+
+#[napi]
+impl NodeInterpreter {
+    #[napi(constructor)]
+    pub fn new(options: Option<Options>) -> Self {
+        let max_depth = options.as_ref().and_then(|options| options.max_depth);
+        let max_depth = max_depth.map_or(context::DEFAULT_MAX_DEPTH, |max_depth| max_depth as usize);
+        let stack_size = options.and_then(|options| options.stack_size).unwrap_or(STACK_SIZE);
+        let mut interpreter = Interpreter::with(max_depth, Backend::Tree);
+        interpreter.set_stack_size(stack_size as usize);
+        NodeInterpreter { interpreter }
+    }
+
+    // Runs code in the global scope of the interpreter and returns the value of its last statement.
+    // An error stopping the code, or `exit`, is thrown as an `Error` with its message.
+    #[napi]
+    pub fn eval_source(&mut self, env: Env, source: String) -> napi::Result<JsUnknown> {
+        match self.interpreter.eval_source(&source) {
+            Ok(value) => to_js(&env, &value),
+            Err(error) => Err(napi::Error::from_reason(error.to_string())),
+        }
+    }
+
+    // Declares a JavaScript function in the global scope, for the code to call with any number of
+    // arguments. An exception it throws stops the code at the call, with its message.
+    #[napi]
+    pub fn register(&mut self, env: Env, name: String, function: JsFunction) -> napi::Result<()> {
+        let callback = Callback { env, function: env.create_reference(function)? };
+        self.interpreter.register(&name, move |arguments| {
+            let env = callback.env;
+            let function: JsFunction = env.get_reference_value(&callback.function).map_err(|error| error.reason)?;
+            let arguments = arguments.iter().map(|argument| to_js(&env, argument)).collect::<napi::Result<Vec<_>>>();
+            let result = arguments.and_then(|arguments| function.call(None, &arguments));
+            result.and_then(from_js).map_err(|error| error.reason)
+        });
+        Ok(())
+    }
+
+    // Returns the value of a variable of the global scope, `undefined` when it isn't declared.
+    #[napi]
+    pub fn get(&self, env: Env, name: String) -> napi::Result<JsUnknown> {
+        match self.interpreter.get(&name) {
+            Some(value) => to_js(&env, &value),
+            None => Ok(env.get_undefined()?.into_unknown()),
+        }
+    }
+
+    // Declares a variable in the global scope, or gives it a new value, for the code to read.
+    #[napi]
+    pub fn set(&mut self, name: String, value: JsUnknown) -> napi::Result<()> {
+        self.interpreter.set(&name, from_js(value)?);
+        Ok(())
+    }
+}
+
+// A function registered, kept alive by a reference as long as the interpreter can call it.
+struct Callback {
+    env: Env,
+    function: Ref<()>,
+}
+
+impl Drop for Callback {
+    fn drop(&mut self) {
+        let _ = self.function.unref(self.env);
+    }
+}
+
+// Converts a value given by the code to JavaScript.
+fn to_js(env: &Env, value: &Value) -> napi::Result<JsUnknown> {
+    if value.is_nil() {
+        return Ok(env.get_null()?.into_unknown());
+    }
+    if let Some(number) = value.as_int() {
+        return Ok(env.create_int64(number)?.into_unknown());
+    }
+    if let Some(number) = value.as_float() {
+        return Ok(env.create_double(number)?.into_unknown());
+    }
+    if let Some(boolean) = value.as_bool() {
+        return Ok(env.get_boolean(boolean)?.into_unknown());
+    }
+    if let Some(elements) = value.as_array() {
+        let mut array = env.create_array_with_length(elements.len())?;
+        for (index, element) in elements.iter().enumerate() {
+            array.set_element(index as u32, to_js(env, element)?)?;
+        }
+        return Ok(array.into_unknown());
+    }
+    if let Some(fields) = value.fields() {
+        let mut object = env.create_object()?;
+        for (name, field) in &fields {
+            object.set_named_property(name, to_js(env, field)?)?;
+        }
+        return Ok(object.into_unknown());
+    }
+    match value.as_str() {
+        Some(string) => Ok(env.create_string(string)?.into_unknown()),
+        None => Ok(env.create_string(&value.to_string())?.into_unknown()),
+    }
+}
+
+// Converts a value given by JavaScript to a value of the code. The numbers without a fractional part
+// are integers, the others floats.
+fn from_js(value: JsUnknown) -> napi::Result<Value> {
+    match value.get_type()? {
+        ValueType::Undefined | ValueType::Null => Ok(Value::NIL),
+        ValueType::Boolean => Ok(value.coerce_to_bool()?.get_value()?.into()),
+        ValueType::Number => {
+            let number = value.coerce_to_number()?.get_double()?;
+            match number.fract() == 0.0 && number.abs() < i64::MAX as f64 {
+                true => Ok((number as i64).into()),
+                false => Ok(number.into()),
+            }
+        }
+        ValueType::String => Ok(unsafe { value.cast::<JsString>() }.into_utf8()?.into_owned()?.into()),
+        ValueType::Object if value.is_array()? => {
+            let array = unsafe { value.cast::<JsObject>() };
+            let elements = (0..array.get_array_length()?).map(|index| from_js(array.get_element(index)?));
+            Ok(elements.collect::<napi::Result<Vec<Value>>>()?.into())
+        }
+        kind => Err(napi::Error::from_reason(format!("cannot give a JavaScript {kind} to mussel"))),
+    }
+}
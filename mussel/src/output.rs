@@ -0,0 +1,60 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Where a script writes: the standard output and error, or a string when it runs in the
+// playground, which has neither, or the editor when it runs in the debugger, which uses the
+// standard output to talk with the editor.
+
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    // The text written so far, while it is being captured.
+    static CAPTURED: RefCell<Option<String>> = const { RefCell::new(None) };
+    // What is given the text as it is written, while it is forwarded.
+    static FORWARDED: Cell<Option<fn(&str)>> = const { Cell::new(None) };
+}
+
+// Runs `run` and returns what it wrote, printed values and diagnostics alike, instead of writing it
+// to the standard output and error.
+pub fn capture(run: impl FnOnce()) -> String {
+    CAPTURED.with_borrow_mut(|captured| *captured = Some(String::new()));
+    run();
+    CAPTURED.with_borrow_mut(Option::take).unwrap_or_default()
+}
+
+// Runs `run`, giving what it writes, printed values and diagnostics alike, to `forward` as it is
+// written, instead of writing it to the standard output and error.
+pub fn forward<T>(forward: fn(&str), run: impl FnOnce() -> T) -> T {
+    FORWARDED.set(Some(forward));
+    let result = run();
+    FORWARDED.set(None);
+    result
+}
+
+// Tells whether the output is being captured or forwarded.
+pub fn capturing() -> bool {
+    CAPTURED.with_borrow(Option::is_some) || FORWARDED.get().is_some()
+}
+
+// Writes text to the standard output, or to the text being captured, or to where it is forwarded.
+pub fn print(text: &str) {
+    if !divert(text) {
+        print!("{text}");
+    }
+}
+
+// Adds text to the text being captured or gives it to where it is forwarded, and tells whether it
+// was.
+fn divert(text: &str) -> bool {
+    let captured = CAPTURED.with_borrow_mut(|captured| captured.as_mut().map(|output| output.push_str(text)));
+    if captured.is_some() {
+        return true;
+    }
+    match FORWARDED.get() {
+        Some(forward) => {
+            forward(text);
+            true
+        }
+        None => false,
+    }
+}
@@ -1,86 +1,132 @@
 // Copyright (c) 2025 Francesco Giannice
 // Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
 
-use color_eyre::{Help};
+//! This is the main parser for the language.
+//! It takes a slice of tokens and returns an AST.
+//! A syntax error doesn't stop it: it skips to the next statement and goes on, so all the syntax
+//! errors of a file are reported at once.
+//!
+//!
+//!
+//! # Grammar
+//!
+//! ```text
+//! // Entry point
+//! // A statement can end with a ';', needed only to put several statements on one line.
+//! unit ::= statement*
+//! statement ::= expr ';'?
+//!
+//! expr ::= include
+//!     | return
+//!     | throw
+//!     | try
+//!     | assert
+//!     | function
+//!     | for
+//!     | until
+//!     | loop
+//!     | labeled
+//!     | break
+//!     | continue
+//!     | if
+//!     | let
+//!     | const
+//!     | struct
+//!     | impl
+//!     | trait
+//!     | enum
+//!     | pipelineExpression
+//!
+//! include ::= 'include' (id | string) ('as' id)?
+//! return ::= 'return' expr
+//! throw ::= 'throw' expr
+//! try ::= 'try' block 'catch' id block
+//! assert ::= 'assert' '(' expr (',' expr)? ')'
+//! function ::= docComment* 'fn' id '(' (parameter (',' parameter)*)? ')' ('->' type)? block
+//! parameter ::= id (':' type)? ('=' expr)?
+//! type ::= id | nil
+//! for ::= 'for' binding 'in' expr block
+//! until ::= 'until' expr block
+//! loop ::= 'loop' block
+//! labeled ::= id ':' (for | until | loop)
+//! break ::= 'break' expr?
+//! continue ::= 'continue' id?
+//! if ::= 'if' expr block ('else' (if | block))?
+//! let ::= 'let' binding '=' expr
+//! const ::= 'const' binding '=' expr
+//! binding ::= id | '_' | '[' (binding (',' binding)*)? ']' | '(' (binding (',' binding)*)? ')'
+//! struct ::= docComment* 'struct' id '{' (id (',' id)*)? ','? '}'
+//! impl ::= 'impl' (id 'for')? id '{' function* '}'
+//! trait ::= 'trait' id '{' (id (',' id)*)? ','? '}'
+//! enum ::= 'enum' id '{' (variant (',' variant)*)? ','? '}'
+//! variant ::= id ('(' (id (',' id)*)? ')')?
+//!
+//! block ::= '{' statement* '}'
+//!
+//! // Math precedence
+//! // left-associative: x |> f |> g == g(f(x))
+//! pipelineExpression: conditionalOrExpression ('|>' conditionalOrExpression)*;
+//! conditionalOrExpression: conditionalAndExpression ('or' conditionalOrExpression)?;
+//! conditionalAndExpression: equalityExpression ('and' conditionalAndExpression)?;
+//! // left-associative: 10 - 4 - 3 == (10 - 4) - 3
+//! equalityExpression: relationalExpression (('==' | '!=') relationalExpression)*;
+//! relationalExpression: rangeExpression (('<' | '>' | '<=' | '>=') rangeExpression)*;
+//! rangeExpression: additiveExpression (('..' | '..=') additiveExpression)?;
+//! additiveExpression: multiplicativeExpression (('+' | '-') multiplicativeExpression)*;
+//! multiplicativeExpression: unaryExpression (('*' | '/') unaryExpression)*;
+//! unaryExpression: ('-' | 'not')? powerExpression;
+//! // right-associative: 2 ** 3 ** 2 == 2 ** (3 ** 2)
+//! powerExpression: factor ('**' unaryExpression)?;
+//!
+//! factor: object postFix* ('=' expr)?;
+//! // calls, array indexing and field access
+//! // the '(' of a call and the '[' of an index must be on the line of what they follow: a line
+//! // starting with '(' or '[' starts a new statement
+//! postFix: '(' expressionList ')' | '[' expr ']' | '.' id;
+//! expressionList: (expr (',' expr)*)?;
+//!
+//! // lowest expression
+//! object: array | closure | match | string | char | integer | float | bool | nil | path | id | '(' expr ')'
+//!
+//! array ::= '[' (expr (',' expr)*)? ']' | '[' expr 'for' binding 'in' expr ('if' expr)? ']'
+//! closure ::= '|' (parameter (',' parameter)*)? '|' ('->' type)? block
+//! match ::= 'match' expr '{' (pattern '=>' (block | expr) ','?)* '}'
+//! pattern ::= '_' | string | char | '-'? integer | '-'? float | bool | nil | path | id
+//!     | '[' (arrayPattern (',' arrayPattern)*)? ']'
+//!     | id '{' (id (':' pattern)? (',' id (':' pattern)?)*)? ','? '}'
+//!     | path '(' (pattern (',' pattern)*)? ')'
+//! arrayPattern ::= pattern | '...' id?
+//! path ::= id '::' id
+//!
+//! # literals
+//! id ::= 'id'
+//! string ::= 'string'
+//! char ::= 'char'
+//! integer ::= 'integer'
+//! float ::= 'float'
+//! bool ::= 'true' | 'false'
+//! nil ::= 'nil'
+//! ```
+
 use nom::multi::{many0, separated_list0};
-use nom::{Parser};
 use nom::branch::alt;
 use nom::combinator::{cut, map, opt};
-use nom::sequence::{delimited, tuple};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 use nom_supreme::final_parser::{final_parser, ExtractContext};
+use std::ops::Range;
 use crate::error;
 use crate::error::{FileIdentifier, LError};
-use crate::expr::{BinOp, Operator};
+use crate::expr::{BinOp, LogicOp, Operator};
 use crate::lexer::{Token, TokenRecord};
 
 
 
-/// This is the main parser for the language.
-/// It takes a slice of tokens and returns an AST.
-///
-///
-///
-/// # Grammar
-///
-/// ```
-/// // Entry point
-/// unit ::= expr*
-///
-/// expr ::= include
-///     | return
-///     | function
-///     | for
-///     | until
-///     | if
-///     | let
-///     | conditionalOrExpression
-///
-/// include ::= 'include' id
-/// return ::= 'return' expr
-/// function ::= 'fn' id '(' (id (',' id)*)? ')' block
-/// for ::= 'for' id 'in' expr block
-/// until ::= 'until' expr block
-/// if ::= 'if' expr block ('else' block)?
-/// let ::= 'let' id '=' expr
-///
-/// block ::= '{' expr* '}'
-///
-/// // Math precedence
-/// conditionalOrExpression: conditionalAndExpression ('||' conditionalOrExpression)?;
-/// conditionalAndExpression: equalityExpression ('&&' conditionalAndExpression)?;
-/// equalityExpression: relationalExpression (('==' | '!=') equalityExpression)?;
-/// relationalExpression: additiveExpression (('<' | '>' | '<=' | '>=') relationalExpression)?;
-/// additiveExpression: multiplicativeExpression (('+' | '-') additiveExpression)?;
-/// multiplicativeExpression: unaryExpression (('*' | '/') multiplicativeExpression)?;
-/// unaryExpression: ('-' | '!')? factor;
-///
-/// factor: object postFix* ('=' expr)?;
-/// // calls and array indexing
-/// postFix: '(' expressionList ')' | '[' expr ']';
-/// expressionList: (expr (',' expr)*)?;
-///
-/// // lowest expression
-/// object: array | closure | string | integer | float | bool | id | '(' expr ')'
-///
-/// array ::= '[' (expr (',' expr)*)? ']'
-/// closure ::= '|' (id (',' id)*)? '|' block
-///
-/// # literals
-/// id ::= 'id'
-/// string ::= 'string'
-/// integer ::= 'integer'
-/// float ::= 'float'
-/// bool ::= 'true' | 'false'
-/// ```
-
-
+/// The most tokens listed as expected in an error.
+const MAX_EXPECTED: usize = 5;
 
 /// Defines a custom Result type with the input of TokenRecords and the custom ErrorType
 type IResult<'a, O> = nom::IResult<&'a [TokenRecord], O, ParseError>;
 
-/// Used to define where an Expression was defined. Currently not used.
-type Range = core::ops::Range<usize>;
-
 
 /// Custom Error Type for better error reporting
 /// `self.get_offset` returns where the error occurred in the input stream (character index)
@@ -101,8 +147,7 @@ pub(crate) enum ParseError {
     // nom::error::ErrorKind is the standard nom error, needed for ParseError
     Internal { record: TokenRecord, kind: nom::error::ErrorKind },
 
-    // Combining multiple errors
-    // fixme: This might not be useful for the user in the future
+    // Combining multiple errors, the alternatives tried at the same place, reported as one
     List(Vec<ParseError>)
 }
 
@@ -153,47 +198,31 @@ impl<'a> nom::error::ParseError<&'a [TokenRecord]> for ParseError {
         ParseError::List(vec![self, other])
     }
 }
-impl ExtractContext<&[TokenRecord], ParseError> for ParseError {
-    fn extract_context(self, _: &[TokenRecord]) -> ParseError {
-        self
-    }
-}
-
-pub fn offset_to_line_column(text: &str, offset: usize) -> Option<(usize, usize)> {
-    if offset > text.len() {
-        return None;
-    }
-
-    let mut current_offset = 0;
-    for (line_number, line) in text.lines().enumerate() {
-        let line_len = line.len();
-
-        if offset <= current_offset + line_len {
-            let column = offset - current_offset + 1;
-            return Some((line_number + 1, column));
+impl ParseError {
+    /// Where the error occurred, the furthest position of a list of errors, or the end of the input.
+    fn offset(&self) -> usize {
+        match self {
+            ParseError::UnexpectedToken { found, .. } | ParseError::UnexpectedEnd { found } => found.offset,
+            ParseError::Internal { record, .. } => record.offset,
+            ParseError::Eof => usize::MAX,
+            ParseError::List(list) => list.iter().map(ParseError::offset).max().unwrap_or(usize::MAX),
         }
-
-        // Add 1 for the '\n' character that .lines() strips
-        current_offset += line_len + 1;
     }
+}
 
-    // If offset is at the very end (after the last line)
-    if offset == text.len() {
-        if let Some(last_line_number) = text.lines().count().checked_sub(1) {
-            return Some((last_line_number + 1, text.lines().last().unwrap_or("").len() + 1));
-        }
+impl ExtractContext<&[TokenRecord], ParseError> for ParseError {
+    fn extract_context(self, _: &[TokenRecord]) -> ParseError {
+        self
     }
-
-    None
 }
 
-
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum BinaryOperator {
     Add,
     Subtract,
     Multiply,
     Divide,
+    Power,
     And,
     Or,
     Equal,
@@ -204,21 +233,22 @@ pub(crate) enum BinaryOperator {
     GreaterThanOrEqual,
 }
 
-impl Into<Option<BinOp>> for BinaryOperator {
-    fn into(self) -> Option<BinOp> {
-        match self {
+impl From<BinaryOperator> for Option<BinOp> {
+    fn from(val: BinaryOperator) -> Self {
+        match val {
             BinaryOperator::Add => Some(BinOp::Add),
             BinaryOperator::Subtract => Some(BinOp::Sub),
             BinaryOperator::Multiply => Some(BinOp::Mul),
             BinaryOperator::Divide => Some(BinOp::Div),
+            BinaryOperator::Power => Some(BinOp::Pow),
             _ => None,
         }
     }
 }
 
-impl Into<Option<Operator>> for BinaryOperator {
-    fn into(self) -> Option<Operator> {
-        match self {
+impl From<BinaryOperator> for Option<Operator> {
+    fn from(val: BinaryOperator) -> Self {
+        match val {
             BinaryOperator::Equal => Some(Operator::Equal),
             BinaryOperator::NotEqual => Some(Operator::NotEqual),
             BinaryOperator::LessThan => Some(Operator::LessThan),
@@ -230,6 +260,16 @@ impl Into<Option<Operator>> for BinaryOperator {
     }
 }
 
+impl From<BinaryOperator> for Option<LogicOp> {
+    fn from(val: BinaryOperator) -> Self {
+        match val {
+            BinaryOperator::And => Some(LogicOp::And),
+            BinaryOperator::Or => Some(LogicOp::Or),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum UnaryOperator {
     Negate,
@@ -238,38 +278,161 @@ pub(crate) enum UnaryOperator {
 
 #[derive(Debug)]
 pub(crate) enum Expression {
-    Include { id: TokenRecord},
+    Include { id: TokenRecord, alias: Option<TokenRecord> },
     Return { expr: Box<Expression> },
-    Function { id: TokenRecord, args: Vec<TokenRecord>, block: Vec<Expression> },
-    For { id: TokenRecord, expr: Box<Expression>, block: Vec<Expression> },
-    Until { expr: Box<Expression>, block: Vec<Expression> },
-    If { expr: Box<Expression>, block: Vec<Expression>, else_block: Option<Vec<Expression>> },
-    Let { id: TokenRecord, expr: Box<Expression> },
+    Throw { keyword: TokenRecord, expr: Box<Expression> },
+    Try { block: Vec<Expression>, id: TokenRecord, catch_block: Vec<Expression> },
+    Assert { region: TokenRecord, expr: Box<Expression>, message: Option<Box<Expression>> },
+    Function {
+        // The `///` comments before the function, for `mussel doc`.
+        doc: Vec<TokenRecord>,
+        id: TokenRecord,
+        args: Vec<ParameterExpression>,
+        return_type: Option<TokenRecord>,
+        block: Vec<Expression>,
+    },
+    For { binding: BindingExpression, keyword: TokenRecord, expr: Box<Expression>, block: Vec<Expression> },
+    Until { keyword: TokenRecord, expr: Box<Expression>, block: Vec<Expression> },
+    Loop { block: Vec<Expression> },
+    Labeled { label: TokenRecord, expr: Box<Expression> },
+    Break { keyword: TokenRecord, expr: Option<Box<Expression>> },
+    Continue { keyword: TokenRecord, label: Option<TokenRecord> },
+    If { keyword: TokenRecord, expr: Box<Expression>, block: Vec<Expression>, else_block: Option<Vec<Expression>> },
+    Let { keyword: TokenRecord, binding: BindingExpression, expr: Box<Expression> },
+    Const { keyword: TokenRecord, binding: BindingExpression, expr: Box<Expression> },
+    Struct { doc: Vec<TokenRecord>, id: TokenRecord, fields: Vec<TokenRecord> },
+    Impl { region: TokenRecord, id: TokenRecord, trait_id: Option<TokenRecord>, methods: Vec<Expression> },
+    Trait { id: TokenRecord, methods: Vec<TokenRecord> },
+    Enum { id: TokenRecord, variants: Vec<(TokenRecord, Vec<TokenRecord>)> },
 
     Binary { left: Box<Expression>, operator: (BinaryOperator, TokenRecord), right: Box<Expression> },
     Unary { operator: (UnaryOperator, TokenRecord), expr: Box<Expression> },
+    Range { start: Box<Expression>, operator: TokenRecord, end: Box<Expression>, inclusive: bool },
+    Pipeline { value: Box<Expression>, operator: TokenRecord, function: Box<Expression> },
 
     Assignment { region: TokenRecord, left: Box<Expression>, right: Box<Expression> },
 
     Identifier(TokenRecord),
+    Path { id: TokenRecord, item: TokenRecord },
     String(TokenRecord),
+    Char(TokenRecord),
     Integer(TokenRecord),
     Float(TokenRecord),
     Bool(TokenRecord),
+    Nil,
     Array(Vec<Expression>),
-    Closure { args: Vec<TokenRecord>, block: Vec<Expression> },
+    Comprehension { expr: Box<Expression>, binding: BindingExpression, keyword: TokenRecord, iterable: Box<Expression>, condition: Option<Box<Expression>> },
+    Closure { args: Vec<ParameterExpression>, return_type: Option<TokenRecord>, block: Vec<Expression> },
+    Match { expr: Box<Expression>, arms: Vec<(PatternExpression, Vec<Expression>)> },
+
+    Call { left: Box<Expression>, args: Vec<Expression>, region: TokenRecord },
+    Index { left: Box<Expression>, index: Box<Expression>, region: TokenRecord },
+    Field { left: Box<Expression>, field: TokenRecord },
+}
+
+impl Expression {
+    /// Returns the part of the file the expression was written in, when its tokens are known.
+    pub(crate) fn span(&self) -> Option<Range<usize>> {
+        fn merge(a: Option<Range<usize>>, b: Option<Range<usize>>) -> Option<Range<usize>> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.start.min(b.start)..a.end.max(b.end)),
+                (a, b) => a.or(b),
+            }
+        }
+        match self {
+            Expression::Identifier(token)
+            | Expression::String(token)
+            | Expression::Char(token)
+            | Expression::Integer(token)
+            | Expression::Float(token)
+            | Expression::Bool(token) => Some(token.range()),
+            Expression::Path { id, item } => Some(id.range().start..item.range().end),
+            Expression::Binary { left, operator: (_, token), right } => {
+                merge(merge(left.span(), Some(token.range())), right.span())
+            }
+            Expression::Unary { operator: (_, token), expr } => merge(Some(token.range()), expr.span()),
+            Expression::Range { start, operator, end, .. } => {
+                merge(merge(start.span(), Some(operator.range())), end.span())
+            }
+            Expression::Pipeline { value, operator, function } => {
+                merge(merge(value.span(), Some(operator.range())), function.span())
+            }
+            Expression::Assignment { region, left, right } => {
+                merge(merge(left.span(), Some(region.range())), right.span())
+            }
+            Expression::Call { left, region, .. } | Expression::Index { left, region, .. } => {
+                merge(left.span(), Some(region.range()))
+            }
+            Expression::Field { left, field } => merge(left.span(), Some(field.range())),
+            Expression::Array(items) => items.iter().fold(None, |range, item| merge(range, item.span())),
+            Expression::Assert { region, .. } => Some(region.range()),
+            Expression::Comprehension { expr, iterable, condition, .. } => {
+                merge(merge(expr.span(), iterable.span()), condition.as_deref().and_then(Expression::span))
+            }
+            _ => None,
+        }
+    }
+}
+
+
+/// A parameter of a function or closure, with its optional type and default value.
+#[derive(Debug)]
+pub(crate) struct ParameterExpression {
+    pub id: TokenRecord,
+    pub annotation: Option<TokenRecord>,
+    pub default: Option<Expression>,
+}
+
+/// The variables declared by a `let` statement or a `for` loop.
+#[derive(Debug)]
+pub(crate) enum BindingExpression {
+    // A single variable, bound to the whole value
+    Identifier(TokenRecord),
+    // `_`, the value is ignored
+    Wildcard,
+    // `[a, b]` or `(a, b)`, an array unpacked into one binding per element, with the region from
+    // its opening to its closing bracket
+    Array(Vec<BindingExpression>, TokenRecord),
+}
 
-    Call { region: TokenRecord, left: Box<Expression>, args: Vec<Expression> },
-    Index { region: TokenRecord, left: Box<Expression>, index: Box<Expression> },
+/// A pattern on the left side of a `match` arm.
+#[derive(Debug)]
+pub(crate) enum PatternExpression {
+    // The `_` arm, matches every value
+    Wildcard,
+    // A literal that is compared against the matched value
+    Literal(Box<Expression>),
+    // A name, matches every value and binds it to a variable
+    Identifier(TokenRecord),
+    // `[a, b]`, matches arrays whose elements match the patterns
+    Array(Vec<PatternExpression>),
+    // `...rest` inside an array pattern, matches the remaining elements
+    Rest(TokenRecord, Option<TokenRecord>),
+    // `Point { x, y: 0 }`, matches instances of a struct whose fields match the patterns
+    Struct(TokenRecord, Vec<(TokenRecord, Option<PatternExpression>)>),
+    // `Result::Ok(value)`, matches a variant whose values match the patterns
+    Variant(Box<Expression>, Vec<PatternExpression>),
 }
 
 
-/// Represents a Call or Index. This is turned into a `Expression` in the `factor` method
+/// Represents a Call, Index or Field access. This is turned into a `Expression` in the `factor` method
 /// The Call and Index expression store the left side of the expression, so this extra step is
 /// needed to satisfy the borrow checker.
+/// The Call and Index expressions keep the region between their brackets, so errors can point at them.
 enum PostFixExpr {
-    Call(TokenRecord, Vec<Expression>),
-    Index(TokenRecord, Box<Expression>),
+    Call(Vec<Expression>, TokenRecord),
+    Index(Box<Expression>, TokenRecord),
+    Field(TokenRecord),
+}
+
+/// Creates a record covering the tokens from `open` to `close`.
+fn region(open: &TokenRecord, close: &TokenRecord) -> TokenRecord {
+    TokenRecord {
+        token_type: open.token_type,
+        offset: open.offset,
+        length: close.offset + close.length - open.offset,
+        after_newline: open.after_newline,
+    }
 }
 
 
@@ -292,14 +455,94 @@ fn match_token<'a>(expected: Token) -> impl Fn(&'a [TokenRecord]) -> IResult<&'a
     }
 }
 
+/// Like `match_token`, but the token must be on the same line as the previous one.
+fn same_line<'a>(expected: Token) -> impl Fn(&'a [TokenRecord]) -> IResult<&'a TokenRecord> {
+    move |input: &'a [TokenRecord]| {
+        let (rest, first) = match_token(expected)(input)?;
+        if first.after_newline {
+            return Err(nom::Err::Error(ParseError::UnexpectedToken { found: first.clone(), expected }));
+        }
+        Ok((rest, first))
+    }
+}
 
 /// Main entry function for the parser
-pub fn parser(file: FileIdentifier, input: &[TokenRecord]) -> Result<Vec<Expression>, Box<dyn LError>> {
+/// The statements are parsed one after the other, so a syntax error doesn't stop the parsing: the
+/// tokens of the statement are skipped up to the next one, see `synchronize`, and all the errors of
+/// the input are reported together.
+pub fn parser(file: FileIdentifier, input: &[TokenRecord]) -> Result<Vec<Expression>, Box<dyn LError + Send>> {
     let max_length = input.last().map(|last| last.offset + last.length).unwrap_or(0);
-    final_parser(unit)(input).map_err(|a| to_external_error(a, file, max_length))
+    let mut expressions = Vec::new();
+    let mut errors = error::ErrorCollection::new();
+    let mut failed = false;
+    // The blocks opened by the skipped tokens, whose statements are parsed until their `}`.
+    let mut depth = 0;
+    let mut rest = input;
+    while let Some((first, after)) = rest.split_first() {
+        if depth > 0 && first.token_type == Token::RBrace {
+            depth -= 1;
+            rest = after;
+            continue;
+        }
+        let offset = match statement(rest) {
+            Ok((remaining, expression)) => {
+                expressions.push(expression);
+                rest = remaining;
+                continue;
+            }
+            // When no rule got past the first token, the error points at the statement.
+            Err(nom::Err::Error(internal)) if internal.offset() <= first.offset => {
+                let internal = ParseError::UnexpectedEnd { found: first.clone() };
+                errors.add_error(to_external_error(internal, file, max_length));
+                first.offset
+            }
+            Err(nom::Err::Error(internal) | nom::Err::Failure(internal)) => {
+                let offset = internal.offset();
+                errors.add_error(to_external_error(internal, file, max_length));
+                offset
+            }
+            Err(nom::Err::Incomplete(_)) => unreachable!("the tokens are all read before parsing"),
+        };
+        failed = true;
+        let stop = rest.iter().position(|record| record.offset >= offset).unwrap_or(rest.len());
+        (rest, depth) = synchronize(rest, stop, depth);
+    }
+    match failed {
+        false => Ok(expressions),
+        true => Err(Box::new(errors)),
+    }
 }
 
-fn to_external_error(internal: ParseError, file: FileIdentifier, max_length: usize) -> Box<dyn LError> {
+/// Skips the tokens of a statement that couldn't be parsed, where `error` is the index of the token
+/// it failed at, up to the next statement: the first token on a new line or after a `;` from the
+/// error on, or the `}` closing a block the statement is in. The braces skipped are counted in
+/// `depth`, the number of blocks open, and the tokens left are returned with it.
+fn synchronize(input: &[TokenRecord], error: usize, mut depth: usize) -> (&[TokenRecord], usize) {
+    for (index, record) in input.iter().enumerate() {
+        if index > 0 {
+            if index >= error && depth > 0 && record.token_type == Token::RBrace {
+                return (&input[index..], depth);
+            }
+            let boundary = record.after_newline || input[index - 1].token_type == Token::Semicolon;
+            if index >= error && boundary {
+                return (&input[index..], depth);
+            }
+        }
+        match record.token_type {
+            Token::LBrace => depth += 1,
+            Token::RBrace => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    (&[], depth)
+}
+
+/// Parses a single expression, like one interpolated in a string.
+pub(crate) fn parse_expression(input: &[TokenRecord]) -> Option<Expression> {
+    final_parser::<_, _, ParseError, ParseError>(expr)(input).ok()
+}
+
+fn to_external_error(internal: ParseError, file: FileIdentifier, max_length: usize) -> Box<dyn LError + Send> {
     match internal {
         ParseError::UnexpectedToken { found, expected } => {
             let message = format!("Unexpected token: {:?}, expected {:?}", found.token_type, expected);
@@ -313,12 +556,38 @@ fn to_external_error(internal: ParseError, file: FileIdentifier, max_length: usi
             Box::new(error::UnexpectedTokenError::new(file, record, message))
         }
         ParseError::List(list) => {
-            let mut compound = error::ErrorCollection::new();
-            for internal_ in list {
-                compound.add_error(to_external_error(internal_, file, max_length));
+            // The alternatives that were tried are reported as one error, where the furthest of
+            // them stopped, with all the tokens expected there.
+            let mut errors = Vec::new();
+            flatten(list, &mut errors);
+            let furthest = errors.iter().map(ParseError::offset).max().unwrap_or(usize::MAX);
+            errors.retain(|error| error.offset() == furthest);
+            let mut expected = Vec::new();
+            let mut found = None;
+            for error in &errors {
+                if let ParseError::UnexpectedToken { found: token, expected: kind } = error {
+                    found.get_or_insert_with(|| token.clone());
+                    if !expected.contains(kind) {
+                        expected.push(*kind);
+                    }
+                }
+            }
+            match (found, errors.into_iter().next()) {
+                (Some(found), _) => {
+                    let mut expected: Vec<String> = expected.iter().map(|token| format!("{token:?}")).collect();
+                    let last = expected.pop().unwrap_or_default();
+                    // Past a few tokens, they are the ones an expression can start with.
+                    let expected = match expected.len() {
+                        0 => last,
+                        1..MAX_EXPECTED => format!("{} or {last}", expected.join(", ")),
+                        _ => "an expression".to_string(),
+                    };
+                    let message = format!("Unexpected token: {:?}, expected {expected}", found.token_type);
+                    Box::new(error::UnexpectedTokenError::new(file, found, message))
+                }
+                (None, Some(first)) => to_external_error(first, file, max_length),
+                (None, None) => Box::new(error::UnexpectedEndOfFileError::new(file, max_length)),
             }
-
-            Box::new(compound)
         }
         ParseError::UnexpectedEnd { found } => {
             let message = "Invalid syntax".to_string();
@@ -327,33 +596,47 @@ fn to_external_error(internal: ParseError, file: FileIdentifier, max_length: usi
     }
 }
 
+/// Collects the errors of a list and of the lists in it.
+fn flatten(list: Vec<ParseError>, errors: &mut Vec<ParseError>) {
+    for error in list {
+        match error {
+            ParseError::List(list) => flatten(list, errors),
+            error => errors.push(error),
+        }
+    }
+}
+
 // <editor-fold desc="Rules">
 
-fn expression_list(input: &[TokenRecord]) -> IResult<Vec<Expression>> {
+fn expression_list(input: &[TokenRecord]) -> IResult<'_, Vec<Expression>> {
     let (input, expr) = separated_list0(match_token(Token::Comma), expr)(input)?;
     Ok((input, expr))
 }
 
-fn post_fix(input: &[TokenRecord]) -> IResult<PostFixExpr> {
-    let call = tuple((match_token(Token::LParenthesis), expression_list, match_token(Token::RParenthesis)));
-    let index = tuple((match_token(Token::LBracket), expr, match_token(Token::RBracket)));
+fn post_fix(input: &[TokenRecord]) -> IResult<'_, PostFixExpr> {
+    let call = tuple((same_line(Token::LParenthesis), expression_list, match_token(Token::RParenthesis)));
+    let index = tuple((same_line(Token::LBracket), expr, match_token(Token::RBracket)));
+    let field = tuple((match_token(Token::Dot), match_token(Token::Identifier)));
     alt((
-        map(call, |(l, args, r)| PostFixExpr::Call(l.clone(), args)),
-        map(index, |(l, index, r)| PostFixExpr::Index(l.clone(), Box::new(index))),
+        map(call, |(open, args, close)| PostFixExpr::Call(args, region(open, close))),
+        map(index, |(open, index, close)| PostFixExpr::Index(Box::new(index), region(open, close))),
+        map(field, |(_, id)| PostFixExpr::Field(id.clone())),
     ))(input)
 }
 
-fn factor(input: &[TokenRecord]) -> IResult<Expression> {
+fn factor(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, left) = object(input)?;
 
     let (input, postfix) = many0(post_fix)(input)?;
 
     fn apply(expr: PostFixExpr, left: Expression) -> Expression {
         match expr {
-            PostFixExpr::Call(record, args) =>
-                Expression::Call { region: record, left: Box::new(left), args },
-            PostFixExpr::Index(record, index) =>
-                Expression::Index { region: record, left: Box::new(left), index },
+            PostFixExpr::Call(args, region) =>
+                Expression::Call { left: Box::new(left), args, region },
+            PostFixExpr::Index(index, region) =>
+                Expression::Index { left: Box::new(left), index, region },
+            PostFixExpr::Field(field) =>
+                Expression::Field { left: Box::new(left), field },
         }
     }
 
@@ -380,12 +663,12 @@ fn factor(input: &[TokenRecord]) -> IResult<Expression> {
     Ok((input, left))
 }
 
-fn unary_expression(input: &[TokenRecord]) -> IResult<Expression> {
+fn unary_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, op) = opt(alt((
         map(match_token(Token::Minus), |f| (UnaryOperator::Negate, f.clone())),
         map(match_token(Token::Not), |f| (UnaryOperator::Not, f.clone())),
     )))(input)?;
-    let (input, expr) = factor(input)?;
+    let (input, expr) = power_expression(input)?;
     if let Some(op) = op {
         Ok((input, Expression::Unary { operator: op.clone(), expr: Box::new(expr) }))
     } else {
@@ -395,14 +678,11 @@ fn unary_expression(input: &[TokenRecord]) -> IResult<Expression> {
 }
 
 
-fn multiplicative_expression(input: &[TokenRecord]) -> IResult<Expression> {
-    let (input, left) = unary_expression(input)?;
+fn power_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, left) = factor(input)?;
     let (input, right) = opt(tuple((
-        alt((
-            map(match_token(Token::Star), |f| (BinaryOperator::Multiply, f.clone())),
-            map(match_token(Token::RSlash), |f| (BinaryOperator::Divide, f.clone())),
-        )),
-        multiplicative_expression
+        map(match_token(Token::StarStar), |f| (BinaryOperator::Power, f.clone())),
+        unary_expression
     )))(input)?;
     if let Some((op, right)) = right {
         Ok((input, Expression::Binary {
@@ -415,71 +695,87 @@ fn multiplicative_expression(input: &[TokenRecord]) -> IResult<Expression> {
     }
 }
 
-fn additive_expression(input: &[TokenRecord]) -> IResult<Expression> {
-    let (input, left) = multiplicative_expression(input)?;
-    let (input, right) = opt(tuple((
+// Chains of operators with the same precedence are evaluated from left to right:
+// `10 - 4 - 3` is `(10 - 4) - 3`.
+fn fold_binary(first: Expression, rest: Vec<((BinaryOperator, TokenRecord), Expression)>) -> Expression {
+    rest.into_iter().fold(first, |left, (operator, right)| Expression::Binary {
+        left: Box::new(left),
+        operator,
+        right: Box::new(right)
+    })
+}
+
+fn multiplicative_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, first) = unary_expression(input)?;
+    let (input, rest) = many0(pair(
+        alt((
+            map(match_token(Token::Star), |f| (BinaryOperator::Multiply, f.clone())),
+            map(match_token(Token::RSlash), |f| (BinaryOperator::Divide, f.clone())),
+        )),
+        unary_expression
+    ))(input)?;
+    Ok((input, fold_binary(first, rest)))
+}
+
+fn additive_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, first) = multiplicative_expression(input)?;
+    let (input, rest) = many0(pair(
         alt((
             map(match_token(Token::Plus), |f| (BinaryOperator::Add, f.clone())),
             map(match_token(Token::Minus), |f| (BinaryOperator::Subtract, f.clone())),
         )),
+        multiplicative_expression
+    ))(input)?;
+    Ok((input, fold_binary(first, rest)))
+}
+
+fn range_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, start) = additive_expression(input)?;
+    let (input, end) = opt(tuple((
+        alt((match_token(Token::DotDot), match_token(Token::DotDotEquals))),
         additive_expression
     )))(input)?;
-    if let Some((op, right)) = right {
-        Ok((input, Expression::Binary {
-            left: Box::new(left),
-            operator: op,
-            right: Box::new(right)
+    if let Some((operator, end)) = end {
+        Ok((input, Expression::Range {
+            start: Box::new(start),
+            operator: operator.clone(),
+            end: Box::new(end),
+            inclusive: operator.token_type == Token::DotDotEquals,
         }))
     } else {
-        Ok((input, left))
+        Ok((input, start))
     }
 }
 
-fn relational_expression(input: &[TokenRecord]) -> IResult<Expression> {
-    let (input, left) = additive_expression(input)?;
-    let (input, right) = opt(tuple((
+fn relational_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, first) = range_expression(input)?;
+    let (input, rest) = many0(pair(
         alt((
             map(match_token(Token::LessThan), |f| (BinaryOperator::LessThan, f.clone())),
             map(match_token(Token::GreaterThan), |f| (BinaryOperator::GreaterThan, f.clone())),
             map(match_token(Token::LessThanEquals), |f| (BinaryOperator::LessThanOrEqual, f.clone())),
             map(match_token(Token::GreaterThanEquals), |f| (BinaryOperator::GreaterThanOrEqual, f.clone())),
         )),
-        relational_expression
-    )))(input)?;
-    if let Some((op, right)) = right {
-        Ok((input, Expression::Binary {
-            left: Box::new(left),
-            operator: op,
-            right: Box::new(right)
-        }))
-    } else {
-        Ok((input, left))
-    }
+        range_expression
+    ))(input)?;
+    Ok((input, fold_binary(first, rest)))
 }
 
-fn equality_expression(input: &[TokenRecord]) -> IResult<Expression> {
-    let (input, left) = relational_expression(input)?;
-    let (input, right) = opt(tuple((
+fn equality_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, first) = relational_expression(input)?;
+    let (input, rest) = many0(pair(
         alt((
             map(match_token(Token::EqualsEquals), |f| (BinaryOperator::Equal, f.clone())),
             map(match_token(Token::NotEquals), |f| (BinaryOperator::NotEqual, f.clone())),
         )),
-        equality_expression
-    )))(input)?;
-    if let Some((op, right)) = right {
-        Ok((input, Expression::Binary {
-            left: Box::new(left),
-            operator: op,
-            right: Box::new(right)
-        }))
-    } else {
-        Ok((input, left))
-    }
+        relational_expression
+    ))(input)?;
+    Ok((input, fold_binary(first, rest)))
 }
 
 
 
-fn conditional_and_expression(input: &[TokenRecord]) -> IResult<Expression> {
+fn conditional_and_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, left) = equality_expression(input)?;
     let (input, right) = opt(tuple((
         map(match_token(Token::And), |f| (BinaryOperator::And, f.clone())),
@@ -495,7 +791,7 @@ fn conditional_and_expression(input: &[TokenRecord]) -> IResult<Expression> {
         Ok((input, left))
     }
 }
-fn conditional_or_expression(input: &[TokenRecord]) -> IResult<Expression> {
+fn conditional_or_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, left) = conditional_and_expression(input)?;
     let (input, right) = opt(tuple((
         map(match_token(Token::Or), |f| (BinaryOperator::Or, f.clone())),
@@ -512,131 +808,441 @@ fn conditional_or_expression(input: &[TokenRecord]) -> IResult<Expression> {
     }
 }
 
-fn array(input: &[TokenRecord]) -> IResult<Expression> {
+fn pipeline_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, first) = conditional_or_expression(input)?;
+    let (input, functions) = many0(pair(match_token(Token::Pipe), cut(conditional_or_expression)))(input)?;
+    let pipeline = functions.into_iter().fold(first, |value, (operator, function)| Expression::Pipeline {
+        value: Box::new(value),
+        operator: operator.clone(),
+        function: Box::new(function),
+    });
+    Ok((input, pipeline))
+}
+
+fn array(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, _) = match_token(Token::LBracket)(input)?;
-    let (input, expr) = separated_list0(match_token(Token::Comma), expr)(input)?;
+    let (input, mut items) = separated_list0(match_token(Token::Comma), expr)(input)?;
+    // A single expression followed by `for` is a comprehension: `[x * x for x in nums if x > 0]`.
+    if items.len() == 1 && let Ok((input, _)) = match_token(Token::For)(input) {
+        let (input, binding) = cut(binding)(input)?;
+        let (input, keyword) = cut(match_token(Token::In))(input)?;
+        let (input, iterable) = cut(expr)(input)?;
+        let (input, condition) = opt(preceded(match_token(Token::If), cut(expr)))(input)?;
+        let (input, _) = cut(match_token(Token::RBracket))(input)?;
+        return Ok((input, Expression::Comprehension {
+            expr: Box::new(items.remove(0)),
+            binding,
+            keyword: keyword.clone(),
+            iterable: Box::new(iterable),
+            condition: condition.map(Box::new),
+        }));
+    }
     let (input, _) = match_token(Token::RBracket)(input)?;
-    Ok((input, Expression::Array(expr)))
+    Ok((input, Expression::Array(items)))
+}
+
+fn type_name(input: &[TokenRecord]) -> IResult<'_, TokenRecord> {
+    map(
+        alt((match_token(Token::Identifier), match_token(Token::Nil))),
+        |r| r.clone(),
+    )(input)
+}
+
+fn return_type(input: &[TokenRecord]) -> IResult<'_, Option<TokenRecord>> {
+    opt(preceded(match_token(Token::Arrow), cut(type_name)))(input)
+}
+
+fn parameter(input: &[TokenRecord]) -> IResult<'_, ParameterExpression> {
+    let (input, id) = match_token(Token::Identifier)(input)?;
+    let (input, annotation) = opt(preceded(match_token(Token::Colon), cut(type_name)))(input)?;
+    let (input, default) = opt(preceded(match_token(Token::Equals), cut(expr)))(input)?;
+    Ok((input, ParameterExpression { id: id.clone(), annotation, default }))
 }
 
-fn closure(input: &[TokenRecord]) -> IResult<Expression> {
+fn closure(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, _) = match_token(Token::Bar)(input)?;
-    let (input, args) = separated_list0(match_token(Token::Comma), match_token(Token::Identifier))(input)?;
+    let (input, args) = separated_list0(match_token(Token::Comma), parameter)(input)?;
     let (input, _) = match_token(Token::Bar)(input)?;
+    let (input, return_type) = return_type(input)?;
     let (input, block) = block(input)?;
-    Ok((input, Expression::Closure { args: args.into_iter().cloned().collect(), block }))
+    Ok((input, Expression::Closure { args, return_type, block }))
+}
+
+fn array_pattern(input: &[TokenRecord]) -> IResult<'_, PatternExpression> {
+    let rest = map(
+        pair(match_token(Token::Ellipsis), opt(match_token(Token::Identifier))),
+        |(ellipsis, id)| PatternExpression::Rest(ellipsis.clone(), id.cloned()),
+    );
+    let (input, _) = match_token(Token::LBracket)(input)?;
+    let (input, items) = separated_list0(match_token(Token::Comma), alt((rest, pattern)))(input)?;
+    let (input, _) = cut(match_token(Token::RBracket))(input)?;
+    Ok((input, PatternExpression::Array(items)))
+}
+
+fn struct_pattern(input: &[TokenRecord]) -> IResult<'_, PatternExpression> {
+    let field = pair(
+        match_token(Token::Identifier),
+        opt(preceded(match_token(Token::Colon), cut(pattern))),
+    );
+    let (input, id) = match_token(Token::Identifier)(input)?;
+    let (input, _) = match_token(Token::LBrace)(input)?;
+    let (input, fields) = separated_list0(match_token(Token::Comma), field)(input)?;
+    let (input, _) = opt(match_token(Token::Comma))(input)?;
+    let (input, _) = cut(match_token(Token::RBrace))(input)?;
+    let fields = fields.into_iter().map(|(field, pattern)| (field.clone(), pattern)).collect();
+    Ok((input, PatternExpression::Struct(id.clone(), fields)))
 }
 
-fn object(input: &[TokenRecord]) -> IResult<Expression> {
+fn variant_pattern(input: &[TokenRecord]) -> IResult<'_, PatternExpression> {
+    let (input, path) = path(input)?;
+    let (input, _) = match_token(Token::LParenthesis)(input)?;
+    let (input, patterns) = separated_list0(match_token(Token::Comma), pattern)(input)?;
+    let (input, _) = cut(match_token(Token::RParenthesis))(input)?;
+    Ok((input, PatternExpression::Variant(Box::new(path), patterns)))
+}
+
+fn pattern(input: &[TokenRecord]) -> IResult<'_, PatternExpression> {
+    alt((
+        variant_pattern,
+        map(path, |p| PatternExpression::Literal(Box::new(p))),
+        struct_pattern,
+        array_pattern,
+        map(match_token(Token::Identifier), |r| PatternExpression::Identifier(r.clone())),
+        map(match_token(Token::Underscore), |_| PatternExpression::Wildcard),
+        map(match_token(Token::String), |r| PatternExpression::Literal(Box::new(Expression::String(r.clone())))),
+        map(match_token(Token::Char), |r| PatternExpression::Literal(Box::new(Expression::Char(r.clone())))),
+        // A negative number is a negation of the literal, as in any other expression.
+        map(
+            pair(
+                match_token(Token::Minus),
+                alt((
+                    map(match_token(Token::Integer), |r| Expression::Integer(r.clone())),
+                    map(match_token(Token::Float), |r| Expression::Float(r.clone())),
+                )),
+            ),
+            |(minus, literal)| {
+                PatternExpression::Literal(Box::new(Expression::Unary {
+                    operator: (UnaryOperator::Negate, minus.clone()),
+                    expr: Box::new(literal),
+                }))
+            },
+        ),
+        map(match_token(Token::Integer), |r| PatternExpression::Literal(Box::new(Expression::Integer(r.clone())))),
+        map(match_token(Token::Float), |r| PatternExpression::Literal(Box::new(Expression::Float(r.clone())))),
+        map(match_token(Token::Boolean), |r| PatternExpression::Literal(Box::new(Expression::Bool(r.clone())))),
+        map(match_token(Token::Nil), |_| PatternExpression::Literal(Box::new(Expression::Nil))),
+    ))(input)
+}
+
+fn match_arm(input: &[TokenRecord]) -> IResult<'_, (PatternExpression, Vec<Expression>)> {
+    let (input, pattern) = pattern(input)?;
+    let (input, _) = cut(match_token(Token::FatArrow))(input)?;
+    let (input, body) = cut(alt((block, map(expr, |e| vec![e]))))(input)?;
+    let (input, _) = opt(match_token(Token::Comma))(input)?;
+    Ok((input, (pattern, body)))
+}
+
+fn match_expression(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, _) = match_token(Token::Match)(input)?;
+    let (input, expr) = cut(expr)(input)?;
+    let (input, _) = cut(match_token(Token::LBrace))(input)?;
+    let (input, arms) = many0(match_arm)(input)?;
+    let (input, _) = cut(match_token(Token::RBrace))(input)?;
+    Ok((input, Expression::Match { expr: Box::new(expr), arms }))
+}
+
+fn path(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, id) = match_token(Token::Identifier)(input)?;
+    let (input, _) = match_token(Token::ColonColon)(input)?;
+    let (input, item) = cut(match_token(Token::Identifier))(input)?;
+    Ok((input, Expression::Path { id: id.clone(), item: item.clone() }))
+}
+
+fn object(input: &[TokenRecord]) -> IResult<'_, Expression> {
     alt((
         array,
         closure,
+        match_expression,
         map(match_token(Token::String), |r| Expression::String(r.clone())),
+        map(match_token(Token::Char), |r| Expression::Char(r.clone())),
         map(match_token(Token::Integer), |r| Expression::Integer(r.clone())),
         map(match_token(Token::Float), |r| Expression::Float(r.clone())),
         map(match_token(Token::Boolean), |r| Expression::Bool(r.clone())),
+        map(match_token(Token::Nil), |_| Expression::Nil),
+        path,
         map(match_token(Token::Identifier), |r| Expression::Identifier(r.clone())),
         delimited(match_token(Token::LParenthesis), expr, match_token(Token::RParenthesis)),
     ))(input)
 }
 
 
-fn let_statement(input: &[TokenRecord]) -> IResult<Expression> {
-    let (input, _) = match_token(Token::Let)(input)?;
-    let (input, id) = cut(match_token(Token::Identifier))(input)?;
+fn binding_list(open: Token, close: Token) -> impl Fn(&[TokenRecord]) -> IResult<'_, BindingExpression> {
+    move |input| {
+        let (input, open) = match_token(open)(input)?;
+        let (input, bindings) = separated_list0(match_token(Token::Comma), binding)(input)?;
+        let (input, close) = cut(match_token(close))(input)?;
+        Ok((input, BindingExpression::Array(bindings, region(open, close))))
+    }
+}
+
+fn binding(input: &[TokenRecord]) -> IResult<'_, BindingExpression> {
+    alt((
+        map(match_token(Token::Identifier), |r| BindingExpression::Identifier(r.clone())),
+        map(match_token(Token::Underscore), |_| BindingExpression::Wildcard),
+        binding_list(Token::LBracket, Token::RBracket),
+        binding_list(Token::LParenthesis, Token::RParenthesis),
+    ))(input)
+}
+
+fn let_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Let)(input)?;
+    let (input, binding) = cut(binding)(input)?;
     let (input, _) = cut(match_token(Token::Equals))(input)?;
     let (input, expr) = cut(expr)(input)?;
-    Ok((input, Expression::Let { id: id.clone(), expr: Box::new(expr) }))
+    Ok((input, Expression::Let { keyword: keyword.clone(), binding, expr: Box::new(expr) }))
+}
+
+fn const_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Const)(input)?;
+    let (input, binding) = cut(binding)(input)?;
+    let (input, _) = cut(match_token(Token::Equals))(input)?;
+    let (input, expr) = cut(expr)(input)?;
+    Ok((input, Expression::Const { keyword: keyword.clone(), binding, expr: Box::new(expr) }))
+}
+
+fn struct_declaration(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, doc) = many0(match_token(Token::DocComment))(input)?;
+    let (input, _) = match_token(Token::Struct)(input)?;
+    let (input, id) = cut(match_token(Token::Identifier))(input)?;
+    let (input, _) = cut(match_token(Token::LBrace))(input)?;
+    let (input, fields) = separated_list0(match_token(Token::Comma), match_token(Token::Identifier))(input)?;
+    let (input, _) = opt(match_token(Token::Comma))(input)?;
+    let (input, _) = cut(match_token(Token::RBrace))(input)?;
+    Ok((input, Expression::Struct {
+        doc: doc.into_iter().cloned().collect(),
+        id: id.clone(),
+        fields: fields.into_iter().cloned().collect()
+    }))
+}
+
+// The methods of a struct, functions declared inside an `impl` block.
+// `impl Trait for Struct` declares the methods required by a trait.
+fn impl_block(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Impl)(input)?;
+    let (input, trait_id) = opt(terminated(match_token(Token::Identifier), match_token(Token::For)))(input)?;
+    let (input, id) = cut(match_token(Token::Identifier))(input)?;
+    // The region covers the header of the block, so a missing method can point at it.
+    let region = TokenRecord {
+        token_type: Token::Impl,
+        offset: keyword.offset,
+        length: id.offset + id.length - keyword.offset,
+        after_newline: keyword.after_newline,
+    };
+    let (input, _) = cut(match_token(Token::LBrace))(input)?;
+    let (input, methods) = many0(function)(input)?;
+    let (input, _) = cut(match_token(Token::RBrace))(input)?;
+    Ok((input, Expression::Impl { region, id: id.clone(), trait_id: trait_id.cloned(), methods }))
+}
+
+fn trait_declaration(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, _) = match_token(Token::Trait)(input)?;
+    let (input, id) = cut(match_token(Token::Identifier))(input)?;
+    let (input, _) = cut(match_token(Token::LBrace))(input)?;
+    let (input, methods) = separated_list0(match_token(Token::Comma), match_token(Token::Identifier))(input)?;
+    let (input, _) = opt(match_token(Token::Comma))(input)?;
+    let (input, _) = cut(match_token(Token::RBrace))(input)?;
+    Ok((input, Expression::Trait { id: id.clone(), methods: methods.into_iter().cloned().collect() }))
+}
+
+fn enum_declaration(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, _) = match_token(Token::Enum)(input)?;
+    let (input, id) = cut(match_token(Token::Identifier))(input)?;
+    let (input, _) = cut(match_token(Token::LBrace))(input)?;
+    let (input, variants) = separated_list0(match_token(Token::Comma), variant)(input)?;
+    let (input, _) = opt(match_token(Token::Comma))(input)?;
+    let (input, _) = cut(match_token(Token::RBrace))(input)?;
+    Ok((input, Expression::Enum { id: id.clone(), variants }))
 }
 
-fn if_statement(input: &[TokenRecord]) -> IResult<Expression> {
-    let (input, _) = match_token(Token::If)(input)?;
+// A variant of an enum, with the names of the values it carries.
+fn variant(input: &[TokenRecord]) -> IResult<'_, (TokenRecord, Vec<TokenRecord>)> {
+    let (input, id) = match_token(Token::Identifier)(input)?;
+    let (input, fields) = opt(delimited(
+        match_token(Token::LParenthesis),
+        separated_list0(match_token(Token::Comma), match_token(Token::Identifier)),
+        cut(match_token(Token::RParenthesis)),
+    ))(input)?;
+    let fields = fields.unwrap_or_default().into_iter().cloned().collect();
+    Ok((input, (id.clone(), fields)))
+}
+
+fn if_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::If)(input)?;
     let (input, expr_) = expr(input)?;
     let (input, block_) = block(input)?;
-    //there might be a better way than to throw away the first token of the tuple
-    let (input, else_block) = opt(tuple((
+    // `else if` is parsed as an `else` block holding a single nested `if`,
+    // so chains of any length are handled by the recursion.
+    let (input, else_block) = opt(preceded(
         match_token(Token::Else),
-        alt((block, map(expr, |e| vec![e])))
-    )))(input)?;
+        cut(alt((map(if_statement, |e| vec![e]), block)))
+    ))(input)?;
     Ok((input, Expression::If {
+        keyword: keyword.clone(),
         expr: Box::new(expr_),
         block: block_,
-        else_block: else_block.map(|(_, vec)| vec)
+        else_block
     }))
 }
 
 
-fn until(input: &[TokenRecord]) -> IResult<Expression> {
-    let (input, _) = match_token(Token::Until)(input)?;
+fn until(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Until)(input)?;
     let (input, expr) = expr(input)?;
     let (input, block) = block(input)?;
     Ok((input, Expression::Until {
+        keyword: keyword.clone(),
         expr: Box::new(expr),
         block
     }))
 }
 
-fn for_loop(input: &[TokenRecord]) -> IResult<Expression> {
+fn loop_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, _) = match_token(Token::Loop)(input)?;
+    let (input, block) = cut(block)(input)?;
+    Ok((input, Expression::Loop { block }))
+}
+
+fn break_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Break)(input)?;
+    let (input, expr) = opt(expr)(input)?;
+    Ok((input, Expression::Break { keyword: keyword.clone(), expr: expr.map(Box::new) }))
+}
+
+// A loop can be labeled, so a `break` or `continue` in a nested loop can refer to it.
+// `break outer` leaves the loop labeled `outer`, `continue outer` starts its next iteration.
+fn labeled_loop(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, label) = match_token(Token::Identifier)(input)?;
+    let (input, _) = match_token(Token::Colon)(input)?;
+    let (input, expr) = cut(alt((for_loop, until, loop_statement)))(input)?;
+    Ok((input, Expression::Labeled { label: label.clone(), expr: Box::new(expr) }))
+}
+
+fn continue_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Continue)(input)?;
+    let (input, label) = opt(match_token(Token::Identifier))(input)?;
+    Ok((input, Expression::Continue { keyword: keyword.clone(), label: label.cloned() }))
+}
+
+fn for_loop(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, _) = match_token(Token::For)(input)?;
-    let (input, id) = match_token(Token::Identifier)(input)?;
-    let (input, _) = match_token(Token::In)(input)?;
+    let (input, binding) = binding(input)?;
+    let (input, keyword) = match_token(Token::In)(input)?;
     let (input, expr) = expr(input)?;
     let (input, block) = block(input)?;
     Ok((input, Expression::For {
-        id: id.clone(),
+        binding,
+        keyword: keyword.clone(),
         expr: Box::new(expr),
         block
     }))
 }
 
-fn function(input: &[TokenRecord]) -> IResult<Expression> {
+fn function(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, doc) = many0(match_token(Token::DocComment))(input)?;
     let (input, _) = match_token(Token::Fn)(input)?;
     let (input, id) = match_token(Token::Identifier)(input)?;
     let (input, _) = match_token(Token::LParenthesis)(input)?;
-    let (input, args) = separated_list0(match_token(Token::Comma), match_token(Token::Identifier))(input)?;
+    let (input, args) = separated_list0(match_token(Token::Comma), parameter)(input)?;
     let (input, _) = match_token(Token::RParenthesis)(input)?;
+    let (input, return_type) = return_type(input)?;
     let (input, block) = block(input)?;
     Ok((input, Expression::Function {
+        doc: doc.into_iter().cloned().collect(),
         id: id.clone(),
-        args: args.into_iter().cloned().collect(),
+        args,
+        return_type,
         block
     }))
 }
 
-fn return_statement(input: &[TokenRecord]) -> IResult<Expression> {
+fn return_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, _) = match_token(Token::Return)(input)?;
     let (input, expr) = expr(input)?;
     Ok((input, Expression::Return { expr: Box::new(expr) }))
 }
 
-fn include(input: &[TokenRecord]) -> IResult<Expression> {
+fn throw_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Throw)(input)?;
+    let (input, expr) = cut(expr)(input)?;
+    Ok((input, Expression::Throw { keyword: keyword.clone(), expr: Box::new(expr) }))
+}
+
+fn try_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, _) = match_token(Token::Try)(input)?;
+    let (input, body) = cut(block)(input)?;
+    let (input, _) = cut(match_token(Token::Catch))(input)?;
+    let (input, id) = cut(match_token(Token::Identifier))(input)?;
+    let (input, catch_block) = cut(block)(input)?;
+    Ok((input, Expression::Try { block: body, id: id.clone(), catch_block }))
+}
+
+fn assert_statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    let (input, keyword) = match_token(Token::Assert)(input)?;
+    let (input, _) = cut(match_token(Token::LParenthesis))(input)?;
+    let (input, condition) = cut(expr)(input)?;
+    let (input, message) = opt(preceded(match_token(Token::Comma), cut(expr)))(input)?;
+    let (input, close) = cut(match_token(Token::RParenthesis))(input)?;
+    // The region covers the whole assertion, so a failure can point at it.
+    let region = TokenRecord {
+        token_type: Token::Assert,
+        offset: keyword.offset,
+        length: close.offset + close.length - keyword.offset,
+        after_newline: keyword.after_newline,
+    };
+    Ok((input, Expression::Assert { region, expr: Box::new(condition), message: message.map(Box::new) }))
+}
+
+fn include(input: &[TokenRecord]) -> IResult<'_, Expression> {
     let (input, _) = match_token(Token::Include)(input)?;
-    let (input, id) = match_token(Token::Identifier)(input)?;
-    Ok((input, Expression::Include { id: id.clone() } ))
+    let (input, id) = alt((match_token(Token::Identifier), match_token(Token::String)))(input)?;
+    let (input, alias) = opt(preceded(match_token(Token::As), match_token(Token::Identifier)))(input)?;
+    Ok((input, Expression::Include { id: id.clone(), alias: alias.cloned() } ))
 }
 
-fn expr(input: &[TokenRecord]) -> IResult<Expression> {
+fn expr(input: &[TokenRecord]) -> IResult<'_, Expression> {
     alt((
         include,
         return_statement,
+        throw_statement,
+        try_statement,
+        assert_statement,
         function,
         for_loop,
         until,
+        loop_statement,
+        labeled_loop,
+        break_statement,
+        continue_statement,
         if_statement,
         let_statement,
-        conditional_or_expression
+        const_statement,
+        struct_declaration,
+        impl_block,
+        trait_declaration,
+        enum_declaration,
+        pipeline_expression
     ))(input)
 }
 
-fn block(input: &[TokenRecord]) -> IResult<Vec<Expression>> {
+fn statement(input: &[TokenRecord]) -> IResult<'_, Expression> {
+    terminated(expr, opt(match_token(Token::Semicolon)))(input)
+}
+
+fn block(input: &[TokenRecord]) -> IResult<'_, Vec<Expression>> {
     let (input, _) = match_token(Token::LBrace)(input)?;
-    let (input, expr) = many0(expr)(input)?;
+    let (input, expr) = many0(statement)(input)?;
     let (input, _) = match_token(Token::RBrace)(input)?;
     Ok((input, expr))
 }
 
-fn unit(input: &[TokenRecord]) -> IResult<Vec<Expression>> {
-    many0(expr)(input)
-}
-
 // </editor-fold>
\ No newline at end of file
@@ -0,0 +1,43 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The entry point of an online playground, which runs the interpreter compiled to WebAssembly:
+//   cargo build --lib --release --target wasm32-unknown-unknown
+//   wasm-bindgen --target web target/wasm32-unknown-unknown/release/mussel.wasm --out-dir playground
+// The page then calls `run_source` from JavaScript with the code typed in it and shows the text
+// returned. There is no file system in the browser, so only the libraries of the standard library
+// can be included.
+// The errors are reported in the output, those found before running, like syntax errors, and those
+// raised while the script runs alike. WebAssembly aborts on a stack overflow, so the calls nested
+// too deep for the stack of the module stop with an error first, see `limits::set_stack`.
+
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+use crate::context;
+use crate::interpreter;
+use crate::interpreter::Backend;
+use crate::limits;
+use crate::loader::Loader;
+use crate::output;
+
+// The name of the script in the errors reported.
+const SCRIPT: &str = "playground.mus";
+
+// The size of the stack of the module, which `wasm-ld` gives 1 MiB unless told otherwise.
+const STACK_SIZE: usize = 1024 * 1024;
+
+// Runs a script and returns what it printed, followed by the errors found in it.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn run_source(source: &str) -> String {
+    output::capture(|| {
+        limits::set_stack(STACK_SIZE);
+        let mut loader = Loader::new(false);
+        loader.enter(SCRIPT.as_ref());
+        match loader.load_source(SCRIPT, source) {
+            Ok(parsed) => {
+                interpreter::interpreter(parsed, loader, context::DEFAULT_MAX_DEPTH, Backend::Tree, Vec::new());
+            }
+            Err(error) => loader.report(error),
+        }
+    })
+}
@@ -0,0 +1,103 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `--profile`, which measures the time spent in each function of a script while it runs. Each
+// function is shown with the number of times it was called, the time spent in its calls, with the
+// functions they called (inclusive), and without them (exclusive), the slowest first.
+// The time can also be written as folded stacks, one line per chain of calls with the microseconds
+// spent in the last function of the chain, which `flamegraph.pl` or `inferno` draw as a flame
+// graph. The built-in functions aren't measured, their time is counted in the function calling them.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use crate::bench;
+
+// The name of the code running outside of any function.
+const SCRIPT: &str = "<script>";
+
+// A function call running.
+struct Frame {
+    name: String,
+    start: Instant,
+    callees: Duration, // The time spent in the calls it made, which ended.
+}
+
+// What was measured for a function.
+#[derive(Default)]
+struct Function {
+    calls: usize,
+    inclusive: Duration,
+    exclusive: Duration,
+}
+
+pub(crate) struct Profiler {
+    stacks: Option<PathBuf>, // Where the folded stacks are written, instead of the table.
+    running: Vec<Frame>,     // The calls running, the outermost first, after the script itself.
+    functions: HashMap<String, Function>,
+    folded: HashMap<String, Duration>, // The exclusive time of each chain of calls, its names joined by `;`.
+}
+
+impl Profiler {
+    // Starts measuring the script.
+    pub(crate) fn new(stacks: Option<PathBuf>) -> Self {
+        let mut profiler = Profiler { stacks, running: Vec::new(), functions: HashMap::new(), folded: HashMap::new() };
+        profiler.enter(SCRIPT);
+        profiler
+    }
+
+    // Records that a function was called.
+    pub(crate) fn enter(&mut self, name: &str) {
+        self.functions.entry(name.to_string()).or_default().calls += 1;
+        self.running.push(Frame { name: name.to_string(), start: Instant::now(), callees: Duration::ZERO });
+    }
+
+    // Records that the innermost function call ended.
+    pub(crate) fn leave(&mut self) {
+        let Some(frame) = self.running.pop() else {
+            return;
+        };
+        let elapsed = frame.start.elapsed();
+        let exclusive = elapsed.saturating_sub(frame.callees);
+        let stack: Vec<&str> = self.running.iter().map(|frame| frame.name.as_str()).chain([frame.name.as_str()]).collect();
+        *self.folded.entry(stack.join(";")).or_default() += exclusive;
+        // The time of a recursive call is already in the time of the outer call of the function.
+        let recursive = self.running.iter().any(|running| running.name == frame.name);
+        let function = self.functions.entry(frame.name).or_default();
+        function.exclusive += exclusive;
+        if !recursive {
+            function.inclusive += elapsed;
+        }
+        if let Some(caller) = self.running.last_mut() {
+            caller.callees += elapsed;
+        }
+    }
+
+    // Ends the calls still running, when the script ended with an error or `exit`, and the script
+    // itself, then writes the table to the standard error, or the folded stacks to their file.
+    pub(crate) fn report(mut self) -> std::io::Result<()> {
+        while !self.running.is_empty() {
+            self.leave();
+        }
+        if let Some(path) = &self.stacks {
+            let mut stacks: Vec<(&String, &Duration)> = self.folded.iter().collect();
+            stacks.sort();
+            let mut folded = String::new();
+            for (stack, time) in stacks {
+                writeln!(folded, "{stack} {}", time.as_micros()).expect("writing to a string can't fail");
+            }
+            return std::fs::write(path, folded);
+        }
+        let mut functions: Vec<(String, Function)> = self.functions.into_iter().collect();
+        functions.sort_by(|(a, first), (b, second)| second.exclusive.cmp(&first.exclusive).then_with(|| a.cmp(b)));
+        let width = functions.iter().map(|(name, _)| name.len()).max().unwrap_or_default().max("function".len());
+        eprintln!("{:width$}  {:>8}  {:>12}  {:>12}", "function", "calls", "inclusive", "exclusive");
+        for (name, function) in functions {
+            let inclusive = bench::duration(function.inclusive.as_secs_f64());
+            let exclusive = bench::duration(function.exclusive.as_secs_f64());
+            eprintln!("{name:width$}  {:>8}  {inclusive:>12}  {exclusive:>12}", function.calls);
+        }
+        Ok(())
+    }
+}
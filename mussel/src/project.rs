@@ -0,0 +1,325 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The projects: a directory with a `mussel.toml` manifest naming the script to run and the
+// directories its includes are looked up in, made with `mussel new` and run with `mussel run`.
+//   [package]
+//   name = "hello"
+//   version = "0.1.0"
+//   entry = "src/main.mus"
+//   include = ["lib"]
+// The manifest is read with the part of TOML it needs: tables, comments, and keys set to strings,
+// integers, booleans or arrays of them.
+
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::Chars;
+
+/// The name of the manifest of a project.
+pub const MANIFEST: &str = "mussel.toml";
+
+// The script of a new project.
+const MAIN: &str = "println(\"Hello, world!\")\n";
+
+/// What the manifest of a project declares.
+pub struct Manifest {
+    pub directory: PathBuf, // The directory of the project, where the manifest is.
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub authors: Vec<String>,
+    pub entry: PathBuf,        // The script run by `mussel run`, from the directory of the project.
+    pub include: Vec<PathBuf>, // The directories the included files are looked up in, from the same one.
+}
+
+impl Manifest {
+    // Finds the manifest of the project the current directory is in, looking in its parents too,
+    // and reads it. The paths of the project are relative to the current directory.
+    pub fn find() -> Result<Manifest, String> {
+        let current = std::env::current_dir().map_err(|error| format!("Failed to read the current directory: {error}"))?;
+        for (depth, directory) in current.ancestors().enumerate() {
+            if directory.join(MANIFEST).is_file() {
+                let directory: PathBuf = std::iter::repeat_n("..", depth).collect();
+                return Manifest::read(&directory);
+            }
+        }
+        Err(format!("could not find `{MANIFEST}` in the current directory or its parents"))
+    }
+
+    // Reads the manifest of the project in a directory.
+    pub fn read(directory: &Path) -> Result<Manifest, String> {
+        let path = directory.join(MANIFEST);
+        let text = std::fs::read_to_string(&path).map_err(|error| format!("Failed to read {}: {error}", path.display()))?;
+        let error = |line: usize, message: String| format!("{}:{line}: {message}", path.display());
+        let mut manifest = Manifest {
+            directory: directory.to_path_buf(),
+            name: String::new(),
+            version: "0.1.0".to_string(),
+            description: None,
+            authors: Vec::new(),
+            entry: PathBuf::from("src/main.mus"),
+            include: Vec::new(),
+        };
+        let mut named = false;
+        for Entry { table, key, value, line } in parse(&text).map_err(|(line, message)| error(line, message))? {
+            let found = value.describe();
+            let expected = |kind: &str| error(line, format!("`{key}` must be {kind}, found {found}"));
+            match (table.as_str(), key.as_str()) {
+                ("package", "name") => {
+                    manifest.name = value.string().ok_or_else(|| expected("a string"))?;
+                    named = true;
+                }
+                ("package", "version") => manifest.version = value.string().ok_or_else(|| expected("a string"))?,
+                ("package", "description") => {
+                    manifest.description = Some(value.string().ok_or_else(|| expected("a string"))?);
+                }
+                ("package", "authors") => manifest.authors = value.strings().ok_or_else(|| expected("an array of strings"))?,
+                ("package", "entry") => manifest.entry = value.string().ok_or_else(|| expected("a string"))?.into(),
+                ("package", "include") => {
+                    let directories = value.strings().ok_or_else(|| expected("an array of strings"))?;
+                    manifest.include = directories.into_iter().map(PathBuf::from).collect();
+                }
+                ("package", _) => return Err(error(line, format!("unknown key `{key}`"))),
+                ("", _) => return Err(error(line, format!("`{key}` must be in the `[package]` table"))),
+                _ => return Err(error(line, format!("unknown table `[{table}]`, expected `[package]`"))),
+            }
+        }
+        if !named {
+            return Err(format!("{}: the `[package]` table must have a `name`", path.display()));
+        }
+        Ok(manifest)
+    }
+
+    // Returns the path of the script to run.
+    pub fn script(&self) -> PathBuf {
+        self.directory.join(&self.entry)
+    }
+
+    // Returns the paths of the directories the included files are looked up in.
+    pub fn directories(&self) -> Vec<PathBuf> {
+        self.include.iter().map(|directory| self.directory.join(directory)).collect()
+    }
+}
+
+// Creates a project in a new directory, named after it, with a manifest and a script printing a
+// greeting. Fails when the directory already exists.
+pub fn new(path: &str) -> ExitCode {
+    let directory = Path::new(path);
+    let name = directory.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+        eprintln!("error: `{path}` isn't a valid project name, use letters, digits, `_` and `-`");
+        return ExitCode::FAILURE;
+    }
+    if directory.exists() {
+        eprintln!("error: `{path}` already exists");
+        return ExitCode::FAILURE;
+    }
+    let manifest = format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nentry = \"src/main.mus\"\ninclude = []\n");
+    let written = std::fs::create_dir_all(directory.join("src")).and_then(|_| {
+        std::fs::write(directory.join(MANIFEST), manifest)?;
+        std::fs::write(directory.join("src").join("main.mus"), MAIN)
+    });
+    match written {
+        Ok(()) => {
+            println!("Created the project `{name}` in {path}, run it with `mussel run` from there");
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: Failed to write the project: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// A key set in the manifest.
+struct Entry {
+    table: String, // The table the key is in, empty before the first table.
+    key: String,
+    value: Value,
+    line: usize, // The line of the key, counted from 1.
+}
+
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    // Describes the value for the errors: `the integer `1``.
+    fn describe(&self) -> String {
+        match self {
+            Value::String(_) => "a string".to_string(),
+            Value::Integer(number) => format!("the integer `{number}`"),
+            Value::Boolean(boolean) => format!("the boolean `{boolean}`"),
+            Value::Array(values) => match values.iter().find(|value| !matches!(value, Value::String(_))) {
+                Some(value) => format!("an array holding {}", value.describe()),
+                None => "an array".to_string(),
+            },
+        }
+    }
+
+    fn string(self) -> Option<String> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    fn strings(self) -> Option<Vec<String>> {
+        match self {
+            Value::Array(values) => values.into_iter().map(Value::string).collect(),
+            _ => None,
+        }
+    }
+}
+
+// Reads the keys of a manifest, or returns the line of the first mistake and what it is.
+fn parse(text: &str) -> Result<Vec<Entry>, (usize, String)> {
+    let mut reader = Reader { chars: text.chars().peekable(), line: 1 };
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut table = String::new();
+    loop {
+        reader.skip_blank();
+        let line = reader.line;
+        match reader.chars.peek() {
+            None => return Ok(entries),
+            Some('[') => {
+                reader.chars.next();
+                table = reader.take_while(|c| c != ']' && c != '\n').trim().to_string();
+                if reader.chars.next() != Some(']') || table.is_empty() {
+                    return Err((line, "expected a table name like `[package]`".to_string()));
+                }
+                if entries.iter().any(|entry| entry.table == table) {
+                    return Err((line, format!("the table `[{table}]` is declared twice")));
+                }
+            }
+            Some(_) => {
+                let key = reader.take_while(|c| c.is_alphanumeric() || c == '_' || c == '-');
+                reader.skip_spaces();
+                if key.is_empty() || reader.chars.next() != Some('=') {
+                    return Err((line, "expected a key followed by `=`".to_string()));
+                }
+                if entries.iter().any(|entry| entry.table == table && entry.key == key) {
+                    return Err((line, format!("`{key}` is set twice")));
+                }
+                let value = reader.value()?;
+                entries.push(Entry { table: table.clone(), key, value, line });
+            }
+        }
+        // A table or a key ends its line.
+        reader.skip_spaces();
+        match reader.chars.peek() {
+            None | Some('\n' | '#') => {}
+            Some(c) => return Err((reader.line, format!("unexpected `{c}` after the value"))),
+        }
+    }
+}
+
+// Reads a decimal integer, its digits optionally grouped with `_`, like `1_000`.
+fn integer(word: &str) -> Option<i64> {
+    let digits = word.strip_prefix(['+', '-']).unwrap_or(word);
+    let grouped = digits.split('_').all(|group| !group.is_empty() && group.chars().all(|c| c.is_ascii_digit()));
+    // Like in TOML, a zero can't start a longer number.
+    if !grouped || (digits.starts_with('0') && digits.len() > 1) {
+        return None;
+    }
+    word.replace('_', "").parse().ok()
+}
+
+// Reads the text of a manifest, counting its lines.
+struct Reader<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+}
+
+impl Reader<'_> {
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> String {
+        let mut text = String::new();
+        while let Some(&c) = self.chars.peek()
+            && accept(c)
+        {
+            text.push(c);
+            self.chars.next();
+        }
+        text
+    }
+
+    // Skips the spaces and the comment ending a line.
+    fn skip_spaces(&mut self) {
+        self.take_while(|c| c == ' ' || c == '\t' || c == '\r');
+        if self.chars.peek() == Some(&'#') {
+            self.take_while(|c| c != '\n');
+        }
+    }
+
+    // Skips the spaces, the comments and the empty lines.
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_spaces();
+            if self.chars.next_if_eq(&'\n').is_none() {
+                return;
+            }
+            self.line += 1;
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, (usize, String)> {
+        self.skip_spaces();
+        let line = self.line;
+        match self.chars.next() {
+            Some('"') => {
+                let mut text = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some('"') => return Ok(Value::String(text)),
+                        Some('\\') => match self.chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some('r') => text.push('\r'),
+                            Some(c @ ('"' | '\\')) => text.push(c),
+                            _ => return Err((line, "unknown escape in a string".to_string())),
+                        },
+                        Some('\n') | None => return Err((line, "unterminated string".to_string())),
+                        Some(c) => text.push(c),
+                    }
+                }
+            }
+            // A literal string, without escapes.
+            Some('\'') => {
+                let text = self.take_while(|c| c != '\'' && c != '\n');
+                match self.chars.next() {
+                    Some('\'') => Ok(Value::String(text)),
+                    _ => Err((line, "unterminated string".to_string())),
+                }
+            }
+            // An array can be written over several lines.
+            Some('[') => {
+                let mut values = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.chars.next_if_eq(&']').is_some() {
+                        return Ok(Value::Array(values));
+                    }
+                    values.push(self.value()?);
+                    self.skip_blank();
+                    if self.chars.next_if_eq(&',').is_none() && self.chars.peek() != Some(&']') {
+                        return Err((self.line, "expected `,` or `]` in an array".to_string()));
+                    }
+                }
+            }
+            Some(c) if c.is_alphanumeric() || c == '+' || c == '-' => {
+                let word = self.take_while(|c| c.is_alphanumeric() || c == '_' || c == '+' || c == '-');
+                let word = format!("{c}{word}");
+                match word.as_str() {
+                    "true" => Ok(Value::Boolean(true)),
+                    "false" => Ok(Value::Boolean(false)),
+                    _ => integer(&word).map(Value::Integer).ok_or((line, format!("`{word}` isn't a valid value"))),
+                }
+            }
+            _ => Err((line, "expected a string, an integer, a boolean or an array".to_string())),
+        }
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The interactive session started by `mussel` without a script.
+// Every entry runs in the same global scope, so what it declares is kept for the next ones, and
+// the value of its last statement is printed. An entry goes on over several lines while a bracket,
+// a string or a block comment is left open. An error is reported and the session goes on.
+// The entries starting with `:` are commands, see `HELP`.
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use crate::context::Context;
+use crate::expr::Expr;
+use crate::interpreter;
+use crate::interpreter::{Backend, Interaction};
+use crate::loader::Loader;
+
+// The name of the entries in the errors reported.
+const ENTRY: &str = "<repl>";
+
+const HELP: &str = "\
+:help          show this help
+:load FILE     run a file, keeping what it declares
+:vars          list the variables and their types
+:type EXPR     show the type of the value of an expression
+:quit          end the session (or Ctrl-D, or `exit(status)`)";
+
+// Reads entries from the standard input and runs them until `:quit`, `exit` or the end of the input,
+// and returns the exit status of the session.
+pub fn run(check_types: bool, max_depth: usize, backend: Backend) -> ExitCode {
+    println!("Mussel {} - type :help for the commands", env!("CARGO_PKG_VERSION"));
+    let mut context = interpreter::session(Loader::new(check_types), max_depth, backend);
+    // The session is the script running, the files it loads are analyzed like included files, whose
+    // declarations are used elsewhere.
+    context.loader().enter(Path::new(ENTRY));
+    let mut lines = io::stdin().lock().lines();
+    loop {
+        let Some(entry) = read_entry(&mut lines) else {
+            return ExitCode::SUCCESS;
+        };
+        let entry = entry.trim();
+        let interaction = match entry.strip_prefix(':') {
+            Some(command) => command_entry(command, &mut context),
+            None if entry.is_empty() => continue,
+            None => run_code(entry, &mut context),
+        };
+        match interaction {
+            Interaction::Value(Expr::Void) | Interaction::Failed => {}
+            Interaction::Value(value) => println!("{value}"),
+            Interaction::Exit(status) => return ExitCode::from(status),
+        }
+    }
+}
+
+// Reads the lines of an entry, until no bracket, string or block comment is left open.
+// Returns `None` at the end of the input. An entry left unfinished at the end of the input is
+// dropped, for the terminal to go on reading after Ctrl-D.
+fn read_entry(lines: &mut impl Iterator<Item = io::Result<String>>) -> Option<String> {
+    let mut entry = String::new();
+    loop {
+        print!("{}", if entry.is_empty() { "> " } else { "... " });
+        io::stdout().flush().expect("Failed to flush stdout");
+        let Some(Ok(line)) = lines.next() else {
+            if entry.is_empty() {
+                println!();
+                return None;
+            }
+            println!("\n(unfinished entry dropped)");
+            return Some(String::new());
+        };
+        entry.push_str(&line);
+        entry.push('\n');
+        if entry.trim_start().starts_with(':') || !unfinished(&entry) {
+            return Some(entry);
+        }
+    }
+}
+
+// Runs a command, the entry without its `:`.
+fn command_entry(command: &str, context: &mut Context) -> Interaction {
+    let (name, argument) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let argument = argument.trim();
+    match name {
+        "help" => println!("{HELP}"),
+        "quit" => return Interaction::Exit(0),
+        "vars" => {
+            for name in context.names() {
+                let value = context.get(&name).expect("the variable was just listed");
+                println!("{name}: {}", value.type_name());
+            }
+        }
+        "type" if !argument.is_empty() => {
+            return match run_code(argument, context) {
+                Interaction::Value(value) => {
+                    println!("{}", value.type_name());
+                    Interaction::Value(Expr::Void)
+                }
+                interaction => interaction,
+            };
+        }
+        "load" if !argument.is_empty() => return load(Path::new(argument), context),
+        "type" | "load" => eprintln!("error: :{name} expects an argument, see :help"),
+        _ => eprintln!("error: unknown command `:{name}`, see :help"),
+    }
+    Interaction::Value(Expr::Void)
+}
+
+// Runs code typed in the session.
+fn run_code(code: &str, context: &mut Context) -> Interaction {
+    match context.loader().load_source(ENTRY, code) {
+        Ok(exprs) => interpreter::interact(exprs, context),
+        Err(error) => {
+            context.loader().show(&error);
+            Interaction::Failed
+        }
+    }
+}
+
+// Runs a file in the global scope of the session, like an entry typed in it. The value of its last
+// statement isn't printed.
+fn load(path: &Path, context: &mut Context) -> Interaction {
+    let running = context.loader().running();
+    context.loader().enter(path);
+    let interaction = match context.loader().load(path) {
+        Ok(exprs) => match interpreter::interact(exprs, context) {
+            Interaction::Value(_) => Interaction::Value(Expr::Void),
+            interaction => interaction,
+        },
+        Err(error) => {
+            context.loader().show(&error);
+            Interaction::Failed
+        }
+    };
+    context.loader().unwind(running);
+    interaction
+}
+
+// Tells whether code is unfinished: a bracket, a string or a block comment is left open.
+pub(crate) fn unfinished(code: &str) -> bool {
+    let mut depth = 0;
+    let mut comments = 0;
+    let mut rest = code;
+    while let Some(c) = rest.chars().next() {
+        let skipped = if comments > 0 {
+            if rest.starts_with("*/") {
+                comments -= 1;
+                2
+            } else if rest.starts_with("/*") {
+                comments += 1;
+                2
+            } else {
+                c.len_utf8()
+            }
+        } else if rest.starts_with("/*") {
+            comments += 1;
+            2
+        } else if rest.starts_with("//") {
+            rest.find('\n').unwrap_or(rest.len())
+        } else if let Some(string) = rest.strip_prefix("\"\"\"") {
+            match string.find("\"\"\"") {
+                Some(end) => end + 6,
+                None => return true,
+            }
+        } else if let Some(string) = rest.strip_prefix('"') {
+            match string.find('"') {
+                Some(end) => end + 2,
+                None => return true,
+            }
+        } else {
+            // A character literal can be a bracket, like `'{'`.
+            let character = rest.strip_prefix('\'').and_then(|literal| {
+                let literal = literal.strip_prefix('\\').unwrap_or(literal);
+                let next = literal.chars().next()?;
+                literal[next.len_utf8()..].starts_with('\'').then(|| rest.len() - literal.len() + next.len_utf8() + 1)
+            });
+            match c {
+                _ if character.is_some() => {}
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+            character.unwrap_or(c.len_utf8())
+        };
+        rest = &rest[skipped..];
+    }
+    depth > 0 || comments > 0
+}
@@ -0,0 +1,1561 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Translation of a script to a Rust program, made with `mussel emit-rs`.
+// The program is a cargo package without dependencies: the script becomes `src/main.rs`, next to a
+// small runtime (`runtime.rs`) with the values of Mussel, the operators and the built-in functions.
+// Every expression becomes a Rust expression giving a `Value`, and the errors of the script are
+// returned with `?`, so the blocks, `return`, `break` and `continue` of the script are the ones of
+// Rust. Inside a `try`, an error leaves the labeled block of the `try` instead of the function.
+// A variable read by a function declared in its block is shared with the function in a `Var`,
+// declared when the block starts so the function can read it before its declaration runs; the
+// other variables are plain Rust variables. The files a script includes are translated along with
+// it, each into a function run by its first `include`. What only the interpreter can do, like
+// `eval`, is reported.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use crate::error;
+use crate::error::{
+    CircularIncludeError, ConstantAssignmentError, EvaluationError, FileError, IncludeError, LError, Location,
+    LoopControlError, UndeclaredLabelError,
+};
+use crate::expr::{
+    Align, Atom, BinOp, Binding, Expr, FormatSpec, IncludeTarget, LogicOp, Operator, Parameter, Pattern,
+    StringPart, UnaryOp,
+};
+use crate::loader::Loader;
+use crate::stdlib;
+use crate::walk::{binding_names, children, location, mentions};
+
+const RUNTIME: &str = include_str!("runtime.rs");
+
+// The built-in functions, with the functions of the runtime implementing them.
+const BUILTINS: [(&str, &str); 9] = [
+    ("println", "println"),
+    ("input", "input"),
+    ("range", "range"),
+    ("is_nil", "is_nil"),
+    ("typeof", "type_of"),
+    ("get", "get"),
+    ("call", "call_function"),
+    ("args", "script_arguments"),
+    ("exit", "exit"),
+];
+
+// The built-in functions working with the variables of the running script, which only the
+// interpreter knows.
+const UNSUPPORTED: [&str; 3] = ["eval", "vars", "defined"];
+
+// The libraries of the standard library written in Rust, implemented by the runtime, and what
+// they declare.
+const LIBRARIES: [(&str, &[&str]); 5] = [
+    ("math", &["abs", "sqrt", "pow"]),
+    ("string", &["lowercase", "uppercase", "length", "split", "reverse", "trim", "ltrim", "rtrim", "chars", "ord", "chr"]),
+    ("time", &["time_ms", "time_sec"]),
+    ("random", &["rand"]),
+    ("os", &["getcwd", "listdir", "exists"]),
+];
+
+// The functions the operators call when their operands are struct instances, which the runtime
+// must be given.
+const OPERATOR_FUNCTIONS: [&str; 7] = ["add", "sub", "mul", "div", "neg", "eq", "lt"];
+
+// The words Rust doesn't allow as names, or that name something the translation uses. A name of
+// the script that is one of them gets a `_` appended.
+const RESERVED: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "Self", "static",
+    "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield", "arguments", "runtime", "script", "main", "Value", "Var", "Key", "Some",
+    "None", "Ok", "Err", "Vec", "String", "Option", "Result", "Box",
+];
+
+type Translated<T> = Result<T, Box<dyn LError + Send>>;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Variable,
+    Constant,
+    Struct,
+    Enum,
+    Builtin,
+    Unsupported,
+}
+
+// A name of the script, with its name in Rust, or the path of the runtime function of a built-in.
+#[derive(Clone)]
+struct Symbol {
+    rust: String,
+    kind: Kind,
+    // Whether the name is a `Var`, shared with the functions reading it.
+    cell: bool,
+    // The number of functions around the declaration.
+    depth: usize,
+}
+
+// The names declared in a block, in the order of their declaration.
+#[derive(Default)]
+struct Scope {
+    symbols: HashMap<String, Symbol>,
+    order: Vec<String>,
+    // The names declared in the block that a function inside it reads, which are `Var`s.
+    cells: HashSet<String>,
+    // The `Var`s declared when the block starts whose declaration hasn't run yet, with whether
+    // they hide a name of an enclosing block read before, in which case that name is read until
+    // then.
+    pending: HashMap<String, (String, bool)>,
+    depth: usize,
+}
+
+impl Scope {
+    // Creates the scope of the built-in functions, around the script and each included file.
+    fn builtins() -> Scope {
+        let mut scope = Scope::default();
+        for (name, function) in BUILTINS {
+            scope.insert(name, Symbol { rust: format!("runtime::{function}"), kind: Kind::Builtin, cell: false, depth: 0 });
+        }
+        for name in UNSUPPORTED {
+            scope.insert(name, Symbol { rust: identifier(name), kind: Kind::Unsupported, cell: false, depth: 0 });
+        }
+        scope
+    }
+
+    fn insert(&mut self, name: &str, symbol: Symbol) {
+        if self.symbols.insert(name.to_string(), symbol).is_none() {
+            self.order.push(name.to_string());
+        }
+    }
+}
+
+// What surrounds the code being translated, which `return`, `break`, `continue` and the errors
+// leave.
+enum Frame {
+    Function,
+    // A loop, with the label of the script and the one in Rust, whether its `break` gives a value,
+    // and whether a `break` or a `continue` names it.
+    Loop { label: Option<String>, rust: String, valued: bool, named: bool },
+    // A `try` block, with its label and the variable receiving its error.
+    Try { label: String, error: String },
+    // A comprehension, at the given location, whose loop can't be left.
+    Comprehension(Location),
+}
+
+// How a name is read.
+enum Access {
+    Plain(String),
+    Cell(String),
+    Builtin(String),
+    Missing(String),
+}
+
+// A file translated into a function giving what it declares.
+#[derive(Clone)]
+struct Module {
+    path: PathBuf,
+    rust: String,
+    name: String,
+    members: Vec<(String, Symbol)>,
+}
+
+struct Emitter<'a> {
+    loader: &'a mut Loader,
+    // The file being translated, named by the errors that have no location.
+    path: PathBuf,
+    code: String,
+    indent: usize,
+    scopes: Vec<Scope>,
+    frames: Vec<Frame>,
+    // The variables each function being translated reads from around it, cloned into it.
+    captures: Vec<Vec<String>>,
+    modules: Vec<Module>,
+    // The functions of the included files, written after the script.
+    definitions: String,
+    temporaries: usize,
+}
+
+// Translates a script and the files it includes to a cargo package in the output directory. The
+// files are checked like when the script runs, and what can't be translated is reported like an
+// error of the script. Fails when an error was reported.
+pub fn translate(file: &str, output: &Path) -> ExitCode {
+    let mut loader = Loader::new(false);
+    loader.enter(Path::new(file));
+    let translated = loader.load(file).and_then(|script| Emitter::new(&mut loader, file).script(&script));
+    let code = match translated {
+        Ok(code) => code,
+        Err(error) => {
+            loader.report(error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let name = package_name(output);
+    let manifest = format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n");
+    let written = std::fs::create_dir_all(output.join("src")).and_then(|_| {
+        std::fs::write(output.join("Cargo.toml"), manifest)?;
+        std::fs::write(output.join("src").join("main.rs"), code)?;
+        std::fs::write(output.join("src").join("runtime.rs"), RUNTIME)
+    });
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            loader.report(FileError::new(output, format!("Failed to write the Rust package: {error}")));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Returns the name of the package written to a directory, made of the characters cargo allows.
+fn package_name(output: &Path) -> String {
+    let directory = output.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let name: String = directory
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    match name.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => name,
+        _ => format!("mussel-{name}"),
+    }
+}
+
+impl<'a> Emitter<'a> {
+    fn new(loader: &'a mut Loader, file: &str) -> Self {
+        Emitter {
+            loader,
+            path: PathBuf::from(file),
+            code: String::new(),
+            indent: 1,
+            scopes: vec![Scope::builtins()],
+            frames: Vec::new(),
+            captures: Vec::new(),
+            modules: Vec::new(),
+            definitions: String::new(),
+            temporaries: 0,
+        }
+    }
+
+    // Translates the script into the function `main` runs.
+    fn script(mut self, script: &[Expr]) -> Translated<String> {
+        // The value of the last statement is the exit status when it is an integer.
+        self.line("Ok({");
+        self.indent += 1;
+        self.enter(script, &[]);
+        self.statements(script, true)?;
+        self.indent -= 1;
+        self.line("})");
+        let name = self.path.file_name().map_or_else(|| self.path.to_string_lossy(), |name| name.to_string_lossy());
+        Ok(format!(
+            "// Translated from {name} by `mussel emit-rs`.\n\
+             #![allow(unused, unreachable_code, non_snake_case, non_upper_case_globals)]\n\n\
+             #[macro_use]\nmod runtime;\n\nuse runtime::{{Key, Value, Var}};\n\n\
+             fn main() {{\n    runtime::run(script);\n}}\n\n\
+             fn script() -> runtime::Result {{\n{}}}\n{}",
+            self.code, self.definitions
+        ))
+    }
+
+    fn line<S: AsRef<str>>(&mut self, line: S) {
+        self.code.push_str(&padding(self.indent));
+        self.code.push_str(line.as_ref());
+        self.code.push('\n');
+    }
+
+    // Writes lines apart from the code written so far and returns them.
+    fn capture(&mut self, write: impl FnOnce(&mut Self) -> Translated<()>) -> Translated<String> {
+        let code = std::mem::take(&mut self.code);
+        let written = write(self);
+        let captured = std::mem::replace(&mut self.code, code);
+        written.map(|_| captured)
+    }
+
+    // Writes lines apart, as the code of an expression starting where it's used.
+    fn nested(&mut self, write: impl FnOnce(&mut Self) -> Translated<()>) -> Translated<String> {
+        Ok(self.capture(write)?.trim().to_string())
+    }
+
+    fn temporary(&mut self, purpose: &str) -> String {
+        self.temporaries += 1;
+        format!("__{purpose}{}", self.temporaries)
+    }
+
+    // Returns the number of functions around the code being translated.
+    fn depth(&self) -> usize {
+        self.frames.iter().filter(|frame| matches!(frame, Frame::Function)).count()
+    }
+
+    // Returns the symbol of a name: the one declared in the innermost block, or the `Var` it will
+    // be once its declaration runs, unless it hides a name read until then.
+    fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.scopes.iter().rev().find_map(|scope| match (scope.symbols.get(name), scope.pending.get(name)) {
+            (Some(symbol), _) => Some(symbol.clone()),
+            (None, Some((rust, false))) => {
+                Some(Symbol { rust: rust.clone(), kind: Kind::Variable, cell: true, depth: scope.depth })
+            }
+            _ => None,
+        })
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("the script has a scope")
+    }
+
+    // Starts the scope of a block, given what it evaluates and the names declared before it runs,
+    // like parameters. The names a function inside the block reads are declared as `Var`s.
+    fn enter<'e>(&mut self, body: impl IntoIterator<Item = &'e Expr> + Clone, names: &[String]) {
+        let captured = captured(body.clone());
+        let mut declared: Vec<String> = names.to_vec();
+        for name in body.clone().into_iter().flat_map(declared_names) {
+            if !declared.contains(&name) {
+                declared.push(name);
+            }
+        }
+        let cells: HashSet<String> = declared.into_iter().filter(|name| captured.contains(name)).collect();
+        let mut pending = Vec::new();
+        for name in cells.iter().filter(|name| !names.contains(name)) {
+            // A name hiding one its declaration reads, like `let n = n + 1`, is renamed, the
+            // hidden one being read until then.
+            let hides = self.lookup(name).is_some()
+                && body.clone().into_iter().any(|expr| declared_names(expr).contains(name) && mentions(expr, name));
+            let rust = if hides { self.rename(name) } else { identifier(name) };
+            pending.push((name.clone(), rust, hides));
+        }
+        pending.sort();
+        let depth = self.depth();
+        self.scopes.push(Scope { cells, depth, ..Scope::default() });
+        for (name, rust, hides) in pending {
+            self.line(format!("let {rust} = Var::new({});", string(&name)));
+            self.scope().pending.insert(name, (rust, hides));
+        }
+    }
+
+    // Returns a name in Rust for a name of the script that no enclosing block uses.
+    fn rename(&self, name: &str) -> String {
+        let base = identifier(name);
+        let taken = |candidate: &str| {
+            self.scopes.iter().any(|scope| {
+                scope.symbols.values().any(|symbol| symbol.rust == candidate)
+                    || scope.pending.values().any(|(rust, _)| rust == candidate)
+            })
+        };
+        (1..).map(|n| format!("{base}_{n}")).find(|candidate| !taken(candidate)).expect("a name is free")
+    }
+
+    // Declares a name in the current block with the given value.
+    fn define(&mut self, name: &str, kind: Kind, value: String) {
+        let depth = self.depth();
+        let scope = self.scopes.last_mut().expect("the script has a scope");
+        let existing = scope.symbols.get(name).filter(|symbol| symbol.cell).map(|symbol| symbol.rust.clone());
+        let symbol = match existing.or_else(|| scope.pending.remove(name).map(|(rust, _)| rust)) {
+            Some(rust) => {
+                self.line(format!("{rust}.set({value});"));
+                Symbol { rust, kind, cell: true, depth }
+            }
+            None if scope.cells.contains(name) => {
+                let rust = identifier(name);
+                self.line(format!("let {rust} = Var::with({}, {value});", string(name)));
+                Symbol { rust, kind, cell: true, depth }
+            }
+            None => {
+                let rust = identifier(name);
+                self.line(format!("let mut {rust} = {value};"));
+                Symbol { rust, kind, cell: false, depth }
+            }
+        };
+        self.scope().insert(name, symbol);
+    }
+
+    // Declares a built-in function under a name, which is given its value only when a function
+    // reads it.
+    fn define_builtin(&mut self, name: &str, path: String) {
+        let scope = self.scopes.last().expect("the script has a scope");
+        if scope.cells.contains(name) {
+            self.define(name, Kind::Variable, format!("runtime::builtin({path})"));
+        } else {
+            let depth = self.depth();
+            self.scope().insert(name, Symbol { rust: path, kind: Kind::Builtin, cell: false, depth });
+        }
+    }
+
+    // Returns the symbol of a name read by the script, noting that the functions between its
+    // declaration and here read it.
+    fn resolve(&mut self, name: &str, location: &Location) -> Translated<Option<Symbol>> {
+        let Some(symbol) = self.lookup(name) else {
+            return Ok(None);
+        };
+        match symbol.kind {
+            Kind::Unsupported => Err(unsupported(
+                Some(location),
+                &self.path,
+                format!("`{name}` can't be translated to Rust"),
+                "it works with the variables of the running script",
+            )),
+            Kind::Builtin => Ok(Some(symbol)),
+            _ => {
+                for captures in &mut self.captures[symbol.depth..] {
+                    if !captures.contains(&symbol.rust) {
+                        captures.push(symbol.rust.clone());
+                    }
+                }
+                Ok(Some(symbol))
+            }
+        }
+    }
+
+    fn access(&mut self, name: &str, location: &Location) -> Translated<Access> {
+        Ok(match self.resolve(name, location)? {
+            Some(Symbol { rust, kind: Kind::Builtin, .. }) => Access::Builtin(rust),
+            Some(Symbol { rust, cell: true, .. }) => Access::Cell(rust),
+            Some(Symbol { rust, .. }) => Access::Plain(rust),
+            None => Access::Missing(name.to_string()),
+        })
+    }
+
+    // Returns the code of a value read from a name.
+    fn read(&self, access: Access) -> String {
+        match access {
+            Access::Plain(rust) => format!("{rust}.clone()"),
+            Access::Cell(rust) => self.fallible(&format!("{rust}.get()")),
+            Access::Builtin(path) => format!("runtime::builtin({path})"),
+            Access::Missing(name) => self.fallible(&format!("runtime::undefined::<Value>({})", string(&name))),
+        }
+    }
+
+    // Returns the code of a call that can fail: the error leaves the innermost `try` block, or
+    // else the function.
+    fn fallible(&self, code: &str) -> String {
+        for frame in self.frames.iter().rev() {
+            match frame {
+                Frame::Try { label, error } => return format!("attempt!({code}, {label}, {error})"),
+                Frame::Function => break,
+                _ => {}
+            }
+        }
+        format!("{code}?")
+    }
+
+    fn statements(&mut self, body: &[Expr], valued: bool) -> Translated<()> {
+        for (i, expr) in body.iter().enumerate() {
+            if valued && i + 1 == body.len() && !is_statement(expr) {
+                let code = self.expression(expr)?;
+                self.line(code);
+                return Ok(());
+            }
+            self.statement(expr)?;
+        }
+        if valued {
+            self.line("Value::Void");
+        }
+        Ok(())
+    }
+
+    // Writes a block of statements, in its own scope, one level deeper. The value of a block used
+    // as a value is the one of its last statement.
+    fn block(&mut self, body: &[Expr], valued: bool) -> Translated<()> {
+        self.indent += 1;
+        self.enter(body, &[]);
+        self.statements(body, valued)?;
+        self.scopes.pop();
+        self.indent -= 1;
+        Ok(())
+    }
+
+    fn statement(&mut self, expr: &Expr) -> Translated<()> {
+        match expr {
+            Expr::Void => {}
+            Expr::Let(binding, value, _) => self.binding(binding, value, Kind::Variable)?,
+            Expr::Const(binding, value, _) => self.binding(binding, value, Kind::Constant)?,
+            Expr::Assign(place, value, location) => self.assign(place, value, location)?,
+            Expr::Function(name, parameters, _, body) => self.function_declaration(name, parameters, body)?,
+            Expr::Struct(name, fields, _) => {
+                let fields = fields.iter().map(|field| string(field)).collect::<Vec<_>>().join(", ");
+                self.define(name, Kind::Struct, format!("runtime::structure({}, &[{fields}])", string(name)));
+            }
+            Expr::Enum(name, variants) => {
+                let variants = variants
+                    .iter()
+                    .map(|(variant, fields)| format!("({}, {})", string(variant), fields.len()))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                self.define(name, Kind::Enum, format!("runtime::enumeration({}, &[{variants}])", string(name)));
+            }
+            Expr::Trait(name, methods) => {
+                let methods = methods.iter().map(|method| string(method)).collect::<Vec<_>>().join(", ");
+                self.define(name, Kind::Constant, format!("runtime::interface({}, &[{methods}])", string(name)));
+            }
+            Expr::Impl(name, trait_name, methods, location) => self.implementation(name, trait_name, methods, location)?,
+            Expr::Include(target, alias, location) => self.include(target, alias, location)?,
+            Expr::Return(value) => self.return_statement(value)?,
+            Expr::Throw(value, _) => {
+                let value = self.expression(value)?;
+                let code = self.fallible(&format!("runtime::throw({value})"));
+                self.line(format!("{code};"));
+            }
+            Expr::Break(value, _, location) => self.break_statement(value, location)?,
+            Expr::Continue(label, location) => {
+                let label = self.find_loop(label.as_deref(), "continue", location)?;
+                self.line(format!("continue{label};"));
+            }
+            Expr::Assert(condition, message, _) => {
+                let holds = self.condition(condition, Check::Condition("assert"))?;
+                let failed = self.assertion(message.as_deref())?;
+                self.line(format!("if !({holds}) {{"));
+                self.indent += 1;
+                self.line(format!("{failed};"));
+                self.indent -= 1;
+                self.line("}");
+            }
+            Expr::If(..) | Expr::Match(..) | Expr::Loop(..) | Expr::Try(..) | Expr::For(..) | Expr::Until(..) => {
+                let code = self.construct(expr, false)?;
+                self.line(code);
+            }
+            expr => {
+                let code = self.expression(expr)?;
+                self.line(format!("{code};"));
+            }
+        }
+        Ok(())
+    }
+
+    fn binding(&mut self, binding: &Binding, value: &Expr, kind: Kind) -> Translated<()> {
+        match (binding, value) {
+            (Binding::Wildcard, value) => self.statement(value),
+            // A closure is named after the variable, like in the errors of the interpreter.
+            (Binding::Name(name), Expr::Closure(parameters, _, body, None)) => {
+                let code = self.function(name, parameters, body)?;
+                self.define(name, kind, code);
+                Ok(())
+            }
+            (binding, value) => {
+                let code = self.expression(value)?;
+                self.bind(binding, code, kind);
+                Ok(())
+            }
+        }
+    }
+
+    // Declares the names of a binding with the parts of a value.
+    fn bind(&mut self, binding: &Binding, value: String, kind: Kind) {
+        match binding {
+            Binding::Name(name) => self.define(name, kind, value),
+            Binding::Wildcard => self.line(format!("{value};")),
+            Binding::Array(bindings, _) => {
+                let values = self.temporary("values");
+                let unpacked = self.fallible(&format!("runtime::unpack({value})"));
+                self.line(format!("let {values}: [Value; {}] = {unpacked};", bindings.len()));
+                for (i, binding) in bindings.iter().enumerate() {
+                    if !matches!(binding, Binding::Wildcard) {
+                        self.bind(binding, format!("{values}[{i}].clone()"), kind);
+                    }
+                }
+            }
+        }
+    }
+
+    fn assign(&mut self, place: &Expr, value: &Expr, location: &Location) -> Translated<()> {
+        let value = self.expression(value)?;
+        let mut keys = Vec::new();
+        let mut root = place;
+        let name = loop {
+            match root {
+                Expr::Get(array, index, _) => {
+                    keys.push(format!("Key::Index({})", self.expression(index)?));
+                    root = array;
+                }
+                Expr::Field(instance, field, _) => {
+                    keys.push(format!("Key::Field({})", string(field)));
+                    root = instance;
+                }
+                Expr::Variable(name, _) => break name,
+                _ => {
+                    return Err(error::boxed(EvaluationError::new(
+                        location.clone(),
+                        "invalid left-hand side of assignment".to_string(),
+                        "only variables, their elements and their fields can be assigned".to_string(),
+                    )));
+                }
+            }
+        };
+        keys.reverse();
+        let symbol = match self.resolve(name, location)? {
+            Some(Symbol { kind: Kind::Constant | Kind::Struct | Kind::Enum, .. }) => {
+                return Err(error::boxed(ConstantAssignmentError::new(location.clone(), name.clone())));
+            }
+            Some(symbol) if symbol.kind != Kind::Builtin => symbol,
+            _ => {
+                let code = self.fallible(&format!("runtime::undefined::<()>({})", string(name)));
+                self.line(format!("{code};"));
+                return Ok(());
+            }
+        };
+        // A function only gets copies of the variables that aren't `Var`s.
+        if !symbol.cell && symbol.depth < self.depth() {
+            return Err(unsupported(
+                Some(location),
+                &self.path,
+                format!("`{name}` can't be assigned by a function in Rust"),
+                "declared by an included file, outside of the function",
+            ));
+        }
+        let rust = symbol.rust;
+        match (keys.is_empty(), symbol.cell) {
+            (true, false) => self.line(format!("{rust} = {value};")),
+            (true, true) => self.line(format!("{rust}.set({value});")),
+            (false, cell) => {
+                let assigned = match cell {
+                    true => self.fallible(&format!("{rust}.assign(&__keys, __value)")),
+                    false => self.fallible(&format!("runtime::set(&mut {rust}, &__keys, __value)")),
+                };
+                self.line("{");
+                self.indent += 1;
+                self.line(format!("let __value = {value};"));
+                self.line(format!("let __keys = [{}];", keys.join(", ")));
+                self.line(format!("{assigned};"));
+                self.indent -= 1;
+                self.line("}");
+            }
+        }
+        Ok(())
+    }
+
+    fn function_declaration(&mut self, name: &str, parameters: &[Parameter], body: &[Expr]) -> Translated<()> {
+        let code = self.function(name, parameters, body)?;
+        self.define(name, Kind::Variable, code);
+        if OPERATOR_FUNCTIONS.contains(&name) {
+            let function = match self.lookup(name) {
+                Some(Symbol { rust, cell: true, .. }) => format!("&{}", self.fallible(&format!("{rust}.get()"))),
+                Some(symbol) => format!("&{}", symbol.rust),
+                None => unreachable!("the function was just declared"),
+            };
+            self.line(format!("runtime::overload({}, {function});", string(name)));
+        }
+        Ok(())
+    }
+
+    // Returns the code of a function, a closure given the arguments. The variables it reads from
+    // around it are cloned into it, which for a `Var` shares it.
+    fn function(&mut self, name: &str, parameters: &[Parameter], body: &[Expr]) -> Translated<String> {
+        let names: Vec<String> = parameters.iter().map(|parameter| parameter.name.clone()).collect();
+        let required = parameters.iter().take_while(|parameter| parameter.default.is_none()).count();
+        self.frames.push(Frame::Function);
+        self.captures.push(Vec::new());
+        self.indent += 1;
+        let translated = self.capture(|emitter| {
+            emitter.enter(body, &names);
+            let rust: Vec<String> = names.iter().map(|name| identifier(name)).collect();
+            let declared = |names: &[String]| names.iter().map(|name| format!("mut {name}")).collect::<Vec<_>>().join(", ");
+            if required == names.len() {
+                emitter.line(format!(
+                    "let [{}] = runtime::arguments({}, arguments)?;",
+                    declared(&rust),
+                    string(name)
+                ));
+            } else {
+                emitter.line(format!(
+                    "let ([{}], [{}]) = runtime::optional_arguments({}, arguments)?;",
+                    declared(&rust[..required]),
+                    rust[required..].join(", "),
+                    string(name)
+                ));
+            }
+            let depth = emitter.depth();
+            for (parameter, rust) in parameters.iter().zip(rust) {
+                if let Some(default) = &parameter.default {
+                    let default = emitter.expression(default)?;
+                    emitter.line(format!("let mut {rust} = match {rust} {{ Some({rust}) => {rust}, None => {default} }};"));
+                }
+                if emitter.scope().cells.contains(&parameter.name) {
+                    emitter.define(&parameter.name, Kind::Variable, rust);
+                } else {
+                    emitter.scope().insert(&parameter.name, Symbol { rust, kind: Kind::Variable, cell: false, depth });
+                }
+            }
+            emitter.statements(body, false)?;
+            emitter.line("Ok(Value::Void)");
+            emitter.scopes.pop();
+            Ok(())
+        });
+        self.indent -= 1;
+        self.frames.pop();
+        let captures = self.captures.pop().expect("the function has captures");
+        let body = translated?;
+        let parameters = names.iter().map(|name| string(name)).collect::<Vec<_>>().join(", ");
+        let header = format!("runtime::function(&[{parameters}], move |arguments| {{");
+        if captures.is_empty() {
+            return Ok(format!("{header}\n{body}{}}})", padding(self.indent)));
+        }
+        let inner = padding(self.indent + 1);
+        let mut code = String::from("{\n");
+        for capture in captures {
+            code.push_str(&format!("{inner}let {capture} = {capture}.clone();\n"));
+        }
+        code.push_str(&format!("{inner}{header}\n"));
+        for line in body.lines() {
+            code.push_str(&format!("    {line}\n"));
+        }
+        code.push_str(&format!("{inner}}})\n{}}}", padding(self.indent)));
+        Ok(code)
+    }
+
+    fn implementation(&mut self, name: &str, trait_name: &Option<String>, methods: &[Expr], location: &Location) -> Translated<()> {
+        let definition = self.borrowed(&Expr::Variable(name.to_string(), location.clone()))?;
+        let interface = match trait_name {
+            Some(trait_name) => format!("Some({})", self.borrowed(&Expr::Variable(trait_name.clone(), location.clone()))?),
+            None => "None".to_string(),
+        };
+        let mut code = format!("runtime::implement({definition}, {interface}, vec![\n");
+        self.indent += 1;
+        for method in methods {
+            let Expr::Function(method, parameters, _, body) = method else {
+                unreachable!("an `impl` block only contains functions");
+            };
+            let function = self.function(method, parameters, body)?;
+            code.push_str(&format!("{}({}, {function}),\n", padding(self.indent), string(method)));
+        }
+        self.indent -= 1;
+        code.push_str(&format!("{}])", padding(self.indent)));
+        let code = self.fallible(&code);
+        self.line(format!("{code};"));
+        Ok(())
+    }
+
+    fn include(&mut self, target: &IncludeTarget, alias: &Option<String>, location: &Location) -> Translated<()> {
+        let library = match target {
+            IncludeTarget::Name(name) => LIBRARIES.iter().find(|(library, _)| library == name),
+            IncludeTarget::Path(_) => None,
+        };
+        let module = match (target, library) {
+            (_, Some((name, functions))) => {
+                match alias {
+                    Some(alias) => self.define(alias, Kind::Variable, format!("runtime::library({})", string(name))),
+                    None => {
+                        for function in *functions {
+                            self.define_builtin(function, format!("runtime::{name}::{function}"));
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            (IncludeTarget::Name(name), None) => {
+                let file = PathBuf::from(format!("{name}.mus"));
+                match stdlib::source(name) {
+                    Some(source) => self.module(Path::new("<std>").join(file), Some(source), location)?,
+                    None => {
+                        let found = self.find(&file, location)?;
+                        self.module(found, None, location)?
+                    }
+                }
+            }
+            (IncludeTarget::Path(path), None) => {
+                let found = self.find(Path::new(path), location)?;
+                self.module(found, None, location)?
+            }
+        };
+        let included = self.fallible(&format!("runtime::include({}, {})", string(&module.name), module.rust));
+        if let Some(alias) = alias {
+            self.define(alias, Kind::Variable, included);
+            return Ok(());
+        }
+        let declarations = self.temporary("module");
+        self.line(format!("let {declarations} = {included};"));
+        for (name, symbol) in module.members {
+            match symbol.kind {
+                Kind::Builtin => self.define_builtin(&name, symbol.rust),
+                kind => {
+                    let member = self.fallible(&format!("runtime::field(&{declarations}, {})", string(&name)));
+                    self.define(&name, kind, member);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find(&self, path: &Path, location: &Location) -> Translated<PathBuf> {
+        self.loader
+            .find(location.file(), path)
+            .map_err(|searched| error::boxed(IncludeError::new(location.clone(), path.to_path_buf(), searched)))
+    }
+
+    // Translates an included file, once, into a function returning what it declares.
+    fn module(&mut self, path: PathBuf, source: Option<&str>, location: &Location) -> Translated<Module> {
+        if let Some(module) = self.modules.iter().find(|module| module.path == path) {
+            return Ok(module.clone());
+        }
+        if let Some(cycle) = self.loader.enter(&path) {
+            return Err(error::boxed(CircularIncludeError::new(location.clone(), cycle)));
+        }
+        let file = match source {
+            Some(source) => self.loader.load_source(&path, source)?,
+            None => self.loader.load(&path)?,
+        };
+        let name = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        let base: String = name.chars().map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' }).collect();
+        let rust = (0..)
+            .map(|n| if n == 0 { format!("module_{base}") } else { format!("module_{base}{n}") })
+            .find(|candidate| self.modules.iter().all(|module| module.rust != *candidate))
+            .expect("a name is free");
+
+        // The file runs apart from the script, with only the built-in functions around it.
+        let scopes = std::mem::replace(&mut self.scopes, vec![Scope::builtins()]);
+        let frames = std::mem::take(&mut self.frames);
+        let captures = std::mem::take(&mut self.captures);
+        let including = std::mem::replace(&mut self.path, path.clone());
+        let indent = std::mem::replace(&mut self.indent, 1);
+        let body = self.capture(|emitter| {
+            emitter.enter(&file, &[]);
+            emitter.statements(&file, false)?;
+            let scope = emitter.scopes.last().expect("the file has a scope");
+            let exported: Vec<String> = scope
+                .order
+                .iter()
+                .map(|name| {
+                    let value = match &scope.symbols[name] {
+                        Symbol { kind: Kind::Builtin, rust, .. } => format!("runtime::builtin({rust})"),
+                        Symbol { cell: true, rust, .. } => format!("{rust}.get()?"),
+                        Symbol { rust, .. } => format!("{rust}.clone()"),
+                    };
+                    format!("{}({}, {value}),", padding(2), string(name))
+                })
+                .collect();
+            emitter.line("Ok(vec![");
+            for member in exported {
+                emitter.code.push_str(&member);
+                emitter.code.push('\n');
+            }
+            emitter.line("])");
+            Ok(())
+        });
+        let members = self.scopes.get(1).map_or_else(Vec::new, |scope| {
+            scope.order.iter().map(|name| (name.clone(), scope.symbols[name].clone())).collect()
+        });
+        self.scopes = scopes;
+        self.frames = frames;
+        self.captures = captures;
+        self.path = including;
+        self.indent = indent;
+        self.loader.leave();
+
+        self.definitions.push_str(&format!(
+            "\nfn {rust}() -> runtime::Result<Vec<(&'static str, Value)>> {{\n{}}}\n",
+            body?
+        ));
+        let module = Module { path, rust, name, members };
+        self.modules.push(module.clone());
+        Ok(module)
+    }
+
+    fn return_statement(&mut self, value: &Expr) -> Translated<()> {
+        if self.depth() == 0 {
+            return Err(unsupported(
+                location(value),
+                &self.path,
+                "`return` outside of a function can't be translated to Rust".to_string(),
+                "not inside a function",
+            ));
+        }
+        let code = self.expression(value)?;
+        self.line(format!("return Ok({code});"));
+        Ok(())
+    }
+
+    fn break_statement(&mut self, value: &Expr, location: &Location) -> Translated<()> {
+        // `break outer` leaves the loop labeled `outer`, when there is one.
+        if let Expr::Variable(name, _) = value
+            && self.frames.iter().rev().take_while(|frame| !matches!(frame, Frame::Function)).any(
+                |frame| matches!(frame, Frame::Loop { label: Some(label), .. } if label == name),
+            )
+        {
+            let label = self.find_loop(Some(name), "break", location)?;
+            let valued = self.frames.iter().rev().any(
+                |frame| matches!(frame, Frame::Loop { label: Some(label), valued: true, .. } if label == name),
+            );
+            self.line(if valued { format!("break{label} Value::Void;") } else { format!("break{label};") });
+            return Ok(());
+        }
+        let label = self.find_loop(None, "break", location)?;
+        let valued = self.frames.iter().rev().find_map(|frame| match frame {
+            Frame::Loop { valued, .. } => Some(*valued),
+            _ => None,
+        });
+        if valued == Some(true) {
+            let code = self.expression(value)?;
+            self.line(format!("break{label} {code};"));
+        } else {
+            self.statement(value)?;
+            self.line(format!("break{label};"));
+        }
+        Ok(())
+    }
+
+    // Finds the loop left by a `break` or a `continue`: the one with the label, or else the
+    // innermost one. Returns the label it's left with, with a space before, when it takes one.
+    fn find_loop(&mut self, label: Option<&str>, keyword: &str, location: &Location) -> Translated<String> {
+        let mut labeled = false;
+        for frame in self.frames.iter_mut().rev() {
+            match frame {
+                Frame::Loop { label: name, rust, named, .. } if label.is_none() || name.as_deref() == label => {
+                    if !labeled {
+                        return Ok(String::new());
+                    }
+                    *named = true;
+                    return Ok(format!(" {rust}"));
+                }
+                Frame::Loop { .. } | Frame::Try { .. } => labeled = true,
+                Frame::Comprehension(comprehension) => {
+                    return Err(unsupported(
+                        Some(location).or(Some(comprehension)),
+                        &self.path,
+                        format!("`{keyword}` can't leave a comprehension in Rust"),
+                        "inside a comprehension",
+                    ));
+                }
+                Frame::Function => break,
+            }
+        }
+        Err(match label {
+            Some(label) => error::boxed(UndeclaredLabelError::new(location.clone(), label.to_string())),
+            None => error::boxed(LoopControlError::new(location.clone(), keyword.to_string())),
+        })
+    }
+
+    // Returns the code of an `if`, `match`, loop or `try`, which gives a value when `valued` is
+    // set and else runs as a statement.
+    fn construct(&mut self, expr: &Expr, valued: bool) -> Translated<String> {
+        match expr {
+            Expr::If(condition, then, otherwise, _) => self.if_expression(condition, then, otherwise.as_deref(), valued),
+            Expr::Match(value, arms) => self.match_expression(value, arms, valued),
+            Expr::Try(body, name, catch) => self.try_expression(body, name, catch, valued),
+            Expr::Loop(body, label) => {
+                let code = self.looping(label, valued, |emitter| {
+                    emitter.block(body, false)?;
+                    Ok("loop".to_string())
+                })?;
+                Ok(code)
+            }
+            Expr::For(binding, collection, body, _, label) => {
+                let collection = self.expression(collection)?;
+                let items = self.fallible(&format!("runtime::iterate({collection})"));
+                let code = self.looping(label, false, |emitter| {
+                    let mut names = Vec::new();
+                    binding_names(binding, &mut names);
+                    emitter.indent += 1;
+                    emitter.enter(body, &names);
+                    let item = emitter.fallible("__item");
+                    emitter.bind(binding, item, Kind::Variable);
+                    emitter.statements(body, false)?;
+                    emitter.scopes.pop();
+                    emitter.indent -= 1;
+                    Ok(format!("for __item in {items}"))
+                })?;
+                Ok(if valued { self.voided(code) } else { code })
+            }
+            Expr::Until(condition, body, _, label) => {
+                let code = self.looping(label, false, |emitter| {
+                    let condition = emitter.condition(condition, Check::Condition("until"))?;
+                    emitter.block(body, false)?;
+                    Ok(format!("while !({condition})"))
+                })?;
+                Ok(if valued { self.voided(code) } else { code })
+            }
+            _ => unreachable!("only blocks are constructs"),
+        }
+    }
+
+    // Writes a loop, given the body written as it returns the loop keyword. The loop is labeled
+    // when a `break` or `continue` names it.
+    fn looping(
+        &mut self,
+        label: &Option<String>,
+        valued: bool,
+        write: impl FnOnce(&mut Self) -> Translated<String>,
+    ) -> Translated<String> {
+        let rust = format!("'{}", self.temporary("loop").trim_start_matches('_'));
+        self.frames.push(Frame::Loop { label: label.clone(), rust: rust.clone(), valued, named: false });
+        let mut keyword = String::new();
+        let body = self.capture(|emitter| {
+            keyword = write(emitter)?;
+            Ok(())
+        });
+        let Some(Frame::Loop { named, .. }) = self.frames.pop() else {
+            unreachable!("the loop is the innermost frame");
+        };
+        let body = body?;
+        let label = if named { format!("{rust}: ") } else { String::new() };
+        Ok(format!("{label}{keyword} {{\n{body}{}}}", padding(self.indent)))
+    }
+
+    // Returns the code of a loop that gives no value used as a value.
+    fn voided(&self, code: String) -> String {
+        let inner = padding(self.indent + 1);
+        let code = code.replace('\n', "\n    ");
+        format!("{{\n{inner}{code}\n{inner}Value::Void\n{}}}", padding(self.indent))
+    }
+
+    fn if_expression(&mut self, condition: &Expr, then: &[Expr], otherwise: Option<&[Expr]>, valued: bool) -> Translated<String> {
+        self.nested(|emitter| {
+            let condition = emitter.condition(condition, Check::Condition("if"))?;
+            emitter.line(format!("if {condition} {{"));
+            emitter.block(then, valued)?;
+            let mut otherwise = otherwise;
+            loop {
+                match otherwise {
+                    Some([Expr::If(condition, then, next, _)]) => {
+                        let condition = emitter.condition(condition, Check::Condition("if"))?;
+                        emitter.line(format!("}} else if {condition} {{"));
+                        emitter.block(then, valued)?;
+                        otherwise = next.as_deref();
+                    }
+                    Some(otherwise) => {
+                        emitter.line("} else {");
+                        emitter.block(otherwise, valued)?;
+                        break;
+                    }
+                    None if valued => {
+                        emitter.line("} else {");
+                        emitter.indent += 1;
+                        emitter.line("Value::Void");
+                        emitter.indent -= 1;
+                        break;
+                    }
+                    None => break,
+                }
+            }
+            emitter.line("}");
+            Ok(())
+        })
+    }
+
+    // Returns the code of a `match`, a chain of `if` each testing a pattern then declaring its names.
+    fn match_expression(&mut self, value: &Expr, arms: &[(Pattern, Vec<Expr>)], valued: bool) -> Translated<String> {
+        let value = self.expression(value)?;
+        self.nested(|emitter| {
+            let subject = emitter.temporary("match");
+            emitter.line("{");
+            emitter.indent += 1;
+            emitter.line(format!("let {subject} = {value};"));
+            let mut closed = false;
+            for (i, (pattern, body)) in arms.iter().enumerate() {
+                let mut conditions = Vec::new();
+                let mut bindings = Vec::new();
+                test(pattern, &subject, &mut conditions, &mut bindings);
+                let always = conditions.is_empty();
+                match (i, always) {
+                    (0, true) => emitter.line("{"),
+                    (_, true) => emitter.line("} else {"),
+                    (0, false) => emitter.line(format!("if {} {{", conditions.join(" && "))),
+                    (_, false) => emitter.line(format!("}} else if {} {{", conditions.join(" && "))),
+                }
+                let names: Vec<String> = bindings.iter().map(|(name, _)| name.clone()).collect();
+                emitter.indent += 1;
+                emitter.enter(body, &names);
+                for (name, element) in bindings {
+                    emitter.define(&name, Kind::Variable, element);
+                }
+                emitter.statements(body, valued)?;
+                emitter.scopes.pop();
+                emitter.indent -= 1;
+                if always {
+                    closed = true;
+                    break;
+                }
+            }
+            match (arms.is_empty(), closed || !valued) {
+                (true, _) if valued => emitter.line("Value::Void"),
+                (true, _) => {}
+                (false, true) => emitter.line("}"),
+                (false, false) => {
+                    emitter.line("} else {");
+                    emitter.indent += 1;
+                    emitter.line("Value::Void");
+                    emitter.indent -= 1;
+                    emitter.line("}");
+                }
+            }
+            emitter.indent -= 1;
+            emitter.line("}");
+            Ok(())
+        })
+    }
+
+    // Returns the code of a `try`: its block is labeled, and an error inside it leaves the block
+    // for the `catch`.
+    fn try_expression(&mut self, body: &[Expr], name: &str, catch: &[Expr], valued: bool) -> Translated<String> {
+        self.nested(|emitter| {
+            let label = format!("'{}", emitter.temporary("try").trim_start_matches('_'));
+            let error = format!("__error{}", emitter.temporaries);
+            emitter.line("{");
+            emitter.indent += 1;
+            emitter.line(format!("let mut {error}: Option<runtime::Error> = None;"));
+            emitter.line(if valued { format!("let __value = {label}: {{") } else { format!("{label}: {{") });
+            emitter.frames.push(Frame::Try { label, error: error.clone() });
+            let translated = emitter.block(body, true);
+            emitter.frames.pop();
+            translated?;
+            emitter.line("};");
+            emitter.line(format!("if let Some(__error) = {error} {{"));
+            emitter.indent += 1;
+            emitter.enter(catch, &[name.to_string()]);
+            let caught = emitter.fallible("runtime::caught(__error)");
+            emitter.define(name, Kind::Variable, caught);
+            emitter.statements(catch, valued)?;
+            emitter.scopes.pop();
+            emitter.indent -= 1;
+            if valued {
+                emitter.line("} else {");
+                emitter.indent += 1;
+                emitter.line("__value");
+                emitter.indent -= 1;
+            }
+            emitter.line("}");
+            emitter.indent -= 1;
+            emitter.line("}");
+            Ok(())
+        })
+    }
+
+    // Returns the code of a failed `assert`, which gives its message.
+    fn assertion(&mut self, message: Option<&Expr>) -> Translated<String> {
+        let message = match message {
+            Some(message) => format!("Some({})", self.borrowed(message)?),
+            None => "None".to_string(),
+        };
+        Ok(self.fallible(&format!("runtime::assertion({message})")))
+    }
+
+    // Returns the code of a boolean of Rust from a value of the script, checked like the
+    // condition of a construct or an operand of `and` and `or`.
+    fn condition(&mut self, expr: &Expr, check: Check) -> Translated<String> {
+        Ok(match expr {
+            Expr::Constant(Atom::Boolean(boolean)) => boolean.to_string(),
+            Expr::Compare(left, operator, right, _) => {
+                let function = match operator {
+                    Operator::Equal => "eq",
+                    Operator::NotEqual => "ne",
+                    Operator::LessThan => "lt",
+                    Operator::LessThanEqual => "le",
+                    Operator::GreaterThan => "gt",
+                    Operator::GreaterThanEqual => "ge",
+                };
+                let (left, right) = (self.borrowed(left)?, self.borrowed(right)?);
+                self.fallible(&format!("runtime::{function}({left}, {right})"))
+            }
+            Expr::Logic(left, operator, right, _) => {
+                let keyword = match operator {
+                    LogicOp::And => "and",
+                    LogicOp::Or => "or",
+                };
+                let mut side = |expr: &Expr| -> Translated<String> {
+                    let code = self.condition(expr, Check::Operand(keyword))?;
+                    Ok(match expr {
+                        Expr::Logic(_, inner, _, _) if inner != operator => format!("({code})"),
+                        _ => code,
+                    })
+                };
+                let left = side(left)?;
+                let right = side(right)?;
+                let operator = match operator {
+                    LogicOp::And => "&&",
+                    LogicOp::Or => "||",
+                };
+                format!("{left} {operator} {right}")
+            }
+            Expr::Unary(UnaryOp::Not, operand, _) => match &**operand {
+                operand @ (Expr::Compare(..) | Expr::Logic(..) | Expr::Unary(UnaryOp::Not, ..)) => {
+                    format!("!({})", self.condition(operand, check)?)
+                }
+                operand => {
+                    let operand = self.borrowed(operand)?;
+                    self.fallible(&format!("runtime::not({operand})"))
+                }
+            },
+            expr => {
+                let value = self.borrowed(expr)?;
+                match check {
+                    Check::Condition(keyword) => self.fallible(&format!("runtime::condition({value}, {})", string(keyword))),
+                    Check::Operand(keyword) => self.fallible(&format!("runtime::operand({value}, {})", string(keyword))),
+                }
+            }
+        })
+    }
+
+    // Returns the code of a reference to a value, borrowing a variable rather than cloning it.
+    fn borrowed(&mut self, expr: &Expr) -> Translated<String> {
+        if let Expr::Variable(name, location) = expr
+            && let Access::Plain(rust) = self.access(name, location)?
+        {
+            return Ok(format!("&{rust}"));
+        }
+        Ok(format!("&{}", self.expression(expr)?))
+    }
+
+    // Returns the code of an expression, giving a `Value`.
+    fn expression(&mut self, expr: &Expr) -> Translated<String> {
+        Ok(match expr {
+            Expr::Void => "Value::Void".to_string(),
+            Expr::Constant(atom) => literal(atom),
+            Expr::Array(elements) => format!("Value::from(vec![{}])", self.list(elements)?),
+            Expr::Variable(name, location) => {
+                let access = self.access(name, location)?;
+                self.read(access)
+            }
+            Expr::Interpolation(parts) => self.interpolation(parts)?,
+            Expr::Call(callee, arguments, _) => {
+                if let Expr::Variable(name, location) = &**callee
+                    && let Access::Builtin(path) = self.access(name, location)?
+                {
+                    let arguments = self.list(arguments)?;
+                    return Ok(self.fallible(&format!("{path}(vec![{arguments}])")));
+                }
+                let callee = self.borrowed(callee)?;
+                let arguments = self.list(arguments)?;
+                self.fallible(&format!("runtime::call({callee}, vec![{arguments}])"))
+            }
+            // The function with the name of the method, if there is one, is called when the
+            // receiver has no such method.
+            Expr::MethodCall(receiver, method, arguments, location) => {
+                let receiver = self.borrowed(receiver)?;
+                let fallback = match self.lookup(method).map(|symbol| symbol.kind) {
+                    Some(Kind::Unsupported) | None => "None".to_string(),
+                    Some(_) => match self.access(method, location)? {
+                        Access::Plain(rust) => format!("Some({rust}.clone())"),
+                        Access::Cell(rust) => format!("{rust}.get().ok()"),
+                        Access::Builtin(path) => format!("Some(runtime::builtin({path}))"),
+                        Access::Missing(_) => "None".to_string(),
+                    },
+                };
+                let arguments = self.list(arguments)?;
+                self.fallible(&format!("runtime::invoke({receiver}, {}, {fallback}, vec![{arguments}])", string(method)))
+            }
+            Expr::Compare(..) | Expr::Logic(..) | Expr::Unary(UnaryOp::Not, ..) => {
+                format!("Value::Bool({})", self.condition(expr, Check::Condition("if"))?)
+            }
+            Expr::Binary(left, operator, right, _) => {
+                let function = match operator {
+                    BinOp::Add => "add",
+                    BinOp::Sub => "sub",
+                    BinOp::Mul => "mul",
+                    BinOp::Div => "div",
+                    BinOp::Pow => "pow",
+                };
+                let (left, right) = (self.borrowed(left)?, self.borrowed(right)?);
+                self.fallible(&format!("runtime::{function}({left}, {right})"))
+            }
+            Expr::Unary(UnaryOp::Neg, operand, _) => match &**operand {
+                Expr::Constant(Atom::Number(number)) => format!("Value::Int(-{number})"),
+                Expr::Constant(Atom::Float(float)) => format!("Value::Float(-{float:?})"),
+                operand => {
+                    let operand = self.borrowed(operand)?;
+                    self.fallible(&format!("runtime::neg({operand})"))
+                }
+            },
+            Expr::Range(start, end, inclusive, _) => {
+                let (start, end) = (self.borrowed(start)?, self.borrowed(end)?);
+                self.fallible(&format!("runtime::interval({start}, {end}, {inclusive})"))
+            }
+            Expr::Get(array, index, _) => {
+                let (array, index) = (self.borrowed(array)?, self.borrowed(index)?);
+                self.fallible(&format!("runtime::index({array}, {index})"))
+            }
+            Expr::Field(instance, field, _) => {
+                let instance = self.borrowed(instance)?;
+                self.fallible(&format!("runtime::field({instance}, {})", string(field)))
+            }
+            Expr::Path(name, member, location) => {
+                let definition = self.borrowed(&Expr::Variable(name.clone(), location.clone()))?;
+                self.fallible(&format!("runtime::path({definition}, {})", string(member)))
+            }
+            Expr::Closure(parameters, _, body, None) => self.function("<anonymous>", parameters, body)?,
+            Expr::Comprehension(value, binding, collection, location, condition) => {
+                self.comprehension(value, binding, collection, location, condition.as_ref().map(|(condition, _)| &**condition))?
+            }
+            Expr::Assert(condition, message, _) => {
+                let holds = self.condition(condition, Check::Condition("assert"))?;
+                let failed = self.assertion(message.as_deref())?;
+                format!("if {holds} {{ Value::Void }} else {{ {failed} }}")
+            }
+            Expr::If(..) | Expr::Match(..) | Expr::Loop(..) | Expr::Try(..) | Expr::For(..) | Expr::Until(..) => {
+                self.construct(expr, true)?
+            }
+            Expr::Let(..)
+            | Expr::Const(..)
+            | Expr::Assign(..)
+            | Expr::Function(..)
+            | Expr::Struct(..)
+            | Expr::Impl(..)
+            | Expr::Trait(..)
+            | Expr::Enum(..)
+            | Expr::Include(..)
+            | Expr::Return(_)
+            | Expr::Throw(..)
+            | Expr::Break(..)
+            | Expr::Continue(..) => self.nested(|emitter| {
+                emitter.line("{");
+                emitter.block(std::slice::from_ref(expr), true)?;
+                emitter.line("}");
+                Ok(())
+            })?,
+            Expr::Closure(_, _, _, Some(_))
+            | Expr::Compiled(..)
+            | Expr::TailCall(..)
+            | Expr::RangeValue(..)
+            | Expr::Instance(..)
+            | Expr::Module(..)
+            | Expr::Variant(..)
+            | Expr::Builtin(_)
+            | Expr::Native(_) => unreachable!("the values made by a running script are never parsed"),
+        })
+    }
+
+    fn list(&mut self, exprs: &[Expr]) -> Translated<String> {
+        let codes = exprs.iter().map(|expr| self.expression(expr)).collect::<Translated<Vec<_>>>()?;
+        Ok(codes.join(", "))
+    }
+
+    // Returns the code of a comprehension, a loop pushing the values to an array.
+    fn comprehension(
+        &mut self,
+        value: &Expr,
+        binding: &Binding,
+        collection: &Expr,
+        location: &Location,
+        condition: Option<&Expr>,
+    ) -> Translated<String> {
+        let collection = self.expression(collection)?;
+        let items = self.fallible(&format!("runtime::iterate({collection})"));
+        self.nested(|emitter| {
+            let values = emitter.temporary("items");
+            emitter.line("{");
+            emitter.indent += 1;
+            emitter.line(format!("let mut {values} = Vec::new();"));
+            emitter.line(format!("for __item in {items} {{"));
+            emitter.indent += 1;
+            emitter.frames.push(Frame::Comprehension(location.clone()));
+            let mut names = Vec::new();
+            binding_names(binding, &mut names);
+            emitter.enter(std::iter::once(value).chain(condition), &names);
+            let item = emitter.fallible("__item");
+            emitter.bind(binding, item, Kind::Variable);
+            let translated = match condition {
+                Some(condition) => emitter.condition(condition, Check::Condition("if")).and_then(|condition| {
+                    emitter.line(format!("if {condition} {{"));
+                    emitter.indent += 1;
+                    let value = emitter.expression(value)?;
+                    emitter.line(format!("{values}.push({value});"));
+                    emitter.indent -= 1;
+                    emitter.line("}");
+                    Ok(())
+                }),
+                None => emitter.expression(value).map(|value| emitter.line(format!("{values}.push({value});"))),
+            };
+            emitter.scopes.pop();
+            emitter.frames.pop();
+            translated?;
+            emitter.indent -= 1;
+            emitter.line("}");
+            emitter.line(format!("Value::from({values})"));
+            emitter.indent -= 1;
+            emitter.line("}");
+            Ok(())
+        })
+    }
+
+    // Returns a string formatting the interpolated values like `println`.
+    fn interpolation(&mut self, parts: &[StringPart]) -> Translated<String> {
+        let mut text = String::new();
+        let mut values = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Text(part) => text.push_str(&part.replace('{', "{{").replace('}', "}}")),
+                StringPart::Value(value, spec) => {
+                    text.push_str("{}");
+                    let value = self.borrowed(value)?;
+                    values.push(match spec {
+                        Some(spec) => format!("runtime::format({value}, {})", string(&specification(spec))),
+                        None => value,
+                    });
+                }
+            }
+        }
+        if values.is_empty() {
+            return Ok(format!("Value::from({})", string(&text.replace("{{", "{").replace("}}", "}"))));
+        }
+        Ok(format!("Value::from(format!({}, {}))", string(&text), values.join(", ")))
+    }
+}
+
+// How a value used as a boolean is checked to be one: as the condition of a construct, or as an
+// operand of `and` or `or`.
+#[derive(Clone, Copy)]
+enum Check<'c> {
+    Condition(&'c str),
+    Operand(&'c str),
+}
+
+// Returns the names a statement declares in its block.
+fn declared_names(expr: &Expr) -> Vec<String> {
+    let mut names = Vec::new();
+    match expr {
+        Expr::Let(binding, ..) | Expr::Const(binding, ..) => binding_names(binding, &mut names),
+        Expr::Function(name, ..) | Expr::Struct(name, ..) | Expr::Enum(name, _) | Expr::Trait(name, _) => {
+            names.push(name.clone());
+        }
+        Expr::Include(_, Some(alias), _) => names.push(alias.clone()),
+        Expr::Include(IncludeTarget::Name(name), None, _) => {
+            if let Some((_, functions)) = LIBRARIES.iter().find(|(library, _)| library == name) {
+                names.extend(functions.iter().map(|function| function.to_string()));
+            }
+        }
+        _ => {}
+    }
+    names
+}
+
+// Returns the names read or assigned by the functions declared in some expressions, which must be
+// shared with them.
+fn captured<'e>(exprs: impl IntoIterator<Item = &'e Expr>) -> HashSet<String> {
+    fn names(expr: &Expr, found: &mut HashSet<String>) {
+        if let Expr::Variable(name, _) | Expr::MethodCall(_, name, _, _) | Expr::Path(name, _, _) = expr {
+            found.insert(name.clone());
+        }
+        children(expr, true).into_iter().for_each(|child| names(child, found));
+    }
+    fn visit(expr: &Expr, found: &mut HashSet<String>) {
+        match expr {
+            Expr::Closure(..) | Expr::Function(..) | Expr::Impl(..) => names(expr, found),
+            expr => children(expr, false).into_iter().for_each(|child| visit(child, found)),
+        }
+    }
+    let mut found = HashSet::new();
+    exprs.into_iter().for_each(|expr| visit(expr, &mut found));
+    found
+}
+
+// Adds the conditions for a value to match a pattern, and the names it binds with their value.
+// The value is a place, borrowed by the conditions and copied for the bindings.
+fn test(pattern: &Pattern, value: &str, conditions: &mut Vec<String>, bindings: &mut Vec<(String, String)>) {
+    match pattern {
+        Pattern::Wildcard | Pattern::Rest(_) => {}
+        Pattern::Binding(name) => bindings.push((name.clone(), format!("{value}.clone()"))),
+        Pattern::Literal(atom) => conditions.push(format!("runtime::same(&{value}, &{})", literal(atom))),
+        Pattern::Variant(name, variant, values) => {
+            conditions.push(format!("runtime::is_variant(&{value}, {}, {})", string(name), string(variant)));
+            if let Some(patterns) = values {
+                conditions.push(format!("runtime::values(&{value}).len() == {}", patterns.len()));
+                for (i, pattern) in patterns.iter().enumerate() {
+                    test(pattern, &format!("runtime::values(&{value})[{i}]"), conditions, bindings);
+                }
+            }
+        }
+        Pattern::Array(patterns) => {
+            conditions.push(format!("runtime::is_array(&{value})"));
+            let length = format!("runtime::values(&{value}).len()");
+            let Some(rest) = patterns.iter().position(|pattern| matches!(pattern, Pattern::Rest(_))) else {
+                conditions.push(format!("{length} == {}", patterns.len()));
+                for (i, pattern) in patterns.iter().enumerate() {
+                    test(pattern, &format!("runtime::values(&{value})[{i}]"), conditions, bindings);
+                }
+                return;
+            };
+            let after = patterns.len() - rest - 1;
+            conditions.push(format!("{length} >= {}", rest + after));
+            for (i, pattern) in patterns[..rest].iter().enumerate() {
+                test(pattern, &format!("runtime::values(&{value})[{i}]"), conditions, bindings);
+            }
+            for (i, pattern) in patterns[rest + 1..].iter().enumerate() {
+                test(pattern, &format!("runtime::values(&{value})[{length} - {}]", after - i), conditions, bindings);
+            }
+            if let Pattern::Rest(Some(name)) = &patterns[rest] {
+                let slice = format!("Value::from(runtime::values(&{value})[{rest}..{length} - {after}].to_vec())");
+                bindings.push((name.clone(), slice));
+            }
+        }
+        Pattern::Struct(name, fields) => {
+            conditions.push(format!("runtime::is_instance(&{value}, {})", string(name)));
+            for (field, pattern) in fields {
+                test(pattern, &format!("(*runtime::member(&{value}, {}))", string(field)), conditions, bindings);
+            }
+        }
+    }
+}
+
+// Returns whether an expression is a statement, which gives no value at the end of a block.
+fn is_statement(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Void
+            | Expr::Let(..)
+            | Expr::Const(..)
+            | Expr::Assign(..)
+            | Expr::Function(..)
+            | Expr::Struct(..)
+            | Expr::Enum(..)
+            | Expr::Trait(..)
+            | Expr::Impl(..)
+            | Expr::Include(..)
+            | Expr::Return(_)
+            | Expr::Throw(..)
+            | Expr::Break(..)
+            | Expr::Continue(..)
+    )
+}
+
+fn unsupported(location: Option<&Location>, path: &Path, message: String, label: &str) -> Box<dyn LError + Send> {
+    match location {
+        Some(location) => error::boxed(EvaluationError::new(location.clone(), message, label.to_string())),
+        None => error::boxed(FileError::new(path, message)),
+    }
+}
+
+// Returns the name of a variable in Rust.
+fn identifier(name: &str) -> String {
+    if RESERVED.contains(&name) {
+        format!("{name}_")
+    } else {
+        name.to_string()
+    }
+}
+
+fn padding(indent: usize) -> String {
+    "    ".repeat(indent)
+}
+
+fn literal(atom: &Atom) -> String {
+    match atom {
+        Atom::Number(number) => format!("Value::Int({number})"),
+        Atom::Float(float) => format!("Value::Float({float:?})"),
+        Atom::Boolean(boolean) => format!("Value::Bool({boolean})"),
+        Atom::String(text) => format!("Value::from({})", string(text)),
+        Atom::Char(c) => format!("Value::Char({c:?})"),
+        Atom::Nil => "Value::Nil".to_string(),
+    }
+}
+
+// Returns a string literal of Rust.
+fn string(text: &str) -> String {
+    format!("{text:?}")
+}
+
+// Returns a format specifier written like in the script, for `runtime::format`.
+fn specification(spec: &FormatSpec) -> String {
+    let mut text = String::new();
+    if let Some(align) = spec.align {
+        if spec.fill != ' ' {
+            text.push(spec.fill);
+        }
+        text.push(match align {
+            Align::Left => '<',
+            Align::Right => '>',
+            Align::Center => '^',
+        });
+    }
+    if spec.zero {
+        text.push('0');
+    }
+    if spec.width > 0 {
+        text.push_str(&spec.width.to_string());
+    }
+    if let Some(precision) = spec.precision {
+        text.push_str(&format!(".{precision}"));
+    }
+    text
+}
@@ -0,0 +1,1257 @@
+// The runtime of the programs translated by `mussel emit-rs`: the values of Mussel, the operators
+// and the built-in functions, written to behave like the interpreter.
+// A value is cheap to clone: arrays, strings and instances are shared until one is assigned to,
+// when the variable assigned gets its own copy, so two variables never share a value.
+// An error stops the program, unless it was thrown by `throw` and a `catch` takes it.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::rc::Rc;
+
+pub enum Error {
+    // A value thrown by `throw`, until a `catch` takes it.
+    Thrown(Value),
+    // An error stopping the program, like the errors reported by the interpreter.
+    Fatal(String),
+}
+
+pub type Result<T = Value> = std::result::Result<T, Error>;
+
+fn fail<T>(message: impl Into<String>) -> Result<T> {
+    Err(Error::Fatal(message.into()))
+}
+
+// Leaves a `try` block on an error, keeping the error for its `catch`, where `?` would leave the
+// function.
+macro_rules! attempt {
+    ($result:expr, $label:lifetime, $error:ident) => {
+        match $result {
+            Ok(value) => value,
+            Err(error) => {
+                $error = Some(error);
+                break $label $crate::runtime::Value::Void;
+            }
+        }
+    };
+}
+
+#[derive(Clone)]
+pub enum Value {
+    // The value of statements, nothing.
+    Void,
+    Nil,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(Rc<str>),
+    Char(char),
+    Array(Rc<Vec<Value>>),
+    // A range from a start to an end, counting by a step, with the end included or not.
+    Range(i64, i64, i64, bool),
+    Function(Rc<Function>),
+    Struct(Rc<Struct>),
+    Instance(Rc<Instance>),
+    Enum(Rc<Enum>),
+    Variant(Rc<Variant>),
+    Trait(Rc<Trait>),
+    Module(Rc<Module>),
+}
+
+pub struct Function {
+    // The names of the parameters, which a built-in function doesn't show.
+    parameters: Option<&'static [&'static str]>,
+    body: Box<dyn Fn(Vec<Value>) -> Result>,
+}
+
+pub struct Struct {
+    name: &'static str,
+    fields: &'static [&'static str],
+    methods: RefCell<Vec<(&'static str, Value)>>,
+}
+
+#[derive(Clone)]
+pub struct Instance {
+    definition: Rc<Struct>,
+    values: Vec<Value>,
+}
+
+pub struct Enum {
+    name: &'static str,
+    // The variants, with the number of values they carry.
+    variants: &'static [(&'static str, usize)],
+}
+
+pub struct Variant {
+    enumeration: &'static str,
+    name: &'static str,
+    // The number of values the variant carries, given when it's called.
+    arity: usize,
+    values: Vec<Value>,
+}
+
+pub struct Trait {
+    name: &'static str,
+    methods: &'static [&'static str],
+}
+
+// The things a file or a library declares, when it's included with a name.
+pub struct Module {
+    name: &'static str,
+    members: Vec<(&'static str, Value)>,
+}
+
+impl From<&str> for Value {
+    fn from(text: &str) -> Value {
+        Value::Str(Rc::from(text))
+    }
+}
+
+impl From<String> for Value {
+    fn from(text: String) -> Value {
+        Value::Str(Rc::from(text))
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(items: Vec<Value>) -> Value {
+        Value::Array(Rc::new(items))
+    }
+}
+
+impl Value {
+    // Returns the name of the type of a value, like `typeof`.
+    pub fn type_name(&self) -> String {
+        match self {
+            Value::Void => "nothing".to_string(),
+            Value::Nil => "nil".to_string(),
+            Value::Int(_) => "int".to_string(),
+            Value::Float(_) => "float".to_string(),
+            Value::Bool(_) => "bool".to_string(),
+            Value::Str(_) => "string".to_string(),
+            Value::Char(_) => "char".to_string(),
+            Value::Array(_) => "array".to_string(),
+            Value::Range(..) => "range".to_string(),
+            Value::Function(_) => "function".to_string(),
+            Value::Struct(_) => "struct".to_string(),
+            Value::Instance(instance) => instance.definition.name.to_string(),
+            Value::Enum(_) => "enum".to_string(),
+            Value::Variant(variant) => variant.enumeration.to_string(),
+            Value::Trait(_) => "trait".to_string(),
+            Value::Module(_) => "module".to_string(),
+        }
+    }
+}
+
+// Writes a value like `println` prints it.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter, values: &[Value]) -> fmt::Result {
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{value}")?;
+            }
+            Ok(())
+        }
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Float(n) => write!(f, "{n}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Str(s) => write!(f, "{s}"),
+            Value::Char(c) => write!(f, "{c}"),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                list(f, items)?;
+                write!(f, "]")
+            }
+            Value::Range(start, end, 1, inclusive) => write!(f, "{start}{}{end}", if *inclusive { "..=" } else { ".." }),
+            Value::Range(start, end, step, _) => write!(f, "range({start}, {end}, {step})"),
+            Value::Function(function) => match function.parameters {
+                Some(parameters) => write!(f, "<function({})>", parameters.join(", ")),
+                None => write!(f, "<built-in function>"),
+            },
+            Value::Instance(instance) => {
+                write!(f, "{} {{ ", instance.definition.name)?;
+                for (i, (field, value)) in instance.definition.fields.iter().zip(&instance.values).enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{field}: {value}")?;
+                }
+                write!(f, " }}")
+            }
+            Value::Variant(variant) if variant.values.is_empty() => write!(f, "{}::{}", variant.enumeration, variant.name),
+            Value::Variant(variant) => {
+                write!(f, "{}::{}(", variant.enumeration, variant.name)?;
+                list(f, &variant.values)?;
+                write!(f, ")")
+            }
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+            Value::Void | Value::Struct(_) | Value::Enum(_) | Value::Trait(_) => Ok(()),
+        }
+    }
+}
+
+// A variable read by a function declared around it, which the function shares with the block
+// declaring it. It's declared when the block starts, so the functions can read the variables
+// declared after them, and set when the declaration runs.
+#[derive(Clone)]
+pub struct Var {
+    name: &'static str,
+    value: Rc<RefCell<Option<Value>>>,
+}
+
+impl Var {
+    pub fn new(name: &'static str) -> Var {
+        Var { name, value: Rc::new(RefCell::new(None)) }
+    }
+
+    pub fn with(name: &'static str, value: Value) -> Var {
+        Var { name, value: Rc::new(RefCell::new(Some(value))) }
+    }
+
+    pub fn get(&self) -> Result {
+        match &*self.value.borrow() {
+            Some(value) => Ok(value.clone()),
+            None => undefined(self.name),
+        }
+    }
+
+    pub fn set(&self, value: Value) {
+        *self.value.borrow_mut() = Some(value);
+    }
+
+    // Assigns an element or a field of the value of the variable.
+    pub fn assign(&self, keys: &[Key], value: Value) -> Result<()> {
+        match &mut *self.value.borrow_mut() {
+            Some(target) => set(target, keys, value),
+            None => undefined(self.name),
+        }
+    }
+}
+
+// Fails for a name that isn't declared where it's read.
+pub fn undefined<T>(name: &str) -> Result<T> {
+    fail(format!("cannot find `{name}` in this scope"))
+}
+
+// Runs the translated script in a thread with a large stack, as deep recursion needs it, and stops
+// the program when it fails.
+pub fn run(script: fn() -> Result) {
+    let thread = std::thread::Builder::new().stack_size(512 * 1024 * 1024).spawn(move || {
+        let result = script();
+        let _ = std::io::stdout().flush();
+        match result {
+            // An integer value of the last statement is the exit status, between 0 and 255.
+            Ok(Value::Int(status)) => u8::try_from(status).unwrap_or_else(|_| {
+                eprintln!("error: The value of the last statement, {status}, isn't an exit status between 0 and 255");
+                1
+            }),
+            Ok(_) => 0,
+            Err(Error::Thrown(value)) => {
+                eprintln!("error: Uncaught error: {value}");
+                1
+            }
+            Err(Error::Fatal(message)) => {
+                eprintln!("error: {message}");
+                1
+            }
+        }
+    });
+    let status = thread.expect("the thread of the script starts").join().unwrap_or(1);
+    if status != 0 {
+        std::process::exit(status.into());
+    }
+}
+
+pub fn function(parameters: &'static [&'static str], body: impl Fn(Vec<Value>) -> Result + 'static) -> Value {
+    Value::Function(Rc::new(Function { parameters: Some(parameters), body: Box::new(body) }))
+}
+
+pub fn builtin(body: fn(Vec<Value>) -> Result) -> Value {
+    Value::Function(Rc::new(Function { parameters: None, body: Box::new(body) }))
+}
+
+fn arity(name: &str, required: usize, total: usize, given: usize) -> Result<()> {
+    if given < required || given > total {
+        let expected = if required == total { required.to_string() } else { format!("{required} to {total}") };
+        return fail(format!("function `{name}` expects {expected} arguments, but {given} were given"));
+    }
+    Ok(())
+}
+
+// Returns the arguments of a function, which must be as many as its parameters.
+pub fn arguments<const N: usize>(name: &str, arguments: Vec<Value>) -> Result<[Value; N]> {
+    arity(name, N, N, arguments.len())?;
+    Ok(arguments.try_into().ok().expect("the number of arguments is checked"))
+}
+
+// Returns the arguments of a function with default values: the required ones, then the others
+// when they are given.
+pub fn optional_arguments<const R: usize, const O: usize>(
+    name: &str,
+    arguments: Vec<Value>,
+) -> Result<([Value; R], [Option<Value>; O])> {
+    arity(name, R, R + O, arguments.len())?;
+    let mut arguments = arguments.into_iter();
+    let required = std::array::from_fn(|_| arguments.next().expect("the number of arguments is checked"));
+    let optional = std::array::from_fn(|_| arguments.next());
+    Ok((required, optional))
+}
+
+// Calls a function. Calling a struct constructs an instance, and calling a variant gives it the
+// values it carries.
+pub fn call(callee: &Value, arguments: Vec<Value>) -> Result {
+    match callee {
+        Value::Function(function) => (function.body)(arguments),
+        Value::Struct(definition) => {
+            if definition.fields.len() != arguments.len() {
+                return fail(format!(
+                    "struct `{}` has {} fields, but {} values were given",
+                    definition.name,
+                    definition.fields.len(),
+                    arguments.len()
+                ));
+            }
+            Ok(Value::Instance(Rc::new(Instance { definition: definition.clone(), values: arguments })))
+        }
+        Value::Variant(variant) if variant.values.is_empty() => {
+            if variant.arity != arguments.len() {
+                return fail(format!(
+                    "variant `{}::{}` carries {} values, but {} were given",
+                    variant.enumeration,
+                    variant.name,
+                    variant.arity,
+                    arguments.len()
+                ));
+            }
+            Ok(Value::Variant(Rc::new(Variant { values: arguments, ..**variant })))
+        }
+        invalid => fail(format!("a value of type `{}` is not a function", invalid.type_name())),
+    }
+}
+
+// Calls a method: a field of an instance holding a function, or a function of a module, is called
+// with the arguments, otherwise the method of the struct, or else the function with the name of
+// the method, is called with the receiver first.
+pub fn invoke(receiver: &Value, name: &str, fallback: Option<Value>, mut arguments: Vec<Value>) -> Result {
+    match receiver {
+        Value::Instance(instance) => {
+            if let Some(i) = instance.definition.fields.iter().position(|field| *field == name) {
+                return call(&instance.values[i], arguments);
+            }
+        }
+        Value::Module(module) => {
+            return match module.members.iter().find(|(member, _)| *member == name) {
+                Some((_, function)) => call(function, arguments),
+                None => fail(format!("cannot find `{name}` in module `{}`", module.name)),
+            };
+        }
+        _ => {}
+    }
+    let Some(method) = method(receiver, name).or(fallback) else {
+        return fail(format!("no method named `{name}` found for `{}`", receiver.type_name()));
+    };
+    arguments.insert(0, receiver.clone());
+    call(&method, arguments)
+}
+
+fn method(value: &Value, name: &str) -> Option<Value> {
+    let Value::Instance(instance) = value else {
+        return None;
+    };
+    let methods = instance.definition.methods.borrow();
+    methods.iter().find(|(method, _)| *method == name).map(|(_, function)| function.clone())
+}
+
+pub fn structure(name: &'static str, fields: &'static [&'static str]) -> Value {
+    Value::Struct(Rc::new(Struct { name, fields, methods: RefCell::new(Vec::new()) }))
+}
+
+pub fn enumeration(name: &'static str, variants: &'static [(&'static str, usize)]) -> Value {
+    Value::Enum(Rc::new(Enum { name, variants }))
+}
+
+pub fn interface(name: &'static str, methods: &'static [&'static str]) -> Value {
+    Value::Trait(Rc::new(Trait { name, methods }))
+}
+
+// Adds methods to a struct. The methods implementing a trait must include all its methods.
+pub fn implement(definition: &Value, interface: Option<&Value>, methods: Vec<(&'static str, Value)>) -> Result<()> {
+    let Value::Struct(definition) = definition else {
+        return fail(format!("methods can only be added to structs, found `{}`", definition.type_name()));
+    };
+    match interface {
+        Some(Value::Trait(interface)) => {
+            let missing: Vec<&str> = interface
+                .methods
+                .iter()
+                .filter(|method| !methods.iter().any(|(name, _)| name == *method))
+                .copied()
+                .collect();
+            if !missing.is_empty() {
+                return fail(format!(
+                    "not all methods of trait `{}` are implemented for `{}`: missing `{}`",
+                    interface.name,
+                    definition.name,
+                    missing.join("`, `")
+                ));
+            }
+        }
+        Some(invalid) => return fail(format!("expected trait, found `{}`", invalid.type_name())),
+        None => {}
+    }
+    let mut declared = definition.methods.borrow_mut();
+    for (name, function) in methods {
+        declared.retain(|(method, _)| *method != name);
+        declared.push((name, function));
+    }
+    Ok(())
+}
+
+// Resolves a path like `Color::Red` to the variant it names, or `Point::new` to a method.
+pub fn path(definition: &Value, name: &str) -> Result {
+    match definition {
+        Value::Enum(enumeration) => match enumeration.variants.iter().find(|(variant, _)| *variant == name) {
+            Some((variant, arity)) => Ok(Value::Variant(Rc::new(Variant {
+                enumeration: enumeration.name,
+                name: variant,
+                arity: *arity,
+                values: Vec::new(),
+            }))),
+            None => fail(format!("no variant named `{name}` in enum `{}`", enumeration.name)),
+        },
+        Value::Struct(structure) => {
+            let methods = structure.methods.borrow();
+            match methods.iter().find(|(method, _)| *method == name) {
+                Some((_, function)) => Ok(function.clone()),
+                None => fail(format!("no method named `{name}` on struct `{}`", structure.name)),
+            }
+        }
+        invalid => fail(format!("expected enum or struct, found `{}`", invalid.type_name())),
+    }
+}
+
+// Returns a field of a struct instance, or something a module declares.
+pub fn field(value: &Value, name: &str) -> Result {
+    match value {
+        Value::Instance(instance) => match instance.definition.fields.iter().position(|field| *field == name) {
+            Some(i) => Ok(instance.values[i].clone()),
+            None => fail(format!("no field `{name}` on struct `{}`", instance.definition.name)),
+        },
+        Value::Module(module) => match module.members.iter().find(|(member, _)| *member == name) {
+            Some((_, value)) => Ok(value.clone()),
+            None => fail(format!("cannot find `{name}` in module `{}`", module.name)),
+        },
+        invalid => fail(format!("no field `{name}` on a value of type `{}`", invalid.type_name())),
+    }
+}
+
+// An element or a field of a value, assigned by `matrix[i][j] = 0` or `point.x = 0`.
+pub enum Key {
+    Index(Value),
+    Field(&'static str),
+}
+
+// Replaces the element or the field at the end of the keys. The arrays and instances on the way
+// are copied when another variable shares them.
+pub fn set(target: &mut Value, keys: &[Key], value: Value) -> Result<()> {
+    let Some((key, keys)) = keys.split_first() else {
+        *target = value;
+        return Ok(());
+    };
+    match (key, target) {
+        (Key::Field(name), Value::Instance(instance)) => {
+            let Some(i) = instance.definition.fields.iter().position(|field| field == name) else {
+                return fail(format!("no field `{name}` on struct `{}`", instance.definition.name));
+            };
+            set(&mut Rc::make_mut(instance).values[i], keys, value)
+        }
+        (Key::Field(name), invalid) => fail(format!("no field `{name}` on a value of type `{}`", invalid.type_name())),
+        (Key::Index(index), Value::Array(items)) => {
+            let i = position(index, items.len())?;
+            set(&mut Rc::make_mut(items)[i], keys, value)
+        }
+        (Key::Index(_), invalid) => fail(format!("cannot index into a value of type `{}`", invalid.type_name())),
+    }
+}
+
+// Checks that an index is an integer within the bounds of an array.
+fn position(index: &Value, length: usize) -> Result<usize> {
+    match index {
+        Value::Int(n) if *n >= 0 && (*n as usize) < length => Ok(*n as usize),
+        Value::Int(n) => fail(format!("index out of bounds: the length is {length} but the index is {n}")),
+        invalid => fail(format!("mismatched types: an array index should be `int`, found `{}`", invalid.type_name())),
+    }
+}
+
+// Returns the element of an array at an index, or the slice at a range.
+pub fn index(array: &Value, index: &Value) -> Result {
+    let Value::Array(items) = array else {
+        return fail(format!("cannot index into a value of type `{}`", array.type_name()));
+    };
+    if let Value::Range(start, end, step, inclusive) = index {
+        let slice = numbers(*start, *end, *step, *inclusive)
+            .map(|i| Ok(items[position(&Value::Int(i), items.len())?].clone()))
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(Value::from(slice));
+    }
+    Ok(items[position(index, items.len())?].clone())
+}
+
+// Returns the range `start..end`, or `start..=end`.
+pub fn interval(start: &Value, end: &Value, inclusive: bool) -> Result {
+    match (start, end) {
+        (Value::Int(start), Value::Int(end)) => Ok(Value::Range(*start, *end, 1, inclusive)),
+        _ => fail(format!(
+            "mismatched types: the bounds of a range should be `int`, found `{}` and `{}`",
+            start.type_name(),
+            end.type_name()
+        )),
+    }
+}
+
+fn numbers(start: i64, end: i64, step: i64, inclusive: bool) -> impl Iterator<Item = i64> {
+    let mut next = Some(start);
+    std::iter::from_fn(move || {
+        let n = next?;
+        let within = if step > 0 { n < end } else { n > end };
+        if !within && !(inclusive && n == end) {
+            return None;
+        }
+        next = n.checked_add(step);
+        Some(n)
+    })
+}
+
+// Returns the items a `for` loop or a comprehension goes through. The `next` function of an
+// instance is called for each item, until it returns nil.
+pub fn iterate(collection: Value) -> Result<Box<dyn Iterator<Item = Result>>> {
+    Ok(match collection {
+        Value::Array(items) => Box::new((0..items.len()).map(move |i| Ok(items[i].clone()))),
+        Value::Range(start, end, step, inclusive) => Box::new(numbers(start, end, step, inclusive).map(|n| Ok(Value::Int(n)))),
+        Value::Str(text) => Box::new(text.chars().collect::<Vec<_>>().into_iter().map(|c| Ok(Value::Char(c)))),
+        Value::Instance(ref instance) => {
+            let Some(i) = instance.definition.fields.iter().position(|field| *field == "next") else {
+                return fail(format!("`{}` is not iterable, it has no `next` field", instance.definition.name));
+            };
+            let next = instance.values[i].clone();
+            Box::new(std::iter::from_fn(move || match call(&next, Vec::new()) {
+                Ok(Value::Nil) => None,
+                item => Some(item),
+            }))
+        }
+        invalid => return fail(format!("`{}` is not iterable", invalid.type_name())),
+    })
+}
+
+// Returns the elements of an array unpacked into as many variables.
+pub fn unpack<const N: usize>(value: Value) -> Result<[Value; N]> {
+    match value {
+        Value::Array(items) if items.len() == N => {
+            Ok(Rc::unwrap_or_clone(items).try_into().ok().expect("the length is checked"))
+        }
+        Value::Array(items) => fail(format!("Can't unpack an array of {} elements into {N} variables", items.len())),
+        invalid => fail(format!("Can't unpack `{invalid}`, it is not an array")),
+    }
+}
+
+// Returns the value of a condition, which must be a boolean.
+pub fn condition(value: &Value, keyword: &str) -> Result<bool> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        invalid => fail(format!("mismatched types: the condition of `{keyword}` should be `bool`, found `{}`", invalid.type_name())),
+    }
+}
+
+// Returns the value of an operand of `and` or `or`, which must be a boolean.
+pub fn operand(value: &Value, operator: &str) -> Result<bool> {
+    match value {
+        Value::Bool(b) => Ok(*b),
+        invalid => fail(format!("mismatched types: `{operator}` expects `bool` operands, found `{}`", invalid.type_name())),
+    }
+}
+
+pub fn not(value: &Value) -> Result<bool> {
+    match value {
+        Value::Bool(b) => Ok(!b),
+        invalid => fail(format!("mismatched types: `not` expects a `bool`, found `{}`", invalid.type_name())),
+    }
+}
+
+// Fails for an `assert` whose condition doesn't hold, with its message.
+pub fn assertion(message: Option<&Value>) -> Result {
+    match message {
+        Some(message) => fail(format!("assertion failed: {message}")),
+        None => fail("assertion failed"),
+    }
+}
+
+pub fn throw(value: Value) -> Result {
+    Err(Error::Thrown(value))
+}
+
+// Returns the value thrown by `throw` to a `catch`, other errors keep stopping the program.
+pub fn caught(error: Error) -> Result {
+    match error {
+        Error::Thrown(value) => Ok(value),
+        error => Err(error),
+    }
+}
+
+// Tells whether a value is the given variant, for the patterns of `match`.
+pub fn is_variant(value: &Value, enumeration: &str, name: &str) -> bool {
+    matches!(value, Value::Variant(variant) if variant.enumeration == enumeration && variant.name == name)
+}
+
+pub fn is_array(value: &Value) -> bool {
+    matches!(value, Value::Array(_))
+}
+
+pub fn is_instance(value: &Value, name: &str) -> bool {
+    matches!(value, Value::Instance(instance) if instance.definition.name == name)
+}
+
+// Returns the values carried by a variant, or the elements of an array, for the patterns of
+// `match`, none for other values.
+pub fn values(value: &Value) -> &[Value] {
+    match value {
+        Value::Variant(variant) => &variant.values,
+        Value::Array(items) => items,
+        _ => &[],
+    }
+}
+
+// Returns a field of an instance for the patterns of `match`, nil when there is none.
+pub fn member<'a>(value: &'a Value, name: &str) -> &'a Value {
+    match value {
+        Value::Instance(instance) => match instance.definition.fields.iter().position(|field| *field == name) {
+            Some(i) => &instance.values[i],
+            None => &Value::Nil,
+        },
+        _ => &Value::Nil,
+    }
+}
+
+// Tells whether a value is the literal of a pattern, of the same type.
+pub fn same(value: &Value, literal: &Value) -> bool {
+    match (value, literal) {
+        (Value::Int(a), Value::Int(b)) => a == b,
+        (Value::Float(a), Value::Float(b)) => a == b,
+        (Value::Bool(a), Value::Bool(b)) => a == b,
+        (Value::Str(a), Value::Str(b)) => a == b,
+        (Value::Char(a), Value::Char(b)) => a == b,
+        (Value::Nil, Value::Nil) => true,
+        _ => false,
+    }
+}
+
+thread_local! {
+    // The functions overloading the operators for struct instances, named after the operator.
+    static OVERLOADS: RefCell<HashMap<&'static str, Value>> = RefCell::new(HashMap::new());
+}
+
+pub fn overload(name: &'static str, function: &Value) {
+    OVERLOADS.with(|overloads| overloads.borrow_mut().insert(name, function.clone()));
+}
+
+// Calls the function overloading an operator when one of the operands is an instance: a method of
+// the struct of the first operand, or else a function.
+fn overloaded(name: &str, operands: [&Value; 2]) -> Option<Result> {
+    if !operands.iter().any(|operand| matches!(operand, Value::Instance(_))) {
+        return None;
+    }
+    let function = method(operands[0], name).or_else(|| OVERLOADS.with(|overloads| overloads.borrow().get(name).cloned()))?;
+    Some(call(&function, operands.iter().map(|&operand| operand.clone()).collect()))
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Arithmetic {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+// The error of an integer result too large for an `int`, which is never wrapped around.
+fn overflow() -> Error {
+    Error::Fatal("attempt to compute a number too large for an `int`".to_string())
+}
+
+// Raises an integer to a positive power, or returns `None` when the result is too large for an
+// `int`. Only 0, 1 and -1 can be raised to a power larger than a `u32`.
+fn power(base: i64, exponent: i64) -> Option<i64> {
+    match (u32::try_from(exponent), base) {
+        (Ok(exponent), _) => base.checked_pow(exponent),
+        (Err(_), 0 | 1) => Some(base),
+        (Err(_), -1) => Some(if exponent % 2 == 0 { 1 } else { -1 }),
+        (Err(_), _) => None,
+    }
+}
+
+fn arithmetic(operator: Arithmetic, left: &Value, right: &Value) -> Result {
+    let name = match operator {
+        Arithmetic::Add => Some("add"),
+        Arithmetic::Sub => Some("sub"),
+        Arithmetic::Mul => Some("mul"),
+        Arithmetic::Div => Some("div"),
+        Arithmetic::Pow => None,
+    };
+    if let Some(result) = name.and_then(|name| overloaded(name, [left, right])) {
+        return result;
+    }
+    match (left, right) {
+        // A negative integer exponent can't produce an integer result.
+        (Value::Int(l), Value::Int(r)) if operator == Arithmetic::Pow && *r < 0 => Ok(Value::Float((*l as f64).powf(*r as f64))),
+        (Value::Int(l), Value::Int(r)) => Ok(Value::Int(match operator {
+            Arithmetic::Add => l.checked_add(*r).ok_or_else(overflow)?,
+            Arithmetic::Sub => l.checked_sub(*r).ok_or_else(overflow)?,
+            Arithmetic::Mul => l.checked_mul(*r).ok_or_else(overflow)?,
+            Arithmetic::Div if *r == 0 => return fail("attempt to divide by zero"),
+            Arithmetic::Div => l.checked_div(*r).ok_or_else(overflow)?,
+            Arithmetic::Pow => power(*l, *r).ok_or_else(overflow)?,
+        })),
+        (Value::Float(l), Value::Float(r)) => Ok(Value::Float(match operator {
+            Arithmetic::Add => l + r,
+            Arithmetic::Sub => l - r,
+            Arithmetic::Mul => l * r,
+            Arithmetic::Div if *r == 0.0 => return fail("attempt to divide by zero"),
+            Arithmetic::Div => l / r,
+            Arithmetic::Pow => l.powf(*r),
+        })),
+        (Value::Str(_) | Value::Char(_), Value::Str(_) | Value::Char(_)) if !matches!((left, right), (Value::Char(_), Value::Char(_))) => {
+            match operator {
+                Arithmetic::Add => Ok(Value::from(format!("{left}{right}"))),
+                _ => fail("strings can only be joined with `+`"),
+            }
+        }
+        _ => fail(format!(
+            "cannot apply an arithmetic operator to `{}` and `{}`",
+            left.type_name(),
+            right.type_name()
+        )),
+    }
+}
+
+pub fn add(left: &Value, right: &Value) -> Result {
+    arithmetic(Arithmetic::Add, left, right)
+}
+
+pub fn sub(left: &Value, right: &Value) -> Result {
+    arithmetic(Arithmetic::Sub, left, right)
+}
+
+pub fn mul(left: &Value, right: &Value) -> Result {
+    arithmetic(Arithmetic::Mul, left, right)
+}
+
+pub fn div(left: &Value, right: &Value) -> Result {
+    arithmetic(Arithmetic::Div, left, right)
+}
+
+pub fn pow(left: &Value, right: &Value) -> Result {
+    arithmetic(Arithmetic::Pow, left, right)
+}
+
+pub fn neg(value: &Value) -> Result {
+    if matches!(value, Value::Instance(_)) {
+        let function = method(value, "neg").or_else(|| OVERLOADS.with(|overloads| overloads.borrow().get("neg").cloned()));
+        if let Some(function) = function {
+            return call(&function, vec![value.clone()]);
+        }
+    }
+    match value {
+        Value::Int(n) => n.checked_neg().map(Value::Int).ok_or_else(overflow),
+        Value::Float(n) => Ok(Value::Float(-n)),
+        invalid => fail(format!("cannot negate a value of type `{}`", invalid.type_name())),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+// Compares two values, or calls `eq` or `lt` when one of them is an instance.
+fn compare(operator: Comparison, left: &Value, right: &Value) -> Result<bool> {
+    if !matches!((left, right), (Value::Nil, _) | (_, Value::Nil)) {
+        let (name, operands, negate) = match operator {
+            Comparison::Equal => ("eq", [left, right], false),
+            Comparison::NotEqual => ("eq", [left, right], true),
+            Comparison::Less => ("lt", [left, right], false),
+            Comparison::Greater => ("lt", [right, left], false),
+            Comparison::LessEqual => ("lt", [right, left], true),
+            Comparison::GreaterEqual => ("lt", [left, right], true),
+        };
+        if let Some(result) = overloaded(name, operands) {
+            return match result? {
+                Value::Bool(result) => Ok(result != negate),
+                invalid => fail(format!("mismatched types: `{name}` should return `bool`, found `{}`", invalid.type_name())),
+            };
+        }
+    }
+    fn ordered<T: PartialOrd>(operator: Comparison, left: T, right: T) -> bool {
+        match operator {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterEqual => left >= right,
+        }
+    }
+    let unordered = |kind: &str, equal: bool| match operator {
+        Comparison::Equal => Ok(equal),
+        Comparison::NotEqual => Ok(!equal),
+        _ => fail(format!("{kind} can only be compared with `==` and `!=`")),
+    };
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => Ok(ordered(operator, l, r)),
+        (Value::Float(l), Value::Float(r)) => Ok(ordered(operator, l, r)),
+        // When an integer is compared with a float, the integer is converted to a float.
+        (Value::Int(l), Value::Float(r)) => Ok(ordered(operator, *l as f64, *r)),
+        (Value::Float(l), Value::Int(r)) => Ok(ordered(operator, *l, *r as f64)),
+        (Value::Char(l), Value::Char(r)) => Ok(ordered(operator, l, r)),
+        (Value::Bool(l), Value::Bool(r)) => unordered("booleans", l == r),
+        (Value::Str(l), Value::Str(r)) => unordered("strings", l == r),
+        (Value::Variant(l), Value::Variant(r)) => {
+            let mut same = l.enumeration == r.enumeration && l.name == r.name && l.values.len() == r.values.len();
+            for (l, r) in l.values.iter().zip(&r.values) {
+                same = same && compare(Comparison::Equal, l, r)?;
+            }
+            unordered("enum variants", same)
+        }
+        (Value::Nil, _) | (_, Value::Nil) => unordered("`nil`", matches!((left, right), (Value::Nil, Value::Nil))),
+        _ => fail(format!("cannot compare `{}` with `{}`", left.type_name(), right.type_name())),
+    }
+}
+
+pub fn eq(left: &Value, right: &Value) -> Result<bool> {
+    compare(Comparison::Equal, left, right)
+}
+
+pub fn ne(left: &Value, right: &Value) -> Result<bool> {
+    compare(Comparison::NotEqual, left, right)
+}
+
+pub fn lt(left: &Value, right: &Value) -> Result<bool> {
+    compare(Comparison::Less, left, right)
+}
+
+pub fn le(left: &Value, right: &Value) -> Result<bool> {
+    compare(Comparison::LessEqual, left, right)
+}
+
+pub fn gt(left: &Value, right: &Value) -> Result<bool> {
+    compare(Comparison::Greater, left, right)
+}
+
+pub fn ge(left: &Value, right: &Value) -> Result<bool> {
+    compare(Comparison::GreaterEqual, left, right)
+}
+
+// Formats an interpolated value following its format specifier, written like in the script:
+// `{price:>8.2}` gives `format(&price, ">8.2")`.
+pub fn format(value: &Value, spec: &str) -> String {
+    let alignment = |c: char| matches!(c, '<' | '>' | '^');
+    let mut chars = spec.chars();
+    let (fill, align, rest) = match (chars.next(), chars.next()) {
+        (Some(fill), Some(align)) if alignment(align) => (fill, Some(align), &spec[fill.len_utf8() + 1..]),
+        (Some(align), _) if alignment(align) => (' ', Some(align), &spec[1..]),
+        _ => (' ', None, spec),
+    };
+    let (zero, rest) = match rest.strip_prefix('0') {
+        Some(rest) => (true, rest),
+        None => (false, rest),
+    };
+    let (width, precision) = rest.split_once('.').map_or((rest, None), |(width, precision)| (width, precision.parse().ok()));
+    let width: usize = width.parse().unwrap_or(0);
+
+    let number = matches!(value, Value::Int(_) | Value::Float(_));
+    let text = match (value, precision) {
+        (Value::Float(n), Some(precision)) => format!("{n:.precision$}"),
+        (Value::Int(n), Some(precision)) => format!("{:.precision$}", *n as f64),
+        (value, Some(precision)) => value.to_string().chars().take(precision).collect(),
+        (value, None) => value.to_string(),
+    };
+    let padding = width.saturating_sub(text.chars().count());
+    // Zeros go between the sign and the digits: `-0042`.
+    if zero && number {
+        let (sign, digits) = text.split_at(if text.starts_with('-') { 1 } else { 0 });
+        return format!("{sign}{}{digits}", "0".repeat(padding));
+    }
+    let fill = |count: usize| fill.to_string().repeat(count);
+    match align.unwrap_or(if number { '>' } else { '<' }) {
+        '<' => format!("{text}{}", fill(padding)),
+        '>' => format!("{}{text}", fill(padding)),
+        _ => format!("{}{text}{}", fill(padding / 2), fill(padding - padding / 2)),
+    }
+}
+
+thread_local! {
+    // What the included files declare once they have run, by the function running each file.
+    static MODULES: RefCell<HashMap<usize, Value>> = RefCell::new(HashMap::new());
+}
+
+// Returns what a file declares, running the file the first time it's included.
+pub fn include(name: &'static str, run: fn() -> Result<Vec<(&'static str, Value)>>) -> Result {
+    let key = run as usize;
+    if let Some(module) = MODULES.with(|modules| modules.borrow().get(&key).cloned()) {
+        return Ok(module);
+    }
+    let module = Value::Module(Rc::new(Module { name, members: run()? }));
+    MODULES.with(|modules| modules.borrow_mut().insert(key, module.clone()));
+    Ok(module)
+}
+
+// Returns one of the libraries of the standard library written in Rust, included with a name.
+pub fn library(name: &'static str) -> Value {
+    let functions: &[(&'static str, fn(Vec<Value>) -> Result)] = match name {
+        "math" => &[("abs", math::abs), ("sqrt", math::sqrt), ("pow", math::pow)],
+        "string" => &[
+            ("lowercase", string::lowercase),
+            ("uppercase", string::uppercase),
+            ("length", string::length),
+            ("split", string::split),
+            ("reverse", string::reverse),
+            ("trim", string::trim),
+            ("ltrim", string::ltrim),
+            ("rtrim", string::rtrim),
+            ("chars", string::chars),
+            ("ord", string::ord),
+            ("chr", string::chr),
+        ],
+        "time" => &[("time_ms", time::time_ms), ("time_sec", time::time_sec)],
+        "random" => &[("rand", random::rand)],
+        "os" => &[("getcwd", os::getcwd), ("listdir", os::listdir), ("exists", os::exists)],
+        _ => &[],
+    };
+    let members = functions.iter().map(|(function, body)| (*function, builtin(*body))).collect();
+    Value::Module(Rc::new(Module { name, members }))
+}
+
+// Returns the arguments of a built-in function, failing with the message when there aren't as
+// many as expected.
+fn expect<const N: usize>(arguments: Vec<Value>, message: &str) -> Result<[Value; N]> {
+    match arguments.try_into() {
+        Ok(arguments) => Ok(arguments),
+        Err(_) => fail(message),
+    }
+}
+
+pub fn println(arguments: Vec<Value>) -> Result {
+    let mut line = String::new();
+    for argument in &arguments {
+        line.push_str(&argument.to_string());
+    }
+    println!("{line}");
+    Ok(Value::Void)
+}
+
+// Reads a line of the standard input, after printing the optional prompt.
+// Returns nil when there is nothing left to read.
+pub fn input(arguments: Vec<Value>) -> Result {
+    if let Some(prompt) = arguments.first() {
+        print!("{prompt}");
+    }
+    let _ = std::io::stdout().flush();
+    let mut line = String::new();
+    match std::io::stdin().read_line(&mut line) {
+        Ok(0) => Ok(Value::Nil),
+        Ok(_) => Ok(Value::from(line.trim_end())),
+        Err(error) => fail(format!("Failed to read line: {error}")),
+    }
+}
+
+pub fn range(arguments: Vec<Value>) -> Result {
+    if arguments.len() != 2 && arguments.len() != 3 {
+        return fail("range expects 2 or 3 arguments: start, end and an optional step");
+    }
+    let numbers = arguments
+        .iter()
+        .map(|argument| match argument {
+            Value::Int(n) => Ok(*n),
+            _ => fail("range expects integer arguments"),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let step = numbers.get(2).copied().unwrap_or(1);
+    if step == 0 {
+        return fail("range step can't be zero");
+    }
+    Ok(Value::Range(numbers[0], numbers[1], step, false))
+}
+
+pub fn is_nil(arguments: Vec<Value>) -> Result {
+    let [value] = expect(arguments, "is_nil expects 1 argument")?;
+    Ok(Value::Bool(matches!(value, Value::Nil)))
+}
+
+pub fn type_of(arguments: Vec<Value>) -> Result {
+    let [value] = expect(arguments, "typeof expects 1 argument")?;
+    Ok(Value::from(value.type_name()))
+}
+
+pub fn get(arguments: Vec<Value>) -> Result {
+    if arguments.len() != 2 && arguments.len() != 3 {
+        return fail("get expects 2 or 3 arguments: an array, an index and an optional default value");
+    }
+    let mut arguments = arguments.into_iter();
+    let (collection, index) = (arguments.next().unwrap_or(Value::Nil), arguments.next().unwrap_or(Value::Nil));
+    let default = arguments.next().unwrap_or(Value::Nil);
+    let Value::Int(index) = index else {
+        return fail(format!("get expects an integer index, got {index}"));
+    };
+    let Ok(index) = usize::try_from(index) else {
+        return Ok(default);
+    };
+    let element = match collection {
+        Value::Array(items) => items.get(index).cloned(),
+        Value::Str(text) => text.chars().nth(index).map(Value::Char),
+        invalid => return fail(format!("get expects an array or a string, got {invalid}")),
+    };
+    Ok(element.unwrap_or(default))
+}
+
+pub fn call_function(arguments: Vec<Value>) -> Result {
+    let message = "call expects 2 arguments: a function or its name, and an array of arguments";
+    let [function, arguments] = expect(arguments, message)?;
+    let Value::Array(arguments) = arguments else {
+        return fail(message);
+    };
+    if let Value::Str(name) = function {
+        return fail(format!("call can't find the function `{name}` by name in a translated program, pass the function itself"));
+    }
+    call(&function, Rc::unwrap_or_clone(arguments))
+}
+
+// Ends the program right away with an exit status, 0 by default, between 0 and 255.
+pub fn exit(arguments: Vec<Value>) -> Result {
+    let status = match arguments.as_slice() {
+        [] => 0,
+        [Value::Int(status)] => match u8::try_from(*status) {
+            Ok(status) => status,
+            Err(_) => return fail(format!("exit expects a status between 0 and 255, not {status}")),
+        },
+        _ => return fail("exit expects an optional integer argument: the exit status"),
+    };
+    let _ = std::io::stdout().flush();
+    std::process::exit(status.into())
+}
+
+// Returns the arguments given to the program, after its path.
+pub fn script_arguments(arguments: Vec<Value>) -> Result {
+    let [] = expect(arguments, "args expects no arguments")?;
+    Ok(Value::from(std::env::args().skip(1).map(|argument| Value::from(argument.as_str())).collect::<Vec<_>>()))
+}
+
+pub mod math {
+    use super::{expect, fail, Result, Value};
+
+    pub fn abs(arguments: Vec<Value>) -> Result {
+        match expect(arguments, "abs expects 1 argument")? {
+            [Value::Int(n)] => n.checked_abs().map(Value::Int).ok_or_else(|| super::overflow()),
+            [Value::Float(n)] => Ok(Value::Float(n.abs())),
+            _ => fail("abs expects a numeric argument"),
+        }
+    }
+
+    pub fn sqrt(arguments: Vec<Value>) -> Result {
+        match expect(arguments, "sqrt expects 1 argument")? {
+            [Value::Int(n)] if n < 0 => Ok(Value::Nil),
+            [Value::Float(n)] if n < 0.0 => Ok(Value::Nil),
+            [Value::Int(n)] => Ok(Value::Float((n as f64).sqrt())),
+            [Value::Float(n)] => Ok(Value::Float(n.sqrt())),
+            _ => fail("sqrt expects a numeric argument"),
+        }
+    }
+
+    pub fn pow(arguments: Vec<Value>) -> Result {
+        let number = |value: &Value| match value {
+            Value::Int(n) => Ok(*n as f64),
+            Value::Float(n) => Ok(*n),
+            _ => fail("pow expects numeric arguments"),
+        };
+        let [base, exponent] = expect(arguments, "pow expects 2 arguments: base and exponent")?;
+        Ok(Value::Float(number(&base)?.powf(number(&exponent)?)))
+    }
+}
+
+pub mod string {
+    use super::{expect, fail, Result, Value};
+
+    // Applies a function to the string given to a built-in function.
+    fn text(arguments: Vec<Value>, name: &str, function: impl Fn(&str) -> Value) -> Result {
+        match expect(arguments, &format!("{name} expects 1 argument: a string"))? {
+            [Value::Str(text)] => Ok(function(&text)),
+            _ => fail(format!("{name} expects a string argument")),
+        }
+    }
+
+    pub fn lowercase(arguments: Vec<Value>) -> Result {
+        text(arguments, "lowercase", |text| Value::from(text.to_lowercase()))
+    }
+
+    pub fn uppercase(arguments: Vec<Value>) -> Result {
+        text(arguments, "uppercase", |text| Value::from(text.to_uppercase()))
+    }
+
+    // The length of a string is its number of bytes in UTF-8.
+    pub fn length(arguments: Vec<Value>) -> Result {
+        match expect(arguments, "length expects 1 argument: a string or an array")? {
+            [Value::Str(text)] => Ok(Value::Int(text.len() as i64)),
+            [Value::Array(items)] => Ok(Value::Int(items.len() as i64)),
+            _ => fail("length expects a string or an array argument"),
+        }
+    }
+
+    pub fn split(arguments: Vec<Value>) -> Result {
+        match expect(arguments, "split expects 2 arguments: strings")? {
+            [Value::Str(text), Value::Str(separator)] => {
+                Ok(Value::from(text.split(&*separator).map(Value::from).collect::<Vec<_>>()))
+            }
+            _ => fail("split expects strings as arguments"),
+        }
+    }
+
+    pub fn reverse(arguments: Vec<Value>) -> Result {
+        text(arguments, "reverse", |text| Value::from(text.chars().rev().collect::<String>()))
+    }
+
+    pub fn trim(arguments: Vec<Value>) -> Result {
+        text(arguments, "trim", |text| Value::from(text.trim()))
+    }
+
+    pub fn ltrim(arguments: Vec<Value>) -> Result {
+        text(arguments, "ltrim", |text| Value::from(text.trim_start()))
+    }
+
+    pub fn rtrim(arguments: Vec<Value>) -> Result {
+        text(arguments, "rtrim", |text| Value::from(text.trim_end()))
+    }
+
+    pub fn chars(arguments: Vec<Value>) -> Result {
+        text(arguments, "chars", |text| Value::from(text.chars().map(Value::Char).collect::<Vec<_>>()))
+    }
+
+    pub fn ord(arguments: Vec<Value>) -> Result {
+        match expect(arguments, "ord expects 1 argument: a character")? {
+            [Value::Char(c)] => Ok(Value::Int(c as i64)),
+            _ => fail("ord expects a character argument"),
+        }
+    }
+
+    pub fn chr(arguments: Vec<Value>) -> Result {
+        match expect(arguments, "chr expects 1 argument: an integer")? {
+            [Value::Int(n)] => match u32::try_from(n).ok().and_then(char::from_u32) {
+                Some(c) => Ok(Value::Char(c)),
+                None => fail(format!("{n} is not a valid character code")),
+            },
+            _ => fail("chr expects an integer argument"),
+        }
+    }
+}
+
+pub mod time {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use super::{expect, Result, Value};
+
+    fn now() -> std::time::Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+
+    pub fn time_ms(arguments: Vec<Value>) -> Result {
+        let [] = expect(arguments, "time_ms expects 0 arguments")?;
+        Ok(Value::Int(now().as_millis() as i64))
+    }
+
+    pub fn time_sec(arguments: Vec<Value>) -> Result {
+        let [] = expect(arguments, "time_sec expects 0 arguments")?;
+        Ok(Value::Float(now().as_secs_f64()))
+    }
+}
+
+pub mod random {
+    use std::cell::Cell;
+    use super::{expect, fail, Result, Value};
+
+    thread_local! {
+        // The state of a xorshift generator, seeded from the clock.
+        static STATE: Cell<u64> = Cell::new(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0x2545_f491_4f6c_dd1d, |now| now.as_nanos() as u64)
+                | 1,
+        );
+    }
+
+    fn next() -> u64 {
+        STATE.with(|state| {
+            let mut x = state.get();
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            state.set(x);
+            x
+        })
+    }
+
+    // Returns a random integer between `min` and `max`, both included.
+    pub fn rand(arguments: Vec<Value>) -> Result {
+        let [min, max] = expect(arguments, "rand expects 2 arguments: min and max")?;
+        let Value::Int(min) = min else {
+            return fail("rand expects numeric arguments for min");
+        };
+        let Value::Int(max) = max else {
+            return fail("rand expects numeric arguments for max");
+        };
+        if min > max {
+            return fail(format!("rand expects min to be at most max, got {min} and {max}"));
+        }
+        let span = (max as i128 - min as i128 + 1) as u128;
+        Ok(Value::Int((min as i128 + (next() as u128 % span) as i128) as i64))
+    }
+}
+
+pub mod os {
+    use super::{expect, fail, Result, Value};
+
+    pub fn getcwd(arguments: Vec<Value>) -> Result {
+        let [] = expect(arguments, "getcwd expects no arguments")?;
+        let directory = std::env::current_dir().map(|path| path.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(Value::from(directory))
+    }
+
+    // Returns the names of the files in a directory, or nil when it can't be read.
+    pub fn listdir(arguments: Vec<Value>) -> Result {
+        let [path] = expect(arguments, "listdir expects 1 argument")?;
+        let Value::Str(path) = path else {
+            return fail("listdir expects a string argument");
+        };
+        let Ok(entries) = std::fs::read_dir(&*path) else {
+            return Ok(Value::Nil);
+        };
+        let names = entries
+            .flatten()
+            .map(|entry| Value::from(entry.file_name().to_string_lossy().to_string()))
+            .collect::<Vec<_>>();
+        Ok(Value::from(names))
+    }
+
+    pub fn exists(arguments: Vec<Value>) -> Result {
+        let [path] = expect(arguments, "exists expects 1 argument")?;
+        let Value::Str(path) = path else {
+            return fail("exists expects a string argument");
+        };
+        Ok(Value::Bool(std::path::Path::new(&*path).exists()))
+    }
+}
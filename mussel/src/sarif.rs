@@ -0,0 +1,126 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `--error-format sarif`, which writes the errors and warnings of `mussel check` and `mussel lint`
+// as a SARIF 2.1.0 document to the standard output, for the code scanning tools, like the ones of
+// GitHub, to show them on the code (https://docs.oasis-open.org/sarif/sarif/v2.1.0/).
+// The diagnostics are collected while the command runs instead of being written, then written in
+// one document. An error has its code and a warning its name, both described in the rules of the
+// document.
+
+use std::cell::RefCell;
+use std::path::Path;
+use std::process::ExitCode;
+use std::str::FromStr;
+use codespan_reporting::diagnostic::{Diagnostic, LabelStyle, Severity};
+use crate::debugger;
+use crate::explain;
+use crate::error::FileSet;
+use crate::lsp::json::{object, Json};
+use crate::warnings::WARNINGS;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How the errors and warnings are written.
+#[derive(Clone, Copy)]
+pub enum ErrorFormat {
+    Human, // With the code they point at, to be read.
+    Sarif, // As a SARIF document, for the code scanning tools.
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "human" => Ok(ErrorFormat::Human),
+            "sarif" => Ok(ErrorFormat::Sarif),
+            _ => Err(format!("unknown error format `{name}`, expected `human` or `sarif`")),
+        }
+    }
+}
+
+thread_local! {
+    // The results found so far, while they are being collected.
+    static RESULTS: RefCell<Option<Vec<Json>>> = const { RefCell::new(None) };
+}
+
+// Runs a command writing its errors and warnings in the given format, and returns its exit status.
+pub fn report(format: ErrorFormat, run: impl FnOnce() -> ExitCode) -> ExitCode {
+    if let ErrorFormat::Human = format {
+        return run();
+    }
+    RESULTS.set(Some(Vec::new()));
+    let status = run();
+    let results = RESULTS.take().unwrap_or_default();
+    let mut rules: Vec<&str> = results.iter().filter_map(|result| result.get(&["ruleId"])?.as_str()).collect();
+    rules.sort();
+    rules.dedup();
+    let rules = rules.into_iter().map(|rule| {
+        let code = explain::CODES.iter().find(|(id, _, _)| *id == rule).map(|(_, title, _)| title);
+        let warning = WARNINGS.iter().find(|(id, _, _)| *id == rule).map(|(_, _, description)| description);
+        let description = code.or(warning).map_or("", |description| description);
+        object([("id", rule.into()), ("shortDescription", object([("text", description.into())]))])
+    });
+    let driver = object([
+        ("name", "mussel".into()),
+        ("version", env!("CARGO_PKG_VERSION").into()),
+        ("informationUri", "https://github.com/gianndev/mussel".into()),
+        ("rules", Json::Array(rules.collect())),
+    ]);
+    let run = object([
+        ("tool", object([("driver", driver)])),
+        ("columnKind", "unicodeCodePoints".into()),
+        ("results", Json::Array(results)),
+    ]);
+    let document = object([("$schema", SCHEMA.into()), ("version", "2.1.0".into()), ("runs", Json::Array(vec![run]))]);
+    println!("{document}");
+    status
+}
+
+// Tells whether the diagnostics are being collected.
+pub(crate) fn collecting() -> bool {
+    RESULTS.with_borrow(Option::is_some)
+}
+
+// Adds a diagnostic to the results, located at its primary labels.
+pub(crate) fn add(files: &FileSet, diagnostic: &Diagnostic<usize>) {
+    let level = match diagnostic.severity {
+        Severity::Bug | Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note | Severity::Help => "note",
+    };
+    let text = std::iter::once(&diagnostic.message).chain(&diagnostic.notes).cloned().collect::<Vec<_>>().join("\n");
+    let locations = diagnostic.labels.iter().filter(|label| label.style == LabelStyle::Primary).filter_map(|label| {
+        let (path, start_line, start_column) = files.position(label.file_id, label.range.start)?;
+        let (_, end_line, end_column) = files.position(label.file_id, label.range.end)?;
+        let region = object([
+            ("startLine", start_line.into()),
+            ("startColumn", start_column.into()),
+            ("endLine", end_line.into()),
+            ("endColumn", end_column.into()),
+        ]);
+        let artifact = object([("uri", uri(path).into())]);
+        Some(object([("physicalLocation", object([("artifactLocation", artifact), ("region", region)]))]))
+    });
+    let mut result = vec![
+        ("level".to_string(), level.into()),
+        ("message".to_string(), object([("text", text.into())])),
+        ("locations".to_string(), Json::Array(locations.collect())),
+    ];
+    if let Some(rule) = &diagnostic.code {
+        result.insert(0, ("ruleId".to_string(), rule.as_str().into()));
+    }
+    RESULTS.with_borrow_mut(|results| results.as_mut().map(|results| results.push(Json::Object(result))));
+}
+
+// Writes the path of a file as a URI, relative to the current directory when it is in it, like
+// the code scanning tools expect for the files of the repository.
+fn uri(path: &Path) -> String {
+    let relative = debugger::relative(path).replace('\\', "/");
+    match Path::new(&relative).is_absolute() {
+        true if relative.starts_with('/') => format!("file://{relative}"),
+        true => format!("file:///{relative}"),
+        false => relative,
+    }
+}
@@ -0,0 +1,203 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+use std::io::{self, Write};
+use std::rc::Rc;
+use crate::context::Context;
+use crate::expr::{Atom, Expr};
+use crate::interpreter::Eval;
+use crate::output;
+
+// Loads the built-ins that are always available, without an `include`.
+pub fn load(context: &mut Context) {
+    context.insert("println".to_string(), Expr::Builtin(core_println));
+    context.insert("input".to_string(), Expr::Builtin(core_input));
+    context.insert("range".to_string(), Expr::Builtin(core_range));
+    context.insert("is_nil".to_string(), Expr::Builtin(core_is_nil));
+    context.insert("typeof".to_string(), Expr::Builtin(core_typeof));
+    context.insert("get".to_string(), Expr::Builtin(core_get));
+    context.insert("eval".to_string(), Expr::Builtin(core_eval));
+    context.insert("vars".to_string(), Expr::Builtin(core_vars));
+    context.insert("defined".to_string(), Expr::Builtin(core_defined));
+    context.insert("call".to_string(), Expr::Builtin(core_call));
+    context.insert("args".to_string(), Expr::Builtin(core_args));
+    context.insert("exit".to_string(), Expr::Builtin(core_exit));
+}
+
+// Prints every argument, followed by a new line.
+//
+// Usage: `println(value)`
+pub fn core_println(args: Vec<Expr>, _context: &mut Context) -> Eval {
+    let line: String = args.iter().map(|arg| arg.to_string()).collect();
+    output::print(&format!("{line}\n"));
+    Ok(Expr::Void)
+}
+
+// Reads a line from the standard input, after printing the optional prompt.
+// Returns nil when there is nothing left to read.
+//
+// Usage: `input()` or `input(prompt)`
+pub fn core_input(args: Vec<Expr>, _context: &mut Context) -> Eval {
+    if let Some(prompt) = args.first() {
+        output::print(&prompt.to_string());
+    }
+    io::stdout().flush().expect("Failed to flush stdout");
+    let mut input_text = String::new();
+    let read = io::stdin()
+        .read_line(&mut input_text)
+        .expect("Failed to read line");
+    // Nothing left to read: the input was closed.
+    if read == 0 {
+        return Ok(Expr::Constant(Atom::Nil));
+    }
+    Ok(Expr::Constant(Atom::String(input_text.trim_end().into())))
+}
+
+// Returns a range from `start` to `end` (excluded), counting by `step`.
+// A negative step counts down, so `range(10, 0, -2)` yields 10, 8, 6, 4, 2.
+//
+// Usage: `range(start, end)` or `range(start, end, step)`
+pub fn core_range(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 2 && args.len() != 3 {
+        return context.fail("range expects 2 or 3 arguments: start, end and an optional step".to_string());
+    }
+    let mut numbers = Vec::with_capacity(args.len());
+    for arg in &args {
+        match arg {
+            Expr::Constant(Atom::Number(n)) => numbers.push(*n),
+            _ => return context.fail("range expects integer arguments".to_string()),
+        }
+    }
+    let (start, end) = (numbers[0], numbers[1]);
+    let step = numbers.get(2).copied().unwrap_or(1);
+    if step == 0 {
+        return context.fail("range step can't be zero".to_string());
+    }
+    Ok(Expr::RangeValue(start, end, step, false))
+}
+
+// Checks whether a value is nil.
+//
+// Usage: `is_nil(value)`
+pub fn core_is_nil(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("is_nil expects 1 argument".to_string());
+    }
+    Ok(Expr::Constant(Atom::Boolean(matches!(args[0], Expr::Constant(Atom::Nil)))))
+}
+
+// Returns the name of the type of a value, like "int", "string" or "function".
+// For struct instances and enum variants, returns the name of the struct or enum.
+//
+// Usage: `typeof(value)`
+pub fn core_typeof(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("typeof expects 1 argument".to_string());
+    }
+    Ok(Expr::Constant(Atom::String(args[0].type_name().into())))
+}
+
+// Returns the element of an array, or the character of a string, at the given index.
+// Unlike `array[index]`, an index out of bounds doesn't stop the script: the default value is
+// returned instead, or nil when there is none.
+//
+// Usage: `get(array, index)` or `get(array, index, default)`
+pub fn core_get(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 2 && args.len() != 3 {
+        return context.fail("get expects 2 or 3 arguments: an array, an index and an optional default value".to_string());
+    }
+    let mut args = args.into_iter();
+    let (collection, index) = (args.next().unwrap(), args.next().unwrap());
+    let default = args.next().unwrap_or(Expr::Constant(Atom::Nil));
+    let Expr::Constant(Atom::Number(index)) = index else {
+        return context.fail(format!("get expects an integer index, got {index}"));
+    };
+    let Ok(index) = usize::try_from(index) else {
+        return Ok(default);
+    };
+    let element = match collection {
+        Expr::Array(items) => items.get(index).cloned(),
+        Expr::Constant(Atom::String(s)) => s.chars().nth(index).map(|c| Expr::Constant(Atom::Char(c))),
+        invalid => return context.fail(format!("get expects an array or a string, got {invalid}")),
+    };
+    Ok(element.unwrap_or(default))
+}
+
+// Runs Mussel code given as a string, as if it was written in place of the call, and returns the
+// value of its last expression. The variables it declares stay in the current scope.
+//
+// Usage: `eval(code)`
+pub fn core_eval(args: Vec<Expr>, context: &mut Context) -> Eval {
+    match args.as_slice() {
+        [Expr::Constant(Atom::String(code))] => crate::interpreter::eval(code, context),
+        _ => context.fail("eval expects 1 argument: a string".to_string()),
+    }
+}
+
+// Returns the names of the variables and functions visible where it is called, sorted.
+// The built-in functions aren't listed.
+//
+// Usage: `vars()`
+pub fn core_vars(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if !args.is_empty() {
+        return context.fail("vars expects no arguments".to_string());
+    }
+    let names = context.names().into_iter().map(|name| Expr::Constant(Atom::String(name.into())));
+    Ok(Expr::Array(Rc::new(names.collect())))
+}
+
+// Tells whether a variable or function with the given name is visible where it is called.
+//
+// Usage: `defined(name)`
+pub fn core_defined(args: Vec<Expr>, context: &mut Context) -> Eval {
+    match args.as_slice() {
+        [Expr::Constant(Atom::String(name))] => Ok(Expr::Constant(Atom::Boolean(context.get(name).is_some()))),
+        _ => context.fail("defined expects 1 argument: a string".to_string()),
+    }
+}
+
+// Calls a function with the arguments in an array. The function can be given by name, so the
+// function to call can be chosen while the script runs.
+//
+// Usage: `call(name, arguments)` or `call(function, arguments)`
+pub fn core_call(args: Vec<Expr>, context: &mut Context) -> Eval {
+    let mut args = args.into_iter();
+    let (Some(function), Some(Expr::Array(arguments)), None) = (args.next(), args.next(), args.next()) else {
+        return context.fail("call expects 2 arguments: a function or its name, and an array of arguments".to_string());
+    };
+    let (name, function) = match function {
+        Expr::Constant(Atom::String(name)) => {
+            let Some(function) = context.get(&name) else {
+                return context.fail_as("E1002", format!("cannot find function `{name}` in this scope"));
+            };
+            (name.to_string(), function)
+        }
+        function => ("<anonymous>".to_string(), function),
+    };
+    crate::interpreter::call(function, name, Rc::unwrap_or_clone(arguments), context)
+}
+
+// Returns the arguments given to the script on the command line, after its path, as strings.
+//
+// Usage: `args()`
+pub fn core_args(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if !args.is_empty() {
+        return context.fail("args expects no arguments".to_string());
+    }
+    let arguments = context.arguments().iter().map(|argument| Expr::Constant(Atom::String(argument.as_str().into())));
+    Ok(Expr::Array(Rc::new(arguments.collect())))
+}
+
+// Ends the script right away with an exit status, 0 by default, between 0 and 255.
+//
+// Usage: `exit()` or `exit(status)`
+pub fn core_exit(args: Vec<Expr>, context: &mut Context) -> Eval {
+    match args.as_slice() {
+        [] => crate::interpreter::exit(0),
+        [Expr::Constant(Atom::Number(status))] => match u8::try_from(*status) {
+            Ok(status) => crate::interpreter::exit(status),
+            Err(_) => context.fail(format!("exit expects a status between 0 and 255, not {status}")),
+        },
+        _ => context.fail("exit expects an optional integer argument: the exit status".to_string()),
+    }
+}
@@ -1,68 +1,74 @@
-// Copyright (c) 2025 Francesco Giannice
-// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
-
-use std::collections::HashMap;
-use crate::expr::{Atom, Expr};
-
-// Loads math-related built-ins into the context.
-pub fn load(context: &mut HashMap<String, Expr>) {
-    context.insert("abs".to_string(), Expr::Builtin(math_abs));
-    context.insert("sqrt".to_string(), Expr::Builtin(math_sqrt));
-    context.insert("pow".to_string(), Expr::Builtin(math_pow));
-}
-
-// Returns the absolute value of a number.
-//
-// Usage: `abs(x)`
-pub fn math_abs(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("abs expects 1 argument");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::Number(n)) => Expr::Constant(Atom::Number(n.abs())),
-        Expr::Constant(Atom::Float(f)) => Expr::Constant(Atom::Float(f.abs())),
-        _ => panic!("abs expects a numeric argument"),
-    }
-}
-
-// Returns the square root of a number.
-//
-// Usage: `sqrt(x)`
-pub fn math_sqrt(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("sqrt expects 1 argument");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::Number(n)) => {
-            let result = (*n as f64).sqrt();
-            Expr::Constant(Atom::Float(result))
-        },
-        Expr::Constant(Atom::Float(f)) => {
-            let result = f.sqrt();
-            Expr::Constant(Atom::Float(result))
-        },
-        _ => panic!("sqrt expects a numeric argument"),
-    }
-}
-
-// Raises a number to a power.
-//
-// Usage: `pow(base, exponent)`
-pub fn math_pow(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 2 {
-        panic!("pow expects 2 arguments: base and exponent");
-    }
-    let base = match &args[0] {
-        Expr::Constant(Atom::Number(n)) => *n as f64,
-        Expr::Constant(Atom::Float(f)) => *f,
-        _ => panic!("pow expects numeric arguments"),
-    };
-    let exponent = match &args[1] {
-        Expr::Constant(Atom::Number(n)) => *n as f64,
-        Expr::Constant(Atom::Float(f)) => *f,
-        _ => panic!("pow expects numeric arguments"),
-    };
-
-    let result = base.powf(exponent);
-    Expr::Constant(Atom::Float(result))
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+use crate::context::Context;
+use crate::expr::{Atom, Expr};
+use crate::interpreter::Eval;
+
+// Loads math-related built-ins into the context.
+pub fn load(context: &mut Context) {
+    context.insert("abs".to_string(), Expr::Builtin(math_abs));
+    context.insert("sqrt".to_string(), Expr::Builtin(math_sqrt));
+    context.insert("pow".to_string(), Expr::Builtin(math_pow));
+}
+
+// Returns the absolute value of a number.
+//
+// Usage: `abs(x)`
+pub fn math_abs(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("abs expects 1 argument".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::Number(n)) => match n.checked_abs() {
+            Some(n) => Expr::Constant(Atom::Number(n)),
+            None => context.fail_as("E1017", "attempt to compute a number too large for an `int`".to_string())?,
+        },
+        Expr::Constant(Atom::Float(f)) => Expr::Constant(Atom::Float(f.abs())),
+        _ => context.fail("abs expects a numeric argument".to_string())?,
+    })
+}
+
+// Returns the square root of a number, or nil for a negative number.
+//
+// Usage: `sqrt(x)`
+pub fn math_sqrt(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("sqrt expects 1 argument".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::Number(n)) if *n < 0 => Expr::Constant(Atom::Nil),
+        Expr::Constant(Atom::Float(f)) if *f < 0.0 => Expr::Constant(Atom::Nil),
+        Expr::Constant(Atom::Number(n)) => {
+            let result = (*n as f64).sqrt();
+            Expr::Constant(Atom::Float(result))
+        },
+        Expr::Constant(Atom::Float(f)) => {
+            let result = f.sqrt();
+            Expr::Constant(Atom::Float(result))
+        },
+        _ => context.fail("sqrt expects a numeric argument".to_string())?,
+    })
+}
+
+// Raises a number to a power.
+//
+// Usage: `pow(base, exponent)`
+pub fn math_pow(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 2 {
+        return context.fail("pow expects 2 arguments: base and exponent".to_string());
+    }
+    let base = match &args[0] {
+        Expr::Constant(Atom::Number(n)) => *n as f64,
+        Expr::Constant(Atom::Float(f)) => *f,
+        _ => context.fail("pow expects numeric arguments".to_string())?,
+    };
+    let exponent = match &args[1] {
+        Expr::Constant(Atom::Number(n)) => *n as f64,
+        Expr::Constant(Atom::Float(f)) => *f,
+        _ => context.fail("pow expects numeric arguments".to_string())?,
+    };
+
+    let result = base.powf(exponent);
+    Ok(Expr::Constant(Atom::Float(result)))
 }
\ No newline at end of file
@@ -1,8 +1,17 @@
-// Copyright (c) 2025 Francesco Giannice
-// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
-
-pub mod math;
-pub mod os;
-pub mod random;
-pub mod string;
-pub mod time;
\ No newline at end of file
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+pub mod core;
+pub mod math;
+pub mod os;
+pub mod random;
+pub mod string;
+pub mod time;
+
+// Returns the source of a library of the standard library written in Mussel.
+pub fn source(name: &str) -> Option<&'static str> {
+    match name {
+        "list" => Some(include_str!("list.mus")),
+        _ => None,
+    }
+}
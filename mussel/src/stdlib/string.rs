@@ -1,113 +1,158 @@
-// Copyright (c) 2025 Francesco Giannice
-// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
-
-use core::panic;
-use std::collections::HashMap;
-use crate::expr::{Atom, Expr};
-
-// This function will be called when the user writes "include string"
-pub fn load(context: &mut HashMap<String, Expr>) {
-    // Insert built-in functions into the context
-    context.insert("lowercase".to_string(), Expr::Builtin(string_lowercase));
-    context.insert("uppercase".to_string(), Expr::Builtin(string_uppercase));
-    context.insert("length".to_string(), Expr::Builtin(string_length));
-    context.insert("split".to_string(), Expr::Builtin(string_split));
-    context.insert("reverse".to_string(), Expr::Builtin(string_reverse));
-    context.insert("trim".to_string(), Expr::Builtin(string_trim));
-    context.insert("ltrim".to_string(), Expr::Builtin(string_ltrim));
-    context.insert("rtrim".to_string(), Expr::Builtin(string_rtrim));
-}
-
-// Convert a string to lowercase
-pub fn string_lowercase(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("lowercase expects 1 argument: a string");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.to_lowercase())),
-        _ => panic!("lowercase expects a string argument"),
-    }
-}
-
-// Convert a string to uppercase
-pub fn string_uppercase(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("uppercase expects 1 argument: a string");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.to_uppercase())),
-        _ => panic!("uppercase expects a string argument"),
-    }
-}
-
-// Get the length of a string
-pub fn string_length(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("length expects 1 argument: a string");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::Number(s.len() as i64)),
-        _ => panic!("length expects a string argument"),
-    }
-}
-
-// Split a string by another
-pub fn string_split(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 2 {
-        panic!("split expects 2 arguments: strings");
-    }
-    match (&args[0], &args[1]) {
-        (Expr::Constant(Atom::String(s1)), Expr::Constant(Atom::String(s2))) => Expr::Array(
-            s1.split(s2.as_str())
-                .map(|s| Expr::Constant(Atom::String(s.to_string())))
-                .collect(),
-        ),
-        _ => panic!("split expects strings as arguments"),
-    }
-}
-
-// Reverse a string
-pub fn string_reverse(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("reverse expects 1 argument: a string");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::String(s)) => {
-            Expr::Constant(Atom::String(s.as_str().chars().rev().collect()))
-        }
-        _ => panic!("reverse expects a string argument"),
-    }
-}
-
-//Remove whitespace from both ends
-pub fn string_trim(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("trim expects 1 argument: a string");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.trim().to_string())),
-        _ => panic!("trim expects a string as argument"),
-    }
-}
-
-//Remove leading whitespace
-pub fn string_ltrim(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("ltrim expects 1 argument: a string");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.trim_start().to_string())),
-        _ => panic!("ltrim expects a string argument"),
-    }
-}
-
-//Remove trailing whitespace
-pub fn string_rtrim(args: Vec<Expr>, _context: &mut HashMap<String, Expr>) -> Expr {
-    if args.len() != 1 {
-        panic!("rtrim expects 1 argument: a string");
-    }
-    match &args[0] {
-        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.trim_end().to_string())),
-        _ => panic!("rtrim expects a string argument"),
-    }
-}
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+use std::rc::Rc;
+use crate::context::Context;
+use crate::expr::{Atom, Expr};
+use crate::interpreter::Eval;
+
+// This function will be called when the user writes "include string"
+pub fn load(context: &mut Context) {
+    // Insert built-in functions into the context
+    context.insert("lowercase".to_string(), Expr::Builtin(string_lowercase));
+    context.insert("uppercase".to_string(), Expr::Builtin(string_uppercase));
+    context.insert("length".to_string(), Expr::Builtin(string_length));
+    context.insert("split".to_string(), Expr::Builtin(string_split));
+    context.insert("reverse".to_string(), Expr::Builtin(string_reverse));
+    context.insert("trim".to_string(), Expr::Builtin(string_trim));
+    context.insert("ltrim".to_string(), Expr::Builtin(string_ltrim));
+    context.insert("rtrim".to_string(), Expr::Builtin(string_rtrim));
+    context.insert("chars".to_string(), Expr::Builtin(string_chars));
+    context.insert("ord".to_string(), Expr::Builtin(string_ord));
+    context.insert("chr".to_string(), Expr::Builtin(string_chr));
+}
+
+// Convert a string to lowercase
+pub fn string_lowercase(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("lowercase expects 1 argument: a string".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.to_lowercase().into())),
+        _ => context.fail("lowercase expects a string argument".to_string())?,
+    })
+}
+
+// Convert a string to uppercase
+pub fn string_uppercase(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("uppercase expects 1 argument: a string".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.to_uppercase().into())),
+        _ => context.fail("uppercase expects a string argument".to_string())?,
+    })
+}
+
+// Get the length of a string, or the number of elements of an array
+pub fn string_length(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("length expects 1 argument: a string or an array".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::Number(s.len() as i64)),
+        Expr::Array(items) => Expr::Constant(Atom::Number(items.len() as i64)),
+        _ => context.fail("length expects a string or an array argument".to_string())?,
+    })
+}
+
+// Split a string by another
+pub fn string_split(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 2 {
+        return context.fail("split expects 2 arguments: strings".to_string());
+    }
+    Ok(match (&args[0], &args[1]) {
+        (Expr::Constant(Atom::String(s1)), Expr::Constant(Atom::String(s2))) => Expr::Array(Rc::new(
+            s1.split(&**s2)
+                .map(|s| Expr::Constant(Atom::String(s.into())))
+                .collect(),
+        )),
+        _ => context.fail("split expects strings as arguments".to_string())?,
+    })
+}
+
+// Reverse a string
+pub fn string_reverse(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("reverse expects 1 argument: a string".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => {
+            Expr::Constant(Atom::String(s.chars().rev().collect::<String>().into()))
+        }
+        _ => context.fail("reverse expects a string argument".to_string())?,
+    })
+}
+
+//Remove whitespace from both ends
+pub fn string_trim(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("trim expects 1 argument: a string".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.trim().into())),
+        _ => context.fail("trim expects a string as argument".to_string())?,
+    })
+}
+
+//Remove leading whitespace
+pub fn string_ltrim(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("ltrim expects 1 argument: a string".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.trim_start().into())),
+        _ => context.fail("ltrim expects a string argument".to_string())?,
+    })
+}
+
+//Remove trailing whitespace
+pub fn string_rtrim(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("rtrim expects 1 argument: a string".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => Expr::Constant(Atom::String(s.trim_end().into())),
+        _ => context.fail("rtrim expects a string argument".to_string())?,
+    })
+}
+
+// Split a string into its characters
+pub fn string_chars(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("chars expects 1 argument: a string".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::String(s)) => {
+            Expr::Array(Rc::new(s.chars().map(|c| Expr::Constant(Atom::Char(c))).collect()))
+        }
+        _ => context.fail("chars expects a string argument".to_string())?,
+    })
+}
+
+// Get the code point of a character
+pub fn string_ord(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("ord expects 1 argument: a character".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::Char(c)) => Expr::Constant(Atom::Number(*c as i64)),
+        _ => context.fail("ord expects a character argument".to_string())?,
+    })
+}
+
+// Get the character with the given code point
+pub fn string_chr(args: Vec<Expr>, context: &mut Context) -> Eval {
+    if args.len() != 1 {
+        return context.fail("chr expects 1 argument: an integer".to_string());
+    }
+    Ok(match &args[0] {
+        Expr::Constant(Atom::Number(n)) => {
+            match u32::try_from(*n).ok().and_then(char::from_u32) {
+                Some(c) => Expr::Constant(Atom::Char(c)),
+                None => context.fail(format!("{n} is not a valid character code"))?,
+            }
+        }
+        _ => context.fail("chr expects an integer argument".to_string())?,
+    })
+}
@@ -0,0 +1,168 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel test`, which runs the tests of a project written in Mussel.
+// The tests are the files whose name ends with `_test.mus`, found in the directories given, and
+// the functions of these files whose name starts with `test_`. Each file runs in its own
+// interpreter, then its test functions are called one after the other: a test fails when an error
+// stops it, like a failed `assert`, or when it calls `exit` with a status other than 0. A file
+// without test functions is a test itself, which fails when an error stops it.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use crate::context::Context;
+use crate::expr::Expr;
+use crate::interpreter;
+use crate::interpreter::{Backend, Interaction};
+use crate::loader::Loader;
+
+// What ends the name of the files holding tests.
+const TEST_FILE_SUFFIX: &str = "_test.mus";
+
+// What starts the name of the test functions.
+const TEST_FUNCTION_PREFIX: &str = "test_";
+
+// The name of the calls to the test functions in the errors reported.
+const CALL: &str = "<test>";
+
+// Runs the tests of the given files, and of the test files found in the given directories, then
+// prints how many passed and failed. Fails when a test failed.
+pub fn run(paths: &[String], check_types: bool, max_depth: usize, backend: Backend) -> ExitCode {
+    let files = files(paths, TEST_FILE_SUFFIX);
+    let mut summary = Summary { passed: 0, failed: 0 };
+    for file in &files {
+        run_file(file, check_types, max_depth, backend, &mut summary);
+    }
+    let Summary { passed, failed } = summary;
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    let plural = if files.len() == 1 { "" } else { "s" };
+    println!("\ntest result: {result}. {passed} passed; {failed} failed; {} file{plural}", files.len());
+    if failed == 0 { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+// How many tests passed and failed.
+struct Summary {
+    passed: usize,
+    failed: usize,
+}
+
+impl Summary {
+    // Prints the result of a test right after it ran, below the errors reported for it.
+    fn record(&mut self, test: &str, succeeded: bool) {
+        println!("test {test} ... {}", if succeeded { "ok" } else { "FAILED" });
+        io::stdout().flush().expect("Failed to flush stdout");
+        if succeeded {
+            self.passed += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+}
+
+// Returns the given files, and the files whose name ends with `suffix` in the given directories.
+pub(crate) fn files(paths: &[String], suffix: &str) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = PathBuf::from(path);
+        if path.is_dir() {
+            discover(&path, suffix, &mut files);
+        } else {
+            files.push(path);
+        }
+    }
+    files
+}
+
+// Adds the files whose name ends with `suffix` in a directory and in the directories inside it, in
+// the order of their names. The hidden directories, like `.git`, are skipped.
+fn discover(directory: &Path, suffix: &str, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+    let mut paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+    paths.sort();
+    for path in paths {
+        let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if path.is_dir() && !name.starts_with('.') {
+            discover(&path, suffix, files);
+        } else if path.is_file() && name.ends_with(suffix) {
+            files.push(path);
+        }
+    }
+}
+
+// Runs a test file and its test functions, in a new interpreter, recording whether each test
+// passed. The errors stopping them are reported.
+fn run_file(file: &Path, check_types: bool, max_depth: usize, backend: Backend, summary: &mut Summary) {
+    let name = display(file);
+    let Some((mut context, tests)) = start(file, TEST_FUNCTION_PREFIX, check_types, max_depth, backend) else {
+        return summary.record(&name, false);
+    };
+    if tests.is_empty() {
+        return summary.record(&name, true);
+    }
+    for test in tests {
+        let succeeded = call(&test, &mut context).is_some_and(|call| passed(interpreter::interact(call, &mut context)));
+        summary.record(&format!("{name}::{test}"), succeeded);
+    }
+}
+
+// Returns how a file is named in the results, without the `./` of the files found in the current
+// directory.
+pub(crate) fn display(file: &Path) -> String {
+    file.strip_prefix(".").unwrap_or(file).display().to_string()
+}
+
+// Runs a file in a new interpreter, like a file included by the code calling its functions, so the
+// functions only called that way aren't reported as unused.
+// Returns the interpreter, with the names of the functions of the file starting with `prefix`,
+// or `None` when an error stopped the file, which was reported.
+pub(crate) fn start(
+    file: &Path,
+    prefix: &str,
+    check_types: bool,
+    max_depth: usize,
+    backend: Backend,
+) -> Option<(Context, Vec<String>)> {
+    let mut context = interpreter::session(Loader::new(check_types), max_depth, backend);
+    context.loader().enter(Path::new(CALL));
+    context.loader().enter(file);
+    let exprs = match context.loader().load(file) {
+        Ok(exprs) => exprs,
+        Err(error) => {
+            context.loader().show(&error);
+            return None;
+        }
+    };
+    let functions: Vec<String> = exprs
+        .iter()
+        .filter_map(|expr| match expr {
+            Expr::Function(function, ..) if function.starts_with(prefix) => Some(function.clone()),
+            _ => None,
+        })
+        .collect();
+    let ran = passed(interpreter::interact(exprs, &mut context));
+    context.loader().leave();
+    ran.then_some((context, functions))
+}
+
+// Parses a call to a function of a file started with `start`, reporting the errors.
+pub(crate) fn call(function: &str, context: &mut Context) -> Option<Vec<Expr>> {
+    match context.loader().load_source(CALL, &format!("{function}()")) {
+        Ok(call) => Some(call),
+        Err(error) => {
+            context.loader().show(&error);
+            None
+        }
+    }
+}
+
+// Tells whether code run by a test ended without an error, or with `exit(0)`.
+pub(crate) fn passed(interaction: Interaction) -> bool {
+    match interaction {
+        Interaction::Value(_) => true,
+        Interaction::Failed => false,
+        Interaction::Exit(status) => status == 0,
+    }
+}
@@ -0,0 +1,82 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `--time`, which prints how long each phase of running a script took once it ends, to see which
+// one got slower: lexing the files into tokens, parsing the tokens, analyzing the statements (the
+// warnings and the types), converting them into the expressions to evaluate, and evaluating them.
+// The peak memory used is printed too when it is known, see `limits::peak`.
+// The files included by the script are loaded while it runs: the time spent loading them is
+// counted in the phases of the loading, not in the evaluation.
+
+use std::cell::{Cell, RefCell};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use crate::bench;
+use crate::limits;
+
+/// A phase of running a script.
+#[derive(Clone, Copy)]
+pub enum Phase {
+    Lexing,
+    Parsing,
+    Analysis,
+    Conversion,
+    Evaluation,
+}
+
+// The names of the phases, in the order they run.
+const PHASES: [&str; 5] = ["lexing", "parsing", "analysis", "conversion", "evaluation"];
+
+thread_local! {
+    // The time spent in each phase so far, while it is being measured.
+    static TIMES: RefCell<Option<[Duration; PHASES.len()]>> = const { RefCell::new(None) };
+    // The time spent in the phases measured within the one running, not counted in it.
+    static NESTED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+// Runs a script, printing how long each phase took to the standard error once it ends when `time`
+// is set, and returns its exit status.
+pub fn report(time: bool, run: impl FnOnce() -> ExitCode) -> ExitCode {
+    if !time {
+        return run();
+    }
+    TIMES.set(Some([Duration::ZERO; PHASES.len()]));
+    NESTED.set(Duration::ZERO);
+    let start = Instant::now();
+    let status = run();
+    let total = start.elapsed();
+    let times = TIMES.take().unwrap_or_default();
+    let width = "evaluation".len();
+    eprintln!("{:width$}  {:>12}", "phase", "time");
+    for (phase, time) in PHASES.iter().zip(times) {
+        eprintln!("{phase:width$}  {:>12}", bench::duration(time.as_secs_f64()));
+    }
+    eprintln!("{:width$}  {:>12}", "total", bench::duration(total.as_secs_f64()));
+    if let Some(peak) = limits::peak() {
+        let (amount, unit) = match peak as f64 / 1024.0 {
+            kib if kib >= 1024.0 => (kib / 1024.0, "MiB"),
+            kib => (kib, "KiB"),
+        };
+        eprintln!("peak memory: {amount:.1} {unit}");
+    }
+    status
+}
+
+// Runs a phase, adding the time it took to it while the phases are measured. The time spent in the
+// phases it runs itself, like the loading of an included file while evaluating, is left out.
+pub fn measure<T>(phase: Phase, run: impl FnOnce() -> T) -> T {
+    if TIMES.with_borrow(Option::is_none) {
+        return run();
+    }
+    let outer = NESTED.replace(Duration::ZERO);
+    let start = Instant::now();
+    let result = run();
+    let elapsed = start.elapsed();
+    let nested = NESTED.replace(outer + elapsed);
+    TIMES.with_borrow_mut(|times| {
+        if let Some(times) = times {
+            times[phase as usize] += elapsed.saturating_sub(nested);
+        }
+    });
+    result
+}
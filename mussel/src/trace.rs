@@ -0,0 +1,101 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `--trace`, which writes the expressions a script evaluates to the standard error, to follow how
+// it runs. Each one is written once evaluated, with where it is written, the code it points at in
+// errors, and its value, indented by the number of function calls running:
+//   main.mus:7:13: Call `square(item)` = 9
+// Only the expressions which point at code are written: the variables, the calls, the operators,
+// the fields and the indexes, not the literals or the statements.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use crate::context::Context;
+use crate::debugger;
+use crate::error::{FileIdentifier, Location};
+use crate::expr::Expr;
+
+/// Which expressions `--trace` writes.
+#[derive(Clone, Copy)]
+pub enum Trace {
+    All,   // Every expression pointing at code.
+    Calls, // The calls of functions and methods only.
+}
+
+impl FromStr for Trace {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "all" => Ok(Trace::All),
+            "calls" => Ok(Trace::Calls),
+            _ => Err(format!("unknown trace `{name}`, expected `all` or `calls`")),
+        }
+    }
+}
+
+// Writes the expressions evaluated, finding their lines in the files.
+pub(crate) struct Tracer {
+    trace: Trace,
+    lines: HashMap<FileIdentifier, Vec<usize>>, // Where each line of a file starts.
+}
+
+impl Tracer {
+    pub(crate) fn new(trace: Trace) -> Self {
+        Tracer { trace, lines: HashMap::new() }
+    }
+
+    // Returns the name and the location of an expression written once evaluated, or `None` for
+    // the expressions which aren't traced. A `return f(x)` is traced as the call.
+    pub(crate) fn traced(&self, expr: &Expr) -> Option<(&'static str, Location)> {
+        let (kind, location) = match expr {
+            Expr::Call(_, _, location) => ("Call", location),
+            Expr::MethodCall(_, _, _, location) => ("MethodCall", location),
+            Expr::Return(value) => match &**value {
+                Expr::Call(_, _, location) => ("Call", location),
+                _ => return None,
+            },
+            _ if matches!(self.trace, Trace::Calls) => return None,
+            Expr::Variable(_, location) => ("Variable", location),
+            Expr::Compare(_, _, _, location) => ("Compare", location),
+            Expr::Logic(_, _, _, location) => ("Logic", location),
+            Expr::Binary(_, _, _, location) => ("Binary", location),
+            Expr::Unary(_, _, location) => ("Unary", location),
+            Expr::Range(_, _, _, location) => ("Range", location),
+            Expr::Get(_, _, location) => ("Get", location),
+            Expr::Field(_, _, location) => ("Field", location),
+            Expr::Path(_, _, location) => ("Path", location),
+            Expr::Comprehension(_, _, _, location, _) => ("Comprehension", location),
+            _ => return None,
+        };
+        Some((kind, location.clone()))
+    }
+
+    // Writes an evaluated expression with its value.
+    pub(crate) fn write(&mut self, kind: &str, location: &Location, value: &Expr, context: &mut Context) {
+        let depth = context.depth();
+        let loader = context.loader();
+        let content = loader.content(location.file());
+        let lines = self.lines.entry(location.file()).or_insert_with(|| {
+            std::iter::once(0).chain(content.match_indices('\n').map(|(index, _)| index + 1)).collect()
+        });
+        let range = location.range();
+        let line = lines.partition_point(|start| *start <= range.start);
+        let column = content[lines[line - 1]..range.start].chars().count() + 1;
+        // A call written over several lines is written on one.
+        let code = content[range].split_whitespace().collect::<Vec<_>>().join(" ");
+        let path = debugger::relative(loader.path(location.file()));
+        eprintln!("{}{path}:{line}:{column}: {kind} `{code}` = {}", "  ".repeat(depth), describe(value));
+    }
+}
+
+// Writes a value, or how the expression giving it left the function or the block it is in.
+fn describe(value: &Expr) -> String {
+    match value {
+        Expr::Return(value) => describe(value),
+        Expr::Throw(error, _) => format!("throws {error}"),
+        // The function called is run once the function returning its value has ended.
+        Expr::TailCall(..) => "(tail call)".to_string(),
+        value => debugger::show(value),
+    }
+}
@@ -0,0 +1,130 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The walks over the expressions of the AST shared by the compiler to bytecode and the emitters of
+// Rust and JavaScript.
+
+use crate::error::Location;
+use crate::expr::{Binding, Expr, Parameter, StringPart};
+
+// Returns the expressions evaluated as part of an expression, with the bodies of the functions it
+// declares when `functions` is set.
+pub(crate) fn children(expr: &Expr, functions: bool) -> Vec<&Expr> {
+    fn parameters(parameters: &[Parameter]) -> impl Iterator<Item = &Expr> {
+        parameters.iter().filter_map(|parameter| parameter.default.as_ref())
+    }
+    let mut children: Vec<&Expr> = Vec::new();
+    match expr {
+        Expr::Array(elements) => children.extend(elements.iter()),
+        Expr::Interpolation(parts) => children.extend(parts.iter().filter_map(|part| match part {
+            StringPart::Value(value, _) => Some(value),
+            StringPart::Text(_) => None,
+        })),
+        Expr::Let(_, value, _) | Expr::Const(_, value, _) | Expr::Return(value) | Expr::Throw(value, _) | Expr::Break(value, _, _) => {
+            children.push(value);
+        }
+        Expr::Unary(_, operand, _) => children.push(operand),
+        Expr::Field(instance, _, _) => children.push(instance),
+        Expr::Assign(place, value, _) => children.extend([&**place, &**value]),
+        Expr::Compare(left, _, right, _)
+        | Expr::Logic(left, _, right, _)
+        | Expr::Binary(left, _, right, _)
+        | Expr::Range(left, right, _, _)
+        | Expr::Get(left, right, _) => children.extend([&**left, &**right]),
+        Expr::Call(callee, arguments, _) => {
+            children.push(callee);
+            children.extend(arguments);
+        }
+        Expr::MethodCall(receiver, _, arguments, _) => {
+            children.push(receiver);
+            children.extend(arguments);
+        }
+        Expr::If(condition, then, otherwise, _) => {
+            children.push(condition);
+            children.extend(then);
+            children.extend(otherwise.iter().flatten());
+        }
+        Expr::Try(body, _, catch) => children.extend(body.iter().chain(catch)),
+        Expr::Assert(condition, message, _) => {
+            children.push(condition);
+            children.extend(message.as_deref());
+        }
+        Expr::For(_, collection, body, _, _) => {
+            children.push(collection);
+            children.extend(body);
+        }
+        Expr::Comprehension(value, _, collection, _, condition) => {
+            children.extend([&**value, &**collection]);
+            children.extend(condition.as_ref().map(|(condition, _)| &**condition));
+        }
+        Expr::Until(condition, body, _, _) => {
+            children.push(condition);
+            children.extend(body);
+        }
+        Expr::Loop(body, _) => children.extend(body),
+        Expr::Match(value, arms) => {
+            children.push(value);
+            children.extend(arms.iter().flat_map(|(_, body)| body));
+        }
+        Expr::Closure(defaults, _, body, _) | Expr::Function(_, defaults, _, body) if functions => {
+            children.extend(parameters(defaults));
+            children.extend(body);
+        }
+        Expr::Impl(_, _, methods, _) if functions => children.extend(methods),
+        _ => {}
+    }
+    children
+}
+
+// Returns whether an expression, or one inside it, matches, looking into the functions it
+// declares when `functions` is set.
+pub(crate) fn any(expr: &Expr, functions: bool, predicate: &dyn Fn(&Expr) -> bool) -> bool {
+    predicate(expr) || children(expr, functions).into_iter().any(|child| any(child, functions, predicate))
+}
+
+// Returns whether an expression reads a name when it's evaluated, not counting the functions it
+// declares, which run later.
+pub(crate) fn mentions(expr: &Expr, name: &str) -> bool {
+    any(expr, false, &|expr| match expr {
+        Expr::Variable(variable, _) | Expr::MethodCall(_, variable, _, _) | Expr::Path(variable, _, _) => variable == name,
+        _ => false,
+    })
+}
+
+// Returns the location of an expression, or else of the first expression inside it with one.
+pub(crate) fn location(expr: &Expr) -> Option<&Location> {
+    let own = match expr {
+        Expr::Variable(_, location)
+        | Expr::Assign(_, _, location)
+        | Expr::Call(_, _, location)
+        | Expr::MethodCall(_, _, _, location)
+        | Expr::Compare(_, _, _, location)
+        | Expr::Logic(_, _, _, location)
+        | Expr::If(_, _, _, location)
+        | Expr::Assert(_, _, location)
+        | Expr::For(_, _, _, location, _)
+        | Expr::Comprehension(_, _, _, location, _)
+        | Expr::Get(_, _, location)
+        | Expr::Until(_, _, location, _)
+        | Expr::Break(_, _, location)
+        | Expr::Continue(_, location)
+        | Expr::Binary(_, _, _, location)
+        | Expr::Unary(_, _, location)
+        | Expr::Range(_, _, _, location)
+        | Expr::Include(_, _, location)
+        | Expr::Impl(_, _, _, location)
+        | Expr::Field(_, _, location)
+        | Expr::Path(_, _, location) => Some(location),
+        _ => None,
+    };
+    own.or_else(|| children(expr, true).into_iter().find_map(location))
+}
+
+// Adds the names a binding declares.
+pub(crate) fn binding_names(binding: &Binding, names: &mut Vec<String>) {
+    match binding {
+        Binding::Name(name) => names.push(name.clone()),
+        Binding::Wildcard => {}
+        Binding::Array(bindings, _) => bindings.iter().for_each(|binding| binding_names(binding, names)),
+    }
+}
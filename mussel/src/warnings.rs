@@ -0,0 +1,88 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// The warnings, found by the analysis done before a script runs and by `mussel lint`, each with a
+// name shown with it. A warning is turned on with `-W name` and off with `-A name`, and the
+// warnings of a line are turned off by a `// mussel:allow(name)` comment on it or on the line
+// before it. With `--deny-warnings` the warnings are errors, so the script doesn't run.
+// The choice holds for the whole process, like `color`, the script runs in another thread than the
+// one reading the options.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// The warnings, with whether they are on by default and what they find: the ones of the analysis
+/// first, then the rules of `mussel lint`, see `lint::RULES`.
+pub const WARNINGS: [(&str, bool, &str); 9] = [
+    ("unused-variable", true, "a variable or a constant which is never read"),
+    ("unused-function", true, "a function which is never called"),
+    ("unreachable-code", true, "a statement following a `return`, `throw`, `break` or `continue`"),
+    ("shadowed-variable", true, "a variable hiding a parameter, or one declared outside its block"),
+    ("constant-condition", true, "an `if` or `until` whose condition is always the same"),
+    ("unused-include", true, "an `include` nothing is used from"),
+    ("suspicious-assignment", true, "an assignment used as a condition, where `==` was likely meant"),
+    ("self-assignment", true, "a variable or field assigned to itself"),
+    ("comparison-to-bool", false, "a comparison with `true` or `false`, which can be the condition itself"),
+];
+
+/// How many of the warnings are found by the analysis, the others are the rules of `mussel lint`.
+pub(crate) const ANALYSIS: usize = 3;
+
+// Whether each warning was turned on or off, or is left as it is by default.
+const DEFAULT: u8 = 0;
+const ON: u8 = 1;
+const OFF: u8 = 2;
+
+static LEVELS: [AtomicU8; WARNINGS.len()] = [const { AtomicU8::new(DEFAULT) }; WARNINGS.len()];
+static DENIED: AtomicBool = AtomicBool::new(false);
+
+// Turns on the warnings named in `warn` and off the ones in `allow`, and makes the warnings errors
+// when `deny` is set. Fails, telling so, when a name isn't the one of a warning.
+pub fn set(warn: &[String], allow: &[String], deny: bool) -> bool {
+    for (names, level) in [(warn, ON), (allow, OFF)] {
+        for name in names {
+            let Some(index) = WARNINGS.iter().position(|(id, _, _)| id == name) else {
+                let names: Vec<&str> = WARNINGS.iter().map(|(id, _, _)| *id).collect();
+                eprintln!("error: unknown warning `{name}`, the warnings are: {}", names.join(", "));
+                return false;
+            };
+            LEVELS[index].store(level, Ordering::Relaxed);
+        }
+    }
+    DENIED.store(deny, Ordering::Relaxed);
+    true
+}
+
+// Tells whether the warnings are errors, with `--deny-warnings`.
+pub(crate) fn denied() -> bool {
+    DENIED.load(Ordering::Relaxed)
+}
+
+// Tells whether a warning found in a file is reported: when it is on, and not allowed by a comment
+// on the line where it starts or on the line before it.
+pub(crate) fn reported(name: &str, content: &str, range: Option<&Range<usize>>) -> bool {
+    let on = match WARNINGS.iter().position(|(id, _, _)| *id == name) {
+        Some(index) => match LEVELS[index].load(Ordering::Relaxed) {
+            DEFAULT => WARNINGS[index].1,
+            level => level == ON,
+        },
+        None => true,
+    };
+    let Some(range) = range else {
+        return on;
+    };
+    let start = content.get(..range.start).and_then(|before| before.rfind('\n')).map_or(0, |newline| newline + 1);
+    let end = content[start..].find('\n').map_or(content.len(), |newline| start + newline);
+    let line = &content[start..end];
+    let previous = content[..start.saturating_sub(1)].rsplit('\n').next().filter(|_| start > 0);
+    on && !allows(line, name) && !previous.is_some_and(|previous| allows(previous, name))
+}
+
+// Tells whether a line has a `// mussel:allow(...)` comment naming the warning.
+fn allows(line: &str, name: &str) -> bool {
+    let Some(index) = line.find("mussel:allow(") else {
+        return false;
+    };
+    let names = line[index + "mussel:allow(".len()..].split(')').next().unwrap_or_default();
+    line[..index].contains("//") && names.split(',').any(|allowed| allowed.trim() == name)
+}
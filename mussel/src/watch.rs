@@ -0,0 +1,129 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// `mussel --watch`, which runs a script again each time it, or a file it includes, changes.
+// The script runs in another `mussel` process, given the same arguments without `--watch`, so a
+// change can stop a script that hasn't ended yet, like one waiting for input or looping forever.
+// The directories of the files are watched rather than the files, as many editors save a file by
+// replacing it with a new one. The screen is cleared before each run.
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitCode};
+use std::sync::mpsc;
+use std::time::Duration;
+use notify::event::ModifyKind;
+use notify::{EventKind, RecursiveMode, Watcher};
+use crate::bundle;
+use crate::loader::Loader;
+
+// How long to wait for the other changes of a save, like a file written in several steps, before
+// running the script again.
+const SETTLE: Duration = Duration::from_millis(100);
+
+// How often to look whether the script ended while waiting for changes.
+const POLL: Duration = Duration::from_millis(100);
+
+// The ANSI sequence clearing the terminal and moving the cursor to its top left corner.
+const CLEAR: &str = "\x1b[2J\x1b[H";
+
+// Runs a script with the given arguments of `mussel`, without `--watch`, and again each time it or
+// one of the files it includes changes, until `mussel` is stopped.
+// Fails only when the files can't be watched.
+pub fn watch(script: &str, arguments: &[String]) -> ExitCode {
+    let interpreter = match std::env::current_exe() {
+        Ok(interpreter) => interpreter,
+        Err(error) => {
+            eprintln!("error: Failed to find the interpreter to run the script: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (sender, changes) = mpsc::channel();
+    let watcher = notify::recommended_watcher(move |event| {
+        // The receiver only goes away when `mussel` ends.
+        let _ = sender.send(event);
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(error) => {
+            eprintln!("error: Failed to watch the files of the script: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut directories: Vec<PathBuf> = Vec::new();
+    loop {
+        // The files are found again before each run, as the includes may have changed.
+        let files = files(script);
+        for directory in &directories {
+            let _ = watcher.unwatch(directory);
+        }
+        directories = files.iter().filter_map(|file| file.parent().map(Path::to_path_buf)).collect();
+        directories.sort();
+        directories.dedup();
+        for directory in &directories {
+            if let Err(error) = watcher.watch(directory, RecursiveMode::NonRecursive) {
+                eprintln!("error: Failed to watch {}: {error}", directory.display());
+                return ExitCode::FAILURE;
+            }
+        }
+        print!("{CLEAR}");
+        io::stdout().flush().expect("Failed to flush stdout");
+        let mut child = Command::new(&interpreter).args(arguments).spawn().ok();
+        if child.is_none() {
+            eprintln!("error: Failed to run the script");
+        }
+        // Waits for a change, telling when the script ends in the meantime.
+        loop {
+            match changes.recv_timeout(POLL) {
+                Ok(Ok(event)) if changed(&event.kind) && event.paths.iter().any(|path| files.contains(path)) => break,
+                Ok(_) | Err(mpsc::RecvTimeoutError::Timeout) => ended(&mut child),
+                Err(mpsc::RecvTimeoutError::Disconnected) => return ExitCode::FAILURE,
+            }
+        }
+        if let Some(mut running) = child {
+            let _ = running.kill();
+            let _ = running.wait();
+        }
+        while changes.recv_timeout(SETTLE).is_ok() {}
+    }
+}
+
+// Returns the script and the files it includes, with their canonical path, which is the one given
+// by the changes. A file which can't be found or read is left out, its errors are reported when
+// the script runs.
+fn files(script: &str) -> Vec<PathBuf> {
+    let loader = Loader::new(false);
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut pending = vec![std::fs::canonicalize(script).unwrap_or_else(|_| PathBuf::from(script))];
+    while let Some(path) = pending.pop() {
+        if files.contains(&path) {
+            continue;
+        }
+        if let Ok(content) = std::fs::read_to_string(&path) {
+            let includes = bundle::includes(&content);
+            pending.extend(includes.iter().filter_map(|include| loader.find_from(&path, Path::new(include)).ok()));
+        }
+        files.push(path);
+    }
+    files
+}
+
+// Tells whether an event changed the content of a file, rather than only reading it.
+fn changed(kind: &EventKind) -> bool {
+    matches!(
+        kind,
+        EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Name(_) | ModifyKind::Any)
+    )
+}
+
+// Tells when the script ended, once.
+fn ended(child: &mut Option<Child>) {
+    let Some(status) = child.as_mut().and_then(|running| running.try_wait().ok().flatten()) else {
+        return;
+    };
+    *child = None;
+    match status.code() {
+        Some(code) => println!("\n[the script exited with status {code}, waiting for changes]"),
+        None => println!("\n[the script was stopped, waiting for changes]"),
+    }
+}
@@ -0,0 +1,51 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Runs the `mussel` command on scripts written for a test, and gives what it printed.
+
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// What a run of `mussel` gave: its exit status, and what it wrote to the standard output and error.
+pub struct Run {
+    pub status: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+// Writes a script in the directory of the tests, under a name which is unique to the test.
+pub fn script(name: &str, source: &str) -> PathBuf {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.mus"));
+    std::fs::write(&path, source).expect("the script can be written");
+    path
+}
+
+// Runs `mussel` with the given arguments, with nothing on its standard input.
+pub fn mussel(args: &[&str]) -> Run {
+    mussel_in(Path::new("."), args)
+}
+
+// Runs `mussel` like `mussel`, from another directory.
+pub fn mussel_in(directory: &Path, args: &[&str]) -> Run {
+    let output = Command::new(env!("CARGO_BIN_EXE_mussel"))
+        .args(args)
+        .current_dir(directory)
+        .env("NO_COLOR", "1")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("mussel can be run");
+    Run {
+        status: output.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+// Runs a script with both backends, the tree-walking interpreter and the virtual machine.
+pub fn run_both(name: &str, source: &str) -> [Run; 2] {
+    let path = script(name, source);
+    let path = path.to_str().expect("the path is valid UTF-8");
+    ["tree", "vm"].map(|backend| mussel(&["--backend", backend, "-A", "unused-variable", path]))
+}
@@ -0,0 +1,35 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+mod common;
+
+#[test]
+fn unpacking_too_few_elements_points_at_the_pattern() {
+    for run in common::run_both("too_few_elements", "let [a, b] = [1]\n") {
+        assert_eq!(run.status, 1);
+        let message = "error[E1000]: cannot unpack an array of 1 elements into 2 variables";
+        assert!(run.stderr.contains(message), "{}", run.stderr);
+        assert!(run.stderr.contains("too_few_elements.mus:1:5"), "{}", run.stderr);
+        assert!(run.stderr.contains("^^^^^^ expected 2 elements"), "{}", run.stderr);
+    }
+}
+
+#[test]
+fn unpacking_a_value_which_is_not_an_array_points_at_the_pattern() {
+    for run in common::run_both("not_an_array", "let [a] = \"x\"\n") {
+        assert_eq!(run.status, 1);
+        assert!(run.stderr.contains("error[E1000]: cannot unpack `x`, it is not an array"), "{}", run.stderr);
+        assert!(run.stderr.contains("not_an_array.mus:1:5"), "{}", run.stderr);
+        assert!(run.stderr.contains("^^^ expected an array, found `string`"), "{}", run.stderr);
+    }
+}
+
+#[test]
+fn unpacking_a_nested_pattern_points_at_the_inner_pattern() {
+    let source = "for (i, [a, b]) in [[0, [1, 2]], [1, [3]]] {\n    println(a)\n}\n";
+    for run in common::run_both("nested_pattern", source) {
+        assert_eq!(run.status, 1);
+        assert_eq!(run.stdout, "1\n");
+        assert!(run.stderr.contains("nested_pattern.mus:1:9"), "{}", run.stderr);
+    }
+}
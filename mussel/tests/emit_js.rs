@@ -0,0 +1,91 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+// Checks that the scripts translated by `mussel emit-js` print what the interpreter prints, when
+// Node.js is installed.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// The examples whose output changes from one run to the next, or which read the standard input.
+const SKIPPED: [&str; 4] = ["input", "os", "random", "timings"];
+
+// Translates a script in the directory of the tests and runs it with Node.js from the directory of
+// the script, giving its exit status and what it printed, or None when Node.js isn't installed.
+fn node(script: &Path) -> Option<(i32, String)> {
+    let name = script.file_stem().expect("the script has a name").to_string_lossy();
+    let translated = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("emit_js_{name}.js"));
+    let path = script.to_str().expect("the path is valid UTF-8");
+    let emitted = common::mussel(&["emit-js", path, "-o", translated.to_str().expect("the path is valid UTF-8")]);
+    assert_eq!(emitted.status, 0, "{}", emitted.stderr);
+    let output = Command::new("node")
+        .arg(&translated)
+        .current_dir(script.parent().expect("the script is in a directory"))
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    Some((output.status.code().unwrap_or(-1), String::from_utf8_lossy(&output.stdout).into_owned()))
+}
+
+// Runs a script with the interpreter from the directory of the script.
+fn interpreter(script: &Path) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_mussel"))
+        .arg(script)
+        .current_dir(script.parent().expect("the script is in a directory"))
+        .stdin(Stdio::null())
+        .output()
+        .expect("mussel can be run");
+    (output.status.code().unwrap_or(-1), String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[test]
+fn translated_examples_print_what_the_interpreter_prints() {
+    let examples = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../examples");
+    let mut scripts: Vec<PathBuf> = std::fs::read_dir(&examples)
+        .expect("the examples can be listed")
+        .map(|entry| entry.expect("the examples can be listed").path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "mus"))
+        .filter(|path| !SKIPPED.iter().any(|skipped| path.file_stem().is_some_and(|stem| stem == *skipped)))
+        .collect();
+    scripts.sort();
+    for example in scripts {
+        let Some(translated) = node(&example) else {
+            eprintln!("Node.js isn't installed, the translated examples aren't run");
+            return;
+        };
+        assert_eq!(translated, interpreter(&example), "{}", example.display());
+    }
+}
+
+#[test]
+fn translated_characters_are_not_strings() {
+    let source = "include string\nprintln(typeof('a'), \" \", typeof(\"a\"), \" \", typeof(chars(\"ab\")[1]))\n";
+    let script = common::script("characters", source);
+    if let Some(translated) = node(&script) {
+        assert_eq!(translated, (0, "char string char\n".to_string()));
+    }
+}
+
+#[test]
+fn translated_integers_too_large_for_an_int_stop_the_script() {
+    for (index, line) in ["2 ** 100", "9223372036854775807 + 1", "(-9223372036854775807 - 1) / -1"].into_iter().enumerate() {
+        let script = common::script(&format!("overflow_{index}"), &format!("println(1)\nprintln({line})\nprintln(3)\n"));
+        if let Some(translated) = node(&script) {
+            assert_eq!(translated, (1, "1\n".to_string()), "{line}");
+            assert_eq!(translated, interpreter(&script), "{line}");
+        }
+    }
+}
+
+#[test]
+fn translated_exit_statuses_out_of_the_range_of_the_system_stop_the_script() {
+    for (index, line) in ["exit(256)", "exit(-1)", "300"].into_iter().enumerate() {
+        let script = common::script(&format!("exit_status_{index}"), &format!("println(1)\n{line}\n"));
+        if let Some(translated) = node(&script) {
+            assert_eq!(translated, (1, "1\n".to_string()), "{line}");
+            assert_eq!(translated, interpreter(&script), "{line}");
+        }
+    }
+}
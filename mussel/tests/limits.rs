@@ -0,0 +1,82 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+mod common;
+
+// Runs a script with both backends and the given limit, and checks that the limit stopped it.
+fn stopped_by(name: &str, source: &str, limit: &[&str], message: &str) {
+    let path = common::script(name, source);
+    for backend in ["tree", "vm"] {
+        let mut args = vec!["--backend", backend];
+        args.extend_from_slice(limit);
+        args.push(path.to_str().expect("the path is valid UTF-8"));
+        let run = common::mussel(&args);
+        assert_eq!(run.status, 1, "{backend}: {}", run.stderr);
+        assert!(run.stderr.contains(message), "{backend}: {}", run.stderr);
+    }
+}
+
+#[test]
+fn an_empty_loop_stops_at_the_maximum_number_of_steps() {
+    let message = "error[E1015]: execution limit exceeded: the script ran more than 1000 steps";
+    stopped_by("empty_loop_steps", "loop {}\n", &["--max-steps", "1000"], message);
+}
+
+#[test]
+fn an_empty_loop_stops_at_the_timeout() {
+    let message = "error[E1015]: execution limit exceeded: the script ran for more than 100ms";
+    stopped_by("empty_loop_timeout", "loop {}\n", &["--timeout", "100ms"], message);
+}
+
+#[test]
+fn an_empty_for_loop_stops_at_the_maximum_number_of_steps() {
+    let message = "error[E1015]: execution limit exceeded: the script ran more than 1000 steps";
+    stopped_by("empty_for_steps", "for i in 0..100000000 {}\n", &["--max-steps", "1000"], message);
+}
+
+#[test]
+fn an_empty_for_loop_stops_at_the_timeout() {
+    let message = "error[E1015]: execution limit exceeded: the script ran for more than 100ms";
+    stopped_by("empty_for_timeout", "for i in 0..100000000 {}\n", &["--timeout", "100ms"], message);
+}
+
+#[test]
+fn deep_recursion_on_the_smallest_stack_stops_with_an_error() {
+    let source = "fn f(n) {\n    if n == 0 { return 0 }\n    return 1 + f(n - 1)\n}\nprintln(f(100000))\n";
+    let message = "error[E1013]: maximum recursion depth exceeded";
+    stopped_by("smallest_stack", source, &["--max-depth", "1000000", "--max-stack", "1M"], message);
+}
+
+#[test]
+fn a_stack_smaller_than_the_minimum_is_rejected() {
+    let path = common::script("too_small_stack", "println(1)\n");
+    let run = common::mussel(&["--max-stack", "64K", path.to_str().expect("the path is valid UTF-8")]);
+    assert_eq!(run.status, 1);
+    assert!(run.stderr.contains("the stack of the interpreter needs at least 1 MiB"), "{}", run.stderr);
+    assert_eq!(run.stdout, "");
+}
+
+#[test]
+fn exit_statuses_out_of_the_range_of_the_system_are_errors() {
+    let scripts = [
+        ("exit_256", "println(1)\nexit(256)\n", "exit expects a status between 0 and 255, not 256"),
+        ("exit_negative", "println(1)\nexit(-1)\n", "exit expects a status between 0 and 255, not -1"),
+        ("last_value_300", "println(1)\n300\n", "The value of the last statement, 300, isn't an exit status"),
+    ];
+    for (name, source, message) in scripts {
+        for run in common::run_both(name, source) {
+            assert_eq!(run.status, 1, "{name}: {}", run.stderr);
+            assert_eq!(run.stdout, "1\n", "{name}");
+            assert!(run.stderr.contains(message), "{name}: {}", run.stderr);
+        }
+    }
+}
+
+#[test]
+fn exit_statuses_within_the_range_of_the_system_are_kept() {
+    for (name, source, status) in [("exit_255", "exit(255)\n", 255), ("last_value_42", "42\n", 42)] {
+        for run in common::run_both(name, source) {
+            assert_eq!(run.status, status, "{name}: {}", run.stderr);
+        }
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+mod common;
+
+#[test]
+fn a_variable_hiding_a_parameter_is_reported() {
+    let source = "fn area(width) {\n    let width = width * 2\n    return width\n}\nprintln(area(1))\n";
+    let path = common::script("lint_parameter", source);
+    let run = common::mussel(&["lint", path.to_str().expect("the path is valid UTF-8")]);
+    assert_eq!(run.status, 1);
+    assert!(run.stderr.contains("warning[shadowed-variable]: `width` shadows a parameter of the function"), "{}", run.stderr);
+    assert!(run.stderr.contains("lint_parameter.mus:2:9"), "{}", run.stderr);
+}
+
+#[test]
+fn a_variable_hiding_a_parameter_of_a_closure_is_reported() {
+    let source = "let scale = |n| {\n    let n = n + 1\n    return n\n}\nprintln(scale(1))\n";
+    let path = common::script("lint_closure_parameter", source);
+    let run = common::mussel(&["lint", path.to_str().expect("the path is valid UTF-8")]);
+    assert_eq!(run.status, 1);
+    assert!(run.stderr.contains("`n` shadows a parameter of the function"), "{}", run.stderr);
+}
+
+#[test]
+fn parameters_and_variables_starting_with_an_underscore_are_not_reported() {
+    let source = "let count = 0\nfn add(count) {\n    let _count = count\n    return _count\n}\nprintln(add(count))\n";
+    let path = common::script("lint_parameter_hiding", source);
+    let run = common::mussel(&["lint", path.to_str().expect("the path is valid UTF-8")]);
+    assert_eq!(run.status, 0, "{}", run.stderr);
+}
+
+#[test]
+fn rules_are_turned_on_and_off_like_the_other_warnings() {
+    let source = "let done = true\nif done == true {\n    let done = false\n    println(done)\n}\n";
+    let path = common::script("lint_flags", source);
+    let path = path.to_str().expect("the path is valid UTF-8");
+    let run = common::mussel(&["lint", "-W", "comparison-to-bool", "-A", "shadowed-variable", path]);
+    assert_eq!(run.status, 1);
+    assert!(run.stderr.contains("warning[comparison-to-bool]"), "{}", run.stderr);
+    assert!(!run.stderr.contains("warning[shadowed-variable]"), "{}", run.stderr);
+    let deprecated = common::mussel(&["lint", "--enable", "comparison-to-bool", "--disable", "shadowed-variable", path]);
+    assert!(deprecated.stderr.contains("warning: `--enable` is deprecated, use `--warn` instead"), "{}", deprecated.stderr);
+    assert!(deprecated.stderr.contains("warning[comparison-to-bool]"), "{}", deprecated.stderr);
+    assert!(!deprecated.stderr.contains("warning[shadowed-variable]"), "{}", deprecated.stderr);
+}
@@ -0,0 +1,69 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+// Opens a document in `mussel lsp` and asks for the completion at a line and a character of it,
+// returning the labels of the items.
+fn completion(text: &str, line: usize, character: usize) -> Vec<String> {
+    let uri = "file:///completion.mus";
+    let text = text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+    let messages = [
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{uri}","languageId":"mussel","version":1,"text":"{text}"}}}}}}"#
+        ),
+        format!(
+            r#"{{"jsonrpc":"2.0","id":1,"method":"textDocument/completion","params":{{"textDocument":{{"uri":"{uri}"}},"position":{{"line":{line},"character":{character}}}}}}}"#
+        ),
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ];
+    let mut server = Command::new(env!("CARGO_BIN_EXE_mussel"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("mussel can be run");
+    let mut input = server.stdin.take().expect("the standard input is piped");
+    for message in messages {
+        write!(input, "Content-Length: {}\r\n\r\n{message}", message.len()).expect("the server reads its input");
+    }
+    drop(input);
+    let output = server.wait_with_output().expect("the server ends");
+    let output = String::from_utf8_lossy(&output.stdout);
+    let response = output.split("Content-Length").find(|message| message.contains(r#""id":1"#)).expect("the completion is answered");
+    response.split(r#""label":""#).skip(1).map(|item| item.split('"').next().unwrap_or_default().to_string()).collect()
+}
+
+#[test]
+fn parameters_are_completed_only_in_their_function() {
+    let text = "fn add(a, b) {\n    let result = a + b\n    return result\n}\nlet total = add(1, 2)\nprintln(total)\n";
+    let inside = completion(text, 2, 14);
+    for name in ["a", "b", "result", "add"] {
+        assert!(inside.iter().any(|label| label == name), "{name}: {inside:?}");
+    }
+    let outside = completion(text, 5, 8);
+    for name in ["a", "b", "result"] {
+        assert!(!outside.iter().any(|label| label == name), "{name}: {outside:?}");
+    }
+    for name in ["add", "total", "println"] {
+        assert!(outside.iter().any(|label| label == name), "{name}: {outside:?}");
+    }
+}
+
+#[test]
+fn variables_are_completed_after_their_declaration() {
+    let text = "let first = 1\nprintln(first)\nlet second = 2\nprintln(second)\n";
+    let labels = completion(text, 1, 8);
+    assert!(labels.iter().any(|label| label == "first"), "{labels:?}");
+    assert!(!labels.iter().any(|label| label == "second"), "{labels:?}");
+}
+
+#[test]
+fn parameters_are_completed_on_a_blank_line_of_their_function() {
+    let text = "fn scale(factor) {\n    println(factor)\n    \n}\nscale(2)\n";
+    let labels = completion(text, 2, 4);
+    assert!(labels.iter().any(|label| label == "factor"), "{labels:?}");
+}
@@ -0,0 +1,78 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+mod common;
+
+// Runs a script printing one value per line with both backends, and checks what they printed.
+fn prints(name: &str, source: &str, expected: &str) {
+    for run in common::run_both(name, source) {
+        assert_eq!(run.status, 0, "{}", run.stderr);
+        assert_eq!(run.stdout, expected);
+    }
+}
+
+#[test]
+fn chained_subtractions_and_divisions_are_evaluated_from_left_to_right() {
+    let source = "println(10 - 4 - 3)\nprintln(100 / 10 / 5)\nprintln(64 / 2 / 2 / 2)\nprintln(1.5 - 0.5 - 1.0)\n";
+    prints("left_to_right", source, "3\n2\n8\n0\n");
+}
+
+#[test]
+fn operators_with_the_same_precedence_are_evaluated_from_left_to_right() {
+    prints("same_precedence", "println(2 - 3 + 4)\nprintln(20 / 2 * 5)\nprintln(7 / 2 * 2)\n", "3\n50\n6\n");
+}
+
+#[test]
+fn multiplications_are_evaluated_before_additions() {
+    prints("precedence", "println(1 + 2 * 3 - 4)\nprintln(10 - 6 / 2 - 1)\nprintln(2 * 3 ** 2)\n", "3\n6\n18\n");
+}
+
+#[test]
+fn powers_are_evaluated_from_right_to_left() {
+    prints("powers", "println(2 ** 3 ** 2)\n", "512\n");
+}
+
+#[test]
+fn comparisons_are_evaluated_after_chained_arithmetic() {
+    prints("comparisons", "println(10 - 4 - 3 == 3)\nprintln(100 / 10 / 5 < 3)\nprintln(1 < 2 == true)\n", "true\ntrue\ntrue\n");
+}
+
+// Runs a script with both backends, and checks that it stops with an integer overflow at a column
+// of its last line.
+fn overflows(name: &str, source: &str, column: usize) {
+    let line = source.lines().count();
+    for run in common::run_both(name, source) {
+        assert_eq!(run.status, 1, "{}", run.stdout);
+        assert!(run.stderr.contains("error[E1017]: attempt to compute a number too large for an `int`"), "{}", run.stderr);
+        assert!(run.stderr.contains(&format!("{name}.mus:{line}:{column}")), "{}", run.stderr);
+    }
+}
+
+#[test]
+fn integer_results_too_large_for_an_int_are_errors() {
+    overflows("overflow_add", "println(9223372036854775807 + 1)\n", 29);
+    overflows("overflow_sub", "let m = -9223372036854775807\nprintln(m - 2)\n", 11);
+    overflows("overflow_mul", "println(3037000500 * 3037000500)\n", 20);
+    overflows("overflow_div", "let m = -9223372036854775807 - 1\nprintln(m / -1)\n", 11);
+    overflows("overflow_neg", "let m = -9223372036854775807 - 1\nprintln(-m)\n", 9);
+    overflows("overflow_abs", "include math\nprintln(abs(-9223372036854775807 - 1))\n", 9);
+}
+
+#[test]
+fn powers_too_large_for_an_int_are_errors() {
+    overflows("overflow_pow", "println(2 ** 64)\n", 11);
+    overflows("overflow_pow_100", "println(2 ** 100)\n", 11);
+    overflows("overflow_pow_u32", "println(2 ** 4294967296)\n", 11);
+}
+
+#[test]
+fn powers_within_an_int_are_exact() {
+    let source = "println(2 ** 62)\nprintln((-2) ** 63)\nprintln(1 ** 4294967296)\nprintln((-1) ** 4294967297)\n";
+    prints("powers_within", source, "4611686018427387904\n-9223372036854775808\n1\n-1\n");
+}
+
+#[test]
+fn integer_results_at_the_bounds_of_an_int_are_kept() {
+    let source = "println(9223372036854775806 + 1)\nprintln(-9223372036854775807 - 1)\nprintln(-(-9223372036854775807))\n";
+    prints("bounds", source, "9223372036854775807\n-9223372036854775808\n9223372036854775807\n");
+}
@@ -0,0 +1,50 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+mod common;
+
+use std::path::PathBuf;
+
+// Writes a project in the directory of the tests, with a manifest and a script printing a greeting.
+fn project(name: &str, manifest: &str) -> PathBuf {
+    let directory = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    std::fs::create_dir_all(directory.join("src")).expect("the project can be written");
+    std::fs::write(directory.join("mussel.toml"), manifest).expect("the manifest can be written");
+    std::fs::write(directory.join("src/main.mus"), "println(\"Hello\")\n").expect("the script can be written");
+    directory
+}
+
+#[test]
+fn a_manifest_runs_its_entry() {
+    let directory = project("project_entry", "[package]\nname = \"hello\"\nentry = 'src/main.mus'\ninclude = []\n");
+    let run = common::mussel_in(&directory, &["run"]);
+    assert_eq!(run.status, 0, "{}", run.stderr);
+    assert_eq!(run.stdout, "Hello\n");
+}
+
+#[test]
+fn integers_and_booleans_are_read_and_reported_with_their_value() {
+    let cases = [
+        ("version = 1", "`version` must be a string, found the integer `1`"),
+        ("version = -1_000", "`version` must be a string, found the integer `-1000`"),
+        ("description = true", "`description` must be a string, found the boolean `true`"),
+        ("include = [\"lib\", false]", "`include` must be an array of strings, found an array holding the boolean `false`"),
+    ];
+    for (index, (line, message)) in cases.into_iter().enumerate() {
+        let directory = project(&format!("project_value_{index}"), &format!("[package]\nname = \"hello\"\n{line}\n"));
+        let run = common::mussel_in(&directory, &["run"]);
+        assert_eq!(run.status, 1, "{line}");
+        assert!(run.stderr.contains(&format!("mussel.toml:3: {message}")), "{}", run.stderr);
+    }
+}
+
+#[test]
+fn malformed_values_are_reported() {
+    for (index, line) in ["version = 01", "version = 1__0", "version = yes"].into_iter().enumerate() {
+        let directory = project(&format!("project_malformed_{index}"), &format!("[package]\nname = \"hello\"\n{line}\n"));
+        let run = common::mussel_in(&directory, &["run"]);
+        assert_eq!(run.status, 1, "{line}");
+        assert!(run.stderr.contains("mussel.toml:3: `"), "{}", run.stderr);
+        assert!(run.stderr.contains("isn't a valid value"), "{}", run.stderr);
+    }
+}
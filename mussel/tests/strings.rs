@@ -0,0 +1,13 @@
+// Copyright (c) 2025 Francesco Giannice
+// Licensed under the Apache License, Version 2.0 (http://www.apache.org/licenses/LICENSE-2.0)
+
+mod common;
+
+#[test]
+fn multiline_strings_of_a_crlf_file_end_their_lines_with_a_newline() {
+    let source = "let query = \"\"\"\r\n    SELECT name\r\n      FROM users\r\n    \"\"\"\r\nprintln(query)\r\n";
+    for run in common::run_both("crlf_multiline", source) {
+        assert_eq!(run.status, 0, "{}", run.stderr);
+        assert_eq!(run.stdout, "SELECT name\n  FROM users\n");
+    }
+}